@@ -33,6 +33,8 @@ impl From<&mc_transaction_core::BlockContents> for blockchain::BlockContents {
         block_contents.set_outputs(outputs);
         block_contents.set_validated_mint_config_txs(validated_mint_config_txs);
         block_contents.set_mint_txs(mint_txs);
+        block_contents.set_num_transactions(source.num_transactions);
+        block_contents.set_recent_block_loads(source.recent_block_loads.clone());
         block_contents
     }
 }
@@ -72,6 +74,8 @@ impl TryFrom<&blockchain::BlockContents> for mc_transaction_core::BlockContents
             outputs,
             validated_mint_config_txs,
             mint_txs,
+            num_transactions: source.get_num_transactions(),
+            recent_block_loads: source.get_recent_block_loads().to_vec(),
         })
     }
 }
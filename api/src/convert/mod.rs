@@ -31,6 +31,7 @@ mod public_address;
 mod ring_mlsag;
 mod ristretto_private;
 mod signature_rct_bulletproofs;
+mod signed_contingent_input;
 mod tx;
 mod tx_hash;
 mod tx_in;
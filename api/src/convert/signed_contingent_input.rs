@@ -0,0 +1,79 @@
+//! Convert to/from external::SignedContingentInput
+
+use crate::{convert::ConversionError, external};
+use mc_transaction_core::{
+    ring_signature::{CurveScalar, RingMLSAG},
+    tx::TxIn,
+    SignedContingentInput, UnmaskedAmount,
+};
+use std::convert::TryFrom;
+
+/// Convert UnmaskedAmount --> external::UnmaskedAmount.
+impl From<&UnmaskedAmount> for external::UnmaskedAmount {
+    fn from(source: &UnmaskedAmount) -> Self {
+        let mut unmasked_amount = external::UnmaskedAmount::new();
+        unmasked_amount.set_value(source.value);
+        unmasked_amount.set_token_id(source.token_id);
+        unmasked_amount.set_blinding((&source.blinding).into());
+        unmasked_amount
+    }
+}
+
+/// Convert external::UnmaskedAmount --> UnmaskedAmount.
+impl TryFrom<&external::UnmaskedAmount> for UnmaskedAmount {
+    type Error = ConversionError;
+
+    fn try_from(source: &external::UnmaskedAmount) -> Result<Self, Self::Error> {
+        let blinding = CurveScalar::try_from(source.get_blinding())?;
+        Ok(UnmaskedAmount {
+            value: source.value,
+            token_id: source.token_id,
+            blinding,
+        })
+    }
+}
+
+/// Convert SignedContingentInput --> external::SignedContingentInput.
+impl From<&SignedContingentInput> for external::SignedContingentInput {
+    fn from(source: &SignedContingentInput) -> Self {
+        let mut sci = external::SignedContingentInput::new();
+        sci.set_block_version(source.block_version);
+        sci.set_tx_in((&source.tx_in).into());
+        sci.set_mlsag((&source.mlsag).into());
+        sci.set_pseudo_output_amount((&source.pseudo_output_amount).into());
+        sci.set_required_output_amounts(
+            source
+                .required_output_amounts
+                .iter()
+                .map(external::UnmaskedAmount::from)
+                .collect(),
+        );
+        sci.set_tx_out_global_indices(source.tx_out_global_indices.clone());
+        sci
+    }
+}
+
+/// Convert external::SignedContingentInput --> SignedContingentInput.
+impl TryFrom<&external::SignedContingentInput> for SignedContingentInput {
+    type Error = ConversionError;
+
+    fn try_from(source: &external::SignedContingentInput) -> Result<Self, Self::Error> {
+        let tx_in = TxIn::try_from(source.get_tx_in())?;
+        let mlsag = RingMLSAG::try_from(source.get_mlsag())?;
+        let pseudo_output_amount = UnmaskedAmount::try_from(source.get_pseudo_output_amount())?;
+        let required_output_amounts = source
+            .get_required_output_amounts()
+            .iter()
+            .map(UnmaskedAmount::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(SignedContingentInput {
+            block_version: source.block_version,
+            tx_in,
+            mlsag,
+            pseudo_output_amount,
+            required_output_amounts,
+            tx_out_global_indices: source.get_tx_out_global_indices().to_vec(),
+        })
+    }
+}
@@ -14,6 +14,7 @@ use mc_util_from_random::FromRandom;
 use mc_util_test_helper::{run_with_several_seeds, CryptoRng, RngCore};
 use prost::Message as ProstMessage;
 use protobuf::Message as ProtobufMessage;
+use std::convert::TryFrom;
 
 // Take a prost type and try to roundtrip it through a protobuf type
 fn round_trip_message<SRC: ProstMessage + Eq + Default, DEST: ProtobufMessage>(prost_val: &SRC) {
@@ -184,3 +185,18 @@ fn signed_contingent_input_round_trip() {
         }
     })
 }
+
+// Test that SignedContingentInput --> external::SignedContingentInput -->
+// SignedContingentInput is the identity function
+#[test]
+fn signed_contingent_input_conversion_round_trip() {
+    run_with_several_seeds(|mut rng| {
+        for block_version in BlockVersion::iterator().skip(3) {
+            for example in signed_contingent_input_examples(block_version, &mut rng) {
+                let external_sci = external::SignedContingentInput::from(&example);
+                let recovered_sci = SignedContingentInput::try_from(&external_sci).unwrap();
+                assert_eq!(example, recovered_sci);
+            }
+        }
+    })
+}
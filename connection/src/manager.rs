@@ -21,12 +21,14 @@ struct ConnectionManagerInner<C: Connection> {
 /// A connection manager manages a list of peers it is connected to.
 pub struct ConnectionManager<C: Connection> {
     inner: Arc<RwLock<ConnectionManagerInner<C>>>,
+    logger: Logger,
 }
 
 impl<C: Connection> Clone for ConnectionManager<C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            logger: self.logger.clone(),
         }
     }
 }
@@ -38,27 +40,59 @@ impl<C: Connection> ConnectionManager<C> {
             inner: Arc::new(RwLock::new(ConnectionManagerInner {
                 id_to_conn: conns
                     .into_iter()
-                    .map(|conn| {
-                        let name = conn.to_string();
-                        let responder_id = conn.uri().responder_id().unwrap_or_else(|_| {
-                            panic!(
-                                "Could not create responder_id from {:?}",
-                                conn.uri().to_string()
-                            )
-                        });
-                        let sync_conn =
-                            SyncConnection::new(conn, logger.new(o!("mc.peers.peer_name" => name)));
-                        (responder_id, sync_conn)
-                    })
+                    .map(|conn| (Self::responder_id(&conn), Self::sync_conn(conn, &logger)))
                     .collect(),
             })),
+            logger,
         }
     }
 
+    fn responder_id(conn: &C) -> ResponderId {
+        conn.uri().responder_id().unwrap_or_else(|_| {
+            panic!(
+                "Could not create responder_id from {:?}",
+                conn.uri().to_string()
+            )
+        })
+    }
+
+    fn sync_conn(conn: C, logger: &Logger) -> SyncConnection<C> {
+        let name = conn.to_string();
+        SyncConnection::new(conn, logger.new(o!("mc.peers.peer_name" => name)))
+    }
+
     fn read(&self) -> RwLockReadGuard<ConnectionManagerInner<C>> {
         self.inner.read().expect("ConnectionManager lock poisoned")
     }
 
+    /// Replace the managed connections with `conns`. Existing connections
+    /// whose URI did not change are kept, so their state (e.g. an attested
+    /// session) is not lost.
+    ///
+    /// Returns the responder ids of connections that were added or replaced,
+    /// and the responder ids of connections that were removed.
+    pub fn update_conns(&self, conns: Vec<C>) -> (Vec<ResponderId>, Vec<ResponderId>) {
+        let mut inner = self.inner.write().expect("ConnectionManager lock poisoned");
+
+        let mut id_to_conn = BTreeMap::new();
+        let mut added = Vec::new();
+        for conn in conns {
+            let responder_id = Self::responder_id(&conn);
+            let sync_conn = match inner.id_to_conn.remove(&responder_id) {
+                Some(existing) if existing.uri() == conn.uri() => existing,
+                _ => {
+                    added.push(responder_id.clone());
+                    Self::sync_conn(conn, &self.logger)
+                }
+            };
+            id_to_conn.insert(responder_id, sync_conn);
+        }
+
+        let removed = inner.id_to_conn.keys().cloned().collect();
+        inner.id_to_conn = id_to_conn;
+        (added, removed)
+    }
+
     /// Retrieve a vector of all the connection URLs owned by this manager.
     pub fn responder_ids(&self) -> Vec<ResponderId> {
         self.read().id_to_conn.keys().cloned().collect()
@@ -14,11 +14,16 @@ use crate::{
     consensus_config,
 };
 use mc_api::ConversionError;
+use mc_common::ResponderId;
+use mc_consensus_scp::{QuorumSet, QuorumSetMember};
 use mc_transaction_core::{
     mint::MintValidationError, ring_signature, validation::TransactionValidationError as Error,
     BlockVersion, InputRuleError, TokenId,
 };
-use std::convert::{From, TryFrom, TryInto};
+use std::{
+    convert::{From, TryFrom, TryInto},
+    str::FromStr,
+};
 
 /// Convert TransactionValidationError --> ProposeTxResult.
 impl From<Error> for ProposeTxResult {
@@ -285,6 +290,61 @@ impl TryFrom<&consensus_config::ActiveMintConfigs> for mc_ledger_db::ActiveMintC
     }
 }
 
+/// Convert QuorumSet --> consensus_config::QuorumSet
+impl From<&QuorumSet<ResponderId>> for consensus_config::QuorumSet {
+    fn from(src: &QuorumSet<ResponderId>) -> Self {
+        let mut dst = Self::new();
+        dst.set_threshold(src.threshold);
+        dst.set_members(
+            src.members
+                .iter()
+                .map(|member| {
+                    let mut dst_member = consensus_config::QuorumSetMember::new();
+                    match member {
+                        QuorumSetMember::Node(responder_id) => {
+                            dst_member.set_node(responder_id.to_string())
+                        }
+                        QuorumSetMember::InnerSet(inner_set) => {
+                            dst_member.set_inner_set(inner_set.into())
+                        }
+                    }
+                    dst_member
+                })
+                .collect(),
+        );
+        dst
+    }
+}
+
+/// Convert consensus_config::QuorumSet --> QuorumSet
+impl TryFrom<&consensus_config::QuorumSet> for QuorumSet<ResponderId> {
+    type Error = ConversionError;
+
+    fn try_from(src: &consensus_config::QuorumSet) -> Result<Self, Self::Error> {
+        let members = src
+            .get_members()
+            .iter()
+            .map(|member| {
+                if member.has_node() {
+                    let responder_id = ResponderId::from_str(member.get_node())
+                        .map_err(|_| ConversionError::InvalidContents)?;
+                    Ok(QuorumSetMember::Node(responder_id))
+                } else if member.has_inner_set() {
+                    Ok(QuorumSetMember::InnerSet(
+                        member.get_inner_set().try_into()?,
+                    ))
+                } else {
+                    Err(ConversionError::ObjectMissing)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            threshold: src.get_threshold(),
+            members,
+        })
+    }
+}
+
 #[cfg(test)]
 mod conversion_tests {
     use super::*;
@@ -397,4 +457,36 @@ mod conversion_tests {
             assert_eq!(source, recovered);
         }
     }
+
+    #[test]
+    fn test_convert_quorum_set() {
+        let source: QuorumSet<ResponderId> = QuorumSet::new(
+            2,
+            vec![
+                QuorumSetMember::Node(ResponderId::from_str("a.mobilecoin.com:443").unwrap()),
+                QuorumSetMember::InnerSet(QuorumSet::new_with_node_ids(
+                    1,
+                    vec![
+                        ResponderId::from_str("b.mobilecoin.com:443").unwrap(),
+                        ResponderId::from_str("c.mobilecoin.com:443").unwrap(),
+                    ],
+                )),
+            ],
+        );
+
+        // Converting QuorumSet -> consensus_config::QuorumSet -> QuorumSet should be
+        // the identity function.
+        let external = consensus_config::QuorumSet::from(&source);
+        let recovered = QuorumSet::try_from(&external).unwrap();
+        assert_eq!(source, recovered);
+
+        // A member that is neither a node nor an inner set is rejected.
+        let mut external = consensus_config::QuorumSet::new();
+        external.set_threshold(1);
+        external.set_members(vec![consensus_config::QuorumSetMember::new()].into());
+        assert_eq!(
+            QuorumSet::<ResponderId>::try_from(&external),
+            Err(ConversionError::ObjectMissing)
+        );
+    }
 }
@@ -47,6 +47,10 @@ pub enum Error {
     /// Invalid membership root element provided by local system
     InvalidLocalMembershipRootElement,
 
+    /// Parent block contents provided by local system do not match the parent
+    /// block
+    InvalidLocalParentBlockContents,
+
     /// Form block error: {0}
     FormBlock(String),
 
@@ -0,0 +1,204 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Minimum fees that rise with network load.
+//!
+//! Starting with block version 4, the minimum fee a transaction must pay to be
+//! included in a block depends on how full the blocks preceding it were. This
+//! is deterministic: every node derives the same minimum fees for a block from
+//! the same ledger.
+//!
+//! The schedule is expressed as a minimum priority, which does not depend on
+//! the token. A transaction's priority is its fee divided by 1/128th of its
+//! token's configured minimum fee (see [SMALLEST_MINIMUM_FEE_LOG2]), so a
+//! transaction paying exactly the configured minimum fee has priority
+//! [BASE_MINIMUM_PRIORITY]. This lets the untrusted side check that a
+//! transaction pays enough without learning which token it pays its fee in.
+//!
+//! The load of a block is the number of transactions it contains, as recorded
+//! in its contents. A transaction counts once no matter how many inputs it
+//! spends, so the load of a block never exceeds
+//! [MAX_TRANSACTIONS_PER_BLOCK].
+//!
+//! Each block's contents also record the loads of the window ending with it
+//! (see [recent_block_loads]). The enclave forming a block takes them from the
+//! contents of the parent block, which it checks against the parent block's
+//! contents hash, so that the untrusted side cannot understate them.
+
+use crate::SMALLEST_MINIMUM_FEE_LOG2;
+use alloc::vec::Vec;
+use mc_transaction_core::{constants::MAX_TRANSACTIONS_PER_BLOCK, BlockVersion};
+
+/// Number of blocks preceding a block whose loads determine its minimum fees.
+pub const FEE_SCHEDULE_WINDOW: usize = 10;
+
+/// Average fullness, in percent, of the blocks in the window above which
+/// minimum fees start rising.
+pub const TARGET_BLOCK_FULLNESS_PERCENT: u64 = 50;
+
+/// Factor by which minimum fees are multiplied when all the blocks in the
+/// window are full.
+pub const MAX_MINIMUM_FEE_MULTIPLIER: u64 = 16;
+
+/// The priority of a transaction paying exactly its token's configured minimum
+/// fee. This is the minimum priority when the network is not congested.
+pub const BASE_MINIMUM_PRIORITY: u64 = 1 << SMALLEST_MINIMUM_FEE_LOG2;
+
+/// The minimum priority of the transactions in a block.
+///
+/// # Arguments
+/// * `block_version` - The block version of the block.
+/// * `recent_block_loads` - The numbers of transactions in the blocks preceding
+///   it, oldest first. Only the last [FEE_SCHEDULE_WINDOW] are considered.
+///   Missing ones (close to the origin block) count as empty.
+pub fn minimum_priority(block_version: BlockVersion, recent_block_loads: &[u64]) -> u64 {
+    if !block_version.dynamic_minimum_fees_are_supported() {
+        return BASE_MINIMUM_PRIORITY;
+    }
+
+    let max_block_load = MAX_TRANSACTIONS_PER_BLOCK as u64;
+    let capacity = FEE_SCHEDULE_WINDOW as u64 * max_block_load;
+    let target = capacity * TARGET_BLOCK_FULLNESS_PERCENT / 100;
+    let load: u64 = recent_block_loads
+        .iter()
+        .rev()
+        .take(FEE_SCHEDULE_WINDOW)
+        .map(|block_load| (*block_load).min(max_block_load))
+        .sum();
+
+    if load <= target {
+        return BASE_MINIMUM_PRIORITY;
+    }
+
+    // Rises linearly from the base priority at the target load, to the maximum
+    // multiple of it when the window is full.
+    BASE_MINIMUM_PRIORITY
+        + BASE_MINIMUM_PRIORITY * (MAX_MINIMUM_FEE_MULTIPLIER - 1) * (load - target)
+            / (capacity - target)
+}
+
+/// The loads to record in the contents of a block.
+///
+/// # Arguments
+/// * `block_version` - The block version of the block.
+/// * `parent_block_loads` - The loads recorded in the contents of its parent
+///   block.
+/// * `num_transactions` - The number of transactions in the block.
+pub fn recent_block_loads(
+    block_version: BlockVersion,
+    parent_block_loads: &[u64],
+    num_transactions: u64,
+) -> Vec<u64> {
+    if !block_version.dynamic_minimum_fees_are_supported() {
+        return Vec::new();
+    }
+
+    let skip = (parent_block_loads.len() + 1).saturating_sub(FEE_SCHEDULE_WINDOW);
+    let mut loads: Vec<u64> = parent_block_loads.iter().skip(skip).cloned().collect();
+    loads.push(num_transactions);
+    loads
+}
+
+/// The minimum fee of a token in a block.
+///
+/// # Arguments
+/// * `configured_minimum_fee` - The token's minimum fee in the fee map.
+/// * `minimum_priority` - The minimum priority of the block's transactions.
+pub fn minimum_fee(configured_minimum_fee: u64, minimum_priority: u64) -> u64 {
+    (configured_minimum_fee >> SMALLEST_MINIMUM_FEE_LOG2)
+        .saturating_mul(minimum_priority)
+        .max(configured_minimum_fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const FULL: u64 = MAX_TRANSACTIONS_PER_BLOCK as u64;
+
+    #[test]
+    fn test_minimum_priority_not_supported() {
+        let full_blocks = vec![FULL; FEE_SCHEDULE_WINDOW];
+        for block_version in BlockVersion::iterator() {
+            if !block_version.dynamic_minimum_fees_are_supported() {
+                assert_eq!(
+                    minimum_priority(block_version, &full_blocks),
+                    BASE_MINIMUM_PRIORITY
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_minimum_priority() {
+        let block_version = BlockVersion::FOUR;
+
+        // No recent blocks, or blocks up to the target fullness.
+        assert_eq!(minimum_priority(block_version, &[]), BASE_MINIMUM_PRIORITY);
+        assert_eq!(
+            minimum_priority(block_version, &vec![FULL / 2; FEE_SCHEDULE_WINDOW]),
+            BASE_MINIMUM_PRIORITY
+        );
+
+        // Full blocks.
+        assert_eq!(
+            minimum_priority(block_version, &vec![FULL; FEE_SCHEDULE_WINDOW]),
+            BASE_MINIMUM_PRIORITY * MAX_MINIMUM_FEE_MULTIPLIER
+        );
+
+        // Loads above the maximum block size are capped, and only the last blocks
+        // count.
+        let mut loads = vec![0; 5];
+        loads.extend(vec![10 * FULL; FEE_SCHEDULE_WINDOW]);
+        assert_eq!(
+            minimum_priority(block_version, &loads),
+            BASE_MINIMUM_PRIORITY * MAX_MINIMUM_FEE_MULTIPLIER
+        );
+
+        // Halfway between the target and full.
+        assert_eq!(
+            minimum_priority(block_version, &vec![3 * FULL / 4; FEE_SCHEDULE_WINDOW]),
+            BASE_MINIMUM_PRIORITY + BASE_MINIMUM_PRIORITY * (MAX_MINIMUM_FEE_MULTIPLIER - 1) / 2
+        );
+    }
+
+    #[test]
+    fn test_recent_block_loads() {
+        for block_version in BlockVersion::iterator() {
+            if !block_version.dynamic_minimum_fees_are_supported() {
+                assert!(recent_block_loads(block_version, &[1, 2], 3).is_empty());
+            }
+        }
+
+        let block_version = BlockVersion::FOUR;
+        assert_eq!(recent_block_loads(block_version, &[], 3), vec![3]);
+        assert_eq!(recent_block_loads(block_version, &[1, 2], 3), vec![1, 2, 3]);
+
+        // Only the last blocks of the window are kept.
+        let parent_block_loads: Vec<u64> = (0..FEE_SCHEDULE_WINDOW as u64).collect();
+        let mut expected: Vec<u64> = (1..FEE_SCHEDULE_WINDOW as u64).collect();
+        expected.push(42);
+        assert_eq!(
+            recent_block_loads(block_version, &parent_block_loads, 42),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_minimum_fee() {
+        let configured_minimum_fee = 400_000_000;
+        assert_eq!(
+            minimum_fee(configured_minimum_fee, BASE_MINIMUM_PRIORITY),
+            configured_minimum_fee
+        );
+        assert_eq!(
+            minimum_fee(configured_minimum_fee, 2 * BASE_MINIMUM_PRIORITY),
+            2 * configured_minimum_fee
+        );
+        assert_eq!(
+            minimum_fee(configured_minimum_fee, BASE_MINIMUM_PRIORITY + 1),
+            configured_minimum_fee + (configured_minimum_fee >> SMALLEST_MINIMUM_FEE_LOG2)
+        );
+        assert_eq!(minimum_fee(u64::MAX - 127, u64::MAX), u64::MAX);
+    }
+}
@@ -10,6 +10,7 @@ extern crate alloc;
 mod config;
 mod error;
 mod fee_map;
+pub mod fee_schedule;
 mod governors_map;
 mod governors_sig;
 mod messages;
@@ -243,6 +244,11 @@ pub struct FormBlockInputs {
 
     /// Minting transactions coupled with configuration information.
     pub mint_txs_with_config: Vec<(MintTx, MintConfigTx, MintConfig)>,
+
+    /// The contents of the parent block, whose recorded loads determine the
+    /// minimum fees of the block being formed (see [fee_schedule]). They must
+    /// match the contents hash of the parent block.
+    pub parent_block_contents: BlockContents,
 }
 
 /// The API for interacting with a consensus node's enclave.
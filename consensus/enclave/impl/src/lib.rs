@@ -45,9 +45,10 @@ use mc_common::{
     ResponderId,
 };
 use mc_consensus_enclave_api::{
-    BlockchainConfig, BlockchainConfigWithDigest, ConsensusEnclave, Error, FeeMap, FeePublicKey,
-    FormBlockInputs, GovernorsVerifier, LocallyEncryptedTx, Result, SealedBlockSigningKey,
-    TxContext, WellFormedEncryptedTx, WellFormedTxContext, SMALLEST_MINIMUM_FEE_LOG2,
+    fee_schedule, BlockchainConfig, BlockchainConfigWithDigest, ConsensusEnclave, Error, FeeMap,
+    FeePublicKey, FormBlockInputs, GovernorsVerifier, LocallyEncryptedTx, Result,
+    SealedBlockSigningKey, TxContext, WellFormedEncryptedTx, WellFormedTxContext,
+    SMALLEST_MINIMUM_FEE_LOG2,
 };
 use mc_crypto_ake_enclave::AkeEnclaveState;
 use mc_crypto_digestible::{DigestTranscript, Digestible, MerlinTranscript};
@@ -784,13 +785,29 @@ impl ConsensusEnclave for SgxConsensusEnclave {
             return Err(Error::BlockVersion(format!("Block version cannot decrease: parent_block.version = {}, config.block_version = {}", parent_block.version, config.block_version)));
         }
 
+        // The loads of the blocks preceding this one are recorded in the contents of
+        // its parent block, which must match it.
+        if inputs.parent_block_contents.hash() != parent_block.contents_hash {
+            return Err(Error::InvalidLocalParentBlockContents);
+        }
+        let parent_block_loads = &inputs.parent_block_contents.recent_block_loads;
+
+        // The minimum fees for this block, which depend on the loads of the blocks
+        // preceding it.
+        let minimum_priority =
+            fee_schedule::minimum_priority(config.block_version, parent_block_loads);
+        let ct_block_min_fee_map: CtTokenMap<u64> = ct_min_fee_map
+            .iter()
+            .map(|(token_id, fee)| (*token_id, fee_schedule::minimum_fee(*fee, minimum_priority)))
+            .collect();
+
         // Get any "original" Tx transactions included in the inputs.
         let transactions = self.get_txs_from_inputs(
             &inputs.well_formed_encrypted_txs_with_proofs,
             parent_block,
             root_element,
             config,
-            ct_min_fee_map,
+            &ct_block_min_fee_map,
             &mut rng,
         )?;
 
@@ -922,6 +939,19 @@ impl ConsensusEnclave for SgxConsensusEnclave {
         outputs.sort_by(|a, b| a.public_key.cmp(&b.public_key));
         key_images.sort();
 
+        // The number of transactions is recorded for the fee schedule, once it is in
+        // effect.
+        let num_transactions = if config.block_version.dynamic_minimum_fees_are_supported() {
+            transactions.len() as u64
+        } else {
+            0
+        };
+        let recent_block_loads = fee_schedule::recent_block_loads(
+            config.block_version,
+            parent_block_loads,
+            num_transactions,
+        );
+
         // Get the list of MintConfigTxs included in the block.
         let validated_mint_config_txs = self.validate_mint_config_txs(
             inputs.mint_config_txs,
@@ -936,6 +966,8 @@ impl ConsensusEnclave for SgxConsensusEnclave {
             outputs,
             validated_mint_config_txs,
             mint_txs,
+            num_transactions,
+            recent_block_loads,
         };
         //
         // Form the block.
@@ -1041,6 +1073,7 @@ mod tests {
     use mc_crypto_multisig::SignerSet;
     use mc_ledger_db::Ledger;
     use mc_transaction_core::{
+        constants::MAX_TRANSACTIONS_PER_BLOCK,
         tokens::Mob,
         tx::TxOutMembershipHash,
         validation::{validate_tx_out, TransactionValidationError},
@@ -1428,6 +1461,9 @@ mod tests {
                     &parent_block,
                     FormBlockInputs {
                         well_formed_encrypted_txs_with_proofs,
+                        parent_block_contents: ledger
+                            .get_block_contents(parent_block.index)
+                            .unwrap(),
                         ..Default::default()
                     },
                     &root_element,
@@ -1614,6 +1650,9 @@ mod tests {
                     &parent_block,
                     FormBlockInputs {
                         well_formed_encrypted_txs_with_proofs,
+                        parent_block_contents: ledger
+                            .get_block_contents(parent_block.index)
+                            .unwrap(),
                         ..Default::default()
                     },
                     &root_element,
@@ -1789,6 +1828,7 @@ mod tests {
                 &parent_block,
                 FormBlockInputs {
                     well_formed_encrypted_txs_with_proofs,
+                    parent_block_contents: ledger.get_block_contents(parent_block.index).unwrap(),
                     ..Default::default()
                 },
                 &root_element,
@@ -1907,6 +1947,7 @@ mod tests {
                 &parent_block,
                 FormBlockInputs {
                     well_formed_encrypted_txs_with_proofs,
+                    parent_block_contents: ledger.get_block_contents(parent_block.index).unwrap(),
                     ..Default::default()
                 },
                 &root_element,
@@ -2019,6 +2060,7 @@ mod tests {
                 &parent_block,
                 FormBlockInputs {
                     well_formed_encrypted_txs_with_proofs,
+                    parent_block_contents: ledger.get_block_contents(parent_block.index).unwrap(),
                     ..Default::default()
                 },
                 &root_element,
@@ -2109,6 +2151,7 @@ mod tests {
                 &parent_block,
                 FormBlockInputs {
                     well_formed_encrypted_txs_with_proofs,
+                    parent_block_contents: ledger.get_block_contents(parent_block.index).unwrap(),
                     ..Default::default()
                 },
                 &root_element,
@@ -2198,6 +2241,7 @@ mod tests {
                 &parent_block,
                 FormBlockInputs {
                     well_formed_encrypted_txs_with_proofs,
+                    parent_block_contents: ledger.get_block_contents(parent_block.index).unwrap(),
                     ..Default::default()
                 },
                 &root_element,
@@ -2215,6 +2259,140 @@ mod tests {
         }
     }
 
+    #[test_with_logger]
+    fn form_block_enforces_dynamic_minimum_fees(logger: Logger) {
+        let mut rng = Hc128Rng::from_seed([77u8; 32]);
+
+        // Every block in the fee schedule's window is full.
+        let recent_block_loads =
+            vec![MAX_TRANSACTIONS_PER_BLOCK as u64; fee_schedule::FEE_SCHEDULE_WINDOW];
+
+        for block_version in BlockVersion::iterator() {
+            let enclave = SgxConsensusEnclave::new(logger.clone());
+            let blockchain_config = BlockchainConfig {
+                block_version,
+                ..Default::default()
+            };
+            enclave
+                .enclave_init(
+                    &Default::default(),
+                    &Default::default(),
+                    &None,
+                    blockchain_config,
+                )
+                .unwrap();
+
+            // Initialize a ledger. `sender` is the owner of all outputs in the initial
+            // ledger.
+            let sender = AccountKey::random(&mut rng);
+            let mut ledger = create_ledger();
+            let n_blocks = 3;
+            initialize_ledger(block_version, &mut ledger, n_blocks, &sender, &mut rng);
+
+            // A transaction paying the configured minimum fee.
+            let block_zero_contents = ledger.get_block_contents(0).unwrap();
+            let tx = create_transaction(
+                block_version,
+                &mut ledger,
+                &block_zero_contents.outputs[0],
+                &sender,
+                &AccountKey::random(&mut rng).default_subaddress(),
+                n_blocks + 2,
+                &mut rng,
+            );
+
+            let well_formed_tx = WellFormedTx::from(tx);
+            let encrypted_tx = enclave
+                .encrypt_well_formed_tx(&well_formed_tx, &mut rng)
+                .unwrap();
+            let highest_indices = well_formed_tx.tx.get_membership_proof_highest_indices();
+            let membership_proofs = ledger
+                .get_tx_out_proof_of_memberships(&highest_indices)
+                .expect("failed getting proof");
+
+            let parent_block = ledger.get_block(ledger.num_blocks().unwrap() - 1).unwrap();
+            let parent_block_contents = ledger.get_block_contents(parent_block.index).unwrap();
+            let root_element = ledger.get_root_tx_out_membership_element().unwrap();
+
+            // A parent block whose contents record congestion.
+            let congested_block_contents = BlockContents {
+                recent_block_loads: recent_block_loads.clone(),
+                ..Default::default()
+            };
+            let congested_block = Block::new_with_parent(
+                block_version,
+                &parent_block,
+                &root_element,
+                &congested_block_contents,
+            );
+
+            // The loads must come from the contents of the parent block.
+            let form_block_result = enclave.form_block(
+                &parent_block,
+                FormBlockInputs {
+                    well_formed_encrypted_txs_with_proofs: vec![(
+                        encrypted_tx.clone(),
+                        membership_proofs.clone(),
+                    )],
+                    parent_block_contents: congested_block_contents.clone(),
+                    ..Default::default()
+                },
+                &root_element,
+            );
+            assert_eq!(
+                form_block_result,
+                Err(Error::InvalidLocalParentBlockContents)
+            );
+
+            let form_block_result = enclave.form_block(
+                &congested_block,
+                FormBlockInputs {
+                    well_formed_encrypted_txs_with_proofs: vec![(
+                        encrypted_tx.clone(),
+                        membership_proofs.clone(),
+                    )],
+                    parent_block_contents: congested_block_contents,
+                    ..Default::default()
+                },
+                &root_element,
+            );
+
+            if block_version.dynamic_minimum_fees_are_supported() {
+                assert!(matches!(
+                    form_block_result,
+                    Err(Error::MalformedTx(TransactionValidationError::TxFeeError))
+                ));
+            } else {
+                assert!(form_block_result.is_ok());
+            }
+
+            // Without recent load, the configured minimum fee is enough, and the
+            // block records its number of transactions and the window of loads for
+            // the fee schedule.
+            let (_block, block_contents, _signature) = enclave
+                .form_block(
+                    &parent_block,
+                    FormBlockInputs {
+                        well_formed_encrypted_txs_with_proofs: vec![(
+                            encrypted_tx,
+                            membership_proofs,
+                        )],
+                        parent_block_contents,
+                        ..Default::default()
+                    },
+                    &root_element,
+                )
+                .unwrap();
+            if block_version.dynamic_minimum_fees_are_supported() {
+                assert_eq!(block_contents.num_transactions, 1);
+                assert_eq!(block_contents.recent_block_loads, vec![1]);
+            } else {
+                assert_eq!(block_contents.num_transactions, 0);
+                assert!(block_contents.recent_block_loads.is_empty());
+            }
+        }
+    }
+
     #[test_with_logger]
     fn form_block_can_mint_new_tokens(logger: Logger) {
         let mut rng = Hc128Rng::from_seed([77u8; 32]);
@@ -2303,6 +2481,9 @@ mod tests {
                                 mint_config_tx2.prefix.configs[0].clone(),
                             ),
                         ],
+                        parent_block_contents: ledger
+                            .get_block_contents(parent_block.index)
+                            .unwrap(),
                         ..Default::default()
                     },
                     &root_element,
@@ -2429,6 +2610,7 @@ mod tests {
                             mint_config_tx1.prefix.configs[0].clone(),
                         ),
                     ],
+                    parent_block_contents: ledger.get_block_contents(parent_block.index).unwrap(),
                     ..Default::default()
                 },
                 &root_element,
@@ -2514,6 +2696,7 @@ mod tests {
                         mint_config_tx1.clone(),
                         mint_config_tx1.prefix.configs[0].clone(),
                     )],
+                    parent_block_contents: ledger.get_block_contents(parent_block.index).unwrap(),
                     ..Default::default()
                 },
                 &root_element,
@@ -2583,6 +2766,9 @@ mod tests {
                     &parent_block,
                     FormBlockInputs {
                         mint_config_txs: vec![mint_config_tx1.clone(), mint_config_tx2.clone()],
+                        parent_block_contents: ledger
+                            .get_block_contents(parent_block.index)
+                            .unwrap(),
                         ..Default::default()
                     },
                     &root_element,
@@ -2675,6 +2861,7 @@ mod tests {
                 &parent_block,
                 FormBlockInputs {
                     mint_config_txs: vec![mint_config_tx1.clone()],
+                    parent_block_contents: ledger.get_block_contents(parent_block.index).unwrap(),
                     ..Default::default()
                 },
                 &root_element,
@@ -2741,6 +2928,7 @@ mod tests {
                 &parent_block,
                 FormBlockInputs {
                     mint_config_txs: vec![mint_config_tx1.clone()],
+                    parent_block_contents: ledger.get_block_contents(parent_block.index).unwrap(),
                     ..Default::default()
                 },
                 &root_element,
@@ -2802,6 +2990,7 @@ mod tests {
                 &parent_block,
                 FormBlockInputs {
                     mint_config_txs: vec![mint_config_tx1.clone(), mint_config_tx1.clone()],
+                    parent_block_contents: ledger.get_block_contents(parent_block.index).unwrap(),
                     ..Default::default()
                 },
                 &root_element,
@@ -2937,6 +3126,9 @@ mod tests {
                                 mint_config_tx2.prefix.configs[0].clone(),
                             ),
                         ],
+                        parent_block_contents: ledger
+                            .get_block_contents(parent_block.index)
+                            .unwrap(),
                         ..Default::default()
                     },
                     &root_element,
@@ -3069,6 +3261,7 @@ mod tests {
                 &parent_block,
                 FormBlockInputs {
                     mint_config_txs: vec![mint_config_tx1.clone(), mint_config_tx2.clone()],
+                    parent_block_contents: ledger.get_block_contents(parent_block.index).unwrap(),
                     ..Default::default()
                 },
                 &root_element,
@@ -0,0 +1,364 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! An INSECURE, in-process implementation of the consensus enclave.
+//!
+//! This runs the same code as the consensus enclave (validation, block
+//! forming, fee collection and minting), but in the consensus service's own
+//! address space, and without SGX. Transactions, block signing keys and
+//! "sealed" data are visible to the host, and the node attests to itself
+//! using the simulated IAS signing key. It exists so that consensus networks
+//! can be run for development and testing on machines without SGX, and must
+//! never be used to process real transactions.
+
+use displaydoc::Display;
+use mc_attest_core::{
+    IasNonce, MrSigner, Quote, QuoteError, QuoteNonce, Report, TargetInfo, VerificationReport,
+};
+use mc_attest_enclave_api::{
+    ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage, PeerAuthRequest,
+    PeerAuthResponse, PeerSession,
+};
+use mc_attest_net::{Error as RaError, RaClient};
+use mc_attest_verifier::MrSignerVerifier;
+use mc_common::{
+    logger::{log, Logger},
+    ResponderId,
+};
+use mc_consensus_enclave_api::{
+    BlockchainConfig, ConsensusEnclave, ConsensusEnclaveProxy, FeePublicKey, FormBlockInputs,
+    LocallyEncryptedTx, Result, SealedBlockSigningKey, TxContext, WellFormedEncryptedTx,
+    WellFormedTxContext,
+};
+use mc_consensus_enclave_impl::SgxConsensusEnclave;
+use mc_crypto_keys::{Ed25519Public, X25519Public};
+use mc_sgx_report_cache_api::{
+    Error as ReportableEnclaveError, ReportableEnclave, Result as ReportableEnclaveResult,
+};
+use mc_sgx_types::sgx_report_t;
+use mc_transaction_core::{
+    tx::{TxOutMembershipElement, TxOutMembershipProof},
+    Block, BlockContents, BlockSignature, TokenId,
+};
+use mc_util_encodings::ToX64;
+use sha2::{Digest, Sha256};
+use std::{convert::TryFrom, result::Result as StdResult, sync::Arc};
+
+/// EPID quote version, as produced by the quoting enclave.
+const QUOTE_VERSION: u16 = 2;
+
+/// Offset of the report body within an EPID quote.
+const QUOTE_REPORT_BODY_START: usize = 48;
+
+/// Size of an EPID quote without a signature.
+const QUOTE_SIZE: usize = 436;
+
+/// An error while attesting the in-process enclave.
+#[derive(Debug, Display)]
+pub enum Error {
+    /// Enclave error: {0}
+    Enclave(ReportableEnclaveError),
+
+    /// Could not construct quote: {0}
+    Quote(QuoteError),
+
+    /// Could not sign the quote: {0}
+    Ra(RaError),
+}
+
+impl From<ReportableEnclaveError> for Error {
+    fn from(src: ReportableEnclaveError) -> Self {
+        Error::Enclave(src)
+    }
+}
+
+impl From<QuoteError> for Error {
+    fn from(src: QuoteError) -> Self {
+        Error::Quote(src)
+    }
+}
+
+impl From<RaError> for Error {
+    fn from(src: RaError) -> Self {
+        Error::Ra(src)
+    }
+}
+
+/// An INSECURE consensus enclave, which runs the enclave implementation
+/// in-process. See the crate documentation.
+#[derive(Clone)]
+pub struct InsecureConsensusEnclave {
+    enclave: Arc<SgxConsensusEnclave>,
+}
+
+impl InsecureConsensusEnclave {
+    /// Create and initialize the in-process enclave, returning it along with
+    /// the (unprotected) sealed block signing key and the target features.
+    pub fn new(
+        self_peer_id: &ResponderId,
+        self_client_id: &ResponderId,
+        sealed_key: &Option<SealedBlockSigningKey>,
+        blockchain_config: BlockchainConfig,
+        logger: Logger,
+    ) -> (Self, SealedBlockSigningKey, Vec<String>) {
+        log::error!(
+            logger,
+            "INSECURE: running the consensus enclave in-process, without SGX protection"
+        );
+
+        let enclave = Arc::new(SgxConsensusEnclave::new(logger));
+        let (sealed_key, features) = enclave
+            .enclave_init(self_peer_id, self_client_id, sealed_key, blockchain_config)
+            .expect("enclave_init failed");
+
+        (Self { enclave }, sealed_key, features)
+    }
+
+    /// Attest to ourselves, in place of the quoting enclave and IAS. The quote
+    /// is built directly from our report, and `ra_client` must be the
+    /// simulated IAS client so that peers built with `sgx-sim` accept the
+    /// resulting verification report.
+    pub fn attest<R: RaClient>(&self, ra_client: &R) -> StdResult<(), Error> {
+        let (report, quote_nonce) = self.enclave.new_ereport(TargetInfo::default())?;
+        let quote = quote_report(&report)?;
+        let qe_report = quoting_enclave_report(&quote_nonce, &quote);
+
+        let ias_nonce = self.enclave.verify_quote(quote.clone(), qe_report)?;
+        let ias_report = ra_client.verify_quote(&quote, Some(ias_nonce))?;
+        self.enclave.verify_ias_report(ias_report)?;
+
+        Ok(())
+    }
+}
+
+/// A verifier for the identity insecure enclaves attest to.
+///
+/// Reports produced without SGX have a default body, so the signer
+/// measurement, product ID and security version are all zero. Clients of a
+/// network of insecure nodes, which must also be built with `sgx-sim`, use
+/// this in place of the consensus enclave's sigstruct.
+pub fn mr_signer_verifier() -> MrSignerVerifier {
+    MrSignerVerifier::new(MrSigner::from([0u8; 32]), 0, 0)
+}
+
+/// Build the quote the quoting enclave would have produced for `report`.
+fn quote_report(report: &Report) -> StdResult<Quote, QuoteError> {
+    let report_body = report.body().to_x64_vec();
+
+    let mut bytes = vec![0u8; QUOTE_SIZE];
+    bytes[..2].copy_from_slice(&QUOTE_VERSION.to_le_bytes());
+    bytes[QUOTE_REPORT_BODY_START..QUOTE_REPORT_BODY_START + report_body.len()]
+        .copy_from_slice(&report_body);
+
+    Quote::try_from(bytes)
+}
+
+/// Build the quoting enclave's report for `quote`, which binds the quote to
+/// the nonce our enclave chose.
+fn quoting_enclave_report(quote_nonce: &QuoteNonce, quote: &Quote) -> Report {
+    let mut hasher = Sha256::new();
+    hasher.update(<QuoteNonce as AsRef<[u8]>>::as_ref(quote_nonce));
+    hasher.update(quote.as_ref());
+    let digest = hasher.finalize();
+
+    let mut report = sgx_report_t::default();
+    report.body.report_data.d[..digest.len()].copy_from_slice(&digest);
+    Report::from(report)
+}
+
+impl ConsensusEnclaveProxy for InsecureConsensusEnclave {}
+
+impl ReportableEnclave for InsecureConsensusEnclave {
+    fn new_ereport(&self, qe_info: TargetInfo) -> ReportableEnclaveResult<(Report, QuoteNonce)> {
+        self.enclave.new_ereport(qe_info)
+    }
+
+    fn verify_quote(&self, quote: Quote, qe_report: Report) -> ReportableEnclaveResult<IasNonce> {
+        self.enclave.verify_quote(quote, qe_report)
+    }
+
+    fn verify_ias_report(&self, ias_report: VerificationReport) -> ReportableEnclaveResult<()> {
+        self.enclave.verify_ias_report(ias_report)
+    }
+
+    fn get_ias_report(&self) -> ReportableEnclaveResult<VerificationReport> {
+        self.enclave.get_ias_report()
+    }
+}
+
+impl ConsensusEnclave for InsecureConsensusEnclave {
+    fn enclave_init(
+        &self,
+        self_peer_id: &ResponderId,
+        self_client_id: &ResponderId,
+        sealed_key: &Option<SealedBlockSigningKey>,
+        blockchain_config: BlockchainConfig,
+    ) -> Result<(SealedBlockSigningKey, Vec<String>)> {
+        self.enclave
+            .enclave_init(self_peer_id, self_client_id, sealed_key, blockchain_config)
+    }
+
+    fn get_minimum_fee(&self, token_id: &TokenId) -> Result<Option<u64>> {
+        self.enclave.get_minimum_fee(token_id)
+    }
+
+    fn get_identity(&self) -> Result<X25519Public> {
+        self.enclave.get_identity()
+    }
+
+    fn get_signer(&self) -> Result<Ed25519Public> {
+        self.enclave.get_signer()
+    }
+
+    fn get_fee_recipient(&self) -> Result<FeePublicKey> {
+        self.enclave.get_fee_recipient()
+    }
+
+    fn get_minting_trust_root(&self) -> Result<Ed25519Public> {
+        self.enclave.get_minting_trust_root()
+    }
+
+    fn client_accept(&self, req: ClientAuthRequest) -> Result<(ClientAuthResponse, ClientSession)> {
+        self.enclave.client_accept(req)
+    }
+
+    fn client_close(&self, channel_id: ClientSession) -> Result<()> {
+        self.enclave.client_close(channel_id)
+    }
+
+    fn client_discard_message(&self, msg: EnclaveMessage<ClientSession>) -> Result<()> {
+        self.enclave.client_discard_message(msg)
+    }
+
+    fn peer_init(&self, peer_id: &ResponderId) -> Result<PeerAuthRequest> {
+        self.enclave.peer_init(peer_id)
+    }
+
+    fn peer_accept(&self, req: PeerAuthRequest) -> Result<(PeerAuthResponse, PeerSession)> {
+        self.enclave.peer_accept(req)
+    }
+
+    fn peer_connect(
+        &self,
+        peer_id: &ResponderId,
+        res: PeerAuthResponse,
+    ) -> Result<(PeerSession, VerificationReport)> {
+        self.enclave.peer_connect(peer_id, res)
+    }
+
+    fn peer_close(&self, channel_id: &PeerSession) -> Result<()> {
+        self.enclave.peer_close(channel_id)
+    }
+
+    fn client_tx_propose(&self, msg: EnclaveMessage<ClientSession>) -> Result<TxContext> {
+        self.enclave.client_tx_propose(msg)
+    }
+
+    fn peer_tx_propose(&self, msg: EnclaveMessage<PeerSession>) -> Result<Vec<TxContext>> {
+        self.enclave.peer_tx_propose(msg)
+    }
+
+    fn tx_is_well_formed(
+        &self,
+        locally_encrypted_tx: LocallyEncryptedTx,
+        block_index: u64,
+        proofs: Vec<TxOutMembershipProof>,
+    ) -> Result<(WellFormedEncryptedTx, WellFormedTxContext)> {
+        self.enclave
+            .tx_is_well_formed(locally_encrypted_tx, block_index, proofs)
+    }
+
+    fn txs_for_peer(
+        &self,
+        encrypted_txs: &[WellFormedEncryptedTx],
+        aad: &[u8],
+        peer: &PeerSession,
+    ) -> Result<EnclaveMessage<PeerSession>> {
+        self.enclave.txs_for_peer(encrypted_txs, aad, peer)
+    }
+
+    fn form_block(
+        &self,
+        parent_block: &Block,
+        inputs: FormBlockInputs,
+        root_element: &TxOutMembershipElement,
+    ) -> Result<(Block, BlockContents, BlockSignature)> {
+        self.enclave.form_block(parent_block, inputs, root_element)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_attest_net::Client;
+    use mc_attest_verifier::Verifier;
+    use mc_common::logger::test_with_logger;
+
+    fn create_enclave(name: &str, logger: Logger) -> InsecureConsensusEnclave {
+        let peer_id = ResponderId(format!("{}:8443", name));
+        let client_id = ResponderId(format!("{}:3223", name));
+        let (enclave, _sealed_key, _features) = InsecureConsensusEnclave::new(
+            &peer_id,
+            &client_id,
+            &None,
+            BlockchainConfig::default(),
+            logger,
+        );
+        enclave
+            .attest(&Client::new("").unwrap())
+            .expect("Could not attest enclave");
+        enclave
+    }
+
+    #[test_with_logger]
+    fn test_attested_peers_connect(logger: Logger) {
+        let node_a = create_enclave("node-a", logger.clone());
+        let node_b = create_enclave("node-b", logger);
+        assert!(node_a.get_ias_report().is_ok());
+
+        // node-a connects to node-b, each verifying the other's self-attestation.
+        let node_b_id = ResponderId("node-b:8443".to_owned());
+        let auth_request = node_a.peer_init(&node_b_id).unwrap();
+        let (auth_response, _session) = node_b.peer_accept(auth_request).unwrap();
+        let (_session, verification_report) =
+            node_a.peer_connect(&node_b_id, auth_response).unwrap();
+
+        assert_eq!(verification_report, node_b.get_ias_report().unwrap());
+    }
+
+    #[test_with_logger]
+    fn test_mr_signer_verifier_accepts_report(logger: Logger) {
+        let enclave = create_enclave("node-a", logger);
+        let ias_report = enclave.get_ias_report().unwrap();
+
+        let mut verifier = Verifier::default();
+        verifier.mr_signer(mr_signer_verifier()).debug(false);
+        verifier
+            .verify(&ias_report)
+            .expect("Insecure identity was not accepted");
+    }
+
+    #[test_with_logger]
+    fn test_sealed_key_round_trips(logger: Logger) {
+        let peer_id = ResponderId("node-a:8443".to_owned());
+        let client_id = ResponderId("node-a:3223".to_owned());
+        let (enclave, sealed_key, _features) = InsecureConsensusEnclave::new(
+            &peer_id,
+            &client_id,
+            &None,
+            BlockchainConfig::default(),
+            logger.clone(),
+        );
+
+        let (restarted, _sealed_key, _features) = InsecureConsensusEnclave::new(
+            &peer_id,
+            &client_id,
+            &Some(sealed_key),
+            BlockchainConfig::default(),
+            logger,
+        );
+        assert_eq!(
+            enclave.get_signer().unwrap(),
+            restarted.get_signer().unwrap()
+        );
+    }
+}
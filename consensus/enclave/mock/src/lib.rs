@@ -19,6 +19,7 @@ use mc_attest_enclave_api::{
     PeerAuthResponse, PeerSession,
 };
 use mc_common::ResponderId;
+use mc_consensus_enclave_api::fee_schedule;
 use mc_crypto_keys::{
     Ed25519Pair, Ed25519Public, RistrettoPublic, X25519EphemeralPrivate, X25519Public,
 };
@@ -276,6 +277,17 @@ impl ConsensusEnclave for ConsensusServiceMockEnclave {
             return Err(Error::InvalidLocalMembershipProof);
         }
 
+        let num_transactions = if block_version.dynamic_minimum_fees_are_supported() {
+            transactions_with_proofs.len() as u64
+        } else {
+            0
+        };
+        let recent_block_loads = fee_schedule::recent_block_loads(
+            block_version,
+            &inputs.parent_block_contents.recent_block_loads,
+            num_transactions,
+        );
+
         let mut key_images: Vec<KeyImage> = Vec::new();
         let mut outputs: Vec<TxOut> = Vec::new();
         for (tx, _proofs) in transactions_with_proofs {
@@ -318,6 +330,8 @@ impl ConsensusEnclave for ConsensusServiceMockEnclave {
                 .map(|(mint_tx, _mint_config_tx, _mint_config)| mint_tx)
                 .collect(),
             validated_mint_config_txs,
+            num_transactions,
+            recent_block_loads,
         };
 
         let block =
@@ -3,8 +3,8 @@
 //! The Consensus Service SGX Enclave Proxy
 
 pub use mc_consensus_enclave_api::{
-    BlockchainConfig, ConsensusEnclave, ConsensusEnclaveProxy, EnclaveCall, Error, FeeMap,
-    FeeMapError, FeePublicKey, FormBlockInputs, GovernorsMap, LocallyEncryptedTx, Result,
+    fee_schedule, BlockchainConfig, ConsensusEnclave, ConsensusEnclaveProxy, EnclaveCall, Error,
+    FeeMap, FeeMapError, FeePublicKey, FormBlockInputs, GovernorsMap, LocallyEncryptedTx, Result,
     TxContext, WellFormedEncryptedTx, WellFormedTxContext,
 };
 
@@ -0,0 +1,191 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+#![deny(missing_docs)]
+
+//! A utility that checks whether a network's quorum set configurations enjoy
+//! quorum intersection, and which sets of nodes can halt or split it.
+//!
+//! Quorum sets are gathered from network.toml/json files, from running nodes,
+//! or both. The analysis is printed as JSON, and the process exits with a
+//! non-zero status if the network lacks quorum intersection.
+
+use clap::Parser;
+use grpcio::{ChannelBuilder, EnvBuilder};
+use mc_common::{
+    logger::{create_app_logger, log, o, Logger},
+    ResponderId,
+};
+use mc_consensus_api::{consensus_client_grpc::ConsensusClientApiClient, empty::Empty};
+use mc_consensus_scp::{quorum_analysis::QuorumAnalyzer, QuorumSet};
+use mc_consensus_service_config::NetworkConfig;
+use mc_util_grpc::ConnectionUriGrpcioChannel;
+use mc_util_uri::ConsensusClientUri;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+    str::FromStr,
+    sync::Arc,
+    thread::sleep,
+    time::Duration,
+};
+
+/// Command line configuration.
+#[derive(Clone, Debug, Parser)]
+pub struct Config {
+    /// A node's network configuration, as `<peer responder id>=<path>`, where
+    /// the path points at the network.toml or network.json file the node is
+    /// started with. May be given multiple times.
+    #[clap(long = "node", parse(try_from_str = parse_node), env = "MC_NODES", use_value_delimiter = true)]
+    pub nodes: Vec<(ResponderId, PathBuf)>,
+
+    /// Client URI of a running node to fetch the quorum set from. May be given
+    /// multiple times.
+    #[clap(long = "peer", env = "MC_PEERS", use_value_delimiter = true)]
+    pub peers: Vec<ConsensusClientUri>,
+
+    /// JSON file mapping organization names to lists of peer responder ids.
+    /// When omitted, nodes are grouped by the last two labels of their host
+    /// name.
+    #[clap(long, parse(from_os_str), env = "MC_ORGANIZATIONS")]
+    pub organizations: Option<PathBuf>,
+
+    /// Largest blocking set to search for.
+    #[clap(long, default_value = "3", env = "MC_MAX_BLOCKING_SET_SIZE")]
+    pub max_blocking_set_size: usize,
+}
+
+/// Parse a `<peer responder id>=<path>` pair.
+fn parse_node(src: &str) -> Result<(ResponderId, PathBuf), String> {
+    let (responder_id, path) = src
+        .split_once('=')
+        .ok_or_else(|| format!("Expected <peer responder id>=<path>, got '{}'", src))?;
+    let responder_id = ResponderId::from_str(responder_id)
+        .map_err(|err| format!("Invalid responder id '{}': {:?}", responder_id, err))?;
+    Ok((responder_id, PathBuf::from(path)))
+}
+
+/// Fetch a running node's peer responder id and quorum set.
+fn fetch_quorum_set(
+    uri: &ConsensusClientUri,
+    env: Arc<grpcio::Environment>,
+    logger: &Logger,
+) -> (ResponderId, QuorumSet<ResponderId>) {
+    let ch = ChannelBuilder::default_channel_builder(env).connect_to_uri(uri, logger);
+    let client_api = ConsensusClientApiClient::new(ch);
+
+    let node_config = client_api
+        .get_node_config(&Empty::new())
+        .unwrap_or_else(|err| panic!("Failed getting node config from {}: {}", uri, err));
+    if !node_config.has_quorum_set() {
+        panic!("{} did not report its quorum set", uri);
+    }
+
+    let responder_id = ResponderId::from_str(node_config.get_peer_responder_id())
+        .unwrap_or_else(|err| panic!("{} reported an invalid responder id: {:?}", uri, err));
+    let quorum_set = QuorumSet::try_from(node_config.get_quorum_set())
+        .unwrap_or_else(|err| panic!("{} reported an invalid quorum set: {:?}", uri, err));
+    (responder_id, quorum_set)
+}
+
+/// Load organizations from a JSON file.
+fn load_organizations(path: &Path) -> BTreeMap<String, BTreeSet<ResponderId>> {
+    let bytes = fs::read(path).unwrap_or_else(|err| panic!("Failed reading {:?}: {}", path, err));
+    serde_json::from_slice(&bytes)
+        .unwrap_or_else(|err| panic!("Failed parsing {:?}: {}", path, err))
+}
+
+/// Group nodes by the last two labels of their host name, e.g.
+/// `node1.prod.mobilecoin.com:443` belongs to `mobilecoin.com`.
+fn default_organizations(
+    nodes: impl IntoIterator<Item = ResponderId>,
+) -> BTreeMap<String, BTreeSet<ResponderId>> {
+    let mut organizations: BTreeMap<String, BTreeSet<ResponderId>> = BTreeMap::new();
+    for responder_id in nodes {
+        let host = responder_id
+            .0
+            .rsplit_once(':')
+            .map_or(&responder_id.0[..], |(host, _)| host);
+        let labels: Vec<&str> = host.rsplitn(3, '.').take(2).collect();
+        let name = labels.into_iter().rev().collect::<Vec<_>>().join(".");
+        organizations.entry(name).or_default().insert(responder_id);
+    }
+    organizations
+}
+
+fn main() {
+    let config = Config::parse();
+
+    mc_common::setup_panic_handler();
+    let (logger, _global_logger_guard) = create_app_logger(o!());
+
+    let mut quorum_sets: BTreeMap<ResponderId, QuorumSet<ResponderId>> = BTreeMap::new();
+
+    for (responder_id, path) in &config.nodes {
+        let network = NetworkConfig::load_from_path(path, responder_id)
+            .unwrap_or_else(|err| panic!("Failed loading {:?}: {:?}", path, err));
+        quorum_sets.insert(responder_id.clone(), network.quorum_set);
+    }
+
+    if !config.peers.is_empty() {
+        let env = Arc::new(
+            EnvBuilder::new()
+                .name_prefix("quorum-analyzer-grpc")
+                .build(),
+        );
+        for uri in &config.peers {
+            let (responder_id, quorum_set) = fetch_quorum_set(uri, env.clone(), &logger);
+            log::info!(
+                logger,
+                "Fetched quorum set of {} from {}",
+                responder_id,
+                uri
+            );
+            if let Some(existing) = quorum_sets.insert(responder_id.clone(), quorum_set.clone()) {
+                if existing != quorum_set {
+                    log::warn!(
+                        logger,
+                        "{} is running with a different quorum set than its configuration file, using the running one",
+                        responder_id
+                    );
+                }
+            }
+        }
+    }
+
+    if quorum_sets.is_empty() {
+        panic!("No quorum sets given, use --node or --peer");
+    }
+
+    let analyzer = QuorumAnalyzer::new(quorum_sets);
+    let organizations = match &config.organizations {
+        Some(path) => load_organizations(path),
+        None => default_organizations(analyzer.nodes()),
+    };
+    let analysis = analyzer.analyze(&organizations, config.max_blocking_set_size);
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&analysis).expect("Failed serializing analysis")
+    );
+
+    let exit_code = if analysis.has_quorum_intersection() {
+        log::info!(logger, "The network enjoys quorum intersection");
+        0
+    } else if analysis.minimal_quorums.is_empty() {
+        log::crit!(logger, "The network has no quorum");
+        1
+    } else {
+        log::crit!(
+            logger,
+            "The network lacks quorum intersection: {} pairs of disjoint quorums",
+            analysis.intersection_violations.len()
+        );
+        1
+    };
+
+    // Give logger a moment to flush.
+    sleep(Duration::from_secs(1));
+    exit(exit_code);
+}
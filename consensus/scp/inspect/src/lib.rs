@@ -0,0 +1,369 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Inspection of SCP message logs written by `LoggingScpNode`.
+//!
+//! The inspector reconstructs, for each logged slot, the timeline of
+//! statements the logged node received and sent. Along the way it replays
+//! federated voting over the latest statement of each node, to show when each
+//! threshold was crossed and by which quorum or blocking set, and explains
+//! each timeout by how the members of the logged node's quorum set stood at
+//! the time.
+
+#![deny(missing_docs)]
+
+mod report;
+mod timeline;
+mod voting;
+
+pub use crate::timeline::{
+    BallotSummary, EventKind, FederatedStatement, SlotTimeline, Statement, StatementKind,
+    Threshold, ThresholdCrossing, TimelineEvent, Timeout, TimeoutKind,
+};
+
+use crate::voting::SlotVotes;
+use mc_consensus_scp::{
+    msg::Topic,
+    scp_log::{LoggedMsg, ScpLogReader, StoredMsg},
+    Msg, Value,
+};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+/// Reconstruct the timelines of the slots in a log.
+///
+/// # Arguments
+/// * `stored_msgs` - The log's messages, in order. Each slot starts with a
+///   `NodeSettings` message.
+pub fn inspect<V: Value>(
+    stored_msgs: impl IntoIterator<Item = StoredMsg<V>>,
+) -> Result<Vec<SlotTimeline>, String> {
+    let mut timelines = Vec::new();
+    let mut inspector: Option<SlotInspector<V>> = None;
+
+    for stored_msg in stored_msgs {
+        if let LoggedMsg::NodeSettings(node_id, quorum_set, slot_index) = stored_msg.msg {
+            timelines.extend(inspector.take().map(|inspector| inspector.timeline));
+            inspector = Some(SlotInspector {
+                timeline: SlotTimeline::new(&node_id, &quorum_set, slot_index),
+                votes: SlotVotes::new(node_id, quorum_set),
+            });
+            continue;
+        }
+
+        inspector
+            .as_mut()
+            .ok_or_else(|| "Log does not start with node settings".to_owned())?
+            .handle(stored_msg);
+    }
+
+    timelines.extend(inspector.map(|inspector| inspector.timeline));
+    Ok(timelines)
+}
+
+/// Reconstruct the timelines of the slots logged in a directory.
+///
+/// # Arguments
+/// * `path` - The directory `LoggingScpNode` writes the current slot's messages
+///   to, or its parent (the node's `--scp-debug-dump` directory).
+pub fn inspect_dir<V: Value + DeserializeOwned>(path: &Path) -> Result<Vec<SlotTimeline>, String> {
+    let cur_slot_path = path.join("cur-slot");
+    let path = if cur_slot_path.is_dir() {
+        cur_slot_path.as_path()
+    } else {
+        path
+    };
+    inspect(ScpLogReader::<V>::new(path)?)
+}
+
+/// Builds the timeline of a single slot.
+struct SlotInspector<V: Value> {
+    timeline: SlotTimeline,
+    votes: SlotVotes<V>,
+}
+
+impl<V: Value> SlotInspector<V> {
+    fn handle(&mut self, stored_msg: StoredMsg<V>) {
+        let msec_since_start = stored_msg.msec_since_start;
+        match stored_msg.msg {
+            LoggedMsg::NodeSettings(..) => unreachable!("handled by inspect"),
+
+            LoggedMsg::IncomingMsg(msg) => {
+                self.push(msec_since_start, EventKind::Received((&msg).into()));
+                self.record(msec_since_start, &msg);
+            }
+
+            LoggedMsg::OutgoingMsg(msg) => self.sent(msec_since_start, &msg),
+
+            LoggedMsg::Nominate(_slot_index, values) => self.push(
+                msec_since_start,
+                EventKind::Proposed {
+                    num_values: values.len(),
+                },
+            ),
+
+            LoggedMsg::ProcessTimeouts(msgs) => {
+                for msg in msgs {
+                    let timeout = self.votes.timeout(&msg);
+                    self.push(msec_since_start, EventKind::Timeout(timeout));
+                    self.sent(msec_since_start, &msg);
+                }
+            }
+
+            LoggedMsg::Marker(text) => self.push(msec_since_start, EventKind::Marker { text }),
+        }
+    }
+
+    fn sent(&mut self, msec_since_start: u64, msg: &Msg<V>) {
+        self.push(msec_since_start, EventKind::Sent(msg.into()));
+        if let Topic::Externalize(payload) = &msg.topic {
+            if self.timeline.externalized.is_none() {
+                self.timeline.externalized = Some((&payload.C).into());
+                self.timeline.externalized_at = Some(msec_since_start);
+            }
+        }
+        self.record(msec_since_start, msg);
+    }
+
+    /// Record a statement, and the thresholds it allowed to cross.
+    fn record(&mut self, msec_since_start: u64, msg: &Msg<V>) {
+        // Messages for other slots are not used by the slot.
+        if msg.slot_index != self.timeline.slot_index {
+            return;
+        }
+        self.votes.insert(msg);
+        for crossing in self.votes.new_crossings() {
+            self.push(msec_since_start, EventKind::Threshold(crossing));
+        }
+    }
+
+    fn push(&mut self, msec_since_start: u64, kind: EventKind) {
+        self.timeline.events.push(TimelineEvent {
+            msec_since_start,
+            kind,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::{
+        logger::{test_with_logger, Logger},
+        NodeID,
+    };
+    use mc_consensus_scp::{
+        core_types::Ballot,
+        msg::{NominatePayload, PreparePayload},
+        scp_log::LoggingScpNode,
+        test_utils::{test_node_id, trivial_combine_fn, trivial_validity_fn},
+        Node, QuorumSet, ScpNode,
+    };
+    use std::{collections::BTreeSet, sync::Arc};
+    use tempdir::TempDir;
+
+    fn stored(msec_since_start: u64, msg: LoggedMsg<u32>) -> StoredMsg<u32> {
+        StoredMsg {
+            msec_since_start,
+            msg,
+        }
+    }
+
+    fn prepare(node_id: &NodeID, quorum_set: &QuorumSet, ballot: Ballot<u32>) -> Msg<u32> {
+        Msg::new(
+            node_id.clone(),
+            quorum_set.clone(),
+            1,
+            Topic::Prepare(PreparePayload {
+                B: ballot,
+                P: None,
+                PP: None,
+                CN: 0,
+                HN: 0,
+            }),
+        )
+    }
+
+    #[test_with_logger]
+    // Two nodes that trust each other should cross every threshold, in order.
+    fn test_inspect_externalized_slot(logger: Logger) {
+        let node_1 = test_node_id(1);
+        let node_2 = test_node_id(2);
+        let temp_dir = TempDir::new("scp_inspect").unwrap();
+
+        let mut nodes: Vec<Box<dyn ScpNode<u32>>> = Vec::new();
+        for (node_id, peer_id) in [(&node_1, &node_2), (&node_2, &node_1)] {
+            let node = Node::new(
+                node_id.clone(),
+                QuorumSet::new_with_node_ids(1, vec![peer_id.clone()]),
+                Arc::new(trivial_validity_fn),
+                Arc::new(trivial_combine_fn),
+                1,
+                logger.clone(),
+            );
+            if *node_id == node_1 {
+                let logging_node =
+                    LoggingScpNode::new(node, temp_dir.path().join("node1"), logger.clone())
+                        .unwrap();
+                nodes.push(Box::new(logging_node));
+            } else {
+                nodes.push(Box::new(node));
+            }
+        }
+
+        // Exchange messages until both nodes externalize.
+        let values: BTreeSet<u32> = [1000, 2000].iter().cloned().collect();
+        let mut pending: Vec<(usize, Msg<u32>)> = Vec::new();
+        for (i, node) in nodes.iter_mut().enumerate() {
+            if let Some(msg) = node.propose_values(values.clone()).unwrap() {
+                pending.push((1 - i, msg));
+            }
+        }
+        let mut num_msgs = 0;
+        while let Some((i, msg)) = pending.pop() {
+            num_msgs += 1;
+            assert!(num_msgs < 1000, "nodes did not stop exchanging messages");
+            if let Some(response) = nodes[i].handle_message(&msg).unwrap() {
+                pending.push((1 - i, response));
+            }
+        }
+        for node in &nodes {
+            assert_eq!(
+                node.get_externalized_values(1),
+                Some(vec![1000, 2000]),
+                "node did not externalize"
+            );
+        }
+
+        let timelines = inspect_dir::<u32>(&temp_dir.path().join("node1")).unwrap();
+        assert_eq!(timelines.len(), 1);
+        let timeline = &timelines[0];
+        assert_eq!(timeline.node, node_1.responder_id);
+        assert_eq!(timeline.slot_index, 1);
+
+        let externalized = timeline.externalized.clone().expect("not externalized");
+        assert_eq!(externalized.num_values, 2);
+
+        // Every step of federated voting is reported, first reached in protocol
+        // order.
+        let mut statements: Vec<FederatedStatement> = Vec::new();
+        for crossing in timeline.threshold_crossings() {
+            if !statements.contains(&crossing.statement) {
+                statements.push(crossing.statement);
+            }
+        }
+        assert_eq!(
+            statements,
+            vec![
+                FederatedStatement::AcceptNominate,
+                FederatedStatement::ConfirmNominate,
+                FederatedStatement::AcceptPrepare,
+                FederatedStatement::ConfirmPrepare,
+                FederatedStatement::AcceptCommit,
+                FederatedStatement::ConfirmCommit,
+            ]
+        );
+
+        // The logged node is part of every quorum it finds.
+        for crossing in timeline.threshold_crossings() {
+            if crossing.threshold == Threshold::Quorum {
+                assert!(crossing.nodes.contains(&node_1.responder_id));
+            }
+        }
+
+        // Both renderings include the externalized ballot.
+        let json = serde_json::to_string(&timelines).unwrap();
+        assert!(json.contains(&externalized.values_hash));
+        assert!(timeline
+            .to_string()
+            .contains(&format!("Externalized {}", externalized)));
+    }
+
+    #[test]
+    // A ballot timeout should be explained by where the quorum set members
+    // stood.
+    fn test_inspect_ballot_timeout() {
+        let local = test_node_id(1);
+        let quorum_set = QuorumSet::new_with_node_ids(
+            2,
+            vec![test_node_id(2), test_node_id(3), test_node_id(4)],
+        );
+        let peer_quorum_set = QuorumSet::new_with_node_ids(1, vec![local.clone()]);
+
+        let nominate = Msg::new(
+            test_node_id(4),
+            peer_quorum_set.clone(),
+            1,
+            Topic::Nominate(NominatePayload {
+                X: [7].iter().cloned().collect(),
+                Y: BTreeSet::default(),
+            }),
+        );
+
+        let timelines = inspect(vec![
+            stored(
+                0,
+                LoggedMsg::NodeSettings(local.clone(), quorum_set.clone(), 1),
+            ),
+            stored(
+                5,
+                LoggedMsg::OutgoingMsg(prepare(&local, &quorum_set, Ballot::new(2, &[7]))),
+            ),
+            stored(
+                6,
+                LoggedMsg::IncomingMsg(prepare(
+                    &test_node_id(2),
+                    &peer_quorum_set,
+                    Ballot::new(2, &[8]),
+                )),
+            ),
+            stored(
+                7,
+                LoggedMsg::IncomingMsg(prepare(
+                    &test_node_id(3),
+                    &peer_quorum_set,
+                    Ballot::new(1, &[7]),
+                )),
+            ),
+            stored(8, LoggedMsg::IncomingMsg(nominate)),
+            stored(
+                1000,
+                LoggedMsg::ProcessTimeouts(vec![prepare(
+                    &local,
+                    &quorum_set,
+                    Ballot::new(3, &[7]),
+                )]),
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(timelines.len(), 1);
+        let timeline = &timelines[0];
+        assert_eq!(timeline.externalized, None);
+
+        let timeouts: Vec<&Timeout> = timeline.timeouts().collect();
+        assert_eq!(timeouts.len(), 1);
+        let timeout = timeouts[0];
+        assert_eq!(timeout.kind, TimeoutKind::Ballot);
+        assert_eq!(timeout.ballot, Some(BallotSummary::new(2, &[7])));
+        assert_eq!(timeout.next_ballot, Some(BallotSummary::new(3, &[7])));
+        assert_eq!(timeout.waiting_for, Some(FederatedStatement::AcceptPrepare));
+        assert!(timeout.agreeing.is_empty());
+        assert_eq!(timeout.other_value, vec![test_node_id(2).responder_id]);
+        assert_eq!(timeout.behind, vec![test_node_id(3).responder_id]);
+        assert_eq!(timeout.silent, vec![test_node_id(4).responder_id]);
+
+        // No threshold was crossed.
+        assert!(timeline.threshold_crossings().next().is_none());
+    }
+
+    #[test]
+    fn test_inspect_requires_node_settings() {
+        let local = test_node_id(1);
+        let quorum_set = QuorumSet::new_with_node_ids(1, vec![test_node_id(2)]);
+        assert!(inspect(vec![stored(
+            0,
+            LoggedMsg::OutgoingMsg(prepare(&local, &quorum_set, Ballot::new(1, &[7]))),
+        )])
+        .is_err());
+    }
+}
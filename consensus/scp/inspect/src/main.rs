@@ -0,0 +1,67 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+#![deny(missing_docs)]
+
+//! A utility to inspect SCP messages logged by `LoggingScpNode`.
+
+use clap::{ArgEnum, Parser};
+use mc_consensus_scp_inspect::{inspect_dir, SlotTimeline};
+use mc_peers::ConsensusValue;
+use std::{fs, path::PathBuf};
+
+/// Output formats.
+#[derive(ArgEnum, Clone, Copy, Debug)]
+pub enum Format {
+    /// A human-readable report.
+    Text,
+
+    /// JSON, for tooling.
+    Json,
+}
+
+/// Configurable options.
+#[derive(Debug, Parser)]
+pub struct Config {
+    /// SCP debug dump directories, one per logged node.
+    ///
+    /// Either a node's `--scp-debug-dump` directory, or its `cur-slot`
+    /// subdirectory.
+    #[clap(required = true, parse(from_os_str))]
+    pub scp_debug_dumps: Vec<PathBuf>,
+
+    /// Output format.
+    #[clap(long, arg_enum, default_value = "text", env = "MC_FORMAT")]
+    pub format: Format,
+
+    /// Write the output to this file instead of stdout.
+    #[clap(long, parse(from_os_str), env = "MC_OUTPUT")]
+    pub output: Option<PathBuf>,
+}
+
+fn main() {
+    let config = Config::parse();
+
+    let mut timelines: Vec<SlotTimeline> = Vec::new();
+    for path in &config.scp_debug_dumps {
+        timelines.extend(
+            inspect_dir::<ConsensusValue>(path)
+                .unwrap_or_else(|err| panic!("failed inspecting {:?}: {}", path, err)),
+        );
+    }
+
+    let output = match config.format {
+        Format::Text => timelines
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Format::Json => {
+            serde_json::to_string_pretty(&timelines).expect("failed serializing timelines")
+        }
+    };
+
+    match config.output {
+        Some(path) => fs::write(&path, output)
+            .unwrap_or_else(|err| panic!("failed writing {:?}: {}", path, err)),
+        None => println!("{}", output),
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Human-readable rendering of slot timelines.
+
+use crate::timeline::{
+    BallotSummary, EventKind, FederatedStatement, SlotTimeline, Threshold, ThresholdCrossing,
+    Timeout, TimeoutKind,
+};
+use mc_common::ResponderId;
+use mc_consensus_scp::{msg::INFINITY, QuorumSet, QuorumSetMember};
+use std::fmt;
+
+impl fmt::Display for BallotSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "<{}, {}:{}>",
+            format_counter(self.counter),
+            self.num_values,
+            self.values_hash
+        )
+    }
+}
+
+impl fmt::Display for FederatedStatement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            FederatedStatement::AcceptNominate => "accept nominate",
+            FederatedStatement::ConfirmNominate => "confirm nominate",
+            FederatedStatement::AcceptPrepare => "accept prepare",
+            FederatedStatement::ConfirmPrepare => "confirm prepare",
+            FederatedStatement::AcceptCommit => "accept commit",
+            FederatedStatement::ConfirmCommit => "confirm commit",
+        })
+    }
+}
+
+impl fmt::Display for ThresholdCrossing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.statement)?;
+        if let Some(num_values) = self.num_values {
+            write!(f, " ({} values)", num_values)?;
+        }
+        if let Some(ballot) = &self.ballot {
+            match self.max_counter {
+                Some(max_counter) if max_counter != ballot.counter => {
+                    write!(f, " {}..{}", ballot, format_counter(max_counter))?
+                }
+                _ => write!(f, " {}", ballot)?,
+            }
+        }
+        let threshold = match self.threshold {
+            Threshold::Quorum => "quorum",
+            Threshold::BlockingSet => "blocking set",
+        };
+        write!(f, ": {} {}", threshold, format_nodes(&self.nodes))
+    }
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.kind, &self.ballot) {
+            (TimeoutKind::Ballot, Some(ballot)) => write!(f, "ballot {} timed out", ballot)?,
+            _ => write!(f, "nomination round timed out")?,
+        }
+        if let Some(next_ballot) = &self.next_ballot {
+            write!(f, ", moving to {}", next_ballot)?;
+        }
+        if let Some(waiting_for) = self.waiting_for {
+            write!(f, "; waiting for {}", waiting_for)?;
+        }
+        for (label, nodes) in [
+            ("agreeing", &self.agreeing),
+            ("other value", &self.other_value),
+            ("behind", &self.behind),
+            ("silent", &self.silent),
+        ] {
+            if !nodes.is_empty() {
+                write!(f, "; {}: {}", label, format_nodes(nodes))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SlotTimeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Slot {} on {}, quorum set {}",
+            self.slot_index,
+            self.node,
+            format_quorum_set(&self.quorum_set)
+        )?;
+
+        for event in &self.events {
+            write!(f, "{:>8}ms  ", event.msec_since_start)?;
+            match &event.kind {
+                EventKind::Proposed { num_values } => {
+                    writeln!(f, "   proposed {} values", num_values)?
+                }
+                EventKind::Received(statement) => writeln!(f, "<- {}", statement.description)?,
+                EventKind::Sent(statement) => writeln!(f, "-> {}", statement.description)?,
+                EventKind::Threshold(crossing) => writeln!(f, "** {}", crossing)?,
+                EventKind::Timeout(timeout) => writeln!(f, "!! {}", timeout)?,
+                EventKind::Marker { text } => writeln!(f, "   marker: {}", text)?,
+            }
+        }
+
+        match (&self.externalized, self.externalized_at) {
+            (Some(ballot), Some(msec)) => writeln!(f, "Externalized {} at {}ms", ballot, msec),
+            _ => writeln!(f, "Not externalized"),
+        }
+    }
+}
+
+fn format_counter(counter: u32) -> String {
+    if counter == INFINITY {
+        "inf".to_owned()
+    } else {
+        counter.to_string()
+    }
+}
+
+fn format_nodes(nodes: &[ResponderId]) -> String {
+    let nodes: Vec<String> = nodes.iter().map(ToString::to_string).collect();
+    format!("[{}]", nodes.join(", "))
+}
+
+fn format_quorum_set(quorum_set: &QuorumSet<ResponderId>) -> String {
+    let members: Vec<String> = quorum_set
+        .members
+        .iter()
+        .map(|member| match member {
+            QuorumSetMember::Node(responder_id) => responder_id.to_string(),
+            QuorumSetMember::InnerSet(inner_set) => format_quorum_set(inner_set),
+        })
+        .collect();
+    format!("{} of [{}]", quorum_set.threshold, members.join(", "))
+}
@@ -0,0 +1,363 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! The reconstructed timeline of a slot, as seen by a logged node.
+
+use mc_common::{NodeID, ResponderId};
+use mc_consensus_scp::{core_types::Ballot, msg::Topic, Msg, QuorumSet, SlotIndex, Value};
+use serde::Serialize;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// A ballot, with its values abbreviated to their number and a hash. The hash
+/// matches the one in the node's own log lines, e.g. `<3, 2:9f3a...>`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct BallotSummary {
+    /// The ballot counter.
+    pub counter: u32,
+
+    /// Number of values in the ballot.
+    pub num_values: usize,
+
+    /// Hash of the ballot's values.
+    pub values_hash: String,
+}
+
+impl BallotSummary {
+    /// Summarize the ballot `<counter, values>`.
+    pub fn new<V: Value>(counter: u32, values: &[V]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        values.hash(&mut hasher);
+        Self {
+            counter,
+            num_values: values.len(),
+            values_hash: format!("{:x}", hasher.finish()),
+        }
+    }
+}
+
+impl<V: Value> From<&Ballot<V>> for BallotSummary {
+    fn from(src: &Ballot<V>) -> Self {
+        Self::new(src.N, &src.X)
+    }
+}
+
+/// The kind of an SCP statement.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementKind {
+    /// Nominate.
+    Nominate,
+
+    /// Nominate, along with Prepare.
+    NominatePrepare,
+
+    /// Prepare.
+    Prepare,
+
+    /// Commit.
+    Commit,
+
+    /// Externalize.
+    Externalize,
+}
+
+/// An SCP statement issued by a node.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Statement {
+    /// The node that issued the statement.
+    pub node: ResponderId,
+
+    /// The kind of statement.
+    pub kind: StatementKind,
+
+    /// Number of values voted nominated (X).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voted_nominated: Option<usize>,
+
+    /// Number of values accepted nominated (Y).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accepted_nominated: Option<usize>,
+
+    /// The current ballot (B), or the committed ballot (C) of an Externalize
+    /// statement.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ballot: Option<BallotSummary>,
+
+    /// The highest ballot accepted prepared (P).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prepared: Option<BallotSummary>,
+
+    /// The highest ballot accepted prepared with a value other than P's (PP).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prepared_prime: Option<BallotSummary>,
+
+    /// The counter of the highest ballot accepted prepared, in a Commit
+    /// statement (PN).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prepared_counter: Option<u32>,
+
+    /// The lowest ballot counter voted or accepted committed (CN).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_counter: Option<u32>,
+
+    /// The highest ballot counter confirmed prepared, or accepted committed
+    /// (HN).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub high_counter: Option<u32>,
+
+    /// The statement, formatted the way the node logs it.
+    pub description: String,
+}
+
+impl<V: Value> From<&Msg<V>> for Statement {
+    fn from(msg: &Msg<V>) -> Self {
+        let mut statement = Self {
+            node: msg.sender_id.responder_id.clone(),
+            kind: StatementKind::Nominate,
+            voted_nominated: None,
+            accepted_nominated: None,
+            ballot: None,
+            prepared: None,
+            prepared_prime: None,
+            prepared_counter: None,
+            commit_counter: None,
+            high_counter: None,
+            description: msg.to_string(),
+        };
+
+        if let Topic::Nominate(payload) | Topic::NominatePrepare(payload, _) = &msg.topic {
+            statement.voted_nominated = Some(payload.X.len());
+            statement.accepted_nominated = Some(payload.Y.len());
+        }
+
+        match &msg.topic {
+            Topic::Nominate(_) => {}
+            Topic::NominatePrepare(_, payload) | Topic::Prepare(payload) => {
+                statement.kind = if let Topic::Prepare(_) = msg.topic {
+                    StatementKind::Prepare
+                } else {
+                    StatementKind::NominatePrepare
+                };
+                statement.ballot = Some((&payload.B).into());
+                statement.prepared = payload.P.as_ref().map(Into::into);
+                statement.prepared_prime = payload.PP.as_ref().map(Into::into);
+                statement.commit_counter = Some(payload.CN);
+                statement.high_counter = Some(payload.HN);
+            }
+            Topic::Commit(payload) => {
+                statement.kind = StatementKind::Commit;
+                statement.ballot = Some((&payload.B).into());
+                statement.prepared_counter = Some(payload.PN);
+                statement.commit_counter = Some(payload.CN);
+                statement.high_counter = Some(payload.HN);
+            }
+            Topic::Externalize(payload) => {
+                statement.kind = StatementKind::Externalize;
+                statement.ballot = Some((&payload.C).into());
+                statement.high_counter = Some(payload.HN);
+            }
+        }
+
+        statement
+    }
+}
+
+/// A federated voting statement, from the logged node's point of view.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FederatedStatement {
+    /// Accept nominate(values).
+    AcceptNominate,
+
+    /// Confirm nominate(values).
+    ConfirmNominate,
+
+    /// Accept prepare(ballot).
+    AcceptPrepare,
+
+    /// Confirm prepare(ballot).
+    ConfirmPrepare,
+
+    /// Accept commit(ballot).
+    AcceptCommit,
+
+    /// Confirm commit(ballot).
+    ConfirmCommit,
+}
+
+/// The kind of threshold that was crossed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Threshold {
+    /// A quorum voted for, or accepted, the statement.
+    Quorum,
+
+    /// A blocking set accepted the statement.
+    BlockingSet,
+}
+
+/// A federated voting threshold was crossed for the first time.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ThresholdCrossing {
+    /// The statement that can now be accepted or confirmed.
+    pub statement: FederatedStatement,
+
+    /// The kind of threshold that was crossed.
+    pub threshold: Threshold,
+
+    /// Number of values the statement newly applies to, for nominate
+    /// statements.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_values: Option<usize>,
+
+    /// The ballot, for prepare and commit statements. For commit statements,
+    /// the counter is the lowest one of the range of ballots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ballot: Option<BallotSummary>,
+
+    /// The highest ballot counter of the range of ballots, for commit
+    /// statements.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_counter: Option<u32>,
+
+    /// The nodes forming the quorum or blocking set. The logged node is part
+    /// of every quorum it finds.
+    pub nodes: Vec<ResponderId>,
+}
+
+/// The kind of timer that expired.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutKind {
+    /// A nomination round ended without confirming any value nominated.
+    Nomination,
+
+    /// A ballot ended without being externalized.
+    Ballot,
+}
+
+/// A timer of the logged node expired.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Timeout {
+    /// The kind of timer.
+    pub kind: TimeoutKind,
+
+    /// The ballot that timed out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ballot: Option<BallotSummary>,
+
+    /// The ballot the node moved to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_ballot: Option<BallotSummary>,
+
+    /// The next federated voting step the node was waiting for on the ballot
+    /// that timed out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub waiting_for: Option<FederatedStatement>,
+
+    /// Quorum set members working on the same value as the ballot.
+    pub agreeing: Vec<ResponderId>,
+
+    /// Quorum set members working on a different value.
+    pub other_value: Vec<ResponderId>,
+
+    /// Quorum set members on a lower ballot counter.
+    pub behind: Vec<ResponderId>,
+
+    /// Quorum set members that had not issued any ballot statement.
+    pub silent: Vec<ResponderId>,
+}
+
+/// Something that happened during a slot.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    /// The logged node proposed values.
+    Proposed {
+        /// Number of values proposed.
+        num_values: usize,
+    },
+
+    /// The logged node received a statement.
+    Received(Statement),
+
+    /// The logged node issued a statement.
+    Sent(Statement),
+
+    /// A federated voting threshold was crossed.
+    Threshold(ThresholdCrossing),
+
+    /// A timer expired.
+    Timeout(Timeout),
+
+    /// A marker was logged.
+    Marker {
+        /// The marker's text.
+        text: String,
+    },
+}
+
+/// An event, and when it happened.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct TimelineEvent {
+    /// Milliseconds since the logged node started logging the slot.
+    pub msec_since_start: u64,
+
+    /// What happened.
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// The timeline of a slot, as seen by a logged node.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct SlotTimeline {
+    /// The logged node.
+    pub node: ResponderId,
+
+    /// The logged node's quorum set.
+    pub quorum_set: QuorumSet<ResponderId>,
+
+    /// The slot.
+    pub slot_index: SlotIndex,
+
+    /// Events, in the order they were logged.
+    pub events: Vec<TimelineEvent>,
+
+    /// The ballot the logged node externalized, if it did.
+    pub externalized: Option<BallotSummary>,
+
+    /// When the logged node externalized the slot, if it did.
+    pub externalized_at: Option<u64>,
+}
+
+impl SlotTimeline {
+    /// An empty timeline.
+    pub fn new(node_id: &NodeID, quorum_set: &QuorumSet, slot_index: SlotIndex) -> Self {
+        Self {
+            node: node_id.responder_id.clone(),
+            quorum_set: quorum_set.into(),
+            slot_index,
+            events: Vec::new(),
+            externalized: None,
+            externalized_at: None,
+        }
+    }
+
+    /// The threshold crossings, in order.
+    pub fn threshold_crossings(&self) -> impl Iterator<Item = &ThresholdCrossing> {
+        self.events.iter().filter_map(|event| match &event.kind {
+            EventKind::Threshold(crossing) => Some(crossing),
+            _ => None,
+        })
+    }
+
+    /// The timeouts, in order.
+    pub fn timeouts(&self) -> impl Iterator<Item = &Timeout> {
+        self.events.iter().filter_map(|event| match &event.kind {
+            EventKind::Timeout(timeout) => Some(timeout),
+            _ => None,
+        })
+    }
+}
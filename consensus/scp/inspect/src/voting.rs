@@ -0,0 +1,517 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Federated voting over the statements a logged node has seen.
+//!
+//! This mirrors the checks `Slot` performs, using the latest statement of
+//! each node (including the logged node's own) to find the quorums and
+//! blocking sets that let the logged node accept or confirm a statement.
+
+use crate::timeline::{
+    BallotSummary, FederatedStatement, Threshold, ThresholdCrossing, Timeout, TimeoutKind,
+};
+use mc_common::{NodeID, ResponderId};
+use mc_consensus_scp::{
+    core_types::Ballot,
+    msg::{Topic, INFINITY},
+    predicates::{BallotRangePredicate, BallotSetPredicate, Predicate, ValueSetPredicate},
+    Msg, QuorumSet, Value,
+};
+use std::{
+    cmp,
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Arc,
+};
+
+/// A function returning the ballots a message votes for, or accepts.
+type BallotsFn<V> = fn(&Msg<V>) -> HashSet<Ballot<V>>;
+
+/// A function returning the range of ballot counters of `values` a message
+/// votes to commit, or accepts committed.
+type CommitsFn<V> = fn(&Msg<V>, &[V], u32, u32) -> Option<(u32, u32)>;
+
+/// A function returning the values a message votes for, or accepts.
+type ValuesFn<V> = fn(&Msg<V>) -> Option<BTreeSet<V>>;
+
+/// Ranges of ballots a quorum or blocking set allows accepting or confirming.
+type FoundBallots<V> = Vec<(Threshold, HashSet<NodeID>, Vec<V>, (u32, u32))>;
+
+/// The statements seen during a slot, and the thresholds already crossed.
+pub struct SlotVotes<V: Value> {
+    /// The logged node.
+    node_id: NodeID,
+
+    /// The logged node's quorum set.
+    quorum_set: QuorumSet,
+
+    /// The latest statement issued by each node, including the logged node.
+    latest: HashMap<NodeID, Msg<V>>,
+
+    /// Values each nominate statement was already reported for.
+    reported_values: HashMap<FederatedStatement, BTreeSet<V>>,
+
+    /// Ballot statements already reported, with their range of counters.
+    reported_ballots: HashSet<(FederatedStatement, Vec<V>, u32, u32)>,
+}
+
+impl<V: Value> SlotVotes<V> {
+    /// Votes of a node with the given quorum set.
+    pub fn new(node_id: NodeID, quorum_set: QuorumSet) -> Self {
+        Self {
+            node_id,
+            quorum_set,
+            latest: HashMap::default(),
+            reported_values: HashMap::default(),
+            reported_ballots: HashSet::default(),
+        }
+    }
+
+    /// Record a statement, replacing the sender's previous one.
+    pub fn insert(&mut self, msg: &Msg<V>) {
+        self.latest.insert(msg.sender_id.clone(), msg.clone());
+    }
+
+    /// Thresholds that were crossed since the last call, in protocol order.
+    pub fn new_crossings(&mut self) -> Vec<ThresholdCrossing> {
+        let mut crossings = Vec::new();
+
+        for (statement, found) in [
+            (FederatedStatement::AcceptNominate, self.accept_nominate()),
+            (FederatedStatement::ConfirmNominate, self.confirm_nominate()),
+        ] {
+            if let Some((threshold, nodes, values)) = found {
+                let reported = self.reported_values.entry(statement).or_default();
+                let new_values: BTreeSet<V> = values.difference(reported).cloned().collect();
+                if new_values.is_empty() {
+                    continue;
+                }
+                reported.extend(new_values.iter().cloned());
+                crossings.push(ThresholdCrossing {
+                    statement,
+                    threshold,
+                    num_values: Some(new_values.len()),
+                    ballot: None,
+                    max_counter: None,
+                    nodes: responder_ids(nodes),
+                });
+            }
+        }
+
+        let ballot_crossings = self
+            .accept_prepare()
+            .into_iter()
+            .map(|found| (FederatedStatement::AcceptPrepare, found))
+            .chain(
+                self.confirm_prepare()
+                    .into_iter()
+                    .map(|found| (FederatedStatement::ConfirmPrepare, found)),
+            )
+            .chain(
+                self.accept_commit()
+                    .into_iter()
+                    .map(|found| (FederatedStatement::AcceptCommit, found)),
+            )
+            .chain(
+                self.confirm_commit()
+                    .into_iter()
+                    .map(|found| (FederatedStatement::ConfirmCommit, found)),
+            )
+            .collect::<Vec<_>>();
+        for (statement, (threshold, nodes, values, (min, max))) in ballot_crossings {
+            if !self
+                .reported_ballots
+                .insert((statement, values.clone(), min, max))
+            {
+                continue;
+            }
+            let is_commit = matches!(
+                statement,
+                FederatedStatement::AcceptCommit | FederatedStatement::ConfirmCommit
+            );
+            crossings.push(ThresholdCrossing {
+                statement,
+                threshold,
+                num_values: None,
+                ballot: Some(BallotSummary::new(min, &values)),
+                max_counter: if is_commit { Some(max) } else { None },
+                nodes: responder_ids(nodes),
+            });
+        }
+
+        crossings
+    }
+
+    /// Explain why the logged node issued `next_msg` when its timers expired.
+    /// Must be called before `next_msg` is inserted.
+    pub fn timeout(&self, next_msg: &Msg<V>) -> Timeout {
+        let ballot = self.local_msg().and_then(ballot);
+        match ballot {
+            Some(ballot) if next_msg.bN() > ballot.N => {
+                let mut timeout = self.classify_members(|msg| match self::ballot(msg) {
+                    None => Member::Silent,
+                    Some(other) if other.X != ballot.X => Member::OtherValue,
+                    Some(_) if msg.bN() < ballot.N => Member::Behind,
+                    Some(_) => Member::Agreeing,
+                });
+                timeout.kind = TimeoutKind::Ballot;
+                timeout.waiting_for = self.local_msg().and_then(waiting_for);
+                timeout.ballot = Some((&ballot).into());
+                timeout.next_ballot = self::ballot(next_msg).as_ref().map(Into::into);
+                timeout
+            }
+            _ => {
+                let voted = self
+                    .local_msg()
+                    .and_then(|msg| msg.votes_or_accepts_nominated())
+                    .unwrap_or_default();
+                let mut timeout =
+                    self.classify_members(|msg| match msg.votes_or_accepts_nominated() {
+                        None => Member::Silent,
+                        Some(values) if values.is_disjoint(&voted) => Member::OtherValue,
+                        Some(_) => Member::Agreeing,
+                    });
+                timeout.waiting_for = Some(FederatedStatement::ConfirmNominate);
+                timeout
+            }
+        }
+    }
+
+    fn local_msg(&self) -> Option<&Msg<V>> {
+        self.latest.get(&self.node_id)
+    }
+
+    /// Sort the members of the quorum set by their latest statement.
+    fn classify_members(&self, classify: impl Fn(&Msg<V>) -> Member) -> Timeout {
+        let mut timeout = Timeout {
+            kind: TimeoutKind::Nomination,
+            ballot: None,
+            next_ballot: None,
+            waiting_for: None,
+            agreeing: Vec::new(),
+            other_value: Vec::new(),
+            behind: Vec::new(),
+            silent: Vec::new(),
+        };
+        let mut members: Vec<NodeID> = self.quorum_set.nodes().into_iter().collect();
+        members.sort();
+        for node_id in members {
+            let member = self.latest.get(&node_id).map_or(Member::Silent, &classify);
+            let responder_id = node_id.responder_id;
+            match member {
+                Member::Agreeing => timeout.agreeing.push(responder_id),
+                Member::OtherValue => timeout.other_value.push(responder_id),
+                Member::Behind => timeout.behind.push(responder_id),
+                Member::Silent => timeout.silent.push(responder_id),
+            }
+        }
+        timeout
+    }
+
+    fn accept_nominate(&self) -> Option<(Threshold, HashSet<NodeID>, BTreeSet<V>)> {
+        // A quorum votes or accepts values the logged node votes for.
+        let voted = self
+            .local_msg()
+            .and_then(|msg| msg.votes_or_accepts_nominated())
+            .unwrap_or_default();
+        let (nodes, pred) = self.quorum_set.findQuorum(
+            &self.node_id,
+            &self.latest,
+            values_predicate(voted, |msg| msg.votes_or_accepts_nominated()),
+        );
+        if !nodes.is_empty() {
+            return Some((Threshold::Quorum, nodes, pred.result()));
+        }
+
+        // A blocking set accepts values.
+        let accepted_by_others: BTreeSet<V> = self
+            .latest
+            .iter()
+            .filter(|(node_id, _)| **node_id != self.node_id)
+            .filter_map(|(_, msg)| msg.accepts_nominated())
+            .flatten()
+            .cloned()
+            .collect();
+        let (nodes, pred) = self.quorum_set.findBlockingSet(
+            &self.latest,
+            values_predicate(accepted_by_others, |msg| msg.accepts_nominated().cloned()),
+        );
+        if !nodes.is_empty() {
+            return Some((Threshold::BlockingSet, nodes, pred.result()));
+        }
+
+        None
+    }
+
+    fn confirm_nominate(&self) -> Option<(Threshold, HashSet<NodeID>, BTreeSet<V>)> {
+        let accepted = self
+            .local_msg()
+            .and_then(|msg| msg.accepts_nominated().cloned())
+            .unwrap_or_default();
+        let (nodes, pred) = self.quorum_set.findQuorum(
+            &self.node_id,
+            &self.latest,
+            values_predicate(accepted, |msg| msg.accepts_nominated().cloned()),
+        );
+        (!nodes.is_empty()).then(|| (Threshold::Quorum, nodes, pred.result()))
+    }
+
+    fn accept_prepare(&self) -> FoundBallots<V> {
+        let mut found = Vec::new();
+
+        // A quorum votes or accepts a ballot the logged node votes for.
+        let voted = self
+            .local_msg()
+            .map(|msg| msg.votes_or_accepts_prepared())
+            .unwrap_or_default();
+        for ballot in voted {
+            let (nodes, pred) = self.quorum_set.findQuorum(
+                &self.node_id,
+                &self.latest,
+                ballots_predicate(ballot, |msg| msg.votes_or_accepts_prepared()),
+            );
+            if !nodes.is_empty() {
+                for ballot in pred.result() {
+                    found.push((
+                        Threshold::Quorum,
+                        nodes.clone(),
+                        ballot.X,
+                        (ballot.N, ballot.N),
+                    ));
+                }
+            }
+        }
+
+        // A blocking set accepts a ballot.
+        for ballot in self.accepted_by_members(|msg| msg.accepts_prepared()) {
+            let (nodes, pred) = self.quorum_set.findBlockingSet(
+                &self.latest,
+                ballots_predicate(ballot, |msg| msg.accepts_prepared()),
+            );
+            if !nodes.is_empty() {
+                for ballot in pred.result() {
+                    found.push((
+                        Threshold::BlockingSet,
+                        nodes.clone(),
+                        ballot.X,
+                        (ballot.N, ballot.N),
+                    ));
+                }
+            }
+        }
+
+        found
+    }
+
+    fn confirm_prepare(&self) -> FoundBallots<V> {
+        let accepted = self
+            .local_msg()
+            .map(|msg| msg.accepts_prepared())
+            .unwrap_or_default();
+        let mut found = Vec::new();
+        for ballot in accepted {
+            let (nodes, pred) = self.quorum_set.findQuorum(
+                &self.node_id,
+                &self.latest,
+                ballots_predicate(ballot, |msg| msg.accepts_prepared()),
+            );
+            if !nodes.is_empty() {
+                for ballot in pred.result() {
+                    found.push((
+                        Threshold::Quorum,
+                        nodes.clone(),
+                        ballot.X,
+                        (ballot.N, ballot.N),
+                    ));
+                }
+            }
+        }
+        found
+    }
+
+    fn accept_commit(&self) -> FoundBallots<V> {
+        let mut found = Vec::new();
+
+        // A quorum votes or accepts committing ballots the logged node votes to
+        // commit.
+        if let Some((values, range)) = self
+            .local_msg()
+            .and_then(|msg| commit_range(msg, Msg::votes_or_accepts_commits))
+        {
+            let (nodes, pred) = self.quorum_set.findQuorum(
+                &self.node_id,
+                &self.latest,
+                range_predicate(values, range, Msg::votes_or_accepts_commits),
+            );
+            if !nodes.is_empty() {
+                for (values, range) in pred.result() {
+                    found.push((Threshold::Quorum, nodes.clone(), values, range));
+                }
+            }
+        }
+
+        // A blocking set accepts committing ballots.
+        let mut candidates: Vec<(Vec<V>, (u32, u32))> = self
+            .quorum_set
+            .nodes()
+            .iter()
+            .filter_map(|node_id| self.latest.get(node_id))
+            .filter_map(|msg| commit_range(msg, Msg::accepts_commits))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        for (values, range) in candidates {
+            let (nodes, pred) = self.quorum_set.findBlockingSet(
+                &self.latest,
+                range_predicate(values, range, Msg::accepts_commits),
+            );
+            if !nodes.is_empty() {
+                for (values, range) in pred.result() {
+                    found.push((Threshold::BlockingSet, nodes.clone(), values, range));
+                }
+            }
+        }
+
+        found
+    }
+
+    fn confirm_commit(&self) -> FoundBallots<V> {
+        let mut found = Vec::new();
+        if let Some((values, range)) = self
+            .local_msg()
+            .and_then(|msg| commit_range(msg, Msg::accepts_commits))
+        {
+            let (nodes, pred) = self.quorum_set.findQuorum(
+                &self.node_id,
+                &self.latest,
+                range_predicate(values, range, Msg::accepts_commits),
+            );
+            if !nodes.is_empty() {
+                for (values, range) in pred.result() {
+                    found.push((Threshold::Quorum, nodes.clone(), values, range));
+                }
+            }
+        }
+        found
+    }
+
+    /// Ballots that members of the quorum set accept.
+    fn accepted_by_members(&self, ballots_fn: BallotsFn<V>) -> HashSet<Ballot<V>> {
+        self.quorum_set
+            .nodes()
+            .iter()
+            .filter_map(|node_id| self.latest.get(node_id))
+            .flat_map(ballots_fn)
+            .collect()
+    }
+}
+
+/// How a member of the quorum set relates to the logged node when a timer
+/// expired.
+enum Member {
+    Agreeing,
+    OtherValue,
+    Behind,
+    Silent,
+}
+
+/// The ballot a message is about: B, or C for Externalize.
+fn ballot<V: Value>(msg: &Msg<V>) -> Option<Ballot<V>> {
+    match &msg.topic {
+        Topic::Nominate(_) => None,
+        Topic::NominatePrepare(_, payload) | Topic::Prepare(payload) => Some(payload.B.clone()),
+        Topic::Commit(payload) => Some(payload.B.clone()),
+        Topic::Externalize(payload) => Some(payload.C.clone()),
+    }
+}
+
+/// The next step of the ballot protocol the logged node needs, given its
+/// latest statement.
+fn waiting_for<V: Value>(msg: &Msg<V>) -> Option<FederatedStatement> {
+    match &msg.topic {
+        Topic::Nominate(_) => Some(FederatedStatement::ConfirmNominate),
+        Topic::NominatePrepare(_, payload) | Topic::Prepare(payload) => {
+            let prepared = payload
+                .P
+                .as_ref()
+                .map_or(false, |P| P.X == payload.B.X && P.N >= payload.B.N);
+            if !prepared {
+                Some(FederatedStatement::AcceptPrepare)
+            } else if payload.HN < payload.B.N {
+                Some(FederatedStatement::ConfirmPrepare)
+            } else {
+                Some(FederatedStatement::AcceptCommit)
+            }
+        }
+        Topic::Commit(_) => Some(FederatedStatement::ConfirmCommit),
+        Topic::Externalize(_) => None,
+    }
+}
+
+/// The range of ballots of `msg`'s value that `msg` votes to commit, or
+/// accepts committed, according to `commits_fn`.
+fn commit_range<V: Value>(msg: &Msg<V>, commits_fn: CommitsFn<V>) -> Option<(Vec<V>, (u32, u32))> {
+    let values = ballot(msg)?.X;
+    let range = commits_fn(msg, &values, 0, INFINITY)?;
+    Some((values, range))
+}
+
+fn values_predicate<V: Value>(values: BTreeSet<V>, values_fn: ValuesFn<V>) -> ValueSetPredicate<V> {
+    ValueSetPredicate {
+        values,
+        test_fn: Arc::new(move |msg, values| match values_fn(msg) {
+            None => BTreeSet::default(),
+            Some(msg_values) => values.intersection(&msg_values).cloned().collect(),
+        }),
+    }
+}
+
+fn ballots_predicate<V: Value>(
+    ballot: Ballot<V>,
+    ballots_fn: BallotsFn<V>,
+) -> BallotSetPredicate<V> {
+    let mut ballots = HashSet::default();
+    ballots.insert(ballot);
+    BallotSetPredicate {
+        ballots,
+        test_fn: Arc::new(move |msg, candidates| {
+            let mut intersections = HashSet::default();
+            for ballot_a in &ballots_fn(msg) {
+                for ballot_b in candidates {
+                    if ballot_a.X == ballot_b.X {
+                        let min_counter = cmp::min(ballot_a.N, ballot_b.N);
+                        intersections.insert(Ballot::new(min_counter, &ballot_a.X));
+                    }
+                }
+            }
+            intersections
+        }),
+    }
+}
+
+fn range_predicate<V: Value>(
+    values: Vec<V>,
+    range: (u32, u32),
+    commits_fn: CommitsFn<V>,
+) -> BallotRangePredicate<V> {
+    let mut ballot_ranges = HashMap::default();
+    ballot_ranges.insert(values, range);
+    BallotRangePredicate {
+        ballot_ranges,
+        test_fn: Arc::new(move |msg, ballot_ranges| {
+            ballot_ranges
+                .iter()
+                .filter_map(|(values, &(min, max))| {
+                    commits_fn(msg, values, min, max).map(|range| (values.clone(), range))
+                })
+                .collect()
+        }),
+    }
+}
+
+fn responder_ids(nodes: HashSet<NodeID>) -> Vec<ResponderId> {
+    let mut responder_ids: Vec<ResponderId> = nodes
+        .into_iter()
+        .map(|node_id| node_id.responder_id)
+        .collect();
+    responder_ids.sort();
+    responder_ids
+}
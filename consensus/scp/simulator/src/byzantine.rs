@@ -0,0 +1,183 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Scripted Byzantine behaviors.
+//!
+//! A Byzantine node runs an honest SCP node internally, and tampers with the
+//! messages it produces before they are sent to its peers.
+
+use mc_common::NodeID;
+use mc_consensus_scp::{
+    core_types::Ballot,
+    msg::{CommitPayload, ExternalizePayload, NominatePayload, PreparePayload},
+    Msg, Topic, Value,
+};
+use std::collections::BTreeSet;
+
+/// How a Byzantine node misbehaves.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ByzantineBehavior<V: Value> {
+    /// Never sends any messages.
+    Silent,
+
+    /// Sends honest statements to half of its peers, and the same statements
+    /// about `values` to the other half.
+    Equivocate {
+        /// Values to make statements about.
+        values: Vec<V>,
+    },
+
+    /// Makes all of its nominate and ballot statements about `values`, e.g.
+    /// values that fail validation.
+    VoteFor {
+        /// Values to make statements about.
+        values: Vec<V>,
+    },
+}
+
+impl<V: Value> ByzantineBehavior<V> {
+    /// The message a Byzantine node sends to a given peer, if any.
+    ///
+    /// # Arguments
+    /// * `msg` - The message its honest node produced.
+    /// * `peer_index` - The peer's position among the node's peers.
+    pub fn tamper(&self, msg: &Msg<V>, peer_index: usize) -> Option<Msg<V>> {
+        match self {
+            Self::Silent => None,
+            Self::Equivocate { values } => {
+                if peer_index % 2 == 0 {
+                    Some(msg.clone())
+                } else {
+                    Some(substitute_values(msg, values))
+                }
+            }
+            Self::VoteFor { values } => Some(substitute_values(msg, values)),
+        }
+    }
+}
+
+/// Replace every value in a message's statements with `values`, keeping the
+/// statements otherwise intact.
+fn substitute_values<V: Value>(msg: &Msg<V>, values: &[V]) -> Msg<V> {
+    let ballot = |ballot: &Ballot<V>| Ballot::new(ballot.N, values);
+
+    let nominate = |payload: &NominatePayload<V>| {
+        // X and Y must stay disjoint, so the values are either voted or accepted.
+        let values: BTreeSet<V> = values.iter().cloned().collect();
+        if payload.Y.is_empty() {
+            NominatePayload {
+                X: values,
+                Y: BTreeSet::new(),
+            }
+        } else {
+            NominatePayload {
+                X: BTreeSet::new(),
+                Y: values,
+            }
+        }
+    };
+
+    let prepare = |payload: &PreparePayload<V>| PreparePayload {
+        B: ballot(&payload.B),
+        P: payload.P.as_ref().map(ballot),
+        // Prepared prime must have a different value than prepared.
+        PP: None,
+        CN: payload.CN,
+        HN: payload.HN,
+    };
+
+    let topic = match &msg.topic {
+        Topic::Nominate(payload) => Topic::Nominate(nominate(payload)),
+        Topic::NominatePrepare(nominate_payload, prepare_payload) => {
+            Topic::NominatePrepare(nominate(nominate_payload), prepare(prepare_payload))
+        }
+        Topic::Prepare(payload) => Topic::Prepare(prepare(payload)),
+        Topic::Commit(payload) => Topic::Commit(CommitPayload {
+            B: ballot(&payload.B),
+            ..payload.clone()
+        }),
+        Topic::Externalize(payload) => Topic::Externalize(ExternalizePayload {
+            C: ballot(&payload.C),
+            HN: payload.HN,
+        }),
+    };
+
+    Msg::<V, NodeID>::new(
+        msg.sender_id.clone(),
+        msg.quorum_set.clone(),
+        msg.slot_index,
+        topic,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_consensus_scp::{test_utils::test_node_id, QuorumSet};
+    use std::iter::FromIterator;
+
+    #[test]
+    // Equivocation only changes the statements sent to every other peer.
+    fn test_equivocate() {
+        let msg = Msg::new(
+            test_node_id(1),
+            QuorumSet::new_with_node_ids(1, vec![test_node_id(2)]),
+            3,
+            Topic::Prepare(PreparePayload {
+                B: Ballot::new(2, &["a".to_string()]),
+                P: Some(Ballot::new(1, &["a".to_string()])),
+                PP: None,
+                CN: 0,
+                HN: 0,
+            }),
+        );
+
+        let behavior = ByzantineBehavior::Equivocate {
+            values: vec!["b".to_string()],
+        };
+        assert_eq!(behavior.tamper(&msg, 0), Some(msg.clone()));
+
+        let tampered = behavior.tamper(&msg, 1).unwrap();
+        assert_eq!(tampered.sender_id, msg.sender_id);
+        assert_eq!(tampered.slot_index, msg.slot_index);
+        assert_eq!(
+            tampered.topic,
+            Topic::Prepare(PreparePayload {
+                B: Ballot::new(2, &["b".to_string()]),
+                P: Some(Ballot::new(1, &["b".to_string()])),
+                PP: None,
+                CN: 0,
+                HN: 0,
+            })
+        );
+        assert!(tampered.validate().is_ok());
+    }
+
+    #[test]
+    // Tampered nominate statements keep X and Y disjoint.
+    fn test_vote_for_nominate() {
+        let msg = Msg::new(
+            test_node_id(1),
+            QuorumSet::new_with_node_ids(1, vec![test_node_id(2)]),
+            3,
+            Topic::Nominate(NominatePayload {
+                X: BTreeSet::from_iter(vec!["a".to_string()]),
+                Y: BTreeSet::from_iter(vec!["c".to_string()]),
+            }),
+        );
+
+        let behavior = ByzantineBehavior::VoteFor {
+            values: vec!["b".to_string()],
+        };
+        let tampered = behavior.tamper(&msg, 0).unwrap();
+        assert_eq!(
+            tampered.topic,
+            Topic::Nominate(NominatePayload {
+                X: BTreeSet::new(),
+                Y: BTreeSet::from_iter(vec!["b".to_string()]),
+            })
+        );
+        assert!(tampered.validate().is_ok());
+
+        assert_eq!(ByzantineBehavior::Silent.tamper(&msg, 0), None);
+    }
+}
@@ -0,0 +1,957 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! A deterministic simulator for SCP networks, with fault injection.
+//!
+//! Unlike the thread-based mock network used by the SCP integration tests,
+//! the simulator runs every node on a single thread, and processes events in
+//! the order of a virtual clock. Message loss and delays are drawn from a
+//! seeded random number generator, so a run is fully reproducible from its
+//! seed, and can simulate minutes of consensus in a fraction of that time.
+//!
+//! On top of an unreliable network, scenarios can partition the network, crash
+//! and restart nodes, and make nodes Byzantine. The simulator checks that no
+//! two honest nodes externalize different values for a slot, and reports
+//! liveness metrics such as how fast slots were externalized.
+
+#![deny(missing_docs)]
+
+mod byzantine;
+mod report;
+mod scenario;
+
+pub use crate::{
+    byzantine::ByzantineBehavior,
+    report::{SafetyViolation, SimulationReport, SlotReport},
+    scenario::{Scenario, ScenarioEvent},
+};
+
+use mc_common::{
+    logger::{log, o, Logger},
+    NodeID,
+};
+use mc_consensus_scp::{
+    test_utils::test_node_id, ClockFn, CombineFn, Msg, Node, QuorumSet, ScpNode, SlotIndex,
+    ValidityFn, Value,
+};
+use rand::{Rng, SeedableRng};
+use rand_hc::Hc128Rng;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Describes one simulated node.
+#[derive(Clone, Debug)]
+pub struct NodeConfig<V: Value> {
+    /// The node's ID.
+    pub id: NodeID,
+
+    /// The node's quorum set.
+    pub quorum_set: QuorumSet,
+
+    /// The nodes this node sends its messages to.
+    pub peers: BTreeSet<NodeID>,
+
+    /// The slot the node starts performing consensus on.
+    pub first_slot_index: SlotIndex,
+
+    /// How the node misbehaves, if it is Byzantine.
+    pub byzantine: Option<ByzantineBehavior<V>>,
+}
+
+impl<V: Value> NodeConfig<V> {
+    /// Create an honest node that starts at slot 0.
+    pub fn new(id: NodeID, quorum_set: QuorumSet, peers: BTreeSet<NodeID>) -> Self {
+        Self {
+            id,
+            quorum_set,
+            peers,
+            first_slot_index: 0,
+            byzantine: None,
+        }
+    }
+}
+
+/// Create a mesh network of `num_nodes` nodes, where each node trusts
+/// `threshold` of its peers.
+pub fn mesh_network<V: Value>(num_nodes: u32, threshold: u32) -> Vec<NodeConfig<V>> {
+    (0..num_nodes)
+        .map(|node_index| {
+            let peers: Vec<NodeID> = (0..num_nodes)
+                .filter(|other_node_index| *other_node_index != node_index)
+                .map(test_node_id)
+                .collect();
+            NodeConfig::new(
+                test_node_id(node_index),
+                QuorumSet::new_with_node_ids(threshold, peers.clone()),
+                peers.into_iter().collect(),
+            )
+        })
+        .collect()
+}
+
+/// How messages between nodes are lost and delayed.
+#[derive(Clone, Debug)]
+pub struct FaultConfig {
+    /// Probability that a message is lost.
+    pub drop_probability: f64,
+
+    /// Shortest time it takes for a message to be delivered.
+    pub min_delay: Duration,
+
+    /// Longest time it takes for a message to be delivered. When this is
+    /// larger than `min_delay`, messages may arrive out of order.
+    pub max_delay: Duration,
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            drop_probability: 0.0,
+            min_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+        }
+    }
+}
+
+/// Simulation parameters.
+#[derive(Clone)]
+pub struct SimulationConfig<V: Value> {
+    /// Seed for the random number generator all faults are drawn from.
+    pub seed: u64,
+
+    /// Network faults.
+    pub faults: FaultConfig,
+
+    /// Nodes propose pending values and process timeouts at this interval.
+    pub tick_interval: Duration,
+
+    /// The simulation stops once this much virtual time has passed.
+    pub max_time: Duration,
+
+    /// If set, the simulation stops once every honest node has externalized
+    /// the slots before this one. Nodes that are crashed at that point keep
+    /// the simulation running until they are restarted and catch up.
+    pub target_slot_index: Option<SlotIndex>,
+
+    /// Nodes propose up to this many of their pending values per slot.
+    pub max_slot_proposed_values: usize,
+
+    /// Validates values.
+    pub validity_fn: ValidityFn<V, String>,
+
+    /// Combines values.
+    pub combine_fn: CombineFn<V, String>,
+}
+
+impl<V: Value> SimulationConfig<V> {
+    /// Create a configuration with a reliable network, where every value is
+    /// valid.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            faults: FaultConfig::default(),
+            tick_interval: Duration::from_millis(100),
+            max_time: Duration::from_secs(600),
+            target_slot_index: None,
+            max_slot_proposed_values: 100,
+            validity_fn: Arc::new(|_value: &V| Ok(())),
+            combine_fn: Arc::new(|values: &[V]| {
+                let mut values = values.to_vec();
+                values.sort();
+                values.dedup();
+                Ok(values)
+            }),
+        }
+    }
+}
+
+/// A simulated node.
+struct SimNode<V: Value> {
+    /// The node's configuration.
+    config: NodeConfig<V>,
+
+    /// The SCP node, or None while the node is crashed.
+    node: Option<Node<V, String>>,
+
+    /// Values submitted to the node that were not yet externalized.
+    pending_values: BTreeSet<V>,
+
+    /// Index of the next slot the node will externalize.
+    next_slot_index: SlotIndex,
+
+    /// Externalized values, by slot. Survives crashes.
+    ledger: BTreeMap<SlotIndex, Vec<V>>,
+
+    /// Whether the node received a message for a slot it has not reached,
+    /// meaning it might have fallen behind.
+    saw_future_msg: bool,
+}
+
+/// Events processed by the simulation.
+enum Event<V: Value> {
+    /// A message arrives at a node.
+    Deliver { to: NodeID, msg: Msg<V> },
+
+    /// All nodes propose values and process timeouts.
+    Tick,
+
+    /// A scripted event.
+    Scenario(ScenarioEvent<V>),
+}
+
+/// A simulated network.
+pub struct Simulation<V: Value> {
+    /// Parameters.
+    config: SimulationConfig<V>,
+
+    /// Simulated nodes.
+    nodes: BTreeMap<NodeID, SimNode<V>>,
+
+    /// Pending events, ordered by time and then by the order they were
+    /// scheduled in.
+    queue: BTreeMap<(Duration, u64), Event<V>>,
+
+    /// Sequence number of the next scheduled event.
+    next_seq: u64,
+
+    /// Virtual time since the start of the simulation.
+    now: Duration,
+
+    /// Virtual time in nanoseconds, shared with the nodes' clock.
+    clock_nanos: Arc<AtomicU64>,
+
+    /// The clock nodes read the virtual time from.
+    clock: ClockFn,
+
+    /// Source of all randomness.
+    rng: Hc128Rng,
+
+    /// The current partition, if any.
+    partition: Option<Vec<BTreeSet<NodeID>>>,
+
+    /// The first honest node to externalize each slot, and what it
+    /// externalized.
+    first_externalized: BTreeMap<SlotIndex, (NodeID, Vec<V>)>,
+
+    /// Externalization times of each slot.
+    slots: BTreeMap<SlotIndex, SlotReport>,
+
+    /// The report, without the parts that are only known at the end.
+    report: SimulationReport<V>,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl<V: Value> Simulation<V> {
+    /// Create a simulation.
+    ///
+    /// # Arguments
+    /// * `nodes` - The simulated nodes.
+    /// * `scenario` - Scripted events.
+    /// * `config` - Simulation parameters.
+    /// * `logger`
+    pub fn new(
+        nodes: Vec<NodeConfig<V>>,
+        scenario: &Scenario<V>,
+        config: SimulationConfig<V>,
+        logger: Logger,
+    ) -> Self {
+        // Virtual time is an offset from a fixed instant, so that only the
+        // simulation decides when timers expire.
+        let start = Instant::now();
+        let clock_nanos = Arc::new(AtomicU64::new(0));
+        let clock: ClockFn = {
+            let clock_nanos = clock_nanos.clone();
+            Arc::new(move || start + Duration::from_nanos(clock_nanos.load(Ordering::SeqCst)))
+        };
+
+        let mut simulation = Self {
+            rng: Hc128Rng::seed_from_u64(config.seed),
+            report: SimulationReport {
+                seed: config.seed,
+                elapsed: Duration::default(),
+                safety_violations: Vec::new(),
+                slots: Vec::new(),
+                next_slot_indices: BTreeMap::new(),
+                msgs_sent: 0,
+                msgs_dropped: 0,
+                msgs_delivered: 0,
+                ledger_syncs: 0,
+                max_ballot_counter: 0,
+                max_nomination_round: 0,
+            },
+            config,
+            nodes: BTreeMap::new(),
+            queue: BTreeMap::new(),
+            next_seq: 0,
+            now: Duration::default(),
+            clock_nanos,
+            clock,
+            partition: None,
+            first_externalized: BTreeMap::new(),
+            slots: BTreeMap::new(),
+            logger,
+        };
+
+        for node_config in nodes {
+            assert!(!node_config.peers.contains(&node_config.id));
+            let node = simulation.start_node(&node_config, node_config.first_slot_index);
+            simulation.nodes.insert(
+                node_config.id.clone(),
+                SimNode {
+                    next_slot_index: node_config.first_slot_index,
+                    config: node_config,
+                    node: Some(node),
+                    pending_values: BTreeSet::new(),
+                    ledger: BTreeMap::new(),
+                    saw_future_msg: false,
+                },
+            );
+        }
+
+        for (time, event) in scenario.events() {
+            simulation.schedule(time, Event::Scenario(event));
+        }
+        let tick_interval = simulation.config.tick_interval;
+        simulation.schedule(tick_interval, Event::Tick);
+
+        simulation
+    }
+
+    /// Run until the target slot or the time limit is reached.
+    pub fn run(&mut self) -> SimulationReport<V> {
+        while let Some((time, event)) = self.pop_event() {
+            if time > self.config.max_time {
+                break;
+            }
+            self.now = time;
+            self.clock_nanos
+                .store(time.as_nanos() as u64, Ordering::SeqCst);
+
+            match event {
+                Event::Deliver { to, msg } => self.deliver(&to, msg),
+                Event::Tick => {
+                    self.tick();
+                    let next_tick = self.now + self.config.tick_interval;
+                    self.schedule(next_tick, Event::Tick);
+                }
+                Event::Scenario(event) => self.handle_scenario_event(event),
+            }
+
+            if self.reached_target() {
+                break;
+            }
+        }
+
+        let mut report = self.report.clone();
+        report.elapsed = self.now;
+        report.slots = self.slots.values().cloned().collect();
+        report.next_slot_indices = self
+            .nodes
+            .values()
+            .filter(|sim_node| sim_node.config.byzantine.is_none())
+            .map(|sim_node| (sim_node.config.id.clone(), sim_node.next_slot_index))
+            .collect();
+        report
+    }
+
+    /// Values externalized by a node, by slot.
+    pub fn ledger(&self, node_id: &NodeID) -> Option<&BTreeMap<SlotIndex, Vec<V>>> {
+        self.nodes.get(node_id).map(|sim_node| &sim_node.ledger)
+    }
+
+    /// Virtual time since the start of the simulation.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    fn start_node(&self, node_config: &NodeConfig<V>, slot_index: SlotIndex) -> Node<V, String> {
+        Node::new_with_clock(
+            node_config.id.clone(),
+            node_config.quorum_set.clone(),
+            self.config.validity_fn.clone(),
+            self.config.combine_fn.clone(),
+            slot_index,
+            self.clock.clone(),
+            self.logger
+                .new(o!("mc.scp.simulator.node" => node_config.id.to_string())),
+        )
+    }
+
+    fn schedule(&mut self, time: Duration, event: Event<V>) {
+        self.queue.insert((time, self.next_seq), event);
+        self.next_seq += 1;
+    }
+
+    fn pop_event(&mut self) -> Option<(Duration, Event<V>)> {
+        let key = *self.queue.keys().next()?;
+        self.queue.remove(&key).map(|event| (key.0, event))
+    }
+
+    fn reached_target(&self) -> bool {
+        match self.config.target_slot_index {
+            None => false,
+            Some(target_slot_index) => self
+                .nodes
+                .values()
+                .filter(|sim_node| sim_node.config.byzantine.is_none())
+                .all(|sim_node| sim_node.next_slot_index >= target_slot_index),
+        }
+    }
+
+    /// True if messages between two nodes are not blocked by a partition.
+    /// Nodes outside of the simulation are never partitioned.
+    fn is_connected(&self, node_id_1: &NodeID, node_id_2: &NodeID) -> bool {
+        match &self.partition {
+            None => true,
+            Some(groups) => {
+                if !self.nodes.contains_key(node_id_1) || !self.nodes.contains_key(node_id_2) {
+                    return true;
+                }
+                let group = |node_id| groups.iter().position(|group| group.contains(node_id));
+                group(node_id_1) == group(node_id_2)
+            }
+        }
+    }
+
+    /// Send a message through the faulty network.
+    fn send(&mut self, to: NodeID, msg: Msg<V>) {
+        self.report.msgs_sent += 1;
+
+        if !self.is_connected(&msg.sender_id, &to)
+            || self.rng.gen_bool(self.config.faults.drop_probability)
+        {
+            self.report.msgs_dropped += 1;
+            return;
+        }
+
+        let faults = &self.config.faults;
+        let delay = if faults.min_delay < faults.max_delay {
+            self.rng.gen_range(faults.min_delay..=faults.max_delay)
+        } else {
+            faults.min_delay
+        };
+        self.schedule(self.now + delay, Event::Deliver { to, msg });
+    }
+
+    /// Send a node's message to its peers.
+    fn broadcast(&mut self, from: &NodeID, msg: &Msg<V>) {
+        let sim_node = &self.nodes[from];
+        let peers = sim_node.config.peers.clone();
+        let byzantine = sim_node.config.byzantine.clone();
+
+        for (peer_index, peer_id) in peers.into_iter().enumerate() {
+            let msg = match &byzantine {
+                None => Some(msg.clone()),
+                Some(behavior) => behavior.tamper(msg, peer_index),
+            };
+            if let Some(msg) = msg {
+                self.send(peer_id, msg);
+            }
+        }
+    }
+
+    fn deliver(&mut self, to: &NodeID, msg: Msg<V>) {
+        let is_connected = self.is_connected(&msg.sender_id, to);
+        let sim_node = self.nodes.get_mut(to).expect("unknown node");
+        let node = match (&mut sim_node.node, is_connected) {
+            (Some(node), true) => node,
+            _ => {
+                self.report.msgs_dropped += 1;
+                return;
+            }
+        };
+
+        self.report.msgs_delivered += 1;
+        if msg.slot_index > node.current_slot_index() {
+            sim_node.saw_future_msg = true;
+        }
+
+        let outgoing_msgs = match node.handle_message(&msg) {
+            Ok(outgoing_msg) => outgoing_msg.into_iter().collect(),
+            Err(err) => {
+                log::debug!(self.logger, "{} rejected {}: {}", to, msg, err);
+                Vec::new()
+            }
+        };
+        self.after_step(to, outgoing_msgs);
+    }
+
+    fn tick(&mut self) {
+        let node_ids: Vec<NodeID> = self.nodes.keys().cloned().collect();
+        for node_id in node_ids {
+            self.sync_ledger(&node_id);
+
+            let mut outgoing_msgs = self.propose(&node_id);
+
+            let sim_node = self.nodes.get_mut(&node_id).expect("unknown node");
+            if let Some(node) = &mut sim_node.node {
+                outgoing_msgs.extend(node.process_timeouts());
+
+                if sim_node.config.byzantine.is_none() {
+                    let metrics = node.get_current_slot_metrics();
+                    self.report.max_ballot_counter = self.report.max_ballot_counter.max(metrics.bN);
+                    self.report.max_nomination_round = self
+                        .report
+                        .max_nomination_round
+                        .max(metrics.cur_nomination_round);
+                }
+            }
+
+            self.after_step(&node_id, outgoing_msgs);
+        }
+    }
+
+    /// Have a node propose its pending values.
+    fn propose(&mut self, node_id: &NodeID) -> Vec<Msg<V>> {
+        let max_slot_proposed_values = self.config.max_slot_proposed_values;
+        let sim_node = self.nodes.get_mut(node_id).expect("unknown node");
+        let node = match &mut sim_node.node {
+            Some(node) if !sim_node.pending_values.is_empty() => node,
+            _ => return Vec::new(),
+        };
+
+        let values = sim_node
+            .pending_values
+            .iter()
+            .take(max_slot_proposed_values)
+            .cloned()
+            .collect();
+        match node.propose_values(values) {
+            Ok(outgoing_msg) => outgoing_msg.into_iter().collect(),
+            Err(err) => {
+                log::debug!(self.logger, "{} failed proposing values: {}", node_id, err);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Record newly externalized slots, and send a node's messages.
+    fn after_step(&mut self, node_id: &NodeID, outgoing_msgs: Vec<Msg<V>>) {
+        self.record_externalized(node_id);
+        for msg in outgoing_msgs {
+            self.broadcast(node_id, &msg);
+        }
+    }
+
+    fn record_externalized(&mut self, node_id: &NodeID) {
+        loop {
+            let sim_node = self.nodes.get_mut(node_id).expect("unknown node");
+            let slot_index = sim_node.next_slot_index;
+            let values = match sim_node
+                .node
+                .as_ref()
+                .and_then(|node| node.get_externalized_values(slot_index))
+            {
+                Some(values) => values,
+                None => return,
+            };
+
+            sim_node.next_slot_index += 1;
+            sim_node.ledger.insert(slot_index, values.clone());
+            sim_node
+                .pending_values
+                .retain(|value| !values.contains(value));
+
+            if sim_node.config.byzantine.is_some() {
+                continue;
+            }
+
+            log::trace!(
+                self.logger,
+                "{} externalized slot {} at {:?}",
+                node_id,
+                slot_index,
+                self.now
+            );
+
+            match self.first_externalized.get(&slot_index) {
+                Some((first_node_id, first_values)) => {
+                    if *first_values != values {
+                        log::error!(
+                            self.logger,
+                            "Safety violation in slot {}: {} externalized {:?}, {} externalized {:?}",
+                            slot_index,
+                            first_node_id,
+                            first_values,
+                            node_id,
+                            values,
+                        );
+                        self.report.safety_violations.push(SafetyViolation {
+                            slot_index,
+                            first_node_id: first_node_id.clone(),
+                            first_values: first_values.clone(),
+                            node_id: node_id.clone(),
+                            values,
+                        });
+                    }
+                }
+                None => {
+                    self.first_externalized
+                        .insert(slot_index, (node_id.clone(), values));
+                }
+            }
+
+            let now = self.now;
+            let slot = self.slots.entry(slot_index).or_insert(SlotReport {
+                slot_index,
+                first_externalized_at: now,
+                last_externalized_at: now,
+                num_nodes: 0,
+            });
+            slot.last_externalized_at = now;
+            slot.num_nodes += 1;
+        }
+    }
+
+    /// Catch a node that fell behind up with the honest node that has the
+    /// most blocks, like a consensus service syncing its ledger from peers.
+    fn sync_ledger(&mut self, node_id: &NodeID) {
+        let sim_node = &self.nodes[node_id];
+        if !sim_node.saw_future_msg || sim_node.node.is_none() {
+            return;
+        }
+
+        let next_slot_index = sim_node.next_slot_index;
+        let source = self
+            .nodes
+            .values()
+            .filter(|other| other.config.byzantine.is_none() && other.config.id != *node_id)
+            .max_by_key(|other| other.next_slot_index);
+        let blocks: Option<Vec<(SlotIndex, Vec<V>)>> = source
+            .filter(|source| source.next_slot_index > next_slot_index)
+            .and_then(|source| {
+                (next_slot_index..source.next_slot_index)
+                    .map(|slot_index| {
+                        source
+                            .ledger
+                            .get(&slot_index)
+                            .map(|values| (slot_index, values.clone()))
+                    })
+                    .collect()
+            });
+
+        let sim_node = self.nodes.get_mut(node_id).expect("unknown node");
+        sim_node.saw_future_msg = false;
+        let blocks = match blocks {
+            Some(blocks) => blocks,
+            None => return,
+        };
+
+        for (slot_index, values) in blocks {
+            sim_node
+                .pending_values
+                .retain(|value| !values.contains(value));
+            sim_node.ledger.insert(slot_index, values);
+            sim_node.next_slot_index = slot_index + 1;
+        }
+        if let Some(node) = &mut sim_node.node {
+            node.reset_slot_index(sim_node.next_slot_index);
+        }
+        self.report.ledger_syncs += 1;
+
+        log::debug!(
+            self.logger,
+            "{} synced its ledger up to slot {}",
+            node_id,
+            sim_node.next_slot_index
+        );
+    }
+
+    fn handle_scenario_event(&mut self, event: ScenarioEvent<V>) {
+        log::debug!(self.logger, "{:?}: {:?}", self.now, event);
+
+        match event {
+            ScenarioEvent::SubmitValues { node_id, values } => {
+                let sim_node = self.nodes.get_mut(&node_id).expect("unknown node");
+                if sim_node.node.is_none() {
+                    return;
+                }
+                sim_node.pending_values.extend(values);
+                let outgoing_msgs = self.propose(&node_id);
+                self.after_step(&node_id, outgoing_msgs);
+            }
+
+            ScenarioEvent::InjectMsg { to, msg } => self.send(to, msg),
+
+            ScenarioEvent::Partition(groups) => self.partition = Some(groups),
+
+            ScenarioEvent::HealPartition => self.partition = None,
+
+            ScenarioEvent::Crash(node_id) => {
+                let sim_node = self.nodes.get_mut(&node_id).expect("unknown node");
+                sim_node.node = None;
+                sim_node.pending_values.clear();
+                sim_node.saw_future_msg = false;
+            }
+
+            ScenarioEvent::Restart(node_id) => {
+                let sim_node = &self.nodes[&node_id];
+                if sim_node.node.is_some() {
+                    return;
+                }
+                let node = self.start_node(&sim_node.config, sim_node.next_slot_index);
+                self.nodes.get_mut(&node_id).expect("unknown node").node = Some(node);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+    use mc_consensus_scp::scp_log::LoggingScpNode;
+    use tempdir::TempDir;
+
+    /// Submit `num_values` values to every honest node, one every 10ms.
+    fn submit_values(nodes: &[NodeConfig<String>], num_values: usize) -> Scenario<String> {
+        let node_ids: Vec<NodeID> = nodes
+            .iter()
+            .filter(|node| node.byzantine.is_none())
+            .map(|node| node.id.clone())
+            .collect();
+        Scenario::new().submit_to_all(
+            &node_ids,
+            (0..num_values).map(|i| format!("value-{:04}", i)),
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+        )
+    }
+
+    /// Sorts and deduplicates values.
+    fn sorted_values(values: &[String]) -> Result<Vec<String>, String> {
+        let mut values = values.to_vec();
+        values.sort();
+        values.dedup();
+        Ok(values)
+    }
+
+    /// A faulty network where values starting with "invalid" fail validation.
+    fn faulty_config(seed: u64) -> SimulationConfig<String> {
+        let mut config = SimulationConfig::new(seed);
+        config.faults = FaultConfig {
+            drop_probability: 0.1,
+            min_delay: Duration::from_millis(5),
+            max_delay: Duration::from_millis(200),
+        };
+        config.max_slot_proposed_values = 5;
+        config.target_slot_index = Some(10);
+        config.validity_fn = Arc::new(|value: &String| {
+            if value.starts_with("invalid") {
+                Err(format!("{} is invalid", value))
+            } else {
+                Ok(())
+            }
+        });
+        config
+    }
+
+    #[test_with_logger]
+    // A healthy network externalizes every value.
+    fn test_reliable_network(logger: Logger) {
+        let nodes = mesh_network(4, 2);
+        let scenario = submit_values(&nodes, 50);
+        let mut config = SimulationConfig::new(1);
+        config.max_time = Duration::from_secs(30);
+
+        let mut simulation = Simulation::new(nodes, &scenario, config, logger);
+        let report = simulation.run();
+
+        assert!(report.is_safe());
+        assert_eq!(report.msgs_dropped, 0);
+        assert_eq!(report.ledger_syncs, 0);
+        for node_index in 0..4 {
+            let num_values: usize = simulation
+                .ledger(&test_node_id(node_index))
+                .unwrap()
+                .values()
+                .map(|values| values.len())
+                .sum();
+            assert_eq!(num_values, 50);
+        }
+    }
+
+    #[test_with_logger]
+    // The same seed gives the same run, and a different seed a different one.
+    fn test_deterministic(logger: Logger) {
+        let run = |seed| {
+            let nodes = mesh_network(4, 2);
+            let scenario = submit_values(&nodes, 50);
+            Simulation::new(nodes, &scenario, faulty_config(seed), logger.clone()).run()
+        };
+
+        let report = run(7);
+        assert_eq!(report, run(7));
+        assert_ne!(report, run(8));
+    }
+
+    #[test_with_logger]
+    // Consensus makes progress despite message loss, delays, a partition that
+    // heals, and a node that crashes and restarts.
+    fn test_faults(logger: Logger) {
+        let nodes = mesh_network(5, 3);
+        let scenario = submit_values(&nodes, 200)
+            .at(
+                Duration::from_secs(2),
+                ScenarioEvent::Partition(vec![
+                    vec![test_node_id(0), test_node_id(1)].into_iter().collect(),
+                    vec![test_node_id(2), test_node_id(3), test_node_id(4)]
+                        .into_iter()
+                        .collect(),
+                ]),
+            )
+            .at(Duration::from_secs(5), ScenarioEvent::HealPartition)
+            .at(
+                Duration::from_secs(6),
+                ScenarioEvent::Crash(test_node_id(4)),
+            )
+            .at(
+                Duration::from_secs(9),
+                ScenarioEvent::Restart(test_node_id(4)),
+            );
+
+        for seed in 0..3 {
+            let mut config = faulty_config(seed);
+            config.target_slot_index = Some(20);
+            let report = Simulation::new(nodes.clone(), &scenario, config, logger.clone()).run();
+
+            assert!(
+                report.is_safe(),
+                "seed {}: {:?}",
+                seed,
+                report.safety_violations
+            );
+            assert!(report.msgs_dropped > 0);
+            assert!(
+                report.min_next_slot_index() >= 20,
+                "seed {}: {:?}",
+                seed,
+                report.next_slot_indices
+            );
+        }
+    }
+
+    #[test_with_logger]
+    // A single Byzantine node can neither split nor halt a 4 node network with
+    // thresholds of 2 out of 3 peers.
+    fn test_byzantine(logger: Logger) {
+        let behaviors = vec![
+            ByzantineBehavior::Silent,
+            ByzantineBehavior::Equivocate {
+                values: vec!["value-9999".to_string()],
+            },
+            ByzantineBehavior::VoteFor {
+                values: vec!["invalid".to_string()],
+            },
+        ];
+
+        for behavior in behaviors {
+            let mut nodes = mesh_network(4, 2);
+            nodes[0].byzantine = Some(behavior.clone());
+            let scenario = submit_values(&nodes, 100);
+
+            let mut simulation =
+                Simulation::new(nodes, &scenario, faulty_config(3), logger.clone());
+            let report = simulation.run();
+
+            assert!(
+                report.is_safe(),
+                "{:?}: {:?}",
+                behavior,
+                report.safety_violations
+            );
+            assert!(
+                report.min_next_slot_index() >= 10,
+                "{:?}: {:?}",
+                behavior,
+                report.next_slot_indices
+            );
+            for node_index in 1..4 {
+                assert!(simulation
+                    .ledger(&test_node_id(node_index))
+                    .unwrap()
+                    .values()
+                    .flatten()
+                    .all(|value| !value.starts_with("invalid")));
+            }
+        }
+    }
+
+    #[test_with_logger]
+    // Logs written by LoggingScpNode can be replayed.
+    fn test_replay_scp_logs(logger: Logger) {
+        let temp_dir = TempDir::new("scp-logs").unwrap();
+
+        // Record node 0 of a 3 node network. The log only contains the node's
+        // settings once it moves past slot 0, so consensus starts at slot 1.
+        let nodes = mesh_network(3, 2);
+        let new_node = |node: &NodeConfig<String>| {
+            Node::<String, String>::new(
+                node.id.clone(),
+                node.quorum_set.clone(),
+                Arc::new(|_value: &String| Ok(())),
+                Arc::new(sorted_values),
+                1,
+                logger.clone(),
+            )
+        };
+        let mut logging_node = LoggingScpNode::new(
+            new_node(&nodes[0]),
+            temp_dir.path().join("node-0"),
+            logger.clone(),
+        )
+        .unwrap();
+        let mut others: Vec<Node<String, String>> = nodes[1..].iter().map(new_node).collect();
+
+        // Run the three nodes in lock step until node 0 externalizes slot 1.
+        let values: BTreeSet<String> = vec!["a".to_string(), "b".to_string()].into_iter().collect();
+        let mut msgs: Vec<Msg<String>> = Vec::new();
+        msgs.extend(logging_node.propose_values(values.clone()).unwrap());
+        for node in others.iter_mut() {
+            msgs.extend(node.propose_values(values.clone()).unwrap());
+        }
+        while logging_node.get_externalized_values(1).is_none() {
+            assert!(!msgs.is_empty());
+            let mut next_msgs = Vec::new();
+            for msg in msgs.drain(..) {
+                if msg.sender_id != logging_node.node_id() {
+                    next_msgs.extend(logging_node.handle_message(&msg).unwrap());
+                }
+                for node in others.iter_mut() {
+                    if msg.sender_id != node.node_id() {
+                        next_msgs.extend(node.handle_message(&msg).unwrap());
+                    }
+                }
+            }
+            msgs = next_msgs;
+        }
+
+        // Replaying the log externalizes the same values.
+        let (replayed_nodes, scenario) =
+            Scenario::<String>::from_scp_logs(&[temp_dir.path().join("node-0").join("cur-slot")])
+                .unwrap();
+        assert_eq!(replayed_nodes.len(), 1);
+        assert_eq!(replayed_nodes[0].id, nodes[0].id);
+        assert_eq!(replayed_nodes[0].first_slot_index, 1);
+        assert!(replayed_nodes[0].peers.is_empty());
+
+        let mut config = SimulationConfig::new(1);
+        config.target_slot_index = Some(2);
+        config.max_time = Duration::from_secs(10);
+        let mut simulation = Simulation::new(replayed_nodes, &scenario, config, logger);
+        let report = simulation.run();
+
+        assert_eq!(report.next_slot_indices[&nodes[0].id], 2);
+        assert_eq!(
+            simulation.ledger(&nodes[0].id).unwrap()[&1],
+            logging_node.get_externalized_values(1).unwrap()
+        );
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Safety and liveness results of a simulation.
+
+use mc_common::NodeID;
+use mc_consensus_scp::{SlotIndex, Value};
+use std::{collections::BTreeMap, time::Duration};
+
+/// Two honest nodes externalized different values for the same slot.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SafetyViolation<V: Value> {
+    /// The slot.
+    pub slot_index: SlotIndex,
+
+    /// The first node to externalize the slot.
+    pub first_node_id: NodeID,
+
+    /// The values the first node externalized.
+    pub first_values: Vec<V>,
+
+    /// A node that externalized different values.
+    pub node_id: NodeID,
+
+    /// The values that node externalized.
+    pub values: Vec<V>,
+}
+
+/// When a slot was externalized.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SlotReport {
+    /// The slot.
+    pub slot_index: SlotIndex,
+
+    /// Time at which the first honest node externalized the slot.
+    pub first_externalized_at: Duration,
+
+    /// Time at which the last honest node externalized the slot.
+    pub last_externalized_at: Duration,
+
+    /// Number of honest nodes that externalized the slot through consensus.
+    /// Nodes that caught up by syncing their ledger are not counted.
+    pub num_nodes: usize,
+}
+
+/// The outcome of a simulation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SimulationReport<V: Value> {
+    /// The seed the simulation ran with.
+    pub seed: u64,
+
+    /// Virtual time at which the simulation ended.
+    pub elapsed: Duration,
+
+    /// Conflicting externalized values.
+    pub safety_violations: Vec<SafetyViolation<V>>,
+
+    /// Externalized slots, in order.
+    pub slots: Vec<SlotReport>,
+
+    /// Index of the next slot each honest node will externalize.
+    pub next_slot_indices: BTreeMap<NodeID, SlotIndex>,
+
+    /// Number of messages sent to peers.
+    pub msgs_sent: u64,
+
+    /// Number of messages lost to faults, partitions or crashed receivers.
+    pub msgs_dropped: u64,
+
+    /// Number of messages handled by their receiver.
+    pub msgs_delivered: u64,
+
+    /// Number of times a node that fell behind copied blocks from a peer.
+    pub ledger_syncs: u64,
+
+    /// Highest ballot counter seen at any honest node.
+    pub max_ballot_counter: u32,
+
+    /// Highest nomination round seen at any honest node.
+    pub max_nomination_round: u32,
+}
+
+impl<V: Value> SimulationReport<V> {
+    /// True if no two honest nodes externalized different values for a slot.
+    pub fn is_safe(&self) -> bool {
+        self.safety_violations.is_empty()
+    }
+
+    /// The lowest next slot index among honest nodes, i.e. the number of slots
+    /// every honest node has a block for.
+    pub fn min_next_slot_index(&self) -> SlotIndex {
+        self.next_slot_indices.values().min().cloned().unwrap_or(0)
+    }
+
+    /// Average time between consecutive slots being first externalized.
+    pub fn mean_slot_interval(&self) -> Option<Duration> {
+        let first = self.slots.first()?;
+        let last = self.slots.last()?;
+        if self.slots.len() < 2 {
+            return None;
+        }
+        Some(
+            (last.first_externalized_at - first.first_externalized_at)
+                / (self.slots.len() - 1) as u32,
+        )
+    }
+
+    /// Longest time from a slot being first externalized until every honest
+    /// node that externalized it through consensus did so.
+    pub fn max_externalize_spread(&self) -> Duration {
+        self.slots
+            .iter()
+            .map(|slot| slot.last_externalized_at - slot.first_externalized_at)
+            .max()
+            .unwrap_or_default()
+    }
+}
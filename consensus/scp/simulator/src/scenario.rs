@@ -0,0 +1,197 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Scripted events that drive a simulation.
+
+use crate::NodeConfig;
+use mc_common::NodeID;
+use mc_consensus_scp::{
+    scp_log::{LoggedMsg, ScpLogReader},
+    Msg, Value,
+};
+use serde::de::DeserializeOwned;
+use std::{collections::BTreeSet, path::Path, time::Duration};
+
+/// An event in a scenario.
+#[derive(Clone, Debug)]
+pub enum ScenarioEvent<V: Value> {
+    /// A client submits values to a node.
+    SubmitValues {
+        /// The node receiving the values.
+        node_id: NodeID,
+
+        /// The submitted values.
+        values: BTreeSet<V>,
+    },
+
+    /// A message from outside of the simulated network is sent to a node. It
+    /// is subject to message loss and delays like any other message.
+    InjectMsg {
+        /// The receiving node.
+        to: NodeID,
+
+        /// The message.
+        msg: Msg<V>,
+    },
+
+    /// Split the network into groups of nodes that can only communicate
+    /// within their group. Nodes that are not in any group form a group of
+    /// their own. Replaces any previous partition.
+    Partition(Vec<BTreeSet<NodeID>>),
+
+    /// Remove the current partition.
+    HealPartition,
+
+    /// A node crashes, losing all state except for its ledger. Messages sent
+    /// to it while it is down are lost.
+    Crash(NodeID),
+
+    /// A crashed node restarts, resuming consensus after the last block in its
+    /// ledger.
+    Restart(NodeID),
+}
+
+/// A list of events, each happening at a given (virtual) time since the
+/// start of the simulation.
+#[derive(Clone, Debug)]
+pub struct Scenario<V: Value> {
+    events: Vec<(Duration, ScenarioEvent<V>)>,
+}
+
+impl<V: Value> Default for Scenario<V> {
+    fn default() -> Self {
+        Self { events: Vec::new() }
+    }
+}
+
+impl<V: Value> Scenario<V> {
+    /// Create an empty scenario.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an event at a given time.
+    pub fn at(mut self, time: Duration, event: ScenarioEvent<V>) -> Self {
+        self.push(time, event);
+        self
+    }
+
+    /// Add an event at a given time.
+    pub fn push(&mut self, time: Duration, event: ScenarioEvent<V>) {
+        self.events.push((time, event));
+    }
+
+    /// Submit each of `values` to all of `node_ids`, one value every
+    /// `interval`, starting at `start`.
+    pub fn submit_to_all(
+        mut self,
+        node_ids: &[NodeID],
+        values: impl IntoIterator<Item = V>,
+        start: Duration,
+        interval: Duration,
+    ) -> Self {
+        for (i, value) in values.into_iter().enumerate() {
+            for node_id in node_ids {
+                self.push(
+                    start + interval * i as u32,
+                    ScenarioEvent::SubmitValues {
+                        node_id: node_id.clone(),
+                        values: vec![value.clone()].into_iter().collect(),
+                    },
+                );
+            }
+        }
+        self
+    }
+
+    /// The events, ordered by time. Events with the same time keep the order
+    /// they were added in.
+    pub fn events(&self) -> Vec<(Duration, ScenarioEvent<V>)> {
+        let mut events = self.events.clone();
+        events.sort_by_key(|(time, _)| *time);
+        events
+    }
+}
+
+impl<V: Value + DeserializeOwned> Scenario<V> {
+    /// Build a scenario from logs written by `LoggingScpNode`, one
+    /// `cur-slot` directory per node.
+    ///
+    /// Each logged node becomes a simulated node that starts at the logged
+    /// slot, with every other logged node as a peer. Values the node was
+    /// asked to nominate are submitted to it at the logged times, and
+    /// messages it received from nodes that were not logged are injected.
+    /// Messages between logged nodes are produced by the simulation itself.
+    pub fn from_scp_logs(paths: &[impl AsRef<Path>]) -> Result<(Vec<NodeConfig<V>>, Self), String> {
+        let mut logs = Vec::new();
+        for path in paths {
+            let path = path.as_ref();
+            let mut node = None;
+            let mut logged_events = Vec::new();
+            for stored_msg in ScpLogReader::<V>::new(path)? {
+                let time = Duration::from_millis(stored_msg.msec_since_start);
+                match stored_msg.msg {
+                    LoggedMsg::NodeSettings(node_id, quorum_set, slot_index) => {
+                        if node.is_none() {
+                            node = Some((node_id, quorum_set, slot_index));
+                        }
+                    }
+                    LoggedMsg::Nominate(_slot_index, values) => {
+                        logged_events.push((time, LoggedEvent::Nominate(values)))
+                    }
+                    LoggedMsg::IncomingMsg(msg) => {
+                        logged_events.push((time, LoggedEvent::Incoming(msg)))
+                    }
+                    LoggedMsg::OutgoingMsg(_)
+                    | LoggedMsg::ProcessTimeouts(_)
+                    | LoggedMsg::Marker(_) => {}
+                }
+            }
+            let (node_id, quorum_set, slot_index) =
+                node.ok_or_else(|| format!("{:?} has no node settings", path))?;
+            logs.push((node_id, quorum_set, slot_index, logged_events));
+        }
+
+        let node_ids: BTreeSet<NodeID> = logs.iter().map(|(node_id, ..)| node_id.clone()).collect();
+
+        let mut nodes = Vec::new();
+        let mut scenario = Self::new();
+        for (node_id, quorum_set, slot_index, logged_events) in logs {
+            let mut peers = node_ids.clone();
+            peers.remove(&node_id);
+            for (time, logged_event) in logged_events {
+                match logged_event {
+                    LoggedEvent::Nominate(values) => scenario.push(
+                        time,
+                        ScenarioEvent::SubmitValues {
+                            node_id: node_id.clone(),
+                            values,
+                        },
+                    ),
+                    LoggedEvent::Incoming(msg) => {
+                        if !node_ids.contains(&msg.sender_id) {
+                            scenario.push(
+                                time,
+                                ScenarioEvent::InjectMsg {
+                                    to: node_id.clone(),
+                                    msg,
+                                },
+                            )
+                        }
+                    }
+                }
+            }
+
+            let mut node = NodeConfig::new(node_id, quorum_set, peers);
+            node.first_slot_index = slot_index;
+            nodes.push(node);
+        }
+
+        Ok((nodes, scenario))
+    }
+}
+
+/// The parts of an SCP log that are replayed.
+enum LoggedEvent<V: Value> {
+    Nominate(BTreeSet<V>),
+    Incoming(Msg<V>),
+}
@@ -11,6 +11,7 @@ use std::{
     fmt::{Debug, Display},
     hash::{Hash, Hasher},
     sync::Arc,
+    time::Instant,
 };
 
 /// A generic node identifier.
@@ -40,6 +41,10 @@ pub type CombineFn<V, E> = Arc<(dyn Fn(&[V]) -> Result<Vec<V>, E> + Sync + Send)
 /// Application-specific validation of value.
 pub type ValidityFn<V, E> = Arc<(dyn Fn(&V) -> Result<(), E> + Sync + Send)>;
 
+/// Returns the current time. Slots read their nomination and ballot timers
+/// through it, so that simulations can run on virtual time.
+pub type ClockFn = Arc<(dyn Fn() -> Instant + Sync + Send)>;
+
 /// The node identifier is used when reasoning about messages in federated
 /// voting.
 ///
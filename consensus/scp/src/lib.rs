@@ -8,6 +8,7 @@ pub mod core_types;
 pub mod msg;
 pub mod node;
 pub mod predicates;
+pub mod quorum_analysis;
 pub mod quorum_set;
 pub mod scp_log;
 pub mod slot;
@@ -17,7 +18,7 @@ mod utils;
 
 #[doc(inline)]
 pub use crate::{
-    core_types::{CombineFn, GenericNodeId, Identifier, SlotIndex, ValidityFn, Value},
+    core_types::{ClockFn, CombineFn, GenericNodeId, Identifier, SlotIndex, ValidityFn, Value},
     msg::{Msg, Topic},
     node::{MockScpNode, Node, ScpNode},
     quorum_set::{QuorumSet, QuorumSetMember},
@@ -3,7 +3,7 @@
 //! A node determines whether transactions are valid, and participates in voting
 //! with the members of its quorum set.
 use crate::{
-    core_types::{CombineFn, SlotIndex, ValidityFn, Value},
+    core_types::{ClockFn, CombineFn, SlotIndex, ValidityFn, Value},
     msg::{ExternalizePayload, Msg, Topic},
     quorum_set::QuorumSet,
     slot::{ScpSlot, Slot, SlotMetrics},
@@ -16,7 +16,8 @@ use mc_common::{
 use std::{
     collections::{BTreeSet, HashMap},
     fmt::Display,
-    time::Duration,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 /// Default limit on number of externalized slots to store.
@@ -46,6 +47,9 @@ pub struct Node<V: Value, ValidationError: Clone + Display> {
     /// deterministic.
     combine_fn: CombineFn<V, ValidationError>,
 
+    /// Source of the current time for slot timers.
+    clock: ClockFn,
+
     /// Logger.
     logger: Logger,
 
@@ -75,7 +79,39 @@ impl<V: Value, ValidationError: Clone + Display + 'static> Node<V, ValidationErr
         current_slot_index: SlotIndex,
         logger: Logger,
     ) -> Self {
-        let slot = Slot::new(
+        Self::new_with_clock(
+            node_id,
+            quorum_set,
+            validity_fn,
+            combine_fn,
+            current_slot_index,
+            Arc::new(Instant::now),
+            logger,
+        )
+    }
+
+    /// Creates a new Node whose slot timers read the time from `clock`.
+    ///
+    /// # Arguments
+    /// * `node_id` - This node's ID.
+    /// * `quorum_set` - This node's quorum set.
+    /// * `validity_fn` - Validates a value.
+    /// * `combine_fn` - Combines a set of values into a composite value (i.e.
+    ///   block).
+    /// * `current_slot_index` - Index of the slot to begin performing consensus
+    ///   on.
+    /// * `clock` - Returns the current time.
+    /// * `logger`
+    pub fn new_with_clock(
+        node_id: NodeID,
+        quorum_set: QuorumSet,
+        validity_fn: ValidityFn<V, ValidationError>,
+        combine_fn: CombineFn<V, ValidationError>,
+        current_slot_index: SlotIndex,
+        clock: ClockFn,
+        logger: Logger,
+    ) -> Self {
+        let mut slot = Slot::new(
             node_id.clone(),
             quorum_set.clone(),
             current_slot_index,
@@ -83,6 +119,7 @@ impl<V: Value, ValidationError: Clone + Display + 'static> Node<V, ValidationErr
             combine_fn.clone(),
             logger.clone(),
         );
+        slot.clock = clock.clone();
 
         Self {
             ID: node_id,
@@ -92,11 +129,26 @@ impl<V: Value, ValidationError: Clone + Display + 'static> Node<V, ValidationErr
             externalized_slots: Vec::new(),
             validity_fn,
             combine_fn,
+            clock,
             logger,
             scp_timebase: Duration::from_millis(1000),
         }
     }
 
+    // Create a slot with this node's settings.
+    fn new_slot(&self, slot_index: SlotIndex) -> Box<dyn ScpSlot<V>> {
+        let mut slot = Slot::new(
+            self.ID.clone(),
+            self.Q.clone(),
+            slot_index,
+            self.validity_fn.clone(),
+            self.combine_fn.clone(),
+            self.logger.clone(),
+        );
+        slot.clock = self.clock.clone();
+        Box::new(slot)
+    }
+
     // Record the values externalized by the current slot and advance the current
     // slot.
     fn externalize(&mut self, payload: &ExternalizePayload<V>) -> Result<(), String> {
@@ -116,14 +168,7 @@ impl<V: Value, ValidationError: Clone + Display + 'static> Node<V, ValidationErr
             }
         }
 
-        let next_slot = Box::new(Slot::new(
-            self.ID.clone(),
-            self.Q.clone(),
-            slot_index + 1,
-            self.validity_fn.clone(),
-            self.combine_fn.clone(),
-            self.logger.clone(),
-        ));
+        let next_slot = self.new_slot(slot_index + 1);
 
         // Advance to the next slot.
         let externalized_slot = std::mem::replace(&mut self.current_slot, next_slot);
@@ -160,6 +205,10 @@ impl<V: Value, ValidationError: Clone + Display + 'static> ScpNode<V> for Node<V
         self.Q.clone()
     }
 
+    fn set_quorum_set(&mut self, quorum_set: QuorumSet) {
+        self.Q = quorum_set;
+    }
+
     /// Propose values for this node to nominate.
     fn propose_values(&mut self, values: BTreeSet<V>) -> Result<Option<Msg<V>>, String> {
         if values.is_empty() {
@@ -303,14 +352,7 @@ impl<V: Value, ValidationError: Clone + Display + 'static> ScpNode<V> for Node<V
         // The slot index should only increase.
         debug_assert!(slot_index > self.current_slot_index());
 
-        self.current_slot = Box::new(Slot::new(
-            self.ID.clone(),
-            self.Q.clone(),
-            slot_index,
-            self.validity_fn.clone(),
-            self.combine_fn.clone(),
-            self.logger.clone(),
-        ));
+        self.current_slot = self.new_slot(slot_index);
 
         self.externalized_slots.clear();
     }
@@ -732,6 +774,41 @@ mod tests {
         assert_eq!(node.externalized_slots.len(), 0);
     }
 
+    #[test_with_logger]
+    // A new quorum set should only be used from the next slot on.
+    fn test_set_quorum_set(logger: Logger) {
+        let slot_index = 3;
+        let quorum_set = QuorumSet::new_with_node_ids(1, vec![test_node_id(2)]);
+        let mut node = Node::<u32, TransactionValidationError>::new(
+            test_node_id(1),
+            quorum_set.clone(),
+            Arc::new(trivial_validity_fn),
+            Arc::new(trivial_combine_fn),
+            slot_index,
+            logger,
+        );
+
+        let new_quorum_set =
+            QuorumSet::new_with_node_ids(2, vec![test_node_id(2), test_node_id(3)]);
+        node.set_quorum_set(new_quorum_set.clone());
+        assert_eq!(node.quorum_set(), new_quorum_set);
+
+        // The current slot keeps its quorum set.
+        let msg = node
+            .propose_values(btreeset! {1000})
+            .expect("error handling msg")
+            .expect("no msg?");
+        assert_eq!(msg.quorum_set, quorum_set);
+
+        node.reset_slot_index(slot_index + 1);
+        let msg = node
+            .propose_values(btreeset! {2000})
+            .expect("error handling msg")
+            .expect("no msg?");
+        assert_eq!(msg.slot_index, slot_index + 1);
+        assert_eq!(msg.quorum_set, new_quorum_set);
+    }
+
     #[test_with_logger]
     /// Steps through a sequence of messages that allow a two-node network to
     /// reach consensus.
@@ -14,6 +14,10 @@ pub trait ScpNode<V: Value>: Send {
     /// Get local node quorum set.
     fn quorum_set(&self) -> QuorumSet;
 
+    /// Set local node quorum set. The current slot keeps using the quorum set
+    /// it was started with, the new one applies from the next slot on.
+    fn set_quorum_set(&mut self, quorum_set: QuorumSet);
+
     /// Propose values for this node to nominate.
     fn propose_values(&mut self, values: BTreeSet<V>) -> Result<Option<Msg<V>>, String>;
 
@@ -0,0 +1,480 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Network-wide analysis of quorum set configurations.
+//!
+//! Given the quorum set of every node in a network, this finds:
+//! * The minimal quorums: sets of nodes that contain a slice of each of their
+//!   members, and have no proper subset with the same property.
+//! * Quorum intersection violations: pairs of disjoint quorums. A network with
+//!   disjoint quorums can externalize conflicting values.
+//! * Minimal blocking sets: sets of nodes whose failure leaves no quorum, and
+//!   so halts the network.
+//! * Minimal splitting sets: sets of nodes that, if Byzantine, can cause two
+//!   quorums to externalize conflicting values, since the quorums only
+//!   intersect at these nodes.
+//!
+//! Nodes that appear in quorum sets but have no quorum set of their own are
+//! reported as unknown, and are treated as never being part of a quorum.
+//!
+//! All of these problems are NP-hard in general. The minimal quorums are
+//! enumerated with a branch and bound search, which is fast enough for
+//! networks with a few dozen nodes.
+
+use crate::{core_types::GenericNodeId, quorum_set::QuorumSet, QuorumSetMember};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A set of nodes.
+pub type NodeSet<ID> = BTreeSet<ID>;
+
+/// How a network is affected by all nodes of an organization failing or being
+/// Byzantine.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct OrganizationFaultTolerance<ID: GenericNodeId> {
+    /// The organization's name.
+    pub name: String,
+
+    /// The organization's nodes.
+    pub nodes: NodeSet<ID>,
+
+    /// True if the organization's nodes failing halts the whole network.
+    pub can_halt_network: bool,
+
+    /// Nodes outside of the organization that can no longer make progress
+    /// when the organization's nodes fail.
+    pub halted_nodes: NodeSet<ID>,
+
+    /// True if the organization's nodes being Byzantine can cause the network
+    /// to externalize conflicting values.
+    pub can_split_network: bool,
+}
+
+/// The result of analyzing a network.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct QuorumAnalysis<ID: GenericNodeId> {
+    /// Nodes that appear in quorum sets, but whose own quorum set is unknown.
+    pub unknown_nodes: NodeSet<ID>,
+
+    /// The minimal quorums.
+    pub minimal_quorums: Vec<NodeSet<ID>>,
+
+    /// Pairs of disjoint minimal quorums. Empty if the network enjoys quorum
+    /// intersection.
+    pub intersection_violations: Vec<(NodeSet<ID>, NodeSet<ID>)>,
+
+    /// Minimal sets of nodes whose failure halts the network, up to the
+    /// requested size.
+    pub minimal_blocking_sets: Vec<NodeSet<ID>>,
+
+    /// Minimal sets of nodes that can split the network if Byzantine.
+    pub minimal_splitting_sets: Vec<NodeSet<ID>>,
+
+    /// Fault tolerance of each organization.
+    pub organizations: Vec<OrganizationFaultTolerance<ID>>,
+}
+
+impl<ID: GenericNodeId> QuorumAnalysis<ID> {
+    /// True if the network enjoys quorum intersection.
+    pub fn has_quorum_intersection(&self) -> bool {
+        !self.minimal_quorums.is_empty() && self.intersection_violations.is_empty()
+    }
+}
+
+/// Analyzes the quorum sets of a whole network.
+pub struct QuorumAnalyzer<ID: GenericNodeId> {
+    /// Every node's quorum set.
+    quorum_sets: BTreeMap<ID, QuorumSet<ID>>,
+}
+
+impl<ID: GenericNodeId> QuorumAnalyzer<ID> {
+    /// Create an analyzer for a network, given each node's quorum set.
+    pub fn new(quorum_sets: BTreeMap<ID, QuorumSet<ID>>) -> Self {
+        Self { quorum_sets }
+    }
+
+    /// Run every analysis.
+    ///
+    /// # Arguments
+    /// * `organizations` - Organization name -> the organization's nodes.
+    /// * `max_blocking_set_size` - Largest blocking set to search for.
+    pub fn analyze(
+        &self,
+        organizations: &BTreeMap<String, NodeSet<ID>>,
+        max_blocking_set_size: usize,
+    ) -> QuorumAnalysis<ID> {
+        let minimal_quorums = self.minimal_quorums();
+        let minimal_splitting_sets = Self::minimal_splitting_sets(&minimal_quorums);
+
+        let organizations = organizations
+            .iter()
+            .map(|(name, nodes)| {
+                let remaining_nodes = self.nodes().difference(nodes).cloned().collect();
+                let available_nodes = self.max_quorum(&remaining_nodes);
+                OrganizationFaultTolerance {
+                    name: name.clone(),
+                    nodes: nodes.clone(),
+                    can_halt_network: available_nodes.is_empty(),
+                    halted_nodes: remaining_nodes
+                        .difference(&available_nodes)
+                        .cloned()
+                        .collect(),
+                    can_split_network: minimal_splitting_sets
+                        .iter()
+                        .any(|splitting_set| splitting_set.is_subset(nodes)),
+                }
+            })
+            .collect();
+
+        QuorumAnalysis {
+            unknown_nodes: self.unknown_nodes(),
+            intersection_violations: Self::intersection_violations(&minimal_quorums),
+            minimal_blocking_sets: self
+                .minimal_blocking_sets(&minimal_quorums, max_blocking_set_size),
+            minimal_splitting_sets,
+            minimal_quorums,
+            organizations,
+        }
+    }
+
+    /// Nodes whose quorum set is known.
+    pub fn nodes(&self) -> NodeSet<ID> {
+        self.quorum_sets.keys().cloned().collect()
+    }
+
+    /// Nodes that appear in quorum sets, but whose own quorum set is unknown.
+    pub fn unknown_nodes(&self) -> NodeSet<ID> {
+        self.quorum_sets
+            .values()
+            .flat_map(|quorum_set| quorum_set.nodes())
+            .filter(|node_id| !self.quorum_sets.contains_key(node_id))
+            .collect()
+    }
+
+    /// True if `nodes` is a quorum: a non-empty set of nodes that contains a
+    /// slice of each of its members.
+    pub fn is_quorum(&self, nodes: &NodeSet<ID>) -> bool {
+        !nodes.is_empty()
+            && nodes.iter().all(|node_id| {
+                self.quorum_sets
+                    .get(node_id)
+                    .map_or(false, |quorum_set| is_satisfied_by(quorum_set, nodes))
+            })
+    }
+
+    /// The largest quorum contained in `nodes`, or an empty set if there is
+    /// none. These are the nodes that can make progress if only `nodes` are
+    /// available.
+    pub fn max_quorum(&self, nodes: &NodeSet<ID>) -> NodeSet<ID> {
+        let mut result = nodes.clone();
+        loop {
+            let unsatisfied: Vec<ID> = result
+                .iter()
+                .filter(|node_id| {
+                    self.quorum_sets
+                        .get(node_id)
+                        .map_or(true, |quorum_set| !is_satisfied_by(quorum_set, &result))
+                })
+                .cloned()
+                .collect();
+            if unsatisfied.is_empty() {
+                return result;
+            }
+            for node_id in unsatisfied {
+                result.remove(&node_id);
+            }
+        }
+    }
+
+    /// All minimal quorums, in ascending order.
+    pub fn minimal_quorums(&self) -> Vec<NodeSet<ID>> {
+        let mut quorums = Vec::new();
+        let candidates: Vec<ID> = self.max_quorum(&self.nodes()).into_iter().collect();
+        self.find_quorums(NodeSet::new(), &candidates, &mut quorums);
+        minimal_sets(quorums)
+    }
+
+    /// Branch and bound search for quorums made of `committed` and some of
+    /// `candidates`. Every minimal quorum is found, along with some
+    /// non-minimal ones.
+    fn find_quorums(
+        &self,
+        committed: NodeSet<ID>,
+        candidates: &[ID],
+        quorums: &mut Vec<NodeSet<ID>>,
+    ) {
+        if self.is_quorum(&committed) {
+            quorums.push(committed);
+            return;
+        }
+
+        // Only nodes that are part of a quorum within what is still available are
+        // useful, and all committed nodes must be among them.
+        let available = committed.iter().chain(candidates.iter()).cloned().collect();
+        let max_quorum = self.max_quorum(&available);
+        if max_quorum.is_empty() || !committed.is_subset(&max_quorum) {
+            return;
+        }
+        let candidates: Vec<ID> = candidates
+            .iter()
+            .filter(|node_id| max_quorum.contains(node_id))
+            .cloned()
+            .collect();
+
+        if let Some((next, rest)) = candidates.split_first() {
+            let mut with_next = committed.clone();
+            with_next.insert(next.clone());
+            self.find_quorums(with_next, rest, quorums);
+            self.find_quorums(committed, rest, quorums);
+        }
+    }
+
+    /// Pairs of disjoint minimal quorums.
+    fn intersection_violations(minimal_quorums: &[NodeSet<ID>]) -> Vec<(NodeSet<ID>, NodeSet<ID>)> {
+        let mut violations = Vec::new();
+        for (i, quorum1) in minimal_quorums.iter().enumerate() {
+            for quorum2 in &minimal_quorums[i + 1..] {
+                if quorum1.is_disjoint(quorum2) {
+                    violations.push((quorum1.clone(), quorum2.clone()));
+                }
+            }
+        }
+        violations
+    }
+
+    /// Minimal sets of at most `max_size` nodes that intersect every quorum.
+    /// When no quorum exists at all, the empty set is the only blocking set.
+    fn minimal_blocking_sets(
+        &self,
+        minimal_quorums: &[NodeSet<ID>],
+        max_size: usize,
+    ) -> Vec<NodeSet<ID>> {
+        if minimal_quorums.is_empty() {
+            return vec![NodeSet::new()];
+        }
+
+        // Only nodes that are part of some quorum matter.
+        let nodes: Vec<ID> = minimal_quorums
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<NodeSet<ID>>()
+            .into_iter()
+            .collect();
+
+        // Go through candidates by increasing size, so that any superset of a
+        // blocking set that was already found can be skipped.
+        let mut blocking_sets: Vec<NodeSet<ID>> = Vec::new();
+        for size in 1..=max_size.min(nodes.len()) {
+            for_each_combination(&nodes, size, &mut |candidate| {
+                let is_minimal = !blocking_sets
+                    .iter()
+                    .any(|blocking_set| blocking_set.is_subset(candidate));
+                let is_blocking = minimal_quorums
+                    .iter()
+                    .all(|quorum| !quorum.is_disjoint(candidate));
+                if is_minimal && is_blocking {
+                    blocking_sets.push(candidate.clone());
+                }
+            });
+        }
+        blocking_sets
+    }
+
+    /// Minimal sets of nodes that two different quorums intersect at. Any
+    /// pair of quorums contains a pair of minimal quorums, so these are the
+    /// minimal intersections of minimal quorums.
+    fn minimal_splitting_sets(minimal_quorums: &[NodeSet<ID>]) -> Vec<NodeSet<ID>> {
+        let mut intersections = Vec::new();
+        for (i, quorum1) in minimal_quorums.iter().enumerate() {
+            for quorum2 in &minimal_quorums[i + 1..] {
+                intersections.push(quorum1.intersection(quorum2).cloned().collect());
+            }
+        }
+        minimal_sets(intersections)
+    }
+}
+
+/// True if `nodes` contains a slice of `quorum_set`.
+fn is_satisfied_by<ID: GenericNodeId>(quorum_set: &QuorumSet<ID>, nodes: &NodeSet<ID>) -> bool {
+    let num_satisfied_members = quorum_set
+        .members
+        .iter()
+        .filter(|member| match member {
+            QuorumSetMember::Node(node_id) => nodes.contains(node_id),
+            QuorumSetMember::InnerSet(inner_set) => is_satisfied_by(inner_set, nodes),
+        })
+        .count();
+    num_satisfied_members >= quorum_set.threshold as usize
+}
+
+/// The sets that have no proper subset in `sets`, sorted and deduplicated.
+fn minimal_sets<ID: GenericNodeId>(mut sets: Vec<NodeSet<ID>>) -> Vec<NodeSet<ID>> {
+    // Smaller sets first, so that each set only needs to be compared against the
+    // sets that were already kept.
+    sets.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    sets.dedup();
+
+    let mut result: Vec<NodeSet<ID>> = Vec::new();
+    for set in sets {
+        if !result.iter().any(|kept| kept.is_subset(&set)) {
+            result.push(set);
+        }
+    }
+    result.sort();
+    result
+}
+
+/// Call `f` with every subset of `items` of the given size.
+fn for_each_combination<ID: GenericNodeId>(
+    items: &[ID],
+    size: usize,
+    f: &mut impl FnMut(&NodeSet<ID>),
+) {
+    fn helper<ID: GenericNodeId>(
+        items: &[ID],
+        size: usize,
+        current: &mut NodeSet<ID>,
+        f: &mut impl FnMut(&NodeSet<ID>),
+    ) {
+        if current.len() == size {
+            f(current);
+            return;
+        }
+        if items.len() < size - current.len() {
+            return;
+        }
+        let (first, rest) = items.split_first().expect("items is not empty");
+        current.insert(first.clone());
+        helper(rest, size, current, f);
+        current.remove(first);
+        helper(rest, size, current, f);
+    }
+
+    helper(items, size, &mut NodeSet::new(), f)
+}
+
+#[cfg(test)]
+mod quorum_analysis_tests {
+    use super::*;
+    use crate::test_utils::test_node_id;
+    use mc_common::NodeID;
+    use std::iter::FromIterator;
+
+    fn node_set(indices: &[u32]) -> NodeSet<NodeID> {
+        NodeSet::from_iter(indices.iter().map(|index| test_node_id(*index)))
+    }
+
+    /// Every node trusts a threshold of the given nodes (including itself).
+    fn flat_network(nodes: &[u32], threshold: u32) -> QuorumAnalyzer<NodeID> {
+        let quorum_set = QuorumSet::new_with_node_ids(
+            threshold,
+            nodes.iter().map(|index| test_node_id(*index)).collect(),
+        );
+        QuorumAnalyzer::new(
+            nodes
+                .iter()
+                .map(|index| (test_node_id(*index), quorum_set.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    // 3-of-4 has quorum intersection, tolerates one failure, and needs two
+    // Byzantine nodes to split.
+    fn test_three_of_four() {
+        let analyzer = flat_network(&[1, 2, 3, 4], 3);
+        let analysis = analyzer.analyze(&BTreeMap::new(), 3);
+
+        assert!(analysis.has_quorum_intersection());
+        assert!(analysis.unknown_nodes.is_empty());
+        assert_eq!(
+            analysis.minimal_quorums,
+            vec![
+                node_set(&[1, 2, 3]),
+                node_set(&[1, 2, 4]),
+                node_set(&[1, 3, 4]),
+                node_set(&[2, 3, 4]),
+            ]
+        );
+        assert_eq!(analysis.minimal_blocking_sets.len(), 6);
+        assert!(analysis
+            .minimal_blocking_sets
+            .iter()
+            .all(|blocking_set| blocking_set.len() == 2));
+        assert_eq!(analysis.minimal_splitting_sets.len(), 6);
+        assert!(analysis
+            .minimal_splitting_sets
+            .iter()
+            .all(|splitting_set| splitting_set.len() == 2));
+    }
+
+    #[test]
+    // 2-of-4 allows two disjoint quorums.
+    fn test_two_of_four_lacks_intersection() {
+        let analyzer = flat_network(&[1, 2, 3, 4], 2);
+        let analysis = analyzer.analyze(&BTreeMap::new(), 3);
+
+        assert!(!analysis.has_quorum_intersection());
+        assert!(analysis
+            .intersection_violations
+            .contains(&(node_set(&[1, 2]), node_set(&[3, 4]))));
+        assert_eq!(analysis.minimal_splitting_sets, vec![NodeSet::new()]);
+    }
+
+    #[test]
+    // Two organizations with 2-of-3 inner sets, each node requiring both
+    // organizations.
+    fn test_organizations() {
+        let org_a = QuorumSet::new_with_node_ids(
+            2,
+            vec![test_node_id(1), test_node_id(2), test_node_id(3)],
+        );
+        let org_b = QuorumSet::new_with_node_ids(
+            2,
+            vec![test_node_id(4), test_node_id(5), test_node_id(6)],
+        );
+        let quorum_set = QuorumSet::new_with_inner_sets(2, vec![org_a, org_b]);
+        let analyzer = QuorumAnalyzer::new(
+            (1..=6)
+                .map(|index| (test_node_id(index), quorum_set.clone()))
+                .collect(),
+        );
+
+        let organizations = BTreeMap::from_iter(vec![
+            ("a".to_string(), node_set(&[1, 2, 3])),
+            ("b".to_string(), node_set(&[4, 5, 6])),
+            ("a-partial".to_string(), node_set(&[1])),
+        ]);
+        let analysis = analyzer.analyze(&organizations, 2);
+
+        assert!(analysis.has_quorum_intersection());
+        assert_eq!(analysis.minimal_quorums.len(), 9);
+
+        let org_a = &analysis.organizations[0];
+        assert_eq!(org_a.name, "a");
+        assert!(org_a.can_halt_network);
+        assert_eq!(org_a.halted_nodes, node_set(&[4, 5, 6]));
+        // Any two quorums share an honest node from organization b.
+        assert!(!org_a.can_split_network);
+
+        let org_a_partial = &analysis.organizations[1];
+        assert_eq!(org_a_partial.name, "a-partial");
+        assert!(!org_a_partial.can_halt_network);
+        assert!(org_a_partial.halted_nodes.is_empty());
+        assert!(!org_a_partial.can_split_network);
+    }
+
+    #[test]
+    // Nodes without a known quorum set are never part of a quorum.
+    fn test_unknown_nodes() {
+        let quorum_set = QuorumSet::new_with_node_ids(2, vec![test_node_id(1), test_node_id(2)]);
+        let analyzer =
+            QuorumAnalyzer::new(BTreeMap::from_iter(vec![(test_node_id(1), quorum_set)]));
+        let analysis = analyzer.analyze(&BTreeMap::new(), 2);
+
+        assert_eq!(analysis.unknown_nodes, node_set(&[2]));
+        assert!(analysis.minimal_quorums.is_empty());
+        assert!(!analysis.has_quorum_intersection());
+        assert_eq!(analysis.minimal_blocking_sets, vec![NodeSet::new()]);
+    }
+}
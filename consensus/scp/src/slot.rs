@@ -5,7 +5,7 @@
 //! The transactions validated in this slot determine the values to include in
 //! the next block appended to the ledger.
 use crate::{
-    core_types::{Ballot, CombineFn, SlotIndex, ValidityFn, Value},
+    core_types::{Ballot, ClockFn, CombineFn, SlotIndex, ValidityFn, Value},
     msg::*,
     predicates::{
         BallotRangePredicate, BallotSetPredicate, FuncPredicate, Predicate, ValueSetPredicate,
@@ -165,6 +165,9 @@ pub struct Slot<V: Value, ValidationError: Display> {
     /// This parameter sets the base interval for ballot timeout.
     /// SCP suggests this should be one second.
     pub base_ballot_interval: Duration,
+
+    /// Source of the current time for timers.
+    pub(crate) clock: ClockFn,
 }
 
 /// Metrics and information about a given slot.
@@ -220,7 +223,7 @@ impl<V: Value, ValidationError: Display> ScpSlot<V> for Slot<V, ValidationError>
 
         // Nomination round timeout.
         if self.next_nominate_round_at.is_some()
-            && Instant::now() > self.next_nominate_round_at.unwrap()
+            && (self.clock)() > self.next_nominate_round_at.unwrap()
         {
             timeout_occurred = true;
             // Canceling is required since schedule_next_nomination_round will not schedule
@@ -243,7 +246,7 @@ impl<V: Value, ValidationError: Display> ScpSlot<V> for Slot<V, ValidationError>
         }
 
         // Ballot timeout.
-        if self.next_ballot_at.is_some() && Instant::now() > self.next_ballot_at.unwrap() {
+        if self.next_ballot_at.is_some() && (self.clock)() > self.next_ballot_at.unwrap() {
             log::debug!(
                 self.logger,
                 "Ballot {} timed out in {:?} phase",
@@ -439,6 +442,7 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
             logger: logger.new(o!("mc.scp.slot" => slot_index)),
             base_round_interval: Duration::from_millis(1000),
             base_ballot_interval: Duration::from_millis(1000),
+            clock: Arc::new(Instant::now),
         };
 
         let max_priority_peer = slot.find_max_priority_peer(slot.nominate_round);
@@ -547,7 +551,7 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
     fn schedule_next_nomination_round(&mut self) {
         if self.next_nominate_round_at.is_none() {
             self.next_nominate_round_at =
-                Some(Instant::now() + self.base_round_interval * self.nominate_round);
+                Some((self.clock)() + self.base_round_interval * self.nominate_round);
         }
     }
 
@@ -1196,7 +1200,7 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
 
             if !quorum_ids.is_empty() {
                 self.next_ballot_at =
-                    Some(Instant::now() + self.base_ballot_interval * self.B.N.saturating_add(1));
+                    Some((self.clock)() + self.base_ballot_interval * self.B.N.saturating_add(1));
             }
         }
     }
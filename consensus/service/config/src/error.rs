@@ -2,9 +2,11 @@
 
 //! Configuration error data type
 
+use crate::rate_limits::RateLimit;
 use displaydoc::Display;
 use mc_common::ResponderId;
 use mc_consensus_enclave_api::{FeeMapError, GovernorsMapError};
+use mc_consensus_scp::QuorumSet;
 use mc_crypto_keys::SignatureError;
 use mc_transaction_core::TokenId;
 use mc_util_uri::UriConversionError;
@@ -85,6 +87,18 @@ pub enum Error {
 
     /// Signature error: {0}
     Signature(SignatureError),
+
+    /// Invalid rate limit: {0:?}
+    InvalidRateLimit(RateLimit),
+
+    /// Invalid quorum set: {0:?}
+    InvalidQuorumSet(QuorumSet<ResponderId>),
+
+    /// Unknown responder id {0} in quorum set
+    UnknownQuorumSetMember(ResponderId),
+
+    /// Node id mismatch for {0}
+    NodeIdMismatch(ResponderId),
 }
 
 impl From<IoError> for Error {
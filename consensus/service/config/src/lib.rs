@@ -5,9 +5,15 @@
 
 mod error;
 mod network;
+mod rate_limits;
 mod tokens;
 
-pub use crate::{error::Error, network::NetworkConfig, tokens::TokensConfig};
+pub use crate::{
+    error::Error,
+    network::NetworkConfig,
+    rate_limits::{ClientRateLimitsConfig, EndpointRateLimits, RateLimit, RateLimitedEndpoint},
+    tokens::TokensConfig,
+};
 
 use clap::Parser;
 use mc_attest_core::ProviderId;
@@ -114,6 +120,29 @@ pub struct Config {
     /// The configured block version
     #[clap(long, default_value = "0", parse(try_from_str = parse_block_version), env = "MC_BLOCK_VERSION")]
     pub block_version: BlockVersion,
+
+    /// Maximum number of well-formed transactions to cache. When full, a new
+    /// transaction is only accepted if it pays a higher fee than the cheapest
+    /// cached transaction, which is then evicted.
+    #[clap(long, default_value = "100000", env = "MC_TX_CACHE_CAPACITY")]
+    pub tx_cache_capacity: usize,
+
+    /// If set, the network and tokens configuration files are checked for
+    /// changes this often (in seconds), and reloaded when they change.
+    #[clap(long, parse(try_from_str = parse_duration_in_seconds), env = "MC_CONFIG_RELOAD_INTERVAL")]
+    pub config_reload_interval: Option<Duration>,
+
+    /// Run as an observer: follow consensus and apply the blocks formed by
+    /// peers, without voting or accepting transactions from clients. An
+    /// observer should not be part of any node's quorum set.
+    #[clap(long, env = "MC_OBSERVER")]
+    pub observer: bool,
+
+    /// Maximum number of transactions whose lifecycle events (received,
+    /// relayed, nominated, externalized...) are kept for the admin API. The
+    /// least recently updated transactions are forgotten first.
+    #[clap(long, default_value = "100000", env = "MC_TX_TRACE_CAPACITY")]
+    pub tx_trace_capacity: usize,
 }
 
 impl Config {
@@ -128,25 +157,37 @@ impl Config {
     /// Get the network configuration by loading the network.toml/json file.
     /// This will panic if the configuration is invalid.
     pub fn network(&self) -> NetworkConfig {
-        NetworkConfig::load_from_path(&self.network_path, &self.peer_responder_id).unwrap_or_else(
-            |_| {
-                panic!(
-                    "Failed loading network configuration from {:?}",
-                    self.network_path,
-                )
-            },
-        )
+        self.try_network().unwrap_or_else(|_| {
+            panic!(
+                "Failed loading network configuration from {:?}",
+                self.network_path,
+            )
+        })
+    }
+
+    /// Get the network configuration by loading the network.toml/json file.
+    pub fn try_network(&self) -> Result<NetworkConfig, Error> {
+        NetworkConfig::load_from_path(&self.network_path, &self.peer_responder_id)
     }
 
     /// Get the tokens configuration from a file, if provided, or the default
     /// configuration.
     pub fn tokens(&self) -> TokensConfig {
+        self.try_tokens().unwrap_or_else(|_| {
+            panic!(
+                "failed loading tokens configuration from {:?}",
+                self.tokens_path
+            )
+        })
+    }
+
+    /// Get the tokens configuration from a file, if provided, or the default
+    /// configuration.
+    pub fn try_tokens(&self) -> Result<TokensConfig, Error> {
         if let Some(tokens_path) = &self.tokens_path {
-            TokensConfig::load_from_path(tokens_path).unwrap_or_else(|_| {
-                panic!("failed loading tokens configuration from {:?}", tokens_path)
-            })
+            TokensConfig::load_from_path(tokens_path)
         } else {
-            TokensConfig::default()
+            Ok(TokensConfig::default())
         }
     }
 }
@@ -200,6 +241,10 @@ mod tests {
             client_auth_token_max_lifetime: Duration::from_secs(60),
             tokens_path: None,
             block_version: BlockVersion::ZERO,
+            tx_cache_capacity: 100000,
+            config_reload_interval: None,
+            observer: false,
+            tx_trace_capacity: 100000,
         };
 
         assert_eq!(
@@ -267,6 +312,10 @@ mod tests {
             client_auth_token_max_lifetime: Duration::from_secs(60),
             tokens_path: None,
             block_version: BlockVersion::ZERO,
+            tx_cache_capacity: 100000,
+            config_reload_interval: None,
+            observer: false,
+            tx_trace_capacity: 100000,
         };
 
         assert_eq!(
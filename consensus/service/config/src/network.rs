@@ -2,7 +2,7 @@
 
 //! Consensus network configuration.
 
-use crate::error::Error;
+use crate::{error::Error, rate_limits::ClientRateLimitsConfig};
 use mc_common::{HashMap, HashSet, NodeID, ResponderId};
 use mc_consensus_scp::{QuorumSet, QuorumSetMember};
 use mc_util_uri::{ConnectionUri, ConsensusPeerUri as PeerUri};
@@ -23,6 +23,10 @@ pub struct NetworkConfig {
 
     /// Optional list of peers we are aware of.
     pub known_peers: Option<Vec<PeerUri>>,
+
+    /// Per-client rate limits for the client API.
+    #[serde(default)]
+    pub client_rate_limits: ClientRateLimitsConfig,
 }
 
 impl NetworkConfig {
@@ -73,45 +77,41 @@ impl NetworkConfig {
             return Err(Error::MissingTxSourceUrls);
         }
 
+        network.client_rate_limits.validate()?;
+
         // Success.
         Ok(network)
     }
 
     /// Construct a quorum set from the configuration.
+    /// This will panic if the quorum set is invalid.
     pub fn quorum_set(&self) -> QuorumSet {
+        self.try_quorum_set()
+            .unwrap_or_else(|err| panic!("Failed constructing quorum set: {}", err))
+    }
+
+    /// Construct a quorum set from the configuration.
+    pub fn try_quorum_set(&self) -> Result<QuorumSet, Error> {
         if !self.quorum_set.is_valid() {
-            panic!("invalid quorum set: {:?}", self.quorum_set);
+            return Err(Error::InvalidQuorumSet(self.quorum_set.clone()));
         }
 
-        let mut peer_map: HashMap<ResponderId, NodeID> = self
+        let mut peer_map: HashMap<ResponderId, NodeID> = HashMap::default();
+        for uri in self
             .broadcast_peers
             .iter()
-            .cloned()
-            .map(|uri| {
-                (
-                    uri.responder_id().unwrap_or_else(|e| {
-                        panic!("unable to get responder_id for {}: {:?}", uri, e)
-                    }),
-                    uri.node_id()
-                        .unwrap_or_else(|e| panic!("unable to get node_id for {}: {:?}", uri, e)),
-                )
-            })
-            .collect();
-
-        if let Some(known_peers) = self.known_peers.as_ref() {
-            for uri in known_peers.iter() {
-                let responder_id = uri
-                    .responder_id()
-                    .unwrap_or_else(|e| panic!("unable to get responder_id for {}: {:?}", uri, e));
-                let node_id = uri
-                    .node_id()
-                    .unwrap_or_else(|e| panic!("unable to get node_id for {}: {:?}", uri, e));
-                if peer_map.get(&responder_id).unwrap_or(&node_id) != &node_id {
-                    panic!("node id mismatch for {}", responder_id);
-                } else {
-                    peer_map.insert(responder_id, node_id);
-                }
+            .chain(self.known_peers.iter().flatten())
+        {
+            let responder_id = uri
+                .responder_id()
+                .map_err(|err| Error::UriConversion(uri.to_string(), err))?;
+            let node_id = uri
+                .node_id()
+                .map_err(|err| Error::UriConversion(uri.to_string(), err))?;
+            if peer_map.get(&responder_id).unwrap_or(&node_id) != &node_id {
+                return Err(Error::NodeIdMismatch(responder_id));
             }
+            peer_map.insert(responder_id, node_id);
         }
 
         Self::resolve_quorum_set(&self.quorum_set, &peer_map)
@@ -127,25 +127,23 @@ impl NetworkConfig {
     fn resolve_quorum_set(
         src: &QuorumSet<ResponderId>,
         peer_map: &HashMap<ResponderId, NodeID>,
-    ) -> QuorumSet<NodeID> {
+    ) -> Result<QuorumSet<NodeID>, Error> {
         let mut new_members = Vec::with_capacity(src.members.len());
         for member in src.members.iter() {
             let new_member = match member {
                 QuorumSetMember::Node(responder_id) => QuorumSetMember::Node(
                     peer_map
                         .get(responder_id)
-                        .unwrap_or_else(|| {
-                            panic!("Unknown responder_id {} in quorum set", responder_id)
-                        })
+                        .ok_or_else(|| Error::UnknownQuorumSetMember(responder_id.clone()))?
                         .clone(),
                 ),
                 QuorumSetMember::InnerSet(qs_config) => {
-                    QuorumSetMember::InnerSet(Self::resolve_quorum_set(qs_config, peer_map))
+                    QuorumSetMember::InnerSet(Self::resolve_quorum_set(qs_config, peer_map)?)
                 }
             };
             new_members.push(new_member);
         }
-        QuorumSet::new(src.threshold, new_members)
+        Ok(QuorumSet::new(src.threshold, new_members))
     }
 }
 
@@ -183,6 +181,7 @@ mod tests {
             assert_eq!(network.quorum_set.members.len(), 0);
             assert_eq!(network.broadcast_peers.len(), 0);
             assert!(network.known_peers.is_none());
+            assert_eq!(network.client_rate_limits, Default::default());
         }
 
         // Real world configuration.
@@ -288,4 +287,36 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_try_quorum_set_errors() {
+        let input_toml: &str = r#"
+            broadcast_peers = [
+                "insecure-mcp://0.0.0.0:8082?consensus-msg-key=MCowBQYDK2VwAyEA_ii3rCch5qhMbLZ2vVgpQr1iTrq1BBN2-i0mMPuAJhQ=",
+            ]
+            tx_source_urls = ["file:///tmp/dump"]
+            quorum_set = { threshold = 1, members = [
+                { type = "Node", args = "0.0.0.0:8083" },
+            ] }
+        "#;
+        let network: NetworkConfig = toml::from_str(input_toml).expect("failed parsing toml");
+        assert!(matches!(
+            network.try_quorum_set(),
+            Err(Error::UnknownQuorumSetMember(responder_id))
+                if responder_id == ResponderId::from_str("0.0.0.0:8083").unwrap()
+        ));
+
+        let input_toml: &str = r#"
+            broadcast_peers = []
+            tx_source_urls = []
+            quorum_set = { threshold = 2, members = [
+                { type = "Node", args = "0.0.0.0:8082" },
+            ] }
+        "#;
+        let network: NetworkConfig = toml::from_str(input_toml).expect("failed parsing toml");
+        assert!(matches!(
+            network.try_quorum_set(),
+            Err(Error::InvalidQuorumSet(_))
+        ));
+    }
 }
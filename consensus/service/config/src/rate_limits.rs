@@ -0,0 +1,209 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Per-client rate limits for the client-facing API.
+
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A client API endpoint that is subject to rate limits.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum RateLimitedEndpoint {
+    /// `ConsensusClientApi::client_tx_propose`
+    ClientTxPropose,
+
+    /// `ConsensusClientApi::propose_mint_tx`
+    ProposeMintTx,
+
+    /// `BlockchainApi::get_blocks`
+    GetBlocks,
+}
+
+/// Limits applied to a single client, for a single endpoint.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct RateLimit {
+    /// Number of requests a client may make per minute, on average.
+    pub requests_per_minute: u32,
+
+    /// Number of requests a client may make at once after being idle. Defaults
+    /// to `requests_per_minute`.
+    #[serde(default)]
+    pub burst: Option<u32>,
+
+    /// Maximum number of requests a client may have in flight at once.
+    /// Unlimited if not set.
+    #[serde(default)]
+    pub max_concurrent: Option<u32>,
+}
+
+impl RateLimit {
+    /// Number of requests a client may make at once after being idle.
+    pub fn burst(&self) -> u32 {
+        self.burst.unwrap_or(self.requests_per_minute)
+    }
+
+    /// Check if the limit is valid.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.requests_per_minute == 0 || self.burst() == 0 || self.max_concurrent == Some(0) {
+            return Err(Error::InvalidRateLimit(*self));
+        }
+        Ok(())
+    }
+}
+
+/// Limits for each rate-limited endpoint. Endpoints without a limit are not
+/// limited.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(default)]
+pub struct EndpointRateLimits {
+    /// Limit for `client_tx_propose`.
+    pub client_tx_propose: Option<RateLimit>,
+
+    /// Limit for `propose_mint_tx`.
+    pub propose_mint_tx: Option<RateLimit>,
+
+    /// Limit for `get_blocks`.
+    pub get_blocks: Option<RateLimit>,
+}
+
+impl EndpointRateLimits {
+    /// The limit for a given endpoint, if any.
+    pub fn get(&self, endpoint: RateLimitedEndpoint) -> Option<&RateLimit> {
+        match endpoint {
+            RateLimitedEndpoint::ClientTxPropose => self.client_tx_propose.as_ref(),
+            RateLimitedEndpoint::ProposeMintTx => self.propose_mint_tx.as_ref(),
+            RateLimitedEndpoint::GetBlocks => self.get_blocks.as_ref(),
+        }
+    }
+
+    /// Check if all limits are valid.
+    pub fn validate(&self) -> Result<(), Error> {
+        for limit in [
+            &self.client_tx_propose,
+            &self.propose_mint_tx,
+            &self.get_blocks,
+        ]
+        .iter()
+        .filter_map(|limit| limit.as_ref())
+        {
+            limit.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Per-client rate limits.
+///
+/// Clients are identified by their authenticated user id, or by their IP
+/// address when client authentication is not enabled.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(default)]
+pub struct ClientRateLimitsConfig {
+    /// Limits applied to every client.
+    pub default: EndpointRateLimits,
+
+    /// Limits for specific clients, keyed by user id or IP address. A limit
+    /// set here replaces the default limit for the same endpoint.
+    pub overrides: BTreeMap<String, EndpointRateLimits>,
+}
+
+impl ClientRateLimitsConfig {
+    /// The limit applied to a given client, for a given endpoint.
+    pub fn limit(&self, client: &str, endpoint: RateLimitedEndpoint) -> Option<&RateLimit> {
+        self.overrides
+            .get(client)
+            .and_then(|limits| limits.get(endpoint))
+            .or_else(|| self.default.get(endpoint))
+    }
+
+    /// Check if all limits are valid.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.default.validate()?;
+        for limits in self.overrides.values() {
+            limits.validate()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_rate_limits_parsing() {
+        let input_toml: &str = r#"
+            [default.client_tx_propose]
+            requests_per_minute = 60
+            max_concurrent = 2
+
+            [default.get_blocks]
+            requests_per_minute = 600
+            burst = 100
+
+            [overrides."wallet-service".client_tx_propose]
+            requests_per_minute = 6000
+
+            [overrides."10.0.0.1".get_blocks]
+            requests_per_minute = 10
+        "#;
+        let config: ClientRateLimitsConfig =
+            toml::from_str(input_toml).expect("failed parsing toml");
+        assert!(config.validate().is_ok());
+
+        let tx_propose = RateLimit {
+            requests_per_minute: 60,
+            burst: None,
+            max_concurrent: Some(2),
+        };
+        assert_eq!(
+            config.limit("anyone", RateLimitedEndpoint::ClientTxPropose),
+            Some(&tx_propose)
+        );
+        assert_eq!(tx_propose.burst(), 60);
+        assert_eq!(
+            config.limit("anyone", RateLimitedEndpoint::ProposeMintTx),
+            None
+        );
+
+        // Overrides replace the default for the endpoints they configure only.
+        assert_eq!(
+            config
+                .limit("wallet-service", RateLimitedEndpoint::ClientTxPropose)
+                .unwrap()
+                .requests_per_minute,
+            6000
+        );
+        assert_eq!(
+            config
+                .limit("wallet-service", RateLimitedEndpoint::GetBlocks)
+                .unwrap()
+                .burst(),
+            100
+        );
+        assert_eq!(
+            config
+                .limit("10.0.0.1", RateLimitedEndpoint::GetBlocks)
+                .unwrap()
+                .requests_per_minute,
+            10
+        );
+    }
+
+    #[test]
+    fn test_client_rate_limits_validate() {
+        let mut config = ClientRateLimitsConfig::default();
+        assert!(config.validate().is_ok());
+
+        let limits = EndpointRateLimits {
+            propose_mint_tx: Some(RateLimit {
+                requests_per_minute: 10,
+                burst: Some(0),
+                max_concurrent: None,
+            }),
+            ..Default::default()
+        };
+        config.overrides.insert("user".to_string(), limits);
+        assert!(matches!(config.validate(), Err(Error::InvalidRateLimit(_))));
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Serves consensus-specific admin gRPC requests.
+
+use crate::{api::grpc_error::ConsensusGrpcError, tx_trace::TxTraceLog};
+use grpcio::{RpcContext, UnarySink};
+use mc_common::logger::Logger;
+use mc_consensus_api::{
+    consensus_admin::{GetTxTraceRequest, GetTxTraceResponse, TxEvent},
+    consensus_admin_grpc::ConsensusAdminApi,
+};
+use mc_transaction_core::tx::TxHash;
+use mc_util_grpc::{rpc_logger, send_result};
+use mc_util_metrics::SVC_COUNTERS;
+use protobuf::RepeatedField;
+use std::{convert::TryFrom, sync::Arc};
+
+#[derive(Clone)]
+pub struct AdminApiService {
+    /// Lifecycle events of the transactions seen by this node.
+    tx_trace_log: Arc<TxTraceLog>,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl AdminApiService {
+    pub fn new(tx_trace_log: Arc<TxTraceLog>, logger: Logger) -> Self {
+        Self {
+            tx_trace_log,
+            logger,
+        }
+    }
+
+    fn get_tx_trace_impl(
+        &self,
+        request: GetTxTraceRequest,
+    ) -> Result<GetTxTraceResponse, ConsensusGrpcError> {
+        let tx_hash = TxHash::try_from(request.get_tx_hash()).map_err(|_| {
+            ConsensusGrpcError::InvalidArgument("tx_hash must be 32 bytes".to_owned())
+        })?;
+
+        let events = self
+            .tx_trace_log
+            .get(&tx_hash)
+            .iter()
+            .map(TxEvent::from)
+            .collect();
+
+        let mut response = GetTxTraceResponse::new();
+        response.set_events(RepeatedField::from_vec(events));
+        Ok(response)
+    }
+}
+
+impl ConsensusAdminApi for AdminApiService {
+    fn get_tx_trace(
+        &mut self,
+        ctx: RpcContext,
+        request: GetTxTraceRequest,
+        sink: UnarySink<GetTxTraceResponse>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        let result = self.get_tx_trace_impl(request).map_err(Into::into);
+
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            send_result(ctx, sink, result, logger)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx_trace::TxEventKind;
+    use grpcio::{ChannelBuilder, Environment, Error as GrpcError, RpcStatusCode, ServerBuilder};
+    use mc_common::logger::test_with_logger;
+    use mc_consensus_api::{consensus_admin::TxEventType, consensus_admin_grpc};
+
+    #[test_with_logger]
+    fn test_get_tx_trace(logger: Logger) {
+        let tx_trace_log = Arc::new(TxTraceLog::new(10));
+        let tx_hash = TxHash([7u8; 32]);
+        tx_trace_log.record(&tx_hash, TxEventKind::Received(None));
+        tx_trace_log.record(&tx_hash, TxEventKind::Relayed);
+        tx_trace_log.record(&tx_hash, TxEventKind::Externalized(12));
+
+        let env = Arc::new(Environment::new(1));
+        let service = consensus_admin_grpc::create_consensus_admin_api(AdminApiService::new(
+            tx_trace_log,
+            logger,
+        ));
+        let mut server = ServerBuilder::new(env.clone())
+            .register_service(service)
+            .bind("127.0.0.1", 0)
+            .build()
+            .unwrap();
+        server.start();
+        let (_, port) = server.bind_addrs().next().unwrap();
+        let channel = ChannelBuilder::new(env).connect(&format!("127.0.0.1:{}", port));
+        let client = consensus_admin_grpc::ConsensusAdminApiClient::new(channel);
+
+        let mut request = GetTxTraceRequest::new();
+        request.set_tx_hash(tx_hash.to_vec());
+        let response = client.get_tx_trace(&request).unwrap();
+        let events = response.get_events();
+        assert_eq!(
+            events
+                .iter()
+                .map(|e| e.get_event_type())
+                .collect::<Vec<_>>(),
+            vec![
+                TxEventType::RECEIVED,
+                TxEventType::RELAYED,
+                TxEventType::EXTERNALIZED
+            ]
+        );
+        assert_eq!(events[2].get_block_index(), 12);
+
+        // Unknown transactions have no events.
+        request.set_tx_hash(vec![1u8; 32]);
+        assert!(client
+            .get_tx_trace(&request)
+            .unwrap()
+            .get_events()
+            .is_empty());
+
+        // Malformed hashes are rejected.
+        request.set_tx_hash(vec![1u8; 5]);
+        match client.get_tx_trace(&request) {
+            Err(GrpcError::RpcFailure(rpc_status)) => {
+                assert_eq!(rpc_status.code(), RpcStatusCode::INVALID_ARGUMENT);
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -9,7 +9,7 @@ use mc_common::{
     logger::{log, Logger},
     HashSet,
 };
-use mc_consensus_enclave::ConsensusEnclave;
+use mc_consensus_enclave_api::ConsensusEnclave;
 use mc_util_grpc::{rpc_logger, rpc_permissions_error, send_result, Authenticator};
 use mc_util_metrics::SVC_COUNTERS;
 use std::sync::{Arc, Mutex};
@@ -2,6 +2,7 @@
 
 //! Serves blockchain-related API requests.
 
+use crate::{api::rate_limiter::ClientRateLimiter, validators::get_recent_block_loads};
 use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
 use mc_common::logger::{log, Logger};
 use mc_consensus_api::{
@@ -10,7 +11,8 @@ use mc_consensus_api::{
     consensus_common_grpc::BlockchainApi,
     empty::Empty,
 };
-use mc_consensus_enclave::FeeMap;
+use mc_consensus_enclave_api::{fee_schedule, FeeMap};
+use mc_consensus_service_config::RateLimitedEndpoint;
 use mc_ledger_db::Ledger;
 use mc_transaction_core::{tokens::Mob, BlockVersion, Token};
 use mc_util_grpc::{rpc_logger, send_result, Authenticator};
@@ -26,6 +28,9 @@ pub struct BlockchainApiService<L: Ledger + Clone> {
     /// GRPC request authenticator.
     authenticator: Arc<dyn Authenticator + Send + Sync>,
 
+    /// Per-client rate limits.
+    rate_limiter: Arc<ClientRateLimiter>,
+
     /// Maximal number of results to return in API calls that return multiple
     /// results.
     max_page_size: u16,
@@ -44,6 +49,7 @@ impl<L: Ledger + Clone> BlockchainApiService<L> {
     pub fn new(
         ledger: L,
         authenticator: Arc<dyn Authenticator + Send + Sync>,
+        rate_limiter: Arc<ClientRateLimiter>,
         fee_map: FeeMap,
         network_block_version: BlockVersion,
         logger: Logger,
@@ -51,6 +57,7 @@ impl<L: Ledger + Clone> BlockchainApiService<L> {
         BlockchainApiService {
             ledger,
             authenticator,
+            rate_limiter,
             max_page_size: 2000,
             fee_map,
             network_block_version,
@@ -64,21 +71,36 @@ impl<L: Ledger + Clone> BlockchainApiService<L> {
         self.max_page_size = max_page_size;
     }
 
-    /// Returns information about the last block.
+    /// Returns information about the last block, and the minimum fees of the
+    /// next one.
     fn get_last_block_info_helper(&mut self) -> Result<LastBlockInfoResponse, mc_ledger_db::Error> {
         let num_blocks = self.ledger.num_blocks()?;
+        let minimum_priority = if self
+            .network_block_version
+            .dynamic_minimum_fees_are_supported()
+        {
+            let recent_block_loads = get_recent_block_loads(&self.ledger, num_blocks)?;
+            fee_schedule::minimum_priority(self.network_block_version, &recent_block_loads)
+        } else {
+            fee_schedule::BASE_MINIMUM_PRIORITY
+        };
+
         let mut resp = LastBlockInfoResponse::new();
         resp.set_index(num_blocks - 1);
-        resp.set_mob_minimum_fee(
+        resp.set_mob_minimum_fee(fee_schedule::minimum_fee(
             self.fee_map
                 .get_fee_for_token(&Mob::ID)
                 .expect("should always have a fee for MOB"),
-        );
-        resp.set_minimum_fees(HashMap::from_iter(
-            self.fee_map
-                .iter()
-                .map(|(token_id, fee)| (**token_id, *fee)),
+            minimum_priority,
         ));
+        resp.set_minimum_fees(HashMap::from_iter(self.fee_map.iter().map(
+            |(token_id, fee)| {
+                (
+                    **token_id,
+                    fee_schedule::minimum_fee(*fee, minimum_priority),
+                )
+            },
+        )));
         resp.set_network_block_version(*self.network_block_version);
 
         Ok(resp)
@@ -160,9 +182,19 @@ impl<L: Ledger + Clone> BlockchainApi for BlockchainApiService<L> {
         let _timer = SVC_COUNTERS.req(&ctx);
 
         mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
-            if let Err(err) = self.authenticator.authenticate_rpc(&ctx) {
-                return send_result(ctx, sink, err.into(), logger);
-            }
+            let user_id = match self.authenticator.authenticate_rpc(&ctx) {
+                Ok(user_id) => user_id,
+                Err(err) => return send_result(ctx, sink, err.into(), logger),
+            };
+
+            let _rate_limit_guard =
+                match self
+                    .rate_limiter
+                    .check_rpc(&ctx, &user_id, RateLimitedEndpoint::GetBlocks)
+                {
+                    Ok(guard) => guard,
+                    Err(err) => return send_result(ctx, sink, Err(err.into()), logger),
+                };
 
             log::trace!(
                 logger,
@@ -247,8 +279,14 @@ mod tests {
             ledger_db.num_blocks().unwrap() - 1
         );
 
-        let mut blockchain_api_service =
-            BlockchainApiService::new(ledger_db, authenticator, fee_map, BlockVersion::MAX, logger);
+        let mut blockchain_api_service = BlockchainApiService::new(
+            ledger_db,
+            authenticator,
+            Default::default(),
+            fee_map,
+            BlockVersion::MAX,
+            logger,
+        );
 
         let block_response = blockchain_api_service.get_last_block_info_helper().unwrap();
         assert_eq!(block_response, expected_response);
@@ -268,6 +306,7 @@ mod tests {
         let blockchain_api_service = BlockchainApiService::new(
             ledger_db,
             authenticator,
+            Default::default(),
             FeeMap::default(),
             BlockVersion::MAX,
             logger,
@@ -311,6 +350,7 @@ mod tests {
         let mut blockchain_api_service = BlockchainApiService::new(
             ledger_db,
             authenticator,
+            Default::default(),
             FeeMap::default(),
             BlockVersion::MAX,
             logger,
@@ -360,6 +400,7 @@ mod tests {
         let mut blockchain_api_service = BlockchainApiService::new(
             ledger_db,
             authenticator,
+            Default::default(),
             FeeMap::default(),
             BlockVersion::MAX,
             logger,
@@ -397,6 +438,7 @@ mod tests {
         let mut blockchain_api_service = BlockchainApiService::new(
             ledger_db,
             authenticator,
+            Default::default(),
             FeeMap::default(),
             BlockVersion::MAX,
             logger,
@@ -426,6 +468,7 @@ mod tests {
         let blockchain_api_service = BlockchainApiService::new(
             ledger_db,
             authenticator,
+            Default::default(),
             FeeMap::default(),
             BlockVersion::MAX,
             logger,
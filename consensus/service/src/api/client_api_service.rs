@@ -3,11 +3,12 @@
 //! Serves client-to-node gRPC requests.
 
 use crate::{
-    api::grpc_error::ConsensusGrpcError,
+    api::{grpc_error::ConsensusGrpcError, rate_limiter::ClientRateLimiter},
     consensus_service::ProposeTxCallback,
     counters,
     mint_tx_manager::MintTxManager,
     tx_manager::{TxManager, TxManagerError},
+    tx_trace::{TxEventKind, TxTraceLog},
 };
 use grpcio::{RpcContext, RpcStatus, UnarySink};
 use mc_attest_api::attest::Message;
@@ -19,8 +20,8 @@ use mc_consensus_api::{
     consensus_config::{ConsensusNodeConfig, TokenConfig},
     empty::Empty,
 };
-use mc_consensus_enclave::ConsensusEnclave;
-use mc_consensus_service_config::Config;
+use mc_consensus_enclave_api::ConsensusEnclave;
+use mc_consensus_service_config::{Config, RateLimitedEndpoint};
 use mc_ledger_db::Ledger;
 use mc_peers::ConsensusValue;
 use mc_transaction_core::mint::{MintConfigTx, MintTx};
@@ -44,6 +45,10 @@ pub struct ClientApiService {
     /// Returns true if this node is able to process proposed transactions.
     is_serving_fn: Arc<(dyn Fn() -> bool + Sync + Send)>,
     authenticator: Arc<dyn Authenticator + Send + Sync>,
+    /// Per-client rate limits.
+    rate_limiter: Arc<ClientRateLimiter>,
+    /// Records what happens to proposed transactions.
+    tx_trace_log: Arc<TxTraceLog>,
     logger: Logger,
 }
 
@@ -57,6 +62,8 @@ impl ClientApiService {
         mint_tx_manager: Arc<dyn MintTxManager + Send + Sync>,
         is_serving_fn: Arc<(dyn Fn() -> bool + Sync + Send)>,
         authenticator: Arc<dyn Authenticator + Send + Sync>,
+        rate_limiter: Arc<ClientRateLimiter>,
+        tx_trace_log: Arc<TxTraceLog>,
         logger: Logger,
     ) -> Self {
         Self {
@@ -68,6 +75,8 @@ impl ClientApiService {
             propose_tx_callback: scp_client_value_sender,
             is_serving_fn,
             authenticator,
+            rate_limiter,
+            tx_trace_log,
             logger,
         }
     }
@@ -85,20 +94,34 @@ impl ClientApiService {
         let tx_context = self.enclave.client_tx_propose(msg.into())?;
         let mut response = ProposeTxResponse::new();
 
-        // Cache the transaction. This performs the well-formedness checks.
-        let tx_hash = self.tx_manager.insert(tx_context).map_err(|err| {
-            if let TxManagerError::TransactionValidation(cause) = &err {
-                counters::TX_VALIDATION_ERROR_COUNTER.inc(&format!("{:?}", cause));
-                let result = ProposeTxResult::from(cause.clone());
-                response.set_result(result);
-            }
+        let tx_hash = tx_context.tx_hash;
+        self.tx_trace_log
+            .record(&tx_hash, TxEventKind::Received(None));
+        let tx_trace_log = &self.tx_trace_log;
+        let record_rejection = |err: TxManagerError| {
+            tx_trace_log.record(&tx_hash, TxEventKind::Rejected(err.to_string()));
             err
-        })?;
+        };
+
+        // Cache the transaction. This performs the well-formedness checks.
+        self.tx_manager
+            .insert(tx_context)
+            .map_err(|err| {
+                if let TxManagerError::TransactionValidation(cause) = &err {
+                    counters::TX_VALIDATION_ERROR_COUNTER.inc(&format!("{:?}", cause));
+                    let result = ProposeTxResult::from(cause.clone());
+                    response.set_result(result);
+                }
+                err
+            })
+            .map_err(record_rejection)?;
 
         // Validate the transaction.
         // This is done here as a courtesy to give clients immediate feedback about the
         // transaction.
-        self.tx_manager.validate(&tx_hash)?;
+        self.tx_manager
+            .validate(&tx_hash)
+            .map_err(record_rejection)?;
 
         // The transaction can be considered by the network.
         (*self.propose_tx_callback)(ConsensusValue::TxHash(tx_hash), None, None);
@@ -195,6 +218,7 @@ impl ClientApiService {
         response.set_block_signing_key((&self.enclave.get_signer()?).into());
         response.set_block_version(*self.config.block_version);
         response.set_scp_message_signing_key((&self.config.msg_signer_key.public_key()).into());
+        response.set_quorum_set((&self.config.network().quorum_set).into());
 
         Ok(response)
     }
@@ -209,9 +233,27 @@ impl ConsensusClientApi for ClientApiService {
     ) {
         let _timer = SVC_COUNTERS.req(&ctx);
 
-        if let Err(err) = self.authenticator.authenticate_rpc(&ctx) {
-            return send_result(ctx, sink, err.into(), &self.logger);
-        }
+        let user_id = match self.authenticator.authenticate_rpc(&ctx) {
+            Ok(user_id) => user_id,
+            Err(err) => return send_result(ctx, sink, err.into(), &self.logger),
+        };
+
+        let rate_limit =
+            self.rate_limiter
+                .check_rpc(&ctx, &user_id, RateLimitedEndpoint::ClientTxPropose);
+        let _rate_limit_guard = match rate_limit {
+            Ok(guard) => guard,
+            Err(err) => {
+                // This client has exceeded its limits.
+                let result: Result<ProposeTxResponse, RpcStatus> =
+                    if let Err(e) = self.enclave.client_discard_message(msg.into()) {
+                        ConsensusGrpcError::Enclave(e).into()
+                    } else {
+                        ConsensusGrpcError::RateLimited(err).into()
+                    };
+                return send_result(ctx, sink, result, &self.logger);
+            }
+        };
 
         let mut result: Result<ProposeTxResponse, RpcStatus> =
             if counters::CUR_NUM_PENDING_VALUES.get() >= PENDING_LIMIT {
@@ -287,9 +329,18 @@ impl ConsensusClientApi for ClientApiService {
     ) {
         let _timer = SVC_COUNTERS.req(&ctx);
 
-        if let Err(err) = self.authenticator.authenticate_rpc(&ctx) {
-            return send_result(ctx, sink, err.into(), &self.logger);
-        }
+        let user_id = match self.authenticator.authenticate_rpc(&ctx) {
+            Ok(user_id) => user_id,
+            Err(err) => return send_result(ctx, sink, err.into(), &self.logger),
+        };
+
+        let rate_limit =
+            self.rate_limiter
+                .check_rpc(&ctx, &user_id, RateLimitedEndpoint::ProposeMintTx);
+        let _rate_limit_guard = match rate_limit {
+            Ok(guard) => guard,
+            Err(err) => return send_result(ctx, sink, Err(err.into()), &self.logger),
+        };
 
         let mut result: Result<ProposeMintTxResponse, RpcStatus> =
             if counters::CUR_NUM_PENDING_VALUES.get() >= PENDING_LIMIT {
@@ -349,9 +400,11 @@ mod client_api_tests {
         consensus_client::MintValidationResultCode, consensus_client_grpc,
         consensus_client_grpc::ConsensusClientApiClient, consensus_common::ProposeTxResult,
     };
-    use mc_consensus_enclave::TxContext;
+    use mc_consensus_enclave_api::TxContext;
     use mc_consensus_enclave_mock::MockConsensusEnclave;
-    use mc_consensus_service_config::Config;
+    use mc_consensus_service_config::{
+        ClientRateLimitsConfig, Config, EndpointRateLimits, RateLimit,
+    };
     use mc_crypto_keys::Ed25519Pair;
     use mc_ledger_db::MockLedger;
     use mc_peers::ConsensusValue;
@@ -464,6 +517,8 @@ mod client_api_tests {
             Arc::new(MockMintTxManager::new()),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -538,6 +593,8 @@ mod client_api_tests {
             Arc::new(MockMintTxManager::new()),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -606,6 +663,8 @@ mod client_api_tests {
             Arc::new(MockMintTxManager::new()),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -625,6 +684,64 @@ mod client_api_tests {
         }
     }
 
+    #[test_with_logger]
+    #[serial(counters)]
+    // Should return RpcStatus Unavailable if the tx cache is full of transactions
+    // with a higher priority.
+    fn test_client_tx_propose_tx_mempool_full(logger: Logger) {
+        let mut consensus_enclave = MockConsensusEnclave::new();
+        consensus_enclave
+            .expect_client_tx_propose()
+            .times(1)
+            .return_const(Ok(TxContext::default()));
+
+        let scp_client_value_sender = Arc::new(
+            |_value: ConsensusValue,
+             _node_id: Option<&NodeID>,
+             _responder_id: Option<&ResponderId>| {
+                panic!("A rejected transaction should not be proposed");
+            },
+        );
+
+        let mut tx_manager = MockTxManager::new();
+        tx_manager
+            .expect_insert()
+            .times(1)
+            .return_const(Err(TxManagerError::MempoolFull(10)));
+
+        let is_serving_fn = Arc::new(|| -> bool { true });
+
+        let authenticator = AnonymousAuthenticator::default();
+
+        let instance = ClientApiService::new(
+            get_config(),
+            Arc::new(consensus_enclave),
+            scp_client_value_sender,
+            Arc::new(MockLedger::new()),
+            Arc::new(tx_manager),
+            Arc::new(MockMintTxManager::new()),
+            is_serving_fn,
+            Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
+            logger,
+        );
+
+        // gRPC client and server.
+        let (client, _server) = get_client_server(instance);
+
+        let message = Message::default();
+        match client.client_tx_propose(&message) {
+            Ok(propose_tx_response) => {
+                panic!("Unexpected response {:?}", propose_tx_response);
+            }
+            Err(GrpcError::RpcFailure(rpc_status)) => {
+                assert_eq!(rpc_status.code(), RpcStatusCode::UNAVAILABLE);
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
     #[test_with_logger]
     #[serial(counters)]
     // Should return RpcStatus Unavailable if the node is not serving.
@@ -654,6 +771,8 @@ mod client_api_tests {
             Arc::new(MockMintTxManager::new()),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -672,6 +791,74 @@ mod client_api_tests {
         }
     }
 
+    #[test_with_logger]
+    #[serial(counters)]
+    // Should return RpcStatus ResourceExhausted once an anonymous client exceeds
+    // the limits configured for its address.
+    fn test_client_tx_propose_rate_limited(logger: Logger) {
+        let mut enclave = MockConsensusEnclave::new();
+        enclave
+            .expect_client_discard_message()
+            .times(2)
+            .return_const(Ok(()));
+
+        let is_serving_fn = Arc::new(|| -> bool { false }); // Not serving
+
+        let scp_client_value_sender = Arc::new(
+            |_value: ConsensusValue,
+             _node_id: Option<&NodeID>,
+             _responder_id: Option<&ResponderId>| {},
+        );
+
+        let authenticator = AnonymousAuthenticator::default();
+
+        let mut rate_limits = ClientRateLimitsConfig::default();
+        rate_limits.overrides.insert(
+            "127.0.0.1".to_owned(),
+            EndpointRateLimits {
+                client_tx_propose: Some(RateLimit {
+                    requests_per_minute: 1,
+                    burst: None,
+                    max_concurrent: None,
+                }),
+                ..Default::default()
+            },
+        );
+
+        let instance = ClientApiService::new(
+            get_config(),
+            Arc::new(enclave),
+            scp_client_value_sender,
+            Arc::new(MockLedger::new()),
+            Arc::new(MockTxManager::new()),
+            Arc::new(MockMintTxManager::new()),
+            is_serving_fn,
+            Arc::new(authenticator),
+            Arc::new(ClientRateLimiter::new(rate_limits)),
+            Default::default(),
+            logger,
+        );
+
+        // gRPC client and server.
+        let (client, _server) = get_client_server(instance);
+
+        let message = Message::default();
+        for expected_code in &[
+            RpcStatusCode::UNAVAILABLE,
+            RpcStatusCode::RESOURCE_EXHAUSTED,
+        ] {
+            match client.client_tx_propose(&message) {
+                Ok(propose_tx_response) => {
+                    panic!("Unexpected response {:?}", propose_tx_response);
+                }
+                Err(GrpcError::RpcFailure(rpc_status)) => {
+                    assert_eq!(rpc_status.code(), *expected_code);
+                }
+                Err(e) => panic!("Unexpected error: {:?}", e),
+            }
+        }
+    }
+
     #[test_with_logger]
     #[serial(counters)]
     // Should return RpcStatus Unavailable if the node is over capacity.
@@ -704,6 +891,8 @@ mod client_api_tests {
             Arc::new(MockMintTxManager::new()),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -758,6 +947,8 @@ mod client_api_tests {
             Arc::new(MockMintTxManager::new()),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -820,6 +1011,8 @@ mod client_api_tests {
             Arc::new(mint_tx_manager),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -890,6 +1083,8 @@ mod client_api_tests {
             Arc::new(mint_tx_manager),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -941,6 +1136,8 @@ mod client_api_tests {
             Arc::new(mint_tx_manager),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -994,6 +1191,8 @@ mod client_api_tests {
             Arc::new(mint_tx_manager),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -1056,6 +1255,8 @@ mod client_api_tests {
             Arc::new(mint_tx_manager),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -1116,6 +1317,8 @@ mod client_api_tests {
             Arc::new(mint_tx_manager),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -1196,6 +1399,8 @@ mod client_api_tests {
             Arc::new(mint_tx_manager),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -1252,6 +1457,8 @@ mod client_api_tests {
             Arc::new(mint_tx_manager),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -1310,6 +1517,8 @@ mod client_api_tests {
             Arc::new(mint_tx_manager),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
@@ -1377,6 +1586,8 @@ mod client_api_tests {
             Arc::new(mint_tx_manager),
             is_serving_fn,
             Arc::new(authenticator),
+            Default::default(),
+            Default::default(),
             logger,
         );
 
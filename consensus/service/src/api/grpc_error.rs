@@ -1,6 +1,9 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
-use crate::{mint_tx_manager::MintTxManagerError, tx_manager::TxManagerError};
+use crate::{
+    api::rate_limiter::RateLimitError, mint_tx_manager::MintTxManagerError,
+    tx_manager::TxManagerError,
+};
 use displaydoc::Display;
 use grpcio::{RpcStatus, RpcStatusCode};
 use mc_common::logger::global_log;
@@ -8,7 +11,7 @@ use mc_consensus_api::{
     consensus_client::{MintValidationResult, ProposeMintConfigTxResponse, ProposeMintTxResponse},
     consensus_common::{ProposeTxResponse, ProposeTxResult},
 };
-use mc_consensus_enclave::Error as EnclaveError;
+use mc_consensus_enclave_api::Error as EnclaveError;
 use mc_ledger_db::Error as LedgerError;
 use mc_transaction_core::{mint::MintValidationError, validation::TransactionValidationError};
 
@@ -26,6 +29,9 @@ pub enum ConsensusGrpcError {
     /// Service is currently not serving requests
     NotServing,
 
+    /// Client is rate limited: {0}
+    RateLimited(RateLimitError),
+
     /// Enclave error: `{0}`
     Enclave(EnclaveError),
 
@@ -48,6 +54,12 @@ impl From<RpcStatus> for ConsensusGrpcError {
     }
 }
 
+impl From<RateLimitError> for ConsensusGrpcError {
+    fn from(src: RateLimitError) -> Self {
+        Self::RateLimited(src)
+    }
+}
+
 impl From<LedgerError> for ConsensusGrpcError {
     fn from(src: LedgerError) -> Self {
         Self::Ledger(src)
@@ -81,6 +93,11 @@ impl From<TxManagerError> for ConsensusGrpcError {
             TxManagerError::Enclave(err) => Self::from(err),
             TxManagerError::TransactionValidation(err) => Self::from(err),
             TxManagerError::LedgerDb(err) => Self::from(err),
+            TxManagerError::MempoolFull(_) => Self::OverCapacity,
+            TxManagerError::KeyImageConflict(_) => Self::RpcStatus(RpcStatus::with_message(
+                RpcStatusCode::FAILED_PRECONDITION,
+                format!("{}", src),
+            )),
             _ => Self::Other(format!("tx manager error: {}", src)),
         }
     }
@@ -110,6 +127,7 @@ impl From<ConsensusGrpcError> for RpcStatus {
                 RpcStatusCode::UNAVAILABLE,
                 "Temporarily not serving requests".into(),
             ),
+            ConsensusGrpcError::RateLimited(err) => RpcStatus::from(err),
             ConsensusGrpcError::Enclave(EnclaveError::Attest(err)) => {
                 global_log::error!("Permission denied: {}", err);
                 RpcStatus::with_message(
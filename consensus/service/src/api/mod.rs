@@ -2,14 +2,18 @@
 
 //! gRPC APIs
 
+mod admin_api_service;
 mod attested_api_service;
 mod blockchain_api_service;
 mod client_api_service;
 mod grpc_error;
 mod peer_api_service;
 mod peer_service_error;
+mod rate_limiter;
 
+pub use admin_api_service::AdminApiService;
 pub use attested_api_service::AttestedApiService;
 pub use blockchain_api_service::BlockchainApiService;
 pub use client_api_service::ClientApiService;
 pub use peer_api_service::PeerApiService;
+pub use rate_limiter::ClientRateLimiter;
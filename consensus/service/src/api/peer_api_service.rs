@@ -8,6 +8,7 @@ use crate::{
     consensus_service::{IncomingConsensusMsg, ProposeTxCallback},
     counters,
     tx_manager::{TxManager, TxManagerError},
+    tx_trace::{TxEventKind, TxTraceLog},
 };
 use grpcio::{RpcContext, RpcStatus, UnarySink};
 use mc_attest_api::attest::Message;
@@ -25,7 +26,7 @@ use mc_consensus_api::{
     consensus_peer_grpc::ConsensusPeerApi,
     empty::Empty,
 };
-use mc_consensus_enclave::{ConsensusEnclave, Error};
+use mc_consensus_enclave_api::{ConsensusEnclave, Error};
 use mc_ledger_db::Ledger;
 use mc_peers::{ConsensusValue, TxProposeAAD};
 use mc_transaction_core::tx::TxHash;
@@ -44,6 +45,11 @@ use std::{
 // node, used to implement the `fetch_latest_msg` RPC call.
 type FetchLatestMsgFn = Arc<dyn Fn() -> Option<mc_peers::ConsensusMsg> + Sync + Send>;
 
+// Callback method for checking if a responder ID belongs to one of the local
+// node's peers. The set of peers can change when the network configuration is
+// reloaded.
+type IsKnownPeerFn = Arc<dyn Fn(&ResponderId) -> bool + Sync + Send>;
+
 #[derive(Clone)]
 pub struct PeerApiService {
     /// Enclave instance.
@@ -70,7 +76,10 @@ pub struct PeerApiService {
     /// requests to. That is necessary for resolving TxHashes into Txs. If
     /// we received a consensus message from a peer not on this list, we
     /// won't be able to reach out to it to ask for the transaction contents.
-    known_responder_ids: Vec<ResponderId>,
+    is_known_peer_fn: IsKnownPeerFn,
+
+    /// Records what happens to proposed transactions.
+    tx_trace_log: Arc<TxTraceLog>,
 
     /// Logger.
     logger: Logger,
@@ -87,8 +96,9 @@ impl PeerApiService {
     ///   message from a peer.
     /// * `scp_client_value_sender` - Callback for proposed transactions.
     /// * `fetch_latest_msg_fn` - Returns highest message emitted by this node.
-    /// * `known_responder_ids` - Messages from peers not on this "whitelist"
-    ///   are ignored.
+    /// * `is_known_peer_fn` - Messages from peers not on this "whitelist" are
+    ///   ignored.
+    /// * `tx_trace_log` - Records what happens to proposed transactions.
     /// * `logger` - Logger.
     pub fn new(
         consensus_enclave: Arc<dyn ConsensusEnclave + Send + Sync>,
@@ -97,7 +107,8 @@ impl PeerApiService {
         incoming_consensus_msgs_sender: BackgroundWorkQueueSenderFn<IncomingConsensusMsg>,
         scp_client_value_sender: ProposeTxCallback,
         fetch_latest_msg_fn: FetchLatestMsgFn,
-        known_responder_ids: Vec<ResponderId>,
+        is_known_peer_fn: IsKnownPeerFn,
+        tx_trace_log: Arc<TxTraceLog>,
         logger: Logger,
     ) -> Self {
         Self {
@@ -107,7 +118,8 @@ impl PeerApiService {
             scp_client_value_sender,
             ledger,
             fetch_latest_msg_fn,
-            known_responder_ids,
+            is_known_peer_fn,
+            tx_trace_log,
             logger,
         }
     }
@@ -150,8 +162,16 @@ impl PeerApiService {
         // Handle each transaction.
         for tx_context in tx_contexts {
             let tx_hash = tx_context.tx_hash;
+            self.tx_trace_log
+                .record(&tx_hash, TxEventKind::Received(relayed_by.clone()));
+
+            let result = self.tx_manager.insert(tx_context);
+            if let Err(err) = &result {
+                self.tx_trace_log
+                    .record(&tx_hash, TxEventKind::Rejected(err.to_string()));
+            }
 
-            match self.tx_manager.insert(tx_context) {
+            match result {
                 Ok(tx_hash) => {
                     // Submit for consideration in next SCP slot.
                     (*self.scp_client_value_sender)(
@@ -192,7 +212,7 @@ impl PeerApiService {
         from_responder_id: ResponderId,
     ) -> Result<(), PeerServiceError> {
         // Ignore a consensus message from an unknown peer.
-        if !self.known_responder_ids.contains(&from_responder_id) {
+        if !(self.is_known_peer_fn)(&from_responder_id) {
             return Err(PeerServiceError::UnknownPeer(from_responder_id.to_string()));
         }
 
@@ -398,8 +418,10 @@ impl ConsensusPeerApi for PeerApiService {
 #[cfg(test)]
 mod tests {
     use crate::{
-        api::peer_api_service::PeerApiService, background_work_queue::BackgroundWorkQueueError,
-        consensus_service::IncomingConsensusMsg, tx_manager::MockTxManager,
+        api::peer_api_service::{IsKnownPeerFn, PeerApiService},
+        background_work_queue::BackgroundWorkQueueError,
+        consensus_service::IncomingConsensusMsg,
+        tx_manager::MockTxManager,
     };
     use grpcio::{ChannelBuilder, Environment, Error::RpcFailure, Server, ServerBuilder};
     use mc_common::{
@@ -460,6 +482,10 @@ mod tests {
         Arc::new(|| None)
     }
 
+    fn get_is_known_peer_fn(known_responder_ids: Vec<ResponderId>) -> IsKnownPeerFn {
+        Arc::new(move |responder_id| known_responder_ids.contains(responder_id))
+    }
+
     fn get_client_server(instance: PeerApiService) -> (ConsensusPeerApiClient, Server) {
         let service = consensus_peer_grpc::create_consensus_peer_api(instance);
         let env = Arc::new(Environment::new(1));
@@ -494,7 +520,8 @@ mod tests {
             get_incoming_consensus_msgs_sender_ok(),
             get_scp_client_value_sender(),
             get_fetch_latest_msg_fn(),
-            known_responder_ids,
+            get_is_known_peer_fn(known_responder_ids),
+            Default::default(),
             logger,
         );
 
@@ -568,7 +595,8 @@ mod tests {
             get_incoming_consensus_msgs_sender_ok(),
             get_scp_client_value_sender(),
             get_fetch_latest_msg_fn(),
-            known_responder_ids.clone(),
+            get_is_known_peer_fn(known_responder_ids.clone()),
+            Default::default(),
             logger,
         );
 
@@ -634,7 +662,8 @@ mod tests {
             get_incoming_consensus_msgs_sender_ok(),
             get_scp_client_value_sender(),
             get_fetch_latest_msg_fn(),
-            known_responder_ids.clone(),
+            get_is_known_peer_fn(known_responder_ids.clone()),
+            Default::default(),
             logger,
         );
 
@@ -682,7 +711,8 @@ mod tests {
             get_incoming_consensus_msgs_sender_ok(),
             get_scp_client_value_sender(),
             get_fetch_latest_msg_fn(),
-            known_responder_ids.clone(),
+            get_is_known_peer_fn(known_responder_ids.clone()),
+            Default::default(),
             logger,
         );
 
@@ -14,7 +14,7 @@ pub enum PeerServiceError {
     UnknownTransactions(Vec<TxHash>),
 
     /// Enclave-related error `{0}`.
-    Enclave(mc_consensus_enclave::Error),
+    Enclave(mc_consensus_enclave_api::Error),
 
     /// Something went wrong...
     InternalError,
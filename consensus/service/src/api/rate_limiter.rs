@@ -0,0 +1,430 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Per-client rate limiting for the client API.
+//!
+//! Each client gets a token bucket per endpoint, refilled at the configured
+//! rate, and optionally a cap on the number of requests it may have in flight.
+
+use crate::counters;
+use displaydoc::Display;
+use grpcio::{RpcContext, RpcStatus, RpcStatusCode};
+use mc_common::LruCache;
+use mc_consensus_service_config::{ClientRateLimitsConfig, RateLimit, RateLimitedEndpoint};
+use mc_util_grpc::ANONYMOUS_USER;
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::Instant,
+};
+
+/// Maximum number of tracked (client, endpoint) pairs. Past this, the least
+/// recently used bucket that is as good as new is dropped to make room for a
+/// new one, and requests from new clients are rejected if there is none.
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+
+/// A request was rejected by the rate limiter.
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+pub enum RateLimitError {
+    /// Too many {0:?} requests, try again later
+    RateExceeded(RateLimitedEndpoint),
+
+    /// Too many concurrent {0:?} requests
+    TooManyConcurrent(RateLimitedEndpoint),
+}
+
+impl From<RateLimitError> for RpcStatus {
+    fn from(src: RateLimitError) -> Self {
+        RpcStatus::with_message(RpcStatusCode::RESOURCE_EXHAUSTED, src.to_string())
+    }
+}
+
+type BucketKey = (String, RateLimitedEndpoint);
+type Buckets = Arc<Mutex<LruCache<BucketKey, Bucket>>>;
+
+/// Rate limiting state for a single client and endpoint.
+#[derive(Debug)]
+struct Bucket {
+    /// Number of requests that may currently be made.
+    tokens: f64,
+
+    /// Last time `tokens` was refilled.
+    refilled_at: Instant,
+
+    /// Number of requests currently being handled.
+    in_flight: u32,
+}
+
+impl Bucket {
+    fn new(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst() as f64,
+            refilled_at: now,
+            in_flight: 0,
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        self.tokens = self.refilled_tokens(limit, now);
+        self.refilled_at = now;
+    }
+
+    fn refilled_tokens(&self, limit: &RateLimit, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        let per_second = limit.requests_per_minute as f64 / 60.0;
+        (self.tokens + elapsed.as_secs_f64() * per_second).min(limit.burst() as f64)
+    }
+
+    /// Whether dropping this bucket would not lose track of anything, i.e. it
+    /// has no requests in flight and has refilled completely.
+    fn is_replaceable(&self, limit: Option<&RateLimit>, now: Instant) -> bool {
+        self.in_flight == 0
+            && limit.map_or(true, |limit| {
+                self.refilled_tokens(limit, now) >= limit.burst() as f64
+            })
+    }
+}
+
+/// Enforces `ClientRateLimitsConfig`. The default value does not limit
+/// anything.
+pub struct ClientRateLimiter {
+    config: RwLock<ClientRateLimitsConfig>,
+    buckets: Buckets,
+}
+
+impl Default for ClientRateLimiter {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl ClientRateLimiter {
+    pub fn new(config: ClientRateLimitsConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            buckets: Arc::new(Mutex::new(LruCache::new(MAX_TRACKED_BUCKETS))),
+        }
+    }
+
+    /// Replace the limits. Requests already in flight keep counting against
+    /// their client's concurrency quota.
+    pub fn set_config(&self, config: ClientRateLimitsConfig) {
+        *self.config.write().expect("lock poisoned") = config;
+    }
+
+    /// Admit a request from `client` to `endpoint`. The request counts
+    /// against the client's concurrency quota until the returned guard is
+    /// dropped.
+    pub fn check(
+        &self,
+        client: &str,
+        endpoint: RateLimitedEndpoint,
+    ) -> Result<RateLimitGuard, RateLimitError> {
+        self.check_at(client, endpoint, Instant::now())
+    }
+
+    /// Admit a request to `endpoint`, identifying the client by its
+    /// authenticated user id, or by its address if it is anonymous.
+    pub fn check_rpc(
+        &self,
+        ctx: &RpcContext,
+        user_id: &str,
+        endpoint: RateLimitedEndpoint,
+    ) -> Result<RateLimitGuard, RateLimitError> {
+        if user_id == ANONYMOUS_USER {
+            self.check(client_address(&ctx.peer()), endpoint)
+        } else {
+            self.check(user_id, endpoint)
+        }
+    }
+
+    fn check_at(
+        &self,
+        client: &str,
+        endpoint: RateLimitedEndpoint,
+        now: Instant,
+    ) -> Result<RateLimitGuard, RateLimitError> {
+        let config = self.config.read().expect("lock poisoned");
+        let limit = match config.limit(client, endpoint) {
+            Some(limit) => limit,
+            None => return Ok(RateLimitGuard { release: None }),
+        };
+
+        let mut buckets = self.buckets.lock().expect("mutex poisoned");
+        let key = (client.to_owned(), endpoint);
+        if !buckets.contains(&key) {
+            if buckets.is_full() {
+                // Dropping a bucket which is still in use would reset its client's
+                // limits, so clients could get around them by making requests
+                // under many other identities.
+                let replaceable = buckets
+                    .iter()
+                    .filter(|((client, endpoint), bucket)| {
+                        bucket.is_replaceable(config.limit(client, *endpoint), now)
+                    })
+                    .last()
+                    .map(|(key, _)| key.clone());
+                match replaceable {
+                    Some(replaceable) => {
+                        buckets.pop(&replaceable);
+                    }
+                    None => {
+                        counters::CLIENT_RATE_LIMITED.inc();
+                        return Err(RateLimitError::RateExceeded(endpoint));
+                    }
+                }
+            }
+            buckets.put(key.clone(), Bucket::new(limit, now));
+        }
+        let bucket = buckets.get_mut(&key).expect("bucket was just inserted");
+        bucket.refill(limit, now);
+
+        if let Some(max_concurrent) = limit.max_concurrent {
+            if bucket.in_flight >= max_concurrent {
+                counters::CLIENT_RATE_LIMITED_CONCURRENT.inc();
+                return Err(RateLimitError::TooManyConcurrent(endpoint));
+            }
+        }
+        if bucket.tokens < 1.0 {
+            counters::CLIENT_RATE_LIMITED.inc();
+            return Err(RateLimitError::RateExceeded(endpoint));
+        }
+
+        bucket.tokens -= 1.0;
+        bucket.in_flight += 1;
+        Ok(RateLimitGuard {
+            release: Some((self.buckets.clone(), key)),
+        })
+    }
+}
+
+/// Holds a request's place in its client's concurrency quota.
+pub struct RateLimitGuard {
+    release: Option<(Buckets, BucketKey)>,
+}
+
+impl Drop for RateLimitGuard {
+    fn drop(&mut self) {
+        if let Some((buckets, key)) = self.release.take() {
+            if let Some(bucket) = buckets.lock().expect("mutex poisoned").get_mut(&key) {
+                bucket.in_flight = bucket.in_flight.saturating_sub(1);
+            }
+        }
+    }
+}
+
+/// The IP address of a gRPC peer, e.g. "1.2.3.4" for "ipv4:1.2.3.4:5678" and
+/// "::1" for "ipv6:[::1]:5678". Other peer strings are returned as is.
+fn client_address(peer: &str) -> &str {
+    if let Some(addr) = peer.strip_prefix("ipv4:") {
+        addr.rsplit_once(':').map_or(addr, |(host, _port)| host)
+    } else if let Some(addr) = peer.strip_prefix("ipv6:") {
+        addr.strip_prefix('[')
+            .and_then(|addr| addr.split_once(']'))
+            .map_or(addr, |(host, _port)| host)
+    } else {
+        peer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_consensus_service_config::EndpointRateLimits;
+    use std::time::Duration;
+
+    fn limits(
+        requests_per_minute: u32,
+        burst: u32,
+        max_concurrent: Option<u32>,
+    ) -> EndpointRateLimits {
+        EndpointRateLimits {
+            client_tx_propose: Some(RateLimit {
+                requests_per_minute,
+                burst: Some(burst),
+                max_concurrent,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let limiter = ClientRateLimiter::default();
+        for _ in 0..1000 {
+            assert!(limiter
+                .check("client", RateLimitedEndpoint::ClientTxPropose)
+                .is_ok());
+        }
+        assert!(limiter.buckets.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_burst_and_refill() {
+        let limiter = ClientRateLimiter::new(ClientRateLimitsConfig {
+            default: limits(60, 3, None),
+            ..Default::default()
+        });
+        let endpoint = RateLimitedEndpoint::ClientTxPropose;
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at("client", endpoint, start).is_ok());
+        }
+        assert_eq!(
+            limiter.check_at("client", endpoint, start).err(),
+            Some(RateLimitError::RateExceeded(endpoint))
+        );
+
+        // Other clients and endpoints have their own buckets.
+        assert!(limiter.check_at("other", endpoint, start).is_ok());
+        assert!(limiter
+            .check_at("client", RateLimitedEndpoint::GetBlocks, start)
+            .is_ok());
+
+        // One request per second.
+        let later = start + Duration::from_millis(1500);
+        assert!(limiter.check_at("client", endpoint, later).is_ok());
+        assert!(limiter.check_at("client", endpoint, later).is_err());
+
+        // Refilling never exceeds the burst.
+        let much_later = start + Duration::from_secs(3600);
+        for _ in 0..3 {
+            assert!(limiter.check_at("client", endpoint, much_later).is_ok());
+        }
+        assert!(limiter.check_at("client", endpoint, much_later).is_err());
+    }
+
+    #[test]
+    fn test_max_concurrent() {
+        let limiter = ClientRateLimiter::new(ClientRateLimitsConfig {
+            default: limits(6000, 100, Some(2)),
+            ..Default::default()
+        });
+        let endpoint = RateLimitedEndpoint::ClientTxPropose;
+
+        let first = limiter.check("client", endpoint).unwrap();
+        let _second = limiter.check("client", endpoint).unwrap();
+        assert_eq!(
+            limiter.check("client", endpoint).err(),
+            Some(RateLimitError::TooManyConcurrent(endpoint))
+        );
+
+        drop(first);
+        assert!(limiter.check("client", endpoint).is_ok());
+    }
+
+    #[test]
+    fn test_overrides() {
+        let mut config = ClientRateLimitsConfig {
+            default: limits(60, 1, None),
+            ..Default::default()
+        };
+        config
+            .overrides
+            .insert("trusted".to_owned(), limits(60, 10, None));
+        let limiter = ClientRateLimiter::new(config);
+        let endpoint = RateLimitedEndpoint::ClientTxPropose;
+        let now = Instant::now();
+
+        assert!(limiter.check_at("client", endpoint, now).is_ok());
+        assert!(limiter.check_at("client", endpoint, now).is_err());
+        for _ in 0..10 {
+            assert!(limiter.check_at("trusted", endpoint, now).is_ok());
+        }
+        assert!(limiter.check_at("trusted", endpoint, now).is_err());
+    }
+
+    #[test]
+    fn test_set_config() {
+        let limiter = ClientRateLimiter::default();
+        let endpoint = RateLimitedEndpoint::ClientTxPropose;
+        let now = Instant::now();
+        for _ in 0..10 {
+            assert!(limiter.check_at("client", endpoint, now).is_ok());
+        }
+
+        limiter.set_config(ClientRateLimitsConfig {
+            default: limits(60, 1, None),
+            ..Default::default()
+        });
+        assert!(limiter.check_at("client", endpoint, now).is_ok());
+        assert!(limiter.check_at("client", endpoint, now).is_err());
+    }
+
+    #[test]
+    fn test_tracked_buckets_are_capped() {
+        let limiter = ClientRateLimiter::new(ClientRateLimitsConfig {
+            default: limits(60, 1, None),
+            ..Default::default()
+        });
+        let endpoint = RateLimitedEndpoint::ClientTxPropose;
+        let now = Instant::now();
+
+        for i in 0..MAX_TRACKED_BUCKETS {
+            assert!(limiter.check_at(&i.to_string(), endpoint, now).is_ok());
+        }
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_TRACKED_BUCKETS);
+
+        // Using a bucket keeps it from being dropped.
+        assert!(limiter.check_at("0", endpoint, now).is_err());
+
+        // Buckets are only dropped once they have refilled.
+        assert_eq!(
+            limiter.check_at("new", endpoint, now).err(),
+            Some(RateLimitError::RateExceeded(endpoint))
+        );
+        assert!(limiter
+            .buckets
+            .lock()
+            .unwrap()
+            .contains(&("1".to_owned(), endpoint)));
+
+        // A new client takes the place of the least recently used one.
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.check_at("new", endpoint, later).is_ok());
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), MAX_TRACKED_BUCKETS);
+        assert!(buckets.contains(&("0".to_owned(), endpoint)));
+        assert!(!buckets.contains(&("1".to_owned(), endpoint)));
+        assert!(buckets.contains(&("new".to_owned(), endpoint)));
+    }
+
+    #[test]
+    fn test_buckets_in_use_are_not_dropped() {
+        let limiter = ClientRateLimiter::new(ClientRateLimitsConfig {
+            default: limits(60, 1, Some(1)),
+            ..Default::default()
+        });
+        let endpoint = RateLimitedEndpoint::ClientTxPropose;
+        let now = Instant::now();
+
+        // The least recently used bucket has a request in flight.
+        let guard = limiter.check_at("busy", endpoint, now).unwrap();
+        for i in 1..MAX_TRACKED_BUCKETS {
+            assert!(limiter.check_at(&i.to_string(), endpoint, now).is_ok());
+        }
+
+        let later = now + Duration::from_secs(3600);
+        assert!(limiter.check_at("new", endpoint, later).is_ok());
+        {
+            let buckets = limiter.buckets.lock().unwrap();
+            assert!(buckets.contains(&("busy".to_owned(), endpoint)));
+            assert!(!buckets.contains(&("1".to_owned(), endpoint)));
+        }
+
+        // The request in flight still counts against the client's quota.
+        assert_eq!(
+            limiter.check_at("busy", endpoint, later).err(),
+            Some(RateLimitError::TooManyConcurrent(endpoint))
+        );
+        drop(guard);
+        assert!(limiter.check_at("busy", endpoint, later).is_ok());
+    }
+
+    #[test]
+    fn test_client_address() {
+        assert_eq!(client_address("ipv4:10.0.0.1:41234"), "10.0.0.1");
+        assert_eq!(client_address("ipv6:[::1]:41234"), "::1");
+        assert_eq!(client_address("ipv6:[fe80::1%eth0]:80"), "fe80::1%eth0");
+        assert_eq!(client_address("unix:/tmp/socket"), "unix:/tmp/socket");
+    }
+}
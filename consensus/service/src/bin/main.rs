@@ -9,7 +9,9 @@ use mc_common::{
     logger::{create_app_logger, log, o},
     time::SystemTimeProvider,
 };
-use mc_consensus_enclave::{BlockchainConfig, ConsensusServiceSgxEnclave, ENCLAVE_FILE};
+use mc_consensus_enclave_api::BlockchainConfig;
+#[cfg(feature = "insecure-enclave")]
+use mc_consensus_enclave_insecure::InsecureConsensusEnclave;
 use mc_consensus_service::{
     consensus_service::{ConsensusService, ConsensusServiceError},
     mint_tx_manager::MintTxManagerImpl,
@@ -20,7 +22,6 @@ use mc_consensus_service_config::Config;
 use mc_ledger_db::LedgerDB;
 use mc_util_cli::ParserWithBuildInfo;
 use std::{
-    env,
     fs::File,
     io::{Read, Write},
     path::{Path, PathBuf},
@@ -72,15 +73,26 @@ fn main() -> Result<(), ConsensusServiceError> {
         block_version: config.block_version,
     };
 
-    let enclave_path = env::current_exe()
-        .expect("Could not get the path of our executable")
-        .with_file_name(ENCLAVE_FILE);
-    let (enclave, sealed_key, features) = ConsensusServiceSgxEnclave::new(
-        enclave_path,
+    #[cfg(all(feature = "sgx-enclave", not(feature = "insecure-enclave")))]
+    let (enclave, sealed_key, features) = {
+        let enclave_path = std::env::current_exe()
+            .expect("Could not get the path of our executable")
+            .with_file_name(mc_consensus_enclave::ENCLAVE_FILE);
+        mc_consensus_enclave::ConsensusServiceSgxEnclave::new(
+            enclave_path,
+            &config.peer_responder_id,
+            &config.client_responder_id,
+            &cached_key,
+            blockchain_config,
+        )
+    };
+    #[cfg(feature = "insecure-enclave")]
+    let (enclave, sealed_key, features) = InsecureConsensusEnclave::new(
         &config.peer_responder_id,
         &config.client_responder_id,
         &cached_key,
         blockchain_config,
+        logger.clone(),
     );
 
     log::info!(logger, "Enclave target features: {}", features.join(", "));
@@ -99,6 +111,11 @@ fn main() -> Result<(), ConsensusServiceError> {
 
     let ias_client = Client::new(&config.ias_api_key).expect("Could not create IAS client");
 
+    #[cfg(feature = "insecure-enclave")]
+    enclave
+        .attest(&ias_client)
+        .expect("Could not attest the insecure enclave");
+
     if DEBUG_ENCLAVE {
         log::error!(
             logger,
@@ -108,9 +125,10 @@ fn main() -> Result<(), ConsensusServiceError> {
         log::debug!(logger, "Enclave will be started in production mode");
     }
 
-    let tx_manager = TxManagerImpl::new(
+    let tx_manager = TxManagerImpl::with_capacity(
         enclave.clone(),
-        DefaultTxManagerUntrustedInterfaces::new(local_ledger.clone()),
+        DefaultTxManagerUntrustedInterfaces::new(local_ledger.clone(), config.block_version),
+        config.tx_cache_capacity,
         logger.clone(),
     );
 
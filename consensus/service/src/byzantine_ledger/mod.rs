@@ -15,11 +15,12 @@ use crate::{
     counters,
     mint_tx_manager::{MintTxManager, MintTxManagerError},
     tx_manager::{TxManager, TxManagerError},
+    tx_trace::TxTraceLog,
 };
 use displaydoc::Display;
 use mc_common::{logger::Logger, NodeID, ResponderId};
 use mc_connection::{BlockchainConnection, ConnectionManager};
-use mc_consensus_enclave::ConsensusEnclave;
+use mc_consensus_enclave_api::ConsensusEnclave;
 use mc_consensus_scp::{scp_log::LoggingScpNode, Node, QuorumSet, ScpNode};
 use mc_crypto_keys::Ed25519Pair;
 use mc_ledger_db::Ledger;
@@ -105,6 +106,10 @@ impl ByzantineLedger {
     /// * `tx_source_urls` - Source URLs for fetching block contents.
     /// * `scp_debug_dir` - If Some, debugging info will be written in this
     ///   directory.
+    /// * `observer` - If true, follow consensus without voting. Values are not
+    ///   validated, and blocks are fetched from peers instead of being formed
+    ///   by the enclave.
+    /// * `tx_trace_log` - Records what happens to transactions.
     /// * `logger` - Logger.
     pub fn new<
         PC: BlockchainConnection + ConsensusConnection + 'static,
@@ -124,6 +129,8 @@ impl ByzantineLedger {
         msg_signer_key: Arc<Ed25519Pair>,
         tx_source_urls: Vec<String>,
         scp_debug_dir: Option<PathBuf>,
+        observer: bool,
+        tx_trace_log: Arc<TxTraceLog>,
         logger: Logger,
     ) -> Self {
         // TODO: this should be passed in as an argument.
@@ -136,8 +143,11 @@ impl ByzantineLedger {
             let node = Node::new(
                 node_id.clone(),
                 quorum_set,
-                // Validation callback
+                // Validation callback. An observer cannot validate transactions, which are
+                // encrypted for the enclave. It trusts its quorum set instead.
                 Arc::new(move |scp_value| match scp_value {
+                    _ if observer => Ok(()),
+
                     ConsensusValue::TxHash(tx_hash) => tx_manager_validate
                         .validate(tx_hash)
                         .map_err(UnifiedNodeError::from),
@@ -152,6 +162,13 @@ impl ByzantineLedger {
                 }),
                 // Combine callback
                 Arc::new(move |scp_values| {
+                    if observer {
+                        let mut values = scp_values.to_vec();
+                        values.sort();
+                        values.dedup();
+                        return Ok(values);
+                    }
+
                     let mut tx_hashes = Vec::new();
                     let mut mint_config_txs = Vec::new();
                     let mut mint_txs = Vec::new();
@@ -232,6 +249,8 @@ impl ByzantineLedger {
                 is_behind.clone(),
                 highest_peer_block.clone(),
                 highest_issued_msg.clone(),
+                observer,
+                tx_trace_log,
                 logger,
             );
 
@@ -275,6 +294,13 @@ impl ByzantineLedger {
             .expect("Could not send consensus msg");
     }
 
+    /// Change the local node's quorum set. Takes effect from the next slot on.
+    pub fn set_quorum_set(&self, quorum_set: QuorumSet) {
+        self.task_sender
+            .send(TaskMessage::QuorumSet(quorum_set))
+            .expect("Could not send quorum set");
+    }
+
     pub fn stop(&mut self) {
         let _ = self.task_sender.send(TaskMessage::StopTrigger);
         self.join();
@@ -481,6 +507,8 @@ mod tests {
             msg_signer_key,
             Vec::new(),
             None,
+            false,
+            Default::default(),
             logger.clone(),
         );
 
@@ -550,7 +578,7 @@ mod tests {
 
         let tx_manager = Arc::new(TxManagerImpl::new(
             enclave.clone(),
-            DefaultTxManagerUntrustedInterfaces::new(ledger.clone()),
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone(), BLOCK_VERSION),
             logger.clone(),
         ));
 
@@ -573,6 +601,8 @@ mod tests {
             local_signer_key.clone(),
             Vec::new(),
             None,
+            false,
+            Default::default(),
             logger.clone(),
         );
 
@@ -929,7 +959,7 @@ mod tests {
 
         let tx_manager = Arc::new(TxManagerImpl::new(
             enclave.clone(),
-            DefaultTxManagerUntrustedInterfaces::new(ledger.clone()),
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone(), BlockVersion::MAX),
             logger.clone(),
         ));
 
@@ -952,6 +982,8 @@ mod tests {
             local_signer_key.clone(),
             Vec::new(),
             None,
+            false,
+            Default::default(),
             logger.clone(),
         );
 
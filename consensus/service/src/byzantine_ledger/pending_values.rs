@@ -2,10 +2,14 @@
 
 //! A utility object for keeping track of pending transaction hashes.
 
-use crate::{mint_tx_manager::MintTxManager, tx_manager::TxManager};
+use crate::{
+    mint_tx_manager::MintTxManager,
+    tx_manager::{TxManager, TxManagerError},
+};
 use mc_peers::ConsensusValue;
+use mc_transaction_core::tx::TxHash;
 use std::{
-    collections::{hash_map::Entry::Vacant, HashMap},
+    collections::{hash_map::Entry::Vacant, HashMap, HashSet},
     sync::Arc,
     time::Instant,
 };
@@ -21,20 +25,24 @@ pub struct PendingValues<TXM: TxManager, MTXM: MintTxManager> {
     mint_tx_manager: Arc<MTXM>,
 
     /// We need to store pending values vec so we can process values
-    /// on a first-come first-served basis. However, we want to be able to:
+    /// in order of priority, and on a first-come first-served basis among
+    /// values with the same priority. However, we want to be able to:
     /// 1) Efficiently see if we already have a given transaction and ignore
     /// duplicates 2) Track how long each transaction took to externalize.
     ///
     /// To accomplish these goals we store, in addition to the queue of pending
-    /// values, a map that maps a value to when we first encountered it.
-    /// This essentially gives us an ordered HashMap.
+    /// values (sorted by decreasing priority), a map that maps a value to when
+    /// we first encountered it. This essentially gives us an ordered HashMap.
+    ///
+    /// Mint transactions do not pay fees, and take precedence over all other
+    /// values.
     ///
     /// Note that we only store a timestamp for values that were handed to us
     /// directly from a client. That behavior is enforced by
     /// ByzantineLedger. We skip tracking processing times for relayed
     /// values since we want to track the time from when the network first
     /// saw a value, and not when a specific node saw it.
-    pending_values: Vec<ConsensusValue>,
+    pending_values: Vec<(u64, ConsensusValue)>,
     pending_values_map: HashMap<ConsensusValue, Option<Instant>>,
 }
 
@@ -73,10 +81,14 @@ impl<TXM: TxManager, MTXM: MintTxManager> PendingValues<TXM, MTXM> {
             match value {
                 ConsensusValue::TxHash(tx_hash) => {
                     // A new transaction.
-                    if self.tx_manager.validate(&tx_hash).is_ok() {
+                    if self.tx_manager.validate(&tx_hash).is_err() {
+                        return false;
+                    }
+                    // Transactions that were evicted from the cache are not proposed.
+                    if let Some(priority) = self.tx_manager.priority(&tx_hash) {
                         // The transaction is well-formed and valid.
                         entry.insert(timestamp);
-                        self.pending_values.push(value);
+                        Self::insert_sorted(&mut self.pending_values, priority, value);
                         true
                     } else {
                         false
@@ -91,7 +103,7 @@ impl<TXM: TxManager, MTXM: MintTxManager> PendingValues<TXM, MTXM> {
                     {
                         // The transaction is well-formed and valid.
                         entry.insert(timestamp);
-                        self.pending_values.push(value);
+                        Self::insert_sorted(&mut self.pending_values, u64::MAX, value);
                         true
                     } else {
                         false
@@ -102,7 +114,7 @@ impl<TXM: TxManager, MTXM: MintTxManager> PendingValues<TXM, MTXM> {
                     if self.mint_tx_manager.validate_mint_tx(mint_tx).is_ok() {
                         // The transaction is well-formed and valid.
                         entry.insert(timestamp);
-                        self.pending_values.push(value);
+                        Self::insert_sorted(&mut self.pending_values, u64::MAX, value);
                         true
                     } else {
                         false
@@ -114,9 +126,9 @@ impl<TXM: TxManager, MTXM: MintTxManager> PendingValues<TXM, MTXM> {
         }
     }
 
-    /// Iterate over the list of pending values.
+    /// Iterate over the list of pending values, by decreasing priority.
     pub fn iter(&self) -> impl Iterator<Item = &ConsensusValue> {
-        self.pending_values.iter()
+        self.pending_values.iter().map(|(_priority, value)| value)
     }
 
     /// Try and get the timestamp associated with a given value.
@@ -135,25 +147,51 @@ impl<TXM: TxManager, MTXM: MintTxManager> PendingValues<TXM, MTXM> {
         // (Help the borrow checker)
         let self_pending_values_map = &self.pending_values_map;
         self.pending_values
-            .retain(|tx_hash| self_pending_values_map.contains_key(tx_hash));
+            .retain(|(_priority, tx_hash)| self_pending_values_map.contains_key(tx_hash));
 
         // Invariant
         assert_eq!(self.pending_values_map.len(), self.pending_values.len());
     }
 
+    /// Insert a value after all values with the same or a higher priority.
+    fn insert_sorted(
+        pending_values: &mut Vec<(u64, ConsensusValue)>,
+        priority: u64,
+        value: ConsensusValue,
+    ) {
+        let index = pending_values.partition_point(|(other, _)| *other >= priority);
+        pending_values.insert(index, (priority, value));
+    }
+
     /// Clear any pending values that are no longer valid.
-    pub fn clear_invalid_values(&mut self) {
-        let tx_manager = self.tx_manager.clone();
-        let mint_tx_manager = self.mint_tx_manager.clone();
-        self.retain(|value| match value {
-            ConsensusValue::TxHash(tx_hash) => tx_manager.validate(tx_hash).is_ok(),
-            ConsensusValue::MintConfigTx(ref mint_config_tx) => mint_tx_manager
-                .validate_mint_config_tx(mint_config_tx)
-                .is_ok(),
-            ConsensusValue::MintTx(ref mint_tx) => {
-                mint_tx_manager.validate_mint_tx(mint_tx).is_ok()
-            }
-        });
+    ///
+    /// Returns the transactions that were cleared, and why they are invalid.
+    pub fn clear_invalid_values(&mut self) -> Vec<(TxHash, TxManagerError)> {
+        let mut invalid_txs = Vec::new();
+        let invalid_values: HashSet<ConsensusValue> = self
+            .pending_values_map
+            .keys()
+            .filter(|value| match value {
+                ConsensusValue::TxHash(tx_hash) => match self.tx_manager.validate(tx_hash) {
+                    Ok(()) => false,
+                    Err(err) => {
+                        invalid_txs.push((*tx_hash, err));
+                        true
+                    }
+                },
+                ConsensusValue::MintConfigTx(ref mint_config_tx) => self
+                    .mint_tx_manager
+                    .validate_mint_config_tx(mint_config_tx)
+                    .is_err(),
+                ConsensusValue::MintTx(ref mint_tx) => {
+                    self.mint_tx_manager.validate_mint_tx(mint_tx).is_err()
+                }
+            })
+            .cloned()
+            .collect();
+
+        self.retain(|value| !invalid_values.contains(value));
+        invalid_txs
     }
 }
 
@@ -193,6 +231,7 @@ mod tests {
             .expect_validate()
             .with(eq(values[2]))
             .return_const(Ok(()));
+        tx_manager.expect_priority().return_const(Some(0));
 
         let mut pending_values =
             PendingValues::new(Arc::new(tx_manager), Arc::new(mint_tx_manager));
@@ -201,7 +240,7 @@ mod tests {
         assert!(pending_values.push(values[2].into(), None));
 
         assert_eq!(
-            pending_values.pending_values,
+            pending_values.iter().cloned().collect::<Vec<_>>(),
             vec![values[0].into(), values[2].into()]
         );
         assert_eq!(
@@ -225,6 +264,7 @@ mod tests {
 
         // All values are considered valid for this test.
         tx_manager.expect_validate().return_const(Ok(()));
+        tx_manager.expect_priority().return_const(Some(0));
 
         let mut pending_values =
             PendingValues::new(Arc::new(tx_manager), Arc::new(mint_tx_manager));
@@ -236,7 +276,7 @@ mod tests {
         assert!(!pending_values.push(values[1].clone(), Some(Instant::now())));
         assert!(!pending_values.push(values[2].clone(), None));
 
-        assert_eq!(pending_values.pending_values, values,);
+        assert_eq!(pending_values.iter().cloned().collect::<Vec<_>>(), values);
         assert_eq!(
             pending_values.pending_values_map,
             HashMap::from_iter(vec![
@@ -247,6 +287,51 @@ mod tests {
         );
     }
 
+    #[test]
+    /// Should order values by decreasing priority, and by arrival among values
+    /// with the same priority.
+    fn test_push_orders_by_priority() {
+        let mut tx_manager = MockTxManager::new();
+        let mint_tx_manager = MockMintTxManager::new();
+
+        let priorities = vec![
+            (TxHash([1u8; 32]), 10),
+            (TxHash([2u8; 32]), 30),
+            (TxHash([3u8; 32]), 10),
+            (TxHash([4u8; 32]), 20),
+        ];
+        tx_manager.expect_validate().return_const(Ok(()));
+        for (tx_hash, priority) in &priorities {
+            tx_manager
+                .expect_priority()
+                .with(eq(*tx_hash))
+                .return_const(Some(*priority));
+        }
+
+        // This transaction was evicted from the cache.
+        let evicted = TxHash([5u8; 32]);
+        tx_manager
+            .expect_priority()
+            .with(eq(evicted))
+            .return_const(None);
+
+        let mut pending_values =
+            PendingValues::new(Arc::new(tx_manager), Arc::new(mint_tx_manager));
+        for (tx_hash, _priority) in &priorities {
+            assert!(pending_values.push((*tx_hash).into(), None));
+        }
+        assert!(!pending_values.push(evicted.into(), None));
+
+        let expected: Vec<ConsensusValue> = vec![
+            TxHash([2u8; 32]).into(),
+            TxHash([4u8; 32]).into(),
+            TxHash([1u8; 32]).into(),
+            TxHash([3u8; 32]).into(),
+        ];
+        assert_eq!(pending_values.iter().cloned().collect::<Vec<_>>(), expected);
+        assert_eq!(pending_values.len(), 4);
+    }
+
     #[test]
     /// Should discard values that are no longer valid.
     fn test_clear_invalid_values_discards_invalid_values() {
@@ -284,18 +369,23 @@ mod tests {
         let mut pending_values =
             PendingValues::new(Arc::new(tx_manager), Arc::new(mint_tx_manager));
 
-        pending_values.pending_values = values.clone();
+        pending_values.pending_values = values.iter().cloned().map(|value| (0, value)).collect();
         pending_values.pending_values_map = values
             .iter()
             .cloned()
             .map(|value| (value, Some(Instant::now())))
             .collect();
 
-        pending_values.clear_invalid_values();
+        let invalid_txs = pending_values.clear_invalid_values();
 
         // The second transaction is no longer valid and should be removed.
+        assert_eq!(invalid_txs.len(), 1);
+        assert_eq!(invalid_txs[0].0, tx_hashes[1]);
         let expected_pending_values = vec![values[0].clone(), values[2].clone()];
-        assert_eq!(pending_values.pending_values, expected_pending_values);
+        assert_eq!(
+            pending_values.iter().cloned().collect::<Vec<_>>(),
+            expected_pending_values
+        );
         assert_eq!(
             pending_values.pending_values.len(),
             pending_values.pending_values_map.len()
@@ -1,6 +1,7 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
 use mc_common::ResponderId;
+use mc_consensus_scp::QuorumSet;
 use mc_peers::{ConsensusValue, VerifiedConsensusMsg};
 use std::time::Instant;
 
@@ -14,6 +15,9 @@ pub enum TaskMessage {
     /// SCP Statement.
     ConsensusMsg(VerifiedConsensusMsg, ResponderId),
 
+    /// A new quorum set for the local node.
+    QuorumSet(QuorumSet),
+
     /// Stop trigger, used for notifying the worker thread to terminate.
     StopTrigger,
 }
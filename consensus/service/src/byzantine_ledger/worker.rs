@@ -7,7 +7,8 @@ use crate::{
     },
     counters,
     mint_tx_manager::MintTxManager,
-    tx_manager::TxManager,
+    tx_manager::{TxManager, TxManagerError},
+    tx_trace::{TxEventKind, TxTraceLog},
 };
 use mc_common::{
     logger::{log, Logger},
@@ -17,7 +18,7 @@ use mc_connection::{
     BlockchainConnection, ConnectionManager,
     _retry::{delay::Fibonacci, Error as RetryError},
 };
-use mc_consensus_enclave::{ConsensusEnclave, FormBlockInputs};
+use mc_consensus_enclave_api::{ConsensusEnclave, FormBlockInputs};
 use mc_consensus_scp::{slot::Phase, Msg, ScpNode, SlotIndex};
 use mc_crypto_keys::Ed25519Pair;
 use mc_ledger_db::Ledger;
@@ -44,6 +45,31 @@ use std::{
 /// Default number of consensus messages to process per batch.
 const CONSENSUS_MSG_BATCH_SIZE: usize = 5;
 
+/// Time an observer waits before retrying to fetch the block of an
+/// externalized slot. Multiplied by the number of failed attempts, up to
+/// `MAX_OBSERVER_FETCH_BLOCK_BACKOFF`.
+const OBSERVER_FETCH_BLOCK_RETRY_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Maximum time an observer waits between attempts to fetch a block.
+const MAX_OBSERVER_FETCH_BLOCK_BACKOFF: Duration = Duration::from_secs(5);
+
+/// A slot externalized by an observer, whose block has not yet been fetched
+/// from peers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct ExternalizedSlot {
+    // When the slot was externalized.
+    externalized_at: Instant,
+
+    // When to next attempt fetching the block.
+    attempt_fetch_at: Instant,
+
+    // Number of failed attempts to fetch the block.
+    num_fetch_attempts: u32,
+
+    // Hashes of the transactions that were externalized.
+    tx_hashes: Vec<TxHash>,
+}
+
 pub struct ByzantineLedgerWorker<
     E: ConsensusEnclave,
     L: Ledger + 'static,
@@ -110,6 +136,17 @@ pub struct ByzantineLedgerWorker<
     // scp_node.
     need_nominate: bool,
 
+    // If true, the local node follows consensus without taking part in it: it does not nominate
+    // values or issue SCP messages, and appends blocks formed by its peers.
+    observer: bool,
+
+    // Observer mode: the current slot, if it has been externalized but its block has not been
+    // appended to the ledger yet.
+    externalized_slot: Option<ExternalizedSlot>,
+
+    // Records what happens to transactions.
+    tx_trace_log: Arc<TxTraceLog>,
+
     logger: Logger,
 }
 
@@ -141,6 +178,9 @@ impl<
     ///   network agrees on.
     /// * `highest_issued_msg` - Worker sets to highest consensus message issued
     ///   by this node.
+    /// * `observer` - If true, follow consensus without voting, and fetch
+    ///   externalized blocks from peers instead of forming them.
+    /// * `tx_trace_log` - Records what happens to transactions.
     /// * `logger` - Logger instance.
     #[allow(clippy::too_many_arguments)]
     pub fn new(
@@ -157,6 +197,8 @@ impl<
         is_behind: Arc<AtomicBool>,
         highest_peer_block: Arc<AtomicU64>,
         highest_issued_msg: Arc<Mutex<Option<ConsensusMsg>>>,
+        observer: bool,
+        tx_trace_log: Arc<TxTraceLog>,
         logger: Logger,
     ) -> Self {
         let current_slot_index = ledger.num_blocks().unwrap();
@@ -181,6 +223,9 @@ impl<
             pending_consensus_msgs: Default::default(),
             pending_values: PendingValues::new(tx_manager, mint_tx_manager),
             need_nominate: false,
+            observer,
+            externalized_slot: None,
+            tx_trace_log,
             network_state,
             ledger_sync_service,
             ledger_sync_state: LedgerSyncState::InSync,
@@ -196,6 +241,13 @@ impl<
             return false;
         }
 
+        // An observer does not move on to the next slot until it has the block for the
+        // current one.
+        if self.externalized_slot.is_some() {
+            self.fetch_externalized_block(Instant::now());
+            return true;
+        }
+
         // Advance the "sync state" state machine.
         let previous_sync_state = {
             let next_state = self.next_sync_state(Instant::now());
@@ -248,7 +300,8 @@ impl<
 
                 self.scp_node.reset_slot_index(self.current_slot_index);
                 // Clear any pending values that might no longer be valid.
-                self.pending_values.clear_invalid_values();
+                let invalid_txs = self.pending_values.clear_invalid_values();
+                self.trace_invalid_txs(invalid_txs);
                 if !self.pending_values.is_empty() {
                     // These values should be proposed for nomination.
                     self.need_nominate = true;
@@ -309,7 +362,11 @@ impl<
             .get_externalized_values(self.current_slot_index)
         {
             // The current slot has reached consensus.
-            self.complete_current_slot(externalized_values);
+            if self.observer {
+                self.observe_current_slot(externalized_values);
+            } else {
+                self.complete_current_slot(externalized_values);
+            }
         }
 
         // Update metrics.
@@ -396,6 +453,10 @@ impl<
                 // Transactions submitted by clients. These are assumed to be well-formed, but may
                 // not be valid.
                 TaskMessage::Values(timestamp, new_values) => {
+                    // Observers do not nominate values.
+                    if self.observer {
+                        continue;
+                    }
                     for tx_hash in new_values {
                         if self.pending_values.push(tx_hash, timestamp) {
                             self.need_nominate = true;
@@ -412,6 +473,13 @@ impl<
                         .push((consensus_msg, from_responder_id));
                 }
 
+                // New quorum set
+                TaskMessage::QuorumSet(quorum_set) => {
+                    log::info!(self.logger, "Updating quorum set to {:?}", quorum_set);
+                    self.network_state.set_local_quorum_set(quorum_set.clone());
+                    self.scp_node.set_quorum_set(quorum_set);
+                }
+
                 // Request to stop thread
                 TaskMessage::StopTrigger => {
                     return false;
@@ -432,15 +500,21 @@ impl<
         assert!(!self.pending_values.is_empty());
 
         // Fairness heuristics:
-        // * Values are proposed in the order that they were received.
+        // * Values are proposed by decreasing priority (fee), and in the order that
+        //   they were received among values with the same priority.
         // * Each node limits the total number of values it proposes per slot.
-        let values = self
+        let values: Vec<ConsensusValue> = self
             .pending_values
             .iter()
             .take(MAX_PENDING_VALUES_TO_NOMINATE)
             .cloned()
             .collect();
 
+        self.tx_trace_log.record_all(
+            tx_hashes(&values),
+            TxEventKind::Nominated(self.current_slot_index),
+        );
+
         let msg_opt = self
             .scp_node
             .propose_values(values)
@@ -495,11 +569,13 @@ impl<
         // Process compatible messages in batches.
         for chunk in compatible_msgs.chunks(CONSENSUS_MSG_BATCH_SIZE) {
             // Omit a message if it references a transaction that cannot be obtained.
+            // Observers do not need transactions, since they never form blocks.
             let (resolved, failed): (Vec<_>, Vec<_>) =
                 chunk
                     .iter()
                     .partition(|(consensus_msg, from_responder_id)| {
-                        self.fetch_missing_txs(consensus_msg.scp_msg(), from_responder_id)
+                        self.observer
+                            || self.fetch_missing_txs(consensus_msg.scp_msg(), from_responder_id)
                     });
 
             if !failed.is_empty() {
@@ -510,12 +586,14 @@ impl<
                 );
             }
 
-            // Broadcast resolved messages.
-            for (consensus_msg, from_responder_id) in &resolved {
-                self.broadcaster
-                    .lock()
-                    .expect("mutex poisoned")
-                    .broadcast_consensus_msg(consensus_msg.as_ref(), from_responder_id);
+            // Broadcast resolved messages. Observers do not relay messages.
+            if !self.observer {
+                for (consensus_msg, from_responder_id) in &resolved {
+                    self.broadcaster
+                        .lock()
+                        .expect("mutex poisoned")
+                        .broadcast_consensus_msg(consensus_msg.as_ref(), from_responder_id);
+                }
             }
 
             let scp_msgs: Vec<Msg<_>> = resolved
@@ -584,6 +662,10 @@ impl<
         });
 
         counters::TX_EXTERNALIZED_COUNT.inc_by(externalized.len() as u64);
+        self.tx_trace_log.record_all(
+            tx_hashes(&externalized),
+            TxEventKind::Externalized(block_data.block().index),
+        );
 
         // Update current slot index.
         self.current_slot_index = {
@@ -601,6 +683,20 @@ impl<
         let purged_hashes = self.tx_manager.remove_expired(expired_block_index);
         let pending_values_len_before_purge = self.pending_values.len();
 
+        // Transactions in the new block no longer take up room in the cache. They stay
+        // cached until the next slot completes, in case peers still ask for them.
+        let externalized_hashes: Vec<TxHash> = tx_hashes(&externalized).cloned().collect();
+        self.tx_manager.remove_externalized(&externalized_hashes);
+
+        // Transactions that were already externalized are also purged, but did not
+        // expire.
+        let expired_hashes: Vec<TxHash> = tx_hashes(self.pending_values.iter())
+            .filter(|tx_hash| purged_hashes.contains(*tx_hash))
+            .cloned()
+            .collect();
+        self.tx_trace_log
+            .record_all(&expired_hashes, TxEventKind::Expired);
+
         self.pending_values.retain(|value| match value {
             ConsensusValue::TxHash(tx_hash) => !purged_hashes.contains(tx_hash),
             ConsensusValue::MintConfigTx(mint_config_tx) => {
@@ -611,7 +707,8 @@ impl<
 
         // Drop pending values that are no longer considered valid.
         let pending_values_len_before_clear_invalid = self.pending_values.len();
-        self.pending_values.clear_invalid_values();
+        let invalid_txs = self.pending_values.clear_invalid_values();
+        self.trace_invalid_txs(invalid_txs);
 
         log::info!(
             self.logger,
@@ -647,6 +744,99 @@ impl<
         }
     }
 
+    // Observer mode: record that the current slot was externalized. Its block is
+    // fetched from peers on the following ticks.
+    fn observe_current_slot(&mut self, externalized: Vec<ConsensusValue>) {
+        log::info!(
+            self.logger,
+            "Slot {} ended with {} externalized values, fetching its block from peers.",
+            self.current_slot_index,
+            externalized.len(),
+        );
+
+        counters::TX_EXTERNALIZED_COUNT.inc_by(externalized.len() as u64);
+        self.tx_trace_log.record_all(
+            tx_hashes(&externalized),
+            TxEventKind::Externalized(self.current_slot_index),
+        );
+        counters::PREV_SLOT_NUMBER.set(self.current_slot_index as i64);
+        counters::PREV_SLOT_ENDED_AT.set(chrono::Utc::now().timestamp_millis());
+        counters::PREV_SLOT_NUM_EXT_VALS.set(externalized.len() as i64);
+
+        let now = Instant::now();
+        self.externalized_slot = Some(ExternalizedSlot {
+            externalized_at: now,
+            attempt_fetch_at: now,
+            num_fetch_attempts: 0,
+            tx_hashes: tx_hashes(&externalized).cloned().collect(),
+        });
+    }
+
+    // Observer mode: attempt to append the block of the externalized current slot,
+    // fetching it from peers.
+    fn fetch_externalized_block(&mut self, now: Instant) {
+        let externalized_slot = self
+            .externalized_slot
+            .as_mut()
+            .expect("No externalized slot");
+        if now < externalized_slot.attempt_fetch_at {
+            // Not yet time to attempt fetching.
+            return;
+        }
+
+        if let Err(err) = self
+            .ledger_sync_service
+            .attempt_ledger_sync(&self.network_state, 1)
+        {
+            // Peers may not have formed the block yet, or it might not have reached the
+            // transaction sources. Reattempt with capped linear backoff.
+            externalized_slot.num_fetch_attempts += 1;
+            log::debug!(
+                self.logger,
+                "Could not fetch block {} (attempt {}): {:?}",
+                self.current_slot_index,
+                externalized_slot.num_fetch_attempts,
+                err
+            );
+            externalized_slot.attempt_fetch_at = now
+                + min(
+                    OBSERVER_FETCH_BLOCK_RETRY_INTERVAL * externalized_slot.num_fetch_attempts,
+                    MAX_OBSERVER_FETCH_BLOCK_BACKOFF,
+                );
+            return;
+        }
+
+        counters::OBSERVER_BLOCK_FETCH_TIME.observe(
+            now.saturating_duration_since(externalized_slot.externalized_at)
+                .as_secs_f64(),
+        );
+        let externalized_hashes = externalized_slot.tx_hashes.clone();
+        self.externalized_slot = None;
+
+        // The SCP node has already moved on to the next slot.
+        self.current_slot_index = {
+            let current_slot_index: SlotIndex = self.ledger.num_blocks().unwrap();
+            assert_eq!(current_slot_index, self.current_slot_index + 1);
+            current_slot_index
+        };
+        log::info!(
+            self.logger,
+            "Appended block {} fetched from peers.",
+            self.current_slot_index - 1
+        );
+
+        // Peers may have proposed transactions to this node. They are never needed.
+        let max_externalized_slots = self.scp_node.max_externalized_slots() as u64;
+        self.tx_manager.remove_expired(
+            self.current_slot_index
+                .saturating_sub(max_externalized_slots),
+        );
+        self.tx_manager.remove_externalized(&externalized_hashes);
+
+        // Same as when completing a slot: progress was made, so we are not behind.
+        self.ledger_sync_state = LedgerSyncState::InSync;
+    }
+
     fn fetch_missing_txs(
         &mut self,
         scp_msg: &Msg<ConsensusValue>,
@@ -716,7 +906,8 @@ impl<
                     }
                     tx_contexts.into_par_iter().for_each_with(
                         (self.tx_manager.clone(), self.logger.clone()),
-                        move |(tx_manager, logger), tx_context| match tx_manager.insert(tx_context)
+                        move |(tx_manager, logger), tx_context| match tx_manager
+                            .insert_referenced(tx_context)
                         {
                             Ok(_) => {}
                             Err(err) => {
@@ -759,6 +950,12 @@ impl<
 
     /// Broadcast a consensus message issued by this node.
     fn issue_consensus_message(&mut self, msg: Msg<ConsensusValue>) -> Result<(), &'static str> {
+        // Observers are not part of anyone's quorum set, and keep their statements to
+        // themselves.
+        if self.observer {
+            return Ok(());
+        }
+
         let consensus_msg =
             ConsensusMsg::from_scp_msg(&self.ledger, msg, self.msg_signer_key.as_ref())
                 .map_err(|_| "Failed creating ConsensusMsg")?;
@@ -810,6 +1007,14 @@ impl<
         counters::CUR_SLOT_BALLOT_COUNTER.set(slot_metrics.bN as i64);
     }
 
+    // Record why transactions were dropped from the pending values.
+    fn trace_invalid_txs(&self, invalid_txs: Vec<(TxHash, TxManagerError)>) {
+        for (tx_hash, err) in invalid_txs {
+            self.tx_trace_log
+                .record(&tx_hash, TxEventKind::Rejected(err.to_string()));
+        }
+    }
+
     fn form_block_from_externalized_values(
         &self,
         externalized_values: Vec<ConsensusValue>,
@@ -856,6 +1061,13 @@ impl<
             .get_root_tx_out_membership_element()
             .expect("Failed getting root tx out membership element");
 
+        // The contents of the parent block record the loads from which the minimum
+        // fees of the next one are derived.
+        let parent_block_contents = self
+            .ledger
+            .get_block_contents(parent_block.index)
+            .expect("Failed getting parent block contents");
+
         // Request the enclave to form the next block.
         let (block, block_contents, mut signature) = self
             .enclave
@@ -865,6 +1077,7 @@ impl<
                     well_formed_encrypted_txs_with_proofs,
                     mint_config_txs,
                     mint_txs_with_config,
+                    parent_block_contents,
                 },
                 &root_element,
             )
@@ -877,6 +1090,16 @@ impl<
     }
 }
 
+/// The transaction hashes among some consensus values.
+fn tx_hashes<'a>(
+    values: impl IntoIterator<Item = &'a ConsensusValue>,
+) -> impl Iterator<Item = &'a TxHash> {
+    values.into_iter().filter_map(|value| match value {
+        ConsensusValue::TxHash(tx_hash) => Some(tx_hash),
+        _ => None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -897,7 +1120,7 @@ mod tests {
         NodeID, ResponderId,
     };
     use mc_connection::ConnectionManager;
-    use mc_consensus_enclave::GovernorsMap;
+    use mc_consensus_enclave_api::GovernorsMap;
     use mc_consensus_enclave_mock::{ConsensusServiceMockEnclave, MockConsensusEnclave};
     use mc_consensus_scp::{
         msg::{NominatePayload, Topic::Nominate},
@@ -925,6 +1148,7 @@ mod tests {
     use rand::rngs::StdRng;
     use rand_core::SeedableRng;
     use std::{
+        collections::HashSet,
         convert::TryFrom,
         ops::Add,
         sync::{
@@ -1026,6 +1250,8 @@ mod tests {
             Arc::new(AtomicBool::new(false)),
             Arc::new(AtomicU64::new(0)),
             Arc::new(Mutex::new(Option::<ConsensusMsg>::None)),
+            false,
+            Default::default(),
             logger,
         );
 
@@ -1078,6 +1304,8 @@ mod tests {
             Arc::new(AtomicBool::new(false)),
             Arc::new(AtomicU64::new(0)),
             Arc::new(Mutex::new(Option::<ConsensusMsg>::None)),
+            false,
+            Default::default(),
             logger,
         );
 
@@ -1197,6 +1425,8 @@ mod tests {
             Arc::new(AtomicBool::new(false)),
             Arc::new(AtomicU64::new(0)),
             Arc::new(Mutex::new(Option::<ConsensusMsg>::None)),
+            false,
+            Default::default(),
             logger,
         );
 
@@ -1258,6 +1488,8 @@ mod tests {
             Arc::new(AtomicBool::new(false)),
             Arc::new(AtomicU64::new(0)),
             Arc::new(Mutex::new(Option::<ConsensusMsg>::None)),
+            false,
+            Default::default(),
             logger,
         );
 
@@ -1296,7 +1528,7 @@ mod tests {
         let num_blocks = 12;
         let (
             enclave,
-            scp_node,
+            mut scp_node,
             mut ledger,
             ledger_sync,
             mut tx_manager,
@@ -1333,6 +1565,17 @@ mod tests {
                 .return_const(Ok(()));
         }
 
+        // All transactions have the same priority.
+        tx_manager.expect_priority().return_const(Some(0));
+
+        // The quorum set is updated once.
+        let new_quorum_set = QuorumSet::new_with_node_ids(1, vec![peers[0].id.clone()]);
+        scp_node
+            .expect_set_quorum_set()
+            .with(eq(new_quorum_set.clone()))
+            .times(1)
+            .return_const(());
+
         let connection_manager = get_connection_manager(&node_id, &peers, &logger);
         let (task_sender, task_receiver) = get_channel();
 
@@ -1359,6 +1602,8 @@ mod tests {
             Arc::new(AtomicBool::new(false)),
             Arc::new(AtomicU64::new(0)),
             Arc::new(Mutex::new(Option::<ConsensusMsg>::None)),
+            false,
+            Default::default(),
             logger,
         );
 
@@ -1398,6 +1643,11 @@ mod tests {
         assert!(worker.receive_tasks());
         // The message from the task queue should now be pending.
         assert_eq!(worker.pending_consensus_msgs.len(), 1);
+
+        task_sender
+            .send(TaskMessage::QuorumSet(new_quorum_set))
+            .unwrap();
+        assert!(worker.receive_tasks());
     }
 
     /// Should maintain the invariant that pending_values and pending_values map
@@ -1445,6 +1695,8 @@ mod tests {
             Arc::new(AtomicBool::new(false)),
             Arc::new(AtomicU64::new(0)),
             Arc::new(Mutex::new(Option::<ConsensusMsg>::None)),
+            false,
+            Default::default(),
             logger,
         );
 
@@ -1519,6 +1771,7 @@ mod tests {
 
         // `validate` will be called one for each pushed value.
         tx_manager.expect_validate().return_const(Ok(()));
+        tx_manager.expect_priority().return_const(Some(0));
 
         // Up to MAX_PENDING_VALUES_TO_NOMINATE values should be proposed to the
         // scp_node.
@@ -1542,6 +1795,8 @@ mod tests {
             Arc::new(AtomicBool::new(false)),
             Arc::new(AtomicU64::new(0)),
             Arc::new(Mutex::new(Option::<ConsensusMsg>::None)),
+            false,
+            Default::default(),
             logger,
         );
 
@@ -1605,11 +1860,11 @@ mod tests {
         ) = get_mocks(&local_node_id, &quorum_set, n_blocks);
         let enclave = ConsensusServiceMockEnclave::default();
 
-        let tx_manager = TxManagerImpl::new(
+        let tx_manager = Arc::new(TxManagerImpl::new(
             enclave.clone(),
-            DefaultTxManagerUntrustedInterfaces::new(ledger.clone()),
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone(), block_version),
             logger.clone(),
-        );
+        ));
 
         let connection_manager = get_connection_manager(&local_node_id, &peers, &logger);
 
@@ -1690,13 +1945,15 @@ mod tests {
             ledger.clone(),
             ledger_sync,
             connection_manager,
-            Arc::new(tx_manager),
+            tx_manager.clone(),
             Arc::new(mint_tx_manager),
             Arc::new(Mutex::new(broadcast)),
             task_receiver,
             Arc::new(AtomicBool::new(false)),
             Arc::new(AtomicU64::new(0)),
             Arc::new(Mutex::new(Option::<ConsensusMsg>::None)),
+            false,
+            Default::default(),
             logger,
         );
 
@@ -1720,10 +1977,114 @@ mod tests {
 
         // Our mint tx should make it into the block.
         assert_eq!(block_contents.mint_txs, vec![mint_tx1]);
+
+        // The transactions no longer take up room in the cache, but are only removed
+        // when the next slot completes.
+        for tx_hash in &[hash_tx1, hash_tx2, hash_tx3] {
+            assert!(tx_manager.contains(tx_hash));
+            assert_eq!(tx_manager.priority(tx_hash), None);
+        }
     }
 
     // TODO: test process_consensus_msgs
 
+    #[test_with_logger]
+    // An observer should retry fetching the externalized block until it is
+    // appended, then move on to the next slot.
+    fn test_observer_fetches_externalized_block(logger: Logger) {
+        let (node_id, _local_node_uri, msg_signer_key) = get_local_node_config(11);
+        let mut rng: StdRng = SeedableRng::from_seed([97u8; 32]);
+        let peers = get_peers(&[22, 33], &mut rng);
+        let quorum_set =
+            QuorumSet::new_with_node_ids(2, vec![peers[0].id.clone(), peers[1].id.clone()]);
+
+        let num_blocks = 12;
+        let (
+            enclave,
+            mut scp_node,
+            ledger,
+            mut ledger_sync,
+            mut tx_manager,
+            mint_tx_manager,
+            broadcast,
+        ) = get_mocks(&node_id, &quorum_set, num_blocks);
+        let connection_manager = get_connection_manager(&node_id, &peers, &logger);
+        let (_task_sender, task_receiver) = get_channel();
+
+        // The first attempt fails, the second one appends the block.
+        let mut num_attempts = 0;
+        ledger_sync
+            .expect_attempt_ledger_sync()
+            .times(2)
+            .returning(move |_, limit| {
+                assert_eq!(limit, 1);
+                num_attempts += 1;
+                if num_attempts == 1 {
+                    Err(LedgerSyncError::NoSafeBlocks)
+                } else {
+                    Ok(())
+                }
+            });
+        scp_node
+            .expect_max_externalized_slots()
+            .return_const(5_usize);
+        tx_manager
+            .expect_remove_expired()
+            .with(eq(num_blocks - 5))
+            .times(1)
+            .return_const(HashSet::default());
+        let tx_hash = TxHash([1u8; 32]);
+        tx_manager
+            .expect_remove_externalized()
+            .withf(move |tx_hashes| tx_hashes == [tx_hash])
+            .times(1)
+            .return_const(());
+
+        let mut worker = ByzantineLedgerWorker::new(
+            enclave,
+            Box::new(scp_node),
+            msg_signer_key,
+            ledger,
+            ledger_sync,
+            connection_manager,
+            Arc::new(tx_manager),
+            Arc::new(mint_tx_manager),
+            Arc::new(Mutex::new(broadcast)),
+            task_receiver,
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(AtomicU64::new(0)),
+            Arc::new(Mutex::new(Option::<ConsensusMsg>::None)),
+            true,
+            Default::default(),
+            logger,
+        );
+
+        // The slot before the last block in the (mock) ledger was just externalized.
+        worker.current_slot_index = num_blocks - 1;
+        worker.observe_current_slot(vec![ConsensusValue::TxHash(tx_hash)]);
+
+        let start = Instant::now();
+        worker.fetch_externalized_block(start);
+        let attempt_fetch_at = match &worker.externalized_slot {
+            Some(externalized_slot) => {
+                assert_eq!(externalized_slot.num_fetch_attempts, 1);
+                assert!(externalized_slot.attempt_fetch_at > start);
+                externalized_slot.attempt_fetch_at
+            }
+            None => panic!("Unexpected"),
+        };
+        assert_eq!(worker.current_slot_index, num_blocks - 1);
+
+        // Not yet time to retry.
+        worker.fetch_externalized_block(start);
+        assert!(worker.externalized_slot.is_some());
+
+        worker.fetch_externalized_block(attempt_fetch_at);
+        assert!(worker.externalized_slot.is_none());
+        assert_eq!(worker.current_slot_index, num_blocks);
+        assert_eq!(worker.ledger_sync_state, LedgerSyncState::InSync);
+    }
+
     // TODO: test fetch_missing_txs
 
     // TODO: test issue_consensus_message
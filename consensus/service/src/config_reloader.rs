@@ -0,0 +1,352 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! ConfigReloader is used to start a thread that periodically checks the
+//! network and tokens configuration files for changes, and reloads them
+//! when they change. This allows changing a node's peers and quorum set
+//! without restarting it, which would cost it its SCP state.
+
+use crate::counters;
+use displaydoc::Display;
+use mc_common::logger::{log, Logger};
+use mc_consensus_enclave_api::{ConsensusEnclave, Error as ConsensusEnclaveError};
+use mc_consensus_service_config::{Error as ConfigError, TokensConfig};
+use mc_transaction_core::TokenId;
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often a stop request is checked for while waiting for the next poll.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Display)]
+pub enum ConfigReloadError {
+    /// Configuration: {0}
+    Config(ConfigError),
+
+    /// Consensus enclave: {0}
+    ConsensusEnclave(ConsensusEnclaveError),
+
+    /// Token {0} has a minimum fee of {1}, but the enclave was initialized
+    /// with {2:?}
+    MinimumFeeMismatch(TokenId, u64, Option<u64>),
+
+    /// The set of tokens differs from the one the enclave was initialized with
+    TokensChanged,
+
+    /// The governors differ from the ones the enclave was initialized with
+    GovernorsChanged,
+}
+
+impl From<ConfigError> for ConfigReloadError {
+    fn from(src: ConfigError) -> Self {
+        ConfigReloadError::Config(src)
+    }
+}
+
+impl From<ConsensusEnclaveError> for ConfigReloadError {
+    fn from(src: ConsensusEnclaveError) -> Self {
+        ConfigReloadError::ConsensusEnclave(src)
+    }
+}
+
+/// A callback that reloads the configuration and applies it.
+pub type ReloadConfigFn = Arc<dyn Fn() -> Result<(), ConfigReloadError> + Sync + Send>;
+
+/// Check that a tokens configuration matches the one the enclave was
+/// initialized with. Minimum fees and governors are baked into the enclave,
+/// so changing them requires restarting the node.
+///
+/// # Arguments
+/// * `enclave` - The local node's consensus enclave.
+/// * `initial` - The tokens configuration the node was started with.
+/// * `tokens` - The reloaded tokens configuration.
+pub fn validate_tokens_config(
+    enclave: &impl ConsensusEnclave,
+    initial: &TokensConfig,
+    tokens: &TokensConfig,
+) -> Result<(), ConfigReloadError> {
+    let fee_map = tokens.fee_map()?;
+    for (token_id, minimum_fee) in fee_map.iter() {
+        let enclave_minimum_fee = enclave.get_minimum_fee(token_id)?;
+        if enclave_minimum_fee != Some(*minimum_fee) {
+            return Err(ConfigReloadError::MinimumFeeMismatch(
+                *token_id,
+                *minimum_fee,
+                enclave_minimum_fee,
+            ));
+        }
+    }
+
+    // Tokens that were removed from the configuration are still accepted by the
+    // enclave.
+    if fee_map != initial.fee_map()? {
+        return Err(ConfigReloadError::TokensChanged);
+    }
+
+    if tokens.token_id_to_governors()? != initial.token_id_to_governors()?
+        || tokens.governors_signature != initial.governors_signature
+    {
+        return Err(ConfigReloadError::GovernorsChanged);
+    }
+
+    Ok(())
+}
+
+pub struct ConfigReloader {
+    join_handle: Option<thread::JoinHandle<()>>,
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl ConfigReloader {
+    /// Start watching configuration files.
+    ///
+    /// # Arguments
+    /// * `paths` - The configuration files to watch.
+    /// * `poll_interval` - How often to check the files for changes.
+    /// * `reload_config_fn` - Called whenever any of the files changed.
+    /// * `logger` - Logger.
+    pub fn start(
+        paths: Vec<PathBuf>,
+        poll_interval: Duration,
+        reload_config_fn: ReloadConfigFn,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("ConfigReloader".into())
+                .spawn(move || {
+                    Self::thread_entrypoint(
+                        paths,
+                        poll_interval,
+                        thread_stop_requested,
+                        reload_config_fn,
+                        logger,
+                    )
+                })
+                .expect("Failed spawning ConfigReloader thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.join_handle.take() {
+            thread.join().expect("ConfigReloader thread join failed");
+        }
+    }
+
+    fn thread_entrypoint(
+        paths: Vec<PathBuf>,
+        poll_interval: Duration,
+        stop_requested: Arc<AtomicBool>,
+        reload_config_fn: ReloadConfigFn,
+        logger: Logger,
+    ) {
+        log::debug!(
+            logger,
+            "ConfigReloader thread started, watching {:?}",
+            paths
+        );
+
+        let mut last_modified = Self::modified_times(&paths);
+        let mut next_poll = Instant::now() + poll_interval;
+        while !stop_requested.load(Ordering::SeqCst) {
+            let now = Instant::now();
+            if now < next_poll {
+                thread::sleep(STOP_CHECK_INTERVAL.min(next_poll - now));
+                continue;
+            }
+            next_poll = now + poll_interval;
+
+            let modified = Self::modified_times(&paths);
+            if modified == last_modified {
+                continue;
+            }
+            // A configuration that failed to load is not retried until the files change
+            // again.
+            last_modified = modified;
+
+            log::info!(logger, "Configuration files changed, reloading");
+            match reload_config_fn() {
+                Ok(()) => {
+                    counters::CONFIG_RELOADS.inc();
+                    log::info!(logger, "Configuration reloaded");
+                }
+                Err(err) => {
+                    counters::CONFIG_RELOAD_FAILURES.inc();
+                    log::error!(logger, "Failed reloading configuration: {}", err);
+                }
+            }
+        }
+
+        log::debug!(logger, "ConfigReloader thread stopped");
+    }
+
+    /// The last modification time of each file, or None if it could not be
+    /// read (e.g. because the file is being replaced).
+    fn modified_times(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+        paths
+            .iter()
+            .map(|path| {
+                fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+impl Drop for ConfigReloader {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_consensus_enclave_api::BlockchainConfig;
+    use mc_consensus_enclave_mock::ConsensusServiceMockEnclave;
+    use std::sync::atomic::AtomicUsize;
+    use tempdir::TempDir;
+
+    fn tokens_config(json: &str) -> TokensConfig {
+        serde_json::from_str(json).expect("failed parsing json")
+    }
+
+    fn enclave_with(tokens: &TokensConfig) -> ConsensusServiceMockEnclave {
+        let enclave = ConsensusServiceMockEnclave::default();
+        *enclave.blockchain_config.lock().unwrap() = BlockchainConfig {
+            fee_map: tokens.fee_map().unwrap(),
+            governors_map: tokens.token_id_to_governors().unwrap(),
+            governors_signature: tokens.governors_signature,
+            ..Default::default()
+        };
+        enclave
+    }
+
+    #[test]
+    fn test_validate_tokens_config() {
+        let initial = tokens_config(
+            r#"{
+                "tokens": [
+                    { "token_id": 0, "minimum_fee": 400000000 },
+                    { "token_id": 1, "minimum_fee": 1024 }
+                ]
+            }"#,
+        );
+        let enclave = enclave_with(&initial);
+
+        assert!(validate_tokens_config(&enclave, &initial, &initial).is_ok());
+
+        // Settings that are not part of the enclave's configuration may change.
+        let allow_any_fee = tokens_config(
+            r#"{
+                "tokens": [
+                    { "token_id": 0, "minimum_fee": 400000000 },
+                    { "token_id": 1, "minimum_fee": 1024, "allow_any_fee": true }
+                ]
+            }"#,
+        );
+        assert!(validate_tokens_config(&enclave, &initial, &allow_any_fee).is_ok());
+
+        let fee_changed = tokens_config(
+            r#"{
+                "tokens": [
+                    { "token_id": 0, "minimum_fee": 400000000 },
+                    { "token_id": 1, "minimum_fee": 2048 }
+                ]
+            }"#,
+        );
+        assert!(matches!(
+            validate_tokens_config(&enclave, &initial, &fee_changed),
+            Err(ConfigReloadError::MinimumFeeMismatch(token_id, 2048, Some(1024)))
+                if token_id == TokenId::from(1)
+        ));
+
+        let token_added = tokens_config(
+            r#"{
+                "tokens": [
+                    { "token_id": 0, "minimum_fee": 400000000 },
+                    { "token_id": 1, "minimum_fee": 1024 },
+                    { "token_id": 2, "minimum_fee": 1024 }
+                ]
+            }"#,
+        );
+        assert!(matches!(
+            validate_tokens_config(&enclave, &initial, &token_added),
+            Err(ConfigReloadError::MinimumFeeMismatch(_, 1024, None))
+        ));
+
+        let token_removed = tokens_config(
+            r#"{
+                "tokens": [
+                    { "token_id": 0, "minimum_fee": 400000000 }
+                ]
+            }"#,
+        );
+        assert!(matches!(
+            validate_tokens_config(&enclave, &initial, &token_removed),
+            Err(ConfigReloadError::TokensChanged)
+        ));
+    }
+
+    #[test_with_logger]
+    fn test_reload_on_change(logger: Logger) {
+        let temp_dir = TempDir::new("config_reloader").unwrap();
+        let path = temp_dir.path().join("network.toml");
+        fs::write(&path, "broadcast_peers = []").unwrap();
+
+        let num_reloads = Arc::new(AtomicUsize::new(0));
+        let reload_config_fn: ReloadConfigFn = {
+            let num_reloads = num_reloads.clone();
+            Arc::new(move || {
+                num_reloads.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+        };
+
+        let wait_for_reloads = |expected: usize| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while num_reloads.load(Ordering::SeqCst) < expected && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+            num_reloads.load(Ordering::SeqCst)
+        };
+
+        let mut reloader = ConfigReloader::start(
+            vec![path.clone()],
+            Duration::from_millis(10),
+            reload_config_fn,
+            logger,
+        );
+
+        // Nothing changed yet.
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(num_reloads.load(Ordering::SeqCst), 0);
+
+        // Removing and recreating the file are both changes, regardless of the
+        // resolution of modification times.
+        fs::remove_file(&path).unwrap();
+        assert_eq!(wait_for_reloads(1), 1);
+
+        fs::write(&path, "broadcast_peers = []").unwrap();
+        assert_eq!(wait_for_reloads(2), 2);
+
+        reloader.stop();
+    }
+}
@@ -3,13 +3,18 @@
 //! The MobileCoin consensus service.
 
 use crate::{
-    api::{AttestedApiService, BlockchainApiService, ClientApiService, PeerApiService},
+    api::{
+        AdminApiService, AttestedApiService, BlockchainApiService, ClientApiService,
+        ClientRateLimiter, PeerApiService,
+    },
     background_work_queue::BackgroundWorkQueue,
     byzantine_ledger::ByzantineLedger,
+    config_reloader::{validate_tokens_config, ConfigReloader, ReloadConfigFn},
     counters,
     mint_tx_manager::MintTxManager,
     peer_keepalive::PeerKeepalive,
     tx_manager::TxManager,
+    tx_trace::{TxEventKind, TxTraceLog},
 };
 use base64::{encode_config, URL_SAFE};
 use displaydoc::Display;
@@ -24,19 +29,22 @@ use mc_common::{
     NodeID, ResponderId,
 };
 use mc_connection::{Connection, ConnectionManager};
-use mc_consensus_api::{consensus_client_grpc, consensus_common_grpc, consensus_peer_grpc};
-use mc_consensus_enclave::{ConsensusEnclave, Error as ConsensusEnclaveError};
-use mc_consensus_service_config::{Config, Error as ConfigError};
+use mc_consensus_api::{
+    consensus_admin_grpc, consensus_client_grpc, consensus_common_grpc, consensus_peer_grpc,
+};
+use mc_consensus_enclave_api::{ConsensusEnclave, Error as ConsensusEnclaveError};
+use mc_consensus_service_config::{Config, Error as ConfigError, TokensConfig};
 use mc_crypto_keys::DistinguishedEncoding;
 use mc_ledger_db::{Error as LedgerDbError, Ledger, LedgerDB};
 use mc_peers::{ConsensusValue, PeerConnection, ThreadedBroadcaster, VerifiedConsensusMsg};
+#[cfg(feature = "sgx-enclave")]
 use mc_sgx_report_cache_untrusted::{Error as ReportCacheError, ReportCacheThread};
 use mc_util_grpc::{
     AdminServer, AnonymousAuthenticator, Authenticator, BuildInfoService,
     ConnectionUriGrpcioServer, GetConfigJsonFn, HealthCheckStatus, HealthService,
     TokenAuthenticator,
 };
-use mc_util_uri::{ConnectionUri, ConsensusPeerUriApi};
+use mc_util_uri::{ConnectionUri, ConsensusPeerUri, ConsensusPeerUriApi};
 use once_cell::sync::OnceCell;
 use serde_json::json;
 use std::{
@@ -59,12 +67,14 @@ pub enum ConsensusServiceError {
     /// Failed to stop background work queue: `{0}`
     BackgroundWorkQueueStop(String),
     /// Report cache error: `{0}`
+    #[cfg(feature = "sgx-enclave")]
     ReportCache(ReportCacheError),
     /// Configuration: `{0}`
     Config(ConfigError),
     /// Consensus enclave error: `{0}`
     ConsensusEnclave(ConsensusEnclaveError),
 }
+#[cfg(feature = "sgx-enclave")]
 impl From<ReportCacheError> for ConsensusServiceError {
     fn from(src: ReportCacheError) -> Self {
         ConsensusServiceError::ReportCache(src)
@@ -114,9 +124,12 @@ pub struct ConsensusService<
     enclave: E,
     ledger_db: LedgerDB,
     env: Arc<Environment>,
+    // Only used to refresh the report of an SGX enclave.
+    #[cfg_attr(feature = "insecure-enclave", allow(dead_code))]
     ra_client: R,
     logger: Logger,
 
+    #[cfg(feature = "sgx-enclave")]
     report_cache_thread: Option<ReportCacheThread>,
 
     consensus_msgs_from_network: BackgroundWorkQueue<IncomingConsensusMsg>,
@@ -134,6 +147,13 @@ pub struct ConsensusService<
     peer_keepalive: Option<Arc<PeerKeepalive>>,
     // GRPC client requests authenticator
     client_authenticator: Arc<dyn Authenticator + Send + Sync>,
+    // Per-client rate limits, shared by the client-facing services
+    rate_limiter: Arc<ClientRateLimiter>,
+    // Lifecycle events of the transactions seen by this node
+    tx_trace_log: Arc<TxTraceLog>,
+    // The tokens configuration the enclave was initialized with
+    tokens_config: TokensConfig,
+    config_reloader: Option<ConfigReloader>,
 
     admin_rpc_server: Option<AdminServer>,
     consensus_rpc_server: Option<Server>,
@@ -217,6 +237,14 @@ impl<
                 Arc::new(AnonymousAuthenticator::default())
             };
 
+        // Rate limiter
+        let rate_limiter = Arc::new(ClientRateLimiter::new(config.network().client_rate_limits));
+
+        // Transaction traces
+        let tx_trace_log = Arc::new(TxTraceLog::new(config.tx_trace_capacity));
+
+        let tokens_config = config.tokens();
+
         // Return
         Self {
             config,
@@ -227,6 +255,7 @@ impl<
             ra_client,
             logger,
 
+            #[cfg(feature = "sgx-enclave")]
             report_cache_thread: None,
 
             consensus_msgs_from_network,
@@ -237,6 +266,10 @@ impl<
             mint_tx_manager,
             peer_keepalive,
             client_authenticator,
+            rate_limiter,
+            tx_trace_log,
+            tokens_config,
+            config_reloader: None,
 
             admin_rpc_server: None,
             consensus_rpc_server: None,
@@ -251,17 +284,12 @@ impl<
 
     pub fn start(&mut self) -> Result<(), ConsensusServiceError> {
         let ret = {
-            self.report_cache_thread = Some(ReportCacheThread::start(
-                self.enclave.clone(),
-                self.ra_client.clone(),
-                self.config.ias_spid,
-                &counters::ENCLAVE_REPORT_TIMESTAMP,
-                self.logger.clone(),
-            )?);
+            self.start_report_cache()?;
             self.start_admin_rpc_server()?;
             self.start_consensus_rpc_server()?;
             self.start_user_rpc_server()?;
             self.start_byzantine_ledger_service()?;
+            self.start_config_reloader();
 
             // Success.
             Ok(())
@@ -275,6 +303,10 @@ impl<
     pub fn stop(&mut self) -> Result<(), ConsensusServiceError> {
         log::debug!(self.logger, "Attempting to stop node...");
 
+        if let Some(ref mut config_reloader) = self.config_reloader.take() {
+            config_reloader.stop();
+        }
+
         // This will join the peer_keepalive in drop if we are the last thread holding
         // it
         self.peer_keepalive = None;
@@ -306,6 +338,7 @@ impl<
         // it
         self.byzantine_ledger = None;
 
+        #[cfg(feature = "sgx-enclave")]
         if let Some(ref mut report_cache_thread) = self.report_cache_thread.take() {
             report_cache_thread.stop()?;
         }
@@ -332,32 +365,12 @@ impl<
         );
 
         // Setup GRPC services.
-        let enclave = Arc::new(self.enclave.clone());
-
-        let client_service =
-            consensus_client_grpc::create_consensus_client_api(ClientApiService::new(
-                self.config.clone(),
-                enclave.clone(),
-                self.create_scp_client_value_sender_fn(),
-                Arc::new(self.ledger_db.clone()),
-                self.tx_manager.clone(),
-                self.mint_tx_manager.clone(),
-                self.create_is_serving_user_requests_fn(),
-                self.client_authenticator.clone(),
-                self.logger.clone(),
-            ));
-
-        let attested_service = create_attested_api(AttestedApiService::<ClientSession>::new(
-            enclave,
-            self.client_authenticator.clone(),
-            self.logger.clone(),
-        ));
-
         let blockchain_service =
             consensus_common_grpc::create_blockchain_api(BlockchainApiService::new(
                 self.ledger_db.clone(),
                 self.client_authenticator.clone(),
-                self.config.tokens().fee_map()?,
+                self.rate_limiter.clone(),
+                self.tokens_config.fee_map()?,
                 self.config.block_version,
                 self.logger.clone(),
             ));
@@ -382,12 +395,42 @@ impl<
                 .build(),
         );
 
-        let server_builder = ServerBuilder::new(env.clone())
-            .register_service(client_service)
+        let mut server_builder = ServerBuilder::new(env.clone())
             .register_service(blockchain_service)
             .register_service(health_service)
-            .register_service(attested_service)
-            .register_service(build_info_service)
+            .register_service(build_info_service);
+
+        // Observers do not accept transactions from clients.
+        if !self.config.observer {
+            let enclave = Arc::new(self.enclave.clone());
+
+            let client_service =
+                consensus_client_grpc::create_consensus_client_api(ClientApiService::new(
+                    self.config.clone(),
+                    enclave.clone(),
+                    self.create_scp_client_value_sender_fn(),
+                    Arc::new(self.ledger_db.clone()),
+                    self.tx_manager.clone(),
+                    self.mint_tx_manager.clone(),
+                    self.create_is_serving_user_requests_fn(),
+                    self.client_authenticator.clone(),
+                    self.rate_limiter.clone(),
+                    self.tx_trace_log.clone(),
+                    self.logger.clone(),
+                ));
+
+            let attested_service = create_attested_api(AttestedApiService::<ClientSession>::new(
+                enclave,
+                self.client_authenticator.clone(),
+                self.logger.clone(),
+            ));
+
+            server_builder = server_builder
+                .register_service(client_service)
+                .register_service(attested_service);
+        }
+
+        let server_builder = server_builder
             .set_default_channel_args(env)
             .bind_using_uri(&self.config.client_listen_uri, self.logger.clone());
 
@@ -404,15 +447,43 @@ impl<
         Ok(())
     }
 
+    #[cfg(all(feature = "sgx-enclave", not(feature = "insecure-enclave")))]
+    fn start_report_cache(&mut self) -> Result<(), ConsensusServiceError> {
+        self.report_cache_thread = Some(ReportCacheThread::start(
+            self.enclave.clone(),
+            self.ra_client.clone(),
+            self.config.ias_spid,
+            &counters::ENCLAVE_REPORT_TIMESTAMP,
+            self.logger.clone(),
+        )?);
+        Ok(())
+    }
+
+    // The insecure in-process enclave attests to itself once at startup, and
+    // has no quoting enclave to refresh its report with.
+    #[cfg(feature = "insecure-enclave")]
+    fn start_report_cache(&mut self) -> Result<(), ConsensusServiceError> {
+        log::error!(
+            self.logger,
+            "INSECURE: not refreshing the enclave's IAS report"
+        );
+        Ok(())
+    }
+
     fn start_admin_rpc_server(&mut self) -> Result<(), ConsensusServiceError> {
         if let Some(admin_listen_uri) = self.config.admin_listen_uri.as_ref() {
+            let consensus_admin_service = consensus_admin_grpc::create_consensus_admin_api(
+                AdminApiService::new(self.tx_trace_log.clone(), self.logger.clone()),
+            );
+
             self.admin_rpc_server = Some(
-                AdminServer::start(
+                AdminServer::start_with_services(
                     Some(self.env.clone()),
                     admin_listen_uri,
                     "Consensus Service".to_owned(),
                     self.config.peer_responder_id.to_string(),
                     Some(self.create_get_config_json_fn()),
+                    vec![consensus_admin_service],
                     self.logger.clone(),
                 )
                 .expect("Failed starting admin grpc server"),
@@ -452,11 +523,17 @@ impl<
             consensus_common_grpc::create_blockchain_api(BlockchainApiService::new(
                 self.ledger_db.clone(),
                 peer_authenticator.clone(),
-                self.config.tokens().fee_map()?,
+                Default::default(),
+                self.tokens_config.fee_map()?,
                 self.config.block_version,
                 self.logger.clone(),
             ));
 
+        // Peers may be added or removed when the network configuration is reloaded.
+        let peer_manager = self.peer_manager.clone();
+        let is_known_peer_fn =
+            Arc::new(move |responder_id: &ResponderId| peer_manager.conn(responder_id).is_some());
+
         let peer_service = consensus_peer_grpc::create_consensus_peer_api(PeerApiService::new(
             Arc::new(self.enclave.clone()),
             Arc::new(self.ledger_db.clone()),
@@ -464,7 +541,8 @@ impl<
             self.consensus_msgs_from_network.get_sender_fn(),
             self.create_scp_client_value_sender_fn(),
             get_highest_scp_message_fn,
-            self.peer_manager.responder_ids(),
+            is_known_peer_fn,
+            self.tx_trace_log.clone(),
             self.logger.clone(),
         ));
 
@@ -499,7 +577,14 @@ impl<
     }
 
     fn start_byzantine_ledger_service(&mut self) -> Result<(), ConsensusServiceError> {
-        log::info!(self.logger, "Starting ByzantineLedger service.");
+        if self.config.observer {
+            log::info!(
+                self.logger,
+                "Starting ByzantineLedger service in observer mode."
+            );
+        } else {
+            log::info!(self.logger, "Starting ByzantineLedger service.");
+        }
 
         let byzantine_ledger_arc = self
             .byzantine_ledger
@@ -518,6 +603,8 @@ impl<
                 self.config.msg_signer_key.clone(),
                 self.config.network().tx_source_urls,
                 self.config.scp_debug_dump.clone(),
+                self.config.observer,
+                self.tx_trace_log.clone(),
                 self.logger.clone(),
             ))
             .is_err()
@@ -562,6 +649,110 @@ impl<
         Ok(())
     }
 
+    fn start_config_reloader(&mut self) {
+        let poll_interval = match self.config.config_reload_interval {
+            Some(poll_interval) => poll_interval,
+            None => return,
+        };
+
+        let mut paths = vec![self.config.network_path.clone()];
+        paths.extend(self.config.tokens_path.clone());
+
+        log::info!(
+            self.logger,
+            "Reloading {:?} when changed, checking every {:?}",
+            paths,
+            poll_interval,
+        );
+        self.config_reloader = Some(ConfigReloader::start(
+            paths,
+            poll_interval,
+            self.create_reload_config_fn(),
+            self.logger.clone(),
+        ));
+    }
+
+    /// Creates a function that reloads the network and tokens configuration,
+    /// and applies changes to our peers, quorum set and client rate limits.
+    /// Nothing is applied if the configuration is invalid, or if the tokens
+    /// configuration differs from the one the enclave was initialized with.
+    fn create_reload_config_fn(&self) -> ReloadConfigFn {
+        let config = self.config.clone();
+        let enclave = self.enclave.clone();
+        let tokens_config = self.tokens_config.clone();
+        let local_node_id = self.local_node_id.clone();
+        let env = self.env.clone();
+        let peer_manager = self.peer_manager.clone();
+        let broadcaster = self.broadcaster.clone();
+        let peer_keepalive = self
+            .peer_keepalive
+            .as_ref()
+            .map(Arc::downgrade)
+            .expect("Server was not initialized");
+        let byzantine_ledger = self
+            .byzantine_ledger
+            .as_ref()
+            .map(Arc::downgrade)
+            .expect("Server was not initialized");
+        let rate_limiter = self.rate_limiter.clone();
+        let tx_source_urls = config.network().tx_source_urls;
+        let logger = self.logger.clone();
+
+        Arc::new(move || {
+            let network = config.try_network()?;
+            let quorum_set = network.try_quorum_set()?;
+            validate_tokens_config(&enclave, &tokens_config, &config.try_tokens()?)?;
+
+            // Connections to peers whose URI did not change are kept.
+            let peers: Vec<PeerConnection<E>> = network
+                .broadcast_peers()
+                .into_iter()
+                .map(|peer_uri| {
+                    PeerConnection::new(
+                        enclave.clone(),
+                        local_node_id.clone(),
+                        peer_uri,
+                        env.clone(),
+                        logger.clone(),
+                    )
+                })
+                .collect();
+            let (added, removed) = peer_manager.update_conns(peers);
+            log::info!(
+                logger,
+                "Peers added or changed: {:?}, peers removed: {:?}",
+                added,
+                removed
+            );
+            broadcaster
+                .lock()
+                .expect("lock poisoned")
+                .update_peers(&peer_manager);
+            if let Some(peer_keepalive) = peer_keepalive.upgrade() {
+                peer_keepalive.update_peers(&added, &removed);
+            }
+
+            // The new quorum set is used starting with the next slot.
+            log::info!(logger, "Quorum set: {:?}", quorum_set);
+            if let Some(ledger) = byzantine_ledger.upgrade() {
+                if let Some(ledger) = ledger.get() {
+                    ledger.set_quorum_set(quorum_set);
+                }
+            }
+
+            rate_limiter.set_config(network.client_rate_limits);
+
+            if network.tx_source_urls != tx_source_urls {
+                log::warn!(
+                    logger,
+                    "tx_source_urls changed, this only takes effect after a restart"
+                );
+            }
+
+            Ok(())
+        })
+    }
+
     /// Creates a function that returns true if the node is currently serving
     /// user requests.
     fn create_is_serving_user_requests_fn(&self) -> Arc<dyn Fn() -> bool + Sync + Send> {
@@ -590,32 +781,9 @@ impl<
         let tx_manager = self.tx_manager.clone();
         let local_node_id = self.local_node_id.clone();
         let broadcaster = self.broadcaster.clone();
+        let tx_trace_log = self.tx_trace_log.clone();
 
-        // Figure out which node IDs we are going to be relaying received transactions
-        // from. See comment below ("Broadcast to peers") for more details.
-        let relay_from_nodes: Vec<ResponderId> = self
-            .peer_manager
-            .conns()
-            .iter()
-            .filter_map(|conn| {
-                let uri = conn.uri();
-                if uri.consensus_relay_incoming_txs() {
-                    match uri.responder_id() {
-                        Ok(responder_id) => Some(responder_id),
-                        Err(_e) => {
-                            log::warn!(
-                                self.logger,
-                                "Could not get responder_id from {:?}",
-                                uri.to_string()
-                            );
-                            None
-                        }
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect();
+        let peer_manager = self.peer_manager.clone();
 
         Arc::new(move |scp_value, origin_node, relayed_from| {
             let origin_node = origin_node.unwrap_or(&local_node_id);
@@ -632,7 +800,7 @@ impl<
                     // selectively have incoming transactions from certain peers be
                     // relayed to other peers in order to improve consensus time.
                     if origin_node == &local_node_id
-                        || relay_from_nodes.contains(&origin_node.responder_id)
+                        || relays_incoming_txs(&peer_manager, &origin_node.responder_id)
                     {
                         if let Some(encrypted_tx) = tx_manager.get_encrypted_tx(&tx_hash) {
                             broadcaster
@@ -644,6 +812,7 @@ impl<
                                     origin_node,
                                     relayed_from.unwrap_or(&local_node_id.responder_id),
                                 );
+                            tx_trace_log.record(&tx_hash, TxEventKind::Relayed);
                         } else {
                             // If a value was submitted to `scp_client_value_sender` that means it
                             // should've found it's way into the cache. Suddenly not having it there
@@ -686,6 +855,11 @@ impl<
         let config = self.config.clone();
         let logger = self.logger.clone();
         Arc::new(move || {
+            // The network configuration file may have been changed since the node started.
+            let network = config
+                .try_network()
+                .map_err(|e| log::error!(logger, "Error loading network configuration {:?}", e))
+                .ok();
             let mut sync_status = "synced";
             let mut peer_block_height: u64 = 0;
             byzantine_ledger.upgrade().map(|ledger| {
@@ -756,13 +930,14 @@ impl<
                     "scp_debug_dump": config.scp_debug_dump,
                     "client_auth_token_enabled": config.client_auth_token_secret.map(|_| true).unwrap_or(false),
                     "client_auth_token_max_lifetime": config.client_auth_token_max_lifetime.as_secs(),
+                    "observer": config.observer,
                 },
-                "network": config.network(),
+                "network": network,
                 "status": {
                     "block_height": block_height,
                     "version": VERSION,
-                    "broadcast_peer_count": config.network().broadcast_peers.len(),
-                    "known_peer_count": config.network().known_peers.map_or(0, |x| x.len()),
+                    "broadcast_peer_count": network.as_ref().map_or(0, |x| x.broadcast_peers.len()),
+                    "known_peer_count": network.as_ref().and_then(|x| x.known_peers.as_ref()).map_or(0, |x| x.len()),
                     "sync_status": sync_status,
                     "blocks_behind": blocks_behind,
                     "latest_block_hash": latest_block_hash,
@@ -774,6 +949,17 @@ impl<
     }
 }
 
+/// Returns true if transactions received from the given peer should be relayed
+/// to our other peers.
+fn relays_incoming_txs<CC: Connection<Uri = ConsensusPeerUri>>(
+    peer_manager: &ConnectionManager<CC>,
+    responder_id: &ResponderId,
+) -> bool {
+    peer_manager
+        .conn(responder_id)
+        .map_or(false, |conn| conn.uri().consensus_relay_incoming_txs())
+}
+
 impl<
         E: ConsensusEnclave + Clone + Send + Sync + 'static,
         R: RaClient + Send + Sync + 'static,
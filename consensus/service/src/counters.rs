@@ -78,6 +78,39 @@ lazy_static::lazy_static! {
     // Number of entries in the transactions cache.
     pub static ref TX_CACHE_NUM_ENTRIES: IntGauge = OP_COUNTERS.gauge("tx_cache_num_entries");
 
+    // Number of entries in the transactions cache that were not evicted or replaced.
+    pub static ref TX_CACHE_NUM_LIVE_ENTRIES: IntGauge = OP_COUNTERS.gauge("tx_cache_num_live_entries");
+
+    // Maximum number of live entries in the transactions cache.
+    pub static ref TX_CACHE_CAPACITY: IntGauge = OP_COUNTERS.gauge("tx_cache_capacity");
+
+    // Transactions evicted from the transactions cache to make room for higher priority ones.
+    pub static ref TX_CACHE_EVICTED: IntCounter = OP_COUNTERS.counter("tx_cache_evicted");
+
+    // Transactions replaced by a higher priority transaction spending the same key image.
+    pub static ref TX_CACHE_REPLACED: IntCounter = OP_COUNTERS.counter("tx_cache_replaced");
+
+    // Transactions rejected because the transactions cache is full.
+    pub static ref TX_CACHE_REJECTED_FULL: IntCounter = OP_COUNTERS.counter("tx_cache_rejected_full");
+
+    // Transactions rejected because they spend the key image of a transaction with at least the same priority.
+    pub static ref TX_CACHE_REJECTED_CONFLICT: IntCounter = OP_COUNTERS.counter("tx_cache_rejected_conflict");
+
+    // Client requests rejected for exceeding their client's request rate.
+    pub static ref CLIENT_RATE_LIMITED: IntCounter = OP_COUNTERS.counter("client_rate_limited");
+
+    // Client requests rejected for exceeding their client's concurrent requests quota.
+    pub static ref CLIENT_RATE_LIMITED_CONCURRENT: IntCounter = OP_COUNTERS.counter("client_rate_limited_concurrent");
+
+    // Time between an observer externalizing a slot and appending its block.
+    pub static ref OBSERVER_BLOCK_FETCH_TIME: Histogram = OP_COUNTERS.histogram("observer_block_fetch_time");
+
+    // Number of times the network and tokens configuration was reloaded.
+    pub static ref CONFIG_RELOADS: IntCounter = OP_COUNTERS.counter("config_reloads");
+
+    // Number of configuration reloads that were rejected.
+    pub static ref CONFIG_RELOAD_FAILURES: IntCounter = OP_COUNTERS.counter("config_reload_failures");
+
     // Number of consensus messages dropped due to referencing an invalid previous block id.
     pub static ref SCP_MESSAGES_DROPPED_DUE_TO_INVALID_PREV_BLOCK_ID: IntCounter = OP_COUNTERS.counter("scp_messages_dropped_due_to_invalid_prev_block_id");
 
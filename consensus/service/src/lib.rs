@@ -7,13 +7,18 @@
 #[cfg(test)]
 extern crate test;
 
+#[cfg(not(any(feature = "sgx-enclave", feature = "insecure-enclave")))]
+compile_error!("One of the `sgx-enclave` or `insecure-enclave` features must be enabled");
+
 pub mod consensus_service;
 pub mod mint_tx_manager;
 pub mod tx_manager;
+pub mod tx_trace;
 pub mod validators; // Public so that it can be benchmarked by the `benchmarks` crate.
 
 mod api;
 mod background_work_queue;
 mod byzantine_ledger;
+mod config_reloader;
 mod counters;
 mod peer_keepalive;
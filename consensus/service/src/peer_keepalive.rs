@@ -94,6 +94,22 @@ impl PeerKeepalive {
         responder_id_to_last_heard.insert(responder_id, Instant::now());
     }
 
+    /// Stop pinging peers that were removed, and start tracking peers that
+    /// were added, assuming we just heard from them.
+    pub fn update_peers(&self, added: &[ResponderId], removed: &[ResponderId]) {
+        let mut responder_id_to_last_heard = self
+            .responder_id_to_last_heard
+            .lock()
+            .expect("mutex poisoned");
+        for responder_id in removed {
+            responder_id_to_last_heard.remove(responder_id);
+        }
+        let now = Instant::now();
+        for responder_id in added {
+            responder_id_to_last_heard.insert(responder_id.clone(), now);
+        }
+    }
+
     fn thread_entrypoint<CC: ConsensusConnection>(
         conn_manager: ConnectionManager<CC>,
         stop_requested: Arc<AtomicBool>,
@@ -1,7 +1,7 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
 use displaydoc::Display;
-use mc_consensus_enclave::Error as ConsensusEnclaveError;
+use mc_consensus_enclave_api::Error as ConsensusEnclaveError;
 use mc_ledger_db::Error as LedgerDbError;
 use mc_transaction_core::{tx::TxHash, validation::TransactionValidationError};
 
@@ -18,6 +18,13 @@ pub enum TxManagerError {
 
     /// Ledger error: {0}
     LedgerDb(LedgerDbError),
+
+    /// Mempool is full, priority must exceed {0}
+    MempoolFull(u64),
+
+    /// Spends a key image also spent by {0}, which has at least the same
+    /// priority
+    KeyImageConflict(TxHash),
 }
 
 impl From<ConsensusEnclaveError> for TxManagerError {
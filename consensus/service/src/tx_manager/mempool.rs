@@ -0,0 +1,410 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! A bounded, fee-prioritized collection of well-formed transactions.
+//!
+//! Admission is decided when a transaction is inserted:
+//! * A transaction that spends a key image already spent by a cached
+//!   transaction is rejected, unless it has a strictly higher priority than
+//!   every transaction it conflicts with. In that case it replaces them.
+//! * When the mempool is full, a transaction is rejected unless it has a
+//!   strictly higher priority than the lowest-priority cached transaction,
+//!   which is then evicted.
+//!
+//! Transactions that are replaced or evicted stop counting towards the
+//! mempool's capacity immediately, but stay in the cache until the next call
+//! to `purge`. This way a transaction that is already referenced by the slot
+//! currently being externalized remains available until the slot completes.
+
+use super::{CacheEntry, TxManagerError, TxManagerResult};
+use mc_common::{HashMap, HashSet};
+use mc_transaction_core::{ring_signature::KeyImage, tx::TxHash};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The result of admitting a transaction into the mempool.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Admission {
+    /// Transactions replaced by the admitted one, due to conflicting key
+    /// images.
+    pub replaced: Vec<TxHash>,
+
+    /// Transactions evicted to make room for the admitted one.
+    pub evicted: Vec<TxHash>,
+}
+
+pub struct Mempool {
+    /// Maximum number of live entries.
+    capacity: usize,
+
+    /// All cached entries, including the ones that were replaced or evicted
+    /// but not yet purged.
+    entries: HashMap<TxHash, CacheEntry>,
+
+    /// Live entries, ordered by priority (lowest first).
+    by_priority: BTreeSet<(u64, TxHash)>,
+
+    /// Live entries, keyed by the key images they spend.
+    key_images: BTreeMap<KeyImage, TxHash>,
+
+    /// Entries that were replaced or evicted, and will be removed by the next
+    /// call to `purge`.
+    removed: HashSet<TxHash>,
+}
+
+impl Mempool {
+    /// Create an empty mempool holding up to `capacity` live entries.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::default(),
+            by_priority: BTreeSet::new(),
+            key_images: BTreeMap::new(),
+            removed: HashSet::default(),
+        }
+    }
+
+    /// Number of cached entries, including the ones that are pending removal.
+    pub fn num_entries(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Number of live entries.
+    pub fn num_live(&self) -> usize {
+        self.by_priority.len()
+    }
+
+    /// Returns true if an entry for the given hash is cached.
+    pub fn contains_key(&self, tx_hash: &TxHash) -> bool {
+        self.entries.contains_key(tx_hash)
+    }
+
+    /// Returns true if an entry for the given hash is cached and not pending
+    /// removal.
+    pub fn is_live(&self, tx_hash: &TxHash) -> bool {
+        self.entries.contains_key(tx_hash) && !self.removed.contains(tx_hash)
+    }
+
+    /// Get a cached entry.
+    pub fn get(&self, tx_hash: &TxHash) -> Option<&CacheEntry> {
+        self.entries.get(tx_hash)
+    }
+
+    /// The priority of a live entry. Entries that are pending removal have no
+    /// priority.
+    pub fn priority(&self, tx_hash: &TxHash) -> Option<u64> {
+        if !self.is_live(tx_hash) {
+            return None;
+        }
+        self.entries
+            .get(tx_hash)
+            .map(|entry| entry.context().priority())
+    }
+
+    /// Insert an entry, subject to the admission policy. An entry that was
+    /// replaced or evicted but not purged yet is admitted again as if it were
+    /// new.
+    pub fn insert(&mut self, tx_hash: TxHash, entry: CacheEntry) -> TxManagerResult<Admission> {
+        let priority = entry.context().priority();
+        if self.is_live(&tx_hash) {
+            return Ok(Admission::default());
+        }
+
+        // Live entries that spend any of the same key images.
+        let conflicts: BTreeSet<TxHash> = entry
+            .context()
+            .key_images()
+            .iter()
+            .filter_map(|key_image| self.key_images.get(key_image).cloned())
+            .collect();
+        for conflict in &conflicts {
+            let conflict_priority = self.entries[conflict].context().priority();
+            if conflict_priority >= priority {
+                return Err(TxManagerError::KeyImageConflict(*conflict));
+            }
+        }
+
+        // When full, the entry must outbid the lowest-priority entry it does not
+        // replace.
+        let num_live_after_replace = self.num_live() - conflicts.len();
+        let to_evict = if num_live_after_replace >= self.capacity {
+            let lowest = self
+                .by_priority
+                .iter()
+                .find(|(_, tx_hash)| !conflicts.contains(tx_hash))
+                .cloned();
+            match lowest {
+                Some((lowest_priority, lowest_tx_hash)) if lowest_priority < priority => {
+                    Some(lowest_tx_hash)
+                }
+                Some((lowest_priority, _)) => {
+                    return Err(TxManagerError::MempoolFull(lowest_priority))
+                }
+                // A mempool with no capacity admits nothing.
+                None => return Err(TxManagerError::MempoolFull(u64::MAX)),
+            }
+        } else {
+            None
+        };
+
+        let admission = Admission {
+            replaced: conflicts.into_iter().collect(),
+            evicted: to_evict.into_iter().collect(),
+        };
+        for tx_hash in admission.replaced.iter().chain(admission.evicted.iter()) {
+            self.mark_removed(tx_hash);
+        }
+        self.removed.remove(&tx_hash);
+        self.insert_live(tx_hash, entry);
+
+        Ok(admission)
+    }
+
+    /// Insert an entry regardless of the admission policy, e.g. because it is
+    /// referenced by a consensus message and must be available to validate
+    /// and externalize it. Lower-priority entries are evicted to make room,
+    /// if possible. An entry that was replaced or evicted but not purged yet is
+    /// live again.
+    pub fn force_insert(&mut self, tx_hash: TxHash, entry: CacheEntry) -> Admission {
+        if self.is_live(&tx_hash) {
+            return Admission::default();
+        }
+        self.removed.remove(&tx_hash);
+        self.insert_live(tx_hash, entry);
+
+        let mut admission = Admission::default();
+        while self.num_live() > self.capacity {
+            let lowest = self
+                .by_priority
+                .iter()
+                .find(|(_, other)| *other != tx_hash)
+                .map(|(_, other)| *other);
+            match lowest {
+                Some(lowest_tx_hash) => {
+                    self.mark_removed(&lowest_tx_hash);
+                    admission.evicted.push(lowest_tx_hash);
+                }
+                None => break,
+            }
+        }
+        admission
+    }
+
+    /// Remove the entries that were replaced or evicted, and the entries for
+    /// which the predicate returns false. Returns the hashes of all removed
+    /// entries.
+    pub fn purge<F>(&mut self, predicate: F) -> HashSet<TxHash>
+    where
+        F: Fn(&CacheEntry) -> bool,
+    {
+        let mut purged = std::mem::take(&mut self.removed);
+        purged.extend(
+            self.entries
+                .iter()
+                .filter(|(_, entry)| !predicate(entry))
+                .map(|(tx_hash, _)| *tx_hash),
+        );
+        for tx_hash in &purged {
+            self.unindex(tx_hash);
+            self.entries.remove(tx_hash);
+        }
+        purged
+    }
+
+    fn insert_live(&mut self, tx_hash: TxHash, entry: CacheEntry) {
+        self.by_priority
+            .insert((entry.context().priority(), tx_hash));
+        for key_image in entry.context().key_images() {
+            // A forced insert may spend a key image that is already indexed. The
+            // existing entry keeps it.
+            self.key_images.entry(*key_image).or_insert(tx_hash);
+        }
+        self.entries.insert(tx_hash, entry);
+    }
+
+    /// Mark a cached entry as removed, e.g. because its transaction was
+    /// externalized. It stops counting against the capacity and spending its
+    /// key images, but remains cached until the next call to `purge`.
+    pub fn mark_removed(&mut self, tx_hash: &TxHash) {
+        if self.entries.contains_key(tx_hash) {
+            self.unindex(tx_hash);
+            self.removed.insert(*tx_hash);
+        }
+    }
+
+    /// Remove an entry from the priority and key image indices.
+    fn unindex(&mut self, tx_hash: &TxHash) {
+        if let Some(entry) = self.entries.get(tx_hash) {
+            self.by_priority
+                .remove(&(entry.context().priority(), *tx_hash));
+            for key_image in entry.context().key_images() {
+                if self.key_images.get(key_image) == Some(tx_hash) {
+                    self.key_images.remove(key_image);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_consensus_enclave_api::WellFormedTxContext;
+    use std::sync::Arc;
+
+    fn hash(id: u8) -> TxHash {
+        TxHash([id; 32])
+    }
+
+    fn entry(id: u8, priority: u64, key_images: &[u8]) -> CacheEntry {
+        CacheEntry {
+            encrypted_tx: Default::default(),
+            context: Arc::new(WellFormedTxContext::new(
+                priority,
+                hash(id),
+                Default::default(),
+                key_images
+                    .iter()
+                    .map(|i| KeyImage::from(*i as u64))
+                    .collect(),
+                Default::default(),
+                Default::default(),
+            )),
+        }
+    }
+
+    #[test]
+    // Should evict the lowest-priority entry when full, and reject entries that
+    // do not outbid it.
+    fn test_insert_evicts_lowest_priority() {
+        let mut mempool = Mempool::new(3);
+        for (id, priority) in &[(1, 10), (2, 30), (3, 20)] {
+            assert_eq!(
+                mempool
+                    .insert(hash(*id), entry(*id, *priority, &[*id]))
+                    .unwrap(),
+                Admission::default()
+            );
+        }
+
+        // Equal priority does not evict.
+        match mempool.insert(hash(4), entry(4, 10, &[4])) {
+            Err(TxManagerError::MempoolFull(10)) => {} // This is expected.
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let admission = mempool.insert(hash(5), entry(5, 11, &[5])).unwrap();
+        assert_eq!(admission.evicted, vec![hash(1)]);
+        assert_eq!(mempool.num_live(), 3);
+        assert_eq!(mempool.priority(&hash(1)), None);
+
+        // Evicted entries remain cached until purged.
+        assert_eq!(mempool.num_entries(), 4);
+        assert!(mempool.contains_key(&hash(1)));
+
+        let purged = mempool.purge(|_| true);
+        assert_eq!(purged.len(), 1);
+        assert!(purged.contains(&hash(1)));
+        assert_eq!(mempool.num_entries(), 3);
+        assert!(!mempool.contains_key(&hash(1)));
+    }
+
+    #[test]
+    // Resubmitting an evicted entry before it is purged must go through
+    // admission again, rather than be reported as cached.
+    fn test_resubmit_after_eviction() {
+        let mut mempool = Mempool::new(2);
+        mempool.insert(hash(1), entry(1, 10, &[1])).unwrap();
+        mempool.insert(hash(2), entry(2, 20, &[2])).unwrap();
+        let admission = mempool.insert(hash(3), entry(3, 30, &[3])).unwrap();
+        assert_eq!(admission.evicted, vec![hash(1)]);
+        assert!(!mempool.is_live(&hash(1)));
+
+        // It still does not outbid the lowest-priority live entry.
+        match mempool.insert(hash(1), entry(1, 10, &[1])) {
+            Err(TxManagerError::MempoolFull(20)) => {} // This is expected.
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(!mempool.is_live(&hash(1)));
+
+        // Once there is room, it is live again and survives the next purge.
+        let admission = mempool.insert(hash(4), entry(4, 40, &[2, 3])).unwrap();
+        assert_eq!(admission.replaced, vec![hash(2), hash(3)]);
+        assert_eq!(mempool.num_live(), 1);
+
+        mempool.insert(hash(1), entry(1, 10, &[1])).unwrap();
+        assert!(mempool.is_live(&hash(1)));
+        assert_eq!(mempool.priority(&hash(1)), Some(10));
+        assert_eq!(mempool.num_live(), 2);
+
+        let purged = mempool.purge(|_| true);
+        assert_eq!(purged.len(), 2);
+        assert!(!purged.contains(&hash(1)));
+        assert!(mempool.is_live(&hash(1)));
+    }
+
+    #[test]
+    // Should only replace conflicting entries when paying more.
+    fn test_insert_key_image_conflict() {
+        let mut mempool = Mempool::new(10);
+        mempool.insert(hash(1), entry(1, 10, &[1, 2])).unwrap();
+        mempool.insert(hash(2), entry(2, 20, &[3])).unwrap();
+
+        match mempool.insert(hash(3), entry(3, 10, &[2])) {
+            Err(TxManagerError::KeyImageConflict(tx_hash)) => {
+                assert_eq!(tx_hash, hash(1))
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Must outbid every conflicting entry.
+        match mempool.insert(hash(4), entry(4, 15, &[2, 3])) {
+            Err(TxManagerError::KeyImageConflict(tx_hash)) => {
+                assert_eq!(tx_hash, hash(2))
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let admission = mempool.insert(hash(5), entry(5, 25, &[2, 3])).unwrap();
+        assert_eq!(admission.replaced, vec![hash(1), hash(2)]);
+        assert!(admission.evicted.is_empty());
+        assert_eq!(mempool.num_live(), 1);
+
+        // The replaced entries no longer block their key images.
+        mempool.insert(hash(6), entry(6, 1, &[1])).unwrap();
+        assert_eq!(mempool.num_live(), 2);
+    }
+
+    #[test]
+    // A replacement does not need to evict when it frees room itself.
+    fn test_replace_when_full() {
+        let mut mempool = Mempool::new(2);
+        mempool.insert(hash(1), entry(1, 10, &[1])).unwrap();
+        mempool.insert(hash(2), entry(2, 5, &[2])).unwrap();
+
+        let admission = mempool.insert(hash(3), entry(3, 11, &[1])).unwrap();
+        assert_eq!(admission.replaced, vec![hash(1)]);
+        assert!(admission.evicted.is_empty());
+        assert_eq!(mempool.priority(&hash(2)), Some(5));
+    }
+
+    #[test]
+    // Forced inserts are always cached, evicting other entries if needed.
+    fn test_force_insert() {
+        let mut mempool = Mempool::new(2);
+        mempool.insert(hash(1), entry(1, 10, &[1])).unwrap();
+        mempool.insert(hash(2), entry(2, 20, &[2])).unwrap();
+
+        let admission = mempool.force_insert(hash(3), entry(3, 1, &[2]));
+        assert_eq!(admission.evicted, vec![hash(1)]);
+        assert!(mempool.contains_key(&hash(3)));
+        assert_eq!(mempool.num_live(), 2);
+
+        // Purging entry 3 must not unindex the key image held by entry 2.
+        mempool.purge(|entry| entry.context().priority() != 1);
+        match mempool.insert(hash(4), entry(4, 15, &[2])) {
+            Err(TxManagerError::KeyImageConflict(tx_hash)) => {
+                assert_eq!(tx_hash, hash(2))
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
@@ -6,14 +6,16 @@
 //! Internally, TxManager maintains a collection of (encrypted) transactions
 //! that have been found to be well-formed. These can be thought of as the
 //! "working set" of transactions that the consensus service may operate on.
+//! The working set is a bounded mempool that favors transactions with a
+//! higher priority (fee), see `mempool` for details.
 
 use crate::counters;
 use mc_attest_enclave_api::{EnclaveMessage, PeerSession};
 use mc_common::{
     logger::{log, Logger},
-    HashMap, HashSet,
+    HashSet,
 };
-use mc_consensus_enclave::{
+use mc_consensus_enclave_api::{
     ConsensusEnclave, TxContext, WellFormedEncryptedTx, WellFormedTxContext,
 };
 use mc_transaction_core::{
@@ -23,6 +25,7 @@ use mc_transaction_core::{
 use std::sync::{Arc, Mutex, MutexGuard};
 
 mod error;
+mod mempool;
 mod tx_manager_trait;
 mod untrusted_interfaces;
 
@@ -30,6 +33,8 @@ pub use error::{TxManagerError, TxManagerResult};
 pub use tx_manager_trait::TxManager;
 pub use untrusted_interfaces::UntrustedInterfaces;
 
+use mempool::{Admission, Mempool};
+
 #[cfg(test)]
 pub use tx_manager_trait::MockTxManager;
 
@@ -61,20 +66,27 @@ pub struct TxManagerImpl<E: ConsensusEnclave + Send, UI: UntrustedInterfaces + S
     untrusted: UI,
 
     /// Well-formed transactions, keyed by hash.
-    cache: Arc<Mutex<HashMap<TxHash, CacheEntry>>>,
+    cache: Arc<Mutex<Mempool>>,
 
     /// Logger.
     logger: Logger,
 }
 
 impl<E: ConsensusEnclave + Send, UI: UntrustedInterfaces + Send> TxManagerImpl<E, UI> {
-    /// Construct a new TxManager instance.
+    /// Construct a new TxManager instance with an unbounded cache.
     pub fn new(enclave: E, untrusted: UI, logger: Logger) -> Self {
+        Self::with_capacity(enclave, untrusted, usize::MAX, logger)
+    }
+
+    /// Construct a new TxManager instance that caches up to `capacity`
+    /// transactions.
+    pub fn with_capacity(enclave: E, untrusted: UI, capacity: usize, logger: Logger) -> Self {
+        counters::TX_CACHE_CAPACITY.set(capacity.min(i64::MAX as usize) as i64);
         Self {
             enclave,
             untrusted,
             logger,
-            cache: Arc::new(Mutex::new(HashMap::default())),
+            cache: Arc::new(Mutex::new(Mempool::new(capacity))),
         }
     }
 
@@ -101,14 +113,14 @@ impl<E: ConsensusEnclave + Send, UI: UntrustedInterfaces + Send> TxManagerImpl<E
         })
     }
 
-    fn lock_cache(&self) -> MutexGuard<HashMap<TxHash, CacheEntry>> {
+    fn lock_cache(&self) -> MutexGuard<Mempool> {
         self.cache.lock().expect("Lock poisoned")
     }
 
     /// A utility method for resolving a list of TxHashes into CacheEntries that
     /// errors if any hashes are missing.
     fn get_cache_entries<'a, 'b, I>(
-        cache: &'a MutexGuard<HashMap<TxHash, CacheEntry>>,
+        cache: &'a MutexGuard<Mempool>,
         tx_hashes: I,
     ) -> Result<Vec<&'a CacheEntry>, TxManagerError>
     where
@@ -136,6 +148,24 @@ impl<E: ConsensusEnclave + Send, UI: UntrustedInterfaces + Send> TxManagerImpl<E
             .map(|(_tx_hash, entry)| entry.unwrap())
             .collect())
     }
+
+    /// Update metrics and log after a transaction was admitted into the cache.
+    fn admitted(&self, tx_hash: &TxHash, admission: &Admission, cache: &Mempool) {
+        counters::TX_CACHE_NUM_ENTRIES.set(cache.num_entries() as i64);
+        counters::TX_CACHE_NUM_LIVE_ENTRIES.set(cache.num_live() as i64);
+        counters::TX_CACHE_REPLACED.inc_by(admission.replaced.len() as u64);
+        counters::TX_CACHE_EVICTED.inc_by(admission.evicted.len() as u64);
+
+        if !admission.replaced.is_empty() || !admission.evicted.is_empty() {
+            log::debug!(
+                self.logger,
+                "Transaction {} replaced {:?} and evicted {:?}",
+                tx_hash,
+                admission.replaced,
+                admission.evicted,
+            );
+        }
+    }
 }
 
 impl<E: ConsensusEnclave + Send, UI: UntrustedInterfaces + Send> TxManager
@@ -148,9 +178,10 @@ impl<E: ConsensusEnclave + Send, UI: UntrustedInterfaces + Send> TxManager
 
         {
             let cache = self.lock_cache();
-            if let Some(entry) = cache.get(&tx_context.tx_hash) {
-                // The transaction is well-formed and is in the cache.
-                return Ok(*entry.context.tx_hash());
+            if cache.is_live(&tx_hash) {
+                // The transaction is well-formed and is in the cache. Evicted or
+                // replaced transactions have to be admitted again.
+                return Ok(tx_hash);
             }
         }
 
@@ -158,8 +189,17 @@ impl<E: ConsensusEnclave + Send, UI: UntrustedInterfaces + Send> TxManager
 
         {
             let mut cache = self.lock_cache();
-            cache.insert(tx_hash, new_entry);
-            counters::TX_CACHE_NUM_ENTRIES.set(cache.len() as i64);
+            let admission = cache.insert(tx_hash, new_entry).map_err(|err| {
+                match err {
+                    TxManagerError::MempoolFull(_) => counters::TX_CACHE_REJECTED_FULL.inc(),
+                    TxManagerError::KeyImageConflict(_) => {
+                        counters::TX_CACHE_REJECTED_CONFLICT.inc()
+                    }
+                    _ => {}
+                }
+                err
+            })?;
+            self.admitted(&tx_hash, &admission, &cache);
         }
 
         log::trace!(
@@ -171,36 +211,67 @@ impl<E: ConsensusEnclave + Send, UI: UntrustedInterfaces + Send> TxManager
         Ok(tx_hash)
     }
 
-    /// Remove expired transactions from the cache and return their hashes.
+    /// Insert a transaction that is referenced by a consensus message into the
+    /// cache, regardless of the cache's capacity and of conflicting key
+    /// images. The transaction must be well-formed.
+    fn insert_referenced(&self, tx_context: TxContext) -> TxManagerResult<TxHash> {
+        let tx_hash = tx_context.tx_hash;
+
+        // Evicted or replaced transactions have to be inserted again, or they
+        // would be removed while consensus still needs them.
+        if self.lock_cache().is_live(&tx_hash) {
+            return Ok(tx_hash);
+        }
+
+        let new_entry = self.is_well_formed(tx_context)?;
+
+        {
+            let mut cache = self.lock_cache();
+            let admission = cache.force_insert(tx_hash, new_entry);
+            self.admitted(&tx_hash, &admission, &cache);
+        }
+
+        log::trace!(
+            self.logger,
+            "Cached referenced transaction {hash}",
+            hash = tx_hash.to_string(),
+        );
+
+        Ok(tx_hash)
+    }
+
+    /// Remove expired transactions, and transactions that were evicted or
+    /// replaced since the last call, from the cache and return their hashes.
     ///
     /// # Arguments
     /// * `block_index` - Current block index.
     fn remove_expired(&self, block_index: u64) -> HashSet<TxHash> {
-        let mut expired = HashSet::<TxHash>::default();
-
         let mut cache = self.lock_cache();
 
-        // find the expired entries and remove them, storing their keys in expired,
-        // without destroying or re-allocating the cache
-        cache.retain(|key, entry| -> bool {
-            if entry.context().tombstone_block() <= block_index {
-                expired.insert(*key);
-                false
-            } else {
-                true
-            }
-        });
+        let removed = cache.purge(|entry| entry.context().tombstone_block() > block_index);
 
-        counters::TX_CACHE_NUM_ENTRIES.set(cache.len() as i64);
+        counters::TX_CACHE_NUM_ENTRIES.set(cache.num_entries() as i64);
+        counters::TX_CACHE_NUM_LIVE_ENTRIES.set(cache.num_live() as i64);
 
         log::debug!(
             self.logger,
-            "Removed {} expired transactions, retained {}",
-            expired.len(),
-            cache.len(),
+            "Removed {} expired or evicted transactions, retained {}",
+            removed.len(),
+            cache.num_entries(),
         );
 
-        expired
+        removed
+    }
+
+    /// Mark transactions that were externalized as removed. They no longer
+    /// take up room in the cache, and are removed by the next call to
+    /// `remove_expired`.
+    fn remove_externalized(&self, tx_hashes: &[TxHash]) {
+        let mut cache = self.lock_cache();
+        for tx_hash in tx_hashes {
+            cache.mark_removed(tx_hash);
+        }
+        counters::TX_CACHE_NUM_LIVE_ENTRIES.set(cache.num_live() as i64);
     }
 
     /// Returns true if the cache contains the corresponding transaction.
@@ -210,7 +281,12 @@ impl<E: ConsensusEnclave + Send, UI: UntrustedInterfaces + Send> TxManager
 
     /// Number of cached entries.
     fn num_entries(&self) -> usize {
-        self.lock_cache().len()
+        self.lock_cache().num_entries()
+    }
+
+    /// The priority of a cached transaction, unless it was evicted or replaced.
+    fn priority(&self, tx_hash: &TxHash) -> Option<u64> {
+        self.lock_cache().priority(tx_hash)
     }
 
     /// Validate the transaction corresponding to the given hash against the
@@ -337,7 +413,8 @@ mod tests {
     use crate::tx_manager::untrusted_interfaces::MockUntrustedInterfaces;
     use mc_common::logger::test_with_logger;
     use mc_consensus_enclave_mock::{Error as EnclaveError, MockConsensusEnclave};
-    use mc_transaction_core::validation::TransactionValidationError;
+    use mc_transaction_core::{ring_signature::KeyImage, validation::TransactionValidationError};
+    use std::iter::FromIterator;
 
     #[test_with_logger]
     // Should return Ok when a well-formed Tx is inserted.
@@ -474,6 +551,250 @@ mod tests {
         assert_eq!(tx_manager.num_entries(), 0);
     }
 
+    /// A cache entry with the given priority and key images.
+    fn cache_entry(tx_hash: TxHash, priority: u64, key_images: Vec<KeyImage>) -> CacheEntry {
+        CacheEntry {
+            encrypted_tx: Default::default(),
+            context: Arc::new(WellFormedTxContext::new(
+                priority,
+                tx_hash,
+                100,
+                key_images,
+                Default::default(),
+                Default::default(),
+            )),
+        }
+    }
+
+    /// A tx manager with the given capacity, that deems a single transaction
+    /// well-formed.
+    fn tx_manager_for_insert(
+        capacity: usize,
+        entry: CacheEntry,
+        logger: Logger,
+    ) -> TxManagerImpl<MockConsensusEnclave, MockUntrustedInterfaces> {
+        let mut mock_untrusted = MockUntrustedInterfaces::new();
+        mock_untrusted
+            .expect_well_formed_check()
+            .times(1)
+            .return_const(Ok((0, vec![])));
+
+        let mut mock_enclave = MockConsensusEnclave::new();
+        mock_enclave
+            .expect_tx_is_well_formed()
+            .times(1)
+            .return_const(Ok((
+                entry.encrypted_tx().clone(),
+                entry.context().as_ref().clone(),
+            )));
+
+        TxManagerImpl::with_capacity(mock_enclave, mock_untrusted, capacity, logger)
+    }
+
+    #[test_with_logger]
+    // Should evict the lowest priority transaction when the cache is full, and
+    // remove it on the next call to `remove_expired`.
+    fn test_insert_evicts_when_full(logger: Logger) {
+        let low = TxHash([1u8; 32]);
+        let high = TxHash([2u8; 32]);
+        let tx_manager = tx_manager_for_insert(1, cache_entry(high, 20, vec![]), logger.clone());
+        tx_manager
+            .lock_cache()
+            .insert(low, cache_entry(low, 10, vec![]))
+            .unwrap();
+
+        let tx_context = TxContext {
+            tx_hash: high,
+            ..Default::default()
+        };
+        assert_eq!(tx_manager.insert(tx_context).unwrap(), high);
+        assert_eq!(tx_manager.priority(&high), Some(20));
+        assert_eq!(tx_manager.priority(&low), None);
+
+        // The evicted transaction stays available until the slot completes.
+        assert!(tx_manager.contains(&low));
+        let removed = tx_manager.remove_expired(0);
+        assert_eq!(removed, HashSet::from_iter(vec![low]));
+        assert!(!tx_manager.contains(&low));
+        assert_eq!(tx_manager.num_entries(), 1);
+    }
+
+    #[test_with_logger]
+    // Should reject a transaction that does not outbid the lowest priority
+    // transaction when the cache is full.
+    fn test_insert_error_mempool_full(logger: Logger) {
+        let low = TxHash([1u8; 32]);
+        let lower = TxHash([2u8; 32]);
+        let tx_manager = tx_manager_for_insert(1, cache_entry(lower, 5, vec![]), logger.clone());
+        tx_manager
+            .lock_cache()
+            .insert(low, cache_entry(low, 10, vec![]))
+            .unwrap();
+
+        let tx_context = TxContext {
+            tx_hash: lower,
+            ..Default::default()
+        };
+        match tx_manager.insert(tx_context) {
+            Err(TxManagerError::MempoolFull(10)) => {} // This is expected.
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(!tx_manager.contains(&lower));
+    }
+
+    #[test_with_logger]
+    // Should not report an evicted transaction as cached when it is resubmitted
+    // before being removed.
+    fn test_insert_resubmitted_after_eviction(logger: Logger) {
+        let low = TxHash([1u8; 32]);
+        let high = TxHash([2u8; 32]);
+        let tx_manager = tx_manager_for_insert(1, cache_entry(low, 10, vec![]), logger.clone());
+        {
+            let mut cache = tx_manager.lock_cache();
+            cache.insert(low, cache_entry(low, 10, vec![])).unwrap();
+            cache.insert(high, cache_entry(high, 20, vec![])).unwrap();
+        }
+        assert!(tx_manager.contains(&low));
+
+        let tx_context = TxContext {
+            tx_hash: low,
+            ..Default::default()
+        };
+        match tx_manager.insert(tx_context) {
+            Err(TxManagerError::MempoolFull(20)) => {} // This is expected.
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(tx_manager.priority(&low), None);
+        assert_eq!(tx_manager.remove_expired(0), HashSet::from_iter(vec![low]));
+    }
+
+    #[test_with_logger]
+    // Should reject a transaction that spends the key image of a cached
+    // transaction, unless it pays more.
+    fn test_insert_key_image_conflict(logger: Logger) {
+        let key_image = KeyImage::from(7);
+        let cached = TxHash([1u8; 32]);
+        let cheaper = TxHash([2u8; 32]);
+        let tx_manager =
+            tx_manager_for_insert(10, cache_entry(cheaper, 5, vec![key_image]), logger.clone());
+        tx_manager
+            .lock_cache()
+            .insert(cached, cache_entry(cached, 10, vec![key_image]))
+            .unwrap();
+
+        let tx_context = TxContext {
+            tx_hash: cheaper,
+            ..Default::default()
+        };
+        match tx_manager.insert(tx_context) {
+            Err(TxManagerError::KeyImageConflict(tx_hash)) => assert_eq!(tx_hash, cached),
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let pricier = TxHash([3u8; 32]);
+        let tx_manager =
+            tx_manager_for_insert(10, cache_entry(pricier, 15, vec![key_image]), logger);
+        tx_manager
+            .lock_cache()
+            .insert(cached, cache_entry(cached, 10, vec![key_image]))
+            .unwrap();
+
+        let tx_context = TxContext {
+            tx_hash: pricier,
+            ..Default::default()
+        };
+        assert!(tx_manager.insert(tx_context).is_ok());
+        assert_eq!(tx_manager.priority(&cached), None);
+        assert_eq!(
+            tx_manager.remove_expired(0),
+            HashSet::from_iter(vec![cached])
+        );
+    }
+
+    #[test_with_logger]
+    // Referenced transactions should be cached even when they would be rejected.
+    fn test_insert_referenced_ignores_admission_policy(logger: Logger) {
+        let key_image = KeyImage::from(7);
+        let cached = TxHash([1u8; 32]);
+        let referenced = TxHash([2u8; 32]);
+        let tx_manager =
+            tx_manager_for_insert(1, cache_entry(referenced, 5, vec![key_image]), logger);
+        tx_manager
+            .lock_cache()
+            .insert(cached, cache_entry(cached, 10, vec![key_image]))
+            .unwrap();
+
+        let tx_context = TxContext {
+            tx_hash: referenced,
+            ..Default::default()
+        };
+        assert_eq!(
+            tx_manager.insert_referenced(tx_context).unwrap(),
+            referenced
+        );
+        assert!(tx_manager.contains(&referenced));
+        assert_eq!(tx_manager.priority(&cached), None);
+    }
+
+    #[test_with_logger]
+    // Externalized transactions should stop taking up room and spending their
+    // key images, and be removed on the next call to `remove_expired`.
+    fn test_remove_externalized(logger: Logger) {
+        let key_image = KeyImage::from(7);
+        let externalized = TxHash([1u8; 32]);
+        let cheaper = TxHash([2u8; 32]);
+        let tx_manager = tx_manager_for_insert(1, cache_entry(cheaper, 5, vec![key_image]), logger);
+        tx_manager
+            .lock_cache()
+            .insert(externalized, cache_entry(externalized, 10, vec![key_image]))
+            .unwrap();
+
+        tx_manager.remove_externalized(&[externalized]);
+        assert!(tx_manager.contains(&externalized));
+        assert_eq!(tx_manager.priority(&externalized), None);
+
+        // Neither the capacity nor the key image are taken anymore.
+        let tx_context = TxContext {
+            tx_hash: cheaper,
+            ..Default::default()
+        };
+        assert_eq!(tx_manager.insert(tx_context).unwrap(), cheaper);
+
+        assert_eq!(
+            tx_manager.remove_expired(0),
+            HashSet::from_iter(vec![externalized])
+        );
+        assert!(!tx_manager.contains(&externalized));
+        assert_eq!(tx_manager.priority(&cheaper), Some(5));
+    }
+
+    #[test_with_logger]
+    // A referenced transaction that was evicted should be cached again, and not
+    // be removed with the evicted transactions.
+    fn test_insert_referenced_after_eviction(logger: Logger) {
+        let low = TxHash([1u8; 32]);
+        let high = TxHash([2u8; 32]);
+        let tx_manager = tx_manager_for_insert(1, cache_entry(low, 10, vec![]), logger);
+        {
+            let mut cache = tx_manager.lock_cache();
+            cache.insert(low, cache_entry(low, 10, vec![])).unwrap();
+            cache.insert(high, cache_entry(high, 20, vec![])).unwrap();
+        }
+        assert_eq!(tx_manager.priority(&low), None);
+
+        let tx_context = TxContext {
+            tx_hash: low,
+            ..Default::default()
+        };
+        assert_eq!(tx_manager.insert_referenced(tx_context).unwrap(), low);
+        assert_eq!(tx_manager.priority(&low), Some(10));
+
+        // Making room for it evicted the other transaction.
+        assert_eq!(tx_manager.remove_expired(0), HashSet::from_iter(vec![high]));
+        assert!(tx_manager.contains(&low));
+        assert_eq!(tx_manager.num_entries(), 1);
+    }
+
     #[test_with_logger]
     // Should remove all transactions that have expired by the given slot.
     fn test_remove_expired(logger: Logger) {
@@ -501,7 +822,8 @@ mod tests {
                 .cache
                 .lock()
                 .unwrap()
-                .insert(*context.tx_hash(), cache_entry);
+                .insert(*context.tx_hash(), cache_entry)
+                .unwrap();
         }
 
         assert_eq!(tx_manager.num_entries(), 14);
@@ -562,7 +884,8 @@ mod tests {
             .cache
             .lock()
             .unwrap()
-            .insert(tx_context.tx_hash, cache_entry);
+            .insert(tx_context.tx_hash, cache_entry)
+            .unwrap();
 
         assert!(tx_manager.validate(&tx_context.tx_hash).is_ok());
     }
@@ -614,7 +937,8 @@ mod tests {
             .cache
             .lock()
             .unwrap()
-            .insert(tx_context.tx_hash, cache_entry);
+            .insert(tx_context.tx_hash, cache_entry)
+            .unwrap();
 
         match tx_manager.validate(&tx_context.tx_hash) {
             Err(TxManagerError::TransactionValidation(
@@ -659,7 +983,8 @@ mod tests {
                 .cache
                 .lock()
                 .unwrap()
-                .insert(*context.tx_hash(), cache_entry);
+                .insert(*context.tx_hash(), cache_entry)
+                .unwrap();
         }
         assert_eq!(tx_manager.num_entries(), tx_hashes.len());
 
@@ -702,7 +1027,8 @@ mod tests {
                 .cache
                 .lock()
                 .unwrap()
-                .insert(*context.tx_hash(), cache_entry);
+                .insert(*context.tx_hash(), cache_entry)
+                .unwrap();
         }
 
         assert!(tx_manager.combine(&tx_hashes).is_err());
@@ -737,7 +1063,10 @@ mod tests {
                 encrypted_tx: WellFormedEncryptedTx(tx_hash.to_vec()),
                 context: Arc::new(Default::default()),
             };
-            tx_manager.lock_cache().insert(*tx_hash, cache_entry);
+            tx_manager
+                .lock_cache()
+                .insert(*tx_hash, cache_entry)
+                .unwrap();
         }
 
         let well_formed_encrypted_txs_with_proofs = tx_manager
@@ -778,7 +1107,10 @@ mod tests {
                 encrypted_tx: Default::default(),
                 context: Arc::new(Default::default()),
             };
-            tx_manager.lock_cache().insert(*tx_hash, cache_entry);
+            tx_manager
+                .lock_cache()
+                .insert(*tx_hash, cache_entry)
+                .unwrap();
         }
 
         // This transaction is not in the cache.
@@ -832,7 +1164,8 @@ mod tests {
                 .cache
                 .lock()
                 .unwrap()
-                .insert(*context.tx_hash(), cache_entry);
+                .insert(*context.tx_hash(), cache_entry)
+                .unwrap();
         }
         assert_eq!(tx_manager.num_entries(), tx_hashes.len());
 
@@ -896,7 +1229,8 @@ mod tests {
                 .cache
                 .lock()
                 .unwrap()
-                .insert(*context.tx_hash(), cache_entry);
+                .insert(*context.tx_hash(), cache_entry)
+                .unwrap();
         }
         assert_eq!(tx_manager.num_entries(), tx_hashes.len());
 
@@ -927,7 +1261,8 @@ mod tests {
             .cache
             .lock()
             .unwrap()
-            .insert(tx_hash, cache_entry);
+            .insert(tx_hash, cache_entry)
+            .unwrap();
 
         // Get something that is in the cache.
         assert_eq!(
@@ -970,7 +1305,8 @@ mod tests {
                 .cache
                 .lock()
                 .unwrap()
-                .insert(*context.tx_hash(), cache_entry);
+                .insert(*context.tx_hash(), cache_entry)
+                .unwrap();
         }
         assert_eq!(tx_manager.num_entries(), tx_hashes.len());
     }
@@ -3,7 +3,7 @@
 use crate::tx_manager::TxManagerResult;
 use mc_attest_enclave_api::{EnclaveMessage, PeerSession};
 use mc_common::HashSet;
-use mc_consensus_enclave::{TxContext, WellFormedEncryptedTx};
+use mc_consensus_enclave_api::{TxContext, WellFormedEncryptedTx};
 use mc_transaction_core::tx::{TxHash, TxOutMembershipProof};
 
 #[cfg(test)]
@@ -15,18 +15,32 @@ pub trait TxManager: Send {
     /// well-formed.
     fn insert(&self, tx_context: TxContext) -> TxManagerResult<TxHash>;
 
-    /// Remove expired transactions from the cache and return their hashes.
+    /// Insert a transaction that is referenced by a consensus message into the
+    /// cache, regardless of the cache's capacity and of conflicting key
+    /// images. The transaction must be well-formed.
+    fn insert_referenced(&self, tx_context: TxContext) -> TxManagerResult<TxHash>;
+
+    /// Remove expired transactions, and transactions that were evicted or
+    /// replaced since the last call, from the cache and return their hashes.
     ///
     /// # Arguments
     /// * `block_index` - Current block index.
     fn remove_expired(&self, block_index: u64) -> HashSet<TxHash>;
 
+    /// Mark transactions that were externalized as removed. They no longer
+    /// take up room in the cache, and are removed by the next call to
+    /// `remove_expired`.
+    fn remove_externalized(&self, tx_hashes: &[TxHash]);
+
     /// Returns true if the cache contains the corresponding transaction.
     fn contains(&self, tx_hash: &TxHash) -> bool;
 
     /// Number of cached entries.
     fn num_entries(&self) -> usize;
 
+    /// The priority of a cached transaction, unless it was evicted or replaced.
+    fn priority(&self, tx_hash: &TxHash) -> Option<u64>;
+
     /// Validate the transaction corresponding to the given hash against the
     /// current ledger.
     fn validate(&self, tx_hash: &TxHash) -> TxManagerResult<()>;
@@ -0,0 +1,214 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! A bounded log of what happened to each transaction seen by this node, from
+//! the moment it was proposed to the block it ended up in.
+//!
+//! Only information the untrusted side of the node already has is recorded:
+//! transaction hashes, the peers they were exchanged with, slot and block
+//! indices, and validation errors.
+
+use mc_common::{LruCache, ResponderId};
+use mc_consensus_api::consensus_admin;
+use mc_transaction_core::tx::TxHash;
+use std::{
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Maximum number of events kept per transaction. Once reached, the oldest
+/// events are dropped, but the first one (how the transaction got here) is
+/// always kept.
+pub const MAX_EVENTS_PER_TX: usize = 32;
+
+/// What happened to a transaction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TxEventKind {
+    /// Received from a client (`None`), or from a peer.
+    Received(Option<ResponderId>),
+
+    /// Rejected, with the reason.
+    Rejected(String),
+
+    /// Sent to this node's peers.
+    Relayed,
+
+    /// Nominated for a slot.
+    Nominated(u64),
+
+    /// Included in a block.
+    Externalized(u64),
+
+    /// Dropped from the pending values, its tombstone block having passed.
+    Expired,
+}
+
+/// A timestamped transaction event.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TxEvent {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+
+    /// What happened.
+    pub kind: TxEventKind,
+}
+
+/// Per-transaction event logs, for the least recently updated transactions to
+/// be forgotten first.
+pub struct TxTraceLog {
+    traces: Mutex<LruCache<TxHash, Vec<TxEvent>>>,
+}
+
+impl TxTraceLog {
+    /// Create a log that remembers up to `capacity` transactions.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            traces: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Record an event. An event identical to the transaction's previous one
+    /// (e.g. nominating it again for the same slot) is not recorded.
+    pub fn record(&self, tx_hash: &TxHash, kind: TxEventKind) {
+        let mut traces = self.traces.lock().expect("mutex poisoned");
+        let event = TxEvent {
+            timestamp_ms: now_ms(),
+            kind,
+        };
+
+        match traces.get_mut(tx_hash) {
+            Some(events) => {
+                if events.last().map(|last| &last.kind) == Some(&event.kind) {
+                    return;
+                }
+                if events.len() >= MAX_EVENTS_PER_TX {
+                    events.remove(1);
+                }
+                events.push(event);
+            }
+            None => {
+                traces.put(*tx_hash, vec![event]);
+            }
+        }
+    }
+
+    /// Record the same event for several transactions.
+    pub fn record_all<'a>(
+        &self,
+        tx_hashes: impl IntoIterator<Item = &'a TxHash>,
+        kind: TxEventKind,
+    ) {
+        for tx_hash in tx_hashes {
+            self.record(tx_hash, kind.clone());
+        }
+    }
+
+    /// The events recorded for a transaction, oldest first.
+    pub fn get(&self, tx_hash: &TxHash) -> Vec<TxEvent> {
+        self.traces
+            .lock()
+            .expect("mutex poisoned")
+            .peek(tx_hash)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl Default for TxTraceLog {
+    fn default() -> Self {
+        Self::new(100_000)
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl From<&TxEvent> for consensus_admin::TxEvent {
+    fn from(src: &TxEvent) -> Self {
+        let mut event = consensus_admin::TxEvent::new();
+        event.set_timestamp_ms(src.timestamp_ms);
+        match &src.kind {
+            TxEventKind::Received(peer) => {
+                event.set_event_type(consensus_admin::TxEventType::RECEIVED);
+                if let Some(peer) = peer {
+                    event.set_peer_responder_id(peer.to_string());
+                }
+            }
+            TxEventKind::Rejected(reason) => {
+                event.set_event_type(consensus_admin::TxEventType::REJECTED);
+                event.set_reason(reason.clone());
+            }
+            TxEventKind::Relayed => event.set_event_type(consensus_admin::TxEventType::RELAYED),
+            TxEventKind::Nominated(slot_index) => {
+                event.set_event_type(consensus_admin::TxEventType::NOMINATED);
+                event.set_block_index(*slot_index);
+            }
+            TxEventKind::Externalized(block_index) => {
+                event.set_event_type(consensus_admin::TxEventType::EXTERNALIZED);
+                event.set_block_index(*block_index);
+            }
+            TxEventKind::Expired => event.set_event_type(consensus_admin::TxEventType::EXPIRED),
+        }
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(events: &[TxEvent]) -> Vec<TxEventKind> {
+        events.iter().map(|event| event.kind.clone()).collect()
+    }
+
+    #[test]
+    fn test_records_events_in_order() {
+        let log = TxTraceLog::new(10);
+        let tx_hash = TxHash([1u8; 32]);
+        let peer = ResponderId("peer1:8443".to_owned());
+
+        log.record(&tx_hash, TxEventKind::Received(Some(peer.clone())));
+        log.record(&tx_hash, TxEventKind::Nominated(5));
+        // Nominating again for the same slot is not recorded.
+        log.record(&tx_hash, TxEventKind::Nominated(5));
+        log.record(&tx_hash, TxEventKind::Nominated(6));
+        log.record(&tx_hash, TxEventKind::Externalized(6));
+
+        assert_eq!(
+            kinds(&log.get(&tx_hash)),
+            vec![
+                TxEventKind::Received(Some(peer)),
+                TxEventKind::Nominated(5),
+                TxEventKind::Nominated(6),
+                TxEventKind::Externalized(6),
+            ]
+        );
+        assert!(log.get(&TxHash([2u8; 32])).is_empty());
+    }
+
+    #[test]
+    fn test_bounded() {
+        let log = TxTraceLog::new(2);
+        let tx_hashes = [TxHash([1u8; 32]), TxHash([2u8; 32]), TxHash([3u8; 32])];
+        log.record_all(&tx_hashes, TxEventKind::Received(None));
+
+        // The least recently updated transaction is forgotten.
+        assert!(log.get(&tx_hashes[0]).is_empty());
+        assert_eq!(log.get(&tx_hashes[2]).len(), 1);
+
+        // Events beyond the limit drop the oldest ones, except the first.
+        for slot_index in 0..(2 * MAX_EVENTS_PER_TX as u64) {
+            log.record(&tx_hashes[1], TxEventKind::Nominated(slot_index));
+        }
+        let events = log.get(&tx_hashes[1]);
+        assert_eq!(events.len(), MAX_EVENTS_PER_TX);
+        assert_eq!(events[0].kind, TxEventKind::Received(None));
+        assert_eq!(
+            events.last().unwrap().kind,
+            TxEventKind::Nominated(2 * MAX_EVENTS_PER_TX as u64 - 1)
+        );
+    }
+}
@@ -23,27 +23,82 @@
 //! and might be renamed in the future to match this.
 
 use crate::tx_manager::UntrustedInterfaces as TxManagerUntrustedInterfaces;
-use mc_consensus_enclave::{TxContext, WellFormedTxContext};
+use mc_consensus_enclave_api::{
+    fee_schedule::{self, BASE_MINIMUM_PRIORITY, FEE_SCHEDULE_WINDOW},
+    TxContext, WellFormedTxContext,
+};
 use mc_crypto_keys::CompressedRistrettoPublic;
-use mc_ledger_db::Ledger;
+use mc_ledger_db::{Error as LedgerError, Ledger};
 use mc_transaction_core::{
     ring_signature::KeyImage,
     tx::{TxHash, TxOutMembershipProof},
     validation::{validate_tombstone, TransactionValidationError, TransactionValidationResult},
+    BlockVersion,
+};
+use std::{
+    collections::HashSet,
+    iter::FromIterator,
+    sync::{Arc, Mutex},
 };
-use std::{collections::HashSet, iter::FromIterator, sync::Arc};
 
 #[derive(Clone)]
 pub struct DefaultTxManagerUntrustedInterfaces<L: Ledger> {
     ledger: L,
+
+    /// The block version of the blocks this node forms.
+    block_version: BlockVersion,
+
+    /// The number of blocks in the ledger when the minimum priority was last
+    /// computed, and that minimum priority.
+    minimum_priority: Arc<Mutex<Option<(u64, u64)>>>,
 }
 
 impl<L: Ledger + Sync> DefaultTxManagerUntrustedInterfaces<L> {
-    pub fn new(ledger: L) -> Self {
-        Self { ledger }
+    pub fn new(ledger: L, block_version: BlockVersion) -> Self {
+        Self {
+            ledger,
+            block_version,
+            minimum_priority: Default::default(),
+        }
+    }
+
+    /// The minimum priority of the transactions in the block following the
+    /// first `num_blocks` blocks of the ledger.
+    fn minimum_priority(&self, num_blocks: u64) -> TransactionValidationResult<u64> {
+        if !self.block_version.dynamic_minimum_fees_are_supported() {
+            return Ok(BASE_MINIMUM_PRIORITY);
+        }
+
+        let mut cached = self.minimum_priority.lock().expect("mutex poisoned");
+        match *cached {
+            Some((cached_num_blocks, minimum_priority)) if cached_num_blocks == num_blocks => {
+                Ok(minimum_priority)
+            }
+            _ => {
+                let recent_block_loads = get_recent_block_loads(&self.ledger, num_blocks)
+                    .map_err(|e| TransactionValidationError::Ledger(e.to_string()))?;
+                let minimum_priority =
+                    fee_schedule::minimum_priority(self.block_version, &recent_block_loads);
+                *cached = Some((num_blocks, minimum_priority));
+                Ok(minimum_priority)
+            }
+        }
     }
 }
 
+/// The loads of the blocks preceding the block following the first
+/// `num_blocks` blocks of the ledger, oldest first, from which its minimum fees
+/// are derived. The load of a block is its number of transactions.
+pub fn get_recent_block_loads<L: Ledger>(
+    ledger: &L,
+    num_blocks: u64,
+) -> Result<Vec<u64>, LedgerError> {
+    let first_block_index = num_blocks.saturating_sub(FEE_SCHEDULE_WINDOW as u64);
+    (first_block_index..num_blocks)
+        .map(|block_index| ledger.get_num_transactions_by_block(block_index))
+        .collect()
+}
+
 impl<L: Ledger + Sync> TxManagerUntrustedInterfaces for DefaultTxManagerUntrustedInterfaces<L> {
     /// Performs **only** the non-enclave part of the well-formed check.
     ///
@@ -81,6 +136,13 @@ impl<L: Ledger + Sync> TxManagerUntrustedInterfaces for DefaultTxManagerUntruste
         // too far in the future.
         validate_tombstone(current_block_index, context.tombstone_block())?;
 
+        // When recent blocks are congested, the transaction must pay more than its
+        // token's configured minimum fee, which the enclave has already checked.
+        let minimum_priority = self.minimum_priority(current_block_index)?;
+        if minimum_priority > BASE_MINIMUM_PRIORITY && context.priority() < minimum_priority {
+            return Err(TransactionValidationError::TxFeeError);
+        }
+
         // The `key_images` must not have already been spent.
         let contains_spent_key_image = context
             .key_images()
@@ -199,7 +261,7 @@ pub mod well_formed_tests {
             .times(1)
             .return_const(Ok(num_blocks));
 
-        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger);
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger, BlockVersion::ZERO);
 
         let tx_context = TxContext {
             locally_encrypted_tx: Default::default(),
@@ -230,7 +292,7 @@ pub mod well_formed_tests {
             .times(1)
             .return_const(Err(LedgerError::CapacityExceeded));
 
-        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger);
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger, BlockVersion::ZERO);
 
         // This tx_context contains highest_indices that exceed the number of TxOuts in
         // the ledger.
@@ -259,8 +321,12 @@ mod is_valid_tests {
     use super::*;
     use mc_ledger_db::{Error as LedgerError, MockLedger};
     use mc_transaction_core::{
-        constants::MAX_TOMBSTONE_BLOCKS, validation::TransactionValidationError,
+        constants::{MAX_TOMBSTONE_BLOCKS, MAX_TRANSACTIONS_PER_BLOCK},
+        validation::TransactionValidationError,
     };
+    use mc_transaction_core_test_utils::{create_ledger, initialize_ledger, AccountKey};
+    use rand::SeedableRng;
+    use rand_hc::Hc128Rng;
 
     #[test]
     /// `is_valid` should accept a valid transaction.
@@ -311,7 +377,7 @@ mod is_valid_tests {
             .times(well_formed_tx_context.output_public_keys().len())
             .return_const(Ok(false));
 
-        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger);
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger, BlockVersion::ZERO);
 
         assert_eq!(untrusted.is_valid(Arc::new(well_formed_tx_context)), Ok(()));
     }
@@ -340,7 +406,7 @@ mod is_valid_tests {
             .times(1)
             .return_const(Ok(num_blocks));
 
-        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger);
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger, BlockVersion::ZERO);
 
         assert_eq!(
             untrusted.is_valid(Arc::new(well_formed_tx_context)),
@@ -373,7 +439,7 @@ mod is_valid_tests {
             .times(1)
             .return_const(Ok(num_blocks));
 
-        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger);
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger, BlockVersion::ZERO);
 
         assert_eq!(
             untrusted.is_valid(Arc::new(well_formed_tx_context)),
@@ -419,7 +485,7 @@ mod is_valid_tests {
             .times(1)
             .return_const(Err(LedgerError::KeyImageAlreadySpent));
 
-        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger);
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger, BlockVersion::ZERO);
 
         assert_eq!(
             untrusted.is_valid(Arc::new(well_formed_tx_context)),
@@ -477,13 +543,153 @@ mod is_valid_tests {
             .times(1)
             .return_const(Ok(true)); // The output public key is in the ledger.
 
-        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger);
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger, BlockVersion::ZERO);
 
         assert_eq!(
             untrusted.is_valid(Arc::new(well_formed_tx_context)),
             Err(TransactionValidationError::ContainsExistingOutputPublicKey),
         );
     }
+
+    #[test]
+    /// `is_valid` should reject a transaction whose fee is below the minimum
+    /// fee of the next block, when recent blocks are full.
+    fn is_valid_rejects_insufficient_fee_when_congested() {
+        // Number of blocks in the local ledger.
+        let num_blocks = 53;
+
+        let well_formed_tx_context = WellFormedTxContext::new(
+            BASE_MINIMUM_PRIORITY, // Pays exactly the configured minimum fee.
+            Default::default(),
+            num_blocks + 17,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        // Mock the local ledger.
+        let mut ledger = MockLedger::new();
+
+        // Untrusted should request num_blocks.
+        ledger
+            .expect_num_blocks()
+            .times(1)
+            .return_const(Ok(num_blocks));
+
+        // Recent blocks are full.
+        ledger
+            .expect_get_num_transactions_by_block()
+            .times(FEE_SCHEDULE_WINDOW)
+            .return_const(Ok(MAX_TRANSACTIONS_PER_BLOCK as u64));
+
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger, BlockVersion::FOUR);
+
+        assert_eq!(
+            untrusted.is_valid(Arc::new(well_formed_tx_context)),
+            Err(TransactionValidationError::TxFeeError),
+        );
+    }
+
+    #[test]
+    /// `is_valid` should accept a transaction paying the minimum fee of the
+    /// next block when recent blocks are full, and only read the ledger
+    /// once per block.
+    fn is_valid_accepts_sufficient_fee_when_congested() {
+        // Number of blocks in the local ledger.
+        let num_blocks = 53;
+
+        let well_formed_tx_context = Arc::new(WellFormedTxContext::new(
+            fee_schedule::MAX_MINIMUM_FEE_MULTIPLIER * BASE_MINIMUM_PRIORITY,
+            Default::default(),
+            num_blocks + 17,
+            vec![KeyImage::default()],
+            Default::default(),
+            vec![CompressedRistrettoPublic::default()],
+        ));
+
+        // Mock the local ledger.
+        let mut ledger = MockLedger::new();
+
+        // Untrusted should request num_blocks.
+        ledger
+            .expect_num_blocks()
+            .times(2)
+            .return_const(Ok(num_blocks));
+
+        // Recent blocks are full. Their loads are only read once.
+        ledger
+            .expect_get_num_transactions_by_block()
+            .times(FEE_SCHEDULE_WINDOW)
+            .return_const(Ok(MAX_TRANSACTIONS_PER_BLOCK as u64));
+
+        // Key images must not be in the ledger.
+        ledger
+            .expect_contains_key_image()
+            .times(2)
+            .return_const(Ok(false));
+
+        // Output public keys must not be in the ledger.
+        ledger
+            .expect_contains_tx_out_public_key()
+            .times(2)
+            .return_const(Ok(false));
+
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger, BlockVersion::FOUR);
+
+        assert_eq!(untrusted.is_valid(well_formed_tx_context.clone()), Ok(()));
+        assert_eq!(untrusted.is_valid(well_formed_tx_context), Ok(()));
+    }
+
+    #[test]
+    /// `get_recent_block_loads` should count the transactions in each of the
+    /// blocks in the fee schedule's window, regardless of how many key images
+    /// they spend.
+    fn get_recent_block_loads_counts_transactions() {
+        let mut ledger = MockLedger::new();
+        ledger
+            .expect_get_num_transactions_by_block()
+            .returning(|block_index| Ok(block_index));
+        ledger.expect_get_key_images_by_block().never();
+
+        assert_eq!(
+            get_recent_block_loads(&ledger, 53).unwrap(),
+            (43..53).collect::<Vec<u64>>()
+        );
+
+        // Close to the origin block, there are fewer blocks in the window.
+        assert_eq!(get_recent_block_loads(&ledger, 3).unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    /// `get_recent_block_loads` should fail with `BlockContentsPruned` when the
+    /// contents of blocks in the fee schedule's window have been pruned.
+    fn get_recent_block_loads_fails_on_pruned_ledger() {
+        let mut rng = Hc128Rng::from_seed([1u8; 32]);
+        let mut ledger = create_ledger();
+        let account_key = AccountKey::random(&mut rng);
+        let num_blocks = 20;
+        initialize_ledger(
+            BlockVersion::MAX,
+            &mut ledger,
+            num_blocks,
+            &account_key,
+            &mut rng,
+        );
+        assert_eq!(
+            get_recent_block_loads(&ledger, num_blocks).unwrap().len(),
+            FEE_SCHEDULE_WINDOW
+        );
+
+        // Keeping fewer blocks than the window leaves part of it without contents.
+        ledger.prune_block_contents(5).unwrap();
+        assert_eq!(
+            get_recent_block_loads(&ledger, num_blocks),
+            Err(LedgerError::BlockContentsPruned(
+                num_blocks - FEE_SCHEDULE_WINDOW as u64,
+                num_blocks - 5
+            ))
+        );
+    }
 }
 
 #[cfg(test)]
@@ -506,7 +712,7 @@ mod combine_tests {
 
     fn combine(tx_contexts: Vec<WellFormedTxContext>, max_elements: usize) -> Vec<TxHash> {
         let ledger = get_mock_ledger(10);
-        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger);
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger, BlockVersion::ZERO);
         let tx_contexts: Vec<_> = tx_contexts.into_iter().map(Arc::new).collect();
         untrusted.combine(&tx_contexts, max_elements)
     }
@@ -0,0 +1,32 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+use displaydoc::Display;
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_fog_recovery_db_iface::{IngestInvocationId, RecoveryDbError};
+use mc_fog_types::common::BlockRange;
+
+/// Enum for error types.
+#[derive(Display, Debug)]
+pub enum Error {
+    /// The following ingress key was not found: {0:?}
+    MissingIngressKey(CompressedRistrettoPublic),
+
+    /// The following ingress key already exists: {0:?}
+    IngressKeyAlreadyExists(CompressedRistrettoPublic),
+
+    /// The following ingest invocation was not found: {0}
+    MissingIngestInvocation(IngestInvocationId),
+
+    /// The following ingest invocation was already decommissioned: {0}
+    IngestInvocationAlreadyDecommissioned(IngestInvocationId),
+
+    /// This missed blocks range was already reported: {0:?}
+    DuplicateMissedBlocksRange(BlockRange),
+}
+
+impl RecoveryDbError for Error {
+    /// Nothing can go wrong transiently when the database is in memory.
+    fn should_retry(&self) -> bool {
+        false
+    }
+}
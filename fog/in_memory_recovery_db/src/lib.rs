@@ -0,0 +1,569 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+#![deny(missing_docs)]
+
+//! Recovery db implementation keeping everything in memory.
+//!
+//! This mirrors the tables and constraints of the PostgreSQL implementation
+//! in `mc-fog-sql-recovery-db`, so that fog servers and tests can run without
+//! a database server. Nothing is persisted, and the data is only shared by
+//! clones of the same instance.
+
+mod error;
+
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_fog_kex_rng::KexRngPubkey;
+use mc_fog_recovery_db_iface::{
+    AddBlockDataStatus, FogUserEvent, IngestInvocationId, IngestableRange, IngressPublicKeyRecord,
+    IngressPublicKeyRecordFilters, IngressPublicKeyStatus, RecoveryDb, ReportData, ReportDb,
+};
+use mc_fog_types::{
+    common::BlockRange,
+    view::{DecommissionedIngestInvocation, RngRecord, TxOutSearchResult, TxOutSearchResultCode},
+    ETxOutRecord,
+};
+use mc_transaction_core::Block;
+use std::{
+    cmp::max,
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+pub use error::Error;
+
+/// The bytes of a compressed ristretto public key, which unlike the key itself
+/// can be used as a map key.
+type KeyBytes = [u8; 32];
+
+fn key_bytes(key: &CompressedRistrettoPublic) -> KeyBytes {
+    let bytes: &KeyBytes = key.as_ref();
+    *bytes
+}
+
+/// An ingest invocation, as in the `ingest_invocations` table.
+struct IngestInvocation {
+    ingress_public_key: KeyBytes,
+    egress_public_key: KexRngPubkey,
+    start_block: u64,
+    decommissioned: bool,
+}
+
+/// A block scanned by an ingest invocation, as in the `ingested_blocks` table.
+struct IngestedBlock {
+    ingest_invocation_id: IngestInvocationId,
+    cumulative_txo_count: u64,
+    block_signature_timestamp: u64,
+    e_tx_out_records: Vec<ETxOutRecord>,
+}
+
+/// A user event, as in the `user_events` table.
+enum UserEvent {
+    NewIngestInvocation(IngestInvocationId),
+    DecommissionIngestInvocation(IngestInvocationId),
+    MissingBlocks(BlockRange),
+}
+
+/// The contents of the database.
+#[derive(Default)]
+struct State {
+    ingress_keys: BTreeMap<KeyBytes, IngressPublicKeyStatus>,
+
+    /// Ingest invocations, the id of each being its index plus one.
+    ingest_invocations: Vec<IngestInvocation>,
+
+    /// Ingested blocks, by ingress key and block index. No block is scanned
+    /// twice with the same key.
+    ingested_blocks: BTreeMap<(KeyBytes, u64), IngestedBlock>,
+
+    /// User events, the id of each being its index plus one.
+    user_events: Vec<UserEvent>,
+
+    /// Reports by report id, in the order they were first set.
+    reports: Vec<(String, ReportData)>,
+}
+
+impl State {
+    fn ingest_invocation(&self, id: &IngestInvocationId) -> Result<&IngestInvocation, Error> {
+        usize::try_from(**id - 1)
+            .ok()
+            .and_then(|index| self.ingest_invocations.get(index))
+            .ok_or(Error::MissingIngestInvocation(*id))
+    }
+
+    fn last_scanned_block_index(&self, key: &KeyBytes) -> Option<u64> {
+        self.ingested_blocks
+            .range((*key, 0)..=(*key, u64::MAX))
+            .next_back()
+            .map(|((_, block_index), _)| *block_index)
+    }
+
+    fn last_ingested_block_index(&self, id: &IngestInvocationId) -> Option<u64> {
+        self.ingested_blocks
+            .iter()
+            .filter(|(_, block)| block.ingest_invocation_id == *id)
+            .map(|((_, block_index), _)| *block_index)
+            .max()
+    }
+
+    fn highest_known_block_index(&self) -> Option<u64> {
+        self.ingested_blocks
+            .keys()
+            .map(|(_, block_index)| *block_index)
+            .max()
+    }
+
+    fn blocks_at_index(&self, block_index: u64) -> impl Iterator<Item = &IngestedBlock> {
+        self.ingested_blocks
+            .iter()
+            .filter(move |((_, index), _)| *index == block_index)
+            .map(|(_, block)| block)
+    }
+
+    fn decommission_ingest_invocation(&mut self, id: &IngestInvocationId) -> Result<(), Error> {
+        let index = usize::try_from(**id - 1)
+            .ok()
+            .filter(|index| *index < self.ingest_invocations.len())
+            .ok_or(Error::MissingIngestInvocation(*id))?;
+
+        let invocation = &mut self.ingest_invocations[index];
+        if invocation.decommissioned {
+            return Err(Error::IngestInvocationAlreadyDecommissioned(*id));
+        }
+        invocation.decommissioned = true;
+
+        self.user_events
+            .push(UserEvent::DecommissionIngestInvocation(*id));
+        Ok(())
+    }
+}
+
+/// In-memory recovery database. Clones share the same data.
+#[derive(Clone, Default)]
+pub struct InMemoryRecoveryDb {
+    state: Arc<Mutex<State>>,
+}
+
+impl InMemoryRecoveryDb {
+    /// Create a new, empty database.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<State> {
+        self.state.lock().expect("mutex poisoned")
+    }
+}
+
+/// See trait `fog_recovery_db_iface::RecoveryDb` for documentation.
+impl RecoveryDb for InMemoryRecoveryDb {
+    type Error = Error;
+
+    fn get_ingress_key_status(
+        &self,
+        key: &CompressedRistrettoPublic,
+    ) -> Result<Option<IngressPublicKeyStatus>, Self::Error> {
+        Ok(self.lock().ingress_keys.get(&key_bytes(key)).cloned())
+    }
+
+    fn new_ingress_key(
+        &self,
+        key: &CompressedRistrettoPublic,
+        start_block_count: u64,
+    ) -> Result<u64, Self::Error> {
+        let mut state = self.lock();
+        if state.ingress_keys.contains_key(&key_bytes(key)) {
+            return Err(Error::IngressKeyAlreadyExists(*key));
+        }
+
+        let highest_known_block_count = state
+            .highest_known_block_index()
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        let accepted_start_block_count = max(start_block_count, highest_known_block_count);
+
+        state.ingress_keys.insert(
+            key_bytes(key),
+            IngressPublicKeyStatus {
+                start_block: accepted_start_block_count,
+                pubkey_expiry: 0,
+                retired: false,
+                lost: false,
+            },
+        );
+        Ok(accepted_start_block_count)
+    }
+
+    fn retire_ingress_key(
+        &self,
+        key: &CompressedRistrettoPublic,
+        set_retired: bool,
+    ) -> Result<(), Self::Error> {
+        if let Some(status) = self.lock().ingress_keys.get_mut(&key_bytes(key)) {
+            status.retired = set_retired;
+        }
+        Ok(())
+    }
+
+    fn get_last_scanned_block_index(
+        &self,
+        key: &CompressedRistrettoPublic,
+    ) -> Result<Option<u64>, Self::Error> {
+        Ok(self.lock().last_scanned_block_index(&key_bytes(key)))
+    }
+
+    fn get_ingress_key_records(
+        &self,
+        start_block_at_least: u64,
+        ingress_public_key_record_filters: &IngressPublicKeyRecordFilters,
+    ) -> Result<Vec<IngressPublicKeyRecord>, Self::Error> {
+        let state = self.lock();
+        Ok(state
+            .ingress_keys
+            .iter()
+            .filter(|(_, status)| status.start_block >= start_block_at_least)
+            .filter(|(_, status)| {
+                ingress_public_key_record_filters.should_include_lost_keys || !status.lost
+            })
+            .filter(|(_, status)| {
+                ingress_public_key_record_filters.should_include_retired_keys || !status.retired
+            })
+            .map(|(key, status)| IngressPublicKeyRecord {
+                key: CompressedRistrettoPublic::from(key),
+                status: status.clone(),
+                last_scanned_block: state.last_scanned_block_index(key),
+            })
+            .filter(|record| {
+                !ingress_public_key_record_filters.should_only_include_unexpired_keys
+                    || record
+                        .last_scanned_block
+                        .map_or(false, |last_scanned_block| {
+                            record.status.pubkey_expiry > last_scanned_block
+                        })
+            })
+            .collect())
+    }
+
+    fn new_ingest_invocation(
+        &self,
+        prev_ingest_invocation_id: Option<IngestInvocationId>,
+        ingress_public_key: &CompressedRistrettoPublic,
+        egress_public_key: &KexRngPubkey,
+        start_block: u64,
+    ) -> Result<IngestInvocationId, Self::Error> {
+        let mut state = self.lock();
+        if !state
+            .ingress_keys
+            .contains_key(&key_bytes(ingress_public_key))
+        {
+            return Err(Error::MissingIngressKey(*ingress_public_key));
+        }
+
+        // Optionally decommission old invocation.
+        if let Some(prev_ingest_invocation_id) = prev_ingest_invocation_id {
+            state.decommission_ingest_invocation(&prev_ingest_invocation_id)?;
+        }
+
+        state.ingest_invocations.push(IngestInvocation {
+            ingress_public_key: key_bytes(ingress_public_key),
+            egress_public_key: egress_public_key.clone(),
+            start_block,
+            decommissioned: false,
+        });
+        let id = IngestInvocationId::from(state.ingest_invocations.len() as i64);
+
+        state.user_events.push(UserEvent::NewIngestInvocation(id));
+        Ok(id)
+    }
+
+    fn get_ingestable_ranges(&self) -> Result<Vec<IngestableRange>, Self::Error> {
+        let state = self.lock();
+        Ok(state
+            .ingest_invocations
+            .iter()
+            .enumerate()
+            .map(|(index, invocation)| {
+                let id = IngestInvocationId::from(index as i64 + 1);
+                IngestableRange {
+                    id,
+                    start_block: invocation.start_block,
+                    decommissioned: invocation.decommissioned,
+                    last_ingested_block: state.last_ingested_block_index(&id),
+                }
+            })
+            .collect())
+    }
+
+    fn decommission_ingest_invocation(
+        &self,
+        ingest_invocation_id: &IngestInvocationId,
+    ) -> Result<(), Self::Error> {
+        self.lock()
+            .decommission_ingest_invocation(ingest_invocation_id)
+    }
+
+    fn add_block_data(
+        &self,
+        ingest_invocation_id: &IngestInvocationId,
+        block: &Block,
+        block_signature_timestamp: u64,
+        txs: &[ETxOutRecord],
+    ) -> Result<AddBlockDataStatus, Self::Error> {
+        let mut state = self.lock();
+        let ingress_public_key = state
+            .ingest_invocation(ingest_invocation_id)?
+            .ingress_public_key;
+
+        let block_key = (ingress_public_key, block.index);
+        if state.ingested_blocks.contains_key(&block_key) {
+            return Ok(AddBlockDataStatus {
+                block_already_scanned_with_this_key: true,
+            });
+        }
+
+        state.ingested_blocks.insert(
+            block_key,
+            IngestedBlock {
+                ingest_invocation_id: *ingest_invocation_id,
+                cumulative_txo_count: block.cumulative_txo_count,
+                block_signature_timestamp,
+                e_tx_out_records: txs.to_vec(),
+            },
+        );
+        Ok(AddBlockDataStatus {
+            block_already_scanned_with_this_key: false,
+        })
+    }
+
+    fn report_lost_ingress_key(
+        &self,
+        lost_ingress_key: CompressedRistrettoPublic,
+    ) -> Result<(), Self::Error> {
+        let mut state = self.lock();
+        let key = key_bytes(&lost_ingress_key);
+        let last_scanned_block_index = state.last_scanned_block_index(&key);
+
+        let status = state
+            .ingress_keys
+            .get(&key)
+            .cloned()
+            .ok_or(Error::MissingIngressKey(lost_ingress_key))?;
+
+        // Blocks that were not scanned before the key expired are missed.
+        let start_block = match last_scanned_block_index {
+            Some(block_index) => max(status.start_block, block_index + 1),
+            None => status.start_block,
+        };
+        let missed_block_range = BlockRange::new(start_block, status.pubkey_expiry);
+
+        // The range is empty if every block was scanned, or if the key was
+        // never published to the report server.
+        if missed_block_range.is_valid() {
+            if state.user_events.iter().any(|event| match event {
+                UserEvent::MissingBlocks(range) => *range == missed_block_range,
+                _ => false,
+            }) {
+                return Err(Error::DuplicateMissedBlocksRange(missed_block_range));
+            }
+            state
+                .user_events
+                .push(UserEvent::MissingBlocks(missed_block_range));
+        }
+
+        if let Some(status) = state.ingress_keys.get_mut(&key) {
+            status.lost = true;
+        }
+        Ok(())
+    }
+
+    fn get_missed_block_ranges(&self) -> Result<Vec<BlockRange>, Self::Error> {
+        Ok(self
+            .lock()
+            .user_events
+            .iter()
+            .filter_map(|event| match event {
+                UserEvent::MissingBlocks(range) => Some(range.clone()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn search_user_events(
+        &self,
+        start_from_user_event_id: i64,
+    ) -> Result<(Vec<FogUserEvent>, i64), Self::Error> {
+        let state = self.lock();
+
+        // User event ids start from 1, so "start_from_user_event_id = 0" will
+        // capture everything.
+        let skip = usize::try_from(max(start_from_user_event_id, 0)).unwrap_or(usize::MAX);
+        let mut events = Vec::new();
+        for event in state.user_events.iter().skip(skip) {
+            events.push(match event {
+                UserEvent::NewIngestInvocation(id) => {
+                    let invocation = state.ingest_invocation(id)?;
+                    FogUserEvent::NewRngRecord(RngRecord {
+                        ingest_invocation_id: **id,
+                        pubkey: invocation.egress_public_key.clone(),
+                        start_block: invocation.start_block,
+                    })
+                }
+                UserEvent::DecommissionIngestInvocation(id) => {
+                    FogUserEvent::DecommissionIngestInvocation(DecommissionedIngestInvocation {
+                        ingest_invocation_id: **id,
+                        last_ingested_block: state.last_ingested_block_index(id).unwrap_or(0),
+                    })
+                }
+                UserEvent::MissingBlocks(range) => FogUserEvent::MissingBlocks(range.clone()),
+            });
+        }
+
+        // If no events are found, return start_from_user_event_id and not 0
+        let next_start_from_user_event_id =
+            max(start_from_user_event_id, state.user_events.len() as i64);
+        Ok((events, next_start_from_user_event_id))
+    }
+
+    fn get_tx_outs(
+        &self,
+        start_block: u64,
+        search_keys: &[Vec<u8>],
+    ) -> Result<Vec<TxOutSearchResult>, Self::Error> {
+        let state = self.lock();
+
+        let mut search_key_to_payload = HashMap::<&[u8], &[u8]>::default();
+        for ((_, block_index), block) in state.ingested_blocks.iter() {
+            if *block_index < start_block {
+                continue;
+            }
+            for e_tx_out_record in block.e_tx_out_records.iter() {
+                search_key_to_payload.insert(
+                    &e_tx_out_record.search_key[..],
+                    &e_tx_out_record.payload[..],
+                );
+            }
+        }
+
+        Ok(search_keys
+            .iter()
+            .map(
+                |search_key| match search_key_to_payload.get(&search_key[..]) {
+                    Some(payload) => TxOutSearchResult {
+                        search_key: search_key.clone(),
+                        result_code: TxOutSearchResultCode::Found as u32,
+                        ciphertext: payload.to_vec(),
+                    },
+                    None => TxOutSearchResult {
+                        search_key: search_key.clone(),
+                        result_code: TxOutSearchResultCode::NotFound as u32,
+                        ciphertext: Default::default(),
+                    },
+                },
+            )
+            .collect())
+    }
+
+    /// Nothing reads back when an ingest invocation was last active, so this
+    /// only checks that it exists.
+    fn update_last_active_at(
+        &self,
+        ingest_invocation_id: &IngestInvocationId,
+    ) -> Result<(), Self::Error> {
+        self.lock().ingest_invocation(ingest_invocation_id)?;
+        Ok(())
+    }
+
+    fn get_tx_outs_by_block_and_key(
+        &self,
+        ingress_key: CompressedRistrettoPublic,
+        block_index: u64,
+    ) -> Result<Option<Vec<ETxOutRecord>>, Self::Error> {
+        Ok(self
+            .lock()
+            .ingested_blocks
+            .get(&(key_bytes(&ingress_key), block_index))
+            .map(|block| block.e_tx_out_records.clone()))
+    }
+
+    fn get_invocation_id_by_block_and_key(
+        &self,
+        ingress_key: CompressedRistrettoPublic,
+        block_index: u64,
+    ) -> Result<Option<IngestInvocationId>, Self::Error> {
+        Ok(self
+            .lock()
+            .ingested_blocks
+            .get(&(key_bytes(&ingress_key), block_index))
+            .map(|block| block.ingest_invocation_id))
+    }
+
+    fn get_cumulative_txo_count_for_block(
+        &self,
+        block_index: u64,
+    ) -> Result<Option<u64>, Self::Error> {
+        Ok(self
+            .lock()
+            .blocks_at_index(block_index)
+            .next()
+            .map(|block| block.cumulative_txo_count))
+    }
+
+    fn get_block_signature_timestamp_for_block(
+        &self,
+        block_index: u64,
+    ) -> Result<Option<u64>, Self::Error> {
+        Ok(self
+            .lock()
+            .blocks_at_index(block_index)
+            .next()
+            .map(|block| block.block_signature_timestamp))
+    }
+
+    fn get_highest_known_block_index(&self) -> Result<Option<u64>, Self::Error> {
+        Ok(self.lock().highest_known_block_index())
+    }
+}
+
+/// See trait `fog_recovery_db_iface::ReportDb` for documentation.
+impl ReportDb for InMemoryRecoveryDb {
+    type Error = Error;
+
+    fn get_all_reports(&self) -> Result<Vec<(String, ReportData)>, Self::Error> {
+        Ok(self.lock().reports.clone())
+    }
+
+    fn set_report(
+        &self,
+        ingress_key: &CompressedRistrettoPublic,
+        report_id: &str,
+        data: &ReportData,
+    ) -> Result<IngressPublicKeyStatus, Self::Error> {
+        let mut state = self.lock();
+
+        // Only allow the pubkey expiry of the key to increase, and only if it is
+        // not retired.
+        let status = state
+            .ingress_keys
+            .get_mut(&key_bytes(ingress_key))
+            .ok_or(Error::MissingIngressKey(*ingress_key))?;
+        if !status.retired && status.pubkey_expiry < data.pubkey_expiry {
+            status.pubkey_expiry = data.pubkey_expiry;
+        }
+        let status = status.clone();
+
+        // Cannot publish the key if it is retired.
+        if status.retired {
+            return Ok(status);
+        }
+
+        match state.reports.iter_mut().find(|(id, _)| id == report_id) {
+            Some((_, report)) => *report = data.clone(),
+            None => state.reports.push((report_id.to_owned(), data.clone())),
+        }
+        Ok(status)
+    }
+
+    fn remove_report(&self, report_id: &str) -> Result<(), Self::Error> {
+        self.lock().reports.retain(|(id, _)| id != report_id);
+        Ok(())
+    }
+}
@@ -40,6 +40,9 @@ pub enum Error {
 
     /// Prost decode error
     ProstDecode,
+
+    /// Store responses do not match the query
+    StoreResponseMismatch,
 }
 
 /// An error when something goes wrong with adding a record
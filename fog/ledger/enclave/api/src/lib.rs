@@ -15,7 +15,11 @@ pub use crate::{
 };
 use alloc::vec::Vec;
 use core::result::Result as StdResult;
-use mc_attest_enclave_api::{ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage};
+use mc_attest_core::VerificationReport;
+use mc_attest_enclave_api::{
+    ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage, PeerAuthRequest,
+    PeerAuthResponse, PeerSession,
+};
 use mc_common::ResponderId;
 use mc_crypto_keys::X25519Public;
 pub use mc_fog_types::ledger::{
@@ -98,6 +102,55 @@ pub trait LedgerEnclave: ReportableEnclave {
 
     /// Add a key image data to the oram Using thrm -rf targete key image
     fn add_key_image_data(&self, records: Vec<KeyImageData>) -> Result<()>;
+
+    // ROUTER AND STORE METHODS
+    //
+    // A fog ledger router serves key image checks by fanning them out to key
+    // image stores, each holding the key images of a range of blocks, and
+    // merging their responses. Routers and stores run this same enclave, and
+    // connect to each other as peers.
+
+    /// Initiate a connection with a key image store (router side)
+    fn peer_init(&self, peer_id: &ResponderId) -> Result<PeerAuthRequest>;
+
+    /// Accept a connection from a fog ledger router (store side)
+    fn peer_accept(&self, req: PeerAuthRequest) -> Result<(PeerAuthResponse, PeerSession)>;
+
+    /// Handle the store's peer_accept response to form the connection (router
+    /// side)
+    fn peer_connect(
+        &self,
+        peer_id: &ResponderId,
+        msg: PeerAuthResponse,
+    ) -> Result<(PeerSession, VerificationReport)>;
+
+    /// Close a connection with a router or a store
+    fn peer_close(&self, session_id: &PeerSession) -> Result<()>;
+
+    /// Decrypt a user's CheckKeyImagesRequest, and encrypt it for each of the
+    /// given stores, in the same order (router side)
+    fn create_key_image_store_queries(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+        store_sessions: Vec<PeerSession>,
+    ) -> Result<Vec<EnclaveMessage<PeerSession>>>;
+
+    /// Service a CheckKeyImagesRequest encrypted by a router, looking up the
+    /// key images among the ones held by this store (store side)
+    fn check_key_image_store(
+        &self,
+        msg: EnclaveMessage<PeerSession>,
+    ) -> Result<EnclaveMessage<PeerSession>>;
+
+    /// Obliviously merge the stores' responses to a user's key image check,
+    /// and encrypt the resulting CheckKeyImagesResponse for the user (router
+    /// side)
+    fn collate_key_image_store_responses(
+        &self,
+        client_session: ClientSession,
+        store_responses: Vec<EnclaveMessage<PeerSession>>,
+        untrusted_keyimagequery_response: UntrustedKeyImageQueryResponse,
+    ) -> Result<Vec<u8>>;
 }
 
 /// Helper trait which reduces boiler-plate in untrusted side
@@ -4,7 +4,10 @@
 use crate::UntrustedKeyImageQueryResponse;
 use alloc::vec::Vec;
 use mc_attest_core::{Quote, Report, TargetInfo, VerificationReport};
-use mc_attest_enclave_api::{ClientAuthRequest, ClientSession, EnclaveMessage};
+use mc_attest_enclave_api::{
+    ClientAuthRequest, ClientSession, EnclaveMessage, PeerAuthRequest, PeerAuthResponse,
+    PeerSession,
+};
 use mc_common::ResponderId;
 use mc_fog_types::ledger::GetOutputsResponse;
 use mc_transaction_core::ring_signature::KeyImage;
@@ -101,4 +104,43 @@ pub enum EnclaveCall {
     ///
     ///  Add key image data to the ORAM.
     AddKeyImageData(Vec<KeyImageData>),
+
+    /// The [LedgerEnclave::peer_init()] method.
+    ///
+    /// Begin a connection to a key image store.
+    PeerInit(ResponderId),
+
+    /// The [LedgerEnclave::peer_accept()] method.
+    ///
+    /// Accept a connection from a fog ledger router.
+    PeerAccept(PeerAuthRequest),
+
+    /// The [LedgerEnclave::peer_connect()] method.
+    ///
+    /// Complete a connection to a key image store.
+    PeerConnect(ResponderId, PeerAuthResponse),
+
+    /// The [LedgerEnclave::peer_close()] method.
+    ///
+    /// Close a connection with a fog ledger router or key image store.
+    PeerClose(PeerSession),
+
+    /// The [LedgerEnclave::create_key_image_store_queries()] method.
+    ///
+    /// Re-encrypt a client's key image check for each of the given stores.
+    CreateKeyImageStoreQueries(EnclaveMessage<ClientSession>, Vec<PeerSession>),
+
+    /// The [LedgerEnclave::check_key_image_store()] method.
+    ///
+    /// Check key images on behalf of a fog ledger router.
+    CheckKeyImageStore(EnclaveMessage<PeerSession>),
+
+    /// The [LedgerEnclave::collate_key_image_store_responses()] method.
+    ///
+    /// Merge the stores' responses to a client's key image check.
+    CollateKeyImageStoreResponses(
+        ClientSession,
+        Vec<EnclaveMessage<PeerSession>>,
+        UntrustedKeyImageQueryResponse,
+    ),
 }
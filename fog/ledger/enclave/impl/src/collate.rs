@@ -0,0 +1,93 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Oblivious merging of the KeyImageResults returned by key image stores.
+//!
+//! Every store is asked about every key image, and at most one of them finds
+//! it spent. The merge itself is shared with the other fog routers.
+
+use aligned_cmov::CMov;
+use alloc::vec::Vec;
+use mc_fog_ledger_enclave_api::Error;
+use mc_fog_store_collate::{collate_store_results, merge_choice, ResultCodes};
+use mc_fog_types::ledger::{KeyImageResult, KeyImageResultCode};
+
+const KEY_IMAGE_RESULT_CODES: ResultCodes = ResultCodes {
+    found: KeyImageResultCode::Spent as u32,
+    not_found: KeyImageResultCode::NotSpent as u32,
+};
+
+/// Merge the results of several stores to the same key image check.
+///
+/// Each store must have returned one result per key image, in the same order.
+/// The merged result for a key image is the one of a store that found it
+/// spent, or else an error reported by a store, or else "not spent".
+pub fn collate_key_image_results(
+    store_results: Vec<Vec<KeyImageResult>>,
+) -> Result<Vec<KeyImageResult>, Error> {
+    collate_store_results(store_results, |result| &result.key_image, merge_result)
+        .ok_or(Error::StoreResponseMismatch)
+}
+
+/// Obliviously replace `dst` with `src` if `src` found the key image spent, or
+/// if `dst` did not and `src` holds an error.
+fn merge_result(dst: &mut KeyImageResult, src: &KeyImageResult) {
+    let choice = merge_choice(
+        dst.key_image_result_code,
+        src.key_image_result_code,
+        KEY_IMAGE_RESULT_CODES,
+    );
+
+    dst.key_image_result_code
+        .cmov(choice, &src.key_image_result_code);
+    dst.spent_at.cmov(choice, &src.spent_at);
+    dst.timestamp.cmov(choice, &src.timestamp);
+    dst.timestamp_result_code
+        .cmov(choice, &src.timestamp_result_code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use mc_transaction_core::ring_signature::KeyImage;
+    use mc_watcher_api::TimestampResultCode;
+
+    fn result(key_image: u64, code: KeyImageResultCode, spent_at: u64) -> KeyImageResult {
+        KeyImageResult {
+            key_image: KeyImage::from(key_image),
+            spent_at,
+            timestamp: spent_at,
+            timestamp_result_code: TimestampResultCode::TimestampFound as u32,
+            key_image_result_code: code as u32,
+        }
+    }
+
+    #[test]
+    fn test_collate_key_image_results() {
+        let store1 = vec![
+            result(1, KeyImageResultCode::Spent, 5),
+            result(2, KeyImageResultCode::NotSpent, u64::MAX),
+            result(3, KeyImageResultCode::KeyImageError, u64::MAX),
+        ];
+        let store2 = vec![
+            result(1, KeyImageResultCode::NotSpent, u64::MAX),
+            result(2, KeyImageResultCode::Spent, 12),
+            result(3, KeyImageResultCode::NotSpent, u64::MAX),
+        ];
+
+        let collated = collate_key_image_results(vec![store1, store2.clone()]).unwrap();
+        assert_eq!(
+            collated,
+            vec![
+                result(1, KeyImageResultCode::Spent, 5),
+                result(2, KeyImageResultCode::Spent, 12),
+                result(3, KeyImageResultCode::KeyImageError, u64::MAX),
+            ]
+        );
+
+        assert!(matches!(
+            collate_key_image_results(vec![store2, vec![]]),
+            Err(Error::StoreResponseMismatch)
+        ));
+    }
+}
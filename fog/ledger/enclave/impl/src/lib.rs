@@ -12,11 +12,16 @@
 #![deny(missing_docs)]
 extern crate alloc;
 
+mod collate;
 mod key_image_store;
 use alloc::vec::Vec;
+use collate::collate_key_image_results;
 use key_image_store::{KeyImageStore, StorageDataSize, StorageMetaSize};
 use mc_attest_core::{IasNonce, Quote, QuoteNonce, Report, TargetInfo, VerificationReport};
-use mc_attest_enclave_api::{ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage};
+use mc_attest_enclave_api::{
+    ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage, PeerAuthRequest,
+    PeerAuthResponse, PeerSession,
+};
 use mc_common::{
     logger::{log, Logger},
     ResponderId,
@@ -28,6 +33,7 @@ use mc_fog_ledger_enclave_api::{
 };
 use mc_fog_types::ledger::{
     CheckKeyImagesRequest, CheckKeyImagesResponse, GetOutputsRequest, GetOutputsResponse,
+    KeyImageResult,
 };
 use mc_oblivious_traits::ORAMStorageCreator;
 use mc_sgx_compat::sync::Mutex;
@@ -61,6 +67,40 @@ where
             logger,
         }
     }
+
+    /// Decode a CheckKeyImagesRequest
+    fn decode_check_key_images_request(&self, plaintext: &[u8]) -> Result<CheckKeyImagesRequest> {
+        mc_util_serial::decode(plaintext).map_err(|e| {
+            log::error!(self.logger, "Could not decode user request: {}", e);
+            Error::ProstDecode
+        })
+    }
+
+    /// Look up the requested key images in the key image store
+    fn find_key_images(&self, req: &CheckKeyImagesRequest) -> Result<Vec<KeyImageResult>> {
+        let mut lk = self.key_image_store.lock()?;
+        let store = lk.as_mut().ok_or(Error::EnclaveNotInitialized)?;
+
+        Ok(req
+            .queries
+            .iter() //  get the key images used to find the key image data using the oram
+            .map(|key| store.find_record(&key.key_image))
+            .collect())
+    }
+}
+
+/// A CheckKeyImagesResponse holding the data injected by untrusted, and no
+/// results yet.
+fn check_key_images_response_from_untrusted(
+    untrusted_key_image_query_response: UntrustedKeyImageQueryResponse,
+) -> CheckKeyImagesResponse {
+    CheckKeyImagesResponse {
+        num_blocks: untrusted_key_image_query_response.highest_processed_block_count,
+        results: Default::default(),
+        global_txo_count: untrusted_key_image_query_response.last_known_block_cumulative_txo_count,
+        latest_block_version: untrusted_key_image_query_response.latest_block_version,
+        max_block_version: untrusted_key_image_query_response.max_block_version,
+    }
 }
 
 /// Implementation of the reportable enclave for sgxledger enclave
@@ -92,7 +132,8 @@ where
     OSC: ORAMStorageCreator<StorageDataSize, StorageMetaSize>,
 {
     fn enclave_init(&self, self_id: &ResponderId, desired_capacity: u64) -> Result<()> {
-        self.ake.init(Default::default(), self_id.clone())?;
+        // Fog ledger routers address key image stores by their client responder id.
+        self.ake.init(self_id.clone(), self_id.clone())?;
         let mut lk = self.key_image_store.lock()?;
 
         *lk = Some(KeyImageStore::new(desired_capacity, self.logger.clone()));
@@ -144,32 +185,10 @@ where
     ) -> Result<Vec<u8>> {
         let channel_id = msg.channel_id.clone(); //client session does not implement copy trait so clone
         let user_plaintext = self.ake.client_decrypt(msg)?;
+        let req = self.decode_check_key_images_request(&user_plaintext)?;
 
-        let req: CheckKeyImagesRequest = mc_util_serial::decode(&user_plaintext).map_err(|e| {
-            log::error!(self.logger, "Could not decode user request: {}", e);
-            Error::ProstDecode
-        })?;
-
-        let mut resp = CheckKeyImagesResponse {
-            num_blocks: untrusted_key_image_query_response.highest_processed_block_count,
-            results: Default::default(),
-            global_txo_count: untrusted_key_image_query_response
-                .last_known_block_cumulative_txo_count,
-            latest_block_version: untrusted_key_image_query_response.latest_block_version,
-            max_block_version: untrusted_key_image_query_response.max_block_version,
-        };
-
-        // Do the scope lock of keyimagetore
-        {
-            let mut lk = self.key_image_store.lock()?;
-            let store = lk.as_mut().ok_or(Error::EnclaveNotInitialized)?;
-
-            resp.results = req
-                .queries
-                .iter() //  get the key images used to find the key image data using the oram
-                .map(|key| store.find_record(&key.key_image))
-                .collect();
-        }
+        let mut resp = check_key_images_response_from_untrusted(untrusted_key_image_query_response);
+        resp.results = self.find_key_images(&req)?;
 
         let response_plaintext_bytes = mc_util_serial::encode(&resp);
 
@@ -191,6 +210,94 @@ where
 
         Ok(())
     }
+
+    // Router and store specific
+
+    fn peer_init(&self, peer_id: &ResponderId) -> Result<PeerAuthRequest> {
+        Ok(self.ake.peer_init(peer_id)?)
+    }
+
+    fn peer_accept(&self, req: PeerAuthRequest) -> Result<(PeerAuthResponse, PeerSession)> {
+        Ok(self.ake.peer_accept(req)?)
+    }
+
+    fn peer_connect(
+        &self,
+        peer_id: &ResponderId,
+        msg: PeerAuthResponse,
+    ) -> Result<(PeerSession, VerificationReport)> {
+        Ok(self.ake.peer_connect(peer_id, msg)?)
+    }
+
+    fn peer_close(&self, session_id: &PeerSession) -> Result<()> {
+        Ok(self.ake.peer_close(session_id)?)
+    }
+
+    fn create_key_image_store_queries(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+        store_sessions: Vec<PeerSession>,
+    ) -> Result<Vec<EnclaveMessage<PeerSession>>> {
+        let user_plaintext = self.ake.client_decrypt(msg)?;
+
+        // Reject malformed requests here rather than in every store.
+        self.decode_check_key_images_request(&user_plaintext)?;
+
+        store_sessions
+            .iter()
+            .map(|session| Ok(self.ake.peer_encrypt(session, &[], &user_plaintext)?))
+            .collect()
+    }
+
+    fn check_key_image_store(
+        &self,
+        msg: EnclaveMessage<PeerSession>,
+    ) -> Result<EnclaveMessage<PeerSession>> {
+        let channel_id = msg.channel_id.clone();
+        let plaintext = self.ake.peer_decrypt(msg)?;
+        let req = self.decode_check_key_images_request(&plaintext)?;
+
+        // The router fills in everything but the results.
+        let resp = CheckKeyImagesResponse {
+            results: self.find_key_images(&req)?,
+            ..Default::default()
+        };
+        let response_plaintext_bytes = mc_util_serial::encode(&resp);
+
+        Ok(self
+            .ake
+            .peer_encrypt(&channel_id, &[], &response_plaintext_bytes)?)
+    }
+
+    fn collate_key_image_store_responses(
+        &self,
+        client_session: ClientSession,
+        store_responses: Vec<EnclaveMessage<PeerSession>>,
+        untrusted_key_image_query_response: UntrustedKeyImageQueryResponse,
+    ) -> Result<Vec<u8>> {
+        let store_results = store_responses
+            .into_iter()
+            .map(|msg| {
+                let plaintext = self.ake.peer_decrypt(msg)?;
+                let resp: CheckKeyImagesResponse =
+                    mc_util_serial::decode(&plaintext).map_err(|e| {
+                        log::error!(self.logger, "Could not decode store response: {}", e);
+                        Error::ProstDecode
+                    })?;
+                Ok(resp.results)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut resp = check_key_images_response_from_untrusted(untrusted_key_image_query_response);
+        resp.results = collate_key_image_results(store_results)?;
+
+        let response_plaintext_bytes = mc_util_serial::encode(&resp);
+        let response = self
+            .ake
+            .client_encrypt(&client_session, &[], &response_plaintext_bytes)?;
+
+        Ok(response.data)
+    }
 }
 
 #[cfg(test)]
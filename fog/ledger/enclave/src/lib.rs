@@ -6,6 +6,8 @@
 
 extern crate mc_fog_ocall_oram_storage_untrusted;
 
+pub use mc_fog_ocall_oram_storage_untrusted::{configure_oram_storage, OramStorageConfig};
+
 pub use mc_fog_ledger_enclave_api::{
     CheckKeyImagesResponse, EnclaveCall, Error, GetOutputsResponse, KeyImageData, KeyImageResult,
     KeyImageResultCode, LedgerEnclave, LedgerEnclaveProxy, OutputContext, OutputResult, Result,
@@ -14,7 +16,10 @@ pub use mc_fog_ledger_enclave_api::{
 use mc_attest_core::{
     IasNonce, Quote, QuoteNonce, Report, SgxError, TargetInfo, VerificationReport,
 };
-use mc_attest_enclave_api::{ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage};
+use mc_attest_enclave_api::{
+    ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage, PeerAuthRequest,
+    PeerAuthResponse, PeerSession,
+};
 use mc_attest_verifier::DEBUG_ENCLAVE;
 use mc_common::{logger::Logger, ResponderId};
 use mc_crypto_keys::X25519Public;
@@ -187,6 +192,71 @@ impl LedgerEnclave for LedgerSgxEnclave {
         let outbuf = self.enclave_call(&inbuf)?;
         mc_util_serial::deserialize(&outbuf[..])?
     }
+
+    fn peer_init(&self, peer_id: &ResponderId) -> Result<PeerAuthRequest> {
+        let inbuf = mc_util_serial::serialize(&EnclaveCall::PeerInit(peer_id.clone()))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn peer_accept(&self, req: PeerAuthRequest) -> Result<(PeerAuthResponse, PeerSession)> {
+        let inbuf = mc_util_serial::serialize(&EnclaveCall::PeerAccept(req))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn peer_connect(
+        &self,
+        peer_id: &ResponderId,
+        msg: PeerAuthResponse,
+    ) -> Result<(PeerSession, VerificationReport)> {
+        let inbuf = mc_util_serial::serialize(&EnclaveCall::PeerConnect(peer_id.clone(), msg))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn peer_close(&self, session_id: &PeerSession) -> Result<()> {
+        let inbuf = mc_util_serial::serialize(&EnclaveCall::PeerClose(session_id.clone()))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn create_key_image_store_queries(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+        store_sessions: Vec<PeerSession>,
+    ) -> Result<Vec<EnclaveMessage<PeerSession>>> {
+        let inbuf = mc_util_serial::serialize(&EnclaveCall::CreateKeyImageStoreQueries(
+            msg,
+            store_sessions,
+        ))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn check_key_image_store(
+        &self,
+        msg: EnclaveMessage<PeerSession>,
+    ) -> Result<EnclaveMessage<PeerSession>> {
+        let inbuf = mc_util_serial::serialize(&EnclaveCall::CheckKeyImageStore(msg))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn collate_key_image_store_responses(
+        &self,
+        client_session: ClientSession,
+        store_responses: Vec<EnclaveMessage<PeerSession>>,
+        untrusted_keyimagequery_response: UntrustedKeyImageQueryResponse,
+    ) -> Result<Vec<u8>> {
+        let inbuf = mc_util_serial::serialize(&EnclaveCall::CollateKeyImageStoreResponses(
+            client_session,
+            store_responses,
+            untrusted_keyimagequery_response,
+        ))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
 }
 
 extern "C" {
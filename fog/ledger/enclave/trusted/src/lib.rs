@@ -61,6 +61,24 @@ pub fn ecall_dispatcher(inbuf: &[u8]) -> Result<Vec<u8>, sgx_status_t> {
         }
         // Add Key Image Data
         EnclaveCall::AddKeyImageData(records) => serialize(&ENCLAVE.add_key_image_data(records)),
+        // Router and store
+        EnclaveCall::PeerInit(peer_id) => serialize(&ENCLAVE.peer_init(&peer_id)),
+        EnclaveCall::PeerAccept(req) => serialize(&ENCLAVE.peer_accept(req)),
+        EnclaveCall::PeerConnect(peer_id, msg) => serialize(&ENCLAVE.peer_connect(&peer_id, msg)),
+        EnclaveCall::PeerClose(session_id) => serialize(&ENCLAVE.peer_close(&session_id)),
+        EnclaveCall::CreateKeyImageStoreQueries(msg, store_sessions) => {
+            serialize(&ENCLAVE.create_key_image_store_queries(msg, store_sessions))
+        }
+        EnclaveCall::CheckKeyImageStore(msg) => serialize(&ENCLAVE.check_key_image_store(msg)),
+        EnclaveCall::CollateKeyImageStoreResponses(
+            client_session,
+            store_responses,
+            untrusted_keyimagequery_response,
+        ) => serialize(&ENCLAVE.collate_key_image_store_responses(
+            client_session,
+            store_responses,
+            untrusted_keyimagequery_response,
+        )),
     }
     .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))
 }
@@ -9,7 +9,7 @@ use mc_common::{
     logger::{create_app_logger, log, o},
     time::SystemTimeProvider,
 };
-use mc_fog_ledger_enclave::{LedgerSgxEnclave, ENCLAVE_FILE};
+use mc_fog_ledger_enclave::{configure_oram_storage, LedgerSgxEnclave, ENCLAVE_FILE};
 use mc_fog_ledger_server::{LedgerServer, LedgerServerConfig};
 use mc_ledger_db::LedgerDB;
 use mc_util_cli::ParserWithBuildInfo;
@@ -42,6 +42,7 @@ fn main() {
         enclave_path.to_str().expect("Could not get enclave path"),
         &config.client_responder_id
     );
+    configure_oram_storage(config.oram_storage_config());
     let enclave = LedgerSgxEnclave::new(
         enclave_path,
         &config.client_responder_id,
@@ -7,6 +7,8 @@
 use clap::Parser;
 use mc_attest_core::ProviderId;
 use mc_common::ResponderId;
+use mc_fog_ledger_enclave::OramStorageConfig;
+use mc_fog_types::common::BlockRange;
 use mc_fog_uri::FogLedgerUri;
 use mc_util_parse::parse_duration_in_seconds;
 use mc_util_uri::AdminUri;
@@ -68,7 +70,60 @@ pub struct LedgerServerConfig {
     /// available SGX EPC memory, and then beyond that it will be allocated on
     /// the heap in the untrusted side. Once the needed capacity exceeds RAM,
     /// you will either get killed by OOM killer, or it will start being swapped
-    /// to disk by linux kernel.
+    /// to disk by linux kernel, unless --oram-storage-dir is used.
     #[clap(long, default_value = "1048576", env = "MC_OMAP_CAPACITY")]
     pub omap_capacity: u64,
+
+    /// A directory, on a local disk, in which to keep the untrusted ORAM
+    /// storage that does not fit in --oram-storage-memory-bytes. By default,
+    /// it is all kept in memory.
+    #[clap(long, env = "MC_ORAM_STORAGE_DIR")]
+    pub oram_storage_dir: Option<PathBuf>,
+
+    /// With --oram-storage-dir, the number of bytes of untrusted ORAM storage
+    /// to keep in memory. The levels of the ORAM trees closest to the root,
+    /// which every access reads from, are kept in memory first.
+    #[clap(
+        long,
+        default_value = "4294967296",
+        env = "MC_ORAM_STORAGE_MEMORY_BYTES"
+    )]
+    pub oram_storage_memory_bytes: u64,
+
+    /// The first block whose key images this server holds.
+    ///
+    /// Setting this or --shard-end-block makes this server a key image store,
+    /// which only holds the key images of a range of blocks, and whose key
+    /// images are checked by fog ledger routers rather than by clients.
+    #[clap(long, default_value = "0", env = "MC_SHARD_START_BLOCK")]
+    pub shard_start_block: u64,
+
+    /// One past the last block whose key images this server holds. Unbounded
+    /// if omitted.
+    #[clap(long, env = "MC_SHARD_END_BLOCK")]
+    pub shard_end_block: Option<u64>,
+}
+
+impl LedgerServerConfig {
+    /// The range of blocks whose key images this server holds.
+    pub fn block_range(&self) -> BlockRange {
+        BlockRange::new(
+            self.shard_start_block,
+            self.shard_end_block.unwrap_or(u64::MAX),
+        )
+    }
+
+    /// Where to keep the untrusted ORAM storage.
+    pub fn oram_storage_config(&self) -> OramStorageConfig {
+        OramStorageConfig {
+            dir: self.oram_storage_dir.clone(),
+            memory_bytes: self.oram_storage_memory_bytes,
+        }
+    }
+
+    /// Whether this server only holds the key images of some of the blocks,
+    /// and can only be asked to check key images through a fog ledger router.
+    pub fn is_store(&self) -> bool {
+        self.shard_start_block != 0 || self.shard_end_block.is_some()
+    }
 }
@@ -10,6 +10,7 @@ use mc_common::{
 };
 use mc_fog_ledger_enclave::LedgerEnclaveProxy;
 use mc_fog_ledger_enclave_api::KeyImageData;
+use mc_fog_types::common::BlockRange;
 use mc_ledger_db::{self, Error as LedgerError, Ledger};
 use mc_util_grpc::ReadinessIndicator;
 use mc_util_telemetry::{
@@ -39,11 +40,14 @@ pub struct DbFetcher {
 }
 
 impl DbFetcher {
+    /// Start adding the key images of the blocks in `block_range` to the
+    /// enclave.
     pub fn new<DB: Ledger + Clone + Send + Sync + 'static, E: LedgerEnclaveProxy>(
         db: DB,
         enclave: E,
         watcher: WatcherDB,
         db_poll_shared_state: Arc<Mutex<DbPollSharedState>>,
+        block_range: BlockRange,
         readiness_indicator: ReadinessIndicator,
         logger: Logger,
     ) -> Self {
@@ -57,7 +61,7 @@ impl DbFetcher {
                     DbFetcherThread::start(
                         db,
                         thread_stop_requested,
-                        0,
+                        block_range,
                         enclave,
                         watcher,
                         thread_shared_state,
@@ -94,6 +98,7 @@ impl Drop for DbFetcher {
 struct DbFetcherThread<DB: Ledger, E: LedgerEnclaveProxy + Clone + Send + Sync + 'static> {
     db: DB,
     stop_requested: Arc<AtomicBool>,
+    block_range: BlockRange,
     next_block_index: u64,
     enclave: E,
     watcher: WatcherDB,
@@ -111,7 +116,7 @@ impl<DB: Ledger, E: LedgerEnclaveProxy + Clone + Send + Sync + 'static> DbFetche
     pub fn start(
         db: DB,
         stop_requested: Arc<AtomicBool>,
-        next_block_index: u64,
+        block_range: BlockRange,
         enclave: E,
         watcher: WatcherDB,
         db_poll_shared_state: Arc<Mutex<DbPollSharedState>>,
@@ -121,7 +126,8 @@ impl<DB: Ledger, E: LedgerEnclaveProxy + Clone + Send + Sync + 'static> DbFetche
         let thread = Self {
             db,
             stop_requested,
-            next_block_index,
+            next_block_index: block_range.start_block,
+            block_range,
             enclave,
             watcher,
             db_poll_shared_state,
@@ -133,7 +139,12 @@ impl<DB: Ledger, E: LedgerEnclaveProxy + Clone + Send + Sync + 'static> DbFetche
 
     fn run(mut self) {
         log::info!(self.logger, "Db fetcher thread started.");
-        self.next_block_index = 0;
+        self.next_block_index = self.block_range.start_block;
+        {
+            // None of the blocks in our range are processed yet.
+            let mut shared_state = self.db_poll_shared_state.lock().expect("mutex poisoned");
+            shared_state.highest_processed_block_count = self.next_block_index;
+        }
         loop {
             if self.stop_requested.load(Ordering::SeqCst) {
                 log::info!(self.logger, "Db fetcher thread stop requested.");
@@ -180,6 +191,11 @@ impl<DB: Ledger, E: LedgerEnclaveProxy + Clone + Send + Sync + 'static> DbFetche
         let mut may_have_more_work = true;
         let watcher_timeout: Duration = Duration::from_millis(5000);
 
+        // Blocks past the end of our range are left to other key image stores.
+        if !self.block_range.contains(self.next_block_index) {
+            return false;
+        }
+
         let start_time = SystemTime::now();
 
         match self.db.get_block_contents(self.next_block_index) {
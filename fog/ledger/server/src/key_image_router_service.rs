@@ -0,0 +1,248 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Serves client key image checks by forwarding them to key image stores and
+//! merging their responses.
+
+use crate::{key_image_store_connection::KeyImageStoreConnectionPool, server::DbPollSharedState};
+use grpcio::{RpcContext, RpcStatus, UnarySink};
+use mc_attest_api::{
+    attest,
+    attest::{AuthMessage, Message},
+};
+use mc_attest_enclave_api::ClientSession;
+use mc_common::logger::{log, Logger};
+use mc_fog_api::{ledger::KeyImageStoreResponse, ledger_grpc::FogKeyImageApi};
+use mc_fog_ledger_enclave::LedgerEnclaveProxy;
+use mc_fog_ledger_enclave_api::{Error as EnclaveError, UntrustedKeyImageQueryResponse};
+use mc_util_grpc::{
+    rpc_internal_error, rpc_invalid_arg_error, rpc_logger, rpc_permissions_error, send_result,
+    Authenticator,
+};
+use mc_util_metrics::SVC_COUNTERS;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct KeyImageRouterService<E: LedgerEnclaveProxy> {
+    enclave: E,
+    /// Connections to the key image stores.
+    store_connections: Arc<KeyImageStoreConnectionPool<E>>,
+    authenticator: Arc<dyn Authenticator + Send + Sync>,
+    logger: Logger,
+}
+
+impl<E: LedgerEnclaveProxy> KeyImageRouterService<E> {
+    pub fn new(
+        enclave: E,
+        store_connections: Arc<KeyImageStoreConnectionPool<E>>,
+        authenticator: Arc<dyn Authenticator + Send + Sync>,
+        logger: Logger,
+    ) -> Self {
+        Self {
+            enclave,
+            store_connections,
+            authenticator,
+            logger,
+        }
+    }
+
+    /// Forward to every store through the enclave, and collate their responses
+    fn check_key_images_auth(
+        &mut self,
+        request: attest::Message,
+    ) -> Result<attest::Message, RpcStatus> {
+        log::trace!(self.logger, "Getting encrypted request");
+        let client_session = ClientSession::from(request.get_channel_id());
+
+        // The enclave re-encrypts the check for every store, over a set of store
+        // connections which this check has to itself until it is done.
+        let result_blob = self.store_connections.query(
+            |store_sessions| {
+                self.enclave
+                    .create_key_image_store_queries(request.into(), store_sessions)
+                    .map_err(|e| self.enclave_err_to_rpc_status("enclave create store queries", e))
+            },
+            |store_responses| self.collate_store_responses(client_session, store_responses),
+        )?;
+
+        let mut resp = attest::Message::new();
+        resp.set_data(result_blob);
+        Ok(resp)
+    }
+
+    /// Have the enclave merge the stores' responses into the client's.
+    fn collate_store_responses(
+        &self,
+        client_session: ClientSession,
+        store_responses: Vec<KeyImageStoreResponse>,
+    ) -> Result<Vec<u8>, RpcStatus> {
+        let block_counts = combine_block_counts(&store_responses);
+        let untrusted_query_response = UntrustedKeyImageQueryResponse {
+            highest_processed_block_count: block_counts.highest_processed_block_count,
+            last_known_block_cumulative_txo_count: block_counts
+                .last_known_block_cumulative_txo_count,
+            latest_block_version: block_counts.latest_block_version,
+            max_block_version: core::cmp::max(
+                block_counts.latest_block_version,
+                *mc_transaction_core::MAX_BLOCK_VERSION,
+            ),
+        };
+
+        let store_messages = store_responses
+            .into_iter()
+            .map(|mut response| response.take_query_response().into())
+            .collect();
+
+        self.enclave
+            .collate_key_image_store_responses(
+                client_session,
+                store_messages,
+                untrusted_query_response,
+            )
+            .map_err(|e| self.enclave_err_to_rpc_status("enclave collate", e))
+    }
+
+    // Helper function that is common
+    fn enclave_err_to_rpc_status(&self, context: &str, src: EnclaveError) -> RpcStatus {
+        // Treat prost-decode error as an invalid arg,
+        // treat attest error as permission denied,
+        // everything else is an internal error
+        match src {
+            EnclaveError::ProstDecode => {
+                rpc_invalid_arg_error(context, "Prost decode failed", &self.logger)
+            }
+            EnclaveError::Attest(err) => rpc_permissions_error(context, err, &self.logger),
+            other => rpc_internal_error(context, format!("{}", &other), &self.logger),
+        }
+    }
+}
+
+/// Combine the progress reported by the key image stores into that of the
+/// whole ledger.
+///
+/// Blocks are processed up to the end of the longest run of processed blocks
+/// starting at the origin block, going from one store's range to the next.
+/// The txo count and block version are those of the store that processed the
+/// most blocks.
+pub fn combine_block_counts(store_responses: &[KeyImageStoreResponse]) -> DbPollSharedState {
+    let mut by_start_block = store_responses.iter().collect::<Vec<_>>();
+    by_start_block.sort_by_key(|response| response.get_block_range().get_start_block());
+
+    let mut result = DbPollSharedState::default();
+    for response in by_start_block {
+        if response.get_block_range().get_start_block() <= result.highest_processed_block_count
+            && response.get_highest_processed_block_count() > result.highest_processed_block_count
+        {
+            result.highest_processed_block_count = response.get_highest_processed_block_count();
+        }
+    }
+
+    if let Some(response) = store_responses
+        .iter()
+        .max_by_key(|response| response.get_highest_processed_block_count())
+    {
+        result.last_known_block_cumulative_txo_count =
+            response.get_last_known_block_cumulative_txo_count();
+        result.latest_block_version = response.get_latest_block_version();
+    }
+
+    result
+}
+
+impl<E: LedgerEnclaveProxy> FogKeyImageApi for KeyImageRouterService<E> {
+    fn check_key_images(&mut self, ctx: RpcContext, request: Message, sink: UnarySink<Message>) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            if let Err(err) = self.authenticator.authenticate_rpc(&ctx) {
+                return send_result(ctx, sink, err.into(), logger);
+            }
+
+            send_result(ctx, sink, self.check_key_images_auth(request), logger)
+        })
+    }
+
+    fn auth(&mut self, ctx: RpcContext, request: AuthMessage, sink: UnarySink<AuthMessage>) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            if let Err(err) = self.authenticator.authenticate_rpc(&ctx) {
+                return send_result(ctx, sink, err.into(), logger);
+            }
+
+            // TODO: Use the prost message directly, once available
+            match self.enclave.client_accept(request.into()) {
+                Ok((response, _session_id)) => {
+                    send_result(ctx, sink, Ok(response.into()), logger);
+                }
+                Err(client_error) => {
+                    // This is debug because there's no requirement on the remote party to trigger
+                    // it.
+                    log::info!(
+                        logger,
+                        "LedgerEnclave::client_accept failed: {}",
+                        client_error
+                    );
+                    send_result(
+                        ctx,
+                        sink,
+                        Err(rpc_permissions_error(
+                            "client_auth",
+                            "Permission denied",
+                            logger,
+                        )),
+                        logger,
+                    );
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_fog_api::fog_common::BlockRange;
+
+    fn store_response(
+        start_block: u64,
+        end_block: u64,
+        highest_processed_block_count: u64,
+    ) -> KeyImageStoreResponse {
+        let mut block_range = BlockRange::new();
+        block_range.set_start_block(start_block);
+        block_range.set_end_block(end_block);
+
+        let mut response = KeyImageStoreResponse::new();
+        response.set_block_range(block_range);
+        response.set_highest_processed_block_count(highest_processed_block_count);
+        response.set_last_known_block_cumulative_txo_count(10 * highest_processed_block_count);
+        response.set_latest_block_version(highest_processed_block_count as u32);
+        response
+    }
+
+    #[test]
+    fn test_combine_block_counts() {
+        // No stores.
+        let combined = combine_block_counts(&[]);
+        assert_eq!(combined.highest_processed_block_count, 0);
+
+        // The first store is done with its range, the second is catching up.
+        let combined =
+            combine_block_counts(&[store_response(10, u64::MAX, 15), store_response(0, 10, 10)]);
+        assert_eq!(combined.highest_processed_block_count, 15);
+        assert_eq!(combined.last_known_block_cumulative_txo_count, 150);
+        assert_eq!(combined.latest_block_version, 15);
+
+        // The first store is lagging, so the second one's progress does not count.
+        let combined =
+            combine_block_counts(&[store_response(0, 10, 7), store_response(10, u64::MAX, 15)]);
+        assert_eq!(combined.highest_processed_block_count, 7);
+        assert_eq!(combined.last_known_block_cumulative_txo_count, 150);
+
+        // Overlapping ranges.
+        let combined = combine_block_counts(&[
+            store_response(0, 12, 12),
+            store_response(8, 20, 10),
+            store_response(10, u64::MAX, 18),
+        ]);
+        assert_eq!(combined.highest_processed_block_count, 18);
+    }
+}
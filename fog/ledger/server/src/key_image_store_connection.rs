@@ -0,0 +1,61 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Attested connections from a fog ledger router to key image stores.
+
+use core::marker::PhantomData;
+use grpcio::{Channel, ClientUnaryReceiver, Result as GrpcResult};
+use mc_attest_api::attest::Message;
+use mc_attest_core::VerificationReport;
+use mc_attest_enclave_api::{PeerAuthRequest, PeerAuthResponse, PeerSession};
+use mc_common::ResponderId;
+use mc_fog_api::{ledger::KeyImageStoreResponse, ledger_grpc::KeyImageStoreApiClient};
+use mc_fog_ledger_enclave::{Error as LedgerEnclaveError, LedgerEnclaveProxy};
+use mc_fog_store_connection::{StoreApi, StoreConnection, StoreConnectionPool};
+use mc_fog_uri::KeyImageStoreUri;
+
+/// The key image store API, as used by a router whose enclave is `E`.
+pub struct KeyImageStoreApi<E: LedgerEnclaveProxy>(PhantomData<E>);
+
+impl<E: LedgerEnclaveProxy> StoreApi for KeyImageStoreApi<E> {
+    type Enclave = E;
+    type EnclaveError = LedgerEnclaveError;
+    type Uri = KeyImageStoreUri;
+    type Client = KeyImageStoreApiClient;
+    type Response = KeyImageStoreResponse;
+
+    fn peer_init(
+        enclave: &E,
+        peer_id: &ResponderId,
+    ) -> Result<PeerAuthRequest, LedgerEnclaveError> {
+        enclave.peer_init(peer_id)
+    }
+
+    fn peer_connect(
+        enclave: &E,
+        peer_id: &ResponderId,
+        msg: PeerAuthResponse,
+    ) -> Result<(PeerSession, VerificationReport), LedgerEnclaveError> {
+        enclave.peer_connect(peer_id, msg)
+    }
+
+    fn peer_close(enclave: &E, session: &PeerSession) -> Result<(), LedgerEnclaveError> {
+        enclave.peer_close(session)
+    }
+
+    fn new_client(channel: Channel) -> KeyImageStoreApiClient {
+        KeyImageStoreApiClient::new(channel)
+    }
+
+    fn query_async(
+        client: &KeyImageStoreApiClient,
+        query: &Message,
+    ) -> GrpcResult<ClientUnaryReceiver<KeyImageStoreResponse>> {
+        client.check_key_images_async(query)
+    }
+}
+
+/// A connection to a key image store.
+pub type KeyImageStoreConnection<E> = StoreConnection<KeyImageStoreApi<E>>;
+
+/// A pool of sets of connections to the key image stores.
+pub type KeyImageStoreConnectionPool<E> = StoreConnectionPool<KeyImageStoreApi<E>>;
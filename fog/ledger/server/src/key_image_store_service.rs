@@ -0,0 +1,100 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Serves the key image checks fog ledger routers forward to this server.
+
+use crate::server::DbPollSharedState;
+use grpcio::{RpcContext, RpcStatus, UnarySink};
+use mc_attest_api::attest;
+use mc_common::logger::{log, Logger};
+use mc_fog_api::{ledger::KeyImageStoreResponse, ledger_grpc::KeyImageStoreApi};
+use mc_fog_ledger_enclave::LedgerEnclaveProxy;
+use mc_fog_ledger_enclave_api::Error as EnclaveError;
+use mc_fog_types::common::BlockRange;
+use mc_util_grpc::{
+    rpc_internal_error, rpc_invalid_arg_error, rpc_logger, rpc_permissions_error, send_result,
+};
+use mc_util_metrics::SVC_COUNTERS;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+pub struct KeyImageStoreService<E: LedgerEnclaveProxy> {
+    /// Enclave holding the key images of the blocks in `block_range`
+    enclave: E,
+
+    /// Shared state from db polling thread.
+    db_poll_shared_state: Arc<Mutex<DbPollSharedState>>,
+
+    /// The range of blocks this store holds the key images of.
+    block_range: BlockRange,
+
+    logger: Logger,
+}
+
+impl<E: LedgerEnclaveProxy> KeyImageStoreService<E> {
+    pub fn new(
+        enclave: E,
+        db_poll_shared_state: Arc<Mutex<DbPollSharedState>>,
+        block_range: BlockRange,
+        logger: Logger,
+    ) -> Self {
+        Self {
+            enclave,
+            db_poll_shared_state,
+            block_range,
+            logger,
+        }
+    }
+
+    /// Forward to enclave, and attach the progress of this store
+    fn check_key_images_impl(
+        &mut self,
+        request: attest::Message,
+    ) -> Result<KeyImageStoreResponse, RpcStatus> {
+        log::trace!(self.logger, "Getting encrypted store request");
+
+        let query_response = self
+            .enclave
+            .check_key_image_store(request.into())
+            .map_err(|e| self.enclave_err_to_rpc_status("enclave store request", e))?;
+
+        let mut response = KeyImageStoreResponse::new();
+        response.set_query_response(query_response.into());
+        response.set_block_range((&self.block_range).into());
+
+        let shared_state = self.db_poll_shared_state.lock().expect("mutex poisoned");
+        response.set_highest_processed_block_count(shared_state.highest_processed_block_count);
+        response.set_last_known_block_cumulative_txo_count(
+            shared_state.last_known_block_cumulative_txo_count,
+        );
+        response.set_latest_block_version(shared_state.latest_block_version);
+        Ok(response)
+    }
+
+    // Helper function that is common
+    fn enclave_err_to_rpc_status(&self, context: &str, src: EnclaveError) -> RpcStatus {
+        // Treat prost-decode error as an invalid arg,
+        // treat attest error as permission denied,
+        // everything else is an internal error
+        match src {
+            EnclaveError::ProstDecode => {
+                rpc_invalid_arg_error(context, "Prost decode failed", &self.logger)
+            }
+            EnclaveError::Attest(err) => rpc_permissions_error(context, err, &self.logger),
+            other => rpc_internal_error(context, format!("{}", &other), &self.logger),
+        }
+    }
+}
+
+impl<E: LedgerEnclaveProxy> KeyImageStoreApi for KeyImageStoreService<E> {
+    fn check_key_images(
+        &mut self,
+        ctx: RpcContext,
+        request: attest::Message,
+        sink: UnarySink<KeyImageStoreResponse>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            send_result(ctx, sink, self.check_key_images_impl(request), logger)
+        })
+    }
+}
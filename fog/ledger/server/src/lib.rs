@@ -1,17 +1,30 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
+mod attested_api_service;
 mod block_service;
 mod config;
 mod counters;
 mod db_fetcher;
+mod key_image_router_service;
 mod key_image_service;
+mod key_image_store_connection;
+mod key_image_store_service;
 mod merkle_proof_service;
+mod router_config;
+mod router_server;
 mod server;
 mod untrusted_tx_out_service;
 
 pub use block_service::BlockService;
 pub use config::LedgerServerConfig;
+pub use key_image_router_service::KeyImageRouterService;
 pub use key_image_service::KeyImageService;
+pub use key_image_store_connection::{
+    KeyImageStoreApi, KeyImageStoreConnection, KeyImageStoreConnectionPool,
+};
+pub use key_image_store_service::KeyImageStoreService;
 pub use merkle_proof_service::MerkleProofService;
+pub use router_config::LedgerRouterConfig;
+pub use router_server::{LedgerRouterServer, ROUTER_OMAP_CAPACITY};
 pub use server::LedgerServer;
 pub use untrusted_tx_out_service::UntrustedTxOutService;
@@ -0,0 +1,80 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Configuration parameters for the ledger router
+
+#![deny(missing_docs)]
+
+use clap::Parser;
+use mc_attest_core::ProviderId;
+use mc_common::ResponderId;
+use mc_fog_uri::{FogLedgerUri, KeyImageStoreUri};
+use mc_util_parse::parse_duration_in_seconds;
+use mc_util_uri::AdminUri;
+use serde::Serialize;
+use std::{path::PathBuf, time::Duration};
+
+/// Configuration parameters for the ledger router
+#[derive(Clone, Parser, Serialize)]
+#[clap(version)]
+pub struct LedgerRouterConfig {
+    /// gRPC listening URI for client requests.
+    #[clap(long, env = "MC_CLIENT_LISTEN_URI")]
+    pub client_listen_uri: FogLedgerUri,
+
+    /// Path to ledger db (lmdb)
+    #[clap(long, parse(from_os_str), env = "MC_LEDGER_DB")]
+    pub ledger_db: PathBuf,
+
+    /// Path to watcher db (lmdb) - includes block timestamps
+    #[clap(long, parse(from_os_str), env = "MC_WATCHER_DB")]
+    pub watcher_db: PathBuf,
+
+    /// Client Responder id.
+    ///
+    /// This ID needs to match the host:port clients use in their URI when
+    /// referencing this node.
+    #[clap(long, env = "MC_CLIENT_RESPONDER_ID")]
+    pub client_responder_id: ResponderId,
+
+    /// IAS Api Key.
+    #[clap(long, env = "MC_IAS_API_KEY")]
+    pub ias_api_key: String,
+
+    /// IAS Service Provider ID.
+    #[clap(long, env = "MC_IAS_SPID")]
+    pub ias_spid: ProviderId,
+
+    /// Optional admin listening URI.
+    #[clap(long, env = "MC_ADMIN_LISTEN_URI")]
+    pub admin_listen_uri: Option<AdminUri>,
+
+    /// Enables authenticating client requests using Authorization tokens using
+    /// the provided hex-encoded 32 bytes shared secret.
+    #[clap(long, parse(try_from_str = hex::FromHex::from_hex), env = "MC_CLIENT_AUTH_TOKEN_SECRET")]
+    pub client_auth_token_secret: Option<[u8; 32]>,
+
+    /// Maximal client authentication token lifetime, in seconds (only relevant
+    /// when --client-auth-token-secret is used. Defaults to 86400 - 24
+    /// hours).
+    #[clap(long, default_value = "86400", parse(try_from_str = parse_duration_in_seconds), env = "MC_CLIENT_AUTH_TOKEN_MAX_LIFETIME")]
+    pub client_auth_token_max_lifetime: Duration,
+
+    /// The key image stores to forward key image checks to.
+    ///
+    /// Each store's responder id needs to match its --client-responder-id.
+    /// Together, the stores' block ranges should cover all the blocks.
+    #[clap(
+        long = "key-image-store-uri",
+        required = true,
+        use_value_delimiter = true,
+        env = "MC_KEY_IMAGE_STORE_URIS"
+    )]
+    pub key_image_store_uris: Vec<KeyImageStoreUri>,
+
+    /// The number of key image checks which can be forwarded to the stores at
+    /// once. Each of them uses an attested session of its own with every
+    /// store, and the router's enclave keeps at most 64 such sessions, so this
+    /// times the number of stores should not exceed that.
+    #[clap(long, default_value = "4", env = "MC_STORE_CONNECTION_POOL_SIZE")]
+    pub store_connection_pool_size: usize,
+}
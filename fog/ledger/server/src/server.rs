@@ -1,12 +1,14 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
 use crate::{
-    config::LedgerServerConfig, counters, db_fetcher::DbFetcher, BlockService, KeyImageService,
-    MerkleProofService, UntrustedTxOutService,
+    attested_api_service::AttestedApiService, config::LedgerServerConfig, counters,
+    db_fetcher::DbFetcher, key_image_store_service::KeyImageStoreService, BlockService,
+    KeyImageService, MerkleProofService, UntrustedTxOutService,
 };
 use displaydoc::Display;
 use futures::executor::block_on;
 use grpcio::Error as GrpcError;
+use mc_attest_api::attest_grpc;
 use mc_attest_net::RaClient;
 use mc_common::{
     logger::{log, Logger},
@@ -161,6 +163,7 @@ impl<E: LedgerEnclaveProxy, R: RaClient + Send + Sync + 'static> LedgerServer<E,
                 self.enclave.clone(),
                 self.key_image_service.get_watcher(),
                 self.key_image_service.get_db_poll_shared_state(),
+                self.config.block_range(),
                 readiness_indicator.clone(),
                 self.logger.clone(),
             ));
@@ -180,6 +183,21 @@ impl<E: LedgerEnclaveProxy, R: RaClient + Send + Sync + 'static> LedgerServer<E,
             let untrusted_tx_out_service =
                 ledger_grpc::create_fog_untrusted_tx_out_api(self.untrusted_tx_out_service.clone());
 
+            // Routers attest with the enclave and then check key images against it. The
+            // attestation is what authenticates them, so the client authenticator is not
+            // used here.
+            let attested_api_service = attest_grpc::create_attested_api(AttestedApiService::new(
+                self.enclave.clone(),
+                self.logger.clone(),
+            ));
+            let key_image_store_service =
+                ledger_grpc::create_key_image_store_api(KeyImageStoreService::new(
+                    self.enclave.clone(),
+                    self.key_image_service.get_db_poll_shared_state(),
+                    self.config.block_range(),
+                    self.logger.clone(),
+                ));
+
             // Health check service
             let health_service = mc_util_grpc::HealthService::new(
                 Some(readiness_indicator.into()),
@@ -193,13 +211,20 @@ impl<E: LedgerEnclaveProxy, R: RaClient + Send + Sync + 'static> LedgerServer<E,
                 "Starting Ledger server on {}",
                 self.config.client_listen_uri.addr(),
             );
-            let server_builder = grpcio::ServerBuilder::new(env)
-                .register_service(key_image_service)
+            let mut server_builder = grpcio::ServerBuilder::new(env)
                 .register_service(merkle_proof_service)
                 .register_service(block_service)
                 .register_service(untrusted_tx_out_service)
-                .register_service(health_service)
-                .bind_using_uri(&self.config.client_listen_uri, self.logger.clone());
+                .register_service(attested_api_service)
+                .register_service(key_image_store_service)
+                .register_service(health_service);
+            // A store only holds some of the key images, so clients must go through a
+            // router to check theirs.
+            if !self.config.is_store() {
+                server_builder = server_builder.register_service(key_image_service);
+            }
+            let server_builder =
+                server_builder.bind_using_uri(&self.config.client_listen_uri, self.logger.clone());
 
             let mut server = server_builder.build()?;
             server.start();
@@ -129,6 +129,10 @@ fn fog_ledger_merkle_proofs_test(logger: Logger) {
                 client_auth_token_secret: None,
                 client_auth_token_max_lifetime: Default::default(),
                 omap_capacity: OMAP_CAPACITY,
+                oram_storage_dir: None,
+                oram_storage_memory_bytes: 0,
+                shard_start_block: 0,
+                shard_end_block: None,
             };
 
             let enclave = LedgerSgxEnclave::new(
@@ -321,6 +325,10 @@ fn fog_ledger_key_images_test(logger: Logger) {
                 client_auth_token_secret: None,
                 client_auth_token_max_lifetime: Default::default(),
                 omap_capacity: OMAP_CAPACITY,
+                oram_storage_dir: None,
+                oram_storage_memory_bytes: 0,
+                shard_start_block: 0,
+                shard_end_block: None,
             };
 
             let enclave = LedgerSgxEnclave::new(
@@ -518,6 +526,10 @@ fn fog_ledger_blocks_api_test(logger: Logger) {
             client_auth_token_secret: None,
             client_auth_token_max_lifetime: Default::default(),
             omap_capacity: OMAP_CAPACITY,
+            oram_storage_dir: None,
+            oram_storage_memory_bytes: 0,
+            shard_start_block: 0,
+            shard_end_block: None,
         };
 
         let enclave = LedgerSgxEnclave::new(
@@ -679,6 +691,10 @@ fn fog_ledger_untrusted_tx_out_api_test(logger: Logger) {
             client_auth_token_secret: None,
             client_auth_token_max_lifetime: Default::default(),
             omap_capacity: OMAP_CAPACITY,
+            oram_storage_dir: None,
+            oram_storage_memory_bytes: 0,
+            shard_start_block: 0,
+            shard_end_block: None,
         };
 
         let enclave = LedgerSgxEnclave::new(
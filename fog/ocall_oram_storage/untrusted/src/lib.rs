@@ -32,6 +32,12 @@
 //! this side, we have to do a little arithmetic ourselves.
 //! It is untenable for the untrusted side to also know these sizes statically,
 //! it would create a strange coupling in the build process.
+//!
+//! Storage can also be kept on disk, see [configure_oram_storage]. Then only
+//! the first items of each allocation are kept in memory, up to a total
+//! memory budget, and the rest are kept in a file. The enclave asks for the
+//! levels of its trees below the ones it caches itself, in order from the root,
+//! so the first items are the levels every access reads from.
 
 #![deny(missing_docs)]
 
@@ -39,9 +45,47 @@ use mc_common::logger::global_log;
 use std::{
     alloc::{alloc, alloc_zeroed, dealloc, Layout},
     boxed::Box,
-    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    fs::{self, File, OpenOptions},
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+    ptr::NonNull,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
 };
 
+/// Where the untrusted side keeps ORAM storage
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OramStorageConfig {
+    /// A directory, on a local disk, in which to keep the storage that does not
+    /// fit in `memory_bytes`. If None, all storage is kept in memory.
+    pub dir: Option<PathBuf>,
+
+    /// When `dir` is set, the number of bytes of storage to keep in memory,
+    /// across all allocations.
+    pub memory_bytes: u64,
+}
+
+lazy_static::lazy_static! {
+    static ref CONFIG: Mutex<OramStorageConfig> = Mutex::new(Default::default());
+}
+
+/// Choose where to keep the ORAM storage allocated from now on.
+///
+/// This should be called before the enclave is initialized, since that is
+/// when it allocates its storage.
+pub fn configure_oram_storage(config: OramStorageConfig) {
+    if let Some(dir) = config.dir.as_ref() {
+        global_log::info!(
+            "Untrusted will keep oram storage in {:?}, beyond the first {} bytes",
+            dir,
+            config.memory_bytes
+        );
+    }
+    *CONFIG.lock().unwrap() = config;
+}
+
 /// Resources held on untrusted side in connection to an allocation request by
 /// enclave
 ///
@@ -54,10 +98,15 @@ struct UntrustedAllocation {
     data_item_size: usize,
     /// The size of a meta item in bytes
     meta_item_size: usize,
-    /// The pointer to the data items
+    /// The number of data and meta items kept in memory, the first ones
+    mem_count: usize,
+    /// The pointer to the data items kept in memory
     data_pointer: *mut u64,
-    /// The pointer to the meta items
+    /// The pointer to the meta items kept in memory
     meta_pointer: *mut u64,
+    /// The file holding the other items: all of their data items, followed by
+    /// all of their meta items
+    file: Option<File>,
     /// A flag set to true when a thread is in the critical section and released
     /// when it leaves. This is used to trigger assertions if there is a
     /// race happening on this API This is simpler and less expensive than
@@ -69,9 +118,16 @@ struct UntrustedAllocation {
     checkout_flag: AtomicBool,
 }
 
-/// Tracks total memory allocated via this mechanism for logging purposes
+/// Tracks total memory allocated via this mechanism, for logging purposes and
+/// to stay within the configured memory budget
 static TOTAL_MEM_FOOTPRINT_KB: AtomicU64 = AtomicU64::new(0);
 
+/// Tracks total disk space allocated via this mechanism for logging purposes
+static TOTAL_DISK_FOOTPRINT_KB: AtomicU64 = AtomicU64::new(0);
+
+/// Used to give the files of allocations distinct names
+static NEXT_FILE_NUMBER: AtomicU64 = AtomicU64::new(0);
+
 /// Helper which computes the total memory in kb allocated for count,
 /// data_item_size, meta_item_size
 fn compute_mem_kb(count: usize, data_item_size: usize, meta_item_size: usize) -> u64 {
@@ -81,15 +137,17 @@ fn compute_mem_kb(count: usize, data_item_size: usize, meta_item_size: usize) ->
 }
 
 impl UntrustedAllocation {
-    /// Create a new untrusted allocation for given count and item sizes, on the
-    /// heap
+    /// Create a new untrusted allocation for given count and item sizes, in
+    /// memory and, if configured, on disk
     ///
     /// Data and meta item sizes must be divisible by 8, consistent with the
     /// contract described in the edl file
-    pub fn new(count: usize, data_item_size: usize, meta_item_size: usize) -> Self {
-        let mem_kb = compute_mem_kb(count, data_item_size, meta_item_size);
-        let total_mem_kb = mem_kb + TOTAL_MEM_FOOTPRINT_KB.fetch_add(mem_kb, Ordering::SeqCst);
-        global_log::info!("Untrusted is allocating oram storage: count = {}, data_size = {}, meta_size = {}, mem = {} KB. Total mem allocated this way = {} KB", count, data_item_size, meta_item_size, mem_kb, total_mem_kb);
+    pub fn new(
+        count: usize,
+        data_item_size: usize,
+        meta_item_size: usize,
+        config: &OramStorageConfig,
+    ) -> Self {
         assert!(
             data_item_size % 8 == 0,
             "data item size is not good: {}",
@@ -101,25 +159,31 @@ impl UntrustedAllocation {
             meta_item_size
         );
 
-        let data_pointer = unsafe {
-            alloc(Layout::from_size_align(count * data_item_size, 8).unwrap()) as *mut u64
-        };
+        let (mem_count, total_mem_kb) = reserve_mem(count, data_item_size, meta_item_size, config);
+        let mem_kb = compute_mem_kb(mem_count, data_item_size, meta_item_size);
+        global_log::info!("Untrusted is allocating oram storage: count = {}, data_size = {}, meta_size = {}, mem = {} KB. Total mem allocated this way = {} KB", count, data_item_size, meta_item_size, mem_kb, total_mem_kb);
+
+        let data_pointer = alloc_items(mem_count * data_item_size, false);
         if data_pointer.is_null() {
             panic!(
                 "Could not allocate memory for data segment: {}",
-                count * data_item_size
+                mem_count * data_item_size
             )
         }
-        let meta_pointer = unsafe {
-            alloc_zeroed(Layout::from_size_align(count * meta_item_size, 8).unwrap()) as *mut u64
-        };
+        let meta_pointer = alloc_items(mem_count * meta_item_size, true);
         if meta_pointer.is_null() {
             panic!(
                 "Could not allocate memory for meta segment: {}",
-                count * meta_item_size
+                mem_count * meta_item_size
             )
         }
 
+        let file = config
+            .dir
+            .as_ref()
+            .filter(|_| mem_count < count)
+            .map(|dir| create_file(dir, count - mem_count, data_item_size, meta_item_size));
+
         let critical_section_flag = AtomicBool::new(false);
         let checkout_flag = AtomicBool::new(false);
 
@@ -127,31 +191,209 @@ impl UntrustedAllocation {
             count,
             data_item_size,
             meta_item_size,
+            mem_count,
             data_pointer,
             meta_pointer,
+            file,
             critical_section_flag,
             checkout_flag,
         }
     }
+
+    /// Copy the data and meta items at an index to the given buffers
+    ///
+    /// # Safety
+    ///
+    /// The buffers must hold data_item_size and meta_item_size bytes.
+    unsafe fn read_item(&self, index: usize, data: *mut u64, meta: *mut u64) {
+        assert!(index < self.count, "index out of bounds");
+        if index < self.mem_count {
+            core::ptr::copy_nonoverlapping(
+                self.data_pointer.add(self.data_item_size / 8 * index),
+                data,
+                self.data_item_size / 8,
+            );
+            core::ptr::copy_nonoverlapping(
+                self.meta_pointer.add(self.meta_item_size / 8 * index),
+                meta,
+                self.meta_item_size / 8,
+            );
+        } else {
+            let (data_offset, meta_offset) = self.file_offsets(index);
+            let file = self.file.as_ref().expect("items beyond memory need a file");
+            file.read_exact_at(
+                core::slice::from_raw_parts_mut(data as *mut u8, self.data_item_size),
+                data_offset,
+            )
+            .expect("Could not read oram storage data from file");
+            file.read_exact_at(
+                core::slice::from_raw_parts_mut(meta as *mut u8, self.meta_item_size),
+                meta_offset,
+            )
+            .expect("Could not read oram storage meta from file");
+        }
+    }
+
+    /// Copy the data and meta items in the given buffers to an index
+    ///
+    /// # Safety
+    ///
+    /// The buffers must hold data_item_size and meta_item_size bytes.
+    unsafe fn write_item(&self, index: usize, data: *const u64, meta: *const u64) {
+        assert!(index < self.count, "index out of bounds");
+        if index < self.mem_count {
+            core::ptr::copy_nonoverlapping(
+                data,
+                self.data_pointer.add(self.data_item_size / 8 * index),
+                self.data_item_size / 8,
+            );
+            core::ptr::copy_nonoverlapping(
+                meta,
+                self.meta_pointer.add(self.meta_item_size / 8 * index),
+                self.meta_item_size / 8,
+            );
+        } else {
+            let (data_offset, meta_offset) = self.file_offsets(index);
+            let file = self.file.as_ref().expect("items beyond memory need a file");
+            file.write_all_at(
+                core::slice::from_raw_parts(data as *const u8, self.data_item_size),
+                data_offset,
+            )
+            .expect("Could not write oram storage data to file");
+            file.write_all_at(
+                core::slice::from_raw_parts(meta as *const u8, self.meta_item_size),
+                meta_offset,
+            )
+            .expect("Could not write oram storage meta to file");
+        }
+    }
+
+    /// The offsets in the file of the data and meta items at an index which is
+    /// not kept in memory
+    fn file_offsets(&self, index: usize) -> (u64, u64) {
+        let file_index = (index - self.mem_count) as u64;
+        let file_count = (self.count - self.mem_count) as u64;
+        (
+            file_index * self.data_item_size as u64,
+            file_count * self.data_item_size as u64 + file_index * self.meta_item_size as u64,
+        )
+    }
 }
 
 impl Drop for UntrustedAllocation {
     fn drop(&mut self) {
         unsafe {
-            dealloc(
-                self.data_pointer as *mut u8,
-                Layout::from_size_align_unchecked(self.count * self.data_item_size, 8),
-            );
-            dealloc(
-                self.meta_pointer as *mut u8,
-                Layout::from_size_align_unchecked(self.count * self.meta_item_size, 8),
+            dealloc_items(self.data_pointer, self.mem_count * self.data_item_size);
+            dealloc_items(self.meta_pointer, self.mem_count * self.meta_item_size);
+        }
+        let mem_kb = compute_mem_kb(self.mem_count, self.data_item_size, self.meta_item_size);
+        TOTAL_MEM_FOOTPRINT_KB.fetch_sub(mem_kb, Ordering::SeqCst);
+        if self.file.is_some() {
+            let disk_kb = compute_mem_kb(
+                self.count - self.mem_count,
+                self.data_item_size,
+                self.meta_item_size,
             );
-            let mem_kb = compute_mem_kb(self.count, self.data_item_size, self.meta_item_size);
-            TOTAL_MEM_FOOTPRINT_KB.fetch_sub(mem_kb, Ordering::SeqCst);
+            TOTAL_DISK_FOOTPRINT_KB.fetch_sub(disk_kb, Ordering::SeqCst);
         }
     }
 }
 
+/// Reserve memory for the first items of an allocation of count items: all of
+/// them, or if storage is also kept on disk, as many as fit in what is left of
+/// the memory budget. Returns the number of items, and the total memory in kb
+/// allocated this way once they are.
+///
+/// The budget is checked and the memory reserved in a single atomic update, so
+/// that concurrent allocations cannot overshoot it.
+fn reserve_mem(
+    count: usize,
+    data_item_size: usize,
+    meta_item_size: usize,
+    config: &OramStorageConfig,
+) -> (usize, u64) {
+    let mut mem_count = count;
+    let prev_mem_kb = TOTAL_MEM_FOOTPRINT_KB
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used_kb| {
+            if config.dir.is_some() {
+                let available_bytes = config.memory_bytes.saturating_sub(used_kb * 1024);
+                mem_count = core::cmp::min(
+                    count,
+                    (available_bytes / (data_item_size + meta_item_size) as u64) as usize,
+                );
+            }
+            Some(used_kb + compute_mem_kb(mem_count, data_item_size, meta_item_size))
+        })
+        .expect("memory reservation is never rejected");
+    (
+        mem_count,
+        prev_mem_kb + compute_mem_kb(mem_count, data_item_size, meta_item_size),
+    )
+}
+
+/// Allocate memory for items, 8-byte aligned. Zero-sized allocations are not
+/// allowed by the allocator, so a dangling pointer is returned for them.
+fn alloc_items(size: usize, zeroed: bool) -> *mut u64 {
+    if size == 0 {
+        return NonNull::dangling().as_ptr();
+    }
+    let layout = Layout::from_size_align(size, 8).unwrap();
+    unsafe {
+        if zeroed {
+            alloc_zeroed(layout) as *mut u64
+        } else {
+            alloc(layout) as *mut u64
+        }
+    }
+}
+
+/// Free memory allocated by alloc_items
+///
+/// # Safety
+///
+/// pointer must have been returned by alloc_items for this size
+unsafe fn dealloc_items(pointer: *mut u64, size: usize) {
+    if size != 0 {
+        dealloc(
+            pointer as *mut u8,
+            Layout::from_size_align_unchecked(size, 8),
+        );
+    }
+}
+
+/// Create a file in dir for count items. The file is zeroed, which is what
+/// the meta items must initially be.
+///
+/// The file is removed from the directory right away, so that it disappears
+/// with the allocation, or the process.
+fn create_file(dir: &Path, count: usize, data_item_size: usize, meta_item_size: usize) -> File {
+    let path = dir.join(format!(
+        "oram-storage-{}-{}",
+        std::process::id(),
+        NEXT_FILE_NUMBER.fetch_add(1, Ordering::SeqCst)
+    ));
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .unwrap_or_else(|err| panic!("Could not create oram storage file {:?}: {}", path, err));
+    fs::remove_file(&path)
+        .unwrap_or_else(|err| panic!("Could not unlink oram storage file {:?}: {}", path, err));
+    file.set_len((count * (data_item_size + meta_item_size)) as u64)
+        .unwrap_or_else(|err| panic!("Could not size oram storage file {:?}: {}", path, err));
+
+    let disk_kb = compute_mem_kb(count, data_item_size, meta_item_size);
+    let total_disk_kb = disk_kb + TOTAL_DISK_FOOTPRINT_KB.fetch_add(disk_kb, Ordering::SeqCst);
+    global_log::info!(
+        "Untrusted is keeping oram storage on disk: count = {}, disk = {} KB. Total disk allocated this way = {} KB",
+        count,
+        disk_kb,
+        total_disk_kb
+    );
+    file
+}
+
 // These extern "C" functions must match edl file
 
 /// # Safety
@@ -165,10 +407,12 @@ pub unsafe extern "C" fn allocate_oram_storage(
     meta_size: u64,
     id_out: *mut u64,
 ) {
+    let config = CONFIG.lock().unwrap().clone();
     let result = Box::new(UntrustedAllocation::new(
         count as usize,
         data_size as usize,
         meta_size as usize,
+        &config,
     ));
     let id = Box::into_raw(result) as u64;
     #[cfg(debug_assertions)]
@@ -240,20 +484,10 @@ pub unsafe extern "C" fn checkout_oram_storage(
     let indices = core::slice::from_raw_parts(idx, idx_len);
 
     for (count, index) in indices.iter().enumerate() {
-        let index = *index as usize;
-        core::ptr::copy_nonoverlapping(
-            (*ptr).data_pointer.add(data_copy_size * index),
+        (*ptr).read_item(
+            *index as usize,
             databuf.add(data_copy_size * count),
-            data_copy_size,
-        );
-    }
-
-    for (count, index) in indices.iter().enumerate() {
-        let index = *index as usize;
-        core::ptr::copy_nonoverlapping(
-            (*ptr).meta_pointer.add(meta_copy_size * index),
             metabuf.add(meta_copy_size * count),
-            meta_copy_size,
         );
     }
 
@@ -312,20 +546,10 @@ pub unsafe extern "C" fn checkin_oram_storage(
     let indices = core::slice::from_raw_parts(idx, idx_len);
 
     for (count, index) in indices.iter().enumerate() {
-        let index = *index as usize;
-        core::ptr::copy_nonoverlapping(
+        (*ptr).write_item(
+            *index as usize,
             databuf.add(data_copy_size * count),
-            (*ptr).data_pointer.add(data_copy_size * index),
-            data_copy_size,
-        );
-    }
-
-    for (count, index) in indices.iter().enumerate() {
-        let index = *index as usize;
-        core::ptr::copy_nonoverlapping(
             metabuf.add(meta_copy_size * count),
-            (*ptr).meta_pointer.add(meta_copy_size * index),
-            meta_copy_size,
         );
     }
 
@@ -360,3 +584,63 @@ mod debug_checks {
         static ref VALID_IDS: Mutex<BTreeSet<u64>> = Mutex::new(Default::default());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    const DATA_ITEM_SIZE: usize = 64;
+    const META_ITEM_SIZE: usize = 8;
+
+    fn write(alloc: &UntrustedAllocation, index: usize, value: u64) {
+        let data = [value; DATA_ITEM_SIZE / 8];
+        let meta = [value; META_ITEM_SIZE / 8];
+        unsafe { alloc.write_item(index, data.as_ptr(), meta.as_ptr()) }
+    }
+
+    fn read(alloc: &UntrustedAllocation, index: usize) -> (Vec<u64>, Vec<u64>) {
+        let mut data = vec![0u64; DATA_ITEM_SIZE / 8];
+        let mut meta = vec![0u64; META_ITEM_SIZE / 8];
+        unsafe { alloc.read_item(index, data.as_mut_ptr(), meta.as_mut_ptr()) }
+        (data, meta)
+    }
+
+    // The storage configuration is passed in rather than set globally, so this
+    // does not affect other tests allocating storage through the ocalls.
+    #[test]
+    fn items_beyond_memory_budget_are_kept_on_disk() {
+        let dir = TempDir::new("oram_storage").unwrap();
+        let item_bytes = (DATA_ITEM_SIZE + META_ITEM_SIZE) as u64;
+        let config = OramStorageConfig {
+            dir: Some(dir.path().to_path_buf()),
+            memory_bytes: TOTAL_MEM_FOOTPRINT_KB.load(Ordering::SeqCst) * 1024 + 32 * item_bytes,
+        };
+
+        let first = UntrustedAllocation::new(64, DATA_ITEM_SIZE, META_ITEM_SIZE, &config);
+        assert_eq!(first.mem_count, 32);
+        assert!(first.file.is_some());
+
+        // The first allocation used up the budget.
+        let second = UntrustedAllocation::new(16, DATA_ITEM_SIZE, META_ITEM_SIZE, &config);
+        assert_eq!(second.mem_count, 0);
+
+        for alloc in [&first, &second] {
+            for index in 0..alloc.count {
+                // Meta items start out zeroed, in memory and on disk.
+                assert_eq!(read(alloc, index).1, vec![0; META_ITEM_SIZE / 8]);
+                write(alloc, index, index as u64 + 1);
+            }
+            for index in 0..alloc.count {
+                let value = index as u64 + 1;
+                assert_eq!(
+                    read(alloc, index),
+                    (
+                        vec![value; DATA_ITEM_SIZE / 8],
+                        vec![value; META_ITEM_SIZE / 8]
+                    )
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+use displaydoc::Display;
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_fog_recovery_db_iface::RecoveryDbError;
+use prost::{DecodeError, EncodeError};
+use rusqlite::{Error as SqliteError, ErrorCode};
+
+/// Enum for error types.
+#[derive(Display, Debug)]
+pub enum Error {
+    /// Sqlite: {0}
+    Sqlite(SqliteError),
+
+    /// The following ingress key was not found: {0:?}
+    MissingIngressKey(CompressedRistrettoPublic),
+
+    /// UserEvent schema violation on row #{0}: {1}
+    UserEventSchemaViolation(i64, &'static str),
+
+    /// IngressKeys schema violation: {0}
+    IngressKeysSchemaViolation(String),
+
+    /// New ingress key wasn't inserted successfully: {0}
+    IngressKeyUnsuccessfulInsert(String),
+
+    /// IngestedBlock schema violation: {0}
+    IngestedBlockSchemaViolation(String),
+
+    /**
+     * The data in the database could not be decoded as a
+     * VerificationReport: {0:?}
+     */
+    Decode(DecodeError),
+
+    /// The data could not be encoded for storage in the database: {0:?}
+    Encode(EncodeError),
+}
+
+impl Error {
+    /// Whether this error is the violation of a uniqueness constraint.
+    pub(crate) fn is_unique_violation(&self) -> bool {
+        matches!(
+            self,
+            Self::Sqlite(SqliteError::SqliteFailure(err, _))
+                if err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE
+                    || err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_PRIMARYKEY
+        )
+    }
+}
+
+impl RecoveryDbError for Error {
+    /// Policy decision, whether the call should be retried.
+    fn should_retry(&self) -> bool {
+        // Another process holds a lock on the database file for longer than
+        // the busy timeout.
+        matches!(
+            self,
+            Self::Sqlite(SqliteError::SqliteFailure(err, _))
+                if err.code == ErrorCode::DatabaseBusy || err.code == ErrorCode::DatabaseLocked
+        )
+    }
+}
+
+impl From<SqliteError> for Error {
+    fn from(src: SqliteError) -> Self {
+        Self::Sqlite(src)
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(src: DecodeError) -> Self {
+        Self::Decode(src)
+    }
+}
+
+impl From<EncodeError> for Error {
+    fn from(src: EncodeError) -> Self {
+        Self::Encode(src)
+    }
+}
@@ -0,0 +1,841 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+#![deny(missing_docs)]
+
+//! Recovery db implementation using an embedded SQLite database.
+//!
+//! This uses the same tables as the PostgreSQL implementation in
+//! `mc-fog-sql-recovery-db`, in a single file that can be shared by fog
+//! servers running on the same machine, so that no database server is needed.
+
+mod error;
+mod proto_types;
+
+use mc_attest_core::VerificationReport;
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_fog_kex_rng::KexRngPubkey;
+use mc_fog_recovery_db_iface::{
+    AddBlockDataStatus, FogUserEvent, IngestInvocationId, IngestableRange, IngressPublicKeyRecord,
+    IngressPublicKeyRecordFilters, IngressPublicKeyStatus, RecoveryDb, ReportData, ReportDb,
+};
+use mc_fog_types::{
+    common::BlockRange,
+    view::{DecommissionedIngestInvocation, RngRecord, TxOutSearchResult, TxOutSearchResultCode},
+    ETxOutRecord,
+};
+use mc_transaction_core::Block;
+use prost::Message;
+use proto_types::ProtoIngestedBlockData;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::{
+    cmp::max,
+    collections::HashMap,
+    convert::TryFrom,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+pub use error::Error;
+
+/// The database schema, created when opening a database if it doesn't exist.
+const SCHEMA: &str = include_str!("schema.sql");
+
+/// How long to wait for other processes sharing the database file to release
+/// their locks, before failing with a retriable error.
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Values of the `event_type` column of the `user_events` table.
+const NEW_INGEST_INVOCATION: &str = "new_ingest_invocation";
+const DECOMMISSION_INGEST_INVOCATION: &str = "decommission_ingest_invocation";
+const MISSING_BLOCKS: &str = "missing_blocks";
+
+/// The current time in seconds since the unix epoch, for the `last_active_at`
+/// column.
+const NOW: &str = "CAST(strftime('%s', 'now') AS INTEGER)";
+
+/// The last block scanned with the key of an `ingress_keys` row.
+const LAST_SCANNED_BLOCK: &str = "(SELECT MAX(block_number) FROM ingested_blocks WHERE ingress_keys.ingress_public_key = ingested_blocks.ingress_public_key)";
+
+fn key_bytes(key: &CompressedRistrettoPublic) -> &[u8] {
+    key.as_ref()
+}
+
+/// SQLite-backed recovery database. Clones share the same connection.
+///
+/// Transactions that write take the database lock when they begin, so that
+/// processes sharing the file wait for each other instead of failing to
+/// upgrade a read lock.
+#[derive(Clone)]
+pub struct SqliteRecoveryDb {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteRecoveryDb {
+    /// Open the database at the given path, creating it if it doesn't exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::new(Connection::open(path)?)
+    }
+
+    /// Create a new database that only lives in memory. It is only shared by
+    /// clones of this instance.
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(conn: Connection) -> Result<Self, Error> {
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // Write-ahead logging lets readers in other processes proceed while a
+        // write is in progress.
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_row| Ok(()))?;
+        conn.pragma_update(None, "foreign_keys", &true)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<Connection> {
+        self.conn.lock().expect("mutex poisoned")
+    }
+
+    /// Mark a given ingest invocation as decommissioned.
+    fn decommission_ingest_invocation_impl(
+        conn: &Connection,
+        ingest_invocation_id: &IngestInvocationId,
+    ) -> Result<(), Error> {
+        // Mark the ingest invocation as decommissioned.
+        conn.execute(
+            &format!(
+                "UPDATE ingest_invocations SET decommissioned = 1, last_active_at = {} WHERE id = ?1",
+                NOW
+            ),
+            params![**ingest_invocation_id],
+        )?;
+
+        // Write a user event.
+        conn.execute(
+            "INSERT INTO user_events (event_type, decommission_ingest_invocation_id) VALUES (?1, ?2)",
+            params![DECOMMISSION_INGEST_INVOCATION, **ingest_invocation_id],
+        )?;
+
+        Ok(())
+    }
+
+    /// Mark a given ingest invocation as still being alive.
+    fn update_last_active_at_impl(
+        conn: &Connection,
+        ingest_invocation_id: &IngestInvocationId,
+    ) -> Result<(), Error> {
+        conn.execute(
+            &format!(
+                "UPDATE ingest_invocations SET last_active_at = {} WHERE id = ?1",
+                NOW
+            ),
+            params![**ingest_invocation_id],
+        )?;
+        Ok(())
+    }
+
+    fn get_ingress_key_status_impl(
+        conn: &Connection,
+        key: &CompressedRistrettoPublic,
+    ) -> Result<Option<IngressPublicKeyStatus>, Error> {
+        Ok(conn
+            .query_row(
+                "SELECT start_block, pubkey_expiry, retired, lost FROM ingress_keys WHERE ingress_public_key = ?1",
+                params![key_bytes(key)],
+                |row| {
+                    Ok(IngressPublicKeyStatus {
+                        start_block: row.get::<_, i64>(0)? as u64,
+                        pubkey_expiry: row.get::<_, i64>(1)? as u64,
+                        retired: row.get(2)?,
+                        lost: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    fn get_last_scanned_block_index_impl(
+        conn: &Connection,
+        key: &CompressedRistrettoPublic,
+    ) -> Result<Option<u64>, Error> {
+        Ok(conn
+            .query_row(
+                "SELECT MAX(block_number) FROM ingested_blocks WHERE ingress_public_key = ?1",
+                params![key_bytes(key)],
+                |row| row.get::<_, Option<i64>>(0),
+            )?
+            .map(|val| val as u64))
+    }
+
+    fn get_highest_known_block_index_impl(conn: &Connection) -> Result<Option<u64>, Error> {
+        Ok(conn
+            .query_row("SELECT MAX(block_number) FROM ingested_blocks", [], |row| {
+                row.get::<_, Option<i64>>(0)
+            })?
+            .map(|val| val as u64))
+    }
+}
+
+/// See trait `fog_recovery_db_iface::RecoveryDb` for documentation.
+impl RecoveryDb for SqliteRecoveryDb {
+    type Error = Error;
+
+    fn get_ingress_key_status(
+        &self,
+        key: &CompressedRistrettoPublic,
+    ) -> Result<Option<IngressPublicKeyStatus>, Self::Error> {
+        Self::get_ingress_key_status_impl(&self.lock(), key)
+    }
+
+    fn new_ingress_key(
+        &self,
+        key: &CompressedRistrettoPublic,
+        start_block_count: u64,
+    ) -> Result<u64, Self::Error> {
+        let mut conn = self.lock();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let highest_known_block_count: u64 = Self::get_highest_known_block_index_impl(&tx)?
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        let accepted_start_block_count = max(start_block_count, highest_known_block_count);
+
+        let inserted_row_count = tx.execute(
+            "INSERT INTO ingress_keys (ingress_public_key, start_block) VALUES (?1, ?2) ON CONFLICT DO NOTHING",
+            params![key_bytes(key), accepted_start_block_count as i64],
+        )?;
+        if inserted_row_count == 0 {
+            return Err(Error::IngressKeyUnsuccessfulInsert(format!(
+                "Unable to insert ingress key: {:?}",
+                key
+            )));
+        }
+
+        tx.commit()?;
+        Ok(accepted_start_block_count)
+    }
+
+    fn retire_ingress_key(
+        &self,
+        key: &CompressedRistrettoPublic,
+        set_retired: bool,
+    ) -> Result<(), Self::Error> {
+        self.lock().execute(
+            "UPDATE ingress_keys SET retired = ?1 WHERE ingress_public_key = ?2",
+            params![set_retired, key_bytes(key)],
+        )?;
+        Ok(())
+    }
+
+    fn get_last_scanned_block_index(
+        &self,
+        key: &CompressedRistrettoPublic,
+    ) -> Result<Option<u64>, Self::Error> {
+        Self::get_last_scanned_block_index_impl(&self.lock(), key)
+    }
+
+    fn get_ingress_key_records(
+        &self,
+        start_block_at_least: u64,
+        ingress_public_key_record_filters: &IngressPublicKeyRecordFilters,
+    ) -> Result<Vec<IngressPublicKeyRecord>, Self::Error> {
+        let mut query = format!(
+            "SELECT ingress_public_key, start_block, pubkey_expiry, retired, lost, {} FROM ingress_keys WHERE start_block >= ?1",
+            LAST_SCANNED_BLOCK
+        );
+        if ingress_public_key_record_filters.should_only_include_unexpired_keys {
+            query.push_str(&format!(
+                " AND {0} IS NOT NULL AND pubkey_expiry > {0}",
+                LAST_SCANNED_BLOCK
+            ));
+        }
+        if !ingress_public_key_record_filters.should_include_lost_keys {
+            query.push_str(" AND lost = 0");
+        }
+        if !ingress_public_key_record_filters.should_include_retired_keys {
+            query.push_str(" AND retired = 0");
+        }
+
+        let conn = self.lock();
+        let mut stmt = conn.prepare(&query)?;
+        // The list of fields here must match the SELECT clause above.
+        let rows = stmt
+            .query_map(params![start_block_at_least as i64], |row| {
+                Ok((
+                    row.get::<_, Vec<u8>>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, bool>(3)?,
+                    row.get::<_, bool>(4)?,
+                    row.get::<_, Option<i64>>(5)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(
+                |(
+                    ingress_public_key,
+                    start_block,
+                    pubkey_expiry,
+                    retired,
+                    lost,
+                    last_scanned_block,
+                )| {
+                    let key = CompressedRistrettoPublic::try_from(&ingress_public_key[..])
+                        .map_err(|err| {
+                            Error::IngressKeysSchemaViolation(format!(
+                                "Invalid ingress key {:?}: {:?}",
+                                ingress_public_key, err
+                            ))
+                        })?;
+
+                    Ok(IngressPublicKeyRecord {
+                        key,
+                        status: IngressPublicKeyStatus {
+                            start_block: start_block as u64,
+                            pubkey_expiry: pubkey_expiry as u64,
+                            retired,
+                            lost,
+                        },
+                        last_scanned_block: last_scanned_block.map(|v| v as u64),
+                    })
+                },
+            )
+            .collect()
+    }
+
+    fn new_ingest_invocation(
+        &self,
+        prev_ingest_invocation_id: Option<IngestInvocationId>,
+        ingress_public_key: &CompressedRistrettoPublic,
+        egress_public_key: &KexRngPubkey,
+        start_block: u64,
+    ) -> Result<IngestInvocationId, Self::Error> {
+        let mut conn = self.lock();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        // Optionally decommission old invocation.
+        if let Some(prev_ingest_invocation_id) = prev_ingest_invocation_id {
+            Self::decommission_ingest_invocation_impl(&tx, &prev_ingest_invocation_id)?;
+        }
+
+        // Write new invocation.
+        tx.execute(
+            &format!(
+                "INSERT INTO ingest_invocations (ingress_public_key, egress_public_key, last_active_at, start_block, rng_version) VALUES (?1, ?2, {}, ?3, ?4)",
+                NOW
+            ),
+            params![
+                key_bytes(ingress_public_key),
+                egress_public_key.public_key,
+                start_block as i64,
+                egress_public_key.version as i64,
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+
+        // Write a user event.
+        tx.execute(
+            "INSERT INTO user_events (event_type, new_ingest_invocation_id) VALUES (?1, ?2)",
+            params![NEW_INGEST_INVOCATION, id],
+        )?;
+
+        tx.commit()?;
+        Ok(IngestInvocationId::from(id))
+    }
+
+    fn get_ingestable_ranges(&self) -> Result<Vec<IngestableRange>, Self::Error> {
+        let conn = self.lock();
+
+        // For each ingest invocation we are aware of get its id, start block, is
+        // decommissioned and the max block number it has ingested (if
+        // available).
+        let mut stmt = conn.prepare(
+            "SELECT id, start_block, decommissioned, (SELECT MAX(block_number) FROM ingested_blocks WHERE ingest_invocations.id = ingested_blocks.ingest_invocation_id) FROM ingest_invocations ORDER BY id",
+        )?;
+        let ranges = stmt
+            .query_map([], |row| {
+                Ok(IngestableRange {
+                    id: IngestInvocationId::from(row.get::<_, i64>(0)?),
+                    start_block: row.get::<_, i64>(1)? as u64,
+                    decommissioned: row.get(2)?,
+                    last_ingested_block: row.get::<_, Option<i64>>(3)?.map(|v| v as u64),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ranges)
+    }
+
+    fn decommission_ingest_invocation(
+        &self,
+        ingest_invocation_id: &IngestInvocationId,
+    ) -> Result<(), Self::Error> {
+        let mut conn = self.lock();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        Self::decommission_ingest_invocation_impl(&tx, ingest_invocation_id)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn add_block_data(
+        &self,
+        ingest_invocation_id: &IngestInvocationId,
+        block: &Block,
+        block_signature_timestamp: u64,
+        txs: &[ETxOutRecord],
+    ) -> Result<AddBlockDataStatus, Self::Error> {
+        let mut conn = self.lock();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        // Get ingress pubkey of this ingest invocation id, which is also stored in the
+        // ingested_block record
+        let ingress_key_bytes: Vec<u8> = tx.query_row(
+            "SELECT ingress_public_key FROM ingest_invocations WHERE id = ?1",
+            params![**ingest_invocation_id],
+            |row| row.get(0),
+        )?;
+
+        // Get bytes of encoded proto ingested block data
+        let proto_bytes = {
+            let proto_ingested_block_data = ProtoIngestedBlockData {
+                e_tx_out_records: txs.to_vec(),
+            };
+            let mut bytes = Vec::<u8>::with_capacity(proto_ingested_block_data.encoded_len());
+            proto_ingested_block_data.encode(&mut bytes)?;
+            bytes
+        };
+
+        // Add an IngestedBlock record.
+        let result = tx
+            .execute(
+                "INSERT INTO ingested_blocks (ingest_invocation_id, ingress_public_key, block_number, cumulative_txo_count, block_signature_timestamp, proto_ingested_block_data) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    **ingest_invocation_id,
+                    ingress_key_bytes,
+                    block.index as i64,
+                    block.cumulative_txo_count as i64,
+                    block_signature_timestamp as i64,
+                    proto_bytes,
+                ],
+            )
+            .map_err(Error::from);
+        match result {
+            Ok(_) => {}
+            // If a unique constraint is violated, we return Ok(block_already_scanned: true)
+            // instead of an error, like the PostgreSQL recovery db.
+            Err(err) if err.is_unique_violation() => {
+                return Ok(AddBlockDataStatus {
+                    block_already_scanned_with_this_key: true,
+                })
+            }
+            Err(err) => return Err(err),
+        }
+
+        // Update last active at.
+        Self::update_last_active_at_impl(&tx, ingest_invocation_id)?;
+
+        tx.commit()?;
+        Ok(AddBlockDataStatus {
+            block_already_scanned_with_this_key: false,
+        })
+    }
+
+    fn report_lost_ingress_key(
+        &self,
+        lost_ingress_key: CompressedRistrettoPublic,
+    ) -> Result<(), Self::Error> {
+        let mut conn = self.lock();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        // Find the ingress key and update it to be marked lost
+        tx.execute(
+            "UPDATE ingress_keys SET lost = 1 WHERE ingress_public_key = ?1",
+            params![key_bytes(&lost_ingress_key)],
+        )?;
+        let status = Self::get_ingress_key_status_impl(&tx, &lost_ingress_key)?
+            .ok_or(Error::MissingIngressKey(lost_ingress_key))?;
+
+        // Compute a missed block range based on looking at the key status, and
+        // shrink it if some blocks have actually been scanned using the key.
+        let mut missed_block_range = BlockRange::new(status.start_block, status.pubkey_expiry);
+        if let Some(block_index) = Self::get_last_scanned_block_index_impl(&tx, &lost_ingress_key)?
+        {
+            missed_block_range.start_block = max(missed_block_range.start_block, block_index + 1);
+        }
+
+        // If the missed block range is invalid (empty), we don't have to add it.
+        // This can happen if all the blocks were scanned, or if the ingress key was
+        // never actually published to the report server, and then pubkey_expiry is
+        // zero.
+        if missed_block_range.is_valid() {
+            tx.execute(
+                "INSERT INTO user_events (event_type, missing_blocks_start, missing_blocks_end) VALUES (?1, ?2, ?3)",
+                params![
+                    MISSING_BLOCKS,
+                    missed_block_range.start_block as i64,
+                    missed_block_range.end_block as i64,
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_missed_block_ranges(&self) -> Result<Vec<BlockRange>, Self::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(
+            "SELECT id, missing_blocks_start, missing_blocks_end FROM user_events WHERE event_type = ?1 ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(params![MISSING_BLOCKS], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|row| match row {
+                (_, Some(start_index), Some(end_index)) => {
+                    Ok(BlockRange::new(start_index as u64, end_index as u64))
+                }
+                (id, _, _) => Err(Error::UserEventSchemaViolation(
+                    id,
+                    "missing start or end block indices",
+                )),
+            })
+            .collect()
+    }
+
+    fn search_user_events(
+        &self,
+        start_from_user_event_id: i64,
+    ) -> Result<(Vec<FogUserEvent>, i64), Self::Error> {
+        let conn = self.lock();
+
+        // Collect all events of interest, with the ingest invocation information
+        // needed for NewRngRecord events, and the last block ingested by
+        // decommissioned ingest invocations.
+        // NOTE: sqlite autoincrement columns start from 1, so
+        // "start_from_user_event_id = 0" will capture everything
+        let mut stmt = conn.prepare(
+            "SELECT
+                user_events.id,
+                user_events.event_type,
+                ingest_invocations.id,
+                ingest_invocations.egress_public_key,
+                ingest_invocations.rng_version,
+                ingest_invocations.start_block,
+                user_events.decommission_ingest_invocation_id,
+                (SELECT COALESCE(MAX(block_number), 0) FROM ingested_blocks WHERE ingested_blocks.ingest_invocation_id = user_events.decommission_ingest_invocation_id),
+                user_events.missing_blocks_start,
+                user_events.missing_blocks_end
+            FROM user_events
+            LEFT JOIN ingest_invocations ON user_events.new_ingest_invocation_id = ingest_invocations.id
+            WHERE user_events.id > ?1
+            ORDER BY user_events.id",
+        )?;
+        // The list of fields here must match the SELECT clause above.
+        let rows = stmt
+            .query_map(params![start_from_user_event_id], |row| {
+                Ok((
+                    // For all event types
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    // For NewRngRecord events
+                    row.get::<_, Option<i64>>(2)?,
+                    row.get::<_, Option<Vec<u8>>>(3)?,
+                    row.get::<_, Option<i64>>(4)?,
+                    row.get::<_, Option<i64>>(5)?,
+                    // For DecommissionIngestInvocation events
+                    row.get::<_, Option<i64>>(6)?,
+                    row.get::<_, i64>(7)?,
+                    // For MissingBlocks events
+                    row.get::<_, Option<i64>>(8)?,
+                    row.get::<_, Option<i64>>(9)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // If no events are found, return start_from_user_event_id and not 0
+        let mut max_user_event_id = start_from_user_event_id;
+        let mut events = Vec::with_capacity(rows.len());
+        for row in rows {
+            let (
+                user_event_id,
+                user_event_type,
+                rng_record_ingest_invocation_id,
+                rng_record_egress_public_key,
+                rng_record_rng_version,
+                rng_record_start_block,
+                decommission_ingest_invocation_id,
+                decommission_ingest_invocation_max_block,
+                missing_blocks_start,
+                missing_blocks_end,
+            ) = row;
+            let missing = |field| Error::UserEventSchemaViolation(user_event_id, field);
+
+            max_user_event_id = max(max_user_event_id, user_event_id);
+
+            events.push(match user_event_type.as_str() {
+                NEW_INGEST_INVOCATION => FogUserEvent::NewRngRecord(RngRecord {
+                    ingest_invocation_id: rng_record_ingest_invocation_id
+                        .ok_or_else(|| missing("missing rng_record_ingest_invocation_id"))?,
+                    pubkey: KexRngPubkey {
+                        public_key: rng_record_egress_public_key
+                            .ok_or_else(|| missing("missing rng_record_egress_public_key"))?,
+                        version: rng_record_rng_version
+                            .ok_or_else(|| missing("missing rng_record_rng_version"))?
+                            as u32,
+                    },
+                    start_block: rng_record_start_block
+                        .ok_or_else(|| missing("missing rng_record_start_block"))?
+                        as u64,
+                }),
+                DECOMMISSION_INGEST_INVOCATION => {
+                    FogUserEvent::DecommissionIngestInvocation(DecommissionedIngestInvocation {
+                        ingest_invocation_id: decommission_ingest_invocation_id
+                            .ok_or_else(|| missing("missing decommission_ingest_invocation_id"))?,
+                        last_ingested_block: decommission_ingest_invocation_max_block as u64,
+                    })
+                }
+                MISSING_BLOCKS => FogUserEvent::MissingBlocks(BlockRange {
+                    start_block: missing_blocks_start
+                        .ok_or_else(|| missing("missing missing_blocks_start"))?
+                        as u64,
+                    end_block: missing_blocks_end
+                        .ok_or_else(|| missing("missing missing_blocks_end"))?
+                        as u64,
+                }),
+                _ => return Err(missing("unknown event_type")),
+            });
+        }
+
+        Ok((events, max_user_event_id))
+    }
+
+    /// Get any TxOutSearchResults corresponding to given search keys.
+    /// Nonzero start_block can be provided as an optimization opportunity.
+    ///
+    /// Note: This is VERY SLOW, as ETxOutRecords are not indexed by search
+    /// key. This should not be used except in tests.
+    fn get_tx_outs(
+        &self,
+        start_block: u64,
+        search_keys: &[Vec<u8>],
+    ) -> Result<Vec<TxOutSearchResult>, Self::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(
+            "SELECT proto_ingested_block_data FROM ingested_blocks WHERE block_number >= ?1",
+        )?;
+        let protos = stmt
+            .query_map(params![start_block as i64], |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut search_key_to_payload = HashMap::<Vec<u8>, Vec<u8>>::default();
+        for proto_bytes in protos {
+            let proto = ProtoIngestedBlockData::decode(&*proto_bytes)?;
+            for e_tx_out_record in proto.e_tx_out_records {
+                search_key_to_payload.insert(e_tx_out_record.search_key, e_tx_out_record.payload);
+            }
+        }
+
+        Ok(search_keys
+            .iter()
+            .map(|search_key| match search_key_to_payload.get(search_key) {
+                Some(payload) => TxOutSearchResult {
+                    search_key: search_key.clone(),
+                    result_code: TxOutSearchResultCode::Found as u32,
+                    ciphertext: payload.clone(),
+                },
+                None => TxOutSearchResult {
+                    search_key: search_key.clone(),
+                    result_code: TxOutSearchResultCode::NotFound as u32,
+                    ciphertext: Default::default(),
+                },
+            })
+            .collect())
+    }
+
+    fn update_last_active_at(
+        &self,
+        ingest_invocation_id: &IngestInvocationId,
+    ) -> Result<(), Self::Error> {
+        Self::update_last_active_at_impl(&self.lock(), ingest_invocation_id)
+    }
+
+    fn get_tx_outs_by_block_and_key(
+        &self,
+        ingress_key: CompressedRistrettoPublic,
+        block_index: u64,
+    ) -> Result<Option<Vec<ETxOutRecord>>, Self::Error> {
+        // There is at most one row, since there is a database constraint
+        // around ingress keys and block indices
+        let maybe_proto_bytes: Option<Vec<u8>> = self
+            .lock()
+            .query_row(
+                "SELECT proto_ingested_block_data FROM ingested_blocks WHERE ingress_public_key = ?1 AND block_number = ?2",
+                params![key_bytes(&ingress_key), block_index as i64],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        maybe_proto_bytes
+            .map(|proto_bytes| Ok(ProtoIngestedBlockData::decode(&*proto_bytes)?.e_tx_out_records))
+            .transpose()
+    }
+
+    fn get_invocation_id_by_block_and_key(
+        &self,
+        ingress_key: CompressedRistrettoPublic,
+        block_index: u64,
+    ) -> Result<Option<IngestInvocationId>, Self::Error> {
+        // There is at most one row, since there is a database constraint
+        // around ingress keys and block indices
+        Ok(self
+            .lock()
+            .query_row(
+                "SELECT ingest_invocation_id FROM ingested_blocks WHERE ingress_public_key = ?1 AND block_number = ?2",
+                params![key_bytes(&ingress_key), block_index as i64],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+            .map(IngestInvocationId::from))
+    }
+
+    fn get_cumulative_txo_count_for_block(
+        &self,
+        block_index: u64,
+    ) -> Result<Option<u64>, Self::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT cumulative_txo_count FROM ingested_blocks WHERE block_number = ?1",
+        )?;
+        let data = stmt
+            .query_map(params![block_index as i64], |row| row.get::<_, i64>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match data[..] {
+            [] => Ok(None),
+            [cumulative_txo_count] => Ok(Some(cumulative_txo_count as u64)),
+            _ => Err(Error::IngestedBlockSchemaViolation(format!(
+                "Found multiple cumulative_txo_count values for block {}: {:?}",
+                block_index, data
+            ))),
+        }
+    }
+
+    fn get_block_signature_timestamp_for_block(
+        &self,
+        block_index: u64,
+    ) -> Result<Option<u64>, Self::Error> {
+        Ok(self
+            .lock()
+            .query_row(
+                "SELECT block_signature_timestamp FROM ingested_blocks WHERE block_number = ?1 LIMIT 1",
+                params![block_index as i64],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+            .map(|val| val as u64))
+    }
+
+    fn get_highest_known_block_index(&self) -> Result<Option<u64>, Self::Error> {
+        Self::get_highest_known_block_index_impl(&self.lock())
+    }
+}
+
+/// See trait `fog_recovery_db_iface::ReportDb` for documentation.
+impl ReportDb for SqliteRecoveryDb {
+    type Error = Error;
+
+    fn get_all_reports(&self) -> Result<Vec<(String, ReportData)>, Self::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(
+            "SELECT ingest_invocation_id, fog_report_id, report, pubkey_expiry FROM reports ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, Option<i64>>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Vec<u8>>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(ingest_invocation_id, report_id, report, pubkey_expiry)| {
+                let report = VerificationReport::decode(&*report)?;
+                Ok((
+                    report_id,
+                    ReportData {
+                        ingest_invocation_id: ingest_invocation_id.map(IngestInvocationId::from),
+                        report,
+                        pubkey_expiry: pubkey_expiry as u64,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    fn set_report(
+        &self,
+        ingress_key: &CompressedRistrettoPublic,
+        report_id: &str,
+        data: &ReportData,
+    ) -> Result<IngressPublicKeyStatus, Self::Error> {
+        let mut conn = self.lock();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        // First, try to update the pubkey_expiry value on this ingress key, only
+        // allowing it to increase, and only if it is not retired
+        tx.execute(
+            "UPDATE ingress_keys SET pubkey_expiry = ?1 WHERE ingress_public_key = ?2 AND retired = 0 AND pubkey_expiry < ?1",
+            params![data.pubkey_expiry as i64, key_bytes(ingress_key)],
+        )?;
+        let status = Self::get_ingress_key_status_impl(&tx, ingress_key)?
+            .ok_or(Error::MissingIngressKey(*ingress_key))?;
+
+        // Cannot publish the key if it is retired.
+        if status.retired {
+            return Ok(status);
+        }
+
+        let mut report_bytes = Vec::with_capacity(data.report.encoded_len());
+        data.report.encode(&mut report_bytes)?;
+        tx.execute(
+            "INSERT INTO reports (ingress_public_key, ingest_invocation_id, fog_report_id, report, pubkey_expiry) VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT (fog_report_id) DO UPDATE SET
+                ingress_public_key = excluded.ingress_public_key,
+                ingest_invocation_id = excluded.ingest_invocation_id,
+                report = excluded.report,
+                pubkey_expiry = excluded.pubkey_expiry",
+            params![
+                key_bytes(ingress_key),
+                data.ingest_invocation_id.map(i64::from),
+                report_id,
+                report_bytes,
+                data.pubkey_expiry as i64,
+            ],
+        )?;
+
+        tx.commit()?;
+        Ok(status)
+    }
+
+    fn remove_report(&self, report_id: &str) -> Result<(), Self::Error> {
+        self.lock().execute(
+            "DELETE FROM reports WHERE fog_report_id = ?1",
+            params![report_id],
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+use mc_fog_types::ETxOutRecord;
+use prost::Message;
+
+/// Protobuf data stored with an ingested block record, in the same format as
+/// the PostgreSQL recovery db.
+#[derive(Message)]
+pub struct ProtoIngestedBlockData {
+    /// Any ETxOutRecord's that fog ingest emitted in connection to this block
+    #[prost(repeated, message, tag = 1)]
+    pub e_tx_out_records: Vec<ETxOutRecord>,
+}
@@ -0,0 +1,59 @@
+-- Copyright (c) 2018-2022 The MobileCoin Foundation
+
+-- The same tables as the PostgreSQL recovery db, see the migrations of the
+-- fog-sql-recovery-db crate for documentation of their columns.
+-- Booleans are stored as integers, and timestamps as seconds since the unix epoch.
+
+CREATE TABLE IF NOT EXISTS ingress_keys (
+    ingress_public_key BLOB PRIMARY KEY,
+    start_block INTEGER NOT NULL,
+    pubkey_expiry INTEGER NOT NULL DEFAULT 0,
+    retired INTEGER NOT NULL DEFAULT 0,
+    lost INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS ingest_invocations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ingress_public_key BLOB NOT NULL REFERENCES ingress_keys(ingress_public_key),
+    egress_public_key BLOB NOT NULL UNIQUE,
+    last_active_at INTEGER NOT NULL,
+    start_block INTEGER NOT NULL,
+    decommissioned INTEGER NOT NULL DEFAULT 0,
+    rng_version INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ingested_blocks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ingest_invocation_id INTEGER NOT NULL REFERENCES ingest_invocations(id),
+    ingress_public_key BLOB NOT NULL REFERENCES ingress_keys(ingress_public_key),
+    block_number INTEGER NOT NULL,
+    cumulative_txo_count INTEGER NOT NULL,
+    block_signature_timestamp INTEGER NOT NULL,
+    proto_ingested_block_data BLOB NOT NULL,
+    UNIQUE (ingest_invocation_id, block_number),
+    UNIQUE (ingress_public_key, block_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ingested_blocks__block_number ON ingested_blocks (block_number);
+
+CREATE TABLE IF NOT EXISTS user_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- One of 'new_ingest_invocation', 'decommission_ingest_invocation', 'missing_blocks'
+    event_type TEXT NOT NULL,
+    new_ingest_invocation_id INTEGER NULL UNIQUE REFERENCES ingest_invocations(id),
+    decommission_ingest_invocation_id INTEGER NULL UNIQUE REFERENCES ingest_invocations(id),
+    missing_blocks_start INTEGER NULL,
+    missing_blocks_end INTEGER NULL,
+    UNIQUE (missing_blocks_start, missing_blocks_end)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_events__event_type__id ON user_events (event_type, id);
+
+CREATE TABLE IF NOT EXISTS reports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ingress_public_key BLOB NOT NULL REFERENCES ingress_keys(ingress_public_key),
+    ingest_invocation_id INTEGER REFERENCES ingest_invocations(id),
+    fog_report_id TEXT NOT NULL UNIQUE,
+    report BLOB NOT NULL,
+    pubkey_expiry INTEGER NOT NULL
+);
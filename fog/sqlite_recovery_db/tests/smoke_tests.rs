@@ -0,0 +1,74 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPublic};
+use mc_fog_recovery_db_iface::RecoveryDb;
+use mc_fog_sqlite_recovery_db::SqliteRecoveryDb;
+use mc_fog_test_infra::db_tests::*;
+use mc_util_from_random::FromRandom;
+use tempdir::TempDir;
+
+#[test]
+fn sqlite_recovery_db_smoke_tests_new_apis() {
+    mc_util_test_helper::run_with_several_seeds(|mut rng| {
+        let db = SqliteRecoveryDb::open_in_memory().unwrap();
+
+        recovery_db_smoke_tests_new_apis(&mut rng, &db);
+    })
+}
+
+#[test]
+fn sqlite_recovery_db_missed_blocks_reporting() {
+    mc_util_test_helper::run_with_several_seeds(|mut rng| {
+        let db = SqliteRecoveryDb::open_in_memory().unwrap();
+
+        recovery_db_missed_blocks_reporting(&mut rng, &db);
+    })
+}
+
+#[test]
+fn sqlite_recovery_db_rng_records_decommissioning() {
+    mc_util_test_helper::run_with_several_seeds(|mut rng| {
+        let db = SqliteRecoveryDb::open_in_memory().unwrap();
+
+        recovery_db_rng_records_decommissioning(&mut rng, &db);
+    })
+}
+
+#[test]
+fn sqlite_recovery_db_ingress_keys() {
+    mc_util_test_helper::run_with_several_seeds(|mut rng| {
+        let db = SqliteRecoveryDb::open_in_memory().unwrap();
+
+        test_recovery_db_ingress_keys(&mut rng, &db);
+    })
+}
+
+// Data written by one instance is seen by another opening the same file.
+#[test]
+fn sqlite_recovery_db_shared_file() {
+    mc_util_test_helper::run_with_several_seeds(|mut rng| {
+        let dir = TempDir::new("sqlite_recovery_db").unwrap();
+        let path = dir.path().join("recovery.db");
+
+        let db1 = SqliteRecoveryDb::open(&path).unwrap();
+        let ingress_key = CompressedRistrettoPublic::from(RistrettoPublic::from_random(&mut rng));
+        db1.new_ingress_key(&ingress_key, 0).unwrap();
+        let invoc_id = db1
+            .new_ingest_invocation(None, &ingress_key, &random_kex_rng_pubkey(&mut rng), 0)
+            .unwrap();
+        let (block, records) = random_block(&mut rng, 0, 10);
+        db1.add_block_data(&invoc_id, &block, 0, &records).unwrap();
+
+        let db2 = SqliteRecoveryDb::open(&path).unwrap();
+        assert_eq!(db2.get_highest_known_block_index().unwrap(), Some(0));
+        assert_eq!(
+            db2.get_tx_outs_by_block_and_key(ingress_key, 0).unwrap(),
+            Some(records)
+        );
+        assert!(
+            db2.add_block_data(&invoc_id, &block, 0, &[])
+                .unwrap()
+                .block_already_scanned_with_this_key
+        );
+    })
+}
@@ -0,0 +1,149 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Oblivious merging of the results fog stores return for the same request.
+//!
+//! A fog router asks every store about every key of a client's request, and
+//! at most one of them (or several holding the same block, if their block
+//! ranges overlap) finds it. Which store found a key must not be revealed by
+//! the router's response, so results are merged without branching on their
+//! contents.
+
+#![no_std]
+#![deny(missing_docs)]
+
+extern crate alloc;
+
+use aligned_cmov::subtle::{Choice, ConstantTimeEq};
+use alloc::vec::Vec;
+
+/// The result codes which tell how to merge results. A result with any other
+/// code holds an error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ResultCodes {
+    /// The code of a result for a key that the store found.
+    pub found: u32,
+    /// The code of a result for a key that the store did not find.
+    pub not_found: u32,
+}
+
+/// Merge the results of several stores to the same request.
+///
+/// Each store must have returned one result per key, in the same order, or
+/// `None` is returned. `merge` obliviously merges the result of a store into
+/// the result collated so far, usually by replacing it when `merge_choice`
+/// says so.
+pub fn collate_store_results<R, K: Eq + ?Sized>(
+    store_results: Vec<Vec<R>>,
+    key: impl Fn(&R) -> &K,
+    merge: impl Fn(&mut R, &R),
+) -> Option<Vec<R>> {
+    let mut store_results = store_results.into_iter();
+    let mut collated = store_results.next()?;
+
+    for results in store_results {
+        if results.len() != collated.len() {
+            return None;
+        }
+        for (collated_result, result) in collated.iter_mut().zip(results.iter()) {
+            if key(collated_result) != key(result) {
+                return None;
+            }
+            merge(collated_result, result);
+        }
+    }
+
+    Some(collated)
+}
+
+/// Whether a result with code `src_code` should obliviously replace one with
+/// code `dst_code`: if it found the key, or if the other one did not find it
+/// and it holds an error.
+///
+/// Merging the results of all stores this way yields the one of a store that
+/// found the key, or else an error reported by a store, or else "not found".
+pub fn merge_choice(dst_code: u32, src_code: u32, codes: ResultCodes) -> Choice {
+    let src_found = src_code.ct_eq(&codes.found);
+    let src_not_found = src_code.ct_eq(&codes.not_found);
+    let dst_not_found = dst_code.ct_eq(&codes.not_found);
+    src_found | (dst_not_found & !src_not_found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aligned_cmov::CMov;
+    use alloc::vec;
+
+    const CODES: ResultCodes = ResultCodes {
+        found: 1,
+        not_found: 2,
+    };
+    const ERROR: u32 = 3;
+
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    struct TestResult {
+        key: u8,
+        code: u32,
+        value: u64,
+    }
+
+    fn result(key: u8, code: u32, value: u64) -> TestResult {
+        TestResult { key, code, value }
+    }
+
+    fn collate(store_results: Vec<Vec<TestResult>>) -> Option<Vec<TestResult>> {
+        collate_store_results(
+            store_results,
+            |result| &result.key,
+            |dst, src| {
+                let choice = merge_choice(dst.code, src.code, CODES);
+                dst.code.cmov(choice, &src.code);
+                dst.value.cmov(choice, &src.value);
+            },
+        )
+    }
+
+    #[test]
+    fn test_collate_store_results() {
+        let store1 = vec![
+            result(1, CODES.found, 5),
+            result(2, CODES.not_found, 0),
+            result(3, CODES.not_found, 0),
+        ];
+        let store2 = vec![
+            result(1, CODES.not_found, 0),
+            result(2, CODES.found, 12),
+            result(3, CODES.not_found, 0),
+        ];
+
+        assert_eq!(
+            collate(vec![store1, store2]).unwrap(),
+            vec![
+                result(1, CODES.found, 5),
+                result(2, CODES.found, 12),
+                result(3, CODES.not_found, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collate_reports_errors_unless_found() {
+        let store1 = vec![result(1, CODES.not_found, 0), result(2, CODES.found, 12)];
+        let store2 = vec![result(1, ERROR, 7), result(2, ERROR, 7)];
+        let store3 = vec![result(1, CODES.not_found, 0), result(2, CODES.not_found, 0)];
+
+        assert_eq!(
+            collate(vec![store1, store2, store3]).unwrap(),
+            vec![result(1, ERROR, 7), result(2, CODES.found, 12)]
+        );
+    }
+
+    #[test]
+    fn test_collate_rejects_mismatched_responses() {
+        let store1 = vec![result(1, CODES.not_found, 0)];
+        let store2 = vec![result(2, CODES.not_found, 0)];
+        assert_eq!(collate(vec![store1.clone(), store2]), None);
+        assert_eq!(collate(vec![store1, vec![]]), None);
+        assert_eq!(collate(vec![]), None);
+    }
+}
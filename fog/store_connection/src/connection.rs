@@ -0,0 +1,161 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! An attested connection from a fog router to a store.
+
+use crate::{StoreApi, StoreAttestationError};
+use core::{
+    cmp::Ordering,
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+};
+use grpcio::{ChannelBuilder, ClientUnaryReceiver, Environment, Result as GrpcResult};
+use mc_attest_api::{attest::Message, attest_grpc::AttestedApiClient};
+use mc_attest_core::VerificationReport;
+use mc_attest_enclave_api::{EnclaveMessage, PeerSession};
+use mc_common::{
+    logger::{log, o, Logger},
+    ResponderId,
+};
+use mc_connection::{AttestedConnection, Connection};
+use mc_util_grpc::ConnectionUriGrpcioChannel;
+use mc_util_uri::ConnectionUri;
+use std::sync::Arc;
+
+/// A connection to a store, which ensures transparent attestation between the
+/// router's enclave and the store's.
+pub struct StoreConnection<S: StoreApi> {
+    /// The router's enclave.
+    enclave: S::Enclave,
+
+    /// The session with the store's enclave, once attested.
+    channel_id: Option<PeerSession>,
+
+    /// The store's responder id, which its enclave authenticates as.
+    remote_responder_id: ResponderId,
+
+    /// The store's URI.
+    uri: S::Uri,
+
+    /// The gRPC client used to attest with the store.
+    attested_api_client: AttestedApiClient,
+
+    /// The gRPC client used to query the store once attested.
+    store_api_client: S::Client,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl<S: StoreApi> StoreConnection<S> {
+    /// Construct a new connection to a store.
+    pub fn new(enclave: S::Enclave, uri: S::Uri, env: Arc<Environment>, logger: Logger) -> Self {
+        let remote_responder_id = uri.responder_id().unwrap_or_else(|_| {
+            panic!("Could not get responder id from uri {:?}", uri.to_string())
+        });
+
+        let logger = logger.new(o!("mc.store.addr" => uri.addr()));
+
+        let ch = ChannelBuilder::default_channel_builder(env)
+            .max_receive_message_len(std::i32::MAX)
+            .max_send_message_len(std::i32::MAX)
+            .connect_to_uri(&uri, &logger);
+
+        let attested_api_client = AttestedApiClient::new(ch.clone());
+        let store_api_client = S::new_client(ch);
+
+        Self {
+            enclave,
+            channel_id: None,
+            remote_responder_id,
+            uri,
+            attested_api_client,
+            store_api_client,
+            logger,
+        }
+    }
+
+    /// The session with the store's enclave, if attested.
+    pub fn channel_id(&self) -> Option<&PeerSession> {
+        self.channel_id.as_ref()
+    }
+
+    /// Send a query created by the router's enclave for this store, without
+    /// waiting for the response.
+    pub fn query_async(
+        &self,
+        query: EnclaveMessage<PeerSession>,
+    ) -> GrpcResult<ClientUnaryReceiver<S::Response>> {
+        S::query_async(&self.store_api_client, &Message::from(query))
+    }
+}
+
+impl<S: StoreApi> Display for StoreConnection<S> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.uri)
+    }
+}
+
+impl<S: StoreApi> Eq for StoreConnection<S> {}
+
+impl<S: StoreApi> Hash for StoreConnection<S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.uri.addr().hash(state);
+    }
+}
+
+impl<S: StoreApi> Ord for StoreConnection<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.uri.addr().cmp(&other.uri.addr())
+    }
+}
+
+impl<S: StoreApi> PartialEq for StoreConnection<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.uri.addr() == other.uri.addr()
+    }
+}
+
+impl<S: StoreApi> PartialOrd for StoreConnection<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.uri.addr().partial_cmp(&other.uri.addr())
+    }
+}
+
+impl<S: StoreApi> Connection for StoreConnection<S> {
+    type Uri = S::Uri;
+
+    fn uri(&self) -> Self::Uri {
+        self.uri.clone()
+    }
+}
+
+impl<S: StoreApi> AttestedConnection for StoreConnection<S> {
+    type Error = StoreAttestationError<S::EnclaveError>;
+
+    fn is_attested(&self) -> bool {
+        self.channel_id.is_some()
+    }
+
+    fn attest(&mut self) -> Result<VerificationReport, Self::Error> {
+        self.deattest();
+        let req = S::peer_init(&self.enclave, &self.remote_responder_id)
+            .map_err(StoreAttestationError::Enclave)?;
+        let res = self.attested_api_client.auth(&req.into())?;
+        let (peer_session, verification_report) =
+            S::peer_connect(&self.enclave, &self.remote_responder_id, res.into())
+                .map_err(StoreAttestationError::Enclave)?;
+
+        self.channel_id = Some(peer_session);
+
+        Ok(verification_report)
+    }
+
+    fn deattest(&mut self) {
+        if let Some(peer_session) = self.channel_id.take() {
+            log::trace!(self.logger, "Tearing down existing attested connection.");
+            if let Err(err) = S::peer_close(&self.enclave, &peer_session) {
+                log::warn!(self.logger, "Failed closing store session: {}", err);
+            }
+        }
+    }
+}
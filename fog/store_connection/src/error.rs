@@ -0,0 +1,23 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+use core::fmt::{Debug, Display};
+use displaydoc::Display;
+use grpcio::Error as GrpcError;
+use mc_connection::AttestationError;
+
+/// An error which can occur when attesting to a store
+#[derive(Debug, Display)]
+pub enum StoreAttestationError<E: Debug + Display> {
+    /// gRPC failure during attestation: {0}
+    Grpc(GrpcError),
+    /// Local enclave failure during attestation: {0}
+    Enclave(E),
+}
+
+impl<E: Debug + Display> From<GrpcError> for StoreAttestationError<E> {
+    fn from(src: GrpcError) -> Self {
+        StoreAttestationError::Grpc(src)
+    }
+}
+
+impl<E: Debug + Display + Send + Sync> AttestationError for StoreAttestationError<E> {}
@@ -0,0 +1,67 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Connections from a fog router to the stores it forwards client requests to.
+
+#![deny(missing_docs)]
+
+mod connection;
+mod error;
+mod pool;
+
+pub use connection::StoreConnection;
+pub use error::StoreAttestationError;
+pub use pool::{PooledStoreConnections, StoreConnectionPool};
+
+use core::fmt::{Debug, Display};
+use grpcio::{Channel, ClientUnaryReceiver, Result as GrpcResult};
+use mc_attest_api::attest::Message;
+use mc_attest_core::VerificationReport;
+use mc_attest_enclave_api::{PeerAuthRequest, PeerAuthResponse, PeerSession};
+use mc_common::ResponderId;
+use mc_util_uri::ConnectionUri;
+
+/// The parts of a router and its stores which differ between fog services:
+/// the router's enclave, and the gRPC API the stores are queried through.
+pub trait StoreApi: Send + Sync + 'static {
+    /// The router's enclave.
+    type Enclave: Clone + Send + Sync + 'static;
+
+    /// An error returned by the router's enclave.
+    type EnclaveError: Debug + Display + Send + Sync;
+
+    /// The URI of a store.
+    type Uri: ConnectionUri;
+
+    /// The gRPC client for a store's query API.
+    type Client: Send + Sync;
+
+    /// A store's response to a query.
+    type Response: Send + 'static;
+
+    /// Begin attesting with a store's enclave.
+    fn peer_init(
+        enclave: &Self::Enclave,
+        peer_id: &ResponderId,
+    ) -> Result<PeerAuthRequest, Self::EnclaveError>;
+
+    /// Complete attesting with a store's enclave.
+    fn peer_connect(
+        enclave: &Self::Enclave,
+        peer_id: &ResponderId,
+        msg: PeerAuthResponse,
+    ) -> Result<(PeerSession, VerificationReport), Self::EnclaveError>;
+
+    /// Close a session with a store's enclave.
+    fn peer_close(enclave: &Self::Enclave, session: &PeerSession)
+        -> Result<(), Self::EnclaveError>;
+
+    /// Construct a client for a store's query API.
+    fn new_client(channel: Channel) -> Self::Client;
+
+    /// Send a query created by the router's enclave to a store, without
+    /// waiting for the response.
+    fn query_async(
+        client: &Self::Client,
+        query: &Message,
+    ) -> GrpcResult<ClientUnaryReceiver<Self::Response>>;
+}
@@ -0,0 +1,320 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! A pool of connections through which a router forwards requests to all of
+//! its stores.
+
+use crate::{StoreApi, StoreAttestationError, StoreConnection};
+use core::ops::{Deref, DerefMut};
+use futures::{executor::block_on, future::try_join_all};
+use grpcio::{Environment, RpcStatus};
+use mc_attest_enclave_api::{EnclaveMessage, PeerSession};
+use mc_common::logger::Logger;
+use mc_connection::AttestedConnection;
+use mc_util_grpc::{rpc_internal_error, rpc_unavailable_error};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// A pool of sets of connections to the stores, with one connection to every
+/// store in each set.
+///
+/// Messages on a session have to reach the other end in the order they were
+/// encrypted in, so a request needs exclusive use of its connections until it
+/// is done. Requests check out a whole set, so that up to `size` of them can be
+/// forwarded at once, each with sessions of its own.
+pub struct StoreConnectionPool<S: StoreApi> {
+    /// The sets of connections which are not in use.
+    available: Mutex<Vec<Vec<StoreConnection<S>>>>,
+
+    /// Signalled when a set of connections is returned to the pool.
+    returned: Condvar,
+
+    /// Held while attesting. The router's enclave tracks pending attestations
+    /// by the store's responder id, so two connections to the same store
+    /// cannot attest at the same time.
+    attestation: Mutex<()>,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl<S: StoreApi> StoreConnectionPool<S> {
+    /// Construct a pool of `size` sets of connections to the given stores.
+    pub fn new(
+        enclave: S::Enclave,
+        uris: &[S::Uri],
+        size: usize,
+        env: Arc<Environment>,
+        logger: Logger,
+    ) -> Self {
+        assert!(size > 0, "store connection pool size must be positive");
+
+        let available = (0..size)
+            .map(|_| {
+                uris.iter()
+                    .map(|uri| {
+                        StoreConnection::new(
+                            enclave.clone(),
+                            uri.clone(),
+                            env.clone(),
+                            logger.clone(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            available: Mutex::new(available),
+            returned: Condvar::new(),
+            attestation: Mutex::new(()),
+            logger,
+        }
+    }
+
+    /// Forward a request to every store, and merge their responses.
+    ///
+    /// `create_queries` is given the sessions with the stores, and must return
+    /// one query per session, in the same order. `collate` is given the
+    /// stores' responses in that order.
+    ///
+    /// Once a message is lost or not decrypted, the sessions are out of step
+    /// with the stores, and we cannot tell which ones. So if anything fails
+    /// after attesting, the whole set of connections is deattested, and
+    /// attests again the next time it is used.
+    pub fn query<T>(
+        &self,
+        create_queries: impl FnOnce(
+            Vec<PeerSession>,
+        ) -> Result<Vec<EnclaveMessage<PeerSession>>, RpcStatus>,
+        collate: impl FnOnce(Vec<S::Response>) -> Result<T, RpcStatus>,
+    ) -> Result<T, RpcStatus> {
+        let mut connections = self.get();
+        self.attest(&mut connections).map_err(|(store, err)| {
+            rpc_unavailable_error("attest_store", format!("{}: {}", store, err), &self.logger)
+        })?;
+
+        let result = self.query_connections(&connections, create_queries, collate);
+        if result.is_err() {
+            for connection in connections.iter_mut() {
+                connection.deattest();
+            }
+        }
+        result
+    }
+
+    /// Check out a set of connections, waiting for one if all are in use. The
+    /// set is returned to the pool when dropped.
+    pub fn get(&self) -> PooledStoreConnections<S> {
+        let mut available = self.available.lock().expect("mutex poisoned");
+        loop {
+            if let Some(connections) = available.pop() {
+                return PooledStoreConnections {
+                    pool: self,
+                    connections,
+                };
+            }
+            available = self.returned.wait(available).expect("mutex poisoned");
+        }
+    }
+
+    /// Attest every connection in the set which is not attested yet.
+    fn attest(
+        &self,
+        connections: &mut [StoreConnection<S>],
+    ) -> Result<(), (String, StoreAttestationError<S::EnclaveError>)> {
+        if connections
+            .iter()
+            .all(|connection| connection.is_attested())
+        {
+            return Ok(());
+        }
+
+        let _attestation = self.attestation.lock().expect("mutex poisoned");
+        for connection in connections.iter_mut() {
+            if !connection.is_attested() {
+                connection
+                    .attest()
+                    .map_err(|err| (connection.to_string(), err))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send one query to each of a set of attested connections at once, and
+    /// collate the responses.
+    fn query_connections<T>(
+        &self,
+        connections: &[StoreConnection<S>],
+        create_queries: impl FnOnce(
+            Vec<PeerSession>,
+        ) -> Result<Vec<EnclaveMessage<PeerSession>>, RpcStatus>,
+        collate: impl FnOnce(Vec<S::Response>) -> Result<T, RpcStatus>,
+    ) -> Result<T, RpcStatus> {
+        let store_sessions = connections
+            .iter()
+            .map(|connection| {
+                connection
+                    .channel_id()
+                    .cloned()
+                    .expect("store connection was just attested")
+            })
+            .collect();
+
+        let store_queries = create_queries(store_sessions)?;
+        if store_queries.len() != connections.len() {
+            return Err(rpc_internal_error(
+                "create_store_queries",
+                format!(
+                    "{} queries for {} stores",
+                    store_queries.len(),
+                    connections.len()
+                ),
+                &self.logger,
+            ));
+        }
+
+        let receivers = connections
+            .iter()
+            .zip(store_queries)
+            .map(|(connection, query)| connection.query_async(query))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| rpc_unavailable_error("query_store", err, &self.logger))?;
+
+        let store_responses = block_on(try_join_all(receivers))
+            .map_err(|err| rpc_unavailable_error("query_store", err, &self.logger))?;
+
+        collate(store_responses)
+    }
+}
+
+/// A set of connections checked out of a `StoreConnectionPool`.
+pub struct PooledStoreConnections<'a, S: StoreApi> {
+    pool: &'a StoreConnectionPool<S>,
+    connections: Vec<StoreConnection<S>>,
+}
+
+impl<'a, S: StoreApi> Deref for PooledStoreConnections<'a, S> {
+    type Target = [StoreConnection<S>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.connections
+    }
+}
+
+impl<'a, S: StoreApi> DerefMut for PooledStoreConnections<'a, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.connections
+    }
+}
+
+impl<'a, S: StoreApi> Drop for PooledStoreConnections<'a, S> {
+    fn drop(&mut self) {
+        if let Ok(mut available) = self.pool.available.lock() {
+            available.push(core::mem::take(&mut self.connections));
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grpcio::{Channel, ClientUnaryReceiver, EnvBuilder, Result as GrpcResult};
+    use mc_attest_api::attest::Message;
+    use mc_attest_core::VerificationReport;
+    use mc_attest_enclave_api::{PeerAuthRequest, PeerAuthResponse};
+    use mc_common::{
+        logger::{test_with_logger, Logger},
+        ResponderId,
+    };
+    use mc_fog_uri::FogViewStoreUri;
+    use std::{str::FromStr, sync::mpsc, thread, time::Duration};
+
+    /// A store API whose enclave cannot attest, for exercising the pool
+    /// without any stores running.
+    struct TestStoreApi;
+
+    impl StoreApi for TestStoreApi {
+        type Enclave = ();
+        type EnclaveError = String;
+        type Uri = FogViewStoreUri;
+        type Client = Channel;
+        type Response = Message;
+
+        fn peer_init(_: &(), _: &ResponderId) -> Result<PeerAuthRequest, String> {
+            Err("no enclave".to_string())
+        }
+
+        fn peer_connect(
+            _: &(),
+            _: &ResponderId,
+            _: PeerAuthResponse,
+        ) -> Result<(PeerSession, VerificationReport), String> {
+            Err("no enclave".to_string())
+        }
+
+        fn peer_close(_: &(), _: &PeerSession) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn new_client(channel: Channel) -> Channel {
+            channel
+        }
+
+        fn query_async(_: &Channel, _: &Message) -> GrpcResult<ClientUnaryReceiver<Message>> {
+            unreachable!("connections never attest")
+        }
+    }
+
+    fn test_pool(size: usize, logger: Logger) -> StoreConnectionPool<TestStoreApi> {
+        let uris = [
+            "insecure-fog-view-store://127.0.0.1:1/",
+            "insecure-fog-view-store://127.0.0.1:2/",
+        ]
+        .iter()
+        .map(|uri| FogViewStoreUri::from_str(uri).unwrap())
+        .collect::<Vec<_>>();
+        let env = Arc::new(EnvBuilder::new().build());
+        StoreConnectionPool::new((), &uris, size, env, logger)
+    }
+
+    #[test_with_logger]
+    fn test_checked_out_connections_are_returned(logger: Logger) {
+        let pool = Arc::new(test_pool(2, logger));
+
+        let first = pool.get();
+        let second = pool.get();
+        assert_eq!(first.len(), 2);
+        assert_eq!(second.len(), 2);
+
+        // A third request waits until a set is returned.
+        let (sender, receiver) = mpsc::channel();
+        {
+            let pool = pool.clone();
+            thread::spawn(move || sender.send(pool.get().len()).unwrap());
+        }
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+
+        drop(first);
+        assert_eq!(receiver.recv().unwrap(), 2);
+        drop(second);
+        assert_eq!(pool.available.lock().unwrap().len(), 2);
+    }
+
+    #[test_with_logger]
+    fn test_query_fails_without_attestation(logger: Logger) {
+        let pool = test_pool(1, logger);
+
+        let result = pool.query(
+            |_| panic!("queries are not created before attesting"),
+            |_: Vec<Message>| Ok(()),
+        );
+        assert_eq!(
+            result.unwrap_err().code(),
+            grpcio::RpcStatusCode::UNAVAILABLE
+        );
+        assert!(pool
+            .get()
+            .iter()
+            .all(|connection| !connection.is_attested()));
+    }
+}
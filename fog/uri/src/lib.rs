@@ -18,6 +18,20 @@ impl UriScheme for FogViewScheme {
     const DEFAULT_INSECURE_PORT: u16 = 3225;
 }
 
+/// Fog View Store Uri Scheme
+#[derive(Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Clone)]
+pub struct FogViewStoreScheme {}
+
+impl UriScheme for FogViewStoreScheme {
+    /// The part before the '://' of a URL.
+    const SCHEME_SECURE: &'static str = "fog-view-store";
+    const SCHEME_INSECURE: &'static str = "insecure-fog-view-store";
+
+    /// Default port numbers
+    const DEFAULT_SECURE_PORT: u16 = 443;
+    const DEFAULT_INSECURE_PORT: u16 = 3225;
+}
+
 /// Fog Ledger Uri Scheme
 #[derive(Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Clone)]
 pub struct FogLedgerScheme {}
@@ -32,6 +46,20 @@ impl UriScheme for FogLedgerScheme {
     const DEFAULT_INSECURE_PORT: u16 = 3223;
 }
 
+/// Fog Key Image Store Uri Scheme
+#[derive(Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Clone)]
+pub struct KeyImageStoreScheme {}
+
+impl UriScheme for KeyImageStoreScheme {
+    /// The part before the '://' of a URL.
+    const SCHEME_SECURE: &'static str = "key-image-store";
+    const SCHEME_INSECURE: &'static str = "insecure-key-image-store";
+
+    /// Default port numbers
+    const DEFAULT_SECURE_PORT: u16 = 443;
+    const DEFAULT_INSECURE_PORT: u16 = 3223;
+}
+
 /// Fog Ingest Uri Scheme
 #[derive(Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Clone)]
 pub struct FogIngestScheme {}
@@ -62,9 +90,15 @@ impl UriScheme for IngestPeerScheme {
 /// Uri used when talking to fog-view service, with the right default ports and
 /// scheme.
 pub type FogViewUri = Uri<FogViewScheme>;
+/// Uri used by fog-view routers when talking to fog-view stores, with the
+/// right default ports and scheme.
+pub type FogViewStoreUri = Uri<FogViewStoreScheme>;
 /// Uri used when talking to fog-ledger service, with the right default ports
 /// and scheme.
 pub type FogLedgerUri = Uri<FogLedgerScheme>;
+/// Uri used by fog-ledger routers when talking to key image stores, with the
+/// right default ports and scheme.
+pub type KeyImageStoreUri = Uri<KeyImageStoreScheme>;
 /// Uri used when talking to fog-ingest service, with the right default ports
 /// and scheme.
 pub type FogIngestUri = Uri<FogIngestScheme>;
@@ -73,7 +107,7 @@ pub type IngestPeerUri = Uri<IngestPeerScheme>;
 
 #[cfg(test)]
 mod tests {
-    use super::{FogLedgerUri, FogViewUri};
+    use super::{FogLedgerUri, FogViewStoreUri, FogViewUri, KeyImageStoreUri};
     use crate::ConnectionUri;
     use core::str::FromStr;
     use mc_common::ResponderId;
@@ -258,4 +292,47 @@ mod tests {
             Some("lol.com".into())
         );
     }
+
+    #[test]
+    fn test_valid_fog_view_store_uris() {
+        let uri = FogViewStoreUri::from_str("fog-view-store://node1.test.mobilecoin.com/").unwrap();
+        assert_eq!(uri.addr(), "node1.test.mobilecoin.com:443");
+        assert_eq!(
+            uri.responder_id().unwrap(),
+            ResponderId::from_str("node1.test.mobilecoin.com:443").unwrap()
+        );
+        assert!(uri.use_tls());
+
+        let uri = FogViewStoreUri::from_str("insecure-fog-view-store://127.0.0.1/").unwrap();
+        assert_eq!(uri.addr(), "127.0.0.1:3225");
+        assert_eq!(
+            uri.responder_id().unwrap(),
+            ResponderId::from_str("127.0.0.1:3225").unwrap()
+        );
+        assert!(!uri.use_tls());
+
+        assert!(FogViewStoreUri::from_str("fog-view://127.0.0.1/").is_err());
+    }
+
+    #[test]
+    fn test_valid_key_image_store_uris() {
+        let uri =
+            KeyImageStoreUri::from_str("key-image-store://node1.test.mobilecoin.com/").unwrap();
+        assert_eq!(uri.addr(), "node1.test.mobilecoin.com:443");
+        assert_eq!(
+            uri.responder_id().unwrap(),
+            ResponderId::from_str("node1.test.mobilecoin.com:443").unwrap()
+        );
+        assert!(uri.use_tls());
+
+        let uri = KeyImageStoreUri::from_str("insecure-key-image-store://127.0.0.1/").unwrap();
+        assert_eq!(uri.addr(), "127.0.0.1:3223");
+        assert_eq!(
+            uri.responder_id().unwrap(),
+            ResponderId::from_str("127.0.0.1:3223").unwrap()
+        );
+        assert!(!uri.use_tls());
+
+        assert!(KeyImageStoreUri::from_str("fog-ledger://127.0.0.1/").is_err());
+    }
 }
@@ -14,12 +14,12 @@ use displaydoc::Display;
 use mc_attest_core::{Quote, Report, SgxError, TargetInfo, VerificationReport};
 use mc_attest_enclave_api::{
     ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage,
-    Error as AttestEnclaveError,
+    Error as AttestEnclaveError, PeerAuthRequest, PeerAuthResponse, PeerSession,
 };
 use mc_common::ResponderId;
 use mc_crypto_keys::X25519Public;
 use mc_fog_recovery_db_iface::FogUserEvent;
-use mc_fog_types::ETxOutRecord;
+use mc_fog_types::{common::BlockRange, view::TxOutSearchResult, ETxOutRecord};
 use mc_sgx_compat::sync::PoisonError;
 use mc_sgx_report_cache_api::ReportableEnclave;
 use mc_sgx_types::{sgx_enclave_id_t, sgx_status_t};
@@ -50,6 +50,47 @@ pub struct UntrustedQueryResponse {
     pub last_known_block_cumulative_txo_count: u64,
 }
 
+/// The blocks whose records a fog view store holds, as reported by its
+/// untrusted side.
+///
+/// The store's enclave attaches it to its responses, so that a router's
+/// enclave can check that the stores it heard from cover every block that the
+/// router claims to have processed.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StoreBlockCoverage {
+    /// The range of blocks the store is configured to hold the records of.
+    pub block_range: BlockRange,
+
+    /// The number of blocks, counted from the origin block, that the store
+    /// has processed. Only the records of blocks in `block_range` below this
+    /// count are held by the store.
+    pub highest_processed_block_count: u64,
+}
+
+impl StoreBlockCoverage {
+    /// The range of blocks whose records the store actually holds.
+    pub fn processed_blocks(&self) -> BlockRange {
+        let start_block = self.block_range.start_block;
+        let end_block = self
+            .block_range
+            .end_block
+            .min(self.highest_processed_block_count)
+            .max(start_block);
+        BlockRange::new(start_block, end_block)
+    }
+}
+
+/// The response of a fog view store to a router's query, as encrypted by the
+/// store's enclave for the router's enclave.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StoreQueryResults {
+    /// The blocks covered by the results.
+    pub block_coverage: StoreBlockCoverage,
+
+    /// The results for each of the search keys of the query, in order.
+    pub tx_out_search_results: Vec<TxOutSearchResult>,
+}
+
 /// Represents a serialized request for the view enclave to service
 #[derive(Serialize, Deserialize)]
 pub enum ViewEnclaveRequest {
@@ -83,6 +124,32 @@ pub enum ViewEnclaveRequest {
     Query(EnclaveMessage<ClientSession>, UntrustedQueryResponse),
     /// Request from untrusted to add encrypted tx out records to ORAM
     AddRecords(Vec<ETxOutRecord>),
+    /// Get the size of the store of records
+    GetStoreStats,
+
+    // Router and store specific
+    /// Begin a connection to a fog view store
+    PeerInit(ResponderId),
+    /// Accept a connection from a fog view router
+    PeerAccept(PeerAuthRequest),
+    /// Complete a connection to a fog view store
+    PeerConnect(ResponderId, PeerAuthResponse),
+    /// Close a connection with a fog view router or store
+    PeerClose(PeerSession),
+    /// An encrypted fog_types::view::QueryRequest, to be re-encrypted for each
+    /// of the given fog view stores
+    CreateStoreQueries(EnclaveMessage<ClientSession>, Vec<PeerSession>),
+    /// A fog_types::view::QueryRequest encrypted by a fog view router, and
+    /// the blocks this store holds the records of
+    /// Respond with StoreQueryResults encrypted for the router
+    StoreQuery(EnclaveMessage<PeerSession>, StoreBlockCoverage),
+    /// The encrypted responses of fog view stores to a client's query
+    /// Respond with fog_types::view::QueryResponse encrypted for the client
+    CollateStoreResponses(
+        ClientSession,
+        Vec<EnclaveMessage<PeerSession>>,
+        UntrustedQueryResponse,
+    ),
 }
 
 /// The parameters needed to initialize the view enclave
@@ -94,10 +161,26 @@ pub struct ViewEnclaveInitParams {
     pub eid: sgx_enclave_id_t,
     /// The responder id for this enclave to use for client connections.
     pub self_client_id: ResponderId,
-    /// The desired capacity of the store of records
+    /// The desired initial capacity of the store of records. The store grows
+    /// as needed.
     pub desired_capacity: u64,
 }
 
+/// The size of the view enclave's store of records
+///
+/// The store grows when it gets too full. While it does, the records of the
+/// previous, smaller oblivious map are moved to the current one.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StoreStats {
+    /// The number of entries in the current oblivious map. Besides records,
+    /// these include entries holding the search keys of its records.
+    pub len: u64,
+    /// The capacity of the current oblivious map
+    pub capacity: u64,
+    /// The number of records left to move to the current oblivious map
+    pub records_to_migrate: u64,
+}
+
 /// The API for the view enclave
 pub trait ViewEnclaveApi: ReportableEnclave {
     /// Perform one-time initialization upon enclave startup.
@@ -134,6 +217,61 @@ pub trait ViewEnclaveApi: ReportableEnclave {
     /// Add encrypted tx out records from the fog recovery db to the view
     /// enclave's ORAM
     fn add_records(&self, records: Vec<ETxOutRecord>) -> Result<()>;
+
+    /// Get the size of the view enclave's store of records
+    fn get_store_stats(&self) -> Result<StoreStats>;
+
+    // ROUTER AND STORE METHODS
+    //
+    // A fog view router serves clients by fanning their queries out to fog
+    // view stores, each holding the records of a range of blocks, and merging
+    // their responses. Routers and stores run this same enclave, and connect
+    // to each other as peers.
+
+    /// Initiate a connection with a fog view store (router side)
+    fn peer_init(&self, peer_id: &ResponderId) -> Result<PeerAuthRequest>;
+
+    /// Accept a connection from a fog view router (store side)
+    fn peer_accept(&self, req: PeerAuthRequest) -> Result<(PeerAuthResponse, PeerSession)>;
+
+    /// Handle the store's peer_accept response to form the connection (router
+    /// side)
+    fn peer_connect(
+        &self,
+        peer_id: &ResponderId,
+        msg: PeerAuthResponse,
+    ) -> Result<(PeerSession, VerificationReport)>;
+
+    /// Close a connection with a router or a store
+    fn peer_close(&self, session_id: &PeerSession) -> Result<()>;
+
+    /// Decrypt a user's QueryRequest, and encrypt it for each of the given
+    /// stores, in the same order (router side)
+    fn create_store_queries(
+        &self,
+        payload: EnclaveMessage<ClientSession>,
+        store_sessions: Vec<PeerSession>,
+    ) -> Result<Vec<EnclaveMessage<PeerSession>>>;
+
+    /// Service a QueryRequest encrypted by a router, looking up the requested
+    /// records among the ones held by this store (store side)
+    fn store_query(
+        &self,
+        payload: EnclaveMessage<PeerSession>,
+        block_coverage: StoreBlockCoverage,
+    ) -> Result<EnclaveMessage<PeerSession>>;
+
+    /// Obliviously merge the stores' responses to a user's query, and encrypt
+    /// the resulting QueryResponse for the user (router side)
+    ///
+    /// Fails unless the stores together hold the records of every block below
+    /// the highest processed block count of the untrusted query response.
+    fn collate_store_responses(
+        &self,
+        client_session: ClientSession,
+        store_responses: Vec<EnclaveMessage<PeerSession>>,
+        untrusted_query_response: UntrustedQueryResponse,
+    ) -> Result<Vec<u8>>;
 }
 
 /// Helper trait which reduces boiler-plate in untrusted side
@@ -187,6 +325,10 @@ pub enum Error {
     Poison,
     /// Enclave not initialized
     EnclaveNotInitialized,
+    /// Store responses do not match the query
+    StoreResponseMismatch,
+    /// Store responses do not cover block {0}
+    BlocksNotCovered(u64),
 }
 
 impl From<SgxError> for Error {
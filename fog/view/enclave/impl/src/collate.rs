@@ -0,0 +1,233 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Oblivious merging of the TxOutSearchResults returned by fog view stores.
+//!
+//! Every store is asked for every search key, and at most one of them (or
+//! several holding the same record, if their block ranges overlap) finds it.
+//! The merge itself is shared with the other fog routers. Every ciphertext is
+//! padded to the same length while it is merged, so that its length does not
+//! reveal which store it came from either.
+
+use crate::e_tx_out_store::MAX_CIPHERTEXT_LEN;
+use aligned_cmov::{subtle::ConditionallySelectable, CMov};
+use alloc::vec::Vec;
+use mc_fog_store_collate::{collate_store_results, merge_choice, ResultCodes};
+use mc_fog_types::view::{TxOutSearchResult, TxOutSearchResultCode};
+use mc_fog_view_enclave_api::{Error, StoreBlockCoverage};
+
+const TX_OUT_SEARCH_RESULT_CODES: ResultCodes = ResultCodes {
+    found: TxOutSearchResultCode::Found as u32,
+    not_found: TxOutSearchResultCode::NotFound as u32,
+};
+
+/// Check that the stores which responded to a query together hold the records
+/// of every block below `block_count`.
+///
+/// Returns `Error::BlocksNotCovered` with the first block that none of them
+/// holds otherwise.
+pub fn check_block_coverage(
+    block_coverages: &[StoreBlockCoverage],
+    block_count: u64,
+) -> Result<(), Error> {
+    let mut processed_blocks = block_coverages
+        .iter()
+        .map(StoreBlockCoverage::processed_blocks)
+        .collect::<Vec<_>>();
+    processed_blocks.sort_by_key(|block_range| block_range.start_block);
+
+    let mut next_block = 0;
+    for block_range in processed_blocks {
+        if block_range.start_block > next_block {
+            break;
+        }
+        next_block = next_block.max(block_range.end_block);
+    }
+
+    if next_block < block_count {
+        return Err(Error::BlocksNotCovered(next_block));
+    }
+    Ok(())
+}
+
+/// Merge the results of several stores to the same query.
+///
+/// Each store must have returned one result per search key, in the same
+/// order. The merged result for a search key is the one of a store that found
+/// it, or else an error reported by a store, or else "not found".
+pub fn collate_tx_out_search_results(
+    store_results: Vec<Vec<TxOutSearchResult>>,
+) -> Result<Vec<TxOutSearchResult>, Error> {
+    let store_results = store_results
+        .into_iter()
+        .map(|results| {
+            results
+                .into_iter()
+                .map(PaddedResult::new)
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let collated = collate_store_results(
+        store_results,
+        |padded| &padded.result.search_key,
+        PaddedResult::merge,
+    )
+    .ok_or(Error::StoreResponseMismatch)?;
+
+    Ok(collated
+        .into_iter()
+        .map(PaddedResult::into_result)
+        .collect())
+}
+
+/// A result whose ciphertext is padded to `MAX_CIPHERTEXT_LEN` while it is
+/// merged, so that the length of the merged result does not reveal which
+/// store it came from. The padding is stripped before the result is returned
+/// to the client.
+struct PaddedResult {
+    result: TxOutSearchResult,
+    ciphertext_len: u32,
+}
+
+impl PaddedResult {
+    fn new(mut result: TxOutSearchResult) -> Result<Self, Error> {
+        if result.ciphertext.len() > MAX_CIPHERTEXT_LEN {
+            return Err(Error::StoreResponseMismatch);
+        }
+        let ciphertext_len = result.ciphertext.len() as u32;
+        result.ciphertext.resize(MAX_CIPHERTEXT_LEN, 0u8);
+        Ok(Self {
+            result,
+            ciphertext_len,
+        })
+    }
+
+    /// Obliviously replace this result with `src` if `src` found the record,
+    /// or if this one did not find it and `src` holds an error.
+    fn merge(&mut self, src: &PaddedResult) {
+        let dst = &mut self.result;
+        let choice = merge_choice(
+            dst.result_code,
+            src.result.result_code,
+            TX_OUT_SEARCH_RESULT_CODES,
+        );
+
+        dst.result_code.cmov(choice, &src.result.result_code);
+        self.ciphertext_len.cmov(choice, &src.ciphertext_len);
+        for (byte, src_byte) in dst.ciphertext.iter_mut().zip(src.result.ciphertext.iter()) {
+            byte.conditional_assign(src_byte, choice);
+        }
+    }
+
+    /// Strip the padding off the ciphertext.
+    fn into_result(mut self) -> TxOutSearchResult {
+        self.result
+            .ciphertext
+            .truncate(self.ciphertext_len as usize);
+        self.result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use mc_fog_types::common::BlockRange;
+
+    fn result(key: u8, code: TxOutSearchResultCode, ciphertext: Vec<u8>) -> TxOutSearchResult {
+        TxOutSearchResult {
+            search_key: vec![key; 16],
+            result_code: code as u32,
+            ciphertext,
+        }
+    }
+
+    #[test]
+    fn test_collate_tx_out_search_results() {
+        let store1 = vec![
+            result(1, TxOutSearchResultCode::Found, vec![1u8; 232]),
+            result(2, TxOutSearchResultCode::NotFound, vec![0u8; 232]),
+            result(3, TxOutSearchResultCode::InternalError, vec![0u8; 230]),
+        ];
+        let store2 = vec![
+            result(1, TxOutSearchResultCode::NotFound, vec![0u8; 239]),
+            result(2, TxOutSearchResultCode::Found, vec![2u8; 200]),
+            result(3, TxOutSearchResultCode::NotFound, vec![0u8; 239]),
+        ];
+
+        // Each merged ciphertext has the length of the one it came from, without
+        // any padding.
+        let collated = collate_tx_out_search_results(vec![store1, store2.clone()]).unwrap();
+        assert_eq!(
+            collated,
+            vec![
+                result(1, TxOutSearchResultCode::Found, vec![1u8; 232]),
+                result(2, TxOutSearchResultCode::Found, vec![2u8; 200]),
+                result(3, TxOutSearchResultCode::InternalError, vec![0u8; 230]),
+            ]
+        );
+
+        assert!(matches!(
+            collate_tx_out_search_results(vec![store2, vec![]]),
+            Err(Error::StoreResponseMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_collate_rejects_oversized_ciphertexts() {
+        let store1 = vec![result(1, TxOutSearchResultCode::NotFound, vec![])];
+        let store2 = vec![result(
+            1,
+            TxOutSearchResultCode::Found,
+            vec![1u8; MAX_CIPHERTEXT_LEN + 1],
+        )];
+        assert!(matches!(
+            collate_tx_out_search_results(vec![store1, store2]),
+            Err(Error::StoreResponseMismatch)
+        ));
+    }
+
+    fn coverage(
+        start_block: u64,
+        end_block: u64,
+        highest_processed_block_count: u64,
+    ) -> StoreBlockCoverage {
+        StoreBlockCoverage {
+            block_range: BlockRange::new(start_block, end_block),
+            highest_processed_block_count,
+        }
+    }
+
+    #[test]
+    fn test_check_block_coverage() {
+        let coverages = vec![coverage(10, 20, 15), coverage(0, 10, 10)];
+        assert!(check_block_coverage(&coverages, 0).is_ok());
+        assert!(check_block_coverage(&coverages, 15).is_ok());
+
+        // The second store has not processed blocks 15 and up yet.
+        assert!(matches!(
+            check_block_coverage(&coverages, 16),
+            Err(Error::BlocksNotCovered(15))
+        ));
+
+        // Overlapping ranges are fine, gaps are not.
+        let coverages = vec![coverage(0, 10, 10), coverage(5, 30, 30)];
+        assert!(check_block_coverage(&coverages, 30).is_ok());
+        let coverages = vec![coverage(0, 10, 10), coverage(11, 30, 30)];
+        assert!(matches!(
+            check_block_coverage(&coverages, 30),
+            Err(Error::BlocksNotCovered(10))
+        ));
+
+        // A store which has not processed any of its blocks covers none of them.
+        let coverages = vec![coverage(0, 10, 10), coverage(10, 20, 3)];
+        assert!(matches!(
+            check_block_coverage(&coverages, 11),
+            Err(Error::BlocksNotCovered(10))
+        ));
+        assert!(matches!(
+            check_block_coverage(&[], 1),
+            Err(Error::BlocksNotCovered(0))
+        ));
+    }
+}
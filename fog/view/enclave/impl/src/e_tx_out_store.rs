@@ -3,8 +3,13 @@
 //! Object representing trusted storage for tx out records.
 //! Mediates between the bytes used in ORAM and the protobuf format,
 //! the various ORAM vs. fog api error codes, etc.
+//!
+//! The store grows when its oblivious map gets too full: it creates a map
+//! twice as large, which new records go to, and moves the records of the old
+//! map to it a few at a time, each time records are added. Until all of them
+//! have moved, lookups read both maps. The old map is dropped once it is empty.
 
-use alloc::vec;
+use alloc::{vec, vec::Vec};
 
 use aligned_cmov::{
     subtle::{Choice, ConstantTimeEq},
@@ -13,9 +18,9 @@ use aligned_cmov::{
 };
 use alloc::boxed::Box;
 use mc_common::logger::Logger;
-use mc_crypto_rand::McRng;
+use mc_crypto_rand::{McRng, RngCore};
 use mc_fog_types::view::{TxOutSearchResult, TxOutSearchResultCode};
-use mc_fog_view_enclave_api::AddRecordsError;
+use mc_fog_view_enclave_api::{AddRecordsError, StoreStats};
 use mc_oblivious_map::CuckooHashTableCreator;
 use mc_oblivious_ram::PathORAM4096Z4Creator;
 use mc_oblivious_traits::{
@@ -28,6 +33,9 @@ use mc_oblivious_traits::{
 // We must choose an oblivious map algorithm that can support that
 type KeySize = U16;
 type ValueSize = U240;
+/// The longest ciphertext a store can hold, the first byte of a value being
+/// the amount of padding after the ciphertext
+pub const MAX_CIPHERTEXT_LEN: usize = ValueSize::USIZE - 1;
 // BlockSize is a tuning parameter for OMap which must become the ValueSize of
 // the selected ORAM
 type BlockSize = U1024;
@@ -46,6 +54,20 @@ const STASH_SIZE: usize = 32;
 
 // This selects the oblivious map algorithm
 type ObliviousMapCreator<OSC> = CuckooHashTableCreator<BlockSize, McRng, ObliviousRAMAlgo<OSC>>;
+type ObliviousMap<OSC> =
+    <ObliviousMapCreator<OSC> as OMapCreator<KeySize, ValueSize, McRng>>::Output;
+
+/// The load factor, in percent, above which the store starts growing
+pub const GROWTH_LOAD_FACTOR_PERCENT: u64 = 75;
+
+/// Number of records moved to the new map each time a record is added. Adding
+/// a record loads the new map by a little more than one entry, so this is
+/// enough for a migration to finish before the new map is full enough to grow
+/// again.
+pub const RECORDS_MIGRATED_PER_ADD: usize = 2;
+
+/// Number of search keys held by an entry of the key log
+const KEYS_PER_LOG_ENTRY: usize = ValueSize::USIZE / KeySize::USIZE;
 
 /// Object which holds ORAM and services TxOutRecord requests
 ///
@@ -59,9 +81,36 @@ type ObliviousMapCreator<OSC> = CuckooHashTableCreator<BlockSize, McRng, Oblivio
 /// - When the lookup misses, we try to obliviously return a buffer of the
 ///   normal size. We do this by remembering the ciphertext size byte of the
 ///   last stored ciphertext.
+///
+/// The search keys of the records in a map are needed to move them to a larger
+/// map, since the oblivious map cannot be iterated. Rather than keeping them
+/// on the enclave heap, they are logged in the map itself, KEYS_PER_LOG_ENTRY
+/// to an entry. The key of a log entry is derived from its index and a random
+/// salt, so that users cannot look it up. Untrusted already knows the search
+/// keys, as it supplies the records.
 pub struct ETxOutStore<OSC: ORAMStorageCreator<StorageDataSize, StorageMetaSize>> {
     /// Oblivious map to hold ETxOutRecords
-    omap: Box<<ObliviousMapCreator<OSC> as OMapCreator<KeySize, ValueSize, McRng>>::Output>,
+    omap: Box<ObliviousMap<OSC>>,
+
+    /// The smaller map records are being moved out of, while growing
+    migration: Option<Migration<OSC>>,
+
+    /// The salt the keys of key log entries are derived from
+    log_salt: A8Bytes<KeySize>,
+
+    /// Index of the first key log entry in the current map
+    log_start: u64,
+
+    /// Index of the next key log entry to write
+    log_end: u64,
+
+    /// Number of search keys logged in the current map, which is the number of
+    /// records in it
+    num_logged_keys: u64,
+
+    /// Search keys of records in the current map, waiting for a full key log
+    /// entry
+    pending_keys: Vec<A8Bytes<KeySize>>,
 
     /// The size byte from the payload for the last ciphertext we stored in omap
     last_ciphertext_size_byte: u8,
@@ -71,21 +120,55 @@ pub struct ETxOutStore<OSC: ORAMStorageCreator<StorageDataSize, StorageMetaSize>
     logger: Logger,
 }
 
+/// The records of a smaller map, left to move to the current one
+struct Migration<OSC: ORAMStorageCreator<StorageDataSize, StorageMetaSize>> {
+    /// The smaller map
+    omap: Box<ObliviousMap<OSC>>,
+
+    /// Index of the next key log entry of the smaller map to read
+    next: u64,
+
+    /// Index of the first key log entry after those of the smaller map
+    end: u64,
+
+    /// Search keys read from the smaller map's key log, of records left to move
+    keys: Vec<A8Bytes<KeySize>>,
+
+    /// Number of records left to move
+    records_left: u64,
+}
+
 impl<OSC: ORAMStorageCreator<StorageDataSize, StorageMetaSize>> ETxOutStore<OSC> {
     pub fn new(desired_capacity: u64, logger: Logger) -> Self {
+        let mut log_salt = A8Bytes::<KeySize>::default();
+        McRng::default().fill_bytes(&mut log_salt);
+
         Self {
-            omap: Box::new(<ObliviousMapCreator<OSC> as OMapCreator<
-                KeySize,
-                ValueSize,
-                McRng,
-            >>::create(
-                desired_capacity, STASH_SIZE, McRng::default
-            )),
+            omap: create_omap::<OSC>(desired_capacity),
+            migration: None,
+            log_salt,
+            log_start: 0,
+            log_end: 0,
+            num_logged_keys: 0,
+            pending_keys: Vec::with_capacity(KEYS_PER_LOG_ENTRY),
             last_ciphertext_size_byte: 0,
             logger,
         }
     }
 
+    /// The size of the store
+    pub fn stats(&self) -> StoreStats {
+        StoreStats {
+            len: self.omap.len(),
+            capacity: self.omap.capacity(),
+            records_to_migrate: self
+                .migration
+                .as_ref()
+                .map(|migration| migration.records_left)
+                .unwrap_or(0),
+        }
+    }
+
     pub fn add_record(
         &mut self,
         search_key: &[u8],
@@ -110,30 +193,125 @@ impl<OSC: ORAMStorageCreator<StorageDataSize, StorageMetaSize>> ETxOutStore<OSC>
         (&mut value[1..data_end]).clone_from_slice(ciphertext);
         self.last_ciphertext_size_byte = value[0];
 
+        // Records left to move count towards the load of the current map
+        let stats = self.stats();
+        if (stats.len + stats.records_to_migrate + 1) * 100
+            > stats.capacity * GROWTH_LOAD_FACTOR_PERCENT
+        {
+            self.grow()?;
+        }
+
         // Note: Passing true means we allow overwrite, which seems fine since
         // the value is not changing
-        let omap_result_code = self.omap.vartime_write(&key, &value, Choice::from(1));
-        if omap_result_code == OMAP_INVALID_KEY {
-            return Err(AddRecordsError::KeyRejected);
-        } else if omap_result_code == OMAP_OVERFLOW {
-            return Err(AddRecordsError::MapOverflow(
-                self.omap.len(),
-                self.omap.capacity(),
-            ));
-        } else if omap_result_code == OMAP_FOUND {
-            // log::debug!(
-            //    self.logger,
-            //    "An omap key was added twice, overwriting previous value"
-            // );
-        } else if omap_result_code != OMAP_NOT_FOUND {
-            panic!(
+        if write_to_omap(&mut *self.omap, &key, &value, Choice::from(1))? {
+            self.log_key(key)?;
+        }
+
+        self.migrate(RECORDS_MIGRATED_PER_ADD)
+    }
+
+    /// Move up to `count` records from the smaller map to the current one, and
+    /// drop the smaller map once it is done.
+    ///
+    /// Every record is read from the smaller map and written to the current
+    /// one, so which records were found is not revealed.
+    fn migrate(&mut self, count: usize) -> Result<(), AddRecordsError> {
+        let mut moved = 0;
+        while let Some(migration) = self.migration.as_mut() {
+            if migration.keys.is_empty() {
+                if migration.next == migration.end {
+                    self.migration = None;
+                    break;
+                }
+                migration.keys =
+                    read_key_log_entry(&mut *migration.omap, &self.log_salt, migration.next);
+                migration.next += 1;
+                continue;
+            }
+            if moved == count {
+                break;
+            }
+
+            let key = migration.keys.pop().expect("keys is not empty");
+            let mut value = A8Bytes::<ValueSize>::default();
+            let omap_result_code = migration.omap.read(&key, &mut value);
+            debug_assert!(
+                omap_result_code == OMAP_FOUND,
                 "omap_result_code had an unexpected value: {}",
                 omap_result_code
             );
+            migration.records_left = migration.records_left.saturating_sub(1);
+
+            // A record added again since growing started is already there, and
+            // is neither overwritten nor logged again.
+            if write_to_omap(&mut *self.omap, &key, &value, Choice::from(0))? {
+                self.log_key(key)?;
+            }
+            moved += 1;
+        }
+        Ok(())
+    }
+
+    /// Replace the map with one twice as large, and start moving records to it.
+    fn grow(&mut self) -> Result<(), AddRecordsError> {
+        // Adding records moves RECORDS_MIGRATED_PER_ADD records each, which
+        // finishes the previous migration long before the current map is full
+        // enough to grow. Finishing it here is only a safeguard.
+        debug_assert!(
+            self.migration.is_none(),
+            "growing before the previous migration is done"
+        );
+        self.migrate(usize::MAX)?;
+
+        // The whole key log of the current map is needed to move its records.
+        self.flush_key_log()?;
+
+        let new_capacity = 2 * self.omap.capacity();
+        let old_omap = core::mem::replace(&mut self.omap, create_omap::<OSC>(new_capacity));
+        self.migration = Some(Migration {
+            omap: old_omap,
+            next: self.log_start,
+            end: self.log_end,
+            keys: Vec::new(),
+            records_left: self.num_logged_keys,
+        });
+        self.log_start = self.log_end;
+        self.num_logged_keys = 0;
+        Ok(())
+    }
+
+    /// Log the search key of a record just added to the current map
+    fn log_key(&mut self, key: A8Bytes<KeySize>) -> Result<(), AddRecordsError> {
+        self.pending_keys.push(key);
+        self.num_logged_keys += 1;
+        if self.pending_keys.len() == KEYS_PER_LOG_ENTRY {
+            self.flush_key_log()?;
         }
         Ok(())
     }
 
+    /// Write the pending search keys to a new key log entry of the current
+    /// map. Unused slots are left zeroed, which no search key is.
+    fn flush_key_log(&mut self) -> Result<(), AddRecordsError> {
+        if self.pending_keys.is_empty() {
+            return Ok(());
+        }
+
+        let mut value = A8Bytes::<ValueSize>::default();
+        for (slot, key) in value
+            .chunks_mut(KeySize::USIZE)
+            .zip(self.pending_keys.iter())
+        {
+            slot.copy_from_slice(key);
+        }
+        let log_key = key_log_entry_key(&self.log_salt, self.log_end);
+        write_to_omap(&mut *self.omap, &log_key, &value, Choice::from(1))?;
+
+        self.log_end += 1;
+        self.pending_keys.clear();
+        Ok(())
+    }
+
     pub fn find_record(&mut self, search_key: &[u8]) -> TxOutSearchResult {
         let mut result = TxOutSearchResult {
             search_key: search_key.to_vec(),
@@ -159,7 +337,20 @@ impl<OSC: ORAMStorageCreator<StorageDataSize, StorageMetaSize>> ETxOutStore<OSC>
         // OMAP_INVALID_KEY -> TxResultCode::BadSearchKey
         // Other -> TxResultCode::InternalError, debug_assert!(false)
         {
-            let oram_result_code = self.omap.read(&key, &mut value);
+            let mut oram_result_code = self.omap.read(&key, &mut value);
+
+            // While growing, a record may not have moved to the current map yet
+            if let Some(migration) = self.migration.as_mut() {
+                let mut old_value = A8Bytes::<ValueSize>::default();
+                old_value[0] = self.last_ciphertext_size_byte;
+                let old_result_code = migration.omap.read(&key, &mut old_value);
+
+                let use_old =
+                    !oram_result_code.ct_eq(&OMAP_FOUND) & old_result_code.ct_eq(&OMAP_FOUND);
+                value.cmov(use_old, &old_value);
+                oram_result_code.cmov(use_old, &old_result_code);
+            }
+
             result.result_code.cmov(
                 oram_result_code.ct_eq(&OMAP_FOUND),
                 &(TxOutSearchResultCode::Found as u32),
@@ -192,3 +383,184 @@ impl<OSC: ORAMStorageCreator<StorageDataSize, StorageMetaSize>> ETxOutStore<OSC>
         result
     }
 }
+
+/// Create an oblivious map of at least the given capacity
+fn create_omap<OSC: ORAMStorageCreator<StorageDataSize, StorageMetaSize>>(
+    desired_capacity: u64,
+) -> Box<ObliviousMap<OSC>> {
+    Box::new(<ObliviousMapCreator<OSC> as OMapCreator<
+        KeySize,
+        ValueSize,
+        McRng,
+    >>::create(desired_capacity, STASH_SIZE, McRng::default))
+}
+
+/// Write a record to an oblivious map, mapping its result code to an error.
+/// Returns whether the key is new to the map.
+fn write_to_omap<M: ObliviousHashMap<KeySize, ValueSize>>(
+    omap: &mut M,
+    key: &A8Bytes<KeySize>,
+    value: &A8Bytes<ValueSize>,
+    allow_overwrite: Choice,
+) -> Result<bool, AddRecordsError> {
+    let omap_result_code = omap.vartime_write(key, value, allow_overwrite);
+    if omap_result_code == OMAP_INVALID_KEY {
+        return Err(AddRecordsError::KeyRejected);
+    } else if omap_result_code == OMAP_OVERFLOW {
+        return Err(AddRecordsError::MapOverflow(omap.len(), omap.capacity()));
+    } else if omap_result_code == OMAP_FOUND {
+        // log::debug!(
+        //    self.logger,
+        //    "An omap key was added twice, overwriting previous value"
+        // );
+    } else if omap_result_code != OMAP_NOT_FOUND {
+        panic!(
+            "omap_result_code had an unexpected value: {}",
+            omap_result_code
+        );
+    }
+    Ok(omap_result_code == OMAP_NOT_FOUND)
+}
+
+/// The key of the key log entry at an index
+fn key_log_entry_key(log_salt: &A8Bytes<KeySize>, index: u64) -> A8Bytes<KeySize> {
+    let mut key = log_salt.clone();
+    for (byte, index_byte) in key.iter_mut().zip(index.to_le_bytes().iter()) {
+        *byte ^= index_byte;
+    }
+    key
+}
+
+/// Read the search keys in the key log entry of an oblivious map at an index
+fn read_key_log_entry<M: ObliviousHashMap<KeySize, ValueSize>>(
+    omap: &mut M,
+    log_salt: &A8Bytes<KeySize>,
+    index: u64,
+) -> Vec<A8Bytes<KeySize>> {
+    let mut value = A8Bytes::<ValueSize>::default();
+    let omap_result_code = omap.read(&key_log_entry_key(log_salt, index), &mut value);
+    debug_assert!(
+        omap_result_code == OMAP_FOUND,
+        "omap_result_code had an unexpected value: {}",
+        omap_result_code
+    );
+
+    value
+        .chunks(KeySize::USIZE)
+        .filter(|slot| slot.iter().any(|byte| *byte != 0))
+        .map(|slot| {
+            let mut key = A8Bytes::<KeySize>::default();
+            key.copy_from_slice(slot);
+            key
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_oblivious_traits::HeapORAMStorageCreator;
+
+    fn search_key(i: u64) -> Vec<u8> {
+        let mut key = vec![1u8; KeySize::USIZE];
+        key[..8].copy_from_slice(&i.to_le_bytes());
+        key
+    }
+
+    fn ciphertext(i: u64) -> Vec<u8> {
+        vec![i as u8; 232]
+    }
+
+    fn assert_found(store: &mut ETxOutStore<HeapORAMStorageCreator>, count: u64) {
+        for i in 0..count {
+            let result = store.find_record(&search_key(i));
+            assert_eq!(result.result_code, TxOutSearchResultCode::Found as u32);
+            assert_eq!(result.ciphertext, ciphertext(i));
+        }
+        let result = store.find_record(&search_key(count));
+        assert_eq!(result.result_code, TxOutSearchResultCode::NotFound as u32);
+    }
+
+    /// The number of entries of a map holding `count` records, all of which
+    /// were logged while it was the current map
+    fn num_entries(count: u64) -> u64 {
+        count + count / KEYS_PER_LOG_ENTRY as u64
+    }
+
+    #[test_with_logger]
+    fn test_grows_when_full(logger: Logger) {
+        let mut store = ETxOutStore::<HeapORAMStorageCreator>::new(1024, logger);
+        let initial_capacity = store.stats().capacity;
+
+        // Fill the store past its growth threshold.
+        let count = initial_capacity;
+        for i in 0..count {
+            store.add_record(&search_key(i), &ciphertext(i)).unwrap();
+        }
+        let stats = store.stats();
+        assert_eq!(stats.capacity, 2 * initial_capacity);
+        assert!(stats.records_to_migrate > 0);
+
+        // Records are found whether or not they moved yet.
+        assert_found(&mut store, count);
+        store.migrate(10).unwrap();
+        assert_eq!(
+            store.stats().records_to_migrate,
+            stats.records_to_migrate - 10
+        );
+        assert_found(&mut store, count);
+
+        // Once every record moved, the old map is gone.
+        store.migrate(usize::MAX).unwrap();
+        assert!(store.migration.is_none());
+        let stats = store.stats();
+        assert_eq!(stats.records_to_migrate, 0);
+        assert_eq!(stats.len, num_entries(count));
+        assert_found(&mut store, count);
+
+        // Search keys are kept in the map, not on the heap.
+        assert!(store.pending_keys.len() < KEYS_PER_LOG_ENTRY);
+    }
+
+    #[test_with_logger]
+    fn test_grows_repeatedly(logger: Logger) {
+        let mut store = ETxOutStore::<HeapORAMStorageCreator>::new(1024, logger);
+        let initial_capacity = store.stats().capacity;
+
+        let count = 2 * initial_capacity;
+        for i in 0..count {
+            store.add_record(&search_key(i), &ciphertext(i)).unwrap();
+        }
+        assert_eq!(store.stats().capacity, 4 * initial_capacity);
+        assert_found(&mut store, count);
+
+        // Adding a record again does not duplicate it.
+        store.add_record(&search_key(0), &ciphertext(0)).unwrap();
+        store.migrate(usize::MAX).unwrap();
+        assert_eq!(store.stats().len, num_entries(count));
+        assert_found(&mut store, count);
+    }
+
+    #[test_with_logger]
+    fn test_migration_finishes_before_growing(logger: Logger) {
+        let mut store = ETxOutStore::<HeapORAMStorageCreator>::new(1024, logger);
+        let initial_capacity = store.stats().capacity;
+
+        // Adding records alone moves records fast enough that the previous
+        // migration is always done by the time the store grows again.
+        let mut num_growths = 0;
+        let mut i = 0;
+        while num_growths < 3 {
+            let stats = store.stats();
+            store.add_record(&search_key(i), &ciphertext(i)).unwrap();
+            if store.stats().capacity > stats.capacity {
+                assert_eq!(stats.records_to_migrate, 0);
+                num_growths += 1;
+            }
+            i += 1;
+        }
+        assert_eq!(store.stats().capacity, 8 * initial_capacity);
+        assert_found(&mut store, i);
+    }
+}
@@ -6,22 +6,31 @@
 
 extern crate alloc;
 
+mod collate;
 mod e_tx_out_store;
+use collate::{check_block_coverage, collate_tx_out_search_results};
 use e_tx_out_store::{ETxOutStore, StorageDataSize, StorageMetaSize};
 
 use alloc::vec::Vec;
 use mc_attest_core::{IasNonce, Quote, QuoteNonce, Report, TargetInfo, VerificationReport};
-use mc_attest_enclave_api::{ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage};
-use mc_common::logger::{log, Logger};
+use mc_attest_enclave_api::{
+    ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage, PeerAuthRequest,
+    PeerAuthResponse, PeerSession,
+};
+use mc_common::{
+    logger::{log, Logger},
+    ResponderId,
+};
 use mc_crypto_ake_enclave::{AkeEnclaveState, NullIdentity};
 use mc_crypto_keys::X25519Public;
 use mc_fog_recovery_db_iface::FogUserEvent;
 use mc_fog_types::{
-    view::{QueryRequest, QueryResponse},
+    view::{QueryRequest, QueryResponse, TxOutSearchResult},
     ETxOutRecord,
 };
 use mc_fog_view_enclave_api::{
-    Error, Result, UntrustedQueryResponse, ViewEnclaveApi, ViewEnclaveInitParams,
+    Error, Result, StoreBlockCoverage, StoreQueryResults, StoreStats, UntrustedQueryResponse,
+    ViewEnclaveApi, ViewEnclaveInitParams,
 };
 use mc_oblivious_traits::ORAMStorageCreator;
 use mc_sgx_compat::sync::Mutex;
@@ -52,6 +61,75 @@ where
             logger,
         }
     }
+
+    /// Decode a decrypted QueryRequest
+    fn decode_query_request(&self, plaintext: &[u8]) -> Result<QueryRequest> {
+        mc_util_serial::decode(plaintext).map_err(|e| {
+            log::error!(self.logger, "Could not decode user request: {}", e);
+            Error::ProstDecode
+        })
+    }
+
+    /// Look up the requested search keys in the store of records
+    fn find_records(&self, search_keys: &[Vec<u8>]) -> Result<Vec<TxOutSearchResult>> {
+        let mut lk = self.e_tx_out_store.lock()?;
+        let store = lk.as_mut().ok_or(Error::EnclaveNotInitialized)?;
+
+        Ok(search_keys
+            .iter()
+            .map(|key| store.find_record(&key[..]))
+            .collect())
+    }
+
+    /// Encode a QueryResponse and encrypt it for a client
+    fn encrypt_query_response(
+        &self,
+        channel_id: &ClientSession,
+        resp: &QueryResponse,
+    ) -> Result<Vec<u8>> {
+        let response_plaintext_bytes = mc_util_serial::encode(resp);
+
+        let response = self
+            .ake
+            .client_encrypt(channel_id, &[], &response_plaintext_bytes)?;
+
+        Ok(response.data)
+    }
+}
+
+/// Prepare the untrusted part of a QueryResponse.
+fn query_response_from_untrusted(
+    untrusted_query_response: UntrustedQueryResponse,
+) -> QueryResponse {
+    let mut missed_block_ranges = Vec::new();
+    let mut rng_records = Vec::new();
+    let mut decommissioned_ingest_invocations = Vec::new();
+
+    for event in untrusted_query_response.user_events.into_iter() {
+        match event {
+            FogUserEvent::NewRngRecord(rng_record) => rng_records.push(rng_record),
+
+            FogUserEvent::DecommissionIngestInvocation(decommissioned_ingest_invocation) => {
+                decommissioned_ingest_invocations.push(decommissioned_ingest_invocation)
+            }
+
+            FogUserEvent::MissingBlocks(range) => missed_block_ranges.push(range),
+        }
+    }
+
+    QueryResponse {
+        highest_processed_block_count: untrusted_query_response.highest_processed_block_count,
+        highest_processed_block_signature_timestamp: untrusted_query_response
+            .highest_processed_block_signature_timestamp,
+        next_start_from_user_event_id: untrusted_query_response.next_start_from_user_event_id,
+        missed_block_ranges,
+        rng_records,
+        decommissioned_ingest_invocations,
+        tx_out_search_results: Default::default(),
+        last_known_block_count: untrusted_query_response.last_known_block_count,
+        last_known_block_cumulative_txo_count: untrusted_query_response
+            .last_known_block_cumulative_txo_count,
+    }
 }
 
 impl<OSC> ReportableEnclave for ViewEnclave<OSC>
@@ -84,7 +162,9 @@ where
         // Note: eid is passed to sgx_enclave_id crate earlier in the system, because
         // that crate is not under sgx_compat and isn't meant to be used outside of
         // enclave
-        self.ake.init(Default::default(), params.self_client_id)?;
+        // Fog view routers address stores by their client responder id.
+        self.ake
+            .init(params.self_client_id.clone(), params.self_client_id)?;
         {
             let mut lk = self.e_tx_out_store.lock()?;
             *lk = Some(ETxOutStore::new(
@@ -118,70 +198,119 @@ where
     ) -> Result<Vec<u8>> {
         let channel_id = msg.channel_id.clone();
         let user_plaintext = self.ake.client_decrypt(msg)?;
+        let req = self.decode_query_request(&user_plaintext)?;
 
-        let req: QueryRequest = mc_util_serial::decode(&user_plaintext).map_err(|e| {
-            log::error!(self.logger, "Could not decode user request: {}", e);
-            Error::ProstDecode
-        })?;
+        let mut resp = query_response_from_untrusted(untrusted_query_response);
+        resp.tx_out_search_results = self.find_records(&req.get_txos)?;
 
-        // Prepare the untrusted part of the response.
-        let mut missed_block_ranges = Vec::new();
-        let mut rng_records = Vec::new();
-        let mut decommissioned_ingest_invocations = Vec::new();
+        self.encrypt_query_response(&channel_id, &resp)
+    }
 
-        for event in untrusted_query_response.user_events.into_iter() {
-            match event {
-                FogUserEvent::NewRngRecord(rng_record) => rng_records.push(rng_record),
+    fn add_records(&self, records: Vec<ETxOutRecord>) -> Result<()> {
+        let mut lk = self.e_tx_out_store.lock()?;
+        let store = lk.as_mut().ok_or(Error::EnclaveNotInitialized)?;
+        for rec in records {
+            store.add_record(&rec.search_key, &rec.payload)?;
+        }
+        Ok(())
+    }
 
-                FogUserEvent::DecommissionIngestInvocation(decommissioned_ingest_invocation) => {
-                    decommissioned_ingest_invocations.push(decommissioned_ingest_invocation)
-                }
+    fn get_store_stats(&self) -> Result<StoreStats> {
+        let lk = self.e_tx_out_store.lock()?;
+        let store = lk.as_ref().ok_or(Error::EnclaveNotInitialized)?;
+        Ok(store.stats())
+    }
 
-                FogUserEvent::MissingBlocks(range) => missed_block_ranges.push(range),
-            }
-        }
+    // Router and store specific
 
-        let mut resp = QueryResponse {
-            highest_processed_block_count: untrusted_query_response.highest_processed_block_count,
-            highest_processed_block_signature_timestamp: untrusted_query_response
-                .highest_processed_block_signature_timestamp,
-            next_start_from_user_event_id: untrusted_query_response.next_start_from_user_event_id,
-            missed_block_ranges,
-            rng_records,
-            decommissioned_ingest_invocations,
-            tx_out_search_results: Default::default(),
-            last_known_block_count: untrusted_query_response.last_known_block_count,
-            last_known_block_cumulative_txo_count: untrusted_query_response
-                .last_known_block_cumulative_txo_count,
-        };
+    fn peer_init(&self, peer_id: &ResponderId) -> Result<PeerAuthRequest> {
+        Ok(self.ake.peer_init(peer_id)?)
+    }
 
-        // Do the txos part, scope lock of e_tx_out_store
-        {
-            let mut lk = self.e_tx_out_store.lock()?;
-            let store = lk.as_mut().ok_or(Error::EnclaveNotInitialized)?;
+    fn peer_accept(&self, req: PeerAuthRequest) -> Result<(PeerAuthResponse, PeerSession)> {
+        Ok(self.ake.peer_accept(req)?)
+    }
 
-            resp.tx_out_search_results = req
-                .get_txos
-                .iter()
-                .map(|key| store.find_record(&key[..]))
-                .collect();
-        }
+    fn peer_connect(
+        &self,
+        peer_id: &ResponderId,
+        msg: PeerAuthResponse,
+    ) -> Result<(PeerSession, VerificationReport)> {
+        Ok(self.ake.peer_connect(peer_id, msg)?)
+    }
 
-        let response_plaintext_bytes = mc_util_serial::encode(&resp);
+    fn peer_close(&self, session_id: &PeerSession) -> Result<()> {
+        Ok(self.ake.peer_close(session_id)?)
+    }
 
-        let response = self
-            .ake
-            .client_encrypt(&channel_id, &[], &response_plaintext_bytes)?;
+    fn create_store_queries(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+        store_sessions: Vec<PeerSession>,
+    ) -> Result<Vec<EnclaveMessage<PeerSession>>> {
+        let user_plaintext = self.ake.client_decrypt(msg)?;
 
-        Ok(response.data)
+        // Reject malformed requests here rather than in every store.
+        self.decode_query_request(&user_plaintext)?;
+
+        store_sessions
+            .iter()
+            .map(|session| Ok(self.ake.peer_encrypt(session, &[], &user_plaintext)?))
+            .collect()
     }
 
-    fn add_records(&self, records: Vec<ETxOutRecord>) -> Result<()> {
-        let mut lk = self.e_tx_out_store.lock()?;
-        let store = lk.as_mut().ok_or(Error::EnclaveNotInitialized)?;
-        for rec in records {
-            store.add_record(&rec.search_key, &rec.payload)?;
-        }
-        Ok(())
+    fn store_query(
+        &self,
+        msg: EnclaveMessage<PeerSession>,
+        block_coverage: StoreBlockCoverage,
+    ) -> Result<EnclaveMessage<PeerSession>> {
+        let channel_id = msg.channel_id.clone();
+        let plaintext = self.ake.peer_decrypt(msg)?;
+        let req = self.decode_query_request(&plaintext)?;
+
+        // The router fills in the rest of the client's response.
+        let resp = StoreQueryResults {
+            block_coverage,
+            tx_out_search_results: self.find_records(&req.get_txos)?,
+        };
+        let response_plaintext_bytes = mc_util_serial::serialize(&resp)?;
+
+        Ok(self
+            .ake
+            .peer_encrypt(&channel_id, &[], &response_plaintext_bytes)?)
+    }
+
+    fn collate_store_responses(
+        &self,
+        client_session: ClientSession,
+        store_responses: Vec<EnclaveMessage<PeerSession>>,
+        untrusted_query_response: UntrustedQueryResponse,
+    ) -> Result<Vec<u8>> {
+        let (block_coverages, store_results): (Vec<_>, Vec<_>) = store_responses
+            .into_iter()
+            .map(|msg| {
+                let plaintext = self.ake.peer_decrypt(msg)?;
+                let resp: StoreQueryResults =
+                    mc_util_serial::deserialize(&plaintext).map_err(|e| {
+                        log::error!(self.logger, "Could not decode store response: {}", e);
+                        Error::SerdeDecode
+                    })?;
+                Ok((resp.block_coverage, resp.tx_out_search_results))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+
+        // The stores must hold the records of every block the client is told were
+        // processed, or the client would miss its records in the blocks they lack.
+        check_block_coverage(
+            &block_coverages,
+            untrusted_query_response.highest_processed_block_count,
+        )?;
+
+        let mut resp = query_response_from_untrusted(untrusted_query_response);
+        resp.tx_out_search_results = collate_tx_out_search_results(store_results)?;
+
+        self.encrypt_query_response(&client_session, &resp)
     }
 }
@@ -6,12 +6,17 @@
 
 extern crate mc_fog_ocall_oram_storage_untrusted;
 
+pub use mc_fog_ocall_oram_storage_untrusted::{configure_oram_storage, OramStorageConfig};
+
 use std::{path, result::Result as StdResult, sync::Arc};
 
 use mc_attest_core::{
     IasNonce, Quote, QuoteNonce, Report, SgxError, TargetInfo, VerificationReport,
 };
-use mc_attest_enclave_api::{ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage};
+use mc_attest_enclave_api::{
+    ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage, PeerAuthRequest,
+    PeerAuthResponse, PeerSession,
+};
 use mc_attest_verifier::DEBUG_ENCLAVE;
 use mc_common::{logger::Logger, ResponderId};
 use mc_crypto_keys::X25519Public;
@@ -23,7 +28,8 @@ use mc_sgx_types::{sgx_attributes_t, sgx_enclave_id_t, sgx_launch_token_t, sgx_m
 use mc_sgx_urts::SgxEnclave;
 
 pub use mc_fog_view_enclave_api::{
-    Error, Result, ViewEnclaveApi, ViewEnclaveInitParams, ViewEnclaveProxy, ViewEnclaveRequest,
+    Error, Result, StoreBlockCoverage, StoreStats, ViewEnclaveApi, ViewEnclaveInitParams,
+    ViewEnclaveProxy, ViewEnclaveRequest,
 };
 
 mod ecall;
@@ -179,4 +185,78 @@ impl ViewEnclaveApi for SgxViewEnclave {
         let outbuf = self.enclave_call(&inbuf)?;
         mc_util_serial::deserialize(&outbuf[..])?
     }
+
+    fn get_store_stats(&self) -> Result<StoreStats> {
+        let inbuf = mc_util_serial::serialize(&ViewEnclaveRequest::GetStoreStats)?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn peer_init(&self, peer_id: &ResponderId) -> Result<PeerAuthRequest> {
+        let inbuf = mc_util_serial::serialize(&ViewEnclaveRequest::PeerInit(peer_id.clone()))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn peer_accept(&self, req: PeerAuthRequest) -> Result<(PeerAuthResponse, PeerSession)> {
+        let inbuf = mc_util_serial::serialize(&ViewEnclaveRequest::PeerAccept(req))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn peer_connect(
+        &self,
+        peer_id: &ResponderId,
+        msg: PeerAuthResponse,
+    ) -> Result<(PeerSession, VerificationReport)> {
+        let inbuf =
+            mc_util_serial::serialize(&ViewEnclaveRequest::PeerConnect(peer_id.clone(), msg))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn peer_close(&self, session_id: &PeerSession) -> Result<()> {
+        let inbuf = mc_util_serial::serialize(&ViewEnclaveRequest::PeerClose(session_id.clone()))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn create_store_queries(
+        &self,
+        payload: EnclaveMessage<ClientSession>,
+        store_sessions: Vec<PeerSession>,
+    ) -> Result<Vec<EnclaveMessage<PeerSession>>> {
+        let inbuf = mc_util_serial::serialize(&ViewEnclaveRequest::CreateStoreQueries(
+            payload,
+            store_sessions,
+        ))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn store_query(
+        &self,
+        payload: EnclaveMessage<PeerSession>,
+        block_coverage: StoreBlockCoverage,
+    ) -> Result<EnclaveMessage<PeerSession>> {
+        let inbuf =
+            mc_util_serial::serialize(&ViewEnclaveRequest::StoreQuery(payload, block_coverage))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
+
+    fn collate_store_responses(
+        &self,
+        client_session: ClientSession,
+        store_responses: Vec<EnclaveMessage<PeerSession>>,
+        untrusted_query_response: UntrustedQueryResponse,
+    ) -> Result<Vec<u8>> {
+        let inbuf = mc_util_serial::serialize(&ViewEnclaveRequest::CollateStoreResponses(
+            client_session,
+            store_responses,
+            untrusted_query_response,
+        ))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mc_util_serial::deserialize(&outbuf[..])?
+    }
 }
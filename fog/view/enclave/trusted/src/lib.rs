@@ -120,6 +120,28 @@ pub fn ecall_dispatcher(inbuf: &[u8]) -> Result<Vec<u8>, sgx_status_t> {
             serialize(&ENCLAVE.query(req, untrusted_query_response))
         }
         ViewEnclaveRequest::AddRecords(records) => serialize(&ENCLAVE.add_records(records)),
+        ViewEnclaveRequest::GetStoreStats => serialize(&ENCLAVE.get_store_stats()),
+        ViewEnclaveRequest::PeerInit(peer_id) => serialize(&ENCLAVE.peer_init(&peer_id)),
+        ViewEnclaveRequest::PeerAccept(req) => serialize(&ENCLAVE.peer_accept(req)),
+        ViewEnclaveRequest::PeerConnect(peer_id, res) => {
+            serialize(&ENCLAVE.peer_connect(&peer_id, res))
+        }
+        ViewEnclaveRequest::PeerClose(session) => serialize(&ENCLAVE.peer_close(&session)),
+        ViewEnclaveRequest::CreateStoreQueries(req, store_sessions) => {
+            serialize(&ENCLAVE.create_store_queries(req, store_sessions))
+        }
+        ViewEnclaveRequest::StoreQuery(req, block_coverage) => {
+            serialize(&ENCLAVE.store_query(req, block_coverage))
+        }
+        ViewEnclaveRequest::CollateStoreResponses(
+            client_session,
+            store_responses,
+            untrusted_query_response,
+        ) => serialize(&ENCLAVE.collate_store_responses(
+            client_session,
+            store_responses,
+            untrusted_query_response,
+        )),
     }
     .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))
 }
@@ -0,0 +1,52 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Serves the attestation requests of the fog view routers that query this
+//! server.
+
+use grpcio::{RpcContext, UnarySink};
+use mc_attest_api::{attest::AuthMessage, attest_grpc::AttestedApi};
+use mc_common::logger::{log, Logger};
+use mc_fog_view_enclave::ViewEnclaveProxy;
+use mc_util_grpc::{rpc_logger, rpc_permissions_error, send_result};
+use mc_util_metrics::SVC_COUNTERS;
+
+#[derive(Clone)]
+pub struct AttestedApiService<E: ViewEnclaveProxy> {
+    enclave: E,
+    logger: Logger,
+}
+
+impl<E: ViewEnclaveProxy> AttestedApiService<E> {
+    pub fn new(enclave: E, logger: Logger) -> Self {
+        Self { enclave, logger }
+    }
+}
+
+impl<E: ViewEnclaveProxy> AttestedApi for AttestedApiService<E> {
+    fn auth(&mut self, ctx: RpcContext, request: AuthMessage, sink: UnarySink<AuthMessage>) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            // TODO: Use the prost message directly, once available
+            match self.enclave.peer_accept(request.into()) {
+                Ok((response, _session_id)) => {
+                    send_result(ctx, sink, Ok(response.into()), logger);
+                }
+                Err(peer_error) => {
+                    // This is debug because there's no requirement on the remote party to trigger
+                    // it.
+                    log::debug!(logger, "ViewEnclaveApi::peer_accept failed: {}", peer_error);
+                    send_result(
+                        ctx,
+                        sink,
+                        Err(rpc_permissions_error(
+                            "peer_auth",
+                            "Permission denied",
+                            logger,
+                        )),
+                        logger,
+                    );
+                }
+            }
+        });
+    }
+}
@@ -6,7 +6,7 @@ use grpcio::{RpcStatus, RpcStatusCode};
 use mc_attest_net::{Client, RaClient};
 use mc_common::{logger::log, time::SystemTimeProvider};
 use mc_fog_sql_recovery_db::SqlRecoveryDb;
-use mc_fog_view_enclave::{SgxViewEnclave, ENCLAVE_FILE};
+use mc_fog_view_enclave::{configure_oram_storage, SgxViewEnclave, ENCLAVE_FILE};
 use mc_fog_view_server::{config::MobileAcctViewConfig, server::ViewServer};
 use mc_util_cli::ParserWithBuildInfo;
 use mc_util_grpc::AdminServer;
@@ -44,6 +44,7 @@ fn main() {
         enclave_path.to_str().unwrap(),
         &config.client_responder_id
     );
+    configure_oram_storage(config.oram_storage_config());
     let sgx_enclave = SgxViewEnclave::new(
         enclave_path,
         config.client_responder_id.clone(),
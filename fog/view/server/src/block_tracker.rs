@@ -3,7 +3,8 @@
 use mc_common::logger::{log, Logger};
 use mc_crypto_keys::CompressedRistrettoPublic;
 use mc_fog_recovery_db_iface::IngressPublicKeyRecord;
-use std::collections::HashMap;
+use mc_fog_types::common::BlockRange;
+use std::{cmp::max, collections::HashMap};
 
 /// A utility object that keeps track of which block number was processed for
 /// every known ingress key. This provides utilities such as:
@@ -12,17 +13,22 @@ use std::collections::HashMap;
 /// - Finding out what is the highest block index we have encountered so far.
 /// - Finding out for which block index have we processed data for all ingress
 ///   keys, while taking into account ingress keys reported lost
+///
+/// Only the blocks in the tracker's block range are considered. A sharded fog
+/// view store is only responsible for the blocks in its range.
 pub struct BlockTracker {
+    block_range: BlockRange,
     processed_block_per_ingress_key: HashMap<CompressedRistrettoPublic, u64>,
     last_highest_processed_block_count: u64,
     logger: Logger,
 }
 
 impl BlockTracker {
-    pub fn new(logger: Logger) -> Self {
+    pub fn new(block_range: BlockRange, logger: Logger) -> Self {
         Self {
+            last_highest_processed_block_count: block_range.start_block,
+            block_range,
             processed_block_per_ingress_key: HashMap::default(),
-            last_highest_processed_block_count: 0,
             logger,
         }
     }
@@ -41,16 +47,17 @@ impl BlockTracker {
                 // next one can be provided by it, and if so add it to the list of next blocks
                 // we would like to process.
                 let next_block = last_processed_block + 1;
-                if rec.covers_block_index(next_block) {
+                if self.block_range.contains(next_block) && rec.covers_block_index(next_block) {
                     next_blocks.insert(rec.key, next_block);
                 }
             } else {
                 // No block has been processed for this ingress key, so the next block is the
-                // first one, assuming it can actually be provided by the ingress key.
-                // (It will not be able to provide the start block if it got lost
+                // first one in our range, assuming it can actually be provided by the ingress
+                // key. (It will not be able to provide the start block if it got lost
                 // immediately after starting before scanning any blocks)
-                if rec.covers_block_index(rec.status.start_block) {
-                    next_blocks.insert(rec.key, rec.status.start_block);
+                let first_block = max(rec.status.start_block, self.block_range.start_block);
+                if self.block_range.contains(first_block) && rec.covers_block_index(first_block) {
+                    next_blocks.insert(rec.key, first_block);
                 }
             }
         }
@@ -85,8 +92,8 @@ impl BlockTracker {
     /// * missing_block_ranges: Any manually entered missing block ranges.
     ///
     /// Returns:
-    /// * The highest fully processed block count, which may be 0 if nothing is
-    ///   processed
+    /// * The highest fully processed block count, which may be the start of our
+    ///   block range if nothing is processed
     /// * Optionally, an IngressPublicKeyRecord which is the *reason* that the
     ///   previous number is less than highest_known_block_index -- the next
     ///   thing we are waiting on for data.
@@ -125,6 +132,12 @@ impl BlockTracker {
                 break 'outer;
             }
 
+            // Blocks past the end of our range are not ours to process.
+            if !self.block_range.contains(next_block_index) {
+                log::trace!(self.logger, "We processed everything in our block range");
+                break 'outer;
+            }
+
             // Go over all known ingress keys and check if
             // any of them need to provide this block and have not provided it
             for rec in ingress_keys {
@@ -195,7 +208,7 @@ mod tests {
 
     #[test_with_logger]
     fn next_blocks_empty(logger: Logger) {
-        let block_tracker = BlockTracker::new(logger.clone());
+        let block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger.clone());
         assert_eq!(block_tracker.next_blocks(&[]).len(), 0);
     }
 
@@ -203,7 +216,7 @@ mod tests {
     #[test_with_logger]
     fn next_blocks_single_key_hasnt_scanned(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
-        let mut block_tracker = BlockTracker::new(logger);
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger);
         let rec = IngressPublicKeyRecord {
             key: CompressedRistrettoPublic::from_random(&mut rng),
             status: IngressPublicKeyStatus {
@@ -245,7 +258,7 @@ mod tests {
     #[test_with_logger]
     fn next_blocks_single_range_commissioned_scanned_some(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
-        let mut block_tracker = BlockTracker::new(logger);
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger);
         let rec = IngressPublicKeyRecord {
             key: CompressedRistrettoPublic::from_random(&mut rng),
             status: IngressPublicKeyStatus {
@@ -281,7 +294,7 @@ mod tests {
     #[test_with_logger]
     fn next_blocks_single_key_retired_hasnt_scanned(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
-        let mut block_tracker = BlockTracker::new(logger);
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger);
 
         let key = CompressedRistrettoPublic::from_random(&mut rng);
         let rec = IngressPublicKeyRecord {
@@ -333,7 +346,7 @@ mod tests {
     #[test_with_logger]
     fn next_blocks_single_range_retired_scanned_some(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
-        let mut block_tracker = BlockTracker::new(logger);
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger);
         let last_ingested_block = 126;
         let rec = IngressPublicKeyRecord {
             key: CompressedRistrettoPublic::from_random(&mut rng),
@@ -377,7 +390,7 @@ mod tests {
     #[test_with_logger]
     fn next_blocks_single_key_lost_hasnt_scanned(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
-        let block_tracker = BlockTracker::new(logger);
+        let block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger);
 
         let key = CompressedRistrettoPublic::from_random(&mut rng);
         let rec = IngressPublicKeyRecord {
@@ -403,7 +416,7 @@ mod tests {
     #[test_with_logger]
     fn next_blocks_single_key_lost_scanned_some(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
-        let mut block_tracker = BlockTracker::new(logger);
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger);
 
         let key = CompressedRistrettoPublic::from_random(&mut rng);
         let rec = IngressPublicKeyRecord {
@@ -452,7 +465,7 @@ mod tests {
     #[test_with_logger]
     fn next_blocks_multiple_keys(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
-        let mut block_tracker = BlockTracker::new(logger);
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger);
         let rec1 = IngressPublicKeyRecord {
             key: CompressedRistrettoPublic::from_random(&mut rng),
             status: IngressPublicKeyStatus {
@@ -509,7 +522,7 @@ mod tests {
     // highest_fully_processed_block_count behaves as expected
     #[test_with_logger]
     fn highest_fully_processed_block_count_all_empty(logger: Logger) {
-        let mut block_tracker = BlockTracker::new(logger.clone());
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger.clone());
 
         assert_eq!(
             block_tracker.highest_fully_processed_block_count(&[]),
@@ -520,7 +533,7 @@ mod tests {
     // Check with a key that hasn't yet processed anything.
     #[test_with_logger]
     fn highest_fully_processed_block_missing_blocks_nothing_processed1(logger: Logger) {
-        let mut block_tracker = BlockTracker::new(logger.clone());
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger.clone());
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let rec = IngressPublicKeyRecord {
             key: CompressedRistrettoPublic::from_random(&mut rng),
@@ -543,7 +556,7 @@ mod tests {
     // are processed when the start block is 0.
     #[test_with_logger]
     fn highest_fully_processed_block_tracks_block_processed1(logger: Logger) {
-        let mut block_tracker = BlockTracker::new(logger.clone());
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger.clone());
 
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let rec = IngressPublicKeyRecord {
@@ -578,7 +591,7 @@ mod tests {
     // when the start block is greater than zero
     #[test_with_logger]
     fn highest_fully_processed_block_tracks_block_processed2(logger: Logger) {
-        let mut block_tracker = BlockTracker::new(logger.clone());
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger.clone());
 
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let rec = IngressPublicKeyRecord {
@@ -614,7 +627,7 @@ mod tests {
     // then the key is reported lost
     #[test_with_logger]
     fn highest_fully_processed_block_tracks_block_processed3(logger: Logger) {
-        let mut block_tracker = BlockTracker::new(logger.clone());
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger.clone());
 
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let mut rec = IngressPublicKeyRecord {
@@ -661,7 +674,7 @@ mod tests {
     // When the slow one is marked lost, that unblocks progress.
     #[test_with_logger]
     fn highest_fully_processed_block_tracks_multiple_recs(logger: Logger) {
-        let mut block_tracker = BlockTracker::new(logger.clone());
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger.clone());
 
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let mut rec1 = IngressPublicKeyRecord {
@@ -760,7 +773,7 @@ mod tests {
     // key is loaded
     #[test_with_logger]
     fn highest_fully_processed_block_tracks_multiple_recs_some_lost2(logger: Logger) {
-        let mut block_tracker = BlockTracker::new(logger.clone());
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger.clone());
 
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let mut rec1 = IngressPublicKeyRecord {
@@ -876,7 +889,7 @@ mod tests {
     /// key, makes progress
     #[test_with_logger]
     fn highest_fully_processed_block_tracks_retired_key_followed_by_gap(logger: Logger) {
-        let mut block_tracker = BlockTracker::new(logger.clone());
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger.clone());
 
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let mut rec1 = IngressPublicKeyRecord {
@@ -975,7 +988,7 @@ mod tests {
     /// when everything works.
     #[test_with_logger]
     fn highest_fully_processed_block_tracks_retired_key_concurrent_with_active(logger: Logger) {
-        let mut block_tracker = BlockTracker::new(logger.clone());
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger.clone());
 
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let mut rec1 = IngressPublicKeyRecord {
@@ -1123,7 +1136,7 @@ mod tests {
     fn highest_fully_processed_block_tracks_retired_key_concurrent_with_active_both_lost(
         logger: Logger,
     ) {
-        let mut block_tracker = BlockTracker::new(logger.clone());
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger.clone());
 
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let mut rec1 = IngressPublicKeyRecord {
@@ -1298,7 +1311,7 @@ mod tests {
     // Highest known block count is 0 when there are no inputs.
     #[test_with_logger]
     fn highest_known_block_count_when_empty(logger: Logger) {
-        let block_tracker = BlockTracker::new(logger);
+        let block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger);
 
         assert_eq!(block_tracker.highest_known_block_count(), 0);
     }
@@ -1308,7 +1321,7 @@ mod tests {
     #[test_with_logger]
     fn highest_known_block_count_tracks_processed(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
-        let mut block_tracker = BlockTracker::new(logger);
+        let mut block_tracker = BlockTracker::new(BlockRange::new(0, u64::MAX), logger);
 
         block_tracker.block_processed(CompressedRistrettoPublic::from_random(&mut rng), 100);
         assert_eq!(block_tracker.highest_known_block_count(), 101);
@@ -1319,4 +1332,83 @@ mod tests {
         block_tracker.block_processed(CompressedRistrettoPublic::from_random(&mut rng), 101);
         assert_eq!(block_tracker.highest_known_block_count(), 102);
     }
+
+    // Only the blocks in the block range are loaded.
+    #[test_with_logger]
+    fn next_blocks_respects_block_range(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let mut block_tracker = BlockTracker::new(BlockRange::new(100, 103), logger);
+
+        // A key that started before the range.
+        let rec1 = IngressPublicKeyRecord {
+            key: CompressedRistrettoPublic::from_random(&mut rng),
+            status: IngressPublicKeyStatus {
+                start_block: 50,
+                pubkey_expiry: 200,
+                retired: false,
+                lost: false,
+            },
+            last_scanned_block: None,
+        };
+
+        // A key that started after the range.
+        let rec2 = IngressPublicKeyRecord {
+            key: CompressedRistrettoPublic::from_random(&mut rng),
+            status: IngressPublicKeyStatus {
+                start_block: 150,
+                pubkey_expiry: 200,
+                retired: false,
+                lost: false,
+            },
+            last_scanned_block: None,
+        };
+
+        for block_index in 100..103 {
+            assert_eq!(
+                block_tracker.next_blocks(&[rec1.clone(), rec2.clone()]),
+                HashMap::from_iter(vec![(rec1.key, block_index)])
+            );
+            block_tracker.block_processed(rec1.key, block_index);
+        }
+
+        assert!(block_tracker.next_blocks(&[rec1, rec2]).is_empty());
+    }
+
+    // The highest fully processed block count starts at the beginning of the
+    // block range, and does not go past its end.
+    #[test_with_logger]
+    fn highest_fully_processed_block_count_respects_block_range(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let mut block_tracker = BlockTracker::new(BlockRange::new(100, 103), logger);
+        let rec = IngressPublicKeyRecord {
+            key: CompressedRistrettoPublic::from_random(&mut rng),
+            status: IngressPublicKeyStatus {
+                start_block: 50,
+                pubkey_expiry: 200,
+                retired: false,
+                lost: false,
+            },
+            last_scanned_block: None,
+        };
+
+        assert_eq!(
+            block_tracker.highest_fully_processed_block_count(&[rec.clone()]),
+            (100, None)
+        );
+
+        for block_index in 100..103 {
+            block_tracker.block_processed(rec.key, block_index);
+            assert_eq!(
+                block_tracker.highest_fully_processed_block_count(&[rec.clone()]),
+                (block_index + 1, None)
+            );
+        }
+
+        // Another key knows of later blocks, but they are outside of our range.
+        block_tracker.block_processed(CompressedRistrettoPublic::from_random(&mut rng), 105);
+        assert_eq!(
+            block_tracker.highest_fully_processed_block_count(&[rec]),
+            (103, None)
+        );
+    }
 }
@@ -7,11 +7,13 @@ use clap::Parser;
 use mc_attest_core::ProviderId;
 use mc_common::ResponderId;
 use mc_fog_sql_recovery_db::SqlRecoveryDbConnectionConfig;
+use mc_fog_types::common::BlockRange;
 use mc_fog_uri::FogViewUri;
+use mc_fog_view_enclave::OramStorageConfig;
 use mc_util_parse::parse_duration_in_seconds;
 use mc_util_uri::AdminUri;
 use serde::Serialize;
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
 /// Configuration parameters for the MobileCoin Fog View Node
 #[derive(Clone, Parser, Serialize)]
@@ -51,20 +53,74 @@ pub struct MobileAcctViewConfig {
     #[clap(long, default_value = "86400", parse(try_from_str = parse_duration_in_seconds), env = "MC_CLIENT_AUTH_TOKEN_MAX_LIFETIME")]
     pub client_auth_token_max_lifetime: Duration,
 
-    /// The capacity to build the OMAP (ORAM hash table) with.
-    /// About 75% of this capacity can be used.
-    /// The hash table will overflow when there are more TxOut's than this,
-    /// and the server will have to be restarted with a larger number.
+    /// The initial capacity to build the OMAP (ORAM hash table) with.
+    /// About 75% of this capacity can be used. Past that, the enclave builds
+    /// a hash table twice as large and moves the TxOut's to it while it keeps
+    /// serving queries, so this only needs to be large enough to avoid growing
+    /// too often.
     ///
     /// Note: At time of writing, the hash table will be allocated to use all
     /// available SGX EPC memory, and then beyond that it will be allocated on
     /// the heap in the untrusted side. Once the needed capacity exceeds RAM,
     /// you will either get killed by OOM killer, or it will start being swapped
-    /// to disk by linux kernel.
+    /// to disk by linux kernel, unless --oram-storage-dir is used.
     #[clap(long, default_value = "1048576", env = "MC_OMAP_CAPACITY")]
     pub omap_capacity: u64,
 
+    /// A directory, on a local disk, in which to keep the untrusted ORAM
+    /// storage that does not fit in --oram-storage-memory-bytes. By default,
+    /// it is all kept in memory.
+    #[clap(long, env = "MC_ORAM_STORAGE_DIR")]
+    pub oram_storage_dir: Option<PathBuf>,
+
+    /// With --oram-storage-dir, the number of bytes of untrusted ORAM storage
+    /// to keep in memory. The levels of the ORAM trees closest to the root,
+    /// which every access reads from, are kept in memory first.
+    #[clap(
+        long,
+        default_value = "4294967296",
+        env = "MC_ORAM_STORAGE_MEMORY_BYTES"
+    )]
+    pub oram_storage_memory_bytes: u64,
+
+    /// The first block whose records this server holds.
+    ///
+    /// Setting this or --shard-end-block makes this server a fog view store,
+    /// which only holds the records of a range of blocks, and is queried by
+    /// fog view routers rather than by clients.
+    #[clap(long, default_value = "0", env = "MC_SHARD_START_BLOCK")]
+    pub shard_start_block: u64,
+
+    /// One past the last block whose records this server holds. Unbounded if
+    /// omitted.
+    #[clap(long, env = "MC_SHARD_END_BLOCK")]
+    pub shard_end_block: Option<u64>,
+
     /// Postgres config
     #[clap(flatten)]
     pub postgres_config: SqlRecoveryDbConnectionConfig,
 }
+
+impl MobileAcctViewConfig {
+    /// The range of blocks whose records this server holds.
+    pub fn block_range(&self) -> BlockRange {
+        BlockRange::new(
+            self.shard_start_block,
+            self.shard_end_block.unwrap_or(u64::MAX),
+        )
+    }
+
+    /// Where to keep the untrusted ORAM storage.
+    pub fn oram_storage_config(&self) -> OramStorageConfig {
+        OramStorageConfig {
+            dir: self.oram_storage_dir.clone(),
+            memory_bytes: self.oram_storage_memory_bytes,
+        }
+    }
+
+    /// Whether this server only holds the records of some of the blocks, and
+    /// can only be queried through a fog view router.
+    pub fn is_store(&self) -> bool {
+        self.shard_start_block != 0 || self.shard_end_block.is_some()
+    }
+}
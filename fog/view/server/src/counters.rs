@@ -41,6 +41,18 @@ lazy_static::lazy_static! {
     // Last known block cumulative txo count
     pub static ref LAST_KNOWN_BLOCK_CUMULATIVE_TXO_COUNT: IntGauge = OP_COUNTERS.gauge("last_known_block_cumulative_txo_count");
 
+    // Capacity of the enclave's oblivious map of txos.
+    pub static ref ENCLAVE_OMAP_CAPACITY: IntGauge = OP_COUNTERS.gauge("enclave_omap_capacity");
+
+    // Number of txos in the enclave's oblivious map, including those left to move to it while it grows.
+    pub static ref ENCLAVE_OMAP_LEN: IntGauge = OP_COUNTERS.gauge("enclave_omap_len");
+
+    // Load factor of the enclave's oblivious map, in percent. It grows when this passes 75.
+    pub static ref ENCLAVE_OMAP_LOAD_FACTOR_PERCENT: IntGauge = OP_COUNTERS.gauge("enclave_omap_load_factor_percent");
+
+    // Number of txos left to move to the enclave's oblivious map while it grows.
+    pub static ref ENCLAVE_OMAP_RECORDS_TO_MIGRATE: IntGauge = OP_COUNTERS.gauge("enclave_omap_records_to_migrate");
+
     // Number of records currently in the db fetcher fetched_records queue.
     pub static ref DB_FETCHER_NUM_QUEUED_RECORDS: IntGauge = OP_COUNTERS.gauge("db_fetcher_num_queued_records");
 }
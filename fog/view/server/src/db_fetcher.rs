@@ -6,7 +6,7 @@ use crate::{block_tracker::BlockTracker, counters};
 use mc_common::logger::{log, Logger};
 use mc_crypto_keys::CompressedRistrettoPublic;
 use mc_fog_recovery_db_iface::{IngressPublicKeyRecord, IngressPublicKeyRecordFilters, RecoveryDb};
-use mc_fog_types::ETxOutRecord;
+use mc_fog_types::{common::BlockRange, ETxOutRecord};
 use mc_util_grpc::ReadinessIndicator;
 use std::{
     sync::{
@@ -75,8 +75,10 @@ pub struct DbFetcher {
 }
 
 impl DbFetcher {
+    /// Start fetching the records of the blocks in `block_range`.
     pub fn new<DB: RecoveryDb + Clone + Send + Sync + 'static>(
         db: DB,
+        block_range: BlockRange,
         readiness_indicator: ReadinessIndicator,
         logger: Logger,
     ) -> Self {
@@ -98,6 +100,7 @@ impl DbFetcher {
                 .spawn(move || {
                     DbFetcherThread::start(
                         db,
+                        block_range,
                         thread_stop_requested,
                         thread_shared_state,
                         thread_num_queued_records_limiter,
@@ -180,6 +183,7 @@ struct DbFetcherThread<DB: RecoveryDb + Clone + Send + Sync + 'static> {
 impl<DB: RecoveryDb + Clone + Send + Sync + 'static> DbFetcherThread<DB> {
     pub fn start(
         db: DB,
+        block_range: BlockRange,
         stop_requested: Arc<AtomicBool>,
         shared_state: Arc<Mutex<DbFetcherSharedState>>,
         num_queued_records_limiter: Arc<(Mutex<usize>, Condvar)>,
@@ -190,7 +194,7 @@ impl<DB: RecoveryDb + Clone + Send + Sync + 'static> DbFetcherThread<DB> {
             db,
             stop_requested,
             shared_state,
-            block_tracker: BlockTracker::new(logger.clone()),
+            block_tracker: BlockTracker::new(block_range, logger.clone()),
             num_queued_records_limiter,
             readiness_indicator,
             logger,
@@ -380,7 +384,12 @@ mod tests {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let db_test_context = SqlRecoveryDbTestContext::new(logger.clone());
         let db = db_test_context.get_db_instance();
-        let db_fetcher = DbFetcher::new(db.clone(), Default::default(), logger);
+        let db_fetcher = DbFetcher::new(
+            db.clone(),
+            BlockRange::new(0, u64::MAX),
+            Default::default(),
+            logger,
+        );
 
         // Initially, our database starts empty.
         let ingress_keys = db_fetcher.get_highest_processed_block_context();
@@ -610,7 +619,12 @@ mod tests {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let db_test_context = SqlRecoveryDbTestContext::new(logger.clone());
         let db = db_test_context.get_db_instance();
-        let db_fetcher = DbFetcher::new(db.clone(), Default::default(), logger);
+        let db_fetcher = DbFetcher::new(
+            db.clone(),
+            BlockRange::new(0, u64::MAX),
+            Default::default(),
+            logger,
+        );
 
         // Register two ingress keys that have some overlap:
         // key_id1 starts at block 0, key2 starts at block 5.
@@ -667,7 +681,12 @@ mod tests {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
         let db_test_context = SqlRecoveryDbTestContext::new(logger.clone());
         let db = db_test_context.get_db_instance();
-        let db_fetcher = DbFetcher::new(db.clone(), Default::default(), logger);
+        let db_fetcher = DbFetcher::new(
+            db.clone(),
+            BlockRange::new(0, u64::MAX),
+            Default::default(),
+            logger,
+        );
 
         // Register two ingress keys that have some overlap:
         // invoc_id1 starts at block 0, invoc_id2 starts at block 50.
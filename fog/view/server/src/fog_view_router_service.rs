@@ -0,0 +1,316 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Serves client queries by forwarding them to fog view stores and merging
+//! their responses.
+
+use crate::{server::DbPollSharedState, view_store_connection::ViewStoreConnectionPool};
+use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
+use mc_attest_api::attest;
+use mc_attest_enclave_api::ClientSession;
+use mc_common::logger::{log, Logger};
+use mc_fog_api::{view::StoreQueryResponse, view_grpc::FogViewApi};
+use mc_fog_recovery_db_iface::{FogUserEvent, RecoveryDb};
+use mc_fog_types::view::QueryRequestAAD;
+use mc_fog_view_enclave::{Error as ViewEnclaveError, ViewEnclaveProxy};
+use mc_fog_view_enclave_api::UntrustedQueryResponse;
+use mc_util_grpc::{
+    rpc_internal_error, rpc_invalid_arg_error, rpc_logger, rpc_permissions_error, send_result,
+    Authenticator,
+};
+use mc_util_metrics::SVC_COUNTERS;
+use mc_util_telemetry::{tracer, Tracer};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct FogViewRouterService<E: ViewEnclaveProxy, DB: RecoveryDb + Send + Sync> {
+    /// Enclave, attested with the clients and with the stores
+    enclave: E,
+
+    /// Recovery DB, from which user events are read.
+    db: Arc<DB>,
+
+    /// Connections to the fog view stores.
+    store_connections: Arc<ViewStoreConnectionPool<E>>,
+
+    /// GRPC request authenticator.
+    authenticator: Arc<dyn Authenticator + Send + Sync>,
+
+    /// Slog logger object
+    logger: Logger,
+}
+
+impl<E: ViewEnclaveProxy, DB: RecoveryDb + Send + Sync> FogViewRouterService<E, DB> {
+    /// Creates a new fog-view-router-service (but does not create sockets and
+    /// start it etc.)
+    pub fn new(
+        enclave: E,
+        db: Arc<DB>,
+        store_connections: Arc<ViewStoreConnectionPool<E>>,
+        authenticator: Arc<dyn Authenticator + Send + Sync>,
+        logger: Logger,
+    ) -> Self {
+        Self {
+            enclave,
+            db,
+            store_connections,
+            authenticator,
+            logger,
+        }
+    }
+
+    /// Forward to every store through the enclave, and collate their responses
+    pub fn query_impl(&mut self, request: attest::Message) -> Result<attest::Message, RpcStatus> {
+        log::trace!(self.logger, "Getting encrypted request");
+        let tracer = tracer!();
+
+        tracer.in_span("query_impl", |_cx| {
+            // Attempt and deserialize the untrusted portion of this request.
+            let query_request_aad: QueryRequestAAD = mc_util_serial::decode(request.get_aad())
+                .map_err(|err| {
+                    RpcStatus::with_message(
+                        RpcStatusCode::INVALID_ARGUMENT,
+                        format!("AAD deserialization error: {}", err),
+                    )
+                })?;
+            let client_session = ClientSession::from(request.get_channel_id());
+
+            let (user_events, next_start_from_user_event_id) =
+                tracer.in_span("search_user_events", |_cx| {
+                    self.db
+                        .search_user_events(query_request_aad.start_from_user_event_id)
+                        .map_err(|e| rpc_internal_error("search_user_events", e, &self.logger))
+                })?;
+
+            // The enclave re-encrypts the query for every store, over a set of store
+            // connections which this query has to itself until it is done.
+            let result_blob = tracer.in_span("query_stores", |_cx| {
+                self.store_connections.query(
+                    |store_sessions| {
+                        self.enclave
+                            .create_store_queries(request.into(), store_sessions)
+                            .map_err(|e| {
+                                self.enclave_err_to_rpc_status("enclave create store queries", e)
+                            })
+                    },
+                    |store_responses| {
+                        self.collate_store_responses(
+                            client_session,
+                            user_events,
+                            next_start_from_user_event_id,
+                            store_responses,
+                        )
+                    },
+                )
+            })?;
+
+            let mut resp = attest::Message::new();
+            resp.set_data(result_blob);
+            Ok(resp)
+        })
+    }
+
+    /// Have the enclave merge the stores' responses into the client's.
+    fn collate_store_responses(
+        &self,
+        client_session: ClientSession,
+        user_events: Vec<FogUserEvent>,
+        next_start_from_user_event_id: i64,
+        store_responses: Vec<StoreQueryResponse>,
+    ) -> Result<Vec<u8>, RpcStatus> {
+        let tracer = tracer!();
+
+        let block_counts = combine_block_counts(&store_responses);
+        let untrusted_query_response = UntrustedQueryResponse {
+            user_events,
+            next_start_from_user_event_id,
+            highest_processed_block_count: block_counts.highest_processed_block_count,
+            highest_processed_block_signature_timestamp: block_counts
+                .highest_processed_block_signature_timestamp,
+            last_known_block_count: block_counts.last_known_block_count,
+            last_known_block_cumulative_txo_count: block_counts
+                .last_known_block_cumulative_txo_count,
+        };
+
+        let store_messages = store_responses
+            .into_iter()
+            .map(|mut response| response.take_query_response().into())
+            .collect();
+
+        tracer.in_span("enclave_collate", |_cx| {
+            self.enclave
+                .collate_store_responses(client_session, store_messages, untrusted_query_response)
+                .map_err(|e| self.enclave_err_to_rpc_status("enclave collate", e))
+        })
+    }
+
+    // Helper function that is common
+    fn enclave_err_to_rpc_status(&self, context: &str, src: ViewEnclaveError) -> RpcStatus {
+        // Treat prost-decode error as an invalid arg,
+        // treat attest error as permission denied,
+        // everything else is an internal error
+        match src {
+            ViewEnclaveError::ProstDecode => {
+                rpc_invalid_arg_error(context, "Prost decode failed", &self.logger)
+            }
+            ViewEnclaveError::AttestEnclave(err) => {
+                rpc_permissions_error(context, err, &self.logger)
+            }
+            other => rpc_internal_error(context, format!("{}", &other), &self.logger),
+        }
+    }
+}
+
+/// Combine the block counts reported by the stores into those of the whole
+/// ledger.
+///
+/// Blocks are processed up to the end of the longest run of processed blocks
+/// starting at the origin block, going from one store's range to the next.
+/// The last known block is the highest one any store knows of.
+pub fn combine_block_counts(store_responses: &[StoreQueryResponse]) -> DbPollSharedState {
+    let mut by_start_block = store_responses.iter().collect::<Vec<_>>();
+    by_start_block.sort_by_key(|response| response.get_block_range().get_start_block());
+
+    let mut result = DbPollSharedState::default();
+    for response in by_start_block {
+        if response.get_block_range().get_start_block() <= result.highest_processed_block_count
+            && response.get_highest_processed_block_count() > result.highest_processed_block_count
+        {
+            result.highest_processed_block_count = response.get_highest_processed_block_count();
+            result.highest_processed_block_signature_timestamp =
+                response.get_highest_processed_block_signature_timestamp();
+        }
+    }
+
+    if let Some(response) = store_responses
+        .iter()
+        .max_by_key(|response| response.get_last_known_block_count())
+    {
+        result.last_known_block_count = response.get_last_known_block_count();
+        result.last_known_block_cumulative_txo_count =
+            response.get_last_known_block_cumulative_txo_count();
+    }
+
+    result
+}
+
+// Implement grpc trait
+impl<E: ViewEnclaveProxy, DB: RecoveryDb + Send + Sync> FogViewApi for FogViewRouterService<E, DB> {
+    fn auth(
+        &mut self,
+        ctx: RpcContext,
+        mut request: attest::AuthMessage,
+        sink: UnarySink<attest::AuthMessage>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            if let Err(err) = self.authenticator.authenticate_rpc(&ctx) {
+                return send_result(ctx, sink, err.into(), logger);
+            }
+
+            // TODO: Use the prost message directly, once available
+            match self.enclave.client_accept(request.take_data().into()) {
+                Ok((response, _)) => {
+                    let mut result = attest::AuthMessage::new();
+                    result.set_data(response.into());
+                    send_result(ctx, sink, Ok(result), logger);
+                }
+                Err(client_error) => {
+                    // This is debug because there's no requirement on the remote party to trigger
+                    // it.
+                    log::debug!(
+                        logger,
+                        "ViewEnclaveApi::client_accept failed: {}",
+                        client_error
+                    );
+                    send_result(
+                        ctx,
+                        sink,
+                        Err(rpc_permissions_error(
+                            "client_auth",
+                            format!("Permission denied: {}", client_error),
+                            logger,
+                        )),
+                        logger,
+                    );
+                }
+            }
+        });
+    }
+
+    fn query(
+        &mut self,
+        ctx: RpcContext,
+        request: attest::Message,
+        sink: UnarySink<attest::Message>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            if let Err(err) = self.authenticator.authenticate_rpc(&ctx) {
+                return send_result(ctx, sink, err.into(), logger);
+            }
+
+            send_result(ctx, sink, self.query_impl(request), logger)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_fog_api::fog_common::BlockRange;
+
+    fn store_response(
+        start_block: u64,
+        end_block: u64,
+        highest_processed_block_count: u64,
+        last_known_block_count: u64,
+    ) -> StoreQueryResponse {
+        let mut block_range = BlockRange::new();
+        block_range.set_start_block(start_block);
+        block_range.set_end_block(end_block);
+
+        let mut response = StoreQueryResponse::new();
+        response.set_block_range(block_range);
+        response.set_highest_processed_block_count(highest_processed_block_count);
+        response
+            .set_highest_processed_block_signature_timestamp(1000 + highest_processed_block_count);
+        response.set_last_known_block_count(last_known_block_count);
+        response.set_last_known_block_cumulative_txo_count(10 * last_known_block_count);
+        response
+    }
+
+    #[test]
+    fn test_combine_block_counts() {
+        // No stores.
+        let combined = combine_block_counts(&[]);
+        assert_eq!(combined.highest_processed_block_count, 0);
+        assert_eq!(combined.last_known_block_count, 0);
+
+        // The first store is done with its range, the second is catching up.
+        let combined = combine_block_counts(&[
+            store_response(10, u64::MAX, 15, 20),
+            store_response(0, 10, 10, 10),
+        ]);
+        assert_eq!(combined.highest_processed_block_count, 15);
+        assert_eq!(combined.highest_processed_block_signature_timestamp, 1015);
+        assert_eq!(combined.last_known_block_count, 20);
+        assert_eq!(combined.last_known_block_cumulative_txo_count, 200);
+
+        // The first store is lagging, so the second one's progress does not count.
+        let combined = combine_block_counts(&[
+            store_response(0, 10, 7, 10),
+            store_response(10, u64::MAX, 15, 20),
+        ]);
+        assert_eq!(combined.highest_processed_block_count, 7);
+        assert_eq!(combined.highest_processed_block_signature_timestamp, 1007);
+        assert_eq!(combined.last_known_block_count, 20);
+
+        // Overlapping ranges.
+        let combined = combine_block_counts(&[
+            store_response(0, 12, 12, 12),
+            store_response(8, 20, 10, 20),
+            store_response(10, u64::MAX, 18, 20),
+        ]);
+        assert_eq!(combined.highest_processed_block_count, 18);
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Serves the queries fog view routers forward to this server.
+
+use crate::server::DbPollSharedState;
+use grpcio::{RpcContext, RpcStatus, UnarySink};
+use mc_attest_api::attest;
+use mc_common::logger::{log, Logger};
+use mc_fog_api::{view::StoreQueryResponse, view_grpc::FogViewStoreApi};
+use mc_fog_types::common::BlockRange;
+use mc_fog_view_enclave::{Error as ViewEnclaveError, StoreBlockCoverage, ViewEnclaveProxy};
+use mc_util_grpc::{
+    rpc_internal_error, rpc_invalid_arg_error, rpc_logger, rpc_permissions_error, send_result,
+};
+use mc_util_metrics::SVC_COUNTERS;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+pub struct FogViewStoreService<E: ViewEnclaveProxy> {
+    /// Enclave holding the records of the blocks in `block_range`
+    enclave: E,
+
+    /// Shared state from db polling thread.
+    db_poll_shared_state: Arc<Mutex<DbPollSharedState>>,
+
+    /// The range of blocks this store holds the records of.
+    block_range: BlockRange,
+
+    /// Slog logger object
+    logger: Logger,
+}
+
+impl<E: ViewEnclaveProxy> FogViewStoreService<E> {
+    /// Creates a new fog-view-store-service (but does not create sockets and
+    /// start it etc.)
+    pub fn new(
+        enclave: E,
+        db_poll_shared_state: Arc<Mutex<DbPollSharedState>>,
+        block_range: BlockRange,
+        logger: Logger,
+    ) -> Self {
+        Self {
+            enclave,
+            db_poll_shared_state,
+            block_range,
+            logger,
+        }
+    }
+
+    /// Forward to enclave, and attach the progress of this store
+    pub fn query_impl(
+        &mut self,
+        request: attest::Message,
+    ) -> Result<StoreQueryResponse, RpcStatus> {
+        log::trace!(self.logger, "Getting encrypted store query");
+
+        // The progress is read before querying the enclave, so that the records of
+        // every block it reports are in the enclave already.
+        let mut response = StoreQueryResponse::new();
+        {
+            let shared_state = self.db_poll_shared_state.lock().expect("mutex poisoned");
+            response.set_highest_processed_block_count(shared_state.highest_processed_block_count);
+            response.set_highest_processed_block_signature_timestamp(
+                shared_state.highest_processed_block_signature_timestamp,
+            );
+            response.set_last_known_block_count(shared_state.last_known_block_count);
+            response.set_last_known_block_cumulative_txo_count(
+                shared_state.last_known_block_cumulative_txo_count,
+            );
+        }
+        let block_coverage = StoreBlockCoverage {
+            block_range: self.block_range.clone(),
+            highest_processed_block_count: response.get_highest_processed_block_count(),
+        };
+
+        let query_response = self
+            .enclave
+            .store_query(request.into(), block_coverage)
+            .map_err(|e| self.enclave_err_to_rpc_status("enclave store query", e))?;
+        response.set_query_response(query_response.into());
+        response.set_block_range((&self.block_range).into());
+        Ok(response)
+    }
+
+    // Helper function that is common
+    fn enclave_err_to_rpc_status(&self, context: &str, src: ViewEnclaveError) -> RpcStatus {
+        // Treat prost-decode error as an invalid arg,
+        // treat attest error as permission denied,
+        // everything else is an internal error
+        match src {
+            ViewEnclaveError::ProstDecode => {
+                rpc_invalid_arg_error(context, "Prost decode failed", &self.logger)
+            }
+            ViewEnclaveError::AttestEnclave(err) => {
+                rpc_permissions_error(context, err, &self.logger)
+            }
+            other => rpc_internal_error(context, format!("{}", &other), &self.logger),
+        }
+    }
+}
+
+// Implement grpc trait
+impl<E: ViewEnclaveProxy> FogViewStoreApi for FogViewStoreService<E> {
+    fn query(
+        &mut self,
+        ctx: RpcContext,
+        request: attest::Message,
+        sink: UnarySink<StoreQueryResponse>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            send_result(ctx, sink, self.query_impl(request), logger)
+        })
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Configuration parameters for the MobileCoin Fog View Router
+#![deny(missing_docs)]
+
+use clap::Parser;
+use mc_attest_core::ProviderId;
+use mc_common::ResponderId;
+use mc_fog_sql_recovery_db::SqlRecoveryDbConnectionConfig;
+use mc_fog_uri::{FogViewStoreUri, FogViewUri};
+use mc_util_parse::parse_duration_in_seconds;
+use mc_util_uri::AdminUri;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Configuration parameters for the MobileCoin Fog View Router
+#[derive(Clone, Parser, Serialize)]
+#[clap(version)]
+pub struct FogViewRouterConfig {
+    /// The ID with which to respond to client attestation requests.
+    ///
+    /// This ID needs to match the host:port clients use in their URI when
+    /// referencing this node.
+    #[clap(long, env = "MC_CLIENT_RESPONDER_ID")]
+    pub client_responder_id: ResponderId,
+
+    /// PEM-formatted keypair to send with an Attestation Request.
+    #[clap(long, env = "MC_IAS_API_KEY")]
+    pub ias_api_key: String,
+
+    /// The IAS SPID to use when getting a quote
+    #[clap(long, env = "MC_IAS_SPID")]
+    pub ias_spid: ProviderId,
+
+    /// gRPC listening URI for client requests.
+    #[clap(long, env = "MC_CLIENT_LISTEN_URI")]
+    pub client_listen_uri: FogViewUri,
+
+    /// The fog view stores to route client queries to.
+    ///
+    /// Each store's responder id needs to match its --client-responder-id.
+    /// Together, the stores' block ranges should cover all the blocks.
+    #[clap(
+        long = "view-store-uri",
+        required = true,
+        use_value_delimiter = true,
+        env = "MC_VIEW_STORE_URIS"
+    )]
+    pub view_store_uris: Vec<FogViewStoreUri>,
+
+    /// The number of client queries which can be forwarded to the stores at
+    /// once. Each of them uses an attested session of its own with every
+    /// store, and the router's enclave keeps at most 64 such sessions, so this
+    /// times the number of stores should not exceed that.
+    #[clap(long, default_value = "4", env = "MC_STORE_CONNECTION_POOL_SIZE")]
+    pub store_connection_pool_size: usize,
+
+    /// Optional admin listening URI.
+    #[clap(long, env = "MC_ADMIN_LISTEN_URI")]
+    pub admin_listen_uri: Option<AdminUri>,
+
+    /// Enables authenticating client requests using Authorization tokens using
+    /// the provided hex-encoded 32 bytes shared secret.
+    #[clap(long, parse(try_from_str = hex::FromHex::from_hex), env = "MC_CLIENT_AUTH_TOKEN_SECRET")]
+    pub client_auth_token_secret: Option<[u8; 32]>,
+
+    /// Maximal client authentication token lifetime, in seconds (only relevant
+    /// when --client-auth-token-secret is used. Defaults to 86400 - 24
+    /// hours).
+    #[clap(long, default_value = "86400", parse(try_from_str = parse_duration_in_seconds), env = "MC_CLIENT_AUTH_TOKEN_MAX_LIFETIME")]
+    pub client_auth_token_max_lifetime: Duration,
+
+    /// Postgres config
+    #[clap(flatten)]
+    pub postgres_config: SqlRecoveryDbConnectionConfig,
+}
@@ -5,10 +5,12 @@
 //! stopping it
 
 use crate::{
-    block_tracker::BlockTracker, config::MobileAcctViewConfig, counters, db_fetcher::DbFetcher,
-    fog_view_service::FogViewService,
+    attested_api_service::AttestedApiService, block_tracker::BlockTracker,
+    config::MobileAcctViewConfig, counters, db_fetcher::DbFetcher,
+    fog_view_service::FogViewService, fog_view_store_service::FogViewStoreService,
 };
 use futures::executor::block_on;
+use mc_attest_api::attest_grpc;
 use mc_attest_net::RaClient;
 use mc_common::{
     logger::{log, Logger},
@@ -18,7 +20,7 @@ use mc_common::{
 use mc_crypto_keys::CompressedRistrettoPublic;
 use mc_fog_api::view_grpc;
 use mc_fog_recovery_db_iface::RecoveryDb;
-use mc_fog_types::ETxOutRecord;
+use mc_fog_types::{common::BlockRange, ETxOutRecord};
 use mc_fog_uri::ConnectionUri;
 use mc_fog_view_enclave::ViewEnclaveProxy;
 use mc_sgx_report_cache_untrusted::ReportCacheThread;
@@ -73,6 +75,7 @@ where
         let db_poll_thread = DbPollThread::new(
             enclave.clone(),
             recovery_db.clone(),
+            config.block_range(),
             readiness_indicator.clone(),
             logger.clone(),
         );
@@ -103,6 +106,21 @@ where
         ));
         log::debug!(logger, "Constructed View GRPC Service");
 
+        // Routers attest with the enclave and then query it. The attestation is what
+        // authenticates them, so the client authenticator is not used here.
+        let attested_api_service = attest_grpc::create_attested_api(AttestedApiService::new(
+            enclave.clone(),
+            logger.clone(),
+        ));
+        let fog_view_store_service =
+            view_grpc::create_fog_view_store_api(FogViewStoreService::new(
+                enclave.clone(),
+                db_poll_thread.get_shared_state(),
+                config.block_range(),
+                logger.clone(),
+            ));
+        log::debug!(logger, "Constructed View Store GRPC Service");
+
         // Health check service
         let health_service =
             mc_util_grpc::HealthService::new(Some(readiness_indicator.into()), logger.clone())
@@ -114,10 +132,16 @@ where
             "Starting View server on {}",
             config.client_listen_uri.addr(),
         );
-        let server_builder = grpcio::ServerBuilder::new(env)
-            .register_service(fog_view_service)
-            .register_service(health_service)
-            .bind_using_uri(&config.client_listen_uri, logger.clone());
+        let mut server_builder = grpcio::ServerBuilder::new(env)
+            .register_service(attested_api_service)
+            .register_service(fog_view_store_service)
+            .register_service(health_service);
+        // A store only holds some of the records, so clients must go through a router.
+        if !config.is_store() {
+            server_builder = server_builder.register_service(fog_view_service);
+        }
+        let server_builder =
+            server_builder.bind_using_uri(&config.client_listen_uri, logger.clone());
 
         let server = server_builder.build().unwrap();
 
@@ -215,6 +239,9 @@ where
     /// Recovery db.
     db: DB,
 
+    /// The range of blocks whose records are loaded into the enclave.
+    block_range: BlockRange,
+
     /// Join handle used to wait for the thread to terminate.
     join_handle: Option<JoinHandle<()>>,
 
@@ -248,6 +275,7 @@ where
     pub fn new(
         enclave: E,
         db: DB,
+        block_range: BlockRange,
         readiness_indicator: ReadinessIndicator,
         logger: Logger,
     ) -> Self {
@@ -257,6 +285,7 @@ where
         Self {
             enclave,
             db,
+            block_range,
             join_handle: None,
             stop_requested,
             shared_state,
@@ -276,6 +305,7 @@ where
 
         let thread_enclave = self.enclave.clone();
         let thread_db = self.db.clone();
+        let thread_block_range = self.block_range.clone();
         let thread_stop_requested = self.stop_requested.clone();
         let thread_shared_state = self.shared_state.clone();
         let thread_readiness_indicator = self.readiness_indicator.clone();
@@ -288,6 +318,7 @@ where
                     Self::thread_entrypoint(
                         thread_enclave,
                         thread_db,
+                        thread_block_range,
                         thread_stop_requested,
                         thread_shared_state,
                         thread_readiness_indicator,
@@ -311,6 +342,7 @@ where
     fn thread_entrypoint(
         enclave: E,
         db: DB,
+        block_range: BlockRange,
         stop_requested: Arc<AtomicBool>,
         shared_state: Arc<Mutex<DbPollSharedState>>,
         readiness_indicator: ReadinessIndicator,
@@ -322,6 +354,7 @@ where
             stop_requested,
             enclave,
             db,
+            block_range,
             shared_state,
             readiness_indicator,
             logger.clone(),
@@ -404,6 +437,7 @@ where
         stop_requested: Arc<AtomicBool>,
         enclave: E,
         db: DB,
+        block_range: BlockRange,
         shared_state: Arc<Mutex<DbPollSharedState>>,
         readiness_indicator: ReadinessIndicator,
         logger: Logger,
@@ -413,8 +447,13 @@ where
             enclave,
             db: db.clone(),
             shared_state,
-            db_fetcher: DbFetcher::new(db, readiness_indicator, logger.clone()),
-            enclave_block_tracker: BlockTracker::new(logger.clone()),
+            db_fetcher: DbFetcher::new(
+                db,
+                block_range.clone(),
+                readiness_indicator,
+                logger.clone(),
+            ),
+            enclave_block_tracker: BlockTracker::new(block_range, logger.clone()),
             last_unblocked_at: Instant::now(),
             logger,
         }
@@ -594,6 +633,24 @@ where
                 // Update metrics
                 counters::BLOCKS_ADDED_COUNT.inc();
                 counters::TXOS_ADDED_COUNT.inc_by(num_records as u64);
+                self.update_store_metrics();
+            }
+        }
+    }
+
+    /// Report the size of the enclave's store of records
+    fn update_store_metrics(&self) {
+        match self.enclave.get_store_stats() {
+            Ok(stats) => {
+                let len = stats.len + stats.records_to_migrate;
+                counters::ENCLAVE_OMAP_CAPACITY.set(stats.capacity as i64);
+                counters::ENCLAVE_OMAP_LEN.set(len as i64);
+                counters::ENCLAVE_OMAP_LOAD_FACTOR_PERCENT
+                    .set((len * 100 / core::cmp::max(stats.capacity, 1)) as i64);
+                counters::ENCLAVE_OMAP_RECORDS_TO_MIGRATE.set(stats.records_to_migrate as i64);
+            }
+            Err(err) => {
+                log::warn!(self.logger, "Failed getting enclave store stats: {}", err);
             }
         }
     }
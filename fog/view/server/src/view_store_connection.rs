@@ -0,0 +1,58 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Attested connections from a fog view router to fog view stores.
+
+use core::marker::PhantomData;
+use grpcio::{Channel, ClientUnaryReceiver, Result as GrpcResult};
+use mc_attest_api::attest::Message;
+use mc_attest_core::VerificationReport;
+use mc_attest_enclave_api::{PeerAuthRequest, PeerAuthResponse, PeerSession};
+use mc_common::ResponderId;
+use mc_fog_api::{view::StoreQueryResponse, view_grpc::FogViewStoreApiClient};
+use mc_fog_store_connection::{StoreApi, StoreConnection, StoreConnectionPool};
+use mc_fog_uri::FogViewStoreUri;
+use mc_fog_view_enclave::{Error as ViewEnclaveError, ViewEnclaveProxy};
+
+/// The fog view store API, as used by a router whose enclave is `E`.
+pub struct ViewStoreApi<E: ViewEnclaveProxy>(PhantomData<E>);
+
+impl<E: ViewEnclaveProxy> StoreApi for ViewStoreApi<E> {
+    type Enclave = E;
+    type EnclaveError = ViewEnclaveError;
+    type Uri = FogViewStoreUri;
+    type Client = FogViewStoreApiClient;
+    type Response = StoreQueryResponse;
+
+    fn peer_init(enclave: &E, peer_id: &ResponderId) -> Result<PeerAuthRequest, ViewEnclaveError> {
+        enclave.peer_init(peer_id)
+    }
+
+    fn peer_connect(
+        enclave: &E,
+        peer_id: &ResponderId,
+        msg: PeerAuthResponse,
+    ) -> Result<(PeerSession, VerificationReport), ViewEnclaveError> {
+        enclave.peer_connect(peer_id, msg)
+    }
+
+    fn peer_close(enclave: &E, session: &PeerSession) -> Result<(), ViewEnclaveError> {
+        enclave.peer_close(session)
+    }
+
+    fn new_client(channel: Channel) -> FogViewStoreApiClient {
+        FogViewStoreApiClient::new(channel)
+    }
+
+    fn query_async(
+        client: &FogViewStoreApiClient,
+        query: &Message,
+    ) -> GrpcResult<ClientUnaryReceiver<StoreQueryResponse>> {
+        client.query_async(query)
+    }
+}
+
+/// A connection to a fog view store.
+pub type ViewStoreConnection<E> = StoreConnection<ViewStoreApi<E>>;
+
+/// A pool of sets of connections to the fog view stores.
+pub type ViewStoreConnectionPool<E> = StoreConnectionPool<ViewStoreApi<E>>;
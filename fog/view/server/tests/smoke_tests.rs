@@ -71,6 +71,10 @@ fn get_test_environment(
             client_listen_uri: uri.clone(),
             client_auth_token_secret: None,
             omap_capacity: view_omap_capacity,
+            oram_storage_dir: None,
+            oram_storage_memory_bytes: 0,
+            shard_start_block: 0,
+            shard_end_block: None,
             ias_spid: Default::default(),
             ias_api_key: Default::default(),
             admin_listen_uri: Default::default(),
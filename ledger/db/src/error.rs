@@ -83,6 +83,9 @@ pub enum Error {
 
     /// DuplicateMintConfigTx
     DuplicateMintConfigTx,
+
+    /// Contents of block {0} were pruned, only blocks {1} and up are available
+    BlockContentsPruned(BlockIndex, BlockIndex),
 }
 
 impl From<lmdb::Error> for Error {
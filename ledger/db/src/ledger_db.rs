@@ -44,12 +44,17 @@ pub const BLOCK_NUMBER_BY_TX_OUT_INDEX: &str = "ledger_db:block_number_by_tx_out
 
 /// Keys used by the `counts` database.
 pub const NUM_BLOCKS_KEY: &str = "num_blocks";
+pub const NUM_PRUNED_BLOCKS_KEY: &str = "num_pruned_blocks";
 
 /// OpenTelemetry keys
 const TELEMETRY_BLOCK_INDEX_KEY: Key = telemetry_static_key!("block-index");
 const TELEMETRY_NUM_KEY_IMAGES_KEY: Key = telemetry_static_key!("num-key-images");
 const TELEMETRY_NUM_TXOS_KEY: Key = telemetry_static_key!("num-txos");
 
+/// Returns the index of the first block whose contents must be kept regardless
+/// of the pruning horizon, e.g. because they have not been scanned yet.
+pub type PruningLimit = Arc<dyn Fn() -> BlockIndex + Send + Sync>;
+
 /// Metadata store settings that are used for version control.
 #[derive(Clone, Default, Debug)]
 pub struct LedgerDbMetadataStoreSettings;
@@ -86,6 +91,14 @@ pub struct TxOutsByBlockValue {
 pub struct KeyImageList {
     #[prost(message, repeated, tag = "1")]
     pub key_images: Vec<KeyImage>,
+
+    /// The number of transactions in the block that spent these key images.
+    #[prost(uint64, tag = "2")]
+    pub num_transactions: u64,
+
+    /// The loads of the fee schedule's window ending with that block.
+    #[prost(uint64, repeated, tag = "3")]
+    pub recent_block_loads: Vec<u64>,
 }
 
 #[derive(Clone)]
@@ -94,6 +107,8 @@ pub struct LedgerDB {
 
     /// Aggregate counts about the ledger.
     /// * `NUM_BLOCKS_KEY` --> number of blocks in the ledger.
+    /// * `NUM_PRUNED_BLOCKS_KEY` --> number of blocks, starting at the origin
+    ///   block, whose contents have been pruned. Absent if nothing was pruned.
     counts: Database,
 
     /// Blocks by block number. `block number -> Block`
@@ -126,6 +141,13 @@ pub struct LedgerDB {
     /// Storage abstraction for mint transactions.
     mint_tx_store: MintTxStore,
 
+    /// When set, the contents of all but the latest `pruning_horizon` blocks
+    /// are pruned as new blocks are appended.
+    pruning_horizon: Option<u64>,
+
+    /// When set, blocks at or above the index it returns are never pruned.
+    pruning_limit: Option<PruningLimit>,
+
     /// Location on filesystem.
     path: PathBuf,
 
@@ -166,7 +188,13 @@ impl Ledger for LedgerDB {
         self.validate_append_block(block, block_contents, &db_transaction)?;
 
         // Write key images included in block.
-        self.write_key_images(block.index, &block_contents.key_images, &mut db_transaction)?;
+        self.write_key_images(
+            block.index,
+            &block_contents.key_images,
+            block_contents.num_transactions,
+            &block_contents.recent_block_loads,
+            &mut db_transaction,
+        )?;
 
         // Write information about TxOuts included in block.
         self.write_tx_outs(block.index, &block_contents.outputs, &mut db_transaction)?;
@@ -191,6 +219,14 @@ impl Ledger for LedgerDB {
         // Write block.
         self.write_block(block, signature.as_ref(), &mut db_transaction)?;
 
+        // Prune contents of blocks that fell out of the pruning horizon.
+        if let Some(pruning_horizon) = self.pruning_horizon {
+            self.prune_block_contents_impl(
+                (block.index + 1).saturating_sub(pruning_horizon),
+                &mut db_transaction,
+            )?;
+        }
+
         // Commit.
         db_transaction.commit()?;
 
@@ -311,11 +347,21 @@ impl Ledger for LedgerDB {
     /// Gets the KeyImages used by transactions in a single Block.
     fn get_key_images_by_block(&self, block_number: BlockIndex) -> Result<Vec<KeyImage>, Error> {
         let db_transaction = self.env.begin_ro_txn()?;
+        self.check_block_contents_not_pruned(&db_transaction, block_number)?;
         let key_image_list: KeyImageList =
             decode(db_transaction.get(self.key_images_by_block, &u64_to_key_bytes(block_number))?)?;
         Ok(key_image_list.key_images)
     }
 
+    /// Gets the number of transactions in a single block.
+    fn get_num_transactions_by_block(&self, block_number: BlockIndex) -> Result<u64, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        self.check_block_contents_not_pruned(&db_transaction, block_number)?;
+        let key_image_list: KeyImageList =
+            decode(db_transaction.get(self.key_images_by_block, &u64_to_key_bytes(block_number))?)?;
+        Ok(key_image_list.num_transactions)
+    }
+
     /// Gets a proof of memberships for TxOuts with indexes `indexes`.
     fn get_tx_out_proof_of_memberships(
         &self,
@@ -446,6 +492,8 @@ impl LedgerDB {
             tx_out_store,
             mint_config_store,
             mint_tx_store,
+            pruning_horizon: None,
+            pruning_limit: None,
             metrics,
         };
 
@@ -505,6 +553,131 @@ impl LedgerDB {
         Ok(())
     }
 
+    /// Get the pruning horizon, if pruning is enabled.
+    pub fn pruning_horizon(&self) -> Option<u64> {
+        self.pruning_horizon
+    }
+
+    /// Enable or disable pruning of block contents as new blocks are appended.
+    ///
+    /// With a pruning horizon of `n`, the contents of all but the latest `n`
+    /// blocks are discarded. Block headers and signatures, TxOuts and the TxOut
+    /// Merkle tree, and key images are always kept, so membership proofs, key
+    /// image checks and mixin selection keep working over the whole ledger.
+    /// Only `get_block_contents` and the calls built on top of it stop working
+    /// for pruned blocks, returning `Error::BlockContentsPruned`.
+    ///
+    /// Blocks that are already beyond the horizon are pruned when the next
+    /// block is appended, or by calling `prune_block_contents`.
+    ///
+    /// Since TxOuts and key images are kept, pruning only drops the per-block
+    /// lists of TxOut indices and key images, and mint transactions. That is
+    /// a small fraction of the ledger, most of which is TxOuts. LMDB also never
+    /// shrinks its file: the freed pages are reused by blocks appended
+    /// later, so pruning slows down the growth of the file rather than
+    /// reducing its size. Copying the environment with compaction
+    /// (e.g. `mdb_copy -c`) is needed to reclaim the space on disk.
+    pub fn set_pruning_horizon(&mut self, pruning_horizon: Option<u64>) {
+        self.pruning_horizon = pruning_horizon;
+    }
+
+    /// Set a limit on pruning, which is consulted every time blocks are
+    /// pruned. The contents of blocks at or above the index it returns are kept
+    /// even if they are beyond the pruning horizon, so that consumers which
+    /// still need to read them are not left behind.
+    pub fn set_pruning_limit(&mut self, pruning_limit: Option<PruningLimit>) {
+        self.pruning_limit = pruning_limit;
+    }
+
+    /// Get the number of blocks, starting at the origin block, whose contents
+    /// have been pruned.
+    pub fn num_pruned_blocks(&self) -> Result<u64, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        self.num_pruned_blocks_impl(&db_transaction)
+    }
+
+    /// Prune the contents of all but the latest `pruning_horizon` blocks, and
+    /// none at or above the pruning limit. Pruning is never undone, so calling
+    /// this with a larger horizon than was previously used has no effect.
+    ///
+    /// Returns the number of pruned blocks.
+    pub fn prune_block_contents(&self, pruning_horizon: u64) -> Result<u64, Error> {
+        let mut db_transaction = self.env.begin_rw_txn()?;
+        let num_blocks = key_bytes_to_u64(db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+        let num_pruned_blocks = self.prune_block_contents_impl(
+            num_blocks.saturating_sub(pruning_horizon),
+            &mut db_transaction,
+        )?;
+        db_transaction.commit()?;
+        Ok(num_pruned_blocks)
+    }
+
+    /// Prune the contents of all blocks below `num_blocks_to_prune`, up to the
+    /// pruning limit, and record the new boundary.
+    ///
+    /// Returns the number of pruned blocks.
+    fn prune_block_contents_impl(
+        &self,
+        num_blocks_to_prune: u64,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<u64, Error> {
+        let num_blocks_to_prune = match self.pruning_limit.as_ref() {
+            Some(pruning_limit) => num_blocks_to_prune.min(pruning_limit()),
+            None => num_blocks_to_prune,
+        };
+        let num_pruned_blocks = self.num_pruned_blocks_impl(db_transaction)?;
+        if num_blocks_to_prune <= num_pruned_blocks {
+            return Ok(num_pruned_blocks);
+        }
+
+        for block_index in num_pruned_blocks..num_blocks_to_prune {
+            let key = u64_to_key_bytes(block_index);
+
+            // TxOuts themselves stay in the TxOutStore, only the per-block index is
+            // removed. The same goes for key images.
+            db_transaction.del(self.tx_outs_by_block, &key, None)?;
+            db_transaction.del(self.key_images_by_block, &key, None)?;
+
+            self.mint_tx_store
+                .prune_mint_txs_by_block_index(block_index, db_transaction)?;
+            self.mint_config_store
+                .prune_validated_mint_config_txs_by_block_index(block_index, db_transaction)?;
+        }
+
+        db_transaction.put(
+            self.counts,
+            &NUM_PRUNED_BLOCKS_KEY,
+            &u64_to_key_bytes(num_blocks_to_prune),
+            WriteFlags::empty(),
+        )?;
+
+        Ok(num_blocks_to_prune)
+    }
+
+    /// Implementation of the `num_pruned_blocks` method that operates inside a
+    /// given transaction.
+    fn num_pruned_blocks_impl(&self, db_transaction: &impl Transaction) -> Result<u64, Error> {
+        match db_transaction.get(self.counts, &NUM_PRUNED_BLOCKS_KEY) {
+            Ok(bytes) => Ok(key_bytes_to_u64(bytes)),
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(e) => Err(Error::Lmdb(e)),
+        }
+    }
+
+    /// Returns `Error::BlockContentsPruned` if the contents of the given block
+    /// have been pruned.
+    fn check_block_contents_not_pruned(
+        &self,
+        db_transaction: &impl Transaction,
+        block_number: u64,
+    ) -> Result<(), Error> {
+        let num_pruned_blocks = self.num_pruned_blocks_impl(db_transaction)?;
+        if block_number < num_pruned_blocks {
+            return Err(Error::BlockContentsPruned(block_number, num_pruned_blocks));
+        }
+        Ok(())
+    }
+
     /// Write a `Block`.
     fn write_block(
         &self,
@@ -545,6 +718,8 @@ impl LedgerDB {
         &self,
         block_index: u64,
         key_images: &[KeyImage],
+        num_transactions: u64,
+        recent_block_loads: &[u64],
         db_transaction: &mut RwTransaction,
     ) -> Result<(), Error> {
         // Update Key Images
@@ -562,6 +737,8 @@ impl LedgerDB {
 
         let key_image_list = KeyImageList {
             key_images: key_images.to_vec(),
+            num_transactions,
+            recent_block_loads: recent_block_loads.to_vec(),
         };
         db_transaction.put(
             self.key_images_by_block,
@@ -772,6 +949,8 @@ impl LedgerDB {
         db_transaction: &impl Transaction,
         block_number: u64,
     ) -> Result<BlockContents, Error> {
+        self.check_block_contents_not_pruned(db_transaction, block_number)?;
+
         // Get all TxOuts in block.
         let bytes = db_transaction.get(self.tx_outs_by_block, &u64_to_key_bytes(block_number))?;
         let value: TxOutsByBlockValue = decode(bytes)?;
@@ -803,6 +982,8 @@ impl LedgerDB {
             outputs,
             validated_mint_config_txs,
             mint_txs,
+            num_transactions: key_image_list.num_transactions,
+            recent_block_loads: key_image_list.recent_block_loads,
         })
     }
 
@@ -884,6 +1065,7 @@ mod ledger_db_test {
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
     use rand_core::RngCore;
+    use std::sync::atomic::{AtomicU64, Ordering};
     use tempdir::TempDir;
     use test::Bencher;
 
@@ -2481,6 +2663,123 @@ mod ledger_db_test {
         }
     }
 
+    #[test]
+    // Pruning discards block contents, but keeps everything needed for proofs
+    // of membership, key image checks and mixin selection.
+    fn test_prune_block_contents() {
+        let mut ledger_db = create_db();
+        let n_blocks = 10;
+        let num_outputs_per_block = 2;
+        let (_, expected_block_contents) =
+            populate_db(&mut ledger_db, n_blocks, num_outputs_per_block);
+        assert_eq!(ledger_db.num_pruned_blocks().unwrap(), 0);
+
+        assert_eq!(ledger_db.prune_block_contents(3).unwrap(), 7);
+        assert_eq!(ledger_db.num_pruned_blocks().unwrap(), 7);
+
+        for block_index in 0..7 {
+            assert_eq!(
+                ledger_db.get_block_contents(block_index),
+                Err(Error::BlockContentsPruned(block_index, 7))
+            );
+            assert_eq!(
+                ledger_db.get_key_images_by_block(block_index),
+                Err(Error::BlockContentsPruned(block_index, 7))
+            );
+
+            // Block headers are kept.
+            assert_eq!(ledger_db.get_block(block_index).unwrap().index, block_index);
+        }
+        for block_index in 7..n_blocks {
+            assert_eq!(
+                ledger_db.get_block_contents(block_index).unwrap(),
+                expected_block_contents[block_index as usize]
+            );
+        }
+
+        // Key images of pruned blocks are still known.
+        for block_index in 1..n_blocks {
+            assert_eq!(
+                ledger_db
+                    .check_key_image(&KeyImage::from(block_index))
+                    .unwrap(),
+                Some(block_index)
+            );
+        }
+
+        // TxOuts and their proofs of membership are still available.
+        let num_txos = n_blocks * num_outputs_per_block;
+        assert_eq!(ledger_db.num_txos().unwrap(), num_txos);
+        for (tx_out_index, tx_out) in expected_block_contents
+            .iter()
+            .flat_map(|block_contents| block_contents.outputs.iter())
+            .enumerate()
+        {
+            assert_eq!(
+                &ledger_db.get_tx_out_by_index(tx_out_index as u64).unwrap(),
+                tx_out
+            );
+        }
+        let indexes: Vec<u64> = (0..num_txos).collect();
+        let root_element = ledger_db.get_root_tx_out_membership_element().unwrap();
+        for proof in ledger_db.get_tx_out_proof_of_memberships(&indexes).unwrap() {
+            assert_eq!(compute_implied_merkle_root(&proof).unwrap(), root_element);
+        }
+
+        // Pruning is never undone.
+        assert_eq!(ledger_db.prune_block_contents(5).unwrap(), 7);
+        assert_eq!(ledger_db.num_pruned_blocks().unwrap(), 7);
+    }
+
+    #[test]
+    // With a pruning horizon set, contents are pruned as blocks are appended.
+    fn test_pruning_horizon() {
+        let mut ledger_db = create_db();
+        ledger_db.set_pruning_horizon(Some(4));
+        assert_eq!(ledger_db.pruning_horizon(), Some(4));
+
+        let n_blocks = 10;
+        let (_, expected_block_contents) = populate_db(&mut ledger_db, n_blocks, 1);
+        assert_eq!(ledger_db.num_pruned_blocks().unwrap(), 6);
+
+        assert_eq!(
+            ledger_db.get_block_contents(5),
+            Err(Error::BlockContentsPruned(5, 6))
+        );
+        assert_eq!(
+            ledger_db.get_block_contents(6).unwrap(),
+            expected_block_contents[6]
+        );
+
+        // Blocks past the end of the ledger are still not found.
+        assert_eq!(ledger_db.get_block_contents(n_blocks), Err(Error::NotFound));
+    }
+
+    #[test]
+    // Blocks at or above the pruning limit are kept, both when appending and when
+    // pruning explicitly.
+    fn test_pruning_limit() {
+        let mut ledger_db = create_db();
+        let pruning_limit = Arc::new(AtomicU64::new(3));
+        let limit = pruning_limit.clone();
+        ledger_db.set_pruning_limit(Some(Arc::new(move || limit.load(Ordering::SeqCst))));
+        ledger_db.set_pruning_horizon(Some(2));
+
+        let n_blocks = 10;
+        let (_, expected_block_contents) = populate_db(&mut ledger_db, n_blocks, 1);
+        assert_eq!(ledger_db.num_pruned_blocks().unwrap(), 3);
+        assert_eq!(
+            ledger_db.get_block_contents(3).unwrap(),
+            expected_block_contents[3]
+        );
+
+        assert_eq!(ledger_db.prune_block_contents(0).unwrap(), 3);
+
+        // Once the limit moves up, pruning catches up to the horizon.
+        pruning_limit.store(n_blocks, Ordering::SeqCst);
+        assert_eq!(ledger_db.prune_block_contents(2).unwrap(), 8);
+    }
+
     #[test]
     // Getting a block by its index should return an error if the block doesn't
     // exist.
@@ -2623,6 +2922,47 @@ mod ledger_db_test {
         assert_eq!(key_images, returned_key_images);
     }
 
+    #[test]
+    // `get_num_transactions_by_block` should return the number of transactions
+    // recorded in a block's contents.
+    fn test_get_num_transactions_by_block() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+
+        // Populate the ledger with some initial blocks.
+        let n_blocks = 3;
+        populate_db(&mut ledger_db, n_blocks, 2);
+        assert_eq!(ledger_db.get_num_transactions_by_block(1).unwrap(), 0);
+
+        // Append a block with fewer transactions than key images.
+        let key_images: Vec<KeyImage> = (0..5).map(|_i| KeyImage::from(rng.next_u64())).collect();
+        let block_contents = BlockContents {
+            key_images,
+            outputs: vec![create_test_tx_out(&mut rng)],
+            num_transactions: 2,
+            recent_block_loads: vec![0, 0, 0, 2],
+            ..Default::default()
+        };
+        let parent = ledger_db.get_block(n_blocks - 1).unwrap();
+        let block =
+            Block::new_with_parent(BLOCK_VERSION, &parent, &Default::default(), &block_contents);
+
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+
+        assert_eq!(
+            ledger_db
+                .get_num_transactions_by_block(block.index)
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            ledger_db.get_block_contents(block.index).unwrap(),
+            block_contents
+        );
+    }
+
     #[test]
     /// Attempting to append an empty block should return Error::NoOutputs.
     fn test_append_empty_block() {
@@ -80,6 +80,11 @@ pub trait Ledger: Send {
     /// Gets the key images used by transactions in a single block.
     fn get_key_images_by_block(&self, block_number: BlockIndex) -> Result<Vec<KeyImage>, Error>;
 
+    /// Gets the number of transactions in a single block.
+    fn get_num_transactions_by_block(&self, block_number: BlockIndex) -> Result<u64, Error> {
+        Ok(self.get_block_contents(block_number)?.num_transactions)
+    }
+
     /// Get the tx out root membership element from the tx out Merkle Tree.
     fn get_root_tx_out_membership_element(&self) -> Result<TxOutMembershipElement, Error>;
 
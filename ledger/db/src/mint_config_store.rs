@@ -300,6 +300,23 @@ impl MintConfigStore {
         Ok(validated_mint_config_tx_list.validated_mint_config_txs)
     }
 
+    /// Remove the list of ValidatedMintConfigTxs stored for a given block.
+    /// Nonces and active configurations are kept.
+    pub fn prune_validated_mint_config_txs_by_block_index(
+        &self,
+        block_index: u64,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        match db_transaction.del(
+            self.validated_mint_config_txs_by_block,
+            &u64_to_key_bytes(block_index),
+            None,
+        ) {
+            Ok(()) | Err(lmdb::Error::NotFound) => Ok(()),
+            Err(e) => Err(Error::Lmdb(e)),
+        }
+    }
+
     /// Get mint configurations for a given token.
     pub fn get_active_mint_configs(
         &self,
@@ -118,6 +118,19 @@ impl MintTxStore {
         Ok(())
     }
 
+    /// Remove the list of mint txs stored for a given block. Nonces are kept,
+    /// since they are needed for rejecting replayed mint txs.
+    pub fn prune_mint_txs_by_block_index(
+        &self,
+        block_index: u64,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        match db_transaction.del(self.mint_txs_by_block, &u64_to_key_bytes(block_index), None) {
+            Ok(()) | Err(lmdb::Error::NotFound) => Ok(()),
+            Err(e) => Err(Error::Lmdb(e)),
+        }
+    }
+
     pub fn check_mint_tx_nonce(
         &self,
         nonce: &[u8],
@@ -0,0 +1,579 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+#![deny(missing_docs)]
+
+//! Ledger verification: re-check that a LedgerDB is internally consistent.
+//!
+//! Every block is checked for:
+//! * A valid block id, and a parent id matching the id of the previous block.
+//! * Contents that hash to the block's `contents_hash`, and a
+//!   `cumulative_txo_count` matching the number of TxOuts.
+//! * A `root_element` matching the root of the TxOut Merkle tree at the time
+//!   the block was formed.
+//! * A valid signature, by one of the known signers (if any were given).
+//! * Key images that appear in no other block.
+//!
+//! Checks that only depend on a single block run in parallel. The Merkle tree
+//! is rebuilt sequentially from the stored TxOuts, and its state is part of
+//! the `Checkpoint` so that verification can be resumed.
+
+mod merkle;
+
+pub use merkle::MerkleAccumulator;
+
+use displaydoc::Display;
+use mc_common::{
+    logger::{log, Logger},
+    HashSet,
+};
+use mc_crypto_keys::Ed25519Public;
+use mc_ledger_db::{Error as LedgerError, Ledger, LedgerDB};
+use mc_transaction_core::{
+    membership_proofs::hash_leaf, ring_signature::KeyImage, BlockID, BlockIndex,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A verification failure, with the block it was found in.
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+pub enum VerifyError {
+    /// Block {0}: ledger error: {1}
+    Ledger(BlockIndex, LedgerError),
+
+    /// Block {0}: stored with index {1}
+    WrongIndex(BlockIndex, BlockIndex),
+
+    /// Block {0}: invalid version {1}
+    InvalidVersion(BlockIndex, u32),
+
+    /// Block {0}: id {1} does not match the block header
+    InvalidBlockId(BlockIndex, BlockID),
+
+    /// Block {0}: parent id {1} does not match the previous block id {2}
+    InvalidParentId(BlockIndex, BlockID, BlockID),
+
+    /// Block {0}: contents do not match the contents hash
+    ContentsHashMismatch(BlockIndex),
+
+    /// Block {0}: cumulative TxOut count is {1}, expected {2}
+    CumulativeTxoCountMismatch(BlockIndex, u64, u64),
+
+    /// Block {0}: TxOut {1} is indexed as belonging to block {2}
+    TxOutBlockIndexMismatch(BlockIndex, u64, BlockIndex),
+
+    /// Block {0}: root element does not match the TxOut Merkle tree
+    RootElementMismatch(BlockIndex),
+
+    /// Block {0}: invalid signature
+    InvalidSignature(BlockIndex),
+
+    /// Block {0}: signed by unknown signer {1:?}
+    UnknownSigner(BlockIndex, Ed25519Public),
+
+    /// Block {0}: key image {1:?} was already spent in block {2}
+    DuplicateKeyImage(BlockIndex, KeyImage, BlockIndex),
+
+    /// Block {0}: key image {1:?} is missing from the key image index
+    MissingKeyImage(BlockIndex, KeyImage),
+
+    /// Ledger has {0} TxOuts, but the last block accounts for {1}
+    NumTxOutsMismatch(u64, u64),
+
+    /// Checkpoint at block {0} does not match the ledger: {1}
+    InvalidCheckpoint(BlockIndex, String),
+}
+
+impl VerifyError {
+    /// The index of the block the failure was found in, if any.
+    pub fn block_index(&self) -> Option<BlockIndex> {
+        match self {
+            Self::Ledger(index, _)
+            | Self::WrongIndex(index, _)
+            | Self::InvalidVersion(index, _)
+            | Self::InvalidBlockId(index, _)
+            | Self::InvalidParentId(index, _, _)
+            | Self::ContentsHashMismatch(index)
+            | Self::CumulativeTxoCountMismatch(index, _, _)
+            | Self::TxOutBlockIndexMismatch(index, _, _)
+            | Self::RootElementMismatch(index)
+            | Self::InvalidSignature(index)
+            | Self::UnknownSigner(index, _)
+            | Self::DuplicateKeyImage(index, _, _)
+            | Self::MissingKeyImage(index, _)
+            | Self::InvalidCheckpoint(index, _) => Some(*index),
+            Self::NumTxOutsMismatch(_, _) => None,
+        }
+    }
+}
+
+/// Verification settings.
+#[derive(Clone, Debug)]
+pub struct VerifyOptions {
+    /// Public keys of the nodes allowed to sign blocks. When empty, signatures
+    /// are checked for validity only.
+    pub known_signers: Vec<Ed25519Public>,
+
+    /// Accept blocks with a default root element, as found in ledgers that
+    /// were generated for testing.
+    pub allow_default_root_element: bool,
+
+    /// Number of blocks verified between checkpoints.
+    pub chunk_size: u64,
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        Self {
+            known_signers: Vec::new(),
+            allow_default_root_element: false,
+            chunk_size: 1000,
+        }
+    }
+}
+
+/// Verification progress. Blocks below `next_block` have been verified.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Checkpoint {
+    /// The next block to verify.
+    pub next_block: BlockIndex,
+
+    /// The TxOut Merkle tree of all verified blocks.
+    pub merkle: MerkleAccumulator,
+
+    /// Number of verified blocks whose contents were pruned, and so could only
+    /// be partially verified.
+    pub num_pruned_blocks: u64,
+
+    /// Number of verified blocks without a signature.
+    pub num_unsigned_blocks: u64,
+}
+
+/// What was learned about a block while checking it in isolation.
+struct BlockReport {
+    pruned: bool,
+    signed: bool,
+}
+
+/// Verify the ledger from `checkpoint` to its end.
+///
+/// `on_checkpoint` is called after every chunk of `options.chunk_size`
+/// blocks. On failure, the earliest failure found is returned.
+pub fn verify_ledger(
+    ledger: &LedgerDB,
+    options: &VerifyOptions,
+    mut checkpoint: Checkpoint,
+    mut on_checkpoint: impl FnMut(&Checkpoint),
+    logger: &Logger,
+) -> Result<Checkpoint, VerifyError> {
+    let num_blocks = ledger
+        .num_blocks()
+        .map_err(|err| VerifyError::Ledger(checkpoint.next_block, err))?;
+
+    check_checkpoint(ledger, &checkpoint)?;
+
+    while checkpoint.next_block < num_blocks {
+        let start = checkpoint.next_block;
+        let end = num_blocks.min(start + options.chunk_size.max(1));
+
+        // Checks that only depend on the block itself run in parallel. Results are
+        // collected in order, so the first error is the earliest one.
+        let reports = (start..end)
+            .into_par_iter()
+            .map(|block_index| verify_block(ledger, block_index, options))
+            .collect::<Vec<_>>();
+        let first_error = reports.iter().position(|report| report.is_err());
+
+        // The Merkle tree can only be rebuilt in order. There is no point in going
+        // beyond the first block that already failed.
+        let merkle_end = first_error.map_or(end, |position| start + position as u64 + 1);
+        for block_index in start..merkle_end {
+            verify_root_element(ledger, block_index, &mut checkpoint.merkle, options)?;
+        }
+
+        for report in reports {
+            let report = report?;
+            checkpoint.next_block += 1;
+            checkpoint.num_pruned_blocks += report.pruned as u64;
+            checkpoint.num_unsigned_blocks += !report.signed as u64;
+        }
+
+        log::info!(
+            logger,
+            "Verified blocks {}-{} of {}",
+            start,
+            end - 1,
+            num_blocks
+        );
+        on_checkpoint(&checkpoint);
+    }
+
+    // Every TxOut in the ledger must belong to a block.
+    let num_tx_outs = ledger
+        .num_txos()
+        .map_err(|err| VerifyError::Ledger(checkpoint.next_block, err))?;
+    if num_tx_outs != checkpoint.merkle.num_leaves() {
+        return Err(VerifyError::NumTxOutsMismatch(
+            num_tx_outs,
+            checkpoint.merkle.num_leaves(),
+        ));
+    }
+
+    Ok(checkpoint)
+}
+
+/// Check that a checkpoint we are resuming from is consistent with the ledger.
+fn check_checkpoint(ledger: &LedgerDB, checkpoint: &Checkpoint) -> Result<(), VerifyError> {
+    if checkpoint.next_block == 0 {
+        return if checkpoint.merkle.num_leaves() == 0 {
+            Ok(())
+        } else {
+            Err(VerifyError::InvalidCheckpoint(
+                0,
+                "TxOuts recorded before the origin block".to_string(),
+            ))
+        };
+    }
+
+    let last_block_index = checkpoint.next_block - 1;
+    let last_block = ledger
+        .get_block(last_block_index)
+        .map_err(|err| VerifyError::Ledger(last_block_index, err))?;
+    if last_block.cumulative_txo_count != checkpoint.merkle.num_leaves() {
+        return Err(VerifyError::InvalidCheckpoint(
+            checkpoint.next_block,
+            format!(
+                "checkpoint has {} TxOuts, ledger has {}",
+                checkpoint.merkle.num_leaves(),
+                last_block.cumulative_txo_count
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Run the checks that only depend on a single block, its parent, and the
+/// key image index.
+fn verify_block(
+    ledger: &LedgerDB,
+    block_index: BlockIndex,
+    options: &VerifyOptions,
+) -> Result<BlockReport, VerifyError> {
+    let ledger_error = |err| VerifyError::Ledger(block_index, err);
+
+    let block = ledger.get_block(block_index).map_err(ledger_error)?;
+    if block.index != block_index {
+        return Err(VerifyError::WrongIndex(block_index, block.index));
+    }
+    if !block.is_block_id_valid() {
+        return Err(VerifyError::InvalidBlockId(block_index, block.id));
+    }
+
+    // The origin block is version 0, and has no parent.
+    let (parent_id, parent_version, parent_txo_count) = if block_index == 0 {
+        (BlockID::default(), 0, 0)
+    } else {
+        let parent = ledger.get_block(block_index - 1).map_err(ledger_error)?;
+        (parent.id, parent.version, parent.cumulative_txo_count)
+    };
+    if block.parent_id != parent_id {
+        return Err(VerifyError::InvalidParentId(
+            block_index,
+            block.parent_id,
+            parent_id,
+        ));
+    }
+    if block.version < parent_version || (block_index == 0 && block.version != 0) {
+        return Err(VerifyError::InvalidVersion(block_index, block.version));
+    }
+
+    let pruned = match ledger.get_block_contents(block_index) {
+        Ok(block_contents) => {
+            if block_contents.hash() != block.contents_hash {
+                return Err(VerifyError::ContentsHashMismatch(block_index));
+            }
+
+            let expected_txo_count = parent_txo_count + block_contents.outputs.len() as u64;
+            if block.cumulative_txo_count != expected_txo_count {
+                return Err(VerifyError::CumulativeTxoCountMismatch(
+                    block_index,
+                    block.cumulative_txo_count,
+                    expected_txo_count,
+                ));
+            }
+
+            // Each key image must be indexed as spent in this block. Otherwise it was
+            // either spent in another block too, or the index is missing it.
+            let mut seen_key_images = HashSet::default();
+            for key_image in block_contents.key_images {
+                if !seen_key_images.insert(key_image) {
+                    return Err(VerifyError::DuplicateKeyImage(
+                        block_index,
+                        key_image,
+                        block_index,
+                    ));
+                }
+                match ledger.check_key_image(&key_image).map_err(ledger_error)? {
+                    Some(spent_at) if spent_at == block_index => {}
+                    Some(spent_at) => {
+                        return Err(VerifyError::DuplicateKeyImage(
+                            block_index,
+                            key_image,
+                            spent_at,
+                        ))
+                    }
+                    None => return Err(VerifyError::MissingKeyImage(block_index, key_image)),
+                }
+            }
+
+            false
+        }
+        Err(LedgerError::BlockContentsPruned(_, _)) => true,
+        Err(err) => return Err(ledger_error(err)),
+    };
+
+    let signed = match ledger.get_block_signature(block_index) {
+        Ok(signature) => {
+            signature
+                .verify(&block)
+                .map_err(|_| VerifyError::InvalidSignature(block_index))?;
+            if !options.known_signers.is_empty()
+                && !options.known_signers.contains(signature.signer())
+            {
+                return Err(VerifyError::UnknownSigner(block_index, *signature.signer()));
+            }
+            true
+        }
+        Err(LedgerError::NotFound) => false,
+        Err(err) => return Err(ledger_error(err)),
+    };
+
+    Ok(BlockReport { pruned, signed })
+}
+
+/// Check a block's root element against the Merkle tree of all TxOuts before
+/// it, then add its own TxOuts to the tree.
+fn verify_root_element(
+    ledger: &LedgerDB,
+    block_index: BlockIndex,
+    merkle: &mut MerkleAccumulator,
+    options: &VerifyOptions,
+) -> Result<(), VerifyError> {
+    let ledger_error = |err| VerifyError::Ledger(block_index, err);
+
+    let block = ledger.get_block(block_index).map_err(ledger_error)?;
+
+    // The origin block has a default root element, since there were no TxOuts
+    // before it.
+    let expected_root_element = merkle.root_element().map_err(ledger_error)?;
+    let is_default_allowed =
+        options.allow_default_root_element && block.root_element == Default::default();
+    if block.root_element != expected_root_element && !is_default_allowed {
+        return Err(VerifyError::RootElementMismatch(block_index));
+    }
+
+    if block.cumulative_txo_count < merkle.num_leaves() {
+        return Err(VerifyError::CumulativeTxoCountMismatch(
+            block_index,
+            block.cumulative_txo_count,
+            merkle.num_leaves(),
+        ));
+    }
+
+    // TxOuts are read by index rather than through the block contents, so that
+    // this also works for pruned blocks.
+    for tx_out_index in merkle.num_leaves()..block.cumulative_txo_count {
+        let tx_out = ledger
+            .get_tx_out_by_index(tx_out_index)
+            .map_err(ledger_error)?;
+
+        let tx_out_block_index = ledger
+            .get_block_index_by_tx_out_index(tx_out_index)
+            .map_err(ledger_error)?;
+        if tx_out_block_index != block_index {
+            return Err(VerifyError::TxOutBlockIndexMismatch(
+                block_index,
+                tx_out_index,
+                tx_out_block_index,
+            ));
+        }
+
+        merkle.push(hash_leaf(&tx_out));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_keys::{Ed25519Pair, RistrettoPrivate};
+    use mc_ledger_db::test_utils::get_test_ledger_blocks;
+    use mc_transaction_core::{
+        tokens::Mob, tx::TxOut, Amount, Block, BlockContents, BlockSignature, BlockVersion, Token,
+    };
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    /// Creates a ledger with `num_blocks` blocks that have correct root
+    /// elements and are signed by `signer`.
+    fn create_ledger(num_blocks: u64, signer: &Ed25519Pair) -> LedgerDB {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+
+        let temp_dir = TempDir::new("test").unwrap();
+        LedgerDB::create(temp_dir.path()).unwrap();
+        let mut ledger = LedgerDB::open(temp_dir.path()).unwrap();
+
+        let mut parent: Option<Block> = None;
+        for block_index in 0..num_blocks {
+            let outputs: Vec<TxOut> = (0..(block_index % 3 + 1))
+                .map(|_| {
+                    let mut tx_out = TxOut::new(
+                        Amount::new(10, Mob::ID),
+                        &account_key.default_subaddress(),
+                        &RistrettoPrivate::from_random(&mut rng),
+                        Default::default(),
+                    )
+                    .unwrap();
+                    tx_out.e_memo = None;
+                    tx_out
+                })
+                .collect();
+            let block_contents = BlockContents {
+                key_images: if block_index > 0 {
+                    vec![KeyImage::from(block_index)]
+                } else {
+                    vec![]
+                },
+                outputs: outputs.clone(),
+                ..Default::default()
+            };
+            let block = match parent {
+                None => Block::new_origin_block(&outputs),
+                Some(parent) => Block::new_with_parent(
+                    BlockVersion::ZERO,
+                    &parent,
+                    &ledger.get_root_tx_out_membership_element().unwrap(),
+                    &block_contents,
+                ),
+            };
+            let signature = BlockSignature::from_block_and_keypair(&block, signer).unwrap();
+            ledger
+                .append_block(&block, &block_contents, Some(signature))
+                .unwrap();
+
+            parent = Some(block);
+        }
+
+        ledger
+    }
+
+    #[test_with_logger]
+    fn verify_consistent_ledger(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let ledger = create_ledger(25, &signer);
+
+        let options = VerifyOptions {
+            known_signers: vec![signer.public_key()],
+            chunk_size: 7,
+            ..Default::default()
+        };
+
+        let mut checkpoints = Vec::new();
+        let checkpoint = verify_ledger(
+            &ledger,
+            &options,
+            Checkpoint::default(),
+            |checkpoint| checkpoints.push(checkpoint.clone()),
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(checkpoint.next_block, 25);
+        assert_eq!(checkpoint.merkle.num_leaves(), ledger.num_txos().unwrap());
+        assert_eq!(checkpoint.num_pruned_blocks, 0);
+        assert_eq!(checkpoint.num_unsigned_blocks, 0);
+        assert_eq!(
+            checkpoints
+                .iter()
+                .map(|checkpoint| checkpoint.next_block)
+                .collect::<Vec<_>>(),
+            vec![7, 14, 21, 25]
+        );
+
+        // Resuming from an intermediate checkpoint reaches the same result.
+        let resumed =
+            verify_ledger(&ledger, &options, checkpoints[1].clone(), |_| {}, &logger).unwrap();
+        assert_eq!(resumed, checkpoint);
+    }
+
+    #[test_with_logger]
+    fn verify_pruned_ledger(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let ledger = create_ledger(10, &signer);
+        ledger.prune_block_contents(4).unwrap();
+
+        let checkpoint = verify_ledger(
+            &ledger,
+            &VerifyOptions::default(),
+            Checkpoint::default(),
+            |_| {},
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(checkpoint.next_block, 10);
+        assert_eq!(checkpoint.num_pruned_blocks, 6);
+    }
+
+    #[test_with_logger]
+    fn verify_reports_unknown_signer(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let other_signer = Ed25519Pair::from_random(&mut rng);
+        let ledger = create_ledger(5, &signer);
+
+        let options = VerifyOptions {
+            known_signers: vec![other_signer.public_key()],
+            ..Default::default()
+        };
+        assert_eq!(
+            verify_ledger(&ledger, &options, Checkpoint::default(), |_| {}, &logger),
+            Err(VerifyError::UnknownSigner(0, signer.public_key()))
+        );
+    }
+
+    #[test_with_logger]
+    fn verify_reports_default_root_element(logger: Logger) {
+        // Test ledgers leave the root element at its default value.
+        let temp_dir = TempDir::new("test").unwrap();
+        LedgerDB::create(temp_dir.path()).unwrap();
+        let mut ledger = LedgerDB::open(temp_dir.path()).unwrap();
+        for (block, block_contents) in get_test_ledger_blocks(3) {
+            ledger.append_block(&block, &block_contents, None).unwrap();
+        }
+
+        assert_eq!(
+            verify_ledger(
+                &ledger,
+                &VerifyOptions::default(),
+                Checkpoint::default(),
+                |_| {},
+                &logger
+            ),
+            Err(VerifyError::RootElementMismatch(1))
+        );
+
+        let options = VerifyOptions {
+            allow_default_root_element: true,
+            ..Default::default()
+        };
+        let checkpoint =
+            verify_ledger(&ledger, &options, Checkpoint::default(), |_| {}, &logger).unwrap();
+        assert_eq!(checkpoint.next_block, 3);
+        assert_eq!(checkpoint.num_unsigned_blocks, 3);
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+#![deny(missing_docs)]
+
+//! Ledger verification: Check that a LedgerDB is internally consistent, from
+//! the origin block to its last block.
+
+use clap::Parser;
+use mc_common::logger::{create_app_logger, log, o};
+use mc_crypto_keys::{DistinguishedEncoding, Ed25519Public};
+use mc_ledger_db::LedgerDB;
+use mc_ledger_verify::{verify_ledger, Checkpoint, VerifyOptions};
+use std::{fs, path::PathBuf, process::exit, thread::sleep, time::Duration};
+
+/// Command line configuration
+#[derive(Clone, Debug, Parser)]
+pub struct Config {
+    /// Ledger DB path.
+    #[clap(long, parse(from_os_str), env = "MC_LEDGER_DB")]
+    pub ledger_db: PathBuf,
+
+    /// PEM file with the public key of a node that is allowed to sign blocks.
+    /// May be given multiple times. When omitted, block signatures are only
+    /// checked for validity.
+    #[clap(long = "signer", parse(try_from_str = load_signer), env = "MC_SIGNERS", use_value_delimiter = true)]
+    pub signers: Vec<Ed25519Public>,
+
+    /// File to store progress in. If it exists, verification resumes from it.
+    #[clap(long, parse(from_os_str), env = "MC_CHECKPOINT")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Number of blocks to verify between checkpoints.
+    #[clap(long, default_value = "1000", env = "MC_CHUNK_SIZE")]
+    pub chunk_size: u64,
+
+    /// Number of threads to verify blocks with. Defaults to the number of
+    /// logical CPU cores.
+    #[clap(long, env = "MC_NUM_THREADS")]
+    pub num_threads: Option<usize>,
+
+    /// Accept blocks with a default root element, as found in ledgers that
+    /// were generated for testing.
+    #[clap(long, env = "MC_ALLOW_DEFAULT_ROOT_ELEMENT")]
+    pub allow_default_root_element: bool,
+}
+
+/// Load an Ed25519 public key from a PEM file.
+fn load_signer(filename: &str) -> Result<Ed25519Public, String> {
+    let bytes =
+        fs::read(filename).map_err(|err| format!("Failed reading file '{}': {}", filename, err))?;
+
+    let parsed_pem = pem::parse(&bytes)
+        .map_err(|err| format!("Failed parsing PEM file '{}': {}", filename, err))?;
+
+    Ed25519Public::try_from_der(&parsed_pem.contents[..])
+        .map_err(|err| format!("Failed parsing DER from PEM file '{}': {}", filename, err))
+}
+
+fn main() {
+    let config = Config::parse();
+
+    mc_common::setup_panic_handler();
+    let _sentry_guard = mc_common::sentry::init();
+    let (logger, _global_logger_guard) = create_app_logger(o!());
+
+    if let Some(num_threads) = config.num_threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build_global()
+            .expect("Failed configuring thread pool");
+    }
+
+    let ledger_db = LedgerDB::open(&config.ledger_db)
+        .unwrap_or_else(|err| panic!("Failed opening ledger db {:?}: {}", config.ledger_db, err));
+
+    let checkpoint = match &config.checkpoint {
+        Some(path) if path.exists() => {
+            let bytes = fs::read(path)
+                .unwrap_or_else(|err| panic!("Failed reading checkpoint {:?}: {}", path, err));
+            let checkpoint: Checkpoint = serde_json::from_slice(&bytes)
+                .unwrap_or_else(|err| panic!("Failed parsing checkpoint {:?}: {}", path, err));
+            log::info!(
+                logger,
+                "Resuming verification from block {}",
+                checkpoint.next_block
+            );
+            checkpoint
+        }
+        _ => Checkpoint::default(),
+    };
+
+    let options = VerifyOptions {
+        known_signers: config.signers.clone(),
+        allow_default_root_element: config.allow_default_root_element,
+        chunk_size: config.chunk_size,
+    };
+
+    let write_checkpoint = |checkpoint: &Checkpoint| {
+        if let Some(path) = &config.checkpoint {
+            // Write to a temporary file first, so that a crash never leaves a
+            // truncated checkpoint behind.
+            let tmp_path = path.with_extension("tmp");
+            let bytes = serde_json::to_vec(checkpoint).expect("Failed serializing checkpoint");
+            fs::write(&tmp_path, bytes)
+                .and_then(|_| fs::rename(&tmp_path, path))
+                .unwrap_or_else(|err| panic!("Failed writing checkpoint {:?}: {}", path, err));
+        }
+    };
+
+    let exit_code = match verify_ledger(&ledger_db, &options, checkpoint, write_checkpoint, &logger)
+    {
+        Ok(checkpoint) => {
+            log::info!(
+                logger,
+                "Ledger is consistent: num_blocks={} num_txos={} num_pruned_blocks={} num_unsigned_blocks={}",
+                checkpoint.next_block,
+                checkpoint.merkle.num_leaves(),
+                checkpoint.num_pruned_blocks,
+                checkpoint.num_unsigned_blocks
+            );
+            0
+        }
+        Err(err) => {
+            log::crit!(logger, "Ledger verification failed: {}", err);
+            if let Some(block_index) = err.block_index() {
+                log::crit!(
+                    logger,
+                    "Blocks before {} were verified successfully",
+                    block_index
+                );
+            }
+            1
+        }
+    };
+
+    // Give logger a moment to flush.
+    sleep(Duration::from_secs(1));
+    exit(exit_code);
+}
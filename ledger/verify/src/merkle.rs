@@ -0,0 +1,170 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! An append-only TxOut Merkle tree that only keeps the hashes it needs for
+//! computing the root, so that historical roots can be recomputed while
+//! streaming through the ledger.
+
+use mc_ledger_db::Error;
+use mc_transaction_core::{
+    membership_proofs::{hash_nodes, Range, NIL_HASH},
+    tx::TxOutMembershipElement,
+};
+use serde::{Deserialize, Serialize};
+
+/// Hashes of the complete subtrees on the right edge of a Merkle tree.
+///
+/// The tree has the same shape as the one maintained by `TxOutStore`: the
+/// leaves are padded to the next power of two, and subtrees that contain no
+/// leaves hash to `NIL_HASH`.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MerkleAccumulator {
+    /// Number of leaves in the tree.
+    num_leaves: u64,
+
+    /// `frontier[k]` is the hash of the complete subtree with `2^k` leaves
+    /// that ends right before the leaves of all smaller complete subtrees. It
+    /// is only meaningful when bit `k` of `num_leaves` is set.
+    frontier: Vec<[u8; 32]>,
+}
+
+impl MerkleAccumulator {
+    /// Number of leaves in the tree.
+    pub fn num_leaves(&self) -> u64 {
+        self.num_leaves
+    }
+
+    /// Append a leaf hash.
+    pub fn push(&mut self, leaf_hash: [u8; 32]) {
+        // Merge complete subtrees of equal size, like carrying in binary addition.
+        let mut hash = leaf_hash;
+        let mut level = 0;
+        while self.num_leaves & (1 << level) != 0 {
+            hash = hash_nodes(&self.frontier[level], &hash);
+            level += 1;
+        }
+
+        if level == self.frontier.len() {
+            self.frontier.push(hash);
+        } else {
+            self.frontier[level] = hash;
+        }
+        self.num_leaves += 1;
+    }
+
+    /// The root element of the tree, as it would be returned by
+    /// `Ledger::get_root_tx_out_membership_element`. An empty tree has a
+    /// default root element, like the origin block.
+    pub fn root_element(&self) -> Result<TxOutMembershipElement, Error> {
+        if self.num_leaves == 0 {
+            return Ok(TxOutMembershipElement::default());
+        }
+
+        let num_leaves_full_tree = self
+            .num_leaves
+            .checked_next_power_of_two()
+            .ok_or(Error::CapacityExceeded)?;
+        let num_levels = num_leaves_full_tree.trailing_zeros() as usize;
+
+        // Walk up the right edge of the tree. `partial` is the hash of the
+        // rightmost subtree at the current level that contains some, but not
+        // all, of its leaves.
+        let mut partial: Option<[u8; 32]> = None;
+        for level in 0..num_levels {
+            partial = if self.num_leaves & (1 << level) != 0 {
+                // A complete subtree on the left, and whatever is left on the right.
+                Some(hash_nodes(
+                    &self.frontier[level],
+                    &partial.unwrap_or(*NIL_HASH),
+                ))
+            } else {
+                // Everything is on the left, and the right subtree is empty.
+                partial.map(|hash| hash_nodes(&hash, &NIL_HASH))
+            };
+        }
+
+        // When the tree is full, the root is a complete subtree.
+        let root_hash = partial.unwrap_or(self.frontier[num_levels]);
+        let range = Range::new(0, num_leaves_full_tree - 1)?;
+        Ok(TxOutMembershipElement::new(range, root_hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_account_keys::AccountKey;
+    use mc_crypto_keys::RistrettoPrivate;
+    use mc_ledger_db::{Ledger, LedgerDB};
+    use mc_transaction_core::{
+        membership_proofs::hash_leaf, tokens::Mob, tx::TxOut, Amount, Block, BlockContents,
+        BlockVersion, Token,
+    };
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    #[test]
+    // The accumulator's root matches the ledger's after every block.
+    fn root_element_matches_ledger() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+
+        let temp_dir = TempDir::new("test").unwrap();
+        LedgerDB::create(temp_dir.path()).unwrap();
+        let mut ledger = LedgerDB::open(temp_dir.path()).unwrap();
+
+        let mut accumulator = MerkleAccumulator::default();
+        assert_eq!(
+            accumulator.root_element().unwrap(),
+            TxOutMembershipElement::default()
+        );
+
+        let mut parent: Option<Block> = None;
+        for block_index in 0..20u64 {
+            // Vary the number of outputs so that the tree is both full and partial.
+            let outputs: Vec<TxOut> = (0..(block_index % 5 + 1))
+                .map(|_| {
+                    let mut tx_out = TxOut::new(
+                        Amount::new(10, Mob::ID),
+                        &account_key.default_subaddress(),
+                        &RistrettoPrivate::from_random(&mut rng),
+                        Default::default(),
+                    )
+                    .unwrap();
+                    tx_out.e_memo = None;
+                    tx_out
+                })
+                .collect();
+            let block_contents = BlockContents {
+                key_images: if block_index > 0 {
+                    vec![block_index.into()]
+                } else {
+                    vec![]
+                },
+                outputs: outputs.clone(),
+                ..Default::default()
+            };
+            let block = match parent {
+                None => Block::new_origin_block(&outputs),
+                Some(parent) => Block::new_with_parent(
+                    BlockVersion::ZERO,
+                    &parent,
+                    &Default::default(),
+                    &block_contents,
+                ),
+            };
+            ledger.append_block(&block, &block_contents, None).unwrap();
+
+            for tx_out in &outputs {
+                accumulator.push(hash_leaf(tx_out));
+            }
+            assert_eq!(accumulator.num_leaves(), ledger.num_txos().unwrap());
+            assert_eq!(
+                accumulator.root_element().unwrap(),
+                ledger.get_root_tx_out_membership_element().unwrap()
+            );
+
+            parent = Some(block);
+        }
+    }
+}
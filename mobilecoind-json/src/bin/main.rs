@@ -511,6 +511,112 @@ fn generate_request_code_transaction(
     Ok(Json(JsonCreateTxProposalResponse::from(&resp)))
 }
 
+/// Creates a signed contingent input, offering a utxo owned by the monitor in
+/// exchange for a list of required outputs. The result can be handed to a
+/// counterparty, who may fill it via fill-signed-contingent-input.
+#[post(
+    "/monitors/<monitor_hex>/signed-contingent-input",
+    format = "json",
+    data = "<request>"
+)]
+fn generate_signed_contingent_input(
+    state: &rocket::State<State>,
+    monitor_hex: String,
+    request: Json<JsonGenerateSignedContingentInputRequest>,
+) -> Result<Json<JsonGenerateSignedContingentInputResponse>, String> {
+    let monitor_id =
+        hex::decode(monitor_hex).map_err(|err| format!("Failed to decode monitor hex: {}", err))?;
+
+    let utxo = mc_mobilecoind_api::UnspentTxOut::try_from(&request.utxo)
+        .map_err(|err| format!("Failed to convert utxo: {}", err))?;
+
+    let required_outlays: Vec<mc_mobilecoind_api::OutlayV2> = request
+        .required_outlay_list
+        .iter()
+        .map(|outlay| {
+            mc_mobilecoind_api::OutlayV2::try_from(outlay)
+                .map_err(|err| format!("Failed to convert outlay: {}", err))
+        })
+        .collect::<Result<_, String>>()?;
+
+    let mut req = mc_mobilecoind_api::GenerateSignedContingentInputRequest::new();
+    req.set_sender_monitor_id(monitor_id);
+    req.set_utxo(utxo);
+    req.set_required_outlay_list(RepeatedField::from_vec(required_outlays));
+    req.set_tombstone(request.tombstone.as_ref().map(u64::from).unwrap_or(0));
+
+    let resp = state
+        .mobilecoind_api_client
+        .generate_signed_contingent_input(&req)
+        .map_err(|err| format!("Failed to generate signed contingent input: {}", err))?;
+
+    Ok(Json(JsonGenerateSignedContingentInputResponse::from(&resp)))
+}
+
+/// Checks a signed contingent input received from a counterparty, and
+/// describes what it offers and requires.
+#[post(
+    "/signed-contingent-input/validate",
+    format = "json",
+    data = "<request>"
+)]
+fn validate_signed_contingent_input(
+    state: &rocket::State<State>,
+    request: Json<JsonValidateSignedContingentInputRequest>,
+) -> Result<Json<JsonValidateSignedContingentInputResponse>, String> {
+    let mut req = mc_mobilecoind_api::ValidateSignedContingentInputRequest::new();
+    req.set_sci(
+        mc_api::external::SignedContingentInput::try_from(&request.sci)
+            .map_err(|err| format!("Failed to convert signed contingent input: {}", err))?,
+    );
+
+    let resp = state
+        .mobilecoind_api_client
+        .validate_signed_contingent_input(&req)
+        .map_err(|err| format!("Failed to validate signed contingent input: {}", err))?;
+
+    Ok(Json(JsonValidateSignedContingentInputResponse::from(&resp)))
+}
+
+/// Fills a signed contingent input received from a counterparty, using funds
+/// from a subaddress, and submits the resulting transaction.
+#[post(
+    "/monitors/<monitor_hex>/subaddresses/<subaddress_index>/fill-signed-contingent-input",
+    format = "json",
+    data = "<request>"
+)]
+fn fill_signed_contingent_input(
+    state: &rocket::State<State>,
+    monitor_hex: String,
+    subaddress_index: u64,
+    request: Json<JsonFillSignedContingentInputRequest>,
+) -> Result<Json<JsonSendPaymentResponse>, String> {
+    let monitor_id =
+        hex::decode(monitor_hex).map_err(|err| format!("Failed to decode monitor hex: {}", err))?;
+
+    let mut req = mc_mobilecoind_api::FillSignedContingentInputRequest::new();
+    req.set_sender_monitor_id(monitor_id);
+    req.set_sender_subaddress(subaddress_index);
+    req.set_sci(
+        mc_api::external::SignedContingentInput::try_from(&request.sci)
+            .map_err(|err| format!("Failed to convert signed contingent input: {}", err))?,
+    );
+    req.set_fee(request.fee.as_ref().map(u64::from).unwrap_or(0));
+    req.set_fee_token_id(request.fee_token_id.as_ref().map(u64::from).unwrap_or(0));
+    req.set_tombstone(request.tombstone.as_ref().map(u64::from).unwrap_or(0));
+    if let Some(subaddress) = request.change_subaddress.as_ref() {
+        req.set_override_change_subaddress(true);
+        req.set_change_subaddress(u64::from(subaddress))
+    }
+
+    let resp = state
+        .mobilecoind_api_client
+        .fill_signed_contingent_input(&req)
+        .map_err(|err| format!("Failed to fill signed contingent input: {}", err))?;
+
+    Ok(Json(JsonSendPaymentResponse::from(&resp)))
+}
+
 /// Submit a prepared TxProposal
 #[post("/submit-tx", format = "json", data = "<proposal>")]
 fn submit_tx(
@@ -659,6 +765,41 @@ fn processed_block(
     Ok(Json(JsonProcessedBlockResponse::from(&resp)))
 }
 
+/// Retrieves a page of a monitor's transaction history.
+#[allow(clippy::too_many_arguments)]
+#[get(
+    "/monitors/<monitor_hex>/transaction-history?<first_subaddress>&<num_subaddresses>&<token_id>&<start_block>&<end_block>&<max_blocks>"
+)]
+fn transaction_history(
+    state: &rocket::State<State>,
+    monitor_hex: String,
+    first_subaddress: Option<u64>,
+    num_subaddresses: Option<u64>,
+    token_id: Vec<u64>,
+    start_block: Option<u64>,
+    end_block: Option<u64>,
+    max_blocks: Option<u32>,
+) -> Result<Json<JsonTransactionHistoryResponse>, String> {
+    let monitor_id =
+        hex::decode(monitor_hex).map_err(|err| format!("Failed to decode monitor hex: {}", err))?;
+
+    let mut req = mc_mobilecoind_api::GetTransactionHistoryRequest::new();
+    req.set_monitor_id(monitor_id);
+    req.set_first_subaddress(first_subaddress.unwrap_or_default());
+    req.set_num_subaddresses(num_subaddresses.unwrap_or_default());
+    req.set_token_ids(token_id);
+    req.set_start_block(start_block.unwrap_or_default());
+    req.set_end_block(end_block.unwrap_or_default());
+    req.set_max_blocks(max_blocks.unwrap_or_default());
+
+    let resp = state
+        .mobilecoind_api_client
+        .get_transaction_history(&req)
+        .map_err(|err| format!("Failed getting transaction history: {}", err))?;
+
+    Ok(Json(JsonTransactionHistoryResponse::from(&resp)))
+}
+
 /// Get the block index of a given tx out, identified by its public key.
 #[get("/tx-out/<public_key_hex>/block-index")]
 fn tx_out_get_block_index_by_public_key(
@@ -819,6 +960,9 @@ async fn main() -> Result<(), rocket::Error> {
                 build_and_submit,
                 pay_address_code,
                 generate_request_code_transaction,
+                generate_signed_contingent_input,
+                validate_signed_contingent_input,
+                fill_signed_contingent_input,
                 submit_tx,
                 check_transfer_status,
                 check_receiver_transfer_status,
@@ -826,6 +970,7 @@ async fn main() -> Result<(), rocket::Error> {
                 block_info,
                 block_details,
                 processed_block,
+                transaction_history,
                 tx_out_get_block_index_by_public_key,
                 get_mixins,
                 get_proof_of_membership,
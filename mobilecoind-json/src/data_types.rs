@@ -3,9 +3,10 @@
 //! Serializeable data types that wrap the mobilecoind API.
 
 use mc_api::external::{
-    CompressedRistretto, EncryptedFogHint, EncryptedMemo, InputRules, KeyImage, MaskedAmount,
-    PublicAddress, RingMLSAG, SignatureRctBulletproofs, Tx, TxIn, TxOutMembershipElement,
-    TxOutMembershipHash, TxOutMembershipProof, TxPrefix,
+    CompressedRistretto, CurveScalar, EncryptedFogHint, EncryptedMemo, InputRules, KeyImage,
+    MaskedAmount, PublicAddress, RingMLSAG, SignatureRctBulletproofs, SignedContingentInput, Tx,
+    TxIn, TxOutMembershipElement, TxOutMembershipHash, TxOutMembershipProof, TxPrefix,
+    UnmaskedAmount,
 };
 use protobuf::RepeatedField;
 use serde_derive::{Deserialize, Serialize};
@@ -514,6 +515,42 @@ impl TryFrom<&JsonOutlay> for mc_mobilecoind_api::Outlay {
     }
 }
 
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct JsonOutlayV2 {
+    pub value: JsonU64,
+    pub receiver: JsonPublicAddress,
+    /// Defaults to MOB, so that outlays of tx proposals from before outlays
+    /// carried a token id can still be read.
+    #[serde(default)]
+    pub token_id: JsonU64,
+}
+
+impl From<&mc_mobilecoind_api::OutlayV2> for JsonOutlayV2 {
+    fn from(src: &mc_mobilecoind_api::OutlayV2) -> Self {
+        Self {
+            value: JsonU64(src.get_value()),
+            receiver: src.get_receiver().into(),
+            token_id: JsonU64(src.get_token_id()),
+        }
+    }
+}
+
+impl TryFrom<&JsonOutlayV2> for mc_mobilecoind_api::OutlayV2 {
+    type Error = String;
+
+    fn try_from(src: &JsonOutlayV2) -> Result<mc_mobilecoind_api::OutlayV2, String> {
+        let mut outlay = mc_mobilecoind_api::OutlayV2::new();
+        outlay.set_value(src.value.into());
+        outlay.set_receiver(
+            PublicAddress::try_from(&src.receiver)
+                .map_err(|err| format!("Could not convert receiver: {}", err))?,
+        );
+        outlay.set_token_id(src.token_id.into());
+
+        Ok(outlay)
+    }
+}
+
 #[derive(Deserialize, Serialize, Default, Debug, Clone)]
 pub struct JsonMaskedAmount {
     pub commitment: String,
@@ -874,6 +911,40 @@ impl From<&RingMLSAG> for JsonRingMLSAG {
     }
 }
 
+impl TryFrom<&JsonRingMLSAG> for RingMLSAG {
+    type Error = String;
+
+    fn try_from(src: &JsonRingMLSAG) -> Result<RingMLSAG, String> {
+        let mut c_zero = CurveScalar::new();
+        c_zero.set_data(
+            hex::decode(&src.c_zero)
+                .map_err(|err| format!("Could not decode from hex: {}", err))?,
+        );
+
+        let mut responses: Vec<CurveScalar> = Vec::new();
+        for resp in &src.responses {
+            let mut response = CurveScalar::new();
+            response.set_data(
+                hex::decode(resp).map_err(|err| format!("Could not decode from hex: {}", err))?,
+            );
+            responses.push(response);
+        }
+
+        let mut key_image = KeyImage::new();
+        key_image.set_data(
+            hex::decode(&src.key_image)
+                .map_err(|err| format!("Could not decode from hex: {}", err))?,
+        );
+
+        let mut ring_sig = RingMLSAG::new();
+        ring_sig.set_c_zero(c_zero);
+        ring_sig.set_responses(RepeatedField::from_vec(responses));
+        ring_sig.set_key_image(key_image);
+
+        Ok(ring_sig)
+    }
+}
+
 #[derive(Deserialize, Serialize, Default, Debug, Clone)]
 pub struct JsonSignatureRctBulletproofs {
     pub ring_signatures: Vec<JsonRingMLSAG>,
@@ -911,34 +982,7 @@ impl TryFrom<&JsonSignatureRctBulletproofs> for SignatureRctBulletproofs {
     fn try_from(src: &JsonSignatureRctBulletproofs) -> Result<SignatureRctBulletproofs, String> {
         let mut ring_sigs: Vec<RingMLSAG> = Vec::new();
         for sig in &src.ring_signatures {
-            let mut c_zero = mc_api::external::CurveScalar::new();
-            c_zero.set_data(
-                hex::decode(&sig.c_zero)
-                    .map_err(|err| format!("Could not decode from hex: {}", err))?,
-            );
-
-            let mut responses: Vec<mc_api::external::CurveScalar> = Vec::new();
-            for resp in &sig.responses {
-                let mut response = mc_api::external::CurveScalar::new();
-                response.set_data(
-                    hex::decode(resp)
-                        .map_err(|err| format!("Could not decode from hex: {}", err))?,
-                );
-                responses.push(response);
-            }
-
-            let mut key_image = KeyImage::new();
-            key_image.set_data(
-                hex::decode(&sig.key_image)
-                    .map_err(|err| format!("Could not decode from hex: {}", err))?,
-            );
-
-            let mut ring_sig = RingMLSAG::new();
-            ring_sig.set_c_zero(c_zero);
-            ring_sig.set_responses(RepeatedField::from_vec(responses));
-            ring_sig.set_key_image(key_image);
-
-            ring_sigs.push(ring_sig);
+            ring_sigs.push(RingMLSAG::try_from(sig)?);
         }
 
         let mut commitments: Vec<CompressedRistretto> = Vec::new();
@@ -1017,10 +1061,176 @@ impl TryFrom<&JsonTx> for Tx {
     }
 }
 
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct JsonUnmaskedAmount {
+    pub value: JsonU64,
+    pub token_id: JsonU64,
+    pub blinding: String,
+}
+
+impl From<&UnmaskedAmount> for JsonUnmaskedAmount {
+    fn from(src: &UnmaskedAmount) -> Self {
+        Self {
+            value: JsonU64(src.get_value()),
+            token_id: JsonU64(src.get_token_id()),
+            blinding: hex::encode(src.get_blinding().get_data()),
+        }
+    }
+}
+
+impl TryFrom<&JsonUnmaskedAmount> for UnmaskedAmount {
+    type Error = String;
+
+    fn try_from(src: &JsonUnmaskedAmount) -> Result<UnmaskedAmount, String> {
+        let mut blinding = CurveScalar::new();
+        blinding.set_data(
+            hex::decode(&src.blinding)
+                .map_err(|err| format!("Could not decode from hex: {}", err))?,
+        );
+
+        let mut amount = UnmaskedAmount::new();
+        amount.set_value(src.value.into());
+        amount.set_token_id(src.token_id.into());
+        amount.set_blinding(blinding);
+
+        Ok(amount)
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct JsonSignedContingentInput {
+    pub block_version: u32,
+    pub tx_in: JsonTxIn,
+    pub mlsag: JsonRingMLSAG,
+    pub pseudo_output_amount: JsonUnmaskedAmount,
+    pub required_output_amounts: Vec<JsonUnmaskedAmount>,
+    pub tx_out_global_indices: Vec<JsonU64>,
+}
+
+impl From<&SignedContingentInput> for JsonSignedContingentInput {
+    fn from(src: &SignedContingentInput) -> Self {
+        Self {
+            block_version: src.get_block_version(),
+            tx_in: src.get_tx_in().into(),
+            mlsag: src.get_mlsag().into(),
+            pseudo_output_amount: src.get_pseudo_output_amount().into(),
+            required_output_amounts: src
+                .get_required_output_amounts()
+                .iter()
+                .map(JsonUnmaskedAmount::from)
+                .collect(),
+            tx_out_global_indices: src
+                .get_tx_out_global_indices()
+                .iter()
+                .map(Into::into)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<&JsonSignedContingentInput> for SignedContingentInput {
+    type Error = String;
+
+    fn try_from(src: &JsonSignedContingentInput) -> Result<SignedContingentInput, String> {
+        let mut required_output_amounts: Vec<UnmaskedAmount> = Vec::new();
+        for amount in &src.required_output_amounts {
+            required_output_amounts.push(
+                UnmaskedAmount::try_from(amount)
+                    .map_err(|err| format!("Could not convert required output amount: {}", err))?,
+            );
+        }
+
+        let mut sci = SignedContingentInput::new();
+        sci.set_block_version(src.block_version);
+        sci.set_tx_in(
+            TxIn::try_from(&src.tx_in).map_err(|err| format!("Could not get TxIn: {}", err))?,
+        );
+        sci.set_mlsag(
+            RingMLSAG::try_from(&src.mlsag)
+                .map_err(|err| format!("Could not convert mlsag: {}", err))?,
+        );
+        sci.set_pseudo_output_amount(
+            UnmaskedAmount::try_from(&src.pseudo_output_amount)
+                .map_err(|err| format!("Could not convert pseudo output amount: {}", err))?,
+        );
+        sci.set_required_output_amounts(RepeatedField::from_vec(required_output_amounts));
+        sci.set_tx_out_global_indices(src.tx_out_global_indices.iter().map(Into::into).collect());
+
+        Ok(sci)
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct JsonGenerateSignedContingentInputRequest {
+    pub utxo: JsonUnspentTxOut,
+    pub required_outlay_list: Vec<JsonOutlayV2>,
+    pub tombstone: Option<JsonU64>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct JsonGenerateSignedContingentInputResponse {
+    pub sci: JsonSignedContingentInput,
+}
+
+impl From<&mc_mobilecoind_api::GenerateSignedContingentInputResponse>
+    for JsonGenerateSignedContingentInputResponse
+{
+    fn from(src: &mc_mobilecoind_api::GenerateSignedContingentInputResponse) -> Self {
+        Self {
+            sci: src.get_sci().into(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct JsonValidateSignedContingentInputRequest {
+    pub sci: JsonSignedContingentInput,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct JsonValidateSignedContingentInputResponse {
+    pub is_valid: bool,
+    pub invalid_reason: String,
+    pub offered_value: JsonU64,
+    pub offered_token_id: JsonU64,
+    pub required_values: Vec<JsonU64>,
+    pub required_token_ids: Vec<JsonU64>,
+    pub max_tombstone_block: JsonU64,
+}
+
+impl From<&mc_mobilecoind_api::ValidateSignedContingentInputResponse>
+    for JsonValidateSignedContingentInputResponse
+{
+    fn from(src: &mc_mobilecoind_api::ValidateSignedContingentInputResponse) -> Self {
+        Self {
+            is_valid: src.get_is_valid(),
+            invalid_reason: src.get_invalid_reason().to_owned(),
+            offered_value: JsonU64(src.get_offered_value()),
+            offered_token_id: JsonU64(src.get_offered_token_id()),
+            required_values: src.get_required_values().iter().map(Into::into).collect(),
+            required_token_ids: src
+                .get_required_token_ids()
+                .iter()
+                .map(Into::into)
+                .collect(),
+            max_tombstone_block: JsonU64(src.get_max_tombstone_block()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct JsonFillSignedContingentInputRequest {
+    pub sci: JsonSignedContingentInput,
+    pub fee: Option<JsonU64>,
+    pub fee_token_id: Option<JsonU64>,
+    pub tombstone: Option<JsonU64>,
+    pub change_subaddress: Option<JsonU64>,
+}
+
 #[derive(Deserialize, Serialize, Default, Debug)]
 pub struct JsonTxProposal {
     pub input_list: Vec<JsonUnspentTxOut>,
-    pub outlay_list: Vec<JsonOutlay>,
+    pub outlay_list: Vec<JsonOutlayV2>,
     pub tx: JsonTx,
     pub fee: u64,
     pub outlay_index_to_tx_out_index: Vec<(usize, usize)>,
@@ -1040,7 +1250,11 @@ impl From<&mc_mobilecoind_api::TxProposal> for JsonTxProposal {
                 .iter()
                 .map(JsonUnspentTxOut::from)
                 .collect(),
-            outlay_list: src.get_outlay_list().iter().map(JsonOutlay::from).collect(),
+            outlay_list: src
+                .get_outlay_list()
+                .iter()
+                .map(JsonOutlayV2::from)
+                .collect(),
             tx: src.get_tx().into(),
             fee: src.get_fee(),
             outlay_index_to_tx_out_index: outlay_map,
@@ -1061,9 +1275,9 @@ impl TryFrom<&JsonTxProposal> for mc_mobilecoind_api::TxProposal {
             inputs.push(utxo);
         }
 
-        let mut outlays: Vec<mc_mobilecoind_api::Outlay> = Vec::new();
+        let mut outlays: Vec<mc_mobilecoind_api::OutlayV2> = Vec::new();
         for outlay in src.outlay_list.iter() {
-            let out = mc_mobilecoind_api::Outlay::try_from(outlay)
+            let out = mc_mobilecoind_api::OutlayV2::try_from(outlay)
                 .map_err(|err| format!("Failed to convert outlay: {}", err))?;
             outlays.push(out);
         }
@@ -1351,6 +1565,60 @@ impl From<&mc_mobilecoind_api::GetProcessedBlockResponse> for JsonProcessedBlock
     }
 }
 
+#[derive(Serialize, Default, Debug)]
+pub struct JsonHistoricalTransaction {
+    pub block_index: String,
+    pub timestamp: String,
+    pub token_id: JsonU64,
+    pub received: Vec<JsonProcessedTxOut>,
+    pub spent: Vec<JsonProcessedTxOut>,
+    pub total_received: JsonU64,
+    pub total_spent: JsonU64,
+}
+
+impl From<&mc_mobilecoind_api::HistoricalTransaction> for JsonHistoricalTransaction {
+    fn from(src: &mc_mobilecoind_api::HistoricalTransaction) -> Self {
+        Self {
+            block_index: src.block_index.to_string(),
+            timestamp: src.timestamp.to_string(),
+            token_id: JsonU64(src.token_id),
+            received: src
+                .get_received()
+                .iter()
+                .map(JsonProcessedTxOut::from)
+                .collect(),
+            spent: src
+                .get_spent()
+                .iter()
+                .map(JsonProcessedTxOut::from)
+                .collect(),
+            total_received: JsonU64(src.total_received),
+            total_spent: JsonU64(src.total_spent),
+        }
+    }
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct JsonTransactionHistoryResponse {
+    pub transactions: Vec<JsonHistoricalTransaction>,
+    pub next_block: String,
+    pub has_more: bool,
+}
+
+impl From<&mc_mobilecoind_api::GetTransactionHistoryResponse> for JsonTransactionHistoryResponse {
+    fn from(src: &mc_mobilecoind_api::GetTransactionHistoryResponse) -> Self {
+        Self {
+            transactions: src
+                .get_transactions()
+                .iter()
+                .map(JsonHistoricalTransaction::from)
+                .collect(),
+            next_block: src.next_block.to_string(),
+            has_more: src.has_more,
+        }
+    }
+}
+
 #[derive(Serialize, Default, Debug)]
 pub struct JsonBlockIndexByTxPubKeyResponse {
     pub block_index: String,
@@ -1440,10 +1708,11 @@ mod test {
         };
 
         // Make proto outlay
-        let mut outlay = mc_mobilecoind_api::Outlay::new();
+        let mut outlay = mc_mobilecoind_api::OutlayV2::new();
         let public_addr = AccountKey::random(&mut rng).default_subaddress();
         outlay.set_receiver(mc_api::external::PublicAddress::from(&public_addr));
         outlay.set_value(1234);
+        outlay.set_token_id(2);
 
         let outlay_index_to_tx_out_index = HashMap::from_iter(vec![(0, 0)]);
         let outlay_confirmation_numbers =
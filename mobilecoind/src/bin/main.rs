@@ -4,12 +4,17 @@
 //! mobilecoind daemon entry point
 
 use clap::Parser;
-use mc_attest_verifier::{MrSignerVerifier, Verifier, DEBUG_ENCLAVE};
+#[cfg(all(feature = "sgx-enclave", not(feature = "insecure-enclave")))]
+use mc_attest_verifier::MrSignerVerifier;
+use mc_attest_verifier::{Verifier, DEBUG_ENCLAVE};
 use mc_common::logger::{create_app_logger, log, o, Logger};
 use mc_ledger_db::{Ledger, LedgerDB};
 use mc_ledger_sync::{LedgerSyncServiceThread, PollingNetworkState, ReqwestTransactionsFetcher};
 use mc_mobilecoind::{
-    config::Config, database::Database, payments::TransactionsManager, service::Service,
+    config::{Command, Config},
+    database::Database,
+    payments::TransactionsManager,
+    service::Service,
 };
 use mc_util_telemetry::setup_default_tracer;
 use mc_watcher::{watcher::WatcherSyncThread, watcher_db::create_or_open_rw_watcher_db};
@@ -18,6 +23,9 @@ use std::{
     sync::{Arc, RwLock},
 };
 
+#[cfg(not(any(feature = "sgx-enclave", feature = "insecure-enclave")))]
+compile_error!("One of the `sgx-enclave` or `insecure-enclave` features must be enabled");
+
 fn main() {
     let config = Config::parse();
     if !cfg!(debug_assertions) && !config.offline {
@@ -28,6 +36,18 @@ fn main() {
     let _sentry_guard = mc_common::sentry::init();
     let (logger, _global_logger_guard) = create_app_logger(o!());
 
+    // Maintenance commands run against the mobilecoind database and exit.
+    if let Some(command) = &config.command {
+        let mobilecoind_db = config
+            .mobilecoind_db
+            .as_ref()
+            .expect("Please provide --mobilecoind-db");
+        let mobilecoind_db =
+            Database::new(mobilecoind_db, logger.clone()).expect("Could not open mobilecoind_db");
+        run_command(command, &mobilecoind_db, &logger);
+        return;
+    }
+
     // Telemetry is disabled if MC_TELEMETRY is set to "0"
     let telemetry_enabled = !std::env::var("MC_TELEMETRY")
         .map(|val| val == "0")
@@ -39,9 +59,21 @@ fn main() {
         None
     };
 
-    let mut mr_signer_verifier =
-        MrSignerVerifier::from(mc_consensus_enclave_measurement::sigstruct());
-    mr_signer_verifier.allow_hardening_advisory("INTEL-SA-00334");
+    #[cfg(all(feature = "sgx-enclave", not(feature = "insecure-enclave")))]
+    let mr_signer_verifier = {
+        let mut mr_signer_verifier =
+            MrSignerVerifier::from(mc_consensus_enclave_measurement::sigstruct());
+        mr_signer_verifier.allow_hardening_advisory("INTEL-SA-00334");
+        mr_signer_verifier
+    };
+    #[cfg(feature = "insecure-enclave")]
+    let mr_signer_verifier = {
+        log::error!(
+            logger,
+            "INSECURE: accepting consensus nodes running without SGX protection"
+        );
+        mc_consensus_enclave_insecure::mr_signer_verifier()
+    };
 
     let mut verifier = Verifier::default();
     verifier.mr_signer(mr_signer_verifier).debug(DEBUG_ENCLAVE);
@@ -65,7 +97,44 @@ fn main() {
     .expect("Failed creating ReqwestTransactionsFetcher");
 
     // Create the ledger_db.
-    let ledger_db = create_or_open_ledger_db(&config, &logger, &transactions_fetcher);
+    let mut ledger_db = create_or_open_ledger_db(&config, &logger, &transactions_fetcher);
+
+    // Open the mobilecoind_db, if we are going to serve the API.
+    let mobilecoind_db = match (&config.mobilecoind_db, &config.listen_uri) {
+        (Some(mobilecoind_db), Some(_)) => {
+            let _ = std::fs::create_dir_all(mobilecoind_db);
+
+            Some(
+                Database::new(mobilecoind_db, logger.clone())
+                    .expect("Could not open mobilecoind_db"),
+            )
+        }
+        (None, None) => None,
+        _ => {
+            panic!(
+                "Please provide both --mobilecoind-db and --listen-uri if you want to enable the API server"
+            );
+        }
+    };
+
+    // Enable pruning, if requested. This needs to happen before the ledger db is
+    // handed out to the threads that append to it. Blocks that monitors have yet
+    // to process are never pruned.
+    if let Some(pruning_horizon) = config.ledger_pruning_horizon {
+        ledger_db.set_pruning_horizon(Some(pruning_horizon));
+        if let Some(mobilecoind_db) = mobilecoind_db.as_ref() {
+            ledger_db.set_pruning_limit(Some(mobilecoind_db.ledger_pruning_limit()));
+        }
+        let num_pruned_blocks = ledger_db
+            .prune_block_contents(pruning_horizon)
+            .expect("Failed pruning ledger db");
+        log::info!(
+            logger,
+            "Ledger DB pruning enabled: horizon={} num_pruned_blocks={}",
+            pruning_horizon,
+            num_pruned_blocks
+        );
+    }
 
     // Start ledger sync thread unless running in offline mode.
     let _ledger_sync_service_thread = if config.offline {
@@ -116,15 +185,10 @@ fn main() {
     };
 
     // Potentially launch API server
-    match (&config.mobilecoind_db, &config.listen_uri) {
+    match (mobilecoind_db, &config.listen_uri) {
         (Some(mobilecoind_db), Some(listen_uri)) => {
             log::info!(logger, "Launching mobilecoind API services");
 
-            let _ = std::fs::create_dir_all(mobilecoind_db);
-
-            let mobilecoind_db = Database::new(mobilecoind_db, logger.clone())
-                .expect("Could not open mobilecoind_db");
-
             let transactions_manager = TransactionsManager::new(
                 ledger_db.clone(),
                 mobilecoind_db.clone(),
@@ -149,18 +213,90 @@ fn main() {
             }
         }
 
-        (None, None) => {
+        _ => {
             // No mobilecoind service, only ledger syncing.
             loop {
                 std::thread::sleep(config.poll_interval);
             }
         }
+    }
+}
 
-        _ => {
-            panic!(
-                "Please provide both --mobilecoind-db and --listen-uri if you want to enable the API server"
+fn run_command(command: &Command, mobilecoind_db: &Database, logger: &Logger) {
+    match command {
+        Command::ExportMonitors {
+            out,
+            bundle_password,
+            db_password,
+            monitor_ids,
+            include_processed_state,
+        } => {
+            unlock_mobilecoind_db(mobilecoind_db, db_password);
+
+            let all_monitor_ids = mobilecoind_db
+                .get_monitor_ids()
+                .expect("Failed getting monitor ids");
+            let monitor_ids_to_export = if monitor_ids.is_empty() {
+                all_monitor_ids
+            } else {
+                monitor_ids
+                    .iter()
+                    .map(|requested_id| {
+                        *all_monitor_ids
+                            .iter()
+                            .find(|id| id.to_string() == requested_id.to_lowercase())
+                            .unwrap_or_else(|| panic!("Unknown monitor id {}", requested_id))
+                    })
+                    .collect()
+            };
+
+            let bundle = mobilecoind_db
+                .export_monitors(
+                    &monitor_ids_to_export,
+                    *include_processed_state,
+                    bundle_password,
+                    &mut rand::thread_rng(),
+                )
+                .expect("Failed exporting monitors");
+            std::fs::write(out, bundle)
+                .unwrap_or_else(|err| panic!("Failed writing {:?}: {}", out, err));
+
+            log::info!(
+                logger,
+                "Exported {} monitors to {:?}",
+                monitor_ids_to_export.len(),
+                out
             );
         }
+
+        Command::ImportMonitors {
+            input,
+            bundle_password,
+            db_password,
+            rescan,
+        } => {
+            unlock_mobilecoind_db(mobilecoind_db, db_password);
+
+            let bundle = std::fs::read(input)
+                .unwrap_or_else(|err| panic!("Failed reading {:?}: {}", input, err));
+            let monitor_ids = mobilecoind_db
+                .import_monitors(&bundle, bundle_password, *rescan)
+                .expect("Failed importing monitors");
+
+            for monitor_id in monitor_ids.iter() {
+                log::info!(logger, "Imported monitor {}", monitor_id);
+            }
+        }
+    }
+}
+
+fn unlock_mobilecoind_db(mobilecoind_db: &Database, db_password: &Option<[u8; 32]>) {
+    if let Some(db_password) = db_password {
+        mobilecoind_db
+            .check_and_store_password(db_password)
+            .expect("Invalid mobilecoind_db password");
+    } else if !mobilecoind_db.is_unlocked() {
+        panic!("mobilecoind_db is encrypted, please provide --db-password");
     }
 }
 
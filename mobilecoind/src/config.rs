@@ -3,7 +3,7 @@
 
 //! Configuration parameters for mobilecoind
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use displaydoc::Display;
 use mc_attest_verifier::{MrSignerVerifier, Verifier, DEBUG_ENCLAVE};
 use mc_common::{logger::Logger, ResponderId};
@@ -20,7 +20,7 @@ use reqwest::{
     blocking::Client,
     header::{HeaderMap, HeaderValue, CONTENT_TYPE},
 };
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{convert::TryFrom, path::PathBuf, sync::Arc, time::Duration};
 
 /// Configuration parameters for mobilecoind
 #[derive(Debug, Parser)]
@@ -98,6 +98,76 @@ pub struct Config {
     /// Automatically migrate the ledger db into the most recent version.
     #[clap(long, env = "MC_LEDGER_DB_MIGRATE")]
     pub ledger_db_migrate: bool,
+
+    /// Only keep the contents of the latest N blocks in the ledger db. TxOuts,
+    /// key images and the TxOut Merkle tree are always kept, so sending
+    /// payments is not affected. Blocks that existing monitors have yet to
+    /// process are kept, but new monitors can not start at a pruned block.
+    #[clap(long, env = "MC_LEDGER_PRUNING_HORIZON")]
+    pub ledger_pruning_horizon: Option<u64>,
+
+    /// Maintenance command to run against the mobilecoind database, instead of
+    /// starting the daemon. Requires --mobilecoind-db (and --offline, since no
+    /// network access is needed).
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Maintenance commands that operate on the mobilecoind database and exit.
+#[derive(Clone, Debug, Subcommand)]
+pub enum Command {
+    /// Export monitors into a password-encrypted bundle file.
+    ExportMonitors {
+        /// File to write the bundle to.
+        #[clap(long, parse(from_os_str))]
+        out: PathBuf,
+
+        /// Hex-encoded 32 byte password to encrypt the bundle with.
+        #[clap(long, parse(try_from_str = parse_password), env = "MC_BUNDLE_PASSWORD")]
+        bundle_password: [u8; 32],
+
+        /// Hex-encoded 32 byte password of the mobilecoind database, if it is
+        /// encrypted.
+        #[clap(long, parse(try_from_str = parse_password), env = "MC_DB_PASSWORD")]
+        db_password: Option<[u8; 32]>,
+
+        /// Hex-encoded ids of the monitors to export. All monitors are
+        /// exported when omitted.
+        #[clap(long = "monitor-id")]
+        monitor_ids: Vec<String>,
+
+        /// Also export the monitors' UTXOs and processed blocks, so that
+        /// importing them does not require rescanning the ledger.
+        #[clap(long)]
+        include_processed_state: bool,
+    },
+
+    /// Import monitors from a bundle file created by export-monitors.
+    ImportMonitors {
+        /// File to read the bundle from.
+        #[clap(long = "in", parse(from_os_str))]
+        input: PathBuf,
+
+        /// Hex-encoded 32 byte password the bundle was encrypted with.
+        #[clap(long, parse(try_from_str = parse_password), env = "MC_BUNDLE_PASSWORD")]
+        bundle_password: [u8; 32],
+
+        /// Hex-encoded 32 byte password of the mobilecoind database, if it is
+        /// encrypted.
+        #[clap(long, parse(try_from_str = parse_password), env = "MC_DB_PASSWORD")]
+        db_password: Option<[u8; 32]>,
+
+        /// Rescan the ledger from each monitor's first block, even if the
+        /// bundle includes processed state.
+        #[clap(long)]
+        rescan: bool,
+    },
+}
+
+fn parse_password(src: &str) -> Result<[u8; 32], String> {
+    let bytes = hex::decode(src).map_err(|err| format!("Invalid hex password: {}", err))?;
+    <[u8; 32]>::try_from(bytes.as_slice())
+        .map_err(|_| format!("Password must be 32 bytes, got {}", bytes.len()))
 }
 
 fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, String> {
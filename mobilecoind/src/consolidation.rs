@@ -0,0 +1,571 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Automatic UTXO consolidation for monitors that have a consolidation policy.
+//!
+//! A background thread periodically goes over all monitors with a policy.
+//! Once a monitor has caught up with the ledger, the pending consolidation
+//! transaction (if any) is resolved: it either landed, in which case its fee is
+//! charged against the policy's budget, or its tombstone block passed. With no
+//! transaction pending, the subaddress holding the most UTXOs of the policy's
+//! token is checked against the target count, and if it is over the target a
+//! transaction merging enough of its UTXOs to reach the target (up to
+//! MAX_INPUTS at a time) is submitted, provided its fee fits in the remaining
+//! budget. Each monitor has at most one consolidation transaction in flight,
+//! and progress is stored alongside the monitor's data.
+
+use crate::{
+    database::Database,
+    error::Error,
+    monitor_store::{ConsolidationPolicy, ConsolidationStatus, MonitorData, MonitorId},
+    payments::TransactionsManager,
+    utxo_store::UtxoId,
+};
+use mc_common::logger::{log, Logger};
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::{Ledger, LedgerDB};
+use mc_transaction_core::{constants::MAX_INPUTS, TokenId};
+use std::{
+    cmp::min,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How often monitors are checked for consolidation work.
+pub const CONSOLIDATION_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Consolidation thread - holds objects needed to cleanly terminate the
+/// consolidation thread.
+pub struct ConsolidationThread {
+    /// The consolidation thread handle.
+    join_handle: Option<thread::JoinHandle<()>>,
+
+    /// Stop trigger, used to signal the thread to terminate.
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl ConsolidationThread {
+    pub fn start<
+        T: BlockchainConnection + UserTxConnection + 'static,
+        FPR: FogPubkeyResolver + 'static,
+    >(
+        ledger_db: LedgerDB,
+        mobilecoind_db: Database,
+        transactions_manager: TransactionsManager<T, FPR>,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("consolidation".to_string())
+                .spawn(move || {
+                    log::debug!(logger, "ConsolidationThread started.");
+
+                    'outer: loop {
+                        // Monitor data cannot be read until the database is unlocked.
+                        if mobilecoind_db.is_unlocked() {
+                            consolidate_all(
+                                &ledger_db,
+                                &mobilecoind_db,
+                                &transactions_manager,
+                                &logger,
+                            );
+                        }
+
+                        for _ in 0..CONSOLIDATION_POLL_INTERVAL.as_secs() {
+                            if thread_stop_requested.load(Ordering::SeqCst) {
+                                log::debug!(logger, "ConsolidationThread stop requested.");
+                                break 'outer;
+                            }
+                            thread::sleep(Duration::from_secs(1));
+                        }
+                    }
+
+                    log::debug!(logger, "ConsolidationThread stopped.");
+                })
+                .expect("failed starting consolidation thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.join().expect("ConsolidationThread join failed");
+        }
+    }
+}
+
+impl Drop for ConsolidationThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Make progress on the consolidation policies of all monitors.
+fn consolidate_all<
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+>(
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    transactions_manager: &TransactionsManager<T, FPR>,
+    logger: &Logger,
+) {
+    let num_blocks = match ledger_db.num_blocks() {
+        Ok(num_blocks) => num_blocks,
+        Err(err) => {
+            log::error!(logger, "Failed getting number of blocks: {}", err);
+            return;
+        }
+    };
+
+    let monitor_map = match mobilecoind_db.get_monitor_map() {
+        Ok(monitor_map) => monitor_map,
+        Err(err) => {
+            log::error!(logger, "Failed getting monitor map: {}", err);
+            return;
+        }
+    };
+
+    for (monitor_id, monitor_data) in monitor_map {
+        let policy = match monitor_data.consolidation_policy.as_ref() {
+            Some(policy) => policy,
+            None => continue,
+        };
+
+        // Wait for the monitor to catch up with the ledger, so that its UTXOs are up
+        // to date.
+        if monitor_data.next_block < num_blocks {
+            continue;
+        }
+
+        let mut status = monitor_data.consolidation_status.clone();
+        if let Err(err) = consolidate_monitor(
+            ledger_db,
+            mobilecoind_db,
+            transactions_manager,
+            &monitor_id,
+            &monitor_data,
+            policy,
+            &mut status,
+            num_blocks,
+            logger,
+        ) {
+            log::debug!(
+                logger,
+                "Failed consolidating UTXOs of monitor {}: {}",
+                monitor_id,
+                err
+            );
+            status.last_error = err.to_string();
+        }
+
+        if status != monitor_data.consolidation_status {
+            if let Err(err) =
+                mobilecoind_db.set_monitor_consolidation_status(&monitor_id, policy, &status)
+            {
+                log::error!(
+                    logger,
+                    "Failed updating consolidation status of monitor {}: {}",
+                    monitor_id,
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Make progress on the consolidation policy of a single monitor that is in
+/// sync with a ledger of `num_blocks` blocks, updating `status` accordingly.
+fn consolidate_monitor<
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+>(
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    transactions_manager: &TransactionsManager<T, FPR>,
+    monitor_id: &MonitorId,
+    monitor_data: &MonitorData,
+    policy: &ConsolidationPolicy,
+    status: &mut ConsolidationStatus,
+    num_blocks: u64,
+    logger: &Logger,
+) -> Result<(), Error> {
+    // Resolve the pending transaction, if any.
+    if !status.pending_key_images.is_empty() {
+        let mut landed = false;
+        for key_image in status.pending_key_images.iter() {
+            if ledger_db.contains_key_image(key_image)? {
+                landed = true;
+                break;
+            }
+        }
+
+        if landed {
+            status.fees_spent += status.pending_fee;
+            status.num_completed_txs += 1;
+        } else if num_blocks >= status.pending_tombstone_block {
+            status.num_expired_txs += 1;
+        } else {
+            // Still in flight.
+            return Ok(());
+        }
+
+        status.pending_key_images.clear();
+        status.pending_tombstone_block = 0;
+        status.pending_fee = 0;
+    }
+
+    // Find the subaddress holding the most countable UTXOs.
+    let mut max_utxo_count = 0;
+    let mut max_utxo_count_subaddress = monitor_data.first_subaddress;
+    for subaddress_index in monitor_data.subaddress_indexes() {
+        let utxo_count = mobilecoind_db
+            .get_utxos_for_subaddress(monitor_id, subaddress_index)?
+            .iter()
+            .filter(|utxo| utxo.token_id == policy.token_id && utxo.value >= policy.min_utxo_value)
+            .count() as u64;
+        if utxo_count > max_utxo_count {
+            max_utxo_count = utxo_count;
+            max_utxo_count_subaddress = subaddress_index;
+        }
+    }
+    status.max_utxo_count = max_utxo_count;
+
+    if max_utxo_count <= policy.target_utxo_count {
+        status.last_error.clear();
+        return Ok(());
+    }
+
+    // Check the fee against what is left of the budget before building anything.
+    let token_id = TokenId::from(policy.token_id);
+    let fee = transactions_manager.get_network_fee(token_id);
+    let remaining_budget = policy.fee_budget.saturating_sub(status.fees_spent);
+    if fee > remaining_budget {
+        return Err(Error::ConsolidationFeeBudgetExhausted(
+            status.fees_spent,
+            fee,
+            policy.fee_budget,
+        ));
+    }
+
+    // Merging n UTXOs into one reduces the count by n - 1.
+    let max_inputs = min(
+        max_utxo_count - policy.target_utxo_count + 1,
+        MAX_INPUTS as u64,
+    ) as usize;
+    let tx_proposal = transactions_manager.generate_consolidation_tx(
+        monitor_id,
+        max_utxo_count_subaddress,
+        token_id,
+        max_inputs,
+        policy.min_utxo_value,
+        fee,
+    )?;
+
+    let block_height = transactions_manager.submit_tx_proposal(&tx_proposal)?;
+
+    // As with user-submitted transactions, failing to record the attempted spend
+    // is not fatal since the transaction was already sent to the network.
+    let utxo_ids: Vec<UtxoId> = tx_proposal.utxos.iter().map(UtxoId::from).collect();
+    if let Err(err) = mobilecoind_db.update_attempted_spend(
+        &utxo_ids,
+        block_height,
+        tx_proposal.tx.prefix.tombstone_block,
+    ) {
+        log::error!(
+            logger,
+            "failed updating attempted_spend_height after submitting tx {}: {:?}",
+            tx_proposal.tx,
+            err
+        );
+    }
+
+    log::info!(
+        logger,
+        "Submitted consolidation tx {} merging {} UTXOs of monitor {} subaddress {}",
+        tx_proposal.tx,
+        tx_proposal.utxos.len(),
+        monitor_id,
+        max_utxo_count_subaddress,
+    );
+
+    status.pending_key_images = tx_proposal
+        .utxos
+        .iter()
+        .map(|utxo| utxo.key_image)
+        .collect();
+    status.pending_tombstone_block = tx_proposal.tx.prefix.tombstone_block;
+    status.pending_fee = fee;
+    status.last_error.clear();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        sync::SyncThread,
+        test_utils::{
+            add_block_to_ledger_db, get_test_databases, wait_for_monitors, BlockVersion,
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+        },
+    };
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_connection::ConnectionManager;
+    use mc_connection_test_utils::{test_client_uri, MockBlockchainConnection};
+    use mc_fog_report_validation_test_utils::MockFogResolver;
+    use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, Amount, Token};
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    const NUM_UTXOS: u64 = 20;
+    const TARGET_UTXO_COUNT: u64 = 5;
+
+    /// Set up a monitor holding NUM_UTXOS UTXOs in its default subaddress,
+    /// along with the objects needed to consolidate them.
+    fn setup(
+        logger: Logger,
+        rng: &mut StdRng,
+    ) -> (
+        LedgerDB,
+        Database,
+        TransactionsManager<MockBlockchainConnection<LedgerDB>, MockFogResolver>,
+        SyncThread,
+        MonitorId,
+        MonitorData,
+    ) {
+        let account_key = AccountKey::random(rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first subaddress
+            2,  // number of subaddresses
+            0,  // first block
+            "", // name
+        )
+        .unwrap();
+
+        // Random recipients are needed for mixins.
+        let (mut ledger_db, mobilecoind_db) =
+            get_test_databases(BlockVersion::MAX, 20, &[], 10, logger.clone(), rng);
+        for _ in 0..NUM_UTXOS {
+            add_block_to_ledger_db(
+                BlockVersion::MAX,
+                &mut ledger_db,
+                &[account_key.default_subaddress()],
+                Amount::new(DEFAULT_PER_RECIPIENT_AMOUNT, Mob::ID),
+                &[KeyImage::from(rng.next_u64())],
+                rng,
+            );
+        }
+
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        let sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            Default::default(),
+            Some(1),
+            logger.clone(),
+        );
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let peer = MockBlockchainConnection::new(test_client_uri(1), ledger_db.clone(), 0);
+        let conn_manager = ConnectionManager::new(vec![peer], logger.clone());
+        let transactions_manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            conn_manager,
+            Arc::new(|_| Ok(MockFogResolver::default())),
+            logger,
+        );
+
+        (
+            ledger_db,
+            mobilecoind_db,
+            transactions_manager,
+            sync_thread,
+            monitor_id,
+            data,
+        )
+    }
+
+    #[test_with_logger]
+    fn test_consolidate_monitor(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([42u8; 32]);
+        let (mut ledger_db, mobilecoind_db, transactions_manager, _sync_thread, monitor_id, data) =
+            setup(logger.clone(), &mut rng);
+
+        let policy = ConsolidationPolicy {
+            token_id: *Mob::ID,
+            target_utxo_count: TARGET_UTXO_COUNT,
+            min_utxo_value: 0,
+            fee_budget: 10 * Mob::MINIMUM_FEE,
+        };
+        let mut status = ConsolidationStatus::default();
+
+        // The first transaction merges as many UTXOs as possible.
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        consolidate_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &transactions_manager,
+            &monitor_id,
+            &data,
+            &policy,
+            &mut status,
+            num_blocks,
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(status.max_utxo_count, NUM_UTXOS);
+        assert_eq!(status.pending_key_images.len(), MAX_INPUTS as usize);
+        assert_eq!(status.pending_fee, Mob::MINIMUM_FEE);
+        assert!(status.pending_tombstone_block > num_blocks);
+        let pending_key_images = status.pending_key_images.clone();
+
+        // Nothing happens while the transaction is pending.
+        let pending_status = status.clone();
+        consolidate_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &transactions_manager,
+            &monitor_id,
+            &data,
+            &policy,
+            &mut status,
+            num_blocks,
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(status, pending_status);
+
+        // Once the transaction lands, its fee is charged, and the merged UTXO brings
+        // the subaddress down to the target.
+        add_block_to_ledger_db(
+            BlockVersion::MAX,
+            &mut ledger_db,
+            &[data.account_key.default_subaddress()],
+            Amount::new(DEFAULT_PER_RECIPIENT_AMOUNT, Mob::ID),
+            &pending_key_images,
+            &mut rng,
+        );
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        consolidate_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &transactions_manager,
+            &monitor_id,
+            &data,
+            &policy,
+            &mut status,
+            ledger_db.num_blocks().unwrap(),
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(
+            status,
+            ConsolidationStatus {
+                fees_spent: Mob::MINIMUM_FEE,
+                num_completed_txs: 1,
+                max_utxo_count: TARGET_UTXO_COUNT,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test_with_logger]
+    fn test_consolidate_monitor_respects_fee_budget(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([42u8; 32]);
+        let (ledger_db, mobilecoind_db, transactions_manager, _sync_thread, monitor_id, data) =
+            setup(logger.clone(), &mut rng);
+
+        let policy = ConsolidationPolicy {
+            token_id: *Mob::ID,
+            target_utxo_count: TARGET_UTXO_COUNT,
+            min_utxo_value: 0,
+            fee_budget: Mob::MINIMUM_FEE - 1,
+        };
+        let mut status = ConsolidationStatus::default();
+
+        let result = consolidate_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &transactions_manager,
+            &monitor_id,
+            &data,
+            &policy,
+            &mut status,
+            ledger_db.num_blocks().unwrap(),
+            &logger,
+        );
+        assert!(matches!(
+            result,
+            Err(Error::ConsolidationFeeBudgetExhausted(0, _, _))
+        ));
+        assert!(status.pending_key_images.is_empty());
+
+        // Nothing was marked as being spent.
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert_eq!(utxos.len() as u64, NUM_UTXOS);
+        assert!(utxos.iter().all(|utxo| utxo.attempted_spend_height == 0));
+    }
+
+    #[test_with_logger]
+    fn test_consolidate_monitor_stops_when_fee_budget_is_spent(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([42u8; 32]);
+        let (ledger_db, mobilecoind_db, transactions_manager, _sync_thread, monitor_id, data) =
+            setup(logger.clone(), &mut rng);
+
+        let policy = ConsolidationPolicy {
+            token_id: *Mob::ID,
+            target_utxo_count: TARGET_UTXO_COUNT,
+            min_utxo_value: 0,
+            fee_budget: 2 * Mob::MINIMUM_FEE,
+        };
+        let mut status = ConsolidationStatus {
+            fees_spent: 2 * Mob::MINIMUM_FEE,
+            num_completed_txs: 2,
+            ..Default::default()
+        };
+
+        let result = consolidate_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &transactions_manager,
+            &monitor_id,
+            &data,
+            &policy,
+            &mut status,
+            ledger_db.num_blocks().unwrap(),
+            &logger,
+        );
+        assert!(matches!(
+            result,
+            Err(Error::ConsolidationFeeBudgetExhausted(spent, fee, budget))
+                if spent == 2 * Mob::MINIMUM_FEE && fee == Mob::MINIMUM_FEE && budget == 2 * Mob::MINIMUM_FEE
+        ));
+        assert!(status.pending_key_images.is_empty());
+        assert_eq!(status.fees_spent, 2 * Mob::MINIMUM_FEE);
+
+        // Nothing was marked as being spent.
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert!(utxos.iter().all(|utxo| utxo.attempted_spend_height == 0));
+    }
+}
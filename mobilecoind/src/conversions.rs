@@ -4,7 +4,8 @@
 //! types.
 
 use crate::{
-    payments::{Outlay, TxProposal},
+    monitor_store::{ConsolidationPolicy, ConsolidationStatus},
+    payments::{Outlay, OutlayV2, TxProposal},
     utxo_store::UnspentTxOut,
 };
 use mc_account_keys::PublicAddress;
@@ -14,6 +15,7 @@ use mc_mobilecoind_api::{self};
 use mc_transaction_core::{
     ring_signature::KeyImage,
     tx::{Tx, TxOut, TxOutConfirmationNumber},
+    Amount, TokenId,
 };
 use protobuf::RepeatedField;
 use std::convert::TryFrom;
@@ -29,6 +31,10 @@ impl From<&UnspentTxOut> for mc_mobilecoind_api::UnspentTxOut {
         dst.set_attempted_spend_height(src.attempted_spend_height);
         dst.set_attempted_spend_tombstone(src.attempted_spend_tombstone);
         dst.set_token_id(src.token_id);
+        dst.set_memo_payload(src.memo_payload.clone());
+        if let Some(sender) = src.authenticated_sender.as_ref() {
+            dst.set_authenticated_sender(sender.into());
+        }
 
         dst
     }
@@ -45,6 +51,12 @@ impl TryFrom<&mc_mobilecoind_api::UnspentTxOut> for UnspentTxOut {
         let attempted_spend_height = src.attempted_spend_height;
         let attempted_spend_tombstone = src.attempted_spend_tombstone;
         let token_id = src.token_id;
+        let memo_payload = src.get_memo_payload().to_vec();
+        let authenticated_sender = if src.has_authenticated_sender() {
+            Some(PublicAddress::try_from(src.get_authenticated_sender())?)
+        } else {
+            None
+        };
 
         Ok(Self {
             tx_out,
@@ -54,6 +66,8 @@ impl TryFrom<&mc_mobilecoind_api::UnspentTxOut> for UnspentTxOut {
             attempted_spend_height,
             attempted_spend_tombstone,
             token_id,
+            memo_payload,
+            authenticated_sender,
         })
     }
 }
@@ -80,6 +94,75 @@ impl TryFrom<&mc_mobilecoind_api::Outlay> for Outlay {
     }
 }
 
+impl From<&OutlayV2> for mc_mobilecoind_api::OutlayV2 {
+    fn from(src: &OutlayV2) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_value(src.amount.value);
+        dst.set_receiver((&src.receiver).into());
+        dst.set_token_id(*src.amount.token_id);
+
+        dst
+    }
+}
+
+impl TryFrom<&mc_mobilecoind_api::OutlayV2> for OutlayV2 {
+    type Error = ConversionError;
+
+    fn try_from(src: &mc_mobilecoind_api::OutlayV2) -> Result<Self, Self::Error> {
+        let amount = Amount::new(src.value, TokenId::from(src.token_id));
+        let receiver = PublicAddress::try_from(src.get_receiver())?;
+
+        Ok(Self { amount, receiver })
+    }
+}
+
+impl From<&ConsolidationPolicy> for mc_mobilecoind_api::ConsolidationPolicy {
+    fn from(src: &ConsolidationPolicy) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_token_id(src.token_id);
+        dst.set_target_utxo_count(src.target_utxo_count);
+        dst.set_min_utxo_value(src.min_utxo_value);
+        dst.set_fee_budget(src.fee_budget);
+
+        dst
+    }
+}
+
+impl From<&mc_mobilecoind_api::ConsolidationPolicy> for ConsolidationPolicy {
+    fn from(src: &mc_mobilecoind_api::ConsolidationPolicy) -> Self {
+        Self {
+            token_id: src.token_id,
+            target_utxo_count: src.target_utxo_count,
+            min_utxo_value: src.min_utxo_value,
+            fee_budget: src.fee_budget,
+        }
+    }
+}
+
+impl From<&ConsolidationStatus> for mc_mobilecoind_api::ConsolidationStatus {
+    fn from(src: &ConsolidationStatus) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_fees_spent(src.fees_spent);
+        dst.set_num_completed_txs(src.num_completed_txs);
+        dst.set_num_expired_txs(src.num_expired_txs);
+        dst.set_pending_key_images(RepeatedField::from_vec(
+            src.pending_key_images
+                .iter()
+                .map(|key_image| key_image.into())
+                .collect(),
+        ));
+        dst.set_pending_tombstone_block(src.pending_tombstone_block);
+        dst.set_pending_fee(src.pending_fee);
+        dst.set_max_utxo_count(src.max_utxo_count);
+        dst.set_last_error(src.last_error.clone());
+
+        dst
+    }
+}
+
 impl From<&TxProposal> for mc_mobilecoind_api::TxProposal {
     fn from(src: &TxProposal) -> mc_mobilecoind_api::TxProposal {
         let mut dst = mc_mobilecoind_api::TxProposal::new();
@@ -126,8 +209,8 @@ impl TryFrom<&mc_mobilecoind_api::TxProposal> for TxProposal {
         let outlays = src
             .get_outlay_list()
             .iter()
-            .map(Outlay::try_from)
-            .collect::<Result<Vec<Outlay>, ConversionError>>()?;
+            .map(OutlayV2::try_from)
+            .collect::<Result<Vec<OutlayV2>, ConversionError>>()?;
 
         let tx = Tx::try_from(src.get_tx())?;
 
@@ -209,6 +292,8 @@ mod test {
         let value = 789;
         let attempted_spend_height = 1000;
         let attempted_spend_tombstone = 1234;
+        let memo_payload = vec![7u8; 66];
+        let authenticated_sender = AccountKey::random(&mut rng).default_subaddress();
 
         let rust = UnspentTxOut {
             tx_out: tx_out.clone(),
@@ -218,6 +303,8 @@ mod test {
             attempted_spend_height,
             attempted_spend_tombstone,
             token_id: *Mob::ID,
+            memo_payload: memo_payload.clone(),
+            authenticated_sender: Some(authenticated_sender.clone()),
         };
 
         let proto = mc_mobilecoind_api::UnspentTxOut::from(&rust);
@@ -231,6 +318,11 @@ mod test {
         assert_eq!(value, proto.value);
         assert_eq!(attempted_spend_height, proto.attempted_spend_height);
         assert_eq!(attempted_spend_tombstone, proto.attempted_spend_tombstone);
+        assert_eq!(memo_payload, proto.memo_payload);
+        assert_eq!(
+            authenticated_sender,
+            PublicAddress::try_from(proto.get_authenticated_sender()).unwrap()
+        );
 
         // Proto -> Rust
         assert_eq!(rust, UnspentTxOut::try_from(&proto).unwrap());
@@ -258,6 +350,49 @@ mod test {
         assert_eq!(rust, Outlay::try_from(&proto).unwrap());
     }
 
+    #[test]
+    fn test_outlay_v2_conversion() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let public_addr = AccountKey::random(&mut rng).default_subaddress();
+
+        // Rust -> Proto
+        let rust = OutlayV2 {
+            receiver: public_addr.clone(),
+            amount: Amount::new(1234, TokenId::from(5)),
+        };
+        let proto = mc_mobilecoind_api::OutlayV2::from(&rust);
+
+        assert_eq!(proto.value, 1234);
+        assert_eq!(proto.token_id, 5);
+        assert_eq!(
+            PublicAddress::try_from(proto.get_receiver()).unwrap(),
+            public_addr
+        );
+
+        // Proto -> Rust
+        assert_eq!(rust, OutlayV2::try_from(&proto).unwrap());
+    }
+
+    #[test]
+    fn test_consolidation_policy_conversion() {
+        // Rust -> Proto
+        let rust = ConsolidationPolicy {
+            token_id: 1,
+            target_utxo_count: 2,
+            min_utxo_value: 3,
+            fee_budget: 4,
+        };
+        let proto = mc_mobilecoind_api::ConsolidationPolicy::from(&rust);
+
+        assert_eq!(proto.token_id, 1);
+        assert_eq!(proto.target_utxo_count, 2);
+        assert_eq!(proto.min_utxo_value, 3);
+        assert_eq!(proto.fee_budget, 4);
+
+        // Proto -> Rust
+        assert_eq!(rust, ConsolidationPolicy::from(&proto));
+    }
+
     #[test]
     fn test_tx_proposal_conversion() {
         let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
@@ -310,14 +445,16 @@ mod test {
                 attempted_spend_height,
                 attempted_spend_tombstone,
                 token_id: *Mob::ID,
+                memo_payload: Vec::new(),
+                authenticated_sender: None,
             }
         };
 
         let outlay = {
             let public_addr = AccountKey::random(&mut rng).default_subaddress();
-            Outlay {
+            OutlayV2 {
                 receiver: public_addr,
-                value: 1234,
+                amount: Amount::new(1234, TokenId::from(2)),
             }
         };
 
@@ -342,7 +479,7 @@ mod test {
 
         assert_eq!(
             rust.outlays,
-            vec![Outlay::try_from(&proto.get_outlay_list()[0]).unwrap()],
+            vec![OutlayV2::try_from(&proto.get_outlay_list()[0]).unwrap()],
         );
 
         assert_eq!(proto.get_outlay_index_to_tx_out_index().len(), 1);
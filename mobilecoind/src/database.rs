@@ -5,7 +5,10 @@
 use crate::{
     db_crypto::DbCryptoProvider,
     error::Error,
-    monitor_store::{MonitorData, MonitorId, MonitorStore},
+    monitor_bundle::{ExportedMonitor, ExportedProcessedBlock, MonitorBundle},
+    monitor_store::{
+        ConsolidationPolicy, ConsolidationStatus, MonitorData, MonitorId, MonitorStore,
+    },
     processed_block_store::{ProcessedBlockStore, ProcessedTxOut},
     subaddress_store::{SubaddressId, SubaddressSPKId, SubaddressStore},
     utxo_store::{UtxoId, UtxoStore},
@@ -13,13 +16,21 @@ use crate::{
 
 use crate::utxo_store::UnspentTxOut;
 use lmdb::{Environment, Transaction};
+use mc_account_keys::PublicAddress;
 use mc_common::{
     logger::{log, Logger},
     HashMap,
 };
-use mc_transaction_core::ring_signature::KeyImage;
+use mc_crypto_rand::{CryptoRng, RngCore};
+use mc_ledger_db::PruningLimit;
+use mc_transaction_core::{ring_signature::KeyImage, BlockIndex};
 use mc_util_lmdb::{MetadataStore, MetadataStoreSettings};
-use std::{path::Path, sync::Arc};
+use std::{
+    cmp::{max, min},
+    ops::Range,
+    path::Path,
+    sync::Arc,
+};
 
 // LMDB Constants
 const MAX_LMDB_FILE_SIZE: usize = 1_099_511_627_776; // 1 TB
@@ -180,11 +191,205 @@ impl Database {
         Ok(())
     }
 
+    /// Export monitors into a bundle encrypted with `password`. When
+    /// `include_processed_state` is set, the bundle also contains the monitors'
+    /// UTXOs and processed blocks, so that they can be imported without
+    /// rescanning the ledger.
+    pub fn export_monitors(
+        &self,
+        ids: &[MonitorId],
+        include_processed_state: bool,
+        password: &[u8],
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<Vec<u8>, Error> {
+        mc_common::trace_time!(self.logger, "export_monitors");
+
+        let db_txn = self.env.begin_ro_txn()?;
+
+        let monitors = ids
+            .iter()
+            .map(|id| {
+                let data = self.monitor_store.get_data(&db_txn, id)?;
+                if !include_processed_state {
+                    return Ok(ExportedMonitor {
+                        data,
+                        has_processed_state: false,
+                        utxos: Vec::new(),
+                        processed_blocks: Vec::new(),
+                    });
+                }
+
+                let mut utxos = Vec::new();
+                for index in data.subaddress_indexes() {
+                    utxos.extend(self.utxo_store.get_utxos(&db_txn, id, index)?);
+                }
+
+                let processed_blocks = self
+                    .processed_block_store
+                    .get_processed_blocks(
+                        &db_txn,
+                        id,
+                        data.first_block..data.next_block,
+                        usize::MAX,
+                        |_| true,
+                    )?
+                    .into_iter()
+                    .map(|(block_index, tx_outs)| ExportedProcessedBlock {
+                        block_index,
+                        tx_outs,
+                    })
+                    .collect();
+
+                Ok(ExportedMonitor {
+                    data,
+                    has_processed_state: true,
+                    utxos,
+                    processed_blocks,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        MonitorBundle { monitors }.encrypt(&self.crypto_provider, password, rng)
+    }
+
+    /// Import the monitors in a bundle created by `export_monitors`.
+    ///
+    /// Monitors that were exported with their processed state resume syncing
+    /// where the exporting database left off, unless `rescan` is set. All
+    /// other monitors start over from their first block. The import is
+    /// atomic: if any monitor fails to import (e.g. because it already
+    /// exists), none are.
+    pub fn import_monitors(
+        &self,
+        bundle: &[u8],
+        password: &[u8],
+        rescan: bool,
+    ) -> Result<Vec<MonitorId>, Error> {
+        mc_common::trace_time!(self.logger, "import_monitors");
+
+        let bundle = MonitorBundle::decrypt(&self.crypto_provider, bundle, password)?;
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let mut ids = Vec::new();
+
+        for exported_monitor in bundle.monitors {
+            let restore_processed_state = exported_monitor.has_processed_state && !rescan;
+
+            let mut data = exported_monitor.data;
+            if !restore_processed_state {
+                data.next_block = data.first_block;
+                data.consolidation_status = Default::default();
+            }
+
+            let id = self.monitor_store.add(&mut db_txn, &data)?;
+            for index in data.subaddress_indexes() {
+                self.subaddress_store
+                    .insert(&mut db_txn, &id, &data, index)?;
+            }
+
+            if restore_processed_state {
+                for utxo in exported_monitor.utxos.iter() {
+                    if !data.subaddress_indexes().contains(&utxo.subaddress_index) {
+                        return Err(Error::InvalidArgument(
+                            "bundle".to_string(),
+                            format!(
+                                "utxo subaddress index {} is not watched by monitor {}",
+                                utxo.subaddress_index, id
+                            ),
+                        ));
+                    }
+                    self.utxo_store
+                        .append_utxo(&mut db_txn, &id, utxo.subaddress_index, utxo)?;
+                }
+
+                for processed_block in exported_monitor.processed_blocks.iter() {
+                    self.processed_block_store.import_processed_block(
+                        &mut db_txn,
+                        &id,
+                        processed_block.block_index,
+                        &processed_block.tx_outs,
+                    )?;
+                }
+            }
+
+            log::info!(
+                self.logger,
+                "Imported monitor {} (next block {})",
+                id,
+                data.next_block
+            );
+            ids.push(id);
+        }
+
+        db_txn.commit()?;
+
+        Ok(ids)
+    }
+
     pub fn get_monitor_data(&self, id: &MonitorId) -> Result<MonitorData, Error> {
         let db_txn = self.env.begin_ro_txn()?;
         self.monitor_store.get_data(&db_txn, id)
     }
 
+    /// Replace the address book used to authenticate sender memos for a
+    /// given monitor. Only TxOuts discovered after this call are affected.
+    pub fn set_monitor_address_book(
+        &self,
+        id: &MonitorId,
+        address_book: &[PublicAddress],
+    ) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        let mut data = self.monitor_store.get_data(&db_txn, id)?;
+        data.address_book = address_book.to_vec();
+        self.monitor_store.set_data(&mut db_txn, id, &data)?;
+
+        db_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Set or clear the consolidation policy of a given monitor. This resets
+    /// the policy's progress.
+    pub fn set_monitor_consolidation_policy(
+        &self,
+        id: &MonitorId,
+        policy: Option<ConsolidationPolicy>,
+    ) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        let mut data = self.monitor_store.get_data(&db_txn, id)?;
+        data.consolidation_policy = policy;
+        data.consolidation_status = Default::default();
+        self.monitor_store.set_data(&mut db_txn, id, &data)?;
+
+        db_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Update the progress of a given monitor's consolidation policy. This is a
+    /// no-op if the policy has changed since `policy` was read.
+    pub fn set_monitor_consolidation_status(
+        &self,
+        id: &MonitorId,
+        policy: &ConsolidationPolicy,
+        status: &ConsolidationStatus,
+    ) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        let mut data = self.monitor_store.get_data(&db_txn, id)?;
+        if data.consolidation_policy.as_ref() != Some(policy) {
+            return Ok(());
+        }
+        data.consolidation_status = status.clone();
+        self.monitor_store.set_data(&mut db_txn, id, &data)?;
+
+        db_txn.commit()?;
+
+        Ok(())
+    }
+
     pub fn get_monitor_map(&self) -> Result<HashMap<MonitorId, MonitorData>, Error> {
         let db_txn = self.env.begin_ro_txn()?;
         self.monitor_store.get_map(&db_txn)
@@ -195,6 +400,34 @@ impl Database {
         self.monitor_store.get_ids(&db_txn)
     }
 
+    /// Get the lowest block index any monitor still has to process, or None if
+    /// there are no monitors.
+    pub fn get_min_next_block(&self) -> Result<Option<BlockIndex>, Error> {
+        Ok(self
+            .get_monitor_map()?
+            .values()
+            .map(|data| data.next_block)
+            .min())
+    }
+
+    /// A pruning limit for the ledger db, which keeps the contents of all
+    /// blocks that some monitor has yet to process. Nothing is pruned while
+    /// the monitors can not be read, e.g. before the database is unlocked.
+    pub fn ledger_pruning_limit(&self) -> PruningLimit {
+        let mobilecoind_db = self.clone();
+        Arc::new(move || match mobilecoind_db.get_min_next_block() {
+            Ok(min_next_block) => min_next_block.unwrap_or(BlockIndex::MAX),
+            Err(err) => {
+                log::warn!(
+                    mobilecoind_db.logger,
+                    "Not pruning ledger db, failed getting monitors: {}",
+                    err
+                );
+                0
+            }
+        })
+    }
+
     pub fn get_subaddress_id_by_spk(
         &self,
         subaddress_spk: &SubaddressSPKId,
@@ -342,14 +575,49 @@ impl Database {
         self.processed_block_store
             .get_processed_block(&db_txn, monitor_id, block_num)
     }
+
+    /// Get processed block information for a given monitor id over a range of
+    /// blocks, keeping only the ProcessedTxOuts that satisfy `filter`.
+    ///
+    /// The range is clamped to the blocks the monitor has processed so far.
+    /// Blocks with no matching data are skipped, and at most `max_blocks`
+    /// blocks are returned.
+    pub fn get_processed_blocks(
+        &self,
+        monitor_id: &MonitorId,
+        block_range: Range<u64>,
+        max_blocks: usize,
+        filter: impl Fn(&ProcessedTxOut) -> bool,
+    ) -> Result<Vec<(u64, Vec<ProcessedTxOut>)>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        let monitor_data = self.monitor_store.get_data(&db_txn, monitor_id)?;
+        let block_range = max(block_range.start, monitor_data.first_block)
+            ..min(block_range.end, monitor_data.next_block);
+
+        self.processed_block_store.get_processed_blocks(
+            &db_txn,
+            monitor_id,
+            block_range,
+            max_blocks,
+            filter,
+        )
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{error::Error, test_utils::get_test_databases};
+    use crate::{
+        db_crypto::PASSWORD_LEN,
+        error::Error,
+        subscriptions::Subscriptions,
+        sync::SyncThread,
+        test_utils::{get_test_databases, get_test_monitor_data_and_id, wait_for_monitors},
+    };
     use mc_account_keys::AccountKey;
     use mc_common::logger::{test_with_logger, Logger};
+    use mc_ledger_db::Ledger;
     use mc_transaction_core::BlockVersion;
     use rand::{rngs::StdRng, SeedableRng};
     use std::iter::FromIterator;
@@ -519,6 +787,29 @@ mod test {
         );
     }
 
+    // Pruning the ledger never discards blocks an existing monitor has yet to
+    // process.
+    #[test_with_logger]
+    fn test_ledger_pruning_limit(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let (mut ledger_db, mobilecoind_db) =
+            get_test_databases(BlockVersion::ZERO, 3, &[], 10, logger, &mut rng);
+        ledger_db.set_pruning_limit(Some(mobilecoind_db.ledger_pruning_limit()));
+
+        // Without monitors, pruning goes all the way to the horizon.
+        assert_eq!(mobilecoind_db.get_min_next_block().unwrap(), None);
+        assert_eq!(ledger_db.prune_block_contents(8).unwrap(), 2);
+
+        // A monitor that is lagging behind keeps the blocks it has not processed.
+        let (mut monitor_data, _) = get_test_monitor_data_and_id(&mut rng);
+        monitor_data.next_block = 4;
+        mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        assert_eq!(mobilecoind_db.get_min_next_block().unwrap(), Some(4));
+
+        assert_eq!(ledger_db.prune_block_contents(1).unwrap(), 4);
+        assert!(ledger_db.get_block_contents(4).is_ok());
+    }
+
     // Inserting a monitor that overlaps subaddresses of another monitor should
     // result in an error.
     #[test_with_logger]
@@ -618,4 +909,138 @@ mod test {
             .add_monitor(&initial_data)
             .expect("failed adding monitor");
     }
+
+    #[test_with_logger]
+    fn test_export_import_monitors(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let password = [7u8; PASSWORD_LEN];
+
+        // Set up a db with 3 random recipients and 10 blocks, paying our account in
+        // every block.
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            BlockVersion::ZERO,
+            3,
+            &[account_key.default_subaddress()],
+            10,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let data = MonitorData::new(
+            account_key,
+            0,  // first_subaddress
+            10, // num_subaddresses
+            2,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        let mut sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            Subscriptions::default(),
+            Some(1),
+            logger.clone(),
+        );
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        sync_thread.stop();
+
+        let expected_data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
+        let expected_utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert_eq!(expected_data.next_block, 10);
+        assert_eq!(expected_utxos.len(), 8);
+
+        let open_empty_db = || {
+            let db_tmp = TempDir::new("mobilecoind_db").unwrap();
+            let db = Database::new(db_tmp.path(), logger.clone()).unwrap();
+            (db, db_tmp)
+        };
+
+        let full_bundle = mobilecoind_db
+            .export_monitors(&[monitor_id], true, &password, &mut rng)
+            .unwrap();
+
+        // Bundles require a password.
+        assert!(mobilecoind_db
+            .export_monitors(&[monitor_id], true, &[], &mut rng)
+            .is_err());
+
+        // Importing with processed state restores everything.
+        {
+            let (db, _db_tmp) = open_empty_db();
+
+            // The wrong password fails.
+            assert!(db
+                .import_monitors(&full_bundle, &[8u8; PASSWORD_LEN], false)
+                .is_err());
+            assert!(db.get_monitor_ids().unwrap().is_empty());
+
+            assert_eq!(
+                db.import_monitors(&full_bundle, &password, false).unwrap(),
+                vec![monitor_id]
+            );
+            assert_eq!(db.get_monitor_data(&monitor_id).unwrap(), expected_data);
+            assert_eq!(
+                db.get_utxos_for_subaddress(&monitor_id, 0).unwrap(),
+                expected_utxos
+            );
+            for block_index in 2..10 {
+                assert_eq!(
+                    db.get_processed_block(&monitor_id, block_index).unwrap(),
+                    mobilecoind_db
+                        .get_processed_block(&monitor_id, block_index)
+                        .unwrap()
+                );
+            }
+
+            // Subaddresses are indexed.
+            let utxo = &expected_utxos[0];
+            assert_eq!(
+                db.get_subaddress_id_by_utxo_id(&UtxoId::from(utxo))
+                    .unwrap()
+                    .monitor_id,
+                monitor_id
+            );
+
+            // Importing the same monitor twice fails.
+            match db.import_monitors(&full_bundle, &password, false) {
+                Ok(_) => panic!("unexpected success!"),
+                Err(Error::MonitorIdExists) => {}
+                Err(err) => panic!("unexpected error {:?}", err),
+            };
+        }
+
+        // Requesting a rescan discards the processed state.
+        {
+            let (db, _db_tmp) = open_empty_db();
+            db.import_monitors(&full_bundle, &password, true).unwrap();
+
+            let imported_data = db.get_monitor_data(&monitor_id).unwrap();
+            assert_eq!(imported_data.account_key, expected_data.account_key);
+            assert_eq!(imported_data.next_block, 2);
+            assert!(db
+                .get_utxos_for_subaddress(&monitor_id, 0)
+                .unwrap()
+                .is_empty());
+        }
+
+        // Bundles without processed state always rescan.
+        {
+            let bundle = mobilecoind_db
+                .export_monitors(&[monitor_id], false, &password, &mut rng)
+                .unwrap();
+            let (db, _db_tmp) = open_empty_db();
+            db.import_monitors(&bundle, &password, false).unwrap();
+
+            assert_eq!(db.get_monitor_data(&monitor_id).unwrap().next_block, 2);
+            assert!(db
+                .get_utxos_for_subaddress(&monitor_id, 0)
+                .unwrap()
+                .is_empty());
+        }
+    }
 }
@@ -257,6 +257,29 @@ impl DbCryptoProvider {
         Ok(cipher.encrypt(&nonce, plaintext_bytes)?)
     }
 
+    /// Decrypt data with a specific password.
+    /// This is the counterpart of `encrypt_with_password`, used for data that
+    /// was encrypted with a password other than the database's.
+    pub fn decrypt_with_password(
+        &self,
+        password: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, DbCryptoError> {
+        // Short-circuit when no password is being used.
+        if password.is_empty() {
+            return Ok(ciphertext.to_vec());
+        }
+
+        if password.len() != PASSWORD_LEN {
+            return Err(DbCryptoError::InvalidPasswordLength);
+        }
+
+        let (key, nonce) = Self::expand_password(password)?;
+
+        let cipher = Aes256Gcm::new(&key);
+        Ok(cipher.decrypt(&nonce, ciphertext)?)
+    }
+
     /// Decrypt data with the currently set password.
     pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, DbCryptoError> {
         let state = self.state.lock().expect("mutex poisoned");
@@ -539,6 +562,36 @@ mod tests {
             .is_ok());
     }
 
+    #[test]
+    fn test_decrypt_with_password() {
+        let (crypto_provider, _) = get_test_db_crypto_provider();
+
+        let encrypted_data = crypto_provider
+            .encrypt_with_password(&[123; PASSWORD_LEN], &TEST_DATA[..])
+            .unwrap();
+
+        assert_eq!(
+            crypto_provider
+                .decrypt_with_password(&[123; PASSWORD_LEN], &encrypted_data)
+                .unwrap(),
+            TEST_DATA.to_vec()
+        );
+
+        // The wrong password fails.
+        assert!(crypto_provider
+            .decrypt_with_password(&[124; PASSWORD_LEN], &encrypted_data)
+            .is_err());
+        assert!(crypto_provider
+            .decrypt_with_password(&[123; PASSWORD_LEN - 1], &encrypted_data)
+            .is_err());
+
+        // The database password is not involved.
+        assert_ne!(
+            crypto_provider.decrypt(&encrypted_data).unwrap(),
+            TEST_DATA.to_vec()
+        );
+    }
+
     #[test]
     fn test_change_password_rejects_invalid_password_len() {
         let (crypto_provider, _) = get_test_db_crypto_provider();
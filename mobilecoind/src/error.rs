@@ -9,6 +9,7 @@ use mc_connection::Error as ConnectionError;
 use mc_consensus_api::ConversionError;
 use mc_crypto_keys::KeyError;
 use mc_ledger_db::Error as LedgerDbError;
+use mc_transaction_core::SignedContingentInputError;
 use mc_util_lmdb::MetadataStoreError;
 use mc_util_serial::{decode::Error as DecodeError, encode::Error as EncodeError};
 use prost::DecodeError as ProstDecodeError;
@@ -79,6 +80,10 @@ pub enum Error {
     /// Optimization not beneficial: {0}
     OptimizationNotBeneficial(String),
 
+    /// Consolidation fee budget exhausted: {0} spent, next transaction costs
+    /// {1}, budget is {2}
+    ConsolidationFeeBudgetExhausted(u64, u64, u64),
+
     /// Tx build error: {0}
     TxBuild(String),
 
@@ -91,6 +96,10 @@ pub enum Error {
     /// Insufficient funds due to UTXO fragmentation
     InsufficientFundsFragmentedUtxos,
 
+    /// Too many token ids: {0} token ids need inputs, but a transaction has at
+    /// most {1} inputs
+    TooManyTokenIds(usize, u64),
+
     /// The validator node we're tying to talk to was not found
     NodeNotFound,
 
@@ -111,6 +120,12 @@ pub enum Error {
 
     /// Db encryption: {0}
     DbCrypto(DbCryptoError),
+
+    /// Invalid signed contingent input: {0}
+    InvalidSignedContingentInput(String),
+
+    /// Key image already spent
+    KeyImageAlreadySpent,
 }
 
 impl From<RetryError<ConnectionError>> for Error {
@@ -184,3 +199,9 @@ impl From<DbCryptoError> for Error {
         Self::DbCrypto(e)
     }
 }
+
+impl From<SignedContingentInputError> for Error {
+    fn from(e: SignedContingentInputError) -> Self {
+        Self::InvalidSignedContingentInput(e.to_string())
+    }
+}
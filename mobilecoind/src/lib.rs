@@ -8,14 +8,18 @@ pub mod config;
 pub mod database;
 pub mod payments;
 pub mod service;
+pub mod utxo_selection;
 
+mod consolidation;
 mod conversions;
 mod database_key;
 mod db_crypto;
 mod error;
+mod monitor_bundle;
 mod monitor_store;
 mod processed_block_store;
 mod subaddress_store;
+mod subscriptions;
 mod sync;
 mod utxo_store;
 pub use utxo_store::UnspentTxOut;
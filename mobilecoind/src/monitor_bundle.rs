@@ -0,0 +1,156 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Password-encrypted bundles of monitors, used for moving monitors between
+//! mobilecoind databases without copying the LMDB files.
+//!
+//! A bundle always contains the monitors' MonitorData, which includes the
+//! account key and the subaddress range and first block settings. It may
+//! optionally contain the state derived from processing blocks (UTXOs and
+//! processed blocks), in which case the importing side does not need to rescan
+//! the ledger.
+//!
+//! Bundles are encrypted with a key derived from the user's password and a
+//! random per-bundle salt, so that two bundles encrypted with the same password
+//! never share an encryption key.
+
+use crate::{
+    db_crypto::{DbCryptoError, DbCryptoProvider, PASSWORD_LEN},
+    error::Error,
+    monitor_store::MonitorData,
+    processed_block_store::ProcessedTxOut,
+    utxo_store::UnspentTxOut,
+};
+use mc_crypto_hashes::{Blake2b512, Digest};
+use mc_crypto_rand::{CryptoRng, RngCore};
+use prost::Message;
+
+/// The current bundle format version.
+pub const MONITOR_BUNDLE_VERSION: u32 = 1;
+
+/// Domain tag for deriving bundle encryption keys.
+const MONITOR_BUNDLE_KEY_DOMAIN_TAG: &str = "mc_mobilecoind_monitor_bundle";
+
+/// Length of the random salt mixed into the bundle encryption key.
+const SALT_LEN: usize = 32;
+
+/// The ProcessedTxOuts a monitor saw in a given block.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct ExportedProcessedBlock {
+    /// The block index.
+    #[prost(uint64, tag = "1")]
+    pub block_index: u64,
+
+    /// Received and spent TxOuts belonging to the monitor in this block.
+    #[prost(message, repeated, tag = "2")]
+    pub tx_outs: Vec<ProcessedTxOut>,
+}
+
+/// A single exported monitor.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct ExportedMonitor {
+    /// The monitor's data.
+    #[prost(message, required, tag = "1")]
+    pub data: MonitorData,
+
+    /// Whether `utxos` and `processed_blocks` were exported. When false, the
+    /// monitor has to rescan the ledger from its first block after import.
+    #[prost(bool, tag = "2")]
+    pub has_processed_state: bool,
+
+    /// The monitor's unspent TxOuts, as of `data.next_block`.
+    #[prost(message, repeated, tag = "3")]
+    pub utxos: Vec<UnspentTxOut>,
+
+    /// The blocks in which the monitor saw activity.
+    #[prost(message, repeated, tag = "4")]
+    pub processed_blocks: Vec<ExportedProcessedBlock>,
+}
+
+/// A set of exported monitors.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct MonitorBundle {
+    /// The exported monitors.
+    #[prost(message, repeated, tag = "1")]
+    pub monitors: Vec<ExportedMonitor>,
+}
+
+/// The serialized form of a bundle.
+#[derive(Clone, Eq, PartialEq, Message)]
+struct EncryptedMonitorBundle {
+    /// Bundle format version.
+    #[prost(uint32, tag = "1")]
+    version: u32,
+
+    /// Salt used for deriving the encryption key.
+    #[prost(bytes, tag = "2")]
+    salt: Vec<u8>,
+
+    /// The encrypted MonitorBundle.
+    #[prost(bytes, tag = "3")]
+    ciphertext: Vec<u8>,
+}
+
+impl MonitorBundle {
+    /// Serialize and encrypt the bundle with a given password.
+    pub fn encrypt(
+        &self,
+        crypto_provider: &DbCryptoProvider,
+        password: &[u8],
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<Vec<u8>, Error> {
+        let mut salt = vec![0u8; SALT_LEN];
+        rng.fill_bytes(&mut salt);
+
+        let ciphertext = crypto_provider.encrypt_with_password(
+            &bundle_password(password, &salt)?,
+            &mc_util_serial::encode(self),
+        )?;
+
+        Ok(mc_util_serial::encode(&EncryptedMonitorBundle {
+            version: MONITOR_BUNDLE_VERSION,
+            salt,
+            ciphertext,
+        }))
+    }
+
+    /// Decrypt and deserialize a bundle with a given password.
+    pub fn decrypt(
+        crypto_provider: &DbCryptoProvider,
+        bytes: &[u8],
+        password: &[u8],
+    ) -> Result<Self, Error> {
+        let encrypted_bundle: EncryptedMonitorBundle = mc_util_serial::decode(bytes)?;
+        if encrypted_bundle.version != MONITOR_BUNDLE_VERSION {
+            return Err(Error::InvalidArgument(
+                "bundle".to_string(),
+                format!(
+                    "unsupported version {}, expected {}",
+                    encrypted_bundle.version, MONITOR_BUNDLE_VERSION
+                ),
+            ));
+        }
+
+        let plaintext = crypto_provider.decrypt_with_password(
+            &bundle_password(password, &encrypted_bundle.salt)?,
+            &encrypted_bundle.ciphertext,
+        )?;
+
+        Ok(mc_util_serial::decode(&plaintext)?)
+    }
+}
+
+/// Derive the password a bundle is encrypted with from the user's password and
+/// the bundle's salt.
+fn bundle_password(password: &[u8], salt: &[u8]) -> Result<Vec<u8>, Error> {
+    // Bundles are always encrypted, so unlike the database an empty password is
+    // not allowed.
+    if password.len() != PASSWORD_LEN {
+        return Err(DbCryptoError::InvalidPasswordLength.into());
+    }
+
+    let mut hasher = Blake2b512::new();
+    hasher.update(&MONITOR_BUNDLE_KEY_DOMAIN_TAG);
+    hasher.update(salt);
+    hasher.update(password);
+    Ok(hasher.finalize()[..PASSWORD_LEN].to_vec())
+}
@@ -7,13 +7,14 @@
 use crate::{database_key::DatabaseByteArrayKey, db_crypto::DbCryptoProvider, error::Error};
 
 use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
-use mc_account_keys::AccountKey;
+use mc_account_keys::{AccountKey, PublicAddress};
 use mc_common::{
     logger::{log, Logger},
     HashMap,
 };
 use mc_crypto_digestible::{Digestible, MerlinTranscript};
 use mc_crypto_keys::RistrettoPublic;
+use mc_transaction_core::ring_signature::KeyImage;
 use mc_util_serial::Message;
 use std::{convert::TryFrom, ops::Range, sync::Arc};
 
@@ -47,6 +48,77 @@ pub struct MonitorData {
     /// Optional monitor name.
     #[prost(string, tag = "6")]
     pub name: String,
+
+    /// Known contacts, used to authenticate the sender memos of incoming
+    /// TxOuts.
+    #[prost(message, repeated, tag = "7")]
+    pub address_book: Vec<PublicAddress>,
+
+    /// Optional policy for automatically consolidating this monitor's UTXOs.
+    #[prost(message, optional, tag = "8")]
+    pub consolidation_policy: Option<ConsolidationPolicy>,
+
+    /// Progress of the consolidation policy, reset whenever the policy is set.
+    #[prost(message, required, tag = "9")]
+    pub consolidation_status: ConsolidationStatus,
+}
+
+/// A policy for automatically merging a monitor's UTXOs, so that payments never
+/// need more than MAX_INPUTS inputs.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct ConsolidationPolicy {
+    /// The token id to consolidate.
+    #[prost(uint64, tag = "1")]
+    pub token_id: u64,
+
+    /// Consolidate while any subaddress holds more than this many UTXOs.
+    #[prost(uint64, tag = "2")]
+    pub target_utxo_count: u64,
+
+    /// UTXOs worth less than this are neither counted nor spent.
+    #[prost(uint64, tag = "3")]
+    pub min_utxo_value: u64,
+
+    /// The total fee consolidation transactions are allowed to spend.
+    #[prost(uint64, tag = "4")]
+    pub fee_budget: u64,
+}
+
+/// Progress of a monitor's consolidation policy.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct ConsolidationStatus {
+    /// Total fee paid by consolidation transactions that landed in the ledger.
+    #[prost(uint64, tag = "1")]
+    pub fees_spent: u64,
+
+    /// Number of consolidation transactions that landed in the ledger.
+    #[prost(uint64, tag = "2")]
+    pub num_completed_txs: u64,
+
+    /// Number of consolidation transactions that expired without landing.
+    #[prost(uint64, tag = "3")]
+    pub num_expired_txs: u64,
+
+    /// Key images spent by the pending consolidation transaction, if any.
+    #[prost(message, repeated, tag = "4")]
+    pub pending_key_images: Vec<KeyImage>,
+
+    /// Tombstone block of the pending consolidation transaction.
+    #[prost(uint64, tag = "5")]
+    pub pending_tombstone_block: u64,
+
+    /// Fee of the pending consolidation transaction.
+    #[prost(uint64, tag = "6")]
+    pub pending_fee: u64,
+
+    /// The largest number of countable UTXOs held by a single subaddress, as
+    /// of the last check.
+    #[prost(uint64, tag = "7")]
+    pub max_utxo_count: u64,
+
+    /// The reason the last consolidation attempt failed, if it did.
+    #[prost(string, tag = "8")]
+    pub last_error: String,
 }
 
 impl MonitorData {
@@ -72,6 +144,9 @@ impl MonitorData {
             // The next block we need to sync is our first block.
             next_block: first_block,
             name: name.to_owned(),
+            address_book: Vec::new(),
+            consolidation_policy: None,
+            consolidation_status: Default::default(),
         })
     }
 
@@ -2,7 +2,13 @@
 
 //! Construct and submit transactions to the validator network.
 
-use crate::{database::Database, error::Error, monitor_store::MonitorId, utxo_store::UnspentTxOut};
+use crate::{
+    database::Database,
+    error::Error,
+    monitor_store::MonitorId,
+    utxo_selection::{SmallestFirst, UtxoSelectionStrategy},
+    utxo_store::UnspentTxOut,
+};
 use mc_account_keys::{AccountKey, PublicAddress};
 use mc_common::{
     logger::{log, o, Logger},
@@ -21,16 +27,18 @@ use mc_transaction_core::{
     onetime_keys::recover_onetime_private_key,
     ring_signature::KeyImage,
     tx::{Tx, TxOut, TxOutConfirmationNumber, TxOutMembershipProof},
-    Amount, BlockIndex, BlockVersion, TokenId,
+    Amount, BlockIndex, BlockVersion, SignedContingentInput, SignedContingentInputError, TokenId,
 };
 use mc_transaction_std::{
-    EmptyMemoBuilder, InputCredentials, MemoBuilder, ReservedDestination, TransactionBuilder,
+    EmptyMemoBuilder, InputCredentials, MemoBuilder, RTHMemoBuilder, ReservedDestination,
+    SenderMemoCredential, SignedContingentInputBuilder, TransactionBuilder,
 };
 use mc_util_uri::FogUri;
 use rand::Rng;
 use rayon::prelude::*;
 use std::{
     cmp::{max, Reverse},
+    collections::BTreeMap,
     convert::TryFrom,
     iter::empty,
     str::FromStr,
@@ -62,14 +70,57 @@ pub struct Outlay {
     pub receiver: PublicAddress,
 }
 
+/// An outlay which carries its own token id, rather than using the token id of
+/// the transaction it is part of.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OutlayV2 {
+    /// Amount being sent.
+    pub amount: Amount,
+
+    /// Destination.
+    pub receiver: PublicAddress,
+}
+
+/// The memos written on the outputs of a transaction built by mobilecoind.
+pub enum TxMemos {
+    /// Unused memos, written by `EmptyMemoBuilder`.
+    Unused,
+
+    /// Recoverable Transaction History memos. The destination memo goes on the
+    /// change output, so these transactions always have a change output in
+    /// the fee token id, even if the change is zero.
+    RecoverableTransactionHistory(RTHMemoBuilder),
+
+    /// Memos written by some other memo builder. A change output is only
+    /// added if there is change.
+    Other(Box<dyn MemoBuilder + 'static + Send + Sync>),
+}
+
+impl TxMemos {
+    /// Whether a change output is added in the fee token id even if there is
+    /// no change.
+    fn always_write_change(&self) -> bool {
+        matches!(self, Self::RecoverableTransactionHistory(_))
+    }
+
+    fn into_memo_builder(self) -> Box<dyn MemoBuilder + 'static + Send + Sync> {
+        match self {
+            Self::Unused => Box::new(EmptyMemoBuilder::default()),
+            Self::RecoverableTransactionHistory(memo_builder) => Box::new(memo_builder),
+            Self::Other(memo_builder) => memo_builder,
+        }
+    }
+}
+
 /// A single pending transaction.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TxProposal {
     /// UTXOs used as inputs for this transaction.
     pub utxos: Vec<UnspentTxOut>,
 
-    /// Destinations the transaction is being sent to.
-    pub outlays: Vec<Outlay>,
+    /// Destinations the transaction is being sent to, with the token id of
+    /// each.
+    pub outlays: Vec<OutlayV2>,
 
     /// The actual transaction.
     pub tx: Tx,
@@ -186,6 +237,13 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         }
     }
 
+    /// Get the fee for a transaction paid in `token_id`: the highest minimum
+    /// fee set by the configured consensus nodes, or the hard-coded
+    /// FALLBACK_FEE.
+    pub fn get_network_fee(&self, token_id: TokenId) -> u64 {
+        get_fee(&get_block_infos(&self.peer_manager), token_id, 0)
+    }
+
     // Gets the network fee and block_version, unless opt_fee is nonzero.
     // If opt fee is nonzero then we use local ledger block version and this fee,
     // and don't make a network call
@@ -221,8 +279,9 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     /// * `outlays` - Output amounts and recipients.
     /// * `opt_fee` - Transaction fee in picoMOB. If zero, defaults to MIN_FEE.
     /// * `opt_tombstone` - Tombstone block. If zero, sets to default.
-    /// * `opt_memo_builder` - Optional memo builder to use instead of the
-    ///   default one (EmptyMemoBuilder).
+    /// * `memos` - The memos to write on the outputs.
+    /// * `utxo_selection_strategy` - How to choose which of the inputs to
+    ///   spend.
     pub fn build_transaction(
         &self,
         sender_monitor_id: &MonitorId,
@@ -232,11 +291,9 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         outlays: &[Outlay],
         opt_fee: u64,
         opt_tombstone: u64,
-        opt_memo_builder: Option<Box<dyn MemoBuilder + 'static + Send + Sync>>,
+        memos: TxMemos,
+        utxo_selection_strategy: &dyn UtxoSelectionStrategy,
     ) -> Result<TxProposal, Error> {
-        let logger = self.logger.new(o!("sender_monitor_id" => sender_monitor_id.to_string(), "outlays" => format!("{:?}", outlays)));
-        log::trace!(logger, "Building pending transaction...");
-
         // All inputs must be of the correct token id.
         if inputs.iter().any(|utxo| utxo.token_id != *token_id) {
             return Err(Error::InvalidArgument(
@@ -245,6 +302,61 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             ));
         }
 
+        let outlays: Vec<OutlayV2> = outlays
+            .iter()
+            .map(|outlay| OutlayV2 {
+                amount: Amount::new(outlay.value, token_id),
+                receiver: outlay.receiver.clone(),
+            })
+            .collect();
+
+        self.build_mixed_transaction(
+            sender_monitor_id,
+            change_subaddress,
+            inputs,
+            &outlays,
+            token_id,
+            opt_fee,
+            opt_tombstone,
+            memos,
+            utxo_selection_strategy,
+        )
+    }
+
+    /// Create a TxProposal whose outlays may be of several token ids. Inputs
+    /// are selected separately for each token id, and each token id gets its
+    /// own change output.
+    ///
+    /// # Arguments
+    /// * `sender_monitor_id` - Indicates the the account key needed to spend
+    ///   the txo's.
+    /// * `change_subaddress` - Recipient of any change.
+    /// * `inputs` - UTXOs that may be spent by the transaction.
+    /// * `outlays` - Output amounts, token ids and recipients.
+    /// * `fee_token_id` - The token id to pay the fee in.
+    /// * `opt_fee` - Transaction fee. If zero, defaults to the highest fee set
+    ///   by configured consensus nodes, or the hard-coded FALLBACK_FEE.
+    /// * `opt_tombstone` - Tombstone block. If zero, sets to default.
+    /// * `memos` - The memos to write on the outputs. Memos other than unused
+    ///   ones do not support transactions that mix token ids.
+    /// * `utxo_selection_strategy` - How to choose which of the inputs to
+    ///   spend.
+    #[allow(clippy::too_many_arguments)]
+    pub fn build_mixed_transaction(
+        &self,
+        sender_monitor_id: &MonitorId,
+        change_subaddress: u64,
+        inputs: &[UnspentTxOut],
+        outlays: &[OutlayV2],
+        fee_token_id: TokenId,
+        opt_fee: u64,
+        opt_tombstone: u64,
+        memos: TxMemos,
+        utxo_selection_strategy: &dyn UtxoSelectionStrategy,
+    ) -> Result<TxProposal, Error> {
+        let logger = self.logger.new(o!("sender_monitor_id" => sender_monitor_id.to_string(), "outlays" => format!("{:?}", outlays)));
+        log::trace!(logger, "Building pending transaction...");
+
         // Must have at least one output
         if outlays.is_empty() {
             return Err(Error::TxBuild("Must have at least one destination".into()));
@@ -253,25 +365,62 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         // Get sender monitor data.
         let sender_monitor_data = self.mobilecoind_db.get_monitor_data(sender_monitor_id)?;
 
-        // Figure out total amount of transaction (excluding fee).
-        let total_value: u64 = outlays.iter().map(|outlay| outlay.value).sum();
-        log::trace!(
-            logger,
-            "Total transaction value excluding fees: {}",
-            total_value
-        );
-
         // Figure out the block_version and fee (involves network round-trips to
         // consensus, unless opt_fee is non-zero)
-        let (fee, block_version) = self.get_network_fee_and_block_version(token_id, opt_fee)?;
+        let (fee, block_version) = self.get_network_fee_and_block_version(fee_token_id, opt_fee)?;
 
         // Confirm that we understand this block version
         let block_version =
             BlockVersion::try_from(block_version).map_err(|err| Error::TxBuild(err.to_string()))?;
 
-        // Select the UTXOs to be used for this transaction.
-        let selected_utxos =
-            Self::select_utxos_for_value(token_id, inputs, total_value + fee, MAX_INPUTS as usize)?;
+        // Figure out the total value we need to spend in each token id, including the
+        // fee.
+        let mut required_values: BTreeMap<u64, u64> = BTreeMap::new();
+        for outlay in outlays {
+            *required_values.entry(*outlay.amount.token_id).or_default() += outlay.amount.value;
+        }
+        *required_values.entry(*fee_token_id).or_default() += fee;
+        log::trace!(
+            logger,
+            "Total transaction values by token id, including fees: {:?}",
+            required_values
+        );
+
+        // Transactions involving several token ids need support from the network.
+        if required_values.len() > 1 {
+            if !block_version.mixed_transactions_are_supported() {
+                return Err(Error::TxBuild(format!(
+                    "block version {} does not support mixed transactions",
+                    *block_version
+                )));
+            }
+            if !matches!(memos, TxMemos::Unused) {
+                return Err(Error::TxBuild(
+                    "memos are not supported for transactions mixing token ids".into(),
+                ));
+            }
+        }
+
+        // Select the UTXOs to be used for this transaction, leaving at least one input
+        // for each token id that still needs to be covered.
+        let nonzero_values: Vec<(u64, u64)> = required_values
+            .into_iter()
+            .filter(|(_token_id, value)| *value > 0)
+            .collect();
+        let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
+        for (i, (token_id, value)) in nonzero_values.iter().enumerate() {
+            let num_remaining_token_ids = nonzero_values.len() - i - 1;
+            let max_inputs = (MAX_INPUTS as usize)
+                .checked_sub(selected_utxos.len() + num_remaining_token_ids)
+                .ok_or(Error::TooManyTokenIds(nonzero_values.len(), MAX_INPUTS))?;
+            selected_utxos.extend(Self::select_utxos_for_value(
+                TokenId::from(*token_id),
+                inputs,
+                *value,
+                max_inputs,
+                utxo_selection_strategy,
+            )?);
+        }
         log::trace!(
             logger,
             "Selected {} utxos ({:?})",
@@ -321,14 +470,13 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             &selected_utxos_with_proofs,
             rings,
             block_version,
-            token_id,
-            fee,
+            Amount::new(fee, fee_token_id),
             &sender_monitor_data.account_key,
             change_subaddress,
             outlays,
             tombstone_block,
             &self.fog_resolver_factory,
-            opt_memo_builder,
+            memos,
             &mut rng,
             &self.logger,
         )?;
@@ -352,6 +500,36 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         subaddress_index: u64,
         token_id: TokenId,
         opt_fee: u64,
+    ) -> Result<TxProposal, Error> {
+        self.generate_consolidation_tx(
+            monitor_id,
+            subaddress_index,
+            token_id,
+            MAX_INPUTS as usize,
+            0,
+            opt_fee,
+        )
+    }
+
+    /// Create a TxProposal that merges up to `max_inputs` UTXOs into a single
+    /// larger UTXO, considering only UTXOs worth at least `min_utxo_value`.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - Monitor ID of the inputs to spend.
+    /// * `subaddress_index` - Subaddress of the inputs to spend.
+    /// * `token_id` - Token id to transact in.
+    /// * `max_inputs` - Maximal number of inputs to merge, at least 2.
+    /// * `min_utxo_value` - UTXOs worth less than this are left alone.
+    /// * `opt_fee` - Optional fee to use. If zero, we will attempt to query the
+    ///   network for fee information.
+    pub fn generate_consolidation_tx(
+        &self,
+        monitor_id: &MonitorId,
+        subaddress_index: u64,
+        token_id: TokenId,
+        max_inputs: usize,
+        min_utxo_value: u64,
+        opt_fee: u64,
     ) -> Result<TxProposal, Error> {
         let logger = self.logger.new(
             o!("monitor_id" => monitor_id.to_string(), "subaddress_index" => subaddress_index),
@@ -373,13 +551,14 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
 
         // Select UTXOs that will be spent by this transaction.
         let selected_utxos = {
-            let inputs = self
+            let mut inputs = self
                 .mobilecoind_db
                 .get_utxos_for_subaddress(monitor_id, subaddress_index)?;
+            inputs.retain(|utxo| utxo.token_id == *token_id && utxo.value >= min_utxo_value);
             Self::select_utxos_for_optimization(
                 num_blocks_in_ledger,
                 &inputs,
-                MAX_INPUTS as usize,
+                max_inputs,
                 token_id,
                 fee,
             )?
@@ -432,9 +611,9 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         log::trace!(logger, "Tombstone block set to {}", tombstone_block);
 
         // We are paying ourselves the entire amount.
-        let outlays = vec![Outlay {
+        let outlays = vec![OutlayV2 {
             receiver: monitor_data.account_key.subaddress(subaddress_index),
-            value: total_value - fee,
+            amount: Amount::new(total_value - fee, token_id),
         }];
 
         // Build and return the TxProposal object
@@ -443,14 +622,13 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             &selected_utxos_with_proofs,
             rings,
             block_version,
-            token_id,
-            fee,
+            Amount::new(fee, token_id),
             &monitor_data.account_key,
             subaddress_index,
             &outlays,
             tombstone_block,
             &self.fog_resolver_factory,
-            None,
+            TxMemos::Unused,
             &mut rng,
             &self.logger,
         )?;
@@ -535,9 +713,9 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         log::trace!(logger, "Tombstone block set to {}", tombstone_block);
 
         // The entire value goes to receiver
-        let outlays = vec![Outlay {
+        let outlays = vec![OutlayV2 {
             receiver: receiver.clone(),
-            value: total_value - fee,
+            amount: Amount::new(total_value - fee, token_id),
         }];
 
         // Build and return the TxProposal object
@@ -546,14 +724,13 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             &inputs_with_proofs,
             rings,
             block_version,
-            token_id,
-            fee,
+            Amount::new(fee, token_id),
             account_key,
             0,
             &outlays,
             tombstone_block,
             &self.fog_resolver_factory,
-            None,
+            TxMemos::Unused,
             &mut rng,
             &self.logger,
         )?;
@@ -566,6 +743,437 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(tx_proposal)
     }
 
+    /// Create a signed contingent input (MCIP #31), which offers the entire
+    /// value of a single UTXO in exchange for a set of required outputs.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - Monitor ID owning the UTXO.
+    /// * `utxo` - The UTXO being offered.
+    /// * `required_outlays` - Outputs that must appear in any transaction
+    ///   spending the UTXO.
+    /// * `opt_tombstone` - Maximum tombstone block of a transaction spending
+    ///   the UTXO. If zero, sets to default.
+    pub fn generate_signed_contingent_input(
+        &self,
+        monitor_id: &MonitorId,
+        utxo: &UnspentTxOut,
+        required_outlays: &[OutlayV2],
+        opt_tombstone: u64,
+    ) -> Result<SignedContingentInput, Error> {
+        let logger = self.logger.new(o!("monitor_id" => monitor_id.to_string(), "required_outlays" => format!("{:?}", required_outlays)));
+        log::trace!(logger, "Generating signed contingent input...");
+
+        // Get monitor data.
+        let monitor_data = self.mobilecoind_db.get_monitor_data(monitor_id)?;
+
+        // Figure out the block_version. The fee is paid by whoever incorporates the
+        // signed contingent input into a transaction, so it is not used here.
+        let (_fee, block_version) =
+            self.get_network_fee_and_block_version(TokenId::from(utxo.token_id), 0)?;
+
+        // Make sure we understand this block version
+        let block_version =
+            BlockVersion::try_from(block_version).map_err(|err| Error::TxBuild(err.to_string()))?;
+
+        // The UTXO with its proof of membership.
+        let proof = self
+            .get_membership_proofs(core::slice::from_ref(&utxo.tx_out))?
+            .pop()
+            .ok_or_else(|| Error::TxBuild("missing membership proof".to_string()))?;
+        log::trace!(logger, "Got membership proof");
+
+        // A ring of mixins for the UTXO.
+        let (ring, membership_proofs): (Vec<TxOut>, Vec<TxOutMembershipProof>) = self
+            .get_rings(DEFAULT_RING_SIZE, 1, &[proof.index])?
+            .pop()
+            .ok_or_else(|| Error::TxBuild("missing ring".to_string()))?
+            .into_iter()
+            .unzip();
+        log::trace!(logger, "Got ring");
+
+        let input_credentials = Self::build_input_credentials(
+            utxo,
+            &proof,
+            ring,
+            membership_proofs,
+            &monitor_data.account_key,
+            &logger,
+        )?;
+
+        // InputCredentials sorts the ring, so the global indices are taken from the
+        // sorted membership proofs.
+        let tx_out_global_indices = input_credentials
+            .membership_proofs
+            .iter()
+            .map(|proof| proof.index)
+            .collect();
+
+        // Collect all required FogUris from the required outputs, then pass to
+        // resolver factory
+        let fog_resolver = {
+            let fog_uris = required_outlays
+                .iter()
+                .filter_map(|outlay| extract_fog_uri(&outlay.receiver).transpose())
+                .collect::<Result<Vec<_>, _>>()?;
+            (self.fog_resolver_factory)(&fog_uris).map_err(Error::Fog)?
+        };
+
+        let mut sci_builder = SignedContingentInputBuilder::new_with_box(
+            block_version,
+            input_credentials,
+            tx_out_global_indices,
+            fog_resolver,
+            Box::new(EmptyMemoBuilder::default()),
+        )
+        .map_err(|err| {
+            Error::TxBuild(format!(
+                "Error creating signed contingent input builder: {}",
+                err
+            ))
+        })?;
+
+        let mut rng = rand::thread_rng();
+        for outlay in required_outlays {
+            sci_builder
+                .add_required_output(outlay.amount, &outlay.receiver, &mut rng)
+                .map_err(|err| Error::TxBuild(format!("failed adding required output: {}", err)))?;
+        }
+
+        // Come up with tombstone block.
+        let tombstone_block = if opt_tombstone > 0 {
+            opt_tombstone
+        } else {
+            self.ledger_db.num_blocks()? + DEFAULT_NEW_TX_BLOCK_ATTEMPTS
+        };
+        let tombstone_block = sci_builder.set_tombstone_block(tombstone_block);
+        log::trace!(logger, "Tombstone block set to {}", tombstone_block);
+
+        let sci = sci_builder.build(&mut rng).map_err(|err| {
+            Error::TxBuild(format!("build signed contingent input failed: {}", err))
+        })?;
+        log::trace!(
+            logger,
+            "Signed contingent input constructed, key image={:?}",
+            sci.key_image()
+        );
+
+        Ok(sci)
+    }
+
+    /// Check that a signed contingent input received from a counterparty is
+    /// well-formed, and that it can currently be incorporated in a transaction
+    /// according to our local ledger.
+    ///
+    /// # Arguments
+    /// * `sci` - The signed contingent input to check.
+    pub fn validate_signed_contingent_input(
+        &self,
+        sci: &SignedContingentInput,
+    ) -> Result<(), Error> {
+        let block_version = BlockVersion::try_from(sci.block_version)
+            .map_err(|err| Error::InvalidSignedContingentInput(err.to_string()))?;
+        if !block_version.signed_input_rules_are_supported() {
+            return Err(Error::InvalidSignedContingentInput(format!(
+                "block version {} does not support signed input rules",
+                *block_version
+            )));
+        }
+
+        // Check the signature and the required output amounts.
+        sci.validate()?;
+
+        // The input must not have been spent already.
+        if self.ledger_db.contains_key_image(&sci.key_image())? {
+            return Err(Error::KeyImageAlreadySpent);
+        }
+
+        // The input must not have expired.
+        let num_blocks = self.ledger_db.num_blocks()?;
+        if let Some(rules) = sci.tx_in.input_rules.as_ref() {
+            if rules.max_tombstone_block != 0 && num_blocks >= rules.max_tombstone_block {
+                return Err(Error::InvalidSignedContingentInput(format!(
+                    "max tombstone block {} exceeded",
+                    rules.max_tombstone_block
+                )));
+            }
+        }
+
+        // Each ring member must be in our ledger, at its claimed global index, so that
+        // we can provide membership proofs for it.
+        // `validate` checks that there is one index per ring member too, but zipping
+        // them must never silently skip any.
+        if sci.tx_out_global_indices.len() != sci.tx_in.ring.len() {
+            return Err(SignedContingentInputError::WrongNumberOfGlobalIndices.into());
+        }
+        for (tx_out, index) in sci.tx_in.ring.iter().zip(sci.tx_out_global_indices.iter()) {
+            match self.ledger_db.get_tx_out_by_index(*index) {
+                Ok(ledger_tx_out) if ledger_tx_out == *tx_out => {}
+                Ok(_) | Err(LedgerError::NotFound) => {
+                    return Err(Error::InvalidSignedContingentInput(format!(
+                        "ring member not found at global index {}",
+                        index
+                    )));
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create a TxProposal which fills a signed contingent input received from
+    /// a counterparty. Our inputs pay for the outputs required by the signed
+    /// contingent input, and for the fee. The value offered by the signed
+    /// contingent input, net of anything we pay in the same token, is sent to
+    /// `change_subaddress` and is the single outlay of the proposal.
+    ///
+    /// # Arguments
+    /// * `sender_monitor_id` - Indicates the the account key needed to spend
+    ///   the txo's.
+    /// * `change_subaddress` - Recipient of any change and of the offered
+    ///   value.
+    /// * `inputs` - UTXOs that may be spent by the transaction.
+    /// * `sci` - The signed contingent input being filled.
+    /// * `fee_token_id` - The token id to pay the fee in.
+    /// * `opt_fee` - Transaction fee. If zero, defaults to the highest fee set
+    ///   by configured consensus nodes, or the hard-coded FALLBACK_FEE.
+    /// * `opt_tombstone` - Tombstone block. If zero, sets to default. This is
+    ///   clamped to the limit imposed by the signed contingent input.
+    #[allow(clippy::too_many_arguments)]
+    pub fn build_signed_contingent_input_fill_transaction(
+        &self,
+        sender_monitor_id: &MonitorId,
+        change_subaddress: u64,
+        inputs: &[UnspentTxOut],
+        mut sci: SignedContingentInput,
+        fee_token_id: TokenId,
+        opt_fee: u64,
+        opt_tombstone: u64,
+    ) -> Result<TxProposal, Error> {
+        let logger = self.logger.new(o!("sender_monitor_id" => sender_monitor_id.to_string(), "key_image" => format!("{:?}", sci.key_image())));
+        log::trace!(
+            logger,
+            "Building signed contingent input fill transaction..."
+        );
+
+        self.validate_signed_contingent_input(&sci)?;
+
+        // Get sender monitor data.
+        let sender_monitor_data = self.mobilecoind_db.get_monitor_data(sender_monitor_id)?;
+        let account_key = &sender_monitor_data.account_key;
+
+        // Figure out the block_version and fee (involves network round-trips to
+        // consensus, unless opt_fee is non-zero)
+        let (fee, block_version) = self.get_network_fee_and_block_version(fee_token_id, opt_fee)?;
+
+        // Confirm that we understand this block version, and that it allows
+        // inputs of different token ids.
+        let block_version =
+            BlockVersion::try_from(block_version).map_err(|err| Error::TxBuild(err.to_string()))?;
+        if !block_version.mixed_transactions_are_supported() {
+            return Err(Error::TxBuild(format!(
+                "block version {} does not support mixed transactions",
+                *block_version
+            )));
+        }
+
+        // The net value we receive in each token id, before spending any of our own
+        // inputs.
+        let offered_amount = Amount::from(&sci.pseudo_output_amount);
+        let mut net_values: BTreeMap<u64, i128> = BTreeMap::new();
+        *net_values.entry(*offered_amount.token_id).or_default() += offered_amount.value as i128;
+        for required_amount in sci.required_output_amounts.iter() {
+            *net_values.entry(required_amount.token_id).or_default() -=
+                required_amount.value as i128;
+        }
+        *net_values.entry(*fee_token_id).or_default() -= fee as i128;
+        log::trace!(logger, "Net values by token id: {:?}", net_values);
+
+        // Select UTXOs covering each token id we are short of. The signed contingent
+        // input takes up one of the input slots.
+        let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
+        for (token_id, net_value) in net_values.iter() {
+            if *net_value >= 0 {
+                continue;
+            }
+            let value = u64::try_from(-net_value).map_err(|_| {
+                Error::InvalidSignedContingentInput("required value overflows u64".to_string())
+            })?;
+            let max_inputs = (MAX_INPUTS as usize - 1).saturating_sub(selected_utxos.len());
+            if max_inputs == 0 {
+                return Err(Error::InsufficientFundsFragmentedUtxos);
+            }
+            selected_utxos.extend(Self::select_utxos_for_value(
+                TokenId::from(*token_id),
+                inputs,
+                value,
+                max_inputs,
+                &SmallestFirst,
+            )?);
+        }
+        log::trace!(
+            logger,
+            "Selected {} utxos ({:?})",
+            selected_utxos.len(),
+            selected_utxos,
+        );
+
+        // The selected_utxos with corresponding proofs of membership.
+        let selected_utxos_with_proofs: Vec<(UnspentTxOut, TxOutMembershipProof)> = {
+            let outputs: Vec<TxOut> = selected_utxos
+                .iter()
+                .map(|utxo| utxo.tx_out.clone())
+                .collect();
+            let proofs = self.get_membership_proofs(&outputs)?;
+
+            selected_utxos
+                .iter()
+                .cloned()
+                .zip(proofs.into_iter())
+                .collect()
+        };
+
+        // The membership proofs supplied by the counterparty may be stale, and are not
+        // covered by its signature, so we replace them with our own.
+        sci.tx_in.proofs = self
+            .ledger_db
+            .get_tx_out_proof_of_memberships(&sci.tx_out_global_indices)?;
+        log::trace!(logger, "Got membership proofs");
+
+        // A ring of mixins for each UTXO.
+        let rings = {
+            let excluded_tx_out_indices: Vec<u64> = selected_utxos_with_proofs
+                .iter()
+                .map(|(_, proof)| proof.index)
+                .chain(sci.tx_out_global_indices.iter().cloned())
+                .collect();
+
+            self.get_rings(
+                DEFAULT_RING_SIZE, // TODO configurable ring size
+                selected_utxos_with_proofs.len(),
+                &excluded_tx_out_indices,
+            )?
+        };
+        log::trace!(logger, "Got {} rings", rings.len());
+
+        // Collect all required FogUris from public addresses, then pass to resolver
+        // factory. The outputs required by the signed contingent input were already
+        // created by the counterparty.
+        let change_address = account_key.subaddress(change_subaddress);
+        let fog_resolver = {
+            let fog_uris = extract_fog_uri(&change_address)?
+                .into_iter()
+                .collect::<Vec<_>>();
+            (self.fog_resolver_factory)(&fog_uris).map_err(Error::Fog)?
+        };
+
+        let mut tx_builder = TransactionBuilder::new_with_box(
+            block_version,
+            Amount::new(fee, fee_token_id),
+            fog_resolver,
+            Box::new(EmptyMemoBuilder::default()),
+        )
+        .map_err(|err| Error::TxBuild(format!("Error creating transaction builder: {}", err)))?;
+
+        // Add the signed contingent input, together with the outputs it requires.
+        tx_builder.add_presigned_input(sci)?;
+
+        // Add our own inputs.
+        let mut rings_and_proofs: Vec<(Vec<TxOut>, Vec<TxOutMembershipProof>)> = rings
+            .into_iter()
+            .map(|tuples| tuples.into_iter().unzip())
+            .collect();
+        for (utxo, proof) in selected_utxos_with_proofs.iter() {
+            let (ring, membership_proofs) = rings_and_proofs
+                .pop()
+                .ok_or_else(|| Error::TxBuild("rings_and_proofs was empty".to_string()))?;
+            tx_builder.add_input(Self::build_input_credentials(
+                utxo,
+                proof,
+                ring,
+                membership_proofs,
+                account_key,
+                &logger,
+            )?);
+        }
+
+        // Whatever is left over in each token id comes back to us. The offered value
+        // is an outlay, so that the caller gets a receipt for it, everything else is
+        // change.
+        let mut rng = rand::thread_rng();
+        let change_dest =
+            ReservedDestination::from_subaddress_index(account_key, Some(change_subaddress), None);
+        let mut outlays = Vec::new();
+        let mut outlay_tx_out = None;
+        let mut outlay_confirmation_numbers = Vec::new();
+        for (token_id, net_value) in net_values.iter() {
+            let selected_value: u64 = selected_utxos
+                .iter()
+                .filter(|utxo| utxo.token_id == *token_id)
+                .map(|utxo| utxo.value)
+                .sum();
+            let leftover = net_value + selected_value as i128;
+            if leftover <= 0 {
+                continue;
+            }
+            let amount = Amount::new(leftover as u64, TokenId::from(*token_id));
+
+            if *net_value > 0 && *token_id == *offered_amount.token_id {
+                let (tx_out, confirmation_number) = tx_builder
+                    .add_output(amount, &change_address, &mut rng)
+                    .map_err(|err| Error::TxBuild(format!("failed adding output: {}", err)))?;
+                outlays.push(OutlayV2 {
+                    amount,
+                    receiver: change_address.clone(),
+                });
+                outlay_tx_out = Some(tx_out);
+                outlay_confirmation_numbers.push(confirmation_number);
+            } else {
+                tx_builder
+                    .add_change_output(amount, &change_dest, &mut rng)
+                    .map_err(|err| {
+                        Error::TxBuild(format!("failed adding output (change): {}", err))
+                    })?;
+            }
+        }
+
+        // Come up with tombstone block.
+        let tombstone_block = if opt_tombstone > 0 {
+            opt_tombstone
+        } else {
+            self.ledger_db.num_blocks()? + DEFAULT_NEW_TX_BLOCK_ATTEMPTS
+        };
+        let tombstone_block = tx_builder.set_tombstone_block(tombstone_block);
+        log::trace!(logger, "Tombstone block set to {}", tombstone_block);
+
+        // Build tx.
+        let tx = tx_builder
+            .build(&mut rng)
+            .map_err(|err| Error::TxBuild(format!("build tx failed: {}", err)))?;
+        log::trace!(logger, "Tx constructed, hash={}", tx.tx_hash());
+
+        // Map the TxOut of our outlay, if any, to its index in the transaction.
+        let outlay_index_to_tx_out_index = outlay_tx_out
+            .and_then(|outlay_tx_out| {
+                tx.prefix
+                    .outputs
+                    .iter()
+                    .position(|tx_out| *tx_out == outlay_tx_out)
+            })
+            .map(|tx_out_index| (0, tx_out_index))
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        assert_eq!(outlay_index_to_tx_out_index.len(), outlays.len());
+
+        Ok(TxProposal {
+            utxos: selected_utxos,
+            outlays,
+            tx,
+            outlay_index_to_tx_out_index,
+            outlay_confirmation_numbers,
+        })
+    }
+
     /// Submit a previously built tx proposal to the network.
     pub fn submit_tx_proposal(&self, tx_proposal: &TxProposal) -> Result<u64, Error> {
         // Pick a peer to submit to.
@@ -596,13 +1204,15 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(block_height)
     }
 
-    /// Returns a subset of UTXOs totalling at least the given amount.
+    /// Returns a subset of UTXOs totalling at least the given amount, chosen
+    /// by the given strategy.
     // TODO: This method should take attempted_spend_height into account.
     fn select_utxos_for_value(
         token_id: TokenId,
         utxos: &[UnspentTxOut],
         value: u64,
         max_inputs: usize,
+        strategy: &dyn UtxoSelectionStrategy,
     ) -> Result<Vec<UnspentTxOut>, Error> {
         // Sort the utxos in descending order by value.
         let mut sorted_utxos: Vec<UnspentTxOut> = utxos
@@ -629,27 +1239,12 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         }
 
         // Choose utxos to spend.
-        let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
-        loop {
-            let total: u64 = selected_utxos.iter().map(|utxo| utxo.value).sum();
-            if total >= value {
-                break;
-            }
-
-            // Grab the next (smallest utxo)
-            let next_utxo = sorted_utxos.pop().ok_or(Error::InsufficientFunds)?;
-            selected_utxos.push(next_utxo.clone());
-
-            // Cap at maximum allowed inputs.
-            if selected_utxos.len() > max_inputs {
-                // Remove the lowest utxo.
-                selected_utxos.remove(0);
-            }
-        }
+        let selected_utxos = strategy.select(sorted_utxos, value, max_inputs);
 
         // Sanity.
         assert!(!selected_utxos.is_empty());
         assert!(selected_utxos.len() <= max_inputs);
+        assert!(selected_utxos.iter().map(|utxo| utxo.value).sum::<u64>() >= value);
 
         // Return selected utxos.
         Ok(selected_utxos)
@@ -821,30 +1416,28 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     /// * `inputs` - UTXOs to spend, with membership proofs.
     /// * `rings` - A set of mixins for each input, with membership proofs.
     /// * `block_version` - The block version to target for this transaction
-    /// * `token_id` - The token id to transact in
-    /// * `fee` - Transaction fee, in picoMOB.
+    /// * `fee` - Transaction fee, and the token id it is paid in.
     /// * `from_account_key` - Owns the inputs. Also the recipient of any
     ///   change.
-    /// * `change_subaddress` - Subaddress for change recipient.
+    /// * `change_subaddress` - Subaddress for change recipient. Each token id
+    ///   gets its own change output.
     /// * `destinations` - Outputs of the transaction.
     /// * `tombstone_block` - Tombstone block of the transaciton.
     /// * `fog_pubkey_resolver` - Provides Fog key report, when Fog is enabled.
-    /// * `opt_memo_builder` - Optional memo builder to use instead of the
-    ///   default one (EmptyMemoBuilder).
+    /// * `memos` - The memos to write on the outputs.
     /// * `rng` - randomness
     /// * `logger` - Logger
     fn build_tx_proposal(
         inputs: &[(UnspentTxOut, TxOutMembershipProof)],
         rings: Vec<Vec<(TxOut, TxOutMembershipProof)>>,
         block_version: BlockVersion,
-        token_id: TokenId,
-        fee: u64,
+        fee: Amount,
         from_account_key: &AccountKey,
         change_subaddress: u64,
-        destinations: &[Outlay],
+        destinations: &[OutlayV2],
         tombstone_block: BlockIndex,
         fog_resolver_factory: &Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>,
-        opt_memo_builder: Option<Box<dyn MemoBuilder + 'static + Send + Sync>>,
+        memos: TxMemos,
         rng: &mut (impl RngCore + CryptoRng),
         logger: &Logger,
     ) -> Result<TxProposal, Error> {
@@ -877,13 +1470,11 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         };
 
         // Create tx_builder.
-        // TODO (GH #1522): Use RTH memo builder, optionally?
-        let memo_builder: Box<dyn MemoBuilder + Send + Sync> =
-            opt_memo_builder.unwrap_or_else(|| Box::new(EmptyMemoBuilder::default()));
+        let always_write_change = memos.always_write_change();
+        let memo_builder = memos.into_memo_builder();
 
-        let fee_amount = Amount::new(fee, token_id);
         let mut tx_builder =
-            TransactionBuilder::new_with_box(block_version, fee_amount, fog_resolver, memo_builder)
+            TransactionBuilder::new_with_box(block_version, fee, fog_resolver, memo_builder)
                 .map_err(|err| {
                     Error::TxBuild(format!("Error creating transaction builder: {}", err))
                 })?;
@@ -896,115 +1487,56 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
 
         // Add inputs to the tx.
         for (utxo, proof) in inputs {
-            let (mut ring, mut membership_proofs) = rings_and_proofs
+            let (ring, membership_proofs) = rings_and_proofs
                 .pop()
                 .ok_or_else(|| Error::TxBuild("rings_and_proofs was empty".to_string()))?;
-            assert_eq!(
-                ring.len(),
-                membership_proofs.len(),
-                "Each ring element must have a corresponding membership proof."
-            );
-
-            // Add the input to the ring.
-            let position_opt = ring.iter().position(|tx_out| *tx_out == utxo.tx_out);
-            let real_key_index = match position_opt {
-                Some(position) => {
-                    // The input is already present in the ring.
-                    // This could happen if ring elements are sampled randomly from the ledger.
-                    position
-                }
-                None => {
-                    // The input is not already in the ring.
-                    if ring.is_empty() {
-                        // Append the input and its proof of membership.
-                        ring.push(utxo.tx_out.clone());
-                        membership_proofs.push(proof.clone());
-                    } else {
-                        // Replace the first element of the ring.
-                        ring[0] = utxo.tx_out.clone();
-                        membership_proofs[0] = proof.clone();
-                    }
-                    // The real input is always the first element. This is safe because
-                    // TransactionBuilder sorts each ring.
-                    0
-                }
-            };
-
-            assert_eq!(
-                ring.len(),
-                membership_proofs.len(),
-                "Each ring element must have a corresponding membership proof."
-            );
 
-            let public_key = RistrettoPublic::try_from(&utxo.tx_out.public_key).unwrap();
-            let onetime_private_key = recover_onetime_private_key(
-                &public_key,
-                from_account_key.view_private_key(),
-                &from_account_key.subaddress_spend_private(utxo.subaddress_index),
-            );
-
-            let key_image = KeyImage::from(&onetime_private_key);
-            log::debug!(
-                logger,
-                "Adding input: ring {:?}, utxo index {:?}, key image {:?}, pubkey {:?}",
+            tx_builder.add_input(Self::build_input_credentials(
+                utxo,
+                proof,
                 ring,
-                real_key_index,
-                key_image,
-                public_key
-            );
+                membership_proofs,
+                from_account_key,
+                logger,
+            )?);
+        }
 
-            tx_builder.add_input(
-                InputCredentials::new(
-                    ring,
-                    membership_proofs,
-                    real_key_index,
-                    onetime_private_key,
-                    *from_account_key.view_private_key(),
-                )
-                .map_err(|_| Error::TxBuild("failed creating InputCredentials".into()))?,
-            );
+        // The value of the inputs of each token id, which is what remains for change
+        // once the outputs and the fee have been paid.
+        let mut change_values: BTreeMap<u64, u64> = BTreeMap::new();
+        for (utxo, _proof) in inputs {
+            *change_values.entry(utxo.token_id).or_default() += utxo.value;
         }
 
         // Add outputs to our destinations.
-        let mut total_value = 0;
         let mut tx_out_to_outlay_index = HashMap::default();
         let mut outlay_confirmation_numbers = Vec::default();
         for (i, outlay) in destinations.iter().enumerate() {
-            // TODO (GH #1867): If you want to support mixed transactions, use
-            // outlay-specific token id here
-            let amount = Amount {
-                value: outlay.value,
-                token_id,
-            };
             let (tx_out, confirmation_number) = tx_builder
-                .add_output(amount, &outlay.receiver, rng)
+                .add_output(outlay.amount, &outlay.receiver, rng)
                 .map_err(|err| Error::TxBuild(format!("failed adding output: {}", err)))?;
 
             tx_out_to_outlay_index.insert(tx_out, i);
             outlay_confirmation_numbers.push(confirmation_number);
 
-            total_value += outlay.value;
+            let change_value = change_values.entry(*outlay.amount.token_id).or_default();
+            *change_value = change_value
+                .checked_sub(outlay.amount.value)
+                .ok_or(Error::InsufficientFunds)?;
         }
 
         // Figure out if we have change.
-        let input_value = inputs
-            .iter()
-            .fold(0, |acc, (utxo, _proof)| acc + utxo.value);
-        if total_value > input_value {
-            return Err(Error::InsufficientFunds);
-        }
-        let change = input_value - total_value - tx_builder.get_fee();
+        let change_value = change_values.entry(*fee.token_id).or_default();
+        *change_value = change_value
+            .checked_sub(fee.value)
+            .ok_or(Error::InsufficientFunds)?;
 
         // If we do have nonzero change, add an output for that as well.
-        // TODO (GH #1522): Should the exchange write destination memos?
-        // If so then we must always write a change output, even if the change is zero
-        if change > 0 {
-            // TODO: If you want to support mixed transactions, use outlay-specific token id
-            // here
-            let change_amount = Amount {
-                value: change,
-                token_id,
-            };
+        for (token_id, change) in change_values {
+            let token_id = TokenId::from(token_id);
+            if change == 0 && !(always_write_change && token_id == fee.token_id) {
+                continue;
+            }
 
             let change_dest = ReservedDestination::from_subaddress_index(
                 from_account_key,
@@ -1013,7 +1545,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             );
 
             tx_builder
-                .add_change_output(change_amount, &change_dest, rng)
+                .add_change_output(Amount::new(change, token_id), &change_dest, rng)
                 .map_err(|err| Error::TxBuild(format!("failed adding output (change): {}", err)))?;
         }
 
@@ -1064,6 +1596,116 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             outlay_confirmation_numbers,
         })
     }
+
+    /// Create the credentials needed to spend a UTXO, using a ring of mixins.
+    ///
+    /// # Arguments
+    /// * `utxo` - The UTXO to spend.
+    /// * `proof` - The membership proof of the UTXO.
+    /// * `ring` - Mixins for the UTXO. The UTXO is added to the ring if it is
+    ///   not already there.
+    /// * `membership_proofs` - The membership proof of each mixin.
+    /// * `from_account_key` - Owns the UTXO.
+    /// * `logger` - Logger
+    fn build_input_credentials(
+        utxo: &UnspentTxOut,
+        proof: &TxOutMembershipProof,
+        mut ring: Vec<TxOut>,
+        mut membership_proofs: Vec<TxOutMembershipProof>,
+        from_account_key: &AccountKey,
+        logger: &Logger,
+    ) -> Result<InputCredentials, Error> {
+        assert_eq!(
+            ring.len(),
+            membership_proofs.len(),
+            "Each ring element must have a corresponding membership proof."
+        );
+
+        // Add the input to the ring.
+        let position_opt = ring.iter().position(|tx_out| *tx_out == utxo.tx_out);
+        let real_key_index = match position_opt {
+            Some(position) => {
+                // The input is already present in the ring.
+                // This could happen if ring elements are sampled randomly from the ledger.
+                position
+            }
+            None => {
+                // The input is not already in the ring.
+                if ring.is_empty() {
+                    // Append the input and its proof of membership.
+                    ring.push(utxo.tx_out.clone());
+                    membership_proofs.push(proof.clone());
+                } else {
+                    // Replace the first element of the ring.
+                    ring[0] = utxo.tx_out.clone();
+                    membership_proofs[0] = proof.clone();
+                }
+                // The real input is always the first element. This is safe because
+                // TransactionBuilder sorts each ring.
+                0
+            }
+        };
+
+        assert_eq!(
+            ring.len(),
+            membership_proofs.len(),
+            "Each ring element must have a corresponding membership proof."
+        );
+
+        let public_key = RistrettoPublic::try_from(&utxo.tx_out.public_key).unwrap();
+        let onetime_private_key = recover_onetime_private_key(
+            &public_key,
+            from_account_key.view_private_key(),
+            &from_account_key.subaddress_spend_private(utxo.subaddress_index),
+        );
+
+        let key_image = KeyImage::from(&onetime_private_key);
+        log::debug!(
+            logger,
+            "Adding input: ring {:?}, utxo index {:?}, key image {:?}, pubkey {:?}",
+            ring,
+            real_key_index,
+            key_image,
+            public_key
+        );
+
+        InputCredentials::new(
+            ring,
+            membership_proofs,
+            real_key_index,
+            onetime_private_key,
+            *from_account_key.view_private_key(),
+        )
+        .map_err(|_| Error::TxBuild("failed creating InputCredentials".into()))
+    }
+}
+
+/// Create a memo builder which writes Recoverable Transaction History memos:
+/// an authenticated sender memo identifying `sender_subaddress` on each
+/// destination output, and a destination memo on the change output.
+///
+/// # Arguments
+/// * `account_key` - The sending account.
+/// * `sender_subaddress` - Subaddress the recipients will see as the sender.
+/// * `opt_payment_request_id` - Optional payment request id to include in the
+///   sender memo.
+pub fn rth_memo_builder(
+    account_key: &AccountKey,
+    sender_subaddress: u64,
+    opt_payment_request_id: Option<u64>,
+) -> RTHMemoBuilder {
+    let mut memo_builder = RTHMemoBuilder::default();
+    memo_builder.set_sender_credential(
+        SenderMemoCredential::new_from_address_and_spend_private_key(
+            &account_key.subaddress(sender_subaddress),
+            account_key.subaddress_spend_private(sender_subaddress),
+        ),
+    );
+    if let Some(payment_request_id) = opt_payment_request_id {
+        memo_builder.set_payment_request_id(payment_request_id);
+    }
+    memo_builder.enable_destination_memo();
+    memo_builder
 }
 
 // Helper which extracts FogUri from PublicAddress or returns None, or returns
@@ -1081,12 +1723,14 @@ fn extract_fog_uri(addr: &PublicAddress) -> Result<Option<FogUri>, Error> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::utxo_selection::{BranchAndBound, LargestFirst, RandomSelection};
     use mc_connection::{HardcodedCredentialsProvider, ThickClient};
     use mc_crypto_keys::RistrettoPrivate;
     use mc_fog_report_validation::MockFogPubkeyResolver;
     use mc_transaction_core::{constants::MILLIMOB_TO_PICOMOB, tokens::Mob, Amount, Token};
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
+    use std::assert_matches::assert_matches;
 
     fn generate_utxos(num_utxos: usize) -> Vec<UnspentTxOut> {
         let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
@@ -1113,6 +1757,8 @@ mod test {
                 attempted_spend_height: 0,
                 attempted_spend_tombstone: 0,
                 token_id: *token_id,
+                memo_payload: Vec::new(),
+                authenticated_sender: None,
             })
             .collect()
     }
@@ -1131,7 +1777,9 @@ mod test {
         let selected_utxos = TransactionsManager::<
             ThickClient<HardcodedCredentialsProvider>,
             MockFogPubkeyResolver,
-        >::select_utxos_for_value(Mob::ID, &utxos, 300, utxos.len())
+        >::select_utxos_for_value(
+            Mob::ID, &utxos, 300, utxos.len(), &SmallestFirst
+        )
         .unwrap();
 
         assert_eq!(selected_utxos, vec![utxos[0].clone(), utxos[1].clone()]);
@@ -1140,7 +1788,9 @@ mod test {
         let selected_utxos = TransactionsManager::<
             ThickClient<HardcodedCredentialsProvider>,
             MockFogPubkeyResolver,
-        >::select_utxos_for_value(Mob::ID, &utxos, 301, utxos.len())
+        >::select_utxos_for_value(
+            Mob::ID, &utxos, 301, utxos.len(), &SmallestFirst
+        )
         .unwrap();
 
         assert_eq!(
@@ -1152,7 +1802,9 @@ mod test {
         let selected_utxos = TransactionsManager::<
             ThickClient<HardcodedCredentialsProvider>,
             MockFogPubkeyResolver,
-        >::select_utxos_for_value(Mob::ID, &utxos, 301, 2)
+        >::select_utxos_for_value(
+            Mob::ID, &utxos, 301, 2, &SmallestFirst
+        )
         .unwrap();
 
         assert_eq!(selected_utxos, vec![utxos[1].clone(), utxos[2].clone()]);
@@ -1164,7 +1816,7 @@ mod test {
         // While we have enough utxos to sum to 5, if the input limit is 4 we should
         // fail.
         match TransactionsManager::<ThickClient<HardcodedCredentialsProvider>, MockFogPubkeyResolver>::select_utxos_for_value(
-            Mob::ID, &utxos, 5, 4,
+            Mob::ID, &utxos, 5, 4, &SmallestFirst,
         ) {
             Err(Error::InsufficientFundsFragmentedUtxos) => {
                 // Expected.
@@ -1179,7 +1831,7 @@ mod test {
         // While we have enough utxos to sum to 5, if the input limit is 4 we should
         // fail.
         match TransactionsManager::<ThickClient<HardcodedCredentialsProvider>, MockFogPubkeyResolver>::select_utxos_for_value(
-            Mob::ID, &utxos, 50, 100,
+            Mob::ID, &utxos, 50, 100, &SmallestFirst,
         ) {
             Err(Error::InsufficientFunds) => {
                 // Expected.
@@ -1188,6 +1840,164 @@ mod test {
         };
     }
 
+    #[test]
+    fn test_select_utxos_for_value_selects_largest_inputs() {
+        let mut utxos = generate_utxos(5);
+
+        utxos[0].value = 100;
+        utxos[1].value = 200;
+        utxos[2].value = 300;
+        utxos[3].value = 2000;
+        utxos[4].value = 1000;
+
+        // Sending 300 should select 2000.
+        let selected_utxos = TransactionsManager::<
+            ThickClient<HardcodedCredentialsProvider>,
+            MockFogPubkeyResolver,
+        >::select_utxos_for_value(
+            Mob::ID, &utxos, 300, utxos.len(), &LargestFirst
+        )
+        .unwrap();
+
+        assert_eq!(selected_utxos, vec![utxos[3].clone()]);
+
+        // Sending 2001 should select 2000 + 1000.
+        let selected_utxos = TransactionsManager::<
+            ThickClient<HardcodedCredentialsProvider>,
+            MockFogPubkeyResolver,
+        >::select_utxos_for_value(
+            Mob::ID, &utxos, 2001, utxos.len(), &LargestFirst
+        )
+        .unwrap();
+
+        assert_eq!(selected_utxos, vec![utxos[3].clone(), utxos[4].clone()]);
+    }
+
+    #[test]
+    fn test_select_utxos_for_value_branch_and_bound_finds_exact_match() {
+        let mut utxos = generate_utxos(5);
+
+        utxos[0].value = 100;
+        utxos[1].value = 200;
+        utxos[2].value = 300;
+        utxos[3].value = 2000;
+        utxos[4].value = 1000;
+
+        // Sending 1300 should select exactly 1000 + 300.
+        let selected_utxos = TransactionsManager::<
+            ThickClient<HardcodedCredentialsProvider>,
+            MockFogPubkeyResolver,
+        >::select_utxos_for_value(
+            Mob::ID, &utxos, 1300, utxos.len(), &BranchAndBound
+        )
+        .unwrap();
+
+        assert_eq!(selected_utxos, vec![utxos[4].clone(), utxos[2].clone()]);
+
+        // The only exact match for 2600 is 2000 + 300 + 200 + 100, which needs 4
+        // inputs. With at most 3 inputs, this falls back to smallest first.
+        let selected_utxos = TransactionsManager::<
+            ThickClient<HardcodedCredentialsProvider>,
+            MockFogPubkeyResolver,
+        >::select_utxos_for_value(
+            Mob::ID, &utxos, 2600, 3, &BranchAndBound
+        )
+        .unwrap();
+
+        assert_eq!(
+            selected_utxos,
+            vec![utxos[2].clone(), utxos[4].clone(), utxos[3].clone()]
+        );
+
+        // There is no exact match for 1301, so this falls back to smallest first.
+        let selected_utxos = TransactionsManager::<
+            ThickClient<HardcodedCredentialsProvider>,
+            MockFogPubkeyResolver,
+        >::select_utxos_for_value(
+            Mob::ID, &utxos, 1301, utxos.len(), &BranchAndBound
+        )
+        .unwrap();
+
+        assert_eq!(
+            selected_utxos,
+            vec![
+                utxos[0].clone(),
+                utxos[1].clone(),
+                utxos[2].clone(),
+                utxos[4].clone()
+            ]
+        );
+    }
+
+    // Properties every UTXO selection strategy must satisfy, checked over
+    // randomly generated sets of UTXOs.
+    #[test]
+    fn test_select_utxos_for_value_strategies_respect_max_inputs() {
+        let strategies: [&dyn UtxoSelectionStrategy; 4] = [
+            &SmallestFirst,
+            &LargestFirst,
+            &BranchAndBound,
+            &RandomSelection,
+        ];
+        let other_token_id = TokenId::from(*Mob::ID + 1);
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+
+        for _ in 0..200 {
+            // A mix of UTXOs of two token ids, with distinct key images so that they can
+            // be told apart.
+            let mut utxos = generate_utxos(rng.gen_range(1..3 * MAX_INPUTS as usize));
+            for (i, utxo) in utxos.iter_mut().enumerate() {
+                utxo.key_image = KeyImage::from(i as u64);
+                utxo.value = rng.gen_range(1..1000);
+                if rng.gen_bool(0.2) {
+                    utxo.token_id = *other_token_id;
+                }
+            }
+            let mut mob_values: Vec<u64> = utxos
+                .iter()
+                .filter(|utxo| utxo.token_id == *Mob::ID)
+                .map(|utxo| utxo.value)
+                .collect();
+            mob_values.sort_unstable_by_key(|value| Reverse(*value));
+
+            let total_value: u64 = mob_values.iter().sum();
+            let value = rng.gen_range(1..total_value + 100);
+            let max_inputs = rng.gen_range(1..=MAX_INPUTS as usize);
+            let max_spendable_value: u64 = mob_values.iter().take(max_inputs).sum();
+
+            for strategy in strategies.iter() {
+                let result = TransactionsManager::<
+                    ThickClient<HardcodedCredentialsProvider>,
+                    MockFogPubkeyResolver,
+                >::select_utxos_for_value(
+                    Mob::ID, &utxos, value, max_inputs, *strategy
+                );
+
+                if value > total_value {
+                    assert_matches!(result, Err(Error::InsufficientFunds));
+                    continue;
+                }
+                if value > max_spendable_value {
+                    assert_matches!(result, Err(Error::InsufficientFundsFragmentedUtxos));
+                    continue;
+                }
+
+                let selected_utxos = result.unwrap();
+                assert!(!selected_utxos.is_empty());
+                assert!(selected_utxos.len() <= max_inputs);
+                assert!(selected_utxos.iter().map(|utxo| utxo.value).sum::<u64>() >= value);
+
+                let key_images: HashSet<KeyImage> =
+                    selected_utxos.iter().map(|utxo| utxo.key_image).collect();
+                assert_eq!(key_images.len(), selected_utxos.len());
+                for selected_utxo in selected_utxos.iter() {
+                    assert_eq!(selected_utxo.token_id, *Mob::ID);
+                    assert!(utxos.contains(selected_utxo));
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_select_utxos_for_optimization_selects_smallest_inputs() {
         // Optimizing with max_inputs=2 should select 100, 2000
@@ -10,7 +10,7 @@ use mc_common::logger::Logger;
 use mc_crypto_keys::CompressedRistrettoPublic;
 use mc_transaction_core::ring_signature::KeyImage;
 use prost::{Enumeration, Message};
-use std::{convert::TryFrom, sync::Arc};
+use std::{collections::BTreeMap, convert::TryFrom, ops::Range, sync::Arc};
 
 // LMDB Database Names
 pub const PROCESSED_BLOCK_KEY_TO_PROCESSED_TX_OUTS_DB_NAME: &str =
@@ -133,6 +133,62 @@ impl ProcessedTxOut {
     }
 }
 
+/// A logical transaction, reconstructed from the ProcessedTxOuts a monitor saw
+/// in a single block.
+///
+/// The ledger does not record which inputs and outputs of a block belong to
+/// the same Tx, so all activity in a given token within one block is grouped
+/// together.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProcessedTransaction {
+    /// The block index the transaction appeared in.
+    pub block_index: u64,
+
+    /// The token id of all TxOuts in this transaction.
+    pub token_id: u64,
+
+    /// TxOuts received by the monitor.
+    pub received: Vec<ProcessedTxOut>,
+
+    /// TxOuts spent by the monitor.
+    pub spent: Vec<ProcessedTxOut>,
+}
+
+impl ProcessedTransaction {
+    /// Group the ProcessedTxOuts of a single block into transactions, one per
+    /// token id, ordered by token id.
+    pub fn from_processed_block(block_index: u64, tx_outs: Vec<ProcessedTxOut>) -> Vec<Self> {
+        let mut transactions: BTreeMap<u64, Self> = BTreeMap::new();
+
+        for tx_out in tx_outs {
+            let transaction = transactions.entry(tx_out.token_id).or_insert_with(|| Self {
+                block_index,
+                token_id: tx_out.token_id,
+                received: Vec::new(),
+                spent: Vec::new(),
+            });
+
+            if tx_out.direction == ProcessedTxOutDirection::Spent as i32 {
+                transaction.spent.push(tx_out);
+            } else {
+                transaction.received.push(tx_out);
+            }
+        }
+
+        transactions.into_values().collect()
+    }
+
+    /// Total value of the received TxOuts.
+    pub fn total_received(&self) -> u64 {
+        self.received.iter().map(|tx_out| tx_out.value).sum()
+    }
+
+    /// Total value of the spent TxOuts.
+    pub fn total_spent(&self) -> u64 {
+        self.spent.iter().map(|tx_out| tx_out.value).sum()
+    }
+}
+
 /// the processed blocks database.
 #[derive(Clone)]
 pub struct ProcessedBlockStore {
@@ -181,6 +237,58 @@ impl ProcessedBlockStore {
             .collect::<Result<Vec<_>, Error>>()
     }
 
+    /// Get processed block information for a range of blocks belonging to a
+    /// given monitor id, keeping only the ProcessedTxOuts that satisfy
+    /// `filter`.
+    ///
+    /// Blocks with no matching data are skipped. At most `max_blocks` blocks
+    /// are returned, in ascending block order.
+    pub fn get_processed_blocks(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        block_range: Range<u64>,
+        max_blocks: usize,
+        filter: impl Fn(&ProcessedTxOut) -> bool,
+    ) -> Result<Vec<(u64, Vec<ProcessedTxOut>)>, Error> {
+        let mut results: Vec<(u64, Vec<ProcessedTxOut>)> = Vec::new();
+        if block_range.is_empty() || max_blocks == 0 {
+            return Ok(results);
+        }
+
+        let start_key = ProcessedBlockKey::new(monitor_id, block_range.start);
+        let start_key_bytes = start_key.to_vec();
+
+        let mut cursor = db_txn.open_ro_cursor(self.processed_block_key_to_processed_tx_outs)?;
+
+        for result in cursor.iter_from(&start_key_bytes) {
+            let (db_key, db_value) = result?;
+            let key = ProcessedBlockKey::try_from(db_key)?;
+            if key.monitor_id != *monitor_id || !block_range.contains(&key.block_index) {
+                break;
+            }
+
+            let processed_tx_out: ProcessedTxOut = mc_util_serial::decode(db_value)?;
+            if !filter(&processed_tx_out) {
+                continue;
+            }
+
+            match results.last_mut() {
+                Some((block_index, tx_outs)) if *block_index == key.block_index => {
+                    tx_outs.push(processed_tx_out);
+                }
+                _ => {
+                    if results.len() == max_blocks {
+                        break;
+                    }
+                    results.push((key.block_index, vec![processed_tx_out]));
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
     /// Remove the data associated with a given monitor id.
     pub fn remove<'env>(
         &self,
@@ -240,6 +348,31 @@ impl ProcessedBlockStore {
 
         Ok(())
     }
+
+    /// Store previously processed data for a given block, e.g. when importing
+    /// a monitor from another database.
+    pub fn import_processed_block<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        block_index: u64,
+        tx_outs: &[ProcessedTxOut],
+    ) -> Result<(), Error> {
+        let key = ProcessedBlockKey::new(monitor_id, block_index);
+        let key_bytes = key.to_vec();
+
+        for processed_tx_out in tx_outs.iter() {
+            let processed_tx_out_bytes = mc_util_serial::encode(processed_tx_out);
+            db_txn.put(
+                self.processed_block_key_to_processed_tx_outs,
+                &key_bytes,
+                &processed_tx_out_bytes,
+                WriteFlags::empty(),
+            )?;
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -322,6 +455,8 @@ mod test {
                     attempted_spend_height: 0,
                     attempted_spend_tombstone: 0,
                     token_id: *Mob::ID,
+                    memo_payload: Vec::new(),
+                    authenticated_sender: None,
                 }
             })
             .collect();
@@ -587,4 +722,136 @@ mod test {
             db_txn.commit().unwrap();
         }
     }
+
+    // Range queries and transaction grouping.
+    #[test_with_logger]
+    fn test_get_processed_blocks(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let (env, _ledger_db, store, account, utxos) =
+            setup_test_processed_block_store(&mut rng, &logger);
+
+        let monitor_data = MonitorData::new(
+            account.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .expect("failed to create data");
+        let monitor_id = MonitorId::from(&monitor_data);
+
+        let monitor_data2 = MonitorData::new(
+            account, 30, // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .expect("failed to create data");
+        let monitor_id2 = MonitorId::from(&monitor_data2);
+
+        // A utxo in a second token.
+        let mut other_token_utxo = utxos[9].clone();
+        other_token_utxo.token_id = 2;
+
+        // Blocks 0, 2 and 4 have data for our monitor. Block 4 has a spend in
+        // addition to two receives, one of which is in a different token.
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            store
+                .block_processed(&mut db_txn, &monitor_id, 0, &utxos[0..2], &[])
+                .unwrap();
+            store
+                .block_processed(&mut db_txn, &monitor_id, 2, &utxos[2..3], &[])
+                .unwrap();
+            store
+                .block_processed(
+                    &mut db_txn,
+                    &monitor_id,
+                    4,
+                    &[utxos[3].clone(), other_token_utxo.clone()],
+                    &utxos[0..1],
+                )
+                .unwrap();
+            store
+                .block_processed(&mut db_txn, &monitor_id2, 1, &utxos[4..5], &[])
+                .unwrap();
+            db_txn.commit().unwrap();
+        }
+
+        let db_txn = env.begin_ro_txn().unwrap();
+
+        // All blocks.
+        let blocks = store
+            .get_processed_blocks(&db_txn, &monitor_id, 0..100, 100, |_| true)
+            .unwrap();
+        assert_eq!(
+            blocks.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            vec![0, 2, 4]
+        );
+        assert_eq!(blocks[0].1.len(), 2);
+        assert_eq!(blocks[1].1.len(), 1);
+        assert_eq!(blocks[2].1.len(), 3);
+
+        // Block range and max blocks are respected.
+        let blocks = store
+            .get_processed_blocks(&db_txn, &monitor_id, 1..4, 100, |_| true)
+            .unwrap();
+        assert_eq!(
+            blocks.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            vec![2]
+        );
+
+        let blocks = store
+            .get_processed_blocks(&db_txn, &monitor_id, 0..100, 2, |_| true)
+            .unwrap();
+        assert_eq!(
+            blocks.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+
+        // Blocks with no matching data are skipped.
+        let blocks = store
+            .get_processed_blocks(&db_txn, &monitor_id, 0..100, 1, |tx_out| {
+                tx_out.token_id == 2
+            })
+            .unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].0, 4);
+        assert_eq!(
+            blocks[0].1,
+            vec![ProcessedTxOut::from_received_utxo(&other_token_utxo)]
+        );
+
+        // Data for other monitors is not returned.
+        let blocks = store
+            .get_processed_blocks(&db_txn, &monitor_id2, 0..100, 100, |_| true)
+            .unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].0, 1);
+
+        // Block 4 groups into one transaction per token.
+        let blocks = store
+            .get_processed_blocks(&db_txn, &monitor_id, 4..5, 100, |_| true)
+            .unwrap();
+        let (block_index, tx_outs) = blocks[0].clone();
+        let transactions = ProcessedTransaction::from_processed_block(block_index, tx_outs);
+        assert_eq!(transactions.len(), 2);
+
+        assert_eq!(transactions[0].block_index, 4);
+        assert_eq!(transactions[0].token_id, utxos[3].token_id);
+        assert_eq!(
+            transactions[0].received,
+            vec![ProcessedTxOut::from_received_utxo(&utxos[3])]
+        );
+        assert_eq!(
+            transactions[0].spent,
+            vec![ProcessedTxOut::from_spent_utxo(&utxos[0])]
+        );
+        assert_eq!(transactions[0].total_received(), utxos[3].value);
+        assert_eq!(transactions[0].total_spent(), utxos[0].value);
+
+        assert_eq!(transactions[1].token_id, 2);
+        assert_eq!(transactions[1].total_received(), other_token_utxo.value);
+        assert!(transactions[1].spent.is_empty());
+    }
 }
@@ -7,15 +7,26 @@
 //! * writes matching transactions to a local DB, organized by subaddress_id
 
 use crate::{
+    consolidation::ConsolidationThread,
     database::Database,
+    db_crypto::PASSWORD_LEN,
     error::Error,
-    monitor_store::{MonitorData, MonitorId},
-    payments::{Outlay, TransactionsManager, TxProposal},
+    monitor_store::{ConsolidationPolicy, MonitorData, MonitorId},
+    payments::{rth_memo_builder, Outlay, OutlayV2, TransactionsManager, TxMemos, TxProposal},
+    processed_block_store::{ProcessedTransaction, ProcessedTxOut},
+    subscriptions::Subscriptions,
     sync::SyncThread,
+    utxo_selection::{
+        BranchAndBound, LargestFirst, RandomSelection, SmallestFirst, UtxoSelectionStrategy,
+    },
     utxo_store::{UnspentTxOut, UtxoId},
 };
 use bip39::{Language, Mnemonic, MnemonicType};
-use grpcio::{EnvBuilder, RpcContext, RpcStatus, RpcStatusCode, ServerBuilder, UnarySink};
+use futures::{stream::BoxStream, SinkExt, StreamExt};
+use grpcio::{
+    EnvBuilder, RpcContext, RpcStatus, RpcStatusCode, ServerBuilder, ServerStreamingSink,
+    UnarySink, WriteFlags,
+};
 use mc_account_keys::{
     burn_address, AccountKey, PublicAddress, RootIdentity, DEFAULT_SUBADDRESS_INDEX,
 };
@@ -38,7 +49,7 @@ use mc_transaction_core::{
     onetime_keys::recover_onetime_private_key,
     ring_signature::KeyImage,
     tx::{TxOut, TxOutConfirmationNumber, TxOutMembershipProof},
-    TokenId,
+    Amount, SignedContingentInput, TokenId,
 };
 use mc_transaction_std::{BurnRedemptionMemo, BurnRedemptionMemoBuilder};
 use mc_util_from_random::FromRandom;
@@ -47,16 +58,24 @@ use mc_util_grpc::{
     BuildInfoService, ConnectionUriGrpcioServer,
 };
 use mc_watcher::watcher_db::WatcherDB;
+use mc_watcher_api::TimestampResultCode;
 use protobuf::{ProtobufEnum, RepeatedField};
 use std::{
+    cmp::min,
     convert::{TryFrom, TryInto},
     sync::{Arc, Mutex, RwLock},
 };
 
+/// The default number of blocks returned by a GetTransactionHistory call.
+const DEFAULT_TRANSACTION_HISTORY_MAX_BLOCKS: usize = 100;
+
 pub struct Service {
     /// Sync thread.
     _sync_thread: Arc<Mutex<Option<SyncThread>>>,
 
+    /// Consolidation thread.
+    _consolidation_thread: ConsolidationThread,
+
     /// GRPC server.
     _server: grpcio::Server,
 }
@@ -75,6 +94,8 @@ impl Service {
         num_workers: Option<usize>,
         logger: Logger,
     ) -> Self {
+        let subscriptions = Subscriptions::default();
+
         let sync_thread = if mobilecoind_db.is_db_encrypted() {
             log::info!(logger, "Db encryption enabled, sync task would start once password is provided via the API.");
             Arc::new(Mutex::new(None))
@@ -83,6 +104,7 @@ impl Service {
             Arc::new(Mutex::new(Some(SyncThread::start(
                 ledger_db.clone(),
                 mobilecoind_db.clone(),
+                subscriptions.clone(),
                 num_workers,
                 logger.clone(),
             ))))
@@ -91,6 +113,7 @@ impl Service {
         let start_sync_thread = {
             let ledger_db = ledger_db.clone();
             let mobilecoind_db = mobilecoind_db.clone();
+            let subscriptions = subscriptions.clone();
             let logger = logger.clone();
             let sync_thread = sync_thread.clone();
             Arc::new(move || {
@@ -100,18 +123,28 @@ impl Service {
                 *sync_thread = Some(SyncThread::start(
                     ledger_db.clone(),
                     mobilecoind_db.clone(),
+                    subscriptions.clone(),
                     num_workers,
                     logger.clone(),
                 ));
             })
         };
 
+        log::info!(logger, "Starting mobilecoind consolidation thread");
+        let consolidation_thread = ConsolidationThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            transactions_manager.clone(),
+            logger.clone(),
+        );
+
         let api = ServiceApi::new(
             transactions_manager,
             ledger_db,
             mobilecoind_db,
             watcher_db,
             network_state,
+            subscriptions,
             start_sync_thread,
             logger.clone(),
         );
@@ -156,6 +189,7 @@ impl Service {
         Self {
             _server: server,
             _sync_thread: sync_thread,
+            _consolidation_thread: consolidation_thread,
         }
     }
 }
@@ -169,6 +203,7 @@ pub struct ServiceApi<
     mobilecoind_db: Database,
     watcher_db: Option<WatcherDB>,
     network_state: Arc<RwLock<PollingNetworkState<T>>>,
+    subscriptions: Subscriptions,
     start_sync_thread: Arc<dyn Fn() + Send + Sync>,
     logger: Logger,
 }
@@ -183,6 +218,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             mobilecoind_db: self.mobilecoind_db.clone(),
             watcher_db: self.watcher_db.clone(),
             network_state: self.network_state.clone(),
+            subscriptions: self.subscriptions.clone(),
             start_sync_thread: self.start_sync_thread.clone(),
             logger: self.logger.clone(),
         }
@@ -198,6 +234,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         mobilecoind_db: Database,
         watcher_db: Option<WatcherDB>,
         network_state: Arc<RwLock<PollingNetworkState<T>>>,
+        subscriptions: Subscriptions,
         start_sync_thread: Arc<dyn Fn() + Send + Sync>,
         logger: Logger,
     ) -> Self {
@@ -207,6 +244,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             mobilecoind_db,
             watcher_db,
             network_state,
+            subscriptions,
             start_sync_thread,
             logger,
         }
@@ -241,6 +279,26 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         }
         .map_err(|err| rpc_internal_error("mobilecoind_db.add_monitor", err, &self.logger))?;
 
+        // The monitor needs the contents of every block starting at its first block.
+        // This is checked after inserting it, since from then on the ledger db will
+        // not prune those blocks.
+        let num_pruned_blocks = self
+            .ledger_db
+            .num_pruned_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_pruned_blocks", err, &self.logger))?;
+        if is_new && request.first_block < num_pruned_blocks {
+            self.mobilecoind_db.remove_monitor(&id).map_err(|err| {
+                rpc_internal_error("mobilecoind_db.remove_monitor", err, &self.logger)
+            })?;
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                format!(
+                    "first_block: blocks below {} have been pruned",
+                    num_pruned_blocks
+                ),
+            ));
+        }
+
         // Return success response.
         let mut response = mc_mobilecoind_api::AddMonitorResponse::new();
         response.set_monitor_id(id.to_vec());
@@ -304,12 +362,150 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         status.set_num_subaddresses(data.num_subaddresses);
         status.set_first_block(data.first_block);
         status.set_next_block(data.next_block);
+        status.set_address_book(RepeatedField::from_vec(
+            data.address_book
+                .iter()
+                .map(|address| address.into())
+                .collect(),
+        ));
+        if let Some(policy) = data.consolidation_policy.as_ref() {
+            status.set_consolidation_policy(policy.into());
+        }
+        status.set_consolidation_status((&data.consolidation_status).into());
 
         let mut response = mc_mobilecoind_api::GetMonitorStatusResponse::new();
         response.set_status(status);
         Ok(response)
     }
 
+    fn set_monitor_address_book_impl(
+        &mut self,
+        request: mc_mobilecoind_api::SetMonitorAddressBookRequest,
+    ) -> Result<mc_mobilecoind_api::Empty, RpcStatus> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let address_book = request
+            .get_address_book()
+            .iter()
+            .map(|address| {
+                PublicAddress::try_from(address)
+                    .map_err(|err| rpc_internal_error("public_address.try_from", err, &self.logger))
+            })
+            .collect::<Result<Vec<PublicAddress>, RpcStatus>>()?;
+
+        self.mobilecoind_db
+            .set_monitor_address_book(&monitor_id, &address_book)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.set_monitor_address_book", err, &self.logger)
+            })?;
+
+        Ok(mc_mobilecoind_api::Empty::new())
+    }
+
+    fn set_monitor_consolidation_policy_impl(
+        &mut self,
+        request: mc_mobilecoind_api::SetMonitorConsolidationPolicyRequest,
+    ) -> Result<mc_mobilecoind_api::Empty, RpcStatus> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let policy = if request.has_policy() {
+            let policy = ConsolidationPolicy::from(request.get_policy());
+            if policy.target_utxo_count == 0 {
+                return Err(RpcStatus::with_message(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    "policy.target_utxo_count".into(),
+                ));
+            }
+            Some(policy)
+        } else {
+            None
+        };
+
+        self.mobilecoind_db
+            .set_monitor_consolidation_policy(&monitor_id, policy)
+            .map_err(|err| {
+                rpc_internal_error(
+                    "mobilecoind_db.set_monitor_consolidation_policy",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        Ok(mc_mobilecoind_api::Empty::new())
+    }
+
+    fn export_monitors_impl(
+        &mut self,
+        request: mc_mobilecoind_api::ExportMonitorsRequest,
+    ) -> Result<mc_mobilecoind_api::ExportMonitorsResponse, RpcStatus> {
+        if request.get_password().len() != PASSWORD_LEN {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "password".into(),
+            ));
+        }
+
+        // Export all monitors unless specific ones were requested.
+        let monitor_ids = if request.get_monitor_id_list().is_empty() {
+            self.mobilecoind_db.get_monitor_ids().map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_ids", err, &self.logger)
+            })?
+        } else {
+            request
+                .get_monitor_id_list()
+                .iter()
+                .map(|monitor_id| {
+                    MonitorId::try_from(monitor_id).map_err(|err| {
+                        rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger)
+                    })
+                })
+                .collect::<Result<Vec<_>, RpcStatus>>()?
+        };
+
+        let bundle = self
+            .mobilecoind_db
+            .export_monitors(
+                &monitor_ids,
+                request.include_processed_state,
+                request.get_password(),
+                &mut rand::thread_rng(),
+            )
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.export_monitors", err, &self.logger)
+            })?;
+
+        let mut response = mc_mobilecoind_api::ExportMonitorsResponse::new();
+        response.set_bundle(bundle);
+        Ok(response)
+    }
+
+    fn import_monitors_impl(
+        &mut self,
+        request: mc_mobilecoind_api::ImportMonitorsRequest,
+    ) -> Result<mc_mobilecoind_api::ImportMonitorsResponse, RpcStatus> {
+        if request.get_password().len() != PASSWORD_LEN {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "password".into(),
+            ));
+        }
+
+        let monitor_ids = self
+            .mobilecoind_db
+            .import_monitors(request.get_bundle(), request.get_password(), request.rescan)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.import_monitors", err, &self.logger)
+            })?;
+
+        let mut response = mc_mobilecoind_api::ImportMonitorsResponse::new();
+        response.set_monitor_id_list(RepeatedField::from_vec(
+            monitor_ids.iter().map(|id| id.to_vec()).collect(),
+        ));
+        Ok(response)
+    }
+
     fn get_unspent_tx_out_list_impl(
         &mut self,
         request: mc_mobilecoind_api::GetUnspentTxOutListRequest,
@@ -592,6 +788,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             token_id: *amount.token_id,
             attempted_spend_height: 0,
             attempted_spend_tombstone: 0,
+            memo_payload: Vec::new(),
+            authenticated_sender: None,
         };
 
         let mut response = mc_mobilecoind_api::ParseTransferCodeResponse::new();
@@ -819,6 +1017,15 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             ));
         }
 
+        // Outlays carrying their own token id replace the single-token outlay list.
+        let is_mixed = !request.get_outlay_list_v2().is_empty();
+        if is_mixed && !request.get_outlay_list().is_empty() {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "outlay_list".into(),
+            ));
+        }
+
         // Get the list of potential inputs passed to.
         let input_list: Vec<UnspentTxOut> = request
             .get_input_list()
@@ -830,8 +1037,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                     rpc_internal_error("unspent_tx_out.try_from", err, &self.logger)
                 })?;
 
-                // Verify token id matches.
-                if utxo.token_id != request.token_id {
+                // Verify token id matches, unless each outlay carries its own token id.
+                if !is_mixed && utxo.token_id != request.token_id {
                     return Err(RpcStatus::with_message(
                         RpcStatusCode::INVALID_ARGUMENT,
                         format!("input_list[{}].token_id", i),
@@ -872,22 +1079,60 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             })
             .collect::<Result<Vec<Outlay>, RpcStatus>>()?;
 
+        // Get the memos. Sender memos identify the change subaddress.
+        let memos = memos_for_mode(
+            request.get_memo_mode(),
+            &sender_monitor_data.account_key,
+            request.change_subaddress,
+            request.payment_request_id,
+        );
+
         // Attempt to construct a transaction.
-        let tx_proposal = self
-            .transactions_manager
-            .build_transaction(
-                &sender_monitor_id,
-                TokenId::from(request.token_id),
-                request.change_subaddress,
-                &input_list,
-                &outlays,
-                request.fee,
-                request.tombstone,
-                None,
-            )
-            .map_err(|err| {
-                rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
-            })?;
+        let tx_proposal = if is_mixed {
+            let outlays: Vec<OutlayV2> = request
+                .get_outlay_list_v2()
+                .iter()
+                .map(|outlay_proto| {
+                    OutlayV2::try_from(outlay_proto)
+                        .map_err(|err| rpc_internal_error("outlay_v2.try_from", err, &self.logger))
+                })
+                .collect::<Result<Vec<OutlayV2>, RpcStatus>>()?;
+            self.transactions_manager
+                .build_mixed_transaction(
+                    &sender_monitor_id,
+                    request.change_subaddress,
+                    &input_list,
+                    &outlays,
+                    TokenId::from(request.fee_token_id),
+                    request.fee,
+                    request.tombstone,
+                    memos,
+                    utxo_selection_strategy(request.get_utxo_selection_strategy()),
+                )
+                .map_err(|err| {
+                    rpc_internal_error(
+                        "transactions_manager.build_mixed_transaction",
+                        err,
+                        &self.logger,
+                    )
+                })?
+        } else {
+            self.transactions_manager
+                .build_transaction(
+                    &sender_monitor_id,
+                    TokenId::from(request.token_id),
+                    request.change_subaddress,
+                    &input_list,
+                    &outlays,
+                    request.fee,
+                    request.tombstone,
+                    memos,
+                    utxo_selection_strategy(request.get_utxo_selection_strategy()),
+                )
+                .map_err(|err| {
+                    rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
+                })?
+        };
 
         // Success.
         let mut response = mc_mobilecoind_api::GenerateTxResponse::new();
@@ -1081,7 +1326,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 &outlays,
                 request.fee,
                 request.tombstone,
-                Some(Box::new(memo_builder)),
+                TxMemos::Other(Box::new(memo_builder)),
+                &SmallestFirst,
             )
             .map_err(|err| {
                 rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
@@ -1292,107 +1538,321 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(response)
     }
 
-    fn get_ledger_info_impl(
+    fn generate_signed_contingent_input_impl(
         &mut self,
-        _request: mc_mobilecoind_api::Empty,
-    ) -> Result<mc_mobilecoind_api::GetLedgerInfoResponse, RpcStatus> {
-        let num_blocks = self
-            .ledger_db
-            .num_blocks()
-            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+        request: mc_mobilecoind_api::GenerateSignedContingentInputRequest,
+    ) -> Result<mc_mobilecoind_api::GenerateSignedContingentInputResponse, RpcStatus> {
+        // Get sender monitor id from request.
+        let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
 
-        let num_txos = self
-            .ledger_db
-            .num_txos()
-            .map_err(|err| rpc_internal_error("ledger_db.num_txos", err, &self.logger))?;
+        // Get the utxo being offered.
+        let utxo = UnspentTxOut::try_from(request.get_utxo())
+            .map_err(|err| rpc_internal_error("unspent_tx_out.try_from", err, &self.logger))?;
 
-        let mut response = mc_mobilecoind_api::GetLedgerInfoResponse::new();
-        response.set_block_count(num_blocks);
-        response.set_txo_count(num_txos);
-        Ok(response)
-    }
+        // Verify this output belongs to the monitor.
+        let subaddress_id = self
+            .mobilecoind_db
+            .get_subaddress_id_by_utxo_id(&UtxoId::from(&utxo))
+            .map_err(|err| {
+                rpc_internal_error(
+                    "mobilecoind_db.get_subaddress_id_by_utxo_id",
+                    err,
+                    &self.logger,
+                )
+            })?;
 
-    fn get_block_info_impl(
-        &mut self,
-        request: mc_mobilecoind_api::GetBlockInfoRequest,
-    ) -> Result<mc_mobilecoind_api::GetBlockInfoResponse, RpcStatus> {
-        let block_contents = self
-            .ledger_db
-            .get_block_contents(request.block)
-            .map_err(|err| rpc_internal_error("ledger_db.get_block_contents", err, &self.logger))?;
+        if subaddress_id.monitor_id != sender_monitor_id {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "utxo".into(),
+            ));
+        }
 
-        let num_tx_outs = block_contents.outputs.len();
-        let num_key_images = block_contents.key_images.len();
+        // Get the list of required outlays.
+        let required_outlays: Vec<OutlayV2> = request
+            .get_required_outlay_list()
+            .iter()
+            .map(|outlay_proto| {
+                OutlayV2::try_from(outlay_proto)
+                    .map_err(|err| rpc_internal_error("outlay_v2.try_from", err, &self.logger))
+            })
+            .collect::<Result<Vec<OutlayV2>, RpcStatus>>()?;
 
-        // Return response.
-        let mut response = mc_mobilecoind_api::GetBlockInfoResponse::new();
-        response.set_key_image_count(num_key_images as u64);
-        response.set_txo_count(num_tx_outs as u64);
+        // Attempt to construct the signed contingent input.
+        let sci = self
+            .transactions_manager
+            .generate_signed_contingent_input(
+                &sender_monitor_id,
+                &utxo,
+                &required_outlays,
+                request.tombstone,
+            )
+            .map_err(|err| {
+                rpc_internal_error(
+                    "transactions_manager.generate_signed_contingent_input",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        // Success.
+        let mut response = mc_mobilecoind_api::GenerateSignedContingentInputResponse::new();
+        response.set_sci((&sci).into());
         Ok(response)
     }
 
-    fn get_block_impl(
+    fn validate_signed_contingent_input_impl(
         &mut self,
-        request: mc_mobilecoind_api::GetBlockRequest,
-    ) -> Result<mc_mobilecoind_api::GetBlockResponse, RpcStatus> {
-        let mut response = mc_mobilecoind_api::GetBlockResponse::new();
-
-        let block_data = self
-            .ledger_db
-            .get_block_data(request.block)
-            .map_err(|err| rpc_internal_error("ledger_db.get_block_data", err, &self.logger))?;
-
-        response.set_block(mc_consensus_api::blockchain::Block::from(
-            block_data.block(),
-        ));
+        request: mc_mobilecoind_api::ValidateSignedContingentInputRequest,
+    ) -> Result<mc_mobilecoind_api::ValidateSignedContingentInputResponse, RpcStatus> {
+        // Get the signed contingent input from the request.
+        let sci = SignedContingentInput::try_from(request.get_sci()).map_err(|err| {
+            rpc_internal_error("signed_contingent_input.try_from", err, &self.logger)
+        })?;
 
-        for key_image in &block_data.contents().key_images {
-            response
-                .mut_key_images()
-                .push(mc_consensus_api::external::KeyImage::from(key_image));
-        }
-        for output in &block_data.contents().outputs {
-            response
-                .mut_txos()
-                .push(mc_consensus_api::external::TxOut::from(output));
-        }
+        let mut response = mc_mobilecoind_api::ValidateSignedContingentInputResponse::new();
 
-        if let Some(watcher_db) = self.watcher_db.as_ref() {
-            let signatures = watcher_db
-                .get_block_signatures(request.block)
-                .map_err(|err| {
-                    rpc_internal_error("watcher_db.get_block_signatures", err, &self.logger)
-                })?;
-            for signature_data in signatures.iter() {
-                let mut signature_message = mc_mobilecoind_api::ArchiveBlockSignatureData::new();
-                signature_message.set_src_url(signature_data.src_url.clone());
-                signature_message.set_filename(signature_data.archive_filename.clone());
-                signature_message.set_signature(
-                    mc_consensus_api::blockchain::BlockSignature::from(
-                        &signature_data.block_signature,
-                    ),
-                );
-                response.mut_signatures().push(signature_message);
+        match self
+            .transactions_manager
+            .validate_signed_contingent_input(&sci)
+        {
+            Ok(()) => response.set_is_valid(true),
+            Err(err @ Error::InvalidSignedContingentInput(_))
+            | Err(err @ Error::KeyImageAlreadySpent) => {
+                response.set_is_valid(false);
+                response.set_invalid_reason(err.to_string());
+            }
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "transactions_manager.validate_signed_contingent_input",
+                    err,
+                    &self.logger,
+                ))
             }
         }
+
+        // Describe what the signed contingent input offers and requires.
+        let offered_amount = Amount::from(&sci.pseudo_output_amount);
+        response.set_offered_value(offered_amount.value);
+        response.set_offered_token_id(*offered_amount.token_id);
+        response.set_required_values(
+            sci.required_output_amounts
+                .iter()
+                .map(|amount| amount.value)
+                .collect(),
+        );
+        response.set_required_token_ids(
+            sci.required_output_amounts
+                .iter()
+                .map(|amount| amount.token_id)
+                .collect(),
+        );
+        response.set_max_tombstone_block(
+            sci.tx_in
+                .input_rules
+                .as_ref()
+                .map(|rules| rules.max_tombstone_block)
+                .unwrap_or(0),
+        );
+
         Ok(response)
     }
 
-    fn get_tx_status_as_sender_impl(
+    fn generate_signed_contingent_input_fill_tx_impl(
         &mut self,
-        request: mc_mobilecoind_api::SubmitTxResponse,
-    ) -> Result<mc_mobilecoind_api::GetTxStatusAsSenderResponse, RpcStatus> {
-        // Sanity-test the request.
-        if request
-            .get_sender_tx_receipt()
-            .get_key_image_list()
-            .is_empty()
-        {
-            return Err(RpcStatus::with_message(
-                RpcStatusCode::INVALID_ARGUMENT,
-                "sender_receipt.key_image_list".into(),
-            ));
-        }
+        request: mc_mobilecoind_api::GenerateSignedContingentInputFillTxRequest,
+    ) -> Result<mc_mobilecoind_api::GenerateSignedContingentInputFillTxResponse, RpcStatus> {
+        // Get sender monitor id from request.
+        let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        // Get monitor data for this monitor.
+        let sender_monitor_data = self
+            .mobilecoind_db
+            .get_monitor_data(&sender_monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?;
+
+        // Check that change_subaddress is covered by this monitor.
+        if !sender_monitor_data
+            .subaddress_indexes()
+            .contains(&request.change_subaddress)
+        {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "change_subaddress".into(),
+            ));
+        }
+
+        // Get the list of potential inputs passed to.
+        let input_list: Vec<UnspentTxOut> = request
+            .get_input_list()
+            .iter()
+            .enumerate()
+            .map(|(i, proto_utxo)| {
+                // Proto -> Rust struct conversion.
+                let utxo = UnspentTxOut::try_from(proto_utxo).map_err(|err| {
+                    rpc_internal_error("unspent_tx_out.try_from", err, &self.logger)
+                })?;
+
+                // Verify this output belongs to the monitor.
+                let subaddress_id = self
+                    .mobilecoind_db
+                    .get_subaddress_id_by_utxo_id(&UtxoId::from(&utxo))
+                    .map_err(|err| {
+                        rpc_internal_error(
+                            "mobilecoind_db.get_subaddress_id_by_utxo_id",
+                            err,
+                            &self.logger,
+                        )
+                    })?;
+
+                if subaddress_id.monitor_id != sender_monitor_id {
+                    return Err(RpcStatus::with_message(
+                        RpcStatusCode::INVALID_ARGUMENT,
+                        format!("input_list.{}", i),
+                    ));
+                }
+
+                // Success.
+                Ok(utxo)
+            })
+            .collect::<Result<Vec<UnspentTxOut>, RpcStatus>>()?;
+
+        // Get the signed contingent input from the request.
+        let sci = SignedContingentInput::try_from(request.get_sci()).map_err(|err| {
+            rpc_internal_error("signed_contingent_input.try_from", err, &self.logger)
+        })?;
+
+        // Attempt to construct a transaction.
+        let tx_proposal = self
+            .transactions_manager
+            .build_signed_contingent_input_fill_transaction(
+                &sender_monitor_id,
+                request.change_subaddress,
+                &input_list,
+                sci,
+                TokenId::from(request.fee_token_id),
+                request.fee,
+                request.tombstone,
+            )
+            .map_err(|err| {
+                rpc_internal_error(
+                    "transactions_manager.build_signed_contingent_input_fill_transaction",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        // Success.
+        let mut response = mc_mobilecoind_api::GenerateSignedContingentInputFillTxResponse::new();
+        response.set_tx_proposal((&tx_proposal).into());
+        Ok(response)
+    }
+
+    fn get_ledger_info_impl(
+        &mut self,
+        _request: mc_mobilecoind_api::Empty,
+    ) -> Result<mc_mobilecoind_api::GetLedgerInfoResponse, RpcStatus> {
+        let num_blocks = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+
+        let num_txos = self
+            .ledger_db
+            .num_txos()
+            .map_err(|err| rpc_internal_error("ledger_db.num_txos", err, &self.logger))?;
+
+        let mut response = mc_mobilecoind_api::GetLedgerInfoResponse::new();
+        response.set_block_count(num_blocks);
+        response.set_txo_count(num_txos);
+        Ok(response)
+    }
+
+    fn get_block_info_impl(
+        &mut self,
+        request: mc_mobilecoind_api::GetBlockInfoRequest,
+    ) -> Result<mc_mobilecoind_api::GetBlockInfoResponse, RpcStatus> {
+        let block_contents = self
+            .ledger_db
+            .get_block_contents(request.block)
+            .map_err(|err| rpc_internal_error("ledger_db.get_block_contents", err, &self.logger))?;
+
+        let num_tx_outs = block_contents.outputs.len();
+        let num_key_images = block_contents.key_images.len();
+
+        // Return response.
+        let mut response = mc_mobilecoind_api::GetBlockInfoResponse::new();
+        response.set_key_image_count(num_key_images as u64);
+        response.set_txo_count(num_tx_outs as u64);
+        Ok(response)
+    }
+
+    fn get_block_impl(
+        &mut self,
+        request: mc_mobilecoind_api::GetBlockRequest,
+    ) -> Result<mc_mobilecoind_api::GetBlockResponse, RpcStatus> {
+        let mut response = mc_mobilecoind_api::GetBlockResponse::new();
+
+        let block_data = self
+            .ledger_db
+            .get_block_data(request.block)
+            .map_err(|err| rpc_internal_error("ledger_db.get_block_data", err, &self.logger))?;
+
+        response.set_block(mc_consensus_api::blockchain::Block::from(
+            block_data.block(),
+        ));
+
+        for key_image in &block_data.contents().key_images {
+            response
+                .mut_key_images()
+                .push(mc_consensus_api::external::KeyImage::from(key_image));
+        }
+        for output in &block_data.contents().outputs {
+            response
+                .mut_txos()
+                .push(mc_consensus_api::external::TxOut::from(output));
+        }
+
+        if let Some(watcher_db) = self.watcher_db.as_ref() {
+            let signatures = watcher_db
+                .get_block_signatures(request.block)
+                .map_err(|err| {
+                    rpc_internal_error("watcher_db.get_block_signatures", err, &self.logger)
+                })?;
+            for signature_data in signatures.iter() {
+                let mut signature_message = mc_mobilecoind_api::ArchiveBlockSignatureData::new();
+                signature_message.set_src_url(signature_data.src_url.clone());
+                signature_message.set_filename(signature_data.archive_filename.clone());
+                signature_message.set_signature(
+                    mc_consensus_api::blockchain::BlockSignature::from(
+                        &signature_data.block_signature,
+                    ),
+                );
+                response.mut_signatures().push(signature_message);
+            }
+        }
+        Ok(response)
+    }
+
+    fn get_tx_status_as_sender_impl(
+        &mut self,
+        request: mc_mobilecoind_api::SubmitTxResponse,
+    ) -> Result<mc_mobilecoind_api::GetTxStatusAsSenderResponse, RpcStatus> {
+        // Sanity-test the request.
+        if request
+            .get_sender_tx_receipt()
+            .get_key_image_list()
+            .is_empty()
+        {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "sender_receipt.key_image_list".into(),
+            ));
+        }
 
         if request.get_sender_tx_receipt().tombstone == 0 {
             return Err(RpcStatus::with_message(
@@ -1693,28 +2153,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 rpc_internal_error("mobilecoind_db.get_processed_block", err, &self.logger)
             })?
             .iter()
-            .map(|src| {
-                let mut dst = mc_mobilecoind_api::ProcessedTxOut::new();
-                dst.set_monitor_id(monitor_id.to_vec());
-                dst.set_subaddress_index(src.subaddress_index);
-                dst.set_public_key((&src.public_key).into());
-                dst.set_key_image((&src.key_image).into());
-                dst.set_value(src.value);
-                dst.set_direction(
-                    mc_mobilecoind_api::ProcessedTxOutDirection::from_i32(src.direction)
-                        .unwrap_or(mc_mobilecoind_api::ProcessedTxOutDirection::Invalid),
-                );
-
-                let subaddress = account_key.subaddress(src.subaddress_index);
-                let mut wrapper = mc_mobilecoind_api::printable::PrintableWrapper::new();
-                wrapper.set_public_address((&subaddress).into());
-                let encoded = wrapper
-                    .b58_encode()
-                    .map_err(|err| rpc_internal_error("wrapper.b58_encode", err, &self.logger))?;
-                dst.set_address_code(encoded);
-                dst.set_token_id(src.token_id);
-                Ok(dst)
-            })
+            .map(|src| self.processed_tx_out_to_proto(&monitor_id, &account_key, src))
             .collect::<Result<Vec<_>, _>>()?;
 
         // Return response
@@ -1723,6 +2162,236 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(response)
     }
 
+    fn get_transaction_history_impl(
+        &mut self,
+        request: mc_mobilecoind_api::GetTransactionHistoryRequest,
+    ) -> Result<mc_mobilecoind_api::GetTransactionHistoryResponse, RpcStatus> {
+        // Get MonitorId from from the GRPC request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let monitor_data = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?;
+
+        // Build the filters.
+        let subaddress_range = if request.num_subaddresses == 0 {
+            0..u64::MAX
+        } else {
+            let end = request
+                .first_subaddress
+                .checked_add(request.num_subaddresses)
+                .ok_or_else(|| {
+                    RpcStatus::with_message(
+                        RpcStatusCode::INVALID_ARGUMENT,
+                        "num_subaddresses".into(),
+                    )
+                })?;
+            request.first_subaddress..end
+        };
+        let token_ids = request.get_token_ids();
+
+        let end_block = if request.end_block == 0 {
+            monitor_data.next_block
+        } else {
+            min(request.end_block, monitor_data.next_block)
+        };
+        if request.start_block > end_block {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "start_block".into(),
+            ));
+        }
+
+        let max_blocks = if request.max_blocks == 0 {
+            DEFAULT_TRANSACTION_HISTORY_MAX_BLOCKS
+        } else {
+            request.max_blocks as usize
+        };
+
+        // Get the matching processed blocks.
+        let processed_blocks = self
+            .mobilecoind_db
+            .get_processed_blocks(
+                &monitor_id,
+                request.start_block..end_block,
+                max_blocks,
+                |tx_out| {
+                    subaddress_range.contains(&tx_out.subaddress_index)
+                        && (token_ids.is_empty() || token_ids.contains(&tx_out.token_id))
+                },
+            )
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_processed_blocks", err, &self.logger)
+            })?;
+
+        // If we got a full page there may be more blocks to look at.
+        let (next_block, has_more) = match processed_blocks.last() {
+            Some((block_index, _)) if processed_blocks.len() == max_blocks => {
+                (block_index + 1, block_index + 1 < end_block)
+            }
+            _ => (end_block, false),
+        };
+
+        // Group into transactions and convert to protos.
+        let mut transactions = Vec::new();
+        for (block_index, tx_outs) in processed_blocks {
+            let timestamp = self.get_block_timestamp(block_index)?;
+
+            for src in ProcessedTransaction::from_processed_block(block_index, tx_outs) {
+                let mut dst = mc_mobilecoind_api::HistoricalTransaction::new();
+                dst.set_block_index(src.block_index);
+                dst.set_timestamp(timestamp);
+                dst.set_token_id(src.token_id);
+                dst.set_total_received(src.total_received());
+                dst.set_total_spent(src.total_spent());
+                dst.set_received(
+                    src.received
+                        .iter()
+                        .map(|tx_out| {
+                            self.processed_tx_out_to_proto(
+                                &monitor_id,
+                                &monitor_data.account_key,
+                                tx_out,
+                            )
+                        })
+                        .collect::<Result<_, _>>()?,
+                );
+                dst.set_spent(
+                    src.spent
+                        .iter()
+                        .map(|tx_out| {
+                            self.processed_tx_out_to_proto(
+                                &monitor_id,
+                                &monitor_data.account_key,
+                                tx_out,
+                            )
+                        })
+                        .collect::<Result<_, _>>()?,
+                );
+                transactions.push(dst);
+            }
+        }
+
+        // Return response
+        let mut response = mc_mobilecoind_api::GetTransactionHistoryResponse::new();
+        response.set_transactions(RepeatedField::from_vec(transactions));
+        response.set_next_block(next_block);
+        response.set_has_more(has_more);
+        Ok(response)
+    }
+
+    /// Convert a ProcessedTxOut into its API representation.
+    fn processed_tx_out_to_proto(
+        &self,
+        monitor_id: &MonitorId,
+        account_key: &AccountKey,
+        src: &ProcessedTxOut,
+    ) -> Result<mc_mobilecoind_api::ProcessedTxOut, RpcStatus> {
+        let mut dst = mc_mobilecoind_api::ProcessedTxOut::new();
+        dst.set_monitor_id(monitor_id.to_vec());
+        dst.set_subaddress_index(src.subaddress_index);
+        dst.set_public_key((&src.public_key).into());
+        dst.set_key_image((&src.key_image).into());
+        dst.set_value(src.value);
+        dst.set_direction(
+            mc_mobilecoind_api::ProcessedTxOutDirection::from_i32(src.direction)
+                .unwrap_or(mc_mobilecoind_api::ProcessedTxOutDirection::Invalid),
+        );
+
+        let subaddress = account_key.subaddress(src.subaddress_index);
+        let mut wrapper = mc_mobilecoind_api::printable::PrintableWrapper::new();
+        wrapper.set_public_address((&subaddress).into());
+        let encoded = wrapper
+            .b58_encode()
+            .map_err(|err| rpc_internal_error("wrapper.b58_encode", err, &self.logger))?;
+        dst.set_address_code(encoded);
+        dst.set_token_id(src.token_id);
+        Ok(dst)
+    }
+
+    /// Get the timestamp of a block from the watcher DB, or zero if there is no
+    /// watcher DB or the timestamp is not available.
+    fn get_block_timestamp(&self, block_index: u64) -> Result<u64, RpcStatus> {
+        let watcher_db = match self.watcher_db.as_ref() {
+            Some(watcher_db) => watcher_db,
+            None => return Ok(0),
+        };
+
+        let (timestamp, result_code) =
+            watcher_db.get_block_timestamp(block_index).map_err(|err| {
+                rpc_internal_error("watcher_db.get_block_timestamp", err, &self.logger)
+            })?;
+
+        Ok(match result_code {
+            TimestampResultCode::TimestampFound => timestamp,
+            _ => 0,
+        })
+    }
+
+    fn subscribe_blocks_impl(
+        &mut self,
+        request: mc_mobilecoind_api::SubscribeBlocksRequest,
+    ) -> Result<
+        BoxStream<'static, Result<mc_mobilecoind_api::GetBlockResponse, RpcStatus>>,
+        RpcStatus,
+    > {
+        let receiver = self.subscriptions.subscribe_blocks(request.start_block);
+
+        let mut api = self.clone();
+        Ok(receiver
+            .map(move |block_index| {
+                let mut request = mc_mobilecoind_api::GetBlockRequest::new();
+                request.set_block(block_index);
+                api.get_block_impl(request)
+            })
+            .boxed())
+    }
+
+    fn subscribe_monitor_events_impl(
+        &mut self,
+        request: mc_mobilecoind_api::SubscribeMonitorEventsRequest,
+    ) -> Result<BoxStream<'static, Result<mc_mobilecoind_api::MonitorEvent, RpcStatus>>, RpcStatus>
+    {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        // We will use the AccountKey to compute the Address Code
+        let account_key = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?
+            .account_key;
+
+        let receiver = self
+            .subscriptions
+            .subscribe_monitor_events(&monitor_id, request.start_block);
+
+        let api = self.clone();
+        Ok(receiver
+            .map(move |event| {
+                let mut dst = mc_mobilecoind_api::MonitorEvent::new();
+                dst.set_monitor_id(monitor_id.to_vec());
+                dst.set_block_index(event.block_index);
+                dst.set_tx_outs(
+                    event
+                        .tx_outs
+                        .iter()
+                        .map(|tx_out| {
+                            api.processed_tx_out_to_proto(&monitor_id, &account_key, tx_out)
+                        })
+                        .collect::<Result<_, _>>()?,
+                );
+                Ok(dst)
+            })
+            .boxed())
+    }
+
     fn get_block_index_by_tx_pub_key_impl(
         &mut self,
         request: mc_mobilecoind_api::GetBlockIndexByTxPubKeyRequest,
@@ -1821,8 +2490,27 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             utxos.retain(|utxo| utxo.value <= request.max_input_utxo_value);
         }
 
-        // Filter for requested token id.
-        utxos.retain(|utxo| utxo.token_id == request.token_id);
+        // Outlays carrying their own token id replace the single-token outlay list.
+        let is_mixed = !request.get_outlay_list_v2().is_empty();
+        if is_mixed && !request.get_outlay_list().is_empty() {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "outlay_list".into(),
+            ));
+        }
+
+        // Filter for requested token ids.
+        if is_mixed {
+            utxos.retain(|utxo| {
+                utxo.token_id == request.fee_token_id
+                    || request
+                        .get_outlay_list_v2()
+                        .iter()
+                        .any(|outlay| outlay.token_id == utxo.token_id)
+            });
+        } else {
+            utxos.retain(|utxo| utxo.token_id == request.token_id);
+        }
 
         // Get the list of outlays.
         let outlays: Vec<Outlay> = request
@@ -1841,22 +2529,71 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             request.sender_subaddress
         };
 
+        // Get the memos. Sender memos identify the sender subaddress.
+        let memos = match request.get_memo_mode() {
+            mc_mobilecoind_api::MemoMode::UnusedMemos => TxMemos::Unused,
+            memo_mode => {
+                let sender_monitor_data = self
+                    .mobilecoind_db
+                    .get_monitor_data(&sender_monitor_id)
+                    .map_err(|err| {
+                        rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+                    })?;
+                memos_for_mode(
+                    memo_mode,
+                    &sender_monitor_data.account_key,
+                    request.sender_subaddress,
+                    request.payment_request_id,
+                )
+            }
+        };
+
         // Attempt to construct a transaction.
-        let tx_proposal = self
-            .transactions_manager
-            .build_transaction(
-                &sender_monitor_id,
-                TokenId::from(request.token_id),
-                change_subaddress,
-                &utxos,
-                &outlays,
-                request.fee,
-                request.tombstone,
-                None,
-            )
-            .map_err(|err| {
-                rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
-            })?;
+        let tx_proposal = if is_mixed {
+            let outlays: Vec<OutlayV2> = request
+                .get_outlay_list_v2()
+                .iter()
+                .map(|outlay_proto| {
+                    OutlayV2::try_from(outlay_proto)
+                        .map_err(|err| rpc_internal_error("outlay_v2.try_from", err, &self.logger))
+                })
+                .collect::<Result<Vec<OutlayV2>, RpcStatus>>()?;
+            self.transactions_manager
+                .build_mixed_transaction(
+                    &sender_monitor_id,
+                    change_subaddress,
+                    &utxos,
+                    &outlays,
+                    TokenId::from(request.fee_token_id),
+                    request.fee,
+                    request.tombstone,
+                    memos,
+                    utxo_selection_strategy(request.get_utxo_selection_strategy()),
+                )
+                .map_err(|err| {
+                    rpc_internal_error(
+                        "transactions_manager.build_mixed_transaction",
+                        err,
+                        &self.logger,
+                    )
+                })?
+        } else {
+            self.transactions_manager
+                .build_transaction(
+                    &sender_monitor_id,
+                    TokenId::from(request.token_id),
+                    change_subaddress,
+                    &utxos,
+                    &outlays,
+                    request.fee,
+                    request.tombstone,
+                    memos,
+                    utxo_selection_strategy(request.get_utxo_selection_strategy()),
+                )
+                .map_err(|err| {
+                    rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
+                })?
+        };
 
         let proto_tx_proposal = mc_mobilecoind_api::TxProposal::from(&tx_proposal);
 
@@ -1906,10 +2643,76 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         send_payment_request.set_override_change_subaddress(request.override_change_subaddress);
         send_payment_request.set_change_subaddress(request.change_subaddress);
         send_payment_request.set_token_id(request.token_id);
+        send_payment_request.set_memo_mode(request.get_memo_mode());
+        send_payment_request.set_payment_request_id(request.payment_request_id);
+        send_payment_request.set_utxo_selection_strategy(request.get_utxo_selection_strategy());
 
         self.send_payment_impl(send_payment_request)
     }
 
+    fn fill_signed_contingent_input_impl(
+        &mut self,
+        request: mc_mobilecoind_api::FillSignedContingentInputRequest,
+    ) -> Result<mc_mobilecoind_api::SendPaymentResponse, RpcStatus> {
+        // Get sender monitor id from request.
+        let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        // Get all utxos for this monitor id.
+        let utxos = self
+            .mobilecoind_db
+            .get_utxos_for_subaddress(&sender_monitor_id, request.sender_subaddress)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
+            })?;
+
+        // Get the signed contingent input from the request.
+        let sci = SignedContingentInput::try_from(request.get_sci()).map_err(|err| {
+            rpc_internal_error("signed_contingent_input.try_from", err, &self.logger)
+        })?;
+
+        // Set change address to sender address unless it has been overridden
+        let change_subaddress = if request.override_change_subaddress {
+            request.change_subaddress
+        } else {
+            request.sender_subaddress
+        };
+
+        // Attempt to construct a transaction.
+        let tx_proposal = self
+            .transactions_manager
+            .build_signed_contingent_input_fill_transaction(
+                &sender_monitor_id,
+                change_subaddress,
+                &utxos,
+                sci,
+                TokenId::from(request.fee_token_id),
+                request.fee,
+                request.tombstone,
+            )
+            .map_err(|err| {
+                rpc_internal_error(
+                    "transactions_manager.build_signed_contingent_input_fill_transaction",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        let proto_tx_proposal = mc_mobilecoind_api::TxProposal::from(&tx_proposal);
+
+        // Submit transaction.
+        let mut submit_tx_request = mc_mobilecoind_api::SubmitTxRequest::new();
+        submit_tx_request.set_tx_proposal(proto_tx_proposal.clone());
+        let mut submit_tx_response = self.submit_tx_impl(submit_tx_request)?;
+
+        // Return response.
+        let mut response = mc_mobilecoind_api::SendPaymentResponse::new();
+        response.set_sender_tx_receipt(submit_tx_response.take_sender_tx_receipt());
+        response.set_receiver_tx_receipt_list(submit_tx_response.take_receiver_tx_receipt_list());
+        response.set_tx_proposal(proto_tx_proposal);
+        Ok(response)
+    }
+
     fn get_network_status_impl(
         &mut self,
         _request: mc_mobilecoind_api::Empty,
@@ -1991,8 +2794,86 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     }
 }
 
+/// Create the memos requested by a transaction-building API call.
+///
+/// A `payment_request_id` of 0 means no payment request id.
+fn memos_for_mode(
+    memo_mode: mc_mobilecoind_api::MemoMode,
+    account_key: &AccountKey,
+    sender_subaddress: u64,
+    payment_request_id: u64,
+) -> TxMemos {
+    match memo_mode {
+        mc_mobilecoind_api::MemoMode::UnusedMemos => TxMemos::Unused,
+        mc_mobilecoind_api::MemoMode::RecoverableTransactionHistory => {
+            let opt_payment_request_id = Some(payment_request_id).filter(|id| *id != 0);
+            TxMemos::RecoverableTransactionHistory(rth_memo_builder(
+                account_key,
+                sender_subaddress,
+                opt_payment_request_id,
+            ))
+        }
+    }
+}
+
+/// Get the UTXO selection strategy requested by a transaction-building API
+/// call.
+fn utxo_selection_strategy(
+    strategy: mc_mobilecoind_api::UtxoSelectionStrategy,
+) -> &'static dyn UtxoSelectionStrategy {
+    match strategy {
+        mc_mobilecoind_api::UtxoSelectionStrategy::SmallestFirst => &SmallestFirst,
+        mc_mobilecoind_api::UtxoSelectionStrategy::LargestFirst => &LargestFirst,
+        mc_mobilecoind_api::UtxoSelectionStrategy::BranchAndBound => &BranchAndBound,
+        mc_mobilecoind_api::UtxoSelectionStrategy::RandomSelection => &RandomSelection,
+    }
+}
+
+/// Send the messages of a stream to a server streaming sink, until either the
+/// stream ends, the stream yields an error, or the client goes away.
+fn send_stream<T: Send + 'static>(
+    ctx: RpcContext,
+    mut sink: ServerStreamingSink<T>,
+    resp: Result<BoxStream<'static, Result<T, RpcStatus>>, RpcStatus>,
+    logger: &Logger,
+) {
+    let logger = logger.clone();
+
+    match resp {
+        Ok(mut stream) => ctx.spawn(async move {
+            while let Some(item) = stream.next().await {
+                match item {
+                    Ok(msg) => {
+                        if let Err(err) = sink.send((msg, WriteFlags::default())).await {
+                            log::debug!(logger, "stream closed: {}", err);
+                            return;
+                        }
+                    }
+                    Err(status) => {
+                        if let Err(err) = sink.fail(status).await {
+                            log::error!(logger, "failed to reply: {}", err);
+                        }
+                        return;
+                    }
+                }
+            }
+            if let Err(err) = sink.close().await {
+                log::error!(logger, "failed to close stream: {}", err);
+            }
+        }),
+        Err(status) => ctx.spawn(async move {
+            if let Err(err) = sink.fail(status).await {
+                log::error!(logger, "failed to reply: {}", err);
+            }
+        }),
+    }
+}
+
 macro_rules! build_api {
-    ($( $service_function_name:ident $service_request_type:ident $service_response_type:ident $service_function_impl:ident ),+)
+    (
+        $( $service_function_name:ident $service_request_type:ident $service_response_type:ident $service_function_impl:ident ),+ ;
+        $( stream $stream_function_name:ident $stream_request_type:ident $stream_response_type:ident $stream_function_impl:ident ),*
+    )
     =>
     (
         impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolver> MobilecoindApi for ServiceApi<T, FPR> {
@@ -2012,6 +2893,23 @@ macro_rules! build_api {
                     )
                 }
             )+
+
+            $(
+                fn $stream_function_name(
+                    &mut self,
+                    ctx: RpcContext,
+                    request: mc_mobilecoind_api::$stream_request_type,
+                    sink: ServerStreamingSink<mc_mobilecoind_api::$stream_response_type>,
+                ) {
+                    let logger = rpc_logger(&ctx, &self.logger);
+                    send_stream(
+                        ctx,
+                        sink,
+                        self.$stream_function_impl(request),
+                        &logger,
+                    )
+                }
+            )*
         }
     );
 }
@@ -2023,6 +2921,10 @@ build_api! {
     get_monitor_list Empty GetMonitorListResponse get_monitor_list_impl,
     get_monitor_status GetMonitorStatusRequest GetMonitorStatusResponse get_monitor_status_impl,
     get_unspent_tx_out_list GetUnspentTxOutListRequest GetUnspentTxOutListResponse get_unspent_tx_out_list_impl,
+    set_monitor_address_book SetMonitorAddressBookRequest Empty set_monitor_address_book_impl,
+    set_monitor_consolidation_policy SetMonitorConsolidationPolicyRequest Empty set_monitor_consolidation_policy_impl,
+    export_monitors ExportMonitorsRequest ExportMonitorsResponse export_monitors_impl,
+    import_monitors ImportMonitorsRequest ImportMonitorsResponse import_monitors_impl,
 
     // Utilities
     generate_root_entropy Empty GenerateRootEntropyResponse generate_root_entropy_impl,
@@ -2049,6 +2951,11 @@ build_api! {
     generate_burn_redemption_tx GenerateBurnRedemptionTxRequest GenerateBurnRedemptionTxResponse generate_burn_redemption_tx_impl,
     submit_tx SubmitTxRequest SubmitTxResponse submit_tx_impl,
 
+    // Signed contingent inputs
+    generate_signed_contingent_input GenerateSignedContingentInputRequest GenerateSignedContingentInputResponse generate_signed_contingent_input_impl,
+    validate_signed_contingent_input ValidateSignedContingentInputRequest ValidateSignedContingentInputResponse validate_signed_contingent_input_impl,
+    generate_signed_contingent_input_fill_tx GenerateSignedContingentInputFillTxRequest GenerateSignedContingentInputFillTxResponse generate_signed_contingent_input_fill_tx_impl,
+
     // Databases
     get_ledger_info Empty GetLedgerInfoResponse get_ledger_info_impl,
     get_block_info GetBlockInfoRequest GetBlockInfoResponse get_block_info_impl,
@@ -2056,19 +2963,25 @@ build_api! {
     get_tx_status_as_sender SubmitTxResponse GetTxStatusAsSenderResponse get_tx_status_as_sender_impl,
     get_tx_status_as_receiver GetTxStatusAsReceiverRequest GetTxStatusAsReceiverResponse get_tx_status_as_receiver_impl,
     get_processed_block GetProcessedBlockRequest GetProcessedBlockResponse get_processed_block_impl,
+    get_transaction_history GetTransactionHistoryRequest GetTransactionHistoryResponse get_transaction_history_impl,
     get_block_index_by_tx_pub_key GetBlockIndexByTxPubKeyRequest GetBlockIndexByTxPubKeyResponse get_block_index_by_tx_pub_key_impl,
 
     // Convenience calls
     get_balance GetBalanceRequest GetBalanceResponse get_balance_impl,
     send_payment SendPaymentRequest SendPaymentResponse send_payment_impl,
     pay_address_code PayAddressCodeRequest SendPaymentResponse pay_address_code_impl,
+    fill_signed_contingent_input FillSignedContingentInputRequest SendPaymentResponse fill_signed_contingent_input_impl,
 
     // Network status
     get_network_status Empty GetNetworkStatusResponse get_network_status_impl,
 
     // Database encryption
     set_db_password SetDbPasswordRequest Empty set_db_password_impl,
-    unlock_db UnlockDbRequest Empty unlock_db_impl
+    unlock_db UnlockDbRequest Empty unlock_db_impl;
+
+    // Subscriptions
+    stream subscribe_blocks SubscribeBlocksRequest GetBlockResponse subscribe_blocks_impl,
+    stream subscribe_monitor_events SubscribeMonitorEventsRequest MonitorEvent subscribe_monitor_events_impl
 }
 
 #[cfg(test)]
@@ -2084,6 +2997,7 @@ mod test {
         },
         utxo_store::UnspentTxOut,
     };
+    use futures::executor::block_on;
     use grpcio::Error as GrpcError;
     use mc_account_keys::{
         burn_address_view_private, AccountKey, PublicAddress, ShortAddressHash,
@@ -2101,7 +3015,7 @@ mod test {
         onetime_keys::{recover_onetime_private_key, recover_public_subaddress_spend_key},
         tokens::Mob,
         tx::{Tx, TxOut},
-        Amount, Block, BlockContents, BlockVersion, Token,
+        Amount, Block, BlockContents, BlockVersion, MemoPayload, SignedContingentInputError, Token,
     };
     use mc_transaction_std::{EmptyMemoBuilder, MemoType, TransactionBuilder};
     use mc_util_repr_bytes::{typenum::U32, GenericArray, ReprBytes};
@@ -2300,28 +3214,156 @@ mod test {
 
         // Verify the data we got matches what we expected
         assert_eq!(
-            data.account_key,
-            AccountKey::try_from(status.account_key.as_ref().unwrap()).unwrap(),
+            data.account_key,
+            AccountKey::try_from(status.account_key.as_ref().unwrap()).unwrap(),
+        );
+        assert_eq!(status.first_subaddress, data.first_subaddress);
+        assert_eq!(status.num_subaddresses, data.num_subaddresses);
+        assert_eq!(status.first_block, data.first_block);
+        assert_eq!(status.next_block, data.next_block);
+
+        // Calling get_monitor_status for nonexistent or invalid monitor_id should
+        // return an error.
+        mobilecoind_db.remove_monitor(&id).unwrap();
+
+        let mut request = mc_mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(id.to_vec());
+        assert!(client.get_monitor_status(&request).is_err());
+
+        let request = mc_mobilecoind_api::GetMonitorStatusRequest::new();
+        assert!(client.get_monitor_status(&request).is_err());
+
+        let mut request = mc_mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(vec![3; 3]);
+        assert!(client.get_monitor_status(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_set_monitor_consolidation_policy(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 10 random recipients and one monitor.
+        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                10,
+                &[],
+                &[data.clone()],
+                logger.clone(),
+                &mut rng,
+            );
+        let monitor_id = MonitorId::from(&data);
+
+        let mut policy = mc_mobilecoind_api::ConsolidationPolicy::new();
+        policy.set_token_id(*Mob::ID);
+        policy.set_target_utxo_count(MAX_INPUTS);
+        policy.set_fee_budget(10 * Mob::MINIMUM_FEE);
+
+        // A target of zero UTXOs is rejected.
+        let mut request = mc_mobilecoind_api::SetMonitorConsolidationPolicyRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        let mut invalid_policy = policy.clone();
+        invalid_policy.set_target_utxo_count(0);
+        request.set_policy(invalid_policy);
+        assert!(client.set_monitor_consolidation_policy(&request).is_err());
+
+        // Set the policy.
+        request.set_policy(policy.clone());
+        client.set_monitor_consolidation_policy(&request).unwrap();
+
+        let mut request = mc_mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        let response = client.get_monitor_status(&request).unwrap();
+        assert_eq!(response.get_status().get_consolidation_policy(), &policy);
+        assert_eq!(
+            mobilecoind_db
+                .get_monitor_data(&monitor_id)
+                .unwrap()
+                .consolidation_policy,
+            Some(ConsolidationPolicy::from(&policy)),
         );
-        assert_eq!(status.first_subaddress, data.first_subaddress);
-        assert_eq!(status.num_subaddresses, data.num_subaddresses);
-        assert_eq!(status.first_block, data.first_block);
-        assert_eq!(status.next_block, data.next_block);
 
-        // Calling get_monitor_status for nonexistent or invalid monitor_id should
-        // return an error.
-        mobilecoind_db.remove_monitor(&id).unwrap();
+        // Clear the policy.
+        let mut request = mc_mobilecoind_api::SetMonitorConsolidationPolicyRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        client.set_monitor_consolidation_policy(&request).unwrap();
 
         let mut request = mc_mobilecoind_api::GetMonitorStatusRequest::new();
-        request.set_monitor_id(id.to_vec());
-        assert!(client.get_monitor_status(&request).is_err());
+        request.set_monitor_id(monitor_id.to_vec());
+        let response = client.get_monitor_status(&request).unwrap();
+        assert!(!response.get_status().has_consolidation_policy());
+
+        // Unknown monitors are rejected.
+        let mut request = mc_mobilecoind_api::SetMonitorConsolidationPolicyRequest::new();
+        request.set_monitor_id(vec![3; 32]);
+        request.set_policy(policy);
+        assert!(client.set_monitor_consolidation_policy(&request).is_err());
+    }
 
-        let request = mc_mobilecoind_api::GetMonitorStatusRequest::new();
-        assert!(client.get_monitor_status(&request).is_err());
+    #[test_with_logger]
+    fn test_export_import_monitors(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
 
-        let mut request = mc_mobilecoind_api::GetMonitorStatusRequest::new();
-        request.set_monitor_id(vec![3; 3]);
-        assert!(client.get_monitor_status(&request).is_err());
+        let data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 10 random recipients and one monitor.
+        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                10,
+                &[],
+                &[data.clone()],
+                logger.clone(),
+                &mut rng,
+            );
+        let monitor_id = MonitorId::from(&data);
+
+        // Passwords must be PASSWORD_LEN bytes.
+        let mut request = mc_mobilecoind_api::ExportMonitorsRequest::new();
+        request.set_password(vec![1; PASSWORD_LEN - 1]);
+        assert!(client.export_monitors(&request).is_err());
+
+        // Export all monitors.
+        request.set_password(vec![1; PASSWORD_LEN]);
+        request.set_include_processed_state(true);
+        let bundle = client.export_monitors(&request).unwrap().take_bundle();
+
+        // Importing a monitor that already exists fails.
+        let mut request = mc_mobilecoind_api::ImportMonitorsRequest::new();
+        request.set_bundle(bundle);
+        request.set_password(vec![1; PASSWORD_LEN]);
+        assert!(client.import_monitors(&request).is_err());
+
+        // Remove the monitor and import it back.
+        mobilecoind_db.remove_monitor(&monitor_id).unwrap();
+        let response = client.import_monitors(&request).unwrap();
+        assert_eq!(
+            response.get_monitor_id_list().to_vec(),
+            vec![monitor_id.to_vec()]
+        );
+        assert_eq!(
+            mobilecoind_db
+                .get_monitor_data(&monitor_id)
+                .unwrap()
+                .account_key,
+            data.account_key
+        );
     }
 
     #[test_with_logger]
@@ -2439,6 +3481,8 @@ mod test {
                     token_id: *amount.token_id,
                     attempted_spend_height: 0,
                     attempted_spend_tombstone: 0,
+                    memo_payload: Vec::new(),
+                    authenticated_sender: None,
                 }
             })
             .collect();
@@ -3167,6 +4211,8 @@ mod test {
                     attempted_spend_height: 0,
                     attempted_spend_tombstone: 0,
                     token_id: *Mob::ID,
+                    memo_payload: Vec::new(),
+                    authenticated_sender: None,
                 }
             })
             .collect();
@@ -3348,6 +4394,232 @@ mod test {
         assert!(client.get_processed_block(&request).is_err());
     }
 
+    #[test_with_logger]
+    fn test_get_transaction_history(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let monitor_data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                3,
+                &[account_key.default_subaddress()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Spend the first two utxos, sending change back to subaddress 1.
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        let num_blocks = add_block_to_ledger_db(
+            BLOCK_VERSION,
+            &mut ledger_db,
+            &[account_key.subaddress(1)],
+            Amount {
+                value: 1234,
+                token_id: Mob::ID,
+            },
+            &[utxos[0].key_image, utxos[1].key_image],
+            &mut rng,
+        );
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // The whole history fits in a single page.
+        let mut request = mc_mobilecoind_api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+
+        let response = client.get_transaction_history(&request).unwrap();
+        assert!(!response.get_has_more());
+        assert_eq!(response.get_next_block(), num_blocks);
+
+        let transactions = response.get_transactions();
+        assert_eq!(transactions.len(), num_blocks as usize);
+        for (block_index, transaction) in transactions.iter().enumerate() {
+            assert_eq!(transaction.get_block_index(), block_index as u64);
+            assert_eq!(transaction.get_token_id(), *Mob::ID);
+            // No watcher DB is configured.
+            assert_eq!(transaction.get_timestamp(), 0);
+        }
+
+        let last = transactions.last().unwrap();
+        assert_eq!(last.get_received().len(), 1);
+        assert_eq!(last.get_received()[0].get_subaddress_index(), 1);
+        assert_eq!(last.get_total_received(), 1234);
+        assert_eq!(last.get_spent().len(), 2);
+        assert_eq!(last.get_total_spent(), utxos[0].value + utxos[1].value);
+
+        // Paging walks the same history.
+        let mut request = mc_mobilecoind_api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_max_blocks(3);
+
+        let mut paged_transactions = Vec::new();
+        loop {
+            let response = client.get_transaction_history(&request).unwrap();
+            assert!(response.get_transactions().len() <= 3);
+            paged_transactions.extend(response.get_transactions().iter().cloned());
+            if !response.get_has_more() {
+                break;
+            }
+            request.set_start_block(response.get_next_block());
+        }
+        assert_eq!(paged_transactions, transactions);
+
+        // Filtering by subaddress and block range.
+        let mut request = mc_mobilecoind_api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_first_subaddress(1);
+        request.set_num_subaddresses(1);
+
+        let response = client.get_transaction_history(&request).unwrap();
+        assert_eq!(response.get_transactions().len(), 1);
+        assert_eq!(
+            response.get_transactions()[0].get_block_index(),
+            num_blocks - 1
+        );
+        assert!(response.get_transactions()[0].get_spent().is_empty());
+
+        let mut request = mc_mobilecoind_api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_start_block(2);
+        request.set_end_block(4);
+
+        let response = client.get_transaction_history(&request).unwrap();
+        assert_eq!(response.get_transactions().len(), 2);
+        assert_eq!(response.get_next_block(), 4);
+
+        // Filtering by a token id the monitor never saw.
+        let mut request = mc_mobilecoind_api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_token_ids(vec![*Mob::ID + 1]);
+
+        let response = client.get_transaction_history(&request).unwrap();
+        assert!(response.get_transactions().is_empty());
+        assert!(!response.get_has_more());
+    }
+
+    #[test_with_logger]
+    fn test_subscriptions(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let monitor_data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                3,
+                &[account_key.default_subaddress()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+        let num_blocks = ledger_db.num_blocks().unwrap();
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        // Subscribe to the last two existing blocks, and to the monitor events from
+        // the same point on.
+        let mut request = mc_mobilecoind_api::SubscribeBlocksRequest::new();
+        request.set_start_block(num_blocks - 2);
+        let mut blocks = client.subscribe_blocks(&request).unwrap();
+
+        let mut request = mc_mobilecoind_api::SubscribeMonitorEventsRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_start_block(num_blocks - 2);
+        let mut events = client.subscribe_monitor_events(&request).unwrap();
+
+        for block_index in num_blocks - 2..num_blocks {
+            let response = block_on(blocks.next()).unwrap().unwrap();
+            assert_eq!(response.get_block().get_index(), block_index);
+            assert_eq!(
+                response.get_txos().len(),
+                ledger_db
+                    .get_block_contents(block_index)
+                    .unwrap()
+                    .outputs
+                    .len()
+            );
+
+            let event = block_on(events.next()).unwrap().unwrap();
+            assert_eq!(event.get_monitor_id(), monitor_id.to_vec().as_slice());
+            assert_eq!(event.get_block_index(), block_index);
+            assert_eq!(event.get_tx_outs().len(), 1);
+            assert_eq!(
+                event.get_tx_outs()[0].get_direction(),
+                mc_mobilecoind_api::ProcessedTxOutDirection::Received
+            );
+        }
+
+        // A new block spending one of our utxos is streamed as it arrives.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        let utxo = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap()
+            .pop()
+            .unwrap();
+        add_block_to_ledger_db(
+            BLOCK_VERSION,
+            &mut ledger_db,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            Amount {
+                value: DEFAULT_PER_RECIPIENT_AMOUNT,
+                token_id: Mob::ID,
+            },
+            &[utxo.key_image],
+            &mut rng,
+        );
+
+        let response = block_on(blocks.next()).unwrap().unwrap();
+        assert_eq!(response.get_block().get_index(), num_blocks);
+        assert_eq!(
+            response.get_key_images(),
+            &[mc_consensus_api::external::KeyImage::from(&utxo.key_image)]
+        );
+
+        let event = block_on(events.next()).unwrap().unwrap();
+        assert_eq!(event.get_block_index(), num_blocks);
+        assert_eq!(event.get_tx_outs().len(), 1);
+        assert_eq!(
+            event.get_tx_outs()[0].get_direction(),
+            mc_mobilecoind_api::ProcessedTxOutDirection::Spent
+        );
+        assert_eq!(event.get_tx_outs()[0].get_value(), utxo.value);
+
+        // Subscribing to an unknown monitor fails.
+        let mut request = mc_mobilecoind_api::SubscribeMonitorEventsRequest::new();
+        request.set_monitor_id(vec![3u8; 32]);
+        let mut events = client.subscribe_monitor_events(&request).unwrap();
+        assert!(block_on(events.next()).unwrap().is_err());
+    }
+
     #[test_with_logger]
     /// Get mixins should return the correct number of distinct mixins.
     fn test_get_mixins(logger: Logger) {
@@ -3703,7 +4975,16 @@ mod test {
                 tx_proposal.get_tx().get_prefix().get_inputs().len(),
                 expected_num_inputs as usize
             );
-            assert_eq!(tx_proposal.get_outlay_list(), request.get_outlay_list());
+            assert_eq!(
+                TxProposal::try_from(tx_proposal).unwrap().outlays,
+                outlays
+                    .iter()
+                    .map(|outlay| OutlayV2 {
+                        amount: Amount::new(outlay.value, Mob::ID),
+                        receiver: outlay.receiver.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            );
             assert_eq!(
                 tx_proposal.get_tx().get_prefix().get_outputs().len(),
                 outlays.len() + 1
@@ -3792,7 +5073,17 @@ mod test {
 
             assert_eq!(tx_proposal.get_input_list().len(), 1,);
             assert_eq!(tx_proposal.get_tx().get_prefix().get_inputs().len(), 1,);
-            assert_eq!(tx_proposal.get_outlay_list(), request.get_outlay_list());
+            // The outlays carry the token id of the request.
+            assert_eq!(
+                TxProposal::try_from(tx_proposal).unwrap().outlays,
+                outlays
+                    .iter()
+                    .map(|outlay| OutlayV2 {
+                        amount: Amount::new(outlay.value, TokenId::from(2)),
+                        receiver: outlay.receiver.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            );
             assert_eq!(
                 tx_proposal.get_tx().get_prefix().get_outputs().len(),
                 outlays.len() + 1
@@ -4082,6 +5373,15 @@ mod test {
             request.set_burn_amount(1_000_000_000_000 - request.get_fee() + 1);
             assert!(client.generate_burn_redemption_tx(&request).is_err());
         }
+
+        // Burning everything leaves no change, so there is no change output.
+        {
+            let mut request = request.clone();
+            request.set_burn_amount(1_000_000_000_000 - request.get_fee());
+            let response = client.generate_burn_redemption_tx(&request).unwrap();
+            let tx = Tx::try_from(response.get_tx_proposal().get_tx()).unwrap();
+            assert_eq!(tx.prefix.outputs.len(), 1);
+        }
     }
 
     #[test_with_logger]
@@ -4296,7 +5596,7 @@ mod test {
             data.account_key.subaddress(0)
         );
         assert_eq!(
-            tx_proposal.outlays[0].value,
+            tx_proposal.outlays[0].amount.value,
             // Each UTXO we have has PER_RECIPIENT_AMOUNT coins. We will be merging MAX_INPUTS of
             // those into a single output, minus the fee.
             (DEFAULT_PER_RECIPIENT_AMOUNT * MAX_INPUTS as u64) - Mob::MINIMUM_FEE,
@@ -4311,7 +5611,7 @@ mod test {
         let shared_secret =
             get_tx_out_shared_secret(data.account_key.view_private_key(), &tx_public_key);
         let (amount, _blinding) = tx_out.masked_amount.get_value(&shared_secret).unwrap();
-        assert_eq!(amount.value, tx_proposal.outlays[0].value);
+        assert_eq!(amount, tx_proposal.outlays[0].amount);
         assert_eq!(amount.token_id, Mob::ID);
 
         // Santity test fee
@@ -4330,10 +5630,91 @@ mod test {
     fn test_generate_tx_from_tx_out_list(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
 
-        let sender = AccountKey::random(&mut rng);
-        let _sender_default_subaddress = sender.default_subaddress();
-        let data = MonitorData::new(
-            sender.clone(),
+        let sender = AccountKey::random(&mut rng);
+        let _sender_default_subaddress = sender.default_subaddress();
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                3,
+                &[sender.default_subaddress()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Get list of unspent tx outs
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert!(!utxos.is_empty());
+
+        // Build a request to transfer the first two TxOuts
+        let tx_utxos = utxos[0..2].to_vec();
+        let mut request = mc_mobilecoind_api::GenerateTxFromTxOutListRequest::new();
+        request.set_account_key((&sender).into());
+        request.set_input_list(RepeatedField::from_vec(
+            tx_utxos
+                .iter()
+                .map(mc_mobilecoind_api::UnspentTxOut::from)
+                .collect(),
+        ));
+        let receiver = AccountKey::random(&mut rng);
+        request.set_receiver((&receiver.default_subaddress()).into());
+        request.set_fee(Mob::MINIMUM_FEE);
+
+        let response = client.generate_tx_from_tx_out_list(&request).unwrap();
+        let tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
+
+        // We should end up with one output
+        assert_eq!(tx_proposal.tx.prefix.outputs.len(), 1);
+
+        // It should equal the sum of the inputs minus the fee
+        let expected_value = tx_utxos.iter().map(|utxo| utxo.value).sum::<u64>() - Mob::MINIMUM_FEE;
+
+        let tx_out = &tx_proposal.tx.prefix.outputs[0];
+        let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+        let shared_secret = get_tx_out_shared_secret(receiver.view_private_key(), &tx_public_key);
+        let (amount, _blinding) = tx_out.masked_amount.get_value(&shared_secret).unwrap();
+        assert_eq!(amount.value, expected_value);
+        assert_eq!(amount.token_id, Mob::ID);
+    }
+
+    #[test_with_logger]
+    fn test_signed_contingent_input(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // Signed contingent inputs require block version 3.
+        let block_version = BlockVersion::THREE;
+
+        let alice = AccountKey::random(&mut rng);
+        let alice_data = MonitorData::new(
+            alice.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        let bob = AccountKey::random(&mut rng);
+        let bob_data = MonitorData::new(
+            bob.clone(),
             0,  // first_subaddress
             20, // num_subaddresses
             0,  // first_block
@@ -4341,58 +5722,166 @@ mod test {
         )
         .unwrap();
 
-        // 1 known recipient, 3 random recipients and no monitors.
-        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+        // Alice has MOB, and 3 random recipients.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
             get_testing_environment(
-                BLOCK_VERSION,
+                block_version,
                 3,
-                &[sender.default_subaddress()],
+                &[alice.default_subaddress()],
                 &[],
                 logger.clone(),
                 &mut rng,
             );
 
+        // Bob has a non-MOB token ID.
+        add_block_to_ledger_db(
+            block_version,
+            &mut ledger_db,
+            &[
+                AccountKey::random(&mut rng).default_subaddress(),
+                AccountKey::random(&mut rng).default_subaddress(),
+                bob.default_subaddress(),
+            ],
+            Amount {
+                value: 1_000_000_000_000,
+                token_id: TokenId::from(2),
+            },
+            &[KeyImage::from(101)],
+            &mut rng,
+        );
+
         // Insert into database.
-        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        let alice_monitor_id = mobilecoind_db.add_monitor(&alice_data).unwrap();
+        let bob_monitor_id = mobilecoind_db.add_monitor(&bob_data).unwrap();
 
-        // Allow the new monitor to process the ledger.
+        // Allow the new monitors to process the ledger.
         wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
 
-        // Get list of unspent tx outs
-        let utxos = mobilecoind_db
-            .get_utxos_for_subaddress(&monitor_id, 0)
+        let alice_utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&alice_monitor_id, 0)
             .unwrap();
-        assert!(!utxos.is_empty());
+        assert!(!alice_utxos.is_empty());
 
-        // Build a request to transfer the first two TxOuts
-        let tx_utxos = utxos[0..2].to_vec();
-        let mut request = mc_mobilecoind_api::GenerateTxFromTxOutListRequest::new();
-        request.set_account_key((&sender).into());
+        // Alice offers a MOB utxo in exchange for some of token id 2.
+        let required_outlay = OutlayV2 {
+            amount: Amount::new(1_000_000, TokenId::from(2)),
+            receiver: alice.default_subaddress(),
+        };
+        let mut request = mc_mobilecoind_api::GenerateSignedContingentInputRequest::new();
+        request.set_sender_monitor_id(alice_monitor_id.to_vec());
+        request.set_utxo((&alice_utxos[0]).into());
+        request.set_required_outlay_list(RepeatedField::from_vec(vec![(&required_outlay).into()]));
+        let response = client.generate_signed_contingent_input(&request).unwrap();
+        let proto_sci = response.get_sci().clone();
+
+        // Bob cannot offer Alice's utxo.
+        {
+            let mut request = request.clone();
+            request.set_sender_monitor_id(bob_monitor_id.to_vec());
+            assert!(client.generate_signed_contingent_input(&request).is_err());
+        }
+
+        // Bob checks what is being offered.
+        let mut request = mc_mobilecoind_api::ValidateSignedContingentInputRequest::new();
+        request.set_sci(proto_sci.clone());
+        let response = client.validate_signed_contingent_input(&request).unwrap();
+        assert!(response.get_is_valid(), "{}", response.get_invalid_reason());
+        assert_eq!(response.get_offered_value(), alice_utxos[0].value);
+        assert_eq!(response.get_offered_token_id(), *Mob::ID);
+        assert_eq!(response.get_required_values(), &[1_000_000]);
+        assert_eq!(response.get_required_token_ids(), &[2]);
+        assert!(response.get_max_tombstone_block() > ledger_db.num_blocks().unwrap());
+
+        // Every ring member must come with its global index.
+        {
+            let mut proto_sci = proto_sci.clone();
+            proto_sci.mut_tx_out_global_indices().pop();
+            let mut request = mc_mobilecoind_api::ValidateSignedContingentInputRequest::new();
+            request.set_sci(proto_sci);
+            let response = client.validate_signed_contingent_input(&request).unwrap();
+            assert!(!response.get_is_valid());
+            assert_eq!(
+                response.get_invalid_reason(),
+                Error::from(SignedContingentInputError::WrongNumberOfGlobalIndices).to_string()
+            );
+        }
+
+        // Bob fills it, paying the fee in token id 2.
+        let bob_utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&bob_monitor_id, 0)
+            .unwrap();
+        assert!(!bob_utxos.is_empty());
+
+        let mut request = mc_mobilecoind_api::GenerateSignedContingentInputFillTxRequest::new();
+        request.set_sender_monitor_id(bob_monitor_id.to_vec());
+        request.set_change_subaddress(0);
         request.set_input_list(RepeatedField::from_vec(
-            tx_utxos
+            bob_utxos
                 .iter()
                 .map(mc_mobilecoind_api::UnspentTxOut::from)
                 .collect(),
         ));
-        let receiver = AccountKey::random(&mut rng);
-        request.set_receiver((&receiver.default_subaddress()).into());
-        request.set_fee(Mob::MINIMUM_FEE);
-
-        let response = client.generate_tx_from_tx_out_list(&request).unwrap();
+        request.set_sci(proto_sci.clone());
+        request.set_fee(1_000);
+        request.set_fee_token_id(2);
+        let response = client
+            .generate_signed_contingent_input_fill_tx(&request)
+            .unwrap();
         let tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
 
-        // We should end up with one output
-        assert_eq!(tx_proposal.tx.prefix.outputs.len(), 1);
+        // One input from Alice, and one from Bob.
+        assert_eq!(tx_proposal.utxos.len(), 1);
+        assert_eq!(tx_proposal.utxos[0].token_id, 2);
+        assert_eq!(tx_proposal.tx.prefix.inputs.len(), 2);
 
-        // It should equal the sum of the inputs minus the fee
-        let expected_value = tx_utxos.iter().map(|utxo| utxo.value).sum::<u64>() - Mob::MINIMUM_FEE;
+        // The required output, the MOB for Bob, and Bob's change.
+        assert_eq!(tx_proposal.tx.prefix.outputs.len(), 3);
+        assert_eq!(tx_proposal.tx.prefix.fee, 1_000);
+        assert_eq!(tx_proposal.tx.prefix.fee_token_id, 2);
+        assert_eq!(
+            tx_proposal.outlays,
+            vec![OutlayV2 {
+                amount: Amount::new(alice_utxos[0].value, Mob::ID),
+                receiver: bob.default_subaddress(),
+            }]
+        );
 
-        let tx_out = &tx_proposal.tx.prefix.outputs[0];
-        let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
-        let shared_secret = get_tx_out_shared_secret(receiver.view_private_key(), &tx_public_key);
-        let (amount, _blinding) = tx_out.masked_amount.get_value(&shared_secret).unwrap();
-        assert_eq!(amount.value, expected_value);
-        assert_eq!(amount.token_id, Mob::ID);
+        // The MOB output is Bob's.
+        let tx_out = &tx_proposal.tx.prefix.outputs[tx_proposal.outlay_index_to_tx_out_index[&0]];
+        let shared_secret = get_tx_out_shared_secret(
+            bob.view_private_key(),
+            &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+        );
+        let (amount, _) = tx_out.masked_amount.get_value(&shared_secret).unwrap();
+        assert_eq!(amount, Amount::new(alice_utxos[0].value, Mob::ID));
+
+        // Once the key image is spent, the signed contingent input is no longer valid.
+        add_block_to_ledger_db(
+            block_version,
+            &mut ledger_db,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            Amount {
+                value: 1_000_000_000_000,
+                token_id: Mob::ID,
+            },
+            &[alice_utxos[0].key_image],
+            &mut rng,
+        );
+
+        let mut request = mc_mobilecoind_api::ValidateSignedContingentInputRequest::new();
+        request.set_sci(proto_sci.clone());
+        let response = client.validate_signed_contingent_input(&request).unwrap();
+        assert!(!response.get_is_valid());
+
+        let mut request = mc_mobilecoind_api::GenerateSignedContingentInputFillTxRequest::new();
+        request.set_sender_monitor_id(bob_monitor_id.to_vec());
+        request.set_change_subaddress(0);
+        request.set_sci(proto_sci);
+        request.set_fee(1_000);
+        request.set_fee_token_id(2);
+        assert!(client
+            .generate_signed_contingent_input_fill_tx(&request)
+            .is_err());
     }
 
     #[test_with_logger]
@@ -4957,6 +6446,176 @@ mod test {
         );
     }
 
+    #[test_with_logger]
+    fn test_send_payment_mixed_token_ids(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let token_id2 = TokenId::from(2);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                3,
+                &[sender.default_subaddress()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Add a block with a non-MOB token ID.
+        add_block_to_ledger_db(
+            BlockVersion::MAX,
+            &mut ledger_db,
+            &[
+                AccountKey::random(&mut rng).default_subaddress(),
+                sender.default_subaddress(),
+            ],
+            Amount::new(1_000_000_000_000, token_id2),
+            &[KeyImage::from(101)],
+            &mut rng,
+        );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Generate two random recipients, one for each token id.
+        let receiver1 = AccountKey::random(&mut rng);
+        let receiver2 = AccountKey::random(&mut rng);
+
+        let outlays = vec![
+            OutlayV2 {
+                amount: Amount::new(123, Mob::ID),
+                receiver: receiver1.default_subaddress(),
+            },
+            OutlayV2 {
+                amount: Amount::new(456, token_id2),
+                receiver: receiver2.default_subaddress(),
+            },
+        ];
+
+        // Call send payment, paying the fee in MOB.
+        let mut request = mc_mobilecoind_api::SendPaymentRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_sender_subaddress(0);
+        request.set_outlay_list_v2(RepeatedField::from_vec(
+            outlays
+                .iter()
+                .map(mc_mobilecoind_api::OutlayV2::from)
+                .collect(),
+        ));
+        request.set_fee_token_id(*Mob::ID);
+
+        // Setting both outlay lists is an error.
+        {
+            let mut request = request.clone();
+            request.set_outlay_list(RepeatedField::from_vec(vec![
+                mc_mobilecoind_api::Outlay::from(&Outlay {
+                    value: 123,
+                    receiver: receiver1.default_subaddress(),
+                }),
+            ]));
+            assert!(client.send_payment(&request).is_err());
+        }
+
+        // Every token id needs at least one input, so a transaction cannot have
+        // outlays of more token ids than it can have inputs.
+        {
+            let mut request = request.clone();
+            request.set_outlay_list_v2(RepeatedField::from_vec(
+                (1..=MAX_INPUTS)
+                    .map(|token_id| {
+                        mc_mobilecoind_api::OutlayV2::from(&OutlayV2 {
+                            amount: Amount::new(1, TokenId::from(token_id)),
+                            receiver: receiver1.default_subaddress(),
+                        })
+                    })
+                    .collect(),
+            ));
+            assert!(client.send_payment(&request).is_err());
+        }
+
+        let response = client.send_payment(&request).unwrap();
+
+        // Find the submitted transaction.
+        let submitted_tx = server_conn_manager
+            .conns()
+            .iter()
+            .find_map(|mock_peer| mock_peer.read().proposed_txs.first().cloned())
+            .unwrap();
+        assert_eq!(
+            submitted_tx,
+            Tx::try_from(response.get_tx_proposal().get_tx()).unwrap()
+        );
+        assert_eq!(submitted_tx.prefix.fee_token_id, *Mob::ID);
+
+        // Inputs of both token ids were spent.
+        let tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
+        assert_eq!(tx_proposal.outlays, outlays);
+        assert!(tx_proposal
+            .utxos
+            .iter()
+            .any(|utxo| utxo.token_id == *Mob::ID));
+        assert!(tx_proposal
+            .utxos
+            .iter()
+            .any(|utxo| utxo.token_id == *token_id2));
+
+        // Each receiver got the amount and token id of its outlay.
+        let received_amount = |account_key: &AccountKey| -> Vec<Amount> {
+            submitted_tx
+                .prefix
+                .outputs
+                .iter()
+                .filter_map(|tx_out| {
+                    tx_out
+                        .view_key_match(account_key.view_private_key())
+                        .map(|(amount, _commitment)| amount)
+                        .ok()
+                })
+                .collect()
+        };
+        assert_eq!(received_amount(&receiver1), vec![outlays[0].amount]);
+        assert_eq!(received_amount(&receiver2), vec![outlays[1].amount]);
+
+        // The sender got change in each token id.
+        let change_amounts = received_amount(&sender);
+        assert_eq!(change_amounts.len(), 2);
+        let token2_change = change_amounts
+            .iter()
+            .find(|amount| amount.token_id == token_id2)
+            .unwrap();
+        assert_eq!(token2_change.value, 1_000_000_000_000 - 456);
+        let mob_change = change_amounts
+            .iter()
+            .find(|amount| amount.token_id == Mob::ID)
+            .unwrap();
+        let mob_input_value: u64 = tx_proposal
+            .utxos
+            .iter()
+            .filter(|utxo| utxo.token_id == *Mob::ID)
+            .map(|utxo| utxo.value)
+            .sum();
+        assert_eq!(
+            mob_change.value,
+            mob_input_value - 123 - submitted_tx.prefix.fee
+        );
+
+        assert_eq!(submitted_tx.prefix.outputs.len(), 4);
+    }
+
     #[test_with_logger]
     fn test_send_payment_to_fog(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -5184,6 +6843,128 @@ mod test {
         assert!(response.is_err());
     }
 
+    #[test_with_logger]
+    fn test_send_payment_with_rth_memos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // Memos require block version 1.
+        let block_version = BlockVersion::ONE;
+
+        let sender = AccountKey::random(&mut rng);
+        let sender_data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        let receiver = AccountKey::random(&mut rng);
+        let receiver_data = MonitorData::new(
+            receiver.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                block_version,
+                3,
+                &[sender.default_subaddress()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let sender_monitor_id = mobilecoind_db.add_monitor(&sender_data).unwrap();
+        let receiver_monitor_id = mobilecoind_db.add_monitor(&receiver_data).unwrap();
+
+        // The receiver knows the sender.
+        let mut request = mc_mobilecoind_api::SetMonitorAddressBookRequest::new();
+        request.set_monitor_id(receiver_monitor_id.to_vec());
+        request.set_address_book(RepeatedField::from_vec(vec![(&sender
+            .default_subaddress())
+            .into()]));
+        client.set_monitor_address_book(&request).unwrap();
+
+        let mut request = mc_mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(receiver_monitor_id.to_vec());
+        let response = client.get_monitor_status(&request).unwrap();
+        assert_eq!(
+            response.get_status().get_address_book(),
+            &[(&sender.default_subaddress()).into()]
+        );
+
+        // Allow the new monitors to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Call send payment, with RTH memos and a payment request id.
+        let mut request = mc_mobilecoind_api::SendPaymentRequest::new();
+        request.set_sender_monitor_id(sender_monitor_id.to_vec());
+        request.set_sender_subaddress(0);
+        request.set_outlay_list(RepeatedField::from_vec(vec![
+            mc_mobilecoind_api::Outlay::from(&Outlay {
+                value: 1234,
+                receiver: receiver.default_subaddress(),
+            }),
+        ]));
+        request.set_memo_mode(mc_mobilecoind_api::MemoMode::RecoverableTransactionHistory);
+        request.set_payment_request_id(42);
+
+        let response = client.send_payment(&request).unwrap();
+        let tx = Tx::try_from(response.get_tx_proposal().get_tx()).unwrap();
+
+        // The change output carries a destination memo.
+        let change_tx_out = tx
+            .prefix
+            .outputs
+            .iter()
+            .find(|tx_out| tx_out.view_key_match(sender.view_private_key()).is_ok())
+            .expect("Didn't find sender's change output");
+        let ss = get_tx_out_shared_secret(
+            sender.view_private_key(),
+            &RistrettoPublic::try_from(&change_tx_out.public_key).unwrap(),
+        );
+        let memo = change_tx_out.e_memo.unwrap().decrypt(&ss);
+        match MemoType::try_from(&memo).expect("Couldn't decrypt memo") {
+            MemoType::Destination(memo) => {
+                assert_eq!(
+                    memo.get_address_hash(),
+                    &ShortAddressHash::from(&receiver.default_subaddress()),
+                );
+                assert_eq!(memo.get_num_recipients(), 1);
+            }
+            _ => panic!("unexpected memo type"),
+        }
+
+        // Once the transaction lands, the receiver's monitor authenticates the
+        // sender against its address book.
+        add_txos_to_ledger_db(block_version, &mut ledger_db, &tx.prefix.outputs, &mut rng);
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&receiver_monitor_id, 0)
+            .unwrap();
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos[0].value, 1234);
+        assert_eq!(
+            utxos[0].authenticated_sender,
+            Some(sender.default_subaddress())
+        );
+
+        let memo_payload = MemoPayload::try_from(&utxos[0].memo_payload[..]).unwrap();
+        assert_matches!(
+            MemoType::try_from(&memo_payload),
+            Ok(MemoType::AuthenticatedSenderWithPaymentRequestId(memo)) if memo.payment_request_id() == 42
+        );
+    }
+
     #[test_with_logger]
     fn test_pay_address_code(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -0,0 +1,358 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Streaming subscriptions to ledger blocks and monitor events.
+//!
+//! Subscribers register a starting block index and receive one message per
+//! block from that point on, first catching up on blocks that were already
+//! available and then following new ones as they arrive. Delivery is driven by
+//! the main sync thread, which calls `Subscriptions::publish` on every
+//! iteration.
+//!
+//! Each subscriber is backed by a bounded channel. When a subscriber's channel
+//! is full, its cursor is not advanced and delivery resumes on a later
+//! iteration, so slow consumers do not cause unbounded memory growth. When the
+//! receiving end of a channel is dropped (e.g. the gRPC client went away), the
+//! subscriber is removed.
+
+use crate::{
+    database::Database, error::Error, monitor_store::MonitorId,
+    processed_block_store::ProcessedTxOut,
+};
+use futures::channel::mpsc::{channel, Receiver, Sender};
+use mc_common::logger::{log, Logger};
+use mc_ledger_db::{Ledger, LedgerDB};
+use std::{
+    cmp::max,
+    sync::{Arc, Mutex},
+};
+
+/// The number of messages buffered for each subscriber.
+pub const SUBSCRIPTION_BUFFER_SIZE: usize = 64;
+
+/// A monitor event: the ProcessedTxOuts a monitor saw in a given block.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MonitorEvent {
+    /// The block index.
+    pub block_index: u64,
+
+    /// Received and spent TxOuts belonging to the monitor in this block.
+    pub tx_outs: Vec<ProcessedTxOut>,
+}
+
+/// A subscriber to new ledger blocks.
+struct BlockSubscriber {
+    /// The next block index to deliver.
+    next_block: u64,
+
+    /// Channel for delivering block indexes.
+    sender: Sender<u64>,
+}
+
+/// A subscriber to the events of a single monitor.
+struct MonitorEventSubscriber {
+    /// The monitor to deliver events for.
+    monitor_id: MonitorId,
+
+    /// The next block index to look for events in.
+    next_block: u64,
+
+    /// Channel for delivering events.
+    sender: Sender<MonitorEvent>,
+}
+
+#[derive(Default)]
+struct Inner {
+    block_subscribers: Vec<BlockSubscriber>,
+    monitor_event_subscribers: Vec<MonitorEventSubscriber>,
+}
+
+/// Registry of active subscriptions, shared between the API service and the
+/// sync thread.
+#[derive(Clone, Default)]
+pub struct Subscriptions {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Subscriptions {
+    /// Subscribe to ledger blocks, starting at `start_block`.
+    ///
+    /// The returned receiver yields block indexes, in order.
+    pub fn subscribe_blocks(&self, start_block: u64) -> Receiver<u64> {
+        let (sender, receiver) = channel(SUBSCRIPTION_BUFFER_SIZE);
+
+        let mut inner = self.inner.lock().expect("mutex poisoned");
+        inner.block_subscribers.push(BlockSubscriber {
+            next_block: start_block,
+            sender,
+        });
+
+        receiver
+    }
+
+    /// Subscribe to the events of a monitor, starting at `start_block`.
+    ///
+    /// Blocks in which the monitor saw no activity are skipped.
+    pub fn subscribe_monitor_events(
+        &self,
+        monitor_id: &MonitorId,
+        start_block: u64,
+    ) -> Receiver<MonitorEvent> {
+        let (sender, receiver) = channel(SUBSCRIPTION_BUFFER_SIZE);
+
+        let mut inner = self.inner.lock().expect("mutex poisoned");
+        inner
+            .monitor_event_subscribers
+            .push(MonitorEventSubscriber {
+                monitor_id: *monitor_id,
+                next_block: start_block,
+                sender,
+            });
+
+        receiver
+    }
+
+    /// Deliver any pending messages to subscribers.
+    ///
+    /// Returns true if at least one message was delivered.
+    pub fn publish(
+        &self,
+        ledger_db: &LedgerDB,
+        mobilecoind_db: &Database,
+        logger: &Logger,
+    ) -> bool {
+        let mut inner = self.inner.lock().expect("mutex poisoned");
+        let mut delivered = false;
+
+        let num_blocks = match ledger_db.num_blocks() {
+            Ok(num_blocks) => num_blocks,
+            Err(err) => {
+                log::error!(logger, "Failed getting number of blocks: {}", err);
+                return false;
+            }
+        };
+
+        inner.block_subscribers.retain_mut(|subscriber| {
+            while subscriber.next_block < num_blocks {
+                match subscriber.sender.try_send(subscriber.next_block) {
+                    Ok(()) => {
+                        subscriber.next_block += 1;
+                        delivered = true;
+                    }
+                    Err(err) if err.is_full() => return true,
+                    Err(_) => return false,
+                }
+            }
+            !subscriber.sender.is_closed()
+        });
+
+        inner.monitor_event_subscribers.retain_mut(|subscriber| {
+            match publish_monitor_events(subscriber, mobilecoind_db) {
+                Ok(sent) => {
+                    delivered |= sent;
+                    !subscriber.sender.is_closed()
+                }
+                // The monitor was removed, end the subscription.
+                Err(Error::MonitorIdNotFound) => false,
+                Err(err) => {
+                    log::error!(
+                        logger,
+                        "Failed publishing events for monitor {}: {}",
+                        subscriber.monitor_id,
+                        err
+                    );
+                    true
+                }
+            }
+        });
+
+        delivered
+    }
+}
+
+/// Deliver pending events to a single monitor event subscriber.
+///
+/// Returns true if at least one event was delivered.
+fn publish_monitor_events(
+    subscriber: &mut MonitorEventSubscriber,
+    mobilecoind_db: &Database,
+) -> Result<bool, Error> {
+    let monitor_data = mobilecoind_db.get_monitor_data(&subscriber.monitor_id)?;
+    subscriber.next_block = max(subscriber.next_block, monitor_data.first_block);
+
+    let mut delivered = false;
+    while subscriber.next_block < monitor_data.next_block {
+        // Find the next block with activity for this monitor.
+        let (block_index, tx_outs) = match mobilecoind_db
+            .get_processed_blocks(
+                &subscriber.monitor_id,
+                subscriber.next_block..monitor_data.next_block,
+                1,
+                |_| true,
+            )?
+            .pop()
+        {
+            Some(processed_block) => processed_block,
+            None => {
+                subscriber.next_block = monitor_data.next_block;
+                break;
+            }
+        };
+
+        match subscriber.sender.try_send(MonitorEvent {
+            block_index,
+            tx_outs,
+        }) {
+            Ok(()) => {
+                subscriber.next_block = block_index + 1;
+                delivered = true;
+            }
+            // Either the channel is full and we will try again later, or the
+            // receiver is gone and the caller will drop this subscriber.
+            Err(_) => break,
+        }
+    }
+
+    Ok(delivered)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        monitor_store::MonitorData,
+        sync::SyncThread,
+        test_utils::{add_block_to_ledger_db, get_test_databases, BlockVersion},
+    };
+    use futures::{executor::block_on, StreamExt};
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, Amount, Token};
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    #[test_with_logger]
+    fn test_subscribe_blocks(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        let (mut ledger_db, mobilecoind_db) =
+            get_test_databases(BlockVersion::MAX, 3, &[], 10, logger.clone(), &mut rng);
+
+        let subscriptions = Subscriptions::default();
+        let mut receiver = subscriptions.subscribe_blocks(7);
+
+        // Catch up on existing blocks.
+        assert!(subscriptions.publish(&ledger_db, &mobilecoind_db, &logger));
+        for expected_block_index in 7..10 {
+            assert_eq!(block_on(receiver.next()), Some(expected_block_index));
+        }
+
+        // Nothing new to deliver.
+        assert!(!subscriptions.publish(&ledger_db, &mobilecoind_db, &logger));
+
+        // Follow new blocks.
+        add_block_to_ledger_db(
+            BlockVersion::MAX,
+            &mut ledger_db,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            Amount::new(10, Mob::ID),
+            &[KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        assert!(subscriptions.publish(&ledger_db, &mobilecoind_db, &logger));
+        assert_eq!(block_on(receiver.next()), Some(10));
+
+        // Dropping the receiver ends the subscription.
+        drop(receiver);
+        subscriptions.publish(&ledger_db, &mobilecoind_db, &logger);
+        assert!(subscriptions
+            .inner
+            .lock()
+            .unwrap()
+            .block_subscribers
+            .is_empty());
+    }
+
+    #[test_with_logger]
+    fn test_subscribe_blocks_backpressure(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        let num_blocks = SUBSCRIPTION_BUFFER_SIZE * 2 + 5;
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            BlockVersion::MAX,
+            1,
+            &[],
+            num_blocks,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let subscriptions = Subscriptions::default();
+        let mut receiver = subscriptions.subscribe_blocks(0);
+
+        // Publishing stops when the channel is full, and resumes once it has been
+        // drained.
+        let mut received = Vec::new();
+        while received.len() < num_blocks {
+            assert!(subscriptions.publish(&ledger_db, &mobilecoind_db, &logger));
+            while let Ok(Some(block_index)) = receiver.try_next() {
+                received.push(block_index);
+            }
+            assert!(received.len() <= num_blocks);
+        }
+        assert_eq!(received, (0..num_blocks as u64).collect::<Vec<_>>());
+    }
+
+    #[test_with_logger]
+    fn test_subscribe_monitor_events(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first subaddress
+            5,  // number of subaddresses
+            2,  // first block
+            "", // name
+        )
+        .unwrap();
+
+        // The monitor's account receives a TxOut in every block.
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            BlockVersion::MAX,
+            3,
+            &[account_key.default_subaddress()],
+            10,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        let subscriptions = Subscriptions::default();
+        // Starting before the monitor's first block starts at its first block.
+        let mut receiver = subscriptions.subscribe_monitor_events(&monitor_id, 0);
+
+        // Nothing has been processed yet.
+        assert!(!subscriptions.publish(&ledger_db, &mobilecoind_db, &logger));
+
+        let mut sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            subscriptions.clone(),
+            Some(1),
+            logger.clone(),
+        );
+
+        for expected_block_index in 2..10 {
+            let event = block_on(receiver.next()).unwrap();
+            assert_eq!(event.block_index, expected_block_index);
+            assert_eq!(event.tx_outs.len(), 1);
+            assert_eq!(event.tx_outs[0].subaddress_index, 0);
+        }
+
+        // Removing the monitor ends the subscription.
+        sync_thread.stop();
+        mobilecoind_db.remove_monitor(&monitor_id).unwrap();
+        subscriptions.publish(&ledger_db, &mobilecoind_db, &logger);
+        assert_eq!(block_on(receiver.next()), None);
+    }
+}
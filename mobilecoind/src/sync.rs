@@ -25,20 +25,24 @@ use crate::{
     error::Error,
     monitor_store::{MonitorData, MonitorId},
     subaddress_store::SubaddressSPKId,
+    subscriptions::Subscriptions,
     utxo_store::UnspentTxOut,
 };
+use mc_account_keys::{PublicAddress, ShortAddressHash};
 use mc_common::{
     logger::{log, Logger},
     HashSet,
 };
-use mc_crypto_keys::RistrettoPublic;
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
 use mc_ledger_db::{Ledger, LedgerDB};
 use mc_transaction_core::{
     get_tx_out_shared_secret,
     onetime_keys::{recover_onetime_private_key, recover_public_subaddress_spend_key},
     ring_signature::KeyImage,
     tx::TxOut,
+    MemoPayload,
 };
+use mc_transaction_std::MemoType;
 use std::{
     convert::TryFrom,
     sync::{
@@ -81,6 +85,7 @@ impl SyncThread {
     pub fn start(
         ledger_db: LedgerDB,
         mobilecoind_db: Database,
+        subscriptions: Subscriptions,
         num_workers: Option<usize>,
         logger: Logger,
     ) -> Self {
@@ -179,6 +184,11 @@ impl SyncThread {
                             message_sent = true;
                         }
 
+                        // Deliver any pending blocks and monitor events to subscribers.
+                        if subscriptions.publish(&ledger_db, &mobilecoind_db, &logger) {
+                            message_sent = true;
+                        }
+
                         // If we saw no activity, sleep for a bit.
                         if !message_sent {
                             thread::sleep(std::time::Duration::from_secs(1));
@@ -396,6 +406,21 @@ fn match_tx_outs_into_utxos(
 
         let key_image = KeyImage::from(&onetime_private_key);
 
+        // Pre-memo TxOuts have no memo, in which case we store an empty payload.
+        let (memo_payload, authenticated_sender) = match tx_out.e_memo {
+            Some(e_memo) => {
+                let memo_payload = e_memo.decrypt(&shared_secret);
+                let authenticated_sender = authenticate_sender(
+                    &memo_payload,
+                    &tx_out.public_key,
+                    &account_key.subaddress_view_private(subaddress_id.index),
+                    &monitor_data.address_book,
+                );
+                (memo_payload.as_ref().to_vec(), authenticated_sender)
+            }
+            None => (Vec::new(), None),
+        };
+
         results.push(UnspentTxOut {
             tx_out: tx_out.clone(),
             subaddress_index: subaddress_id.index,
@@ -404,12 +429,53 @@ fn match_tx_outs_into_utxos(
             attempted_spend_height: 0,
             attempted_spend_tombstone: 0,
             token_id: *amount.token_id,
+            memo_payload,
+            authenticated_sender,
         });
     }
 
     Ok(results)
 }
 
+/// Look up the sender of an authenticated sender memo in an address book, and
+/// return the matching address if the memo's HMAC checks out against it.
+///
+/// Returns None for any other memo type, for senders that are not in the
+/// address book, and for memos that fail validation.
+fn authenticate_sender(
+    memo_payload: &MemoPayload,
+    tx_out_public_key: &CompressedRistrettoPublic,
+    receiving_subaddress_view_private_key: &RistrettoPrivate,
+    address_book: &[PublicAddress],
+) -> Option<PublicAddress> {
+    let memo_type = MemoType::try_from(memo_payload).ok()?;
+    let sender_address_hash = match &memo_type {
+        MemoType::AuthenticatedSender(memo) => memo.sender_address_hash(),
+        MemoType::AuthenticatedSenderWithPaymentRequestId(memo) => memo.sender_address_hash(),
+        _ => return None,
+    };
+
+    let sender = address_book
+        .iter()
+        .find(|address| ShortAddressHash::from(*address) == sender_address_hash)?;
+
+    let is_valid = match &memo_type {
+        MemoType::AuthenticatedSender(memo) => memo.validate(
+            sender,
+            receiving_subaddress_view_private_key,
+            tx_out_public_key,
+        ),
+        MemoType::AuthenticatedSenderWithPaymentRequestId(memo) => memo.validate(
+            sender,
+            receiving_subaddress_view_private_key,
+            tx_out_public_key,
+        ),
+        _ => return None,
+    };
+
+    bool::from(is_valid).then(|| sender.clone())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
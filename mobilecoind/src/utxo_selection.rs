@@ -0,0 +1,202 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Strategies for choosing which UTXOs to spend when building a transaction.
+
+use crate::utxo_store::UnspentTxOut;
+use rand::seq::SliceRandom;
+
+/// The maximal number of steps the branch and bound search takes before giving
+/// up on finding an exact match.
+pub const BRANCH_AND_BOUND_MAX_TRIES: usize = 100_000;
+
+/// A strategy for choosing which UTXOs to spend.
+pub trait UtxoSelectionStrategy {
+    /// Returns a subset of `candidates` totalling at least `value`, with at
+    /// most `max_inputs` elements.
+    ///
+    /// Callers guarantee that all candidates are of the same token id, that
+    /// they are sorted in descending order by value, and that the first
+    /// `max_inputs` of them total at least `value`.
+    fn select(
+        &self,
+        candidates: Vec<UnspentTxOut>,
+        value: u64,
+        max_inputs: usize,
+    ) -> Vec<UnspentTxOut>;
+}
+
+/// Spend the smallest UTXOs first. This consolidates dust as a side effect of
+/// regular payments.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SmallestFirst;
+
+impl UtxoSelectionStrategy for SmallestFirst {
+    fn select(
+        &self,
+        mut candidates: Vec<UnspentTxOut>,
+        value: u64,
+        max_inputs: usize,
+    ) -> Vec<UnspentTxOut> {
+        let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
+        let mut total: u64 = 0;
+        while total < value {
+            // Grab the next (smallest utxo)
+            let next_utxo = match candidates.pop() {
+                Some(utxo) => utxo,
+                None => break,
+            };
+            total += next_utxo.value;
+            selected_utxos.push(next_utxo);
+
+            // Cap at maximum allowed inputs.
+            if selected_utxos.len() > max_inputs {
+                // Remove the lowest utxo.
+                total -= selected_utxos.remove(0).value;
+            }
+        }
+        selected_utxos
+    }
+}
+
+/// Spend the largest UTXOs first. This minimizes the number of inputs, and
+/// therefore the size of the transaction, at the cost of leaving dust behind.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LargestFirst;
+
+impl UtxoSelectionStrategy for LargestFirst {
+    fn select(
+        &self,
+        candidates: Vec<UnspentTxOut>,
+        value: u64,
+        _max_inputs: usize,
+    ) -> Vec<UnspentTxOut> {
+        let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
+        let mut total: u64 = 0;
+        for utxo in candidates {
+            if total >= value {
+                break;
+            }
+            total += utxo.value;
+            selected_utxos.push(utxo);
+        }
+        selected_utxos
+    }
+}
+
+/// Search for a set of UTXOs totalling exactly the requested value, so that
+/// the transaction has no change. The search is a depth-first branch and bound
+/// over the candidates, largest first, and is capped at
+/// BRANCH_AND_BOUND_MAX_TRIES steps. If no exact match is found, falls back to
+/// SmallestFirst.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BranchAndBound;
+
+impl UtxoSelectionStrategy for BranchAndBound {
+    fn select(
+        &self,
+        candidates: Vec<UnspentTxOut>,
+        value: u64,
+        max_inputs: usize,
+    ) -> Vec<UnspentTxOut> {
+        match find_exact_match(&candidates, value, max_inputs) {
+            Some(indices) => indices
+                .into_iter()
+                .map(|index| candidates[index].clone())
+                .collect(),
+            None => SmallestFirst.select(candidates, value, max_inputs),
+        }
+    }
+}
+
+/// Returns the indices of at most `max_inputs` candidates, totalling exactly
+/// `value`. Candidates must be sorted in descending order by value.
+fn find_exact_match(
+    candidates: &[UnspentTxOut],
+    value: u64,
+    max_inputs: usize,
+) -> Option<Vec<usize>> {
+    // remaining_values[i] is the total value of candidates[i..].
+    let mut remaining_values = vec![0u64; candidates.len() + 1];
+    for index in (0..candidates.len()).rev() {
+        remaining_values[index] = remaining_values[index + 1] + candidates[index].value;
+    }
+
+    let mut selected: Vec<usize> = Vec::new();
+    let mut total: u64 = 0;
+    let mut index = 0;
+    for _ in 0..BRANCH_AND_BOUND_MAX_TRIES {
+        if total == value {
+            return Some(selected);
+        }
+
+        let dead_end = index == candidates.len()
+            || selected.len() == max_inputs
+            || total + remaining_values[index] < value;
+        if dead_end {
+            // Backtrack: undo the last inclusion and explore the branch that excludes
+            // it instead. Candidates of the same value would lead to the same
+            // branches, so skip them too.
+            let last = selected.pop()?;
+            total -= candidates[last].value;
+            index = last + 1;
+            while index < candidates.len() && candidates[index].value == candidates[last].value {
+                index += 1;
+            }
+        } else {
+            // Include the current candidate if it doesn't overshoot.
+            if total + candidates[index].value <= value {
+                selected.push(index);
+                total += candidates[index].value;
+            }
+            index += 1;
+        }
+    }
+
+    None
+}
+
+/// Spend a random subset of the UTXOs. Deterministic strategies make it easier
+/// to link transactions to the same wallet by the inputs they spend; random
+/// selection avoids that pattern.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RandomSelection;
+
+impl UtxoSelectionStrategy for RandomSelection {
+    fn select(
+        &self,
+        mut candidates: Vec<UnspentTxOut>,
+        value: u64,
+        max_inputs: usize,
+    ) -> Vec<UnspentTxOut> {
+        candidates.shuffle(&mut rand::thread_rng());
+
+        let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
+        let mut total: u64 = 0;
+        for utxo in candidates {
+            if total >= value {
+                break;
+            }
+
+            if selected_utxos.len() < max_inputs {
+                total += utxo.value;
+                selected_utxos.push(utxo);
+                continue;
+            }
+
+            // Out of inputs. Replace the smallest selected UTXO if this one is larger.
+            // Once every candidate has been seen, this leaves the largest
+            // `max_inputs` candidates selected, which are known to be sufficient.
+            let (smallest_index, smallest_value) = selected_utxos
+                .iter()
+                .enumerate()
+                .map(|(index, selected_utxo)| (index, selected_utxo.value))
+                .min_by_key(|(_, value)| *value)
+                .expect("max_inputs is positive since value is positive");
+            if utxo.value > smallest_value {
+                total = total - smallest_value + utxo.value;
+                selected_utxos[smallest_index] = utxo;
+            }
+        }
+        selected_utxos
+    }
+}
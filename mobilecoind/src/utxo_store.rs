@@ -9,6 +9,7 @@ use crate::{
 };
 
 use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mc_account_keys::PublicAddress;
 use mc_common::{
     logger::{log, Logger},
     HashMap,
@@ -60,6 +61,15 @@ pub struct UnspentTxOut {
     /// The token id of this TxOut
     #[prost(uint64, tag = "7")]
     pub token_id: u64,
+
+    /// The decrypted memo payload of this TxOut, or empty if it has no memo.
+    #[prost(bytes, tag = "8")]
+    pub memo_payload: Vec<u8>,
+
+    /// The sender of this TxOut, if its memo is an authenticated sender memo
+    /// that was verified against the monitor's address book.
+    #[prost(message, optional, tag = "9")]
+    pub authenticated_sender: Option<PublicAddress>,
 }
 
 /// Type used as the key in the utxo_id_to_utxo  database.
@@ -456,6 +466,8 @@ mod test {
                     attempted_spend_height: 0,
                     attempted_spend_tombstone: 0,
                     token_id: *Mob::ID,
+                    memo_payload: Vec::new(),
+                    authenticated_sender: None,
                 }
             })
             .collect();
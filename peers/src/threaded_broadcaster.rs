@@ -64,25 +64,9 @@ impl<RP: RetryPolicy> ThreadedBroadcaster<RP> {
         retry_policy: &RP,
         logger: Logger,
     ) -> Self {
-        let peer_threads: Vec<PeerThread> = manager
-            .conns()
+        let peer_threads: Vec<PeerThread> = Self::broadcast_conns(manager)
             .into_iter()
-            .filter(|conn| {
-                conn.uri()
-                    .get_param("broadcast-consensus-msgs")
-                    .unwrap_or_else(|| "1".to_string())
-                    == "1"
-            })
-            .map(|conn| {
-                let peer_name = conn.to_string();
-                PeerThread::new(
-                    conn,
-                    retry_policy,
-                    logger.new(o!(
-                        "mc.peers.peer_name" => peer_name,
-                    )),
-                )
-            })
+            .map(|conn| Self::new_peer_thread(conn, retry_policy, &logger))
             .collect();
         Self {
             peer_threads,
@@ -93,6 +77,75 @@ impl<RP: RetryPolicy> ThreadedBroadcaster<RP> {
         }
     }
 
+    /// Update the list of peers to match the connections `manager` currently
+    /// holds. Peers whose URI did not change keep their worker thread and any
+    /// messages queued for them.
+    pub fn update_peers<CC: ConsensusConnection + 'static>(
+        &mut self,
+        manager: &ConnectionManager<CC>,
+    ) {
+        let mut peer_threads = Vec::new();
+        for conn in Self::broadcast_conns(manager) {
+            let uri = conn.uri().to_string();
+            match self
+                .peer_threads
+                .iter()
+                .position(|peer_thread| peer_thread.uri == uri)
+            {
+                Some(index) => peer_threads.push(self.peer_threads.swap_remove(index)),
+                None => {
+                    log::info!(self.logger, "Broadcasting to new peer {}", conn);
+                    peer_threads.push(Self::new_peer_thread(
+                        conn,
+                        &self.retry_policy,
+                        &self.logger,
+                    ));
+                }
+            }
+        }
+
+        // Whatever is left belongs to peers that were removed, or whose URI changed.
+        for mut peer_thread in std::mem::replace(&mut self.peer_threads, peer_threads) {
+            log::info!(
+                self.logger,
+                "No longer broadcasting to {}",
+                peer_thread.responder_id()
+            );
+            peer_thread.stop();
+        }
+    }
+
+    /// Connections to peers we should broadcast messages to.
+    fn broadcast_conns<CC: ConsensusConnection + 'static>(
+        manager: &ConnectionManager<CC>,
+    ) -> Vec<SyncConnection<CC>> {
+        manager
+            .conns()
+            .into_iter()
+            .filter(|conn| {
+                conn.uri()
+                    .get_param("broadcast-consensus-msgs")
+                    .unwrap_or_else(|| "1".to_string())
+                    == "1"
+            })
+            .collect()
+    }
+
+    fn new_peer_thread<CC: ConsensusConnection + 'static>(
+        conn: SyncConnection<CC>,
+        retry_policy: &RP,
+        logger: &Logger,
+    ) -> PeerThread {
+        let peer_name = conn.to_string();
+        PeerThread::new(
+            conn,
+            retry_policy,
+            logger.new(o!(
+                "mc.peers.peer_name" => peer_name,
+            )),
+        )
+    }
+
     pub fn stop(&mut self) {
         for peer_thread in self.peer_threads.iter_mut() {
             peer_thread.stop();
@@ -256,6 +309,7 @@ enum ThreadMsg {
 /// A single peer thread.
 struct PeerThread {
     responder_id: ResponderId,
+    uri: String,
     sender: crossbeam_channel::Sender<ThreadMsg>,
     join_handle: Option<thread::JoinHandle<()>>,
 }
@@ -269,6 +323,7 @@ impl PeerThread {
         let (sender, receiver) = crossbeam_channel::unbounded();
 
         let responder_id = conn.remote_responder_id();
+        let uri = conn.uri().to_string();
 
         let retry_policy = retry_policy.clone();
 
@@ -282,6 +337,7 @@ impl PeerThread {
         );
         Self {
             responder_id,
+            uri,
             sender,
             join_handle,
         }
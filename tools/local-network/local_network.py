@@ -460,11 +460,29 @@ class Network:
                 check=True,
             )
 
-        subprocess.run(
-            f'cd {PROJECT_DIR} && CONSENSUS_ENCLAVE_PRIVKEY="{enclave_pem}" cargo build -p mc-consensus-service -p mc-ledger-distribution -p mc-admin-http-gateway -p mc-util-grpc-admin-tool -p mc-mobilecoind -p mc-crypto-x509-test-vectors -p mc-consensus-mint-client -p mc-util-seeded-ed25519-key-gen {CARGO_FLAGS}',
-            shell=True,
-            check=True,
-        )
+        build_env = f'CONSENSUS_ENCLAVE_PRIVKEY="{enclave_pem}"'
+        packages = '-p mc-ledger-distribution -p mc-admin-http-gateway -p mc-util-grpc-admin-tool -p mc-crypto-x509-test-vectors -p mc-consensus-mint-client -p mc-util-seeded-ed25519-key-gen'
+        if self.insecure_enclave:
+            # Run the consensus enclave in-process, attesting with the simulated IAS key, so
+            # that no SGX SDK, hardware, aesmd or IAS credentials are needed. The consensus
+            # service and mobilecoind are built separately, without their default SGX
+            # features, so that neither the enclave nor its measurement gets built.
+            print('WARNING: building an INSECURE consensus service, with no enclave')
+            build_env += ' SGX_MODE=SW IAS_MODE=DEV'
+            commands = [
+                f'cargo build -p mc-consensus-service --no-default-features --features insecure-enclave {CARGO_FLAGS}',
+                f'cargo build -p mc-mobilecoind --no-default-features --features ip-check,insecure-enclave {CARGO_FLAGS}',
+                f'cargo build {packages} {CARGO_FLAGS}',
+            ]
+        else:
+            commands = [f'cargo build -p mc-consensus-service -p mc-mobilecoind {packages} {CARGO_FLAGS}']
+
+        for command in commands:
+            subprocess.run(
+                f'cd {PROJECT_DIR} && {build_env} {command}',
+                shell=True,
+                check=True,
+            )
 
     def add_node(self, name, peers, quorum_set):
         node_num = len(self.nodes)
@@ -553,8 +571,9 @@ class Network:
                 raise
 
 
-    def default_entry_point(self, network_type, skip_build=False, block_version=None):
+    def default_entry_point(self, network_type, skip_build=False, block_version=None, insecure_enclave=False):
         self.block_version = block_version
+        self.insecure_enclave = insecure_enclave
 
         if network_type == 'dense5':
             #  5 node interconnected network requiring 4 out of 5  nodes.
@@ -606,6 +625,7 @@ if __name__ == '__main__':
     parser.add_argument('--network-type', help='Type of network to create', required=True)
     parser.add_argument('--skip-build', help='Skip building binaries', action='store_true')
     parser.add_argument('--block-version', help='Set the block version argument', type=int)
+    parser.add_argument('--insecure-enclave', help='INSECURE: run consensus without SGX, for development only', action='store_true')
     args = parser.parse_args()
 
-    Network().default_entry_point(args.network_type, args.skip_build, args.block_version)
+    Network().default_entry_point(args.network_type, args.skip_build, args.block_version, args.insecure_enclave)
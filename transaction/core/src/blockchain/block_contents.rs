@@ -34,6 +34,21 @@ pub struct BlockContents {
     /// Mint transactions in this block.
     #[prost(message, repeated, tag = "4")]
     pub mint_txs: Vec<MintTx>,
+
+    /// Number of transactions in this block, from which the minimum fees of
+    /// the blocks following it are derived. Only recorded starting with block
+    /// version 4, and zero before.
+    #[prost(uint64, tag = "5")]
+    #[digestible(omit_when = 0)]
+    pub num_transactions: u64,
+
+    /// Numbers of transactions in the blocks of the fee schedule's window
+    /// ending with this block, oldest first. The minimum fees of the next
+    /// block are derived from them, so that the enclave forming it only needs
+    /// the contents of its parent block. Only recorded starting with block
+    /// version 4, and empty before.
+    #[prost(uint64, repeated, tag = "6")]
+    pub recent_block_loads: Vec<u64>,
 }
 
 impl BlockContents {
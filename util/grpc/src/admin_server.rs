@@ -4,7 +4,7 @@ use crate::{
     AdminService, BuildInfoService, ConnectionUriGrpcioServer, GetConfigJsonFn, HealthService,
 };
 use futures::executor::block_on;
-use grpcio::{Environment, ShutdownFuture};
+use grpcio::{Environment, Service, ShutdownFuture};
 use mc_common::logger::{log, Logger};
 use mc_util_uri::{AdminUri, ConnectionUri};
 use std::sync::Arc;
@@ -23,6 +23,28 @@ impl AdminServer {
         id: String,
         get_config_json: Option<GetConfigJsonFn>,
         logger: Logger,
+    ) -> Result<Self, grpcio::Error> {
+        Self::start_with_services(
+            env,
+            admin_listen_uri,
+            name,
+            id,
+            get_config_json,
+            Vec::new(),
+            logger,
+        )
+    }
+
+    /// Initilaizes and starts the admin server, also serving some
+    /// service-specific admin endpoints
+    pub fn start_with_services(
+        env: Option<Arc<Environment>>,
+        admin_listen_uri: &AdminUri,
+        name: String,
+        id: String,
+        get_config_json: Option<GetConfigJsonFn>,
+        additional_services: Vec<Service>,
+        logger: Logger,
     ) -> Result<Self, grpcio::Error> {
         log::info!(
             logger,
@@ -45,11 +67,14 @@ impl AdminServer {
         let health_service = HealthService::new(None, logger.clone()).into_service();
         let build_info_service = BuildInfoService::new(logger.clone()).into_service();
 
-        let server_builder = grpcio::ServerBuilder::new(env)
+        let mut server_builder = grpcio::ServerBuilder::new(env)
             .register_service(admin_service)
             .register_service(health_service)
-            .register_service(build_info_service)
-            .bind_using_uri(admin_listen_uri, logger.clone());
+            .register_service(build_info_service);
+        for service in additional_services {
+            server_builder = server_builder.register_service(service);
+        }
+        let server_builder = server_builder.bind_using_uri(admin_listen_uri, logger.clone());
 
         let mut server = server_builder.build()?;
         server.start();